@@ -188,6 +188,14 @@ function isValidIdent(pattern) {
 
 function isValidPattern(pattern) {
   if (!pattern) return false;
+
+  // The version requirement (e.g. `foo@~1.2`) is validated by the server
+  let separator = pattern.indexOf('@');
+  if (separator !== -1) {
+    if (separator === pattern.length - 1) return false;
+    pattern = pattern.slice(0, separator);
+  }
+
   if (pattern === '*') return true;
 
   if (pattern.endsWith('*')) {
@@ -13,6 +13,7 @@ export default class Crate extends Model {
   @attr max_version;
   @attr max_stable_version;
   @attr newest_version;
+  @attr recommended_version;
 
   @attr description;
   @attr homepage;
@@ -56,6 +56,9 @@ Router.map(function () {
   this.route('data-access');
   this.route('confirm', { path: '/confirm/:email_token' });
   this.route('accept-invite', { path: '/accept-invite/:token' });
+  this.route('unsubscribe-follow-digest', { path: '/unsubscribe/follow_digest/:token' });
+  this.route('revoke-token', { path: '/tokens/revoke/:token' });
+  this.route('confirm-publishes', { path: '/confirm_publishes/:token' });
 
   this.route('catch-all', { path: '*path' });
 });
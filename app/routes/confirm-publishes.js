@@ -0,0 +1,25 @@
+import Route from '@ember/routing/route';
+import { inject as service } from '@ember/service';
+
+import ajax from '../utils/ajax';
+
+export default class ConfirmPublishesRoute extends Route {
+  @service notifications;
+  @service router;
+
+  async model(params) {
+    try {
+      await ajax(`/api/v1/confirm_publishes/${params.token}`, { method: 'PUT', body: '{}' });
+      this.notifications.success('Thank you for confirming the activity! Publishing is unlocked again.');
+    } catch (error) {
+      let detail = error.errors?.[0]?.detail;
+      if (detail && !detail.startsWith('{')) {
+        this.notifications.error(`Error while confirming the activity: ${detail}`);
+      } else {
+        this.notifications.error(`Unknown error while confirming the activity`);
+      }
+    }
+
+    this.router.replaceWith('index');
+  }
+}
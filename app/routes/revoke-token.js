@@ -0,0 +1,25 @@
+import Route from '@ember/routing/route';
+import { inject as service } from '@ember/service';
+
+import ajax from '../utils/ajax';
+
+export default class RevokeTokenRoute extends Route {
+  @service notifications;
+  @service router;
+
+  async model(params) {
+    try {
+      await ajax(`/api/v1/tokens/revoke/${params.token}`, { method: 'PUT', body: '{}' });
+      this.notifications.success('The API token has been revoked.');
+    } catch (error) {
+      let detail = error.errors?.[0]?.detail;
+      if (detail && !detail.startsWith('{')) {
+        this.notifications.error(`Error while revoking the API token: ${detail}`);
+      } else {
+        this.notifications.error(`Unknown error while revoking the API token`);
+      }
+    }
+
+    this.router.replaceWith('index');
+  }
+}
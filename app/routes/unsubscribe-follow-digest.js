@@ -0,0 +1,25 @@
+import Route from '@ember/routing/route';
+import { inject as service } from '@ember/service';
+
+import ajax from '../utils/ajax';
+
+export default class UnsubscribeFollowDigestRoute extends Route {
+  @service notifications;
+  @service router;
+
+  async model(params) {
+    try {
+      await ajax(`/api/v1/unsubscribe/follow_digest/${params.token}`, { method: 'PUT', body: '{}' });
+      this.notifications.success('You will no longer receive the weekly digest of the crates you follow.');
+    } catch (error) {
+      let detail = error.errors?.[0]?.detail;
+      if (detail && !detail.startsWith('{')) {
+        this.notifications.error(`Error while unsubscribing: ${detail}`);
+      } else {
+        this.notifications.error(`Unknown error while unsubscribing`);
+      }
+    }
+
+    this.router.replaceWith('index');
+  }
+}
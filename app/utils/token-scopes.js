@@ -1,4 +1,7 @@
 import { htmlSafe } from '@ember/template';
+import Ember from 'ember';
+
+const escape = Ember.Handlebars.Utils.escapeExpression;
 
 const DESCRIPTIONS = {
   'change-owners': 'Invite new crate owners or remove existing ones',
@@ -12,11 +15,18 @@ export function scopeDescription(scope) {
 }
 
 export function patternDescription(pattern) {
+  let separator = pattern.indexOf('@');
+  if (separator !== -1) {
+    let description = patternDescription(pattern.slice(0, separator));
+    let versionReq = pattern.slice(separator + 1);
+    return htmlSafe(`${description}, and only versions matching <strong>${escape(versionReq)}</strong> can be published`);
+  }
+
   if (pattern === '*') {
     return 'Matches all crates on crates.io';
   } else if (pattern.endsWith('*')) {
-    return htmlSafe(`Matches all crates starting with <strong>${pattern.slice(0, -1)}</strong>`);
+    return htmlSafe(`Matches all crates starting with <strong>${escape(pattern.slice(0, -1))}</strong>`);
   } else {
-    return htmlSafe(`Matches only the <strong>${pattern}</strong> crate`);
+    return htmlSafe(`Matches only the <strong>${escape(pattern)}</strong> crate`);
   }
 }
@@ -0,0 +1,28 @@
+//! Records information about the build, which the `/api/private/build_info`
+//! endpoint exposes to verify which code is deployed.
+//!
+//! The script is run again whenever a file of the package changes, so the
+//! timestamp is the time of the last build of the package.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let build_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    println!("cargo:rustc-env=CRATES_IO_RUSTC_VERSION={rustc_version}");
+    println!("cargo:rustc-env=CRATES_IO_BUILD_TIMESTAMP={build_timestamp}");
+}
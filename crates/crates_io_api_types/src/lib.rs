@@ -0,0 +1,141 @@
+//! The JSON views of the crates.io API that are shared between the backend,
+//! which serializes them, and the `crates_io_client` crate and the test
+//! suites, which deserialize them.
+//!
+//! The backend builds these views from its database models, so they only
+//! contain the plain data of the responses.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+pub mod rfc3339;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableCategory {
+    pub id: String,
+    pub category: String,
+    pub slug: String,
+    pub description: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub crates_cnt: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableKeyword {
+    pub id: String,
+    pub keyword: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub crates_cnt: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableCrate {
+    pub id: String,
+    pub name: String,
+    #[serde(with = "rfc3339")]
+    pub updated_at: NaiveDateTime,
+    pub versions: Option<Vec<i32>>,
+    pub keywords: Option<Vec<String>>,
+    pub categories: Option<Vec<String>>,
+    pub badges: Option<Vec<()>>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    // NOTE: Used by shields.io, altering `downloads` requires a PR with shields.io
+    pub downloads: i64,
+    pub recent_downloads: Option<i64>,
+    // NOTE: Used by shields.io, altering `max_version` requires a PR with shields.io
+    pub max_version: String,
+    pub newest_version: String, // Most recently updated version, which may not be max
+    pub max_stable_version: Option<String>,
+    pub recommended_version: Option<String>, // Recommended by the owners, which may not be max
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub documentation: Option<String>,
+    pub repository: Option<String>,
+    pub links: EncodableCrateLinks,
+    pub exact_match: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableCrateLinks {
+    pub version_downloads: String,
+    pub versions: Option<String>,
+    pub owners: Option<String>,
+    pub owner_team: Option<String>,
+    pub owner_user: Option<String>,
+    pub reverse_dependencies: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableOwner {
+    pub id: i32,
+    pub login: String,
+    pub kind: String,
+    pub url: Option<String>,
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+}
+
+/// The public profile of a user.
+/// Same as the private user of the backend, except no email field
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EncodablePublicUser {
+    pub id: i32,
+    pub login: String,
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableAuditAction {
+    pub action: String,
+    pub user: EncodablePublicUser,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableVersion {
+    pub id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    pub dl_path: String,
+    pub readme_path: String,
+    pub release_notes_path: String,
+    #[serde(with = "rfc3339")]
+    pub updated_at: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    // NOTE: Used by shields.io, altering `downloads` requires a PR with shields.io
+    pub downloads: i32,
+    pub features: serde_json::Value,
+    pub yanked: bool,
+    // NOTE: Used by shields.io, altering `license` requires a PR with shields.io
+    pub license: Option<String>,
+    pub links: EncodableVersionLinks,
+    pub crate_size: Option<i32>,
+    pub published_by: Option<EncodablePublicUser>,
+    pub audit_actions: Vec<EncodableAuditAction>,
+    pub published_via: Option<EncodablePublishedVia>,
+    pub checksum: String,
+    pub rust_version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableVersionLinks {
+    pub dependencies: String,
+    pub version_downloads: String,
+    pub authors: String,
+}
+
+/// The publicly visible part of the provenance record of a version.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EncodablePublishedVia {
+    /// Either `api_token` or `session`.
+    pub kind: String,
+    pub ci_service: Option<String>,
+}
@@ -0,0 +1,120 @@
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use tracing::instrument;
+
+#[derive(Clone, Default)]
+pub struct ClientVersionsMap(HashMap<(String, NaiveDate, String), u64>);
+
+impl ClientVersionsMap {
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+
+    /// Increments the download count for the given crate and client version
+    /// on the given date.
+    pub fn add(&mut self, name: String, date: NaiveDate, client_version: String) {
+        *self.0.entry((name, date, client_version)).or_default() += 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Converts the map into a vector of `(crate, date, client version, downloads)` tuples.
+    pub fn into_vec(self) -> Vec<(String, NaiveDate, String, u64)> {
+        self.0
+            .into_iter()
+            .map(|((name, date, client_version), downloads)| {
+                (name, date, client_version, downloads)
+            })
+            .collect()
+    }
+}
+
+impl Debug for ClientVersionsMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut downloads = self
+            .0
+            .iter()
+            .map(|((krate, date, client_version), downloads)| {
+                (date, krate, client_version, downloads)
+            })
+            .collect::<Vec<_>>();
+
+        downloads.sort();
+
+        f.write_str("ClientVersionsMap {\n")?;
+        for (date, krate, client_version, downloads) in downloads {
+            f.write_str("    ")?;
+            f.write_fmt(format_args!(
+                "{date}  {krate}  cargo {client_version} .. {downloads}"
+            ))?;
+            f.write_str("\n")?;
+        }
+        f.write_str("}")?;
+
+        Ok(())
+    }
+}
+
+/// Parses the cargo release (e.g. `1.74`) from a cargo `User-Agent` header
+/// like `cargo 1.74.0 (ecb9851af 2023-10-18)`.
+///
+/// Since cargo is always on major version 1, the minor version is included
+/// too, which corresponds to the Rust release the cargo binary belongs to.
+/// Returns `None` for all other clients.
+#[instrument(level = "debug")]
+pub fn parse_client_version(user_agent: &str) -> Option<String> {
+    let version = user_agent.strip_prefix("cargo ")?;
+    let version = version.split(' ').next()?;
+    let version = semver::Version::parse(version).ok()?;
+    Some(format!("{}.{}", version.major, version.minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    #[test]
+    fn test_parse_client_version() {
+        let parse = parse_client_version;
+        assert_eq!(
+            parse("cargo 1.74.0 (ecb9851af 2023-10-18)").as_deref(),
+            Some("1.74")
+        );
+        assert_eq!(
+            parse("cargo 1.78.0-nightly (7b7af3077 2024-02-17)").as_deref(),
+            Some("1.78")
+        );
+        assert_eq!(parse("cargo 1.60.0").as_deref(), Some("1.60"));
+        assert_eq!(parse("cargo"), None);
+        assert_eq!(parse("cargo foo"), None);
+        assert_eq!(parse("curl/8.4.0"), None);
+        assert_eq!(parse("-"), None);
+    }
+
+    #[test]
+    fn test_client_versions_map() {
+        let date = |date: &str| date.parse::<NaiveDate>().unwrap();
+
+        let mut downloads = ClientVersionsMap::new();
+        downloads.add("foo".into(), date("2023-12-25"), "1.74".into());
+        downloads.add("foo".into(), date("2023-12-25"), "1.74".into());
+        downloads.add("foo".into(), date("2023-12-25"), "1.60".into());
+        downloads.add("xmas".into(), date("2023-12-26"), "1.74".into());
+
+        assert_debug_snapshot!(downloads, @r###"
+        ClientVersionsMap {
+            2023-12-25  foo  cargo 1.60 .. 1
+            2023-12-25  foo  cargo 1.74 .. 2
+            2023-12-26  xmas  cargo 1.74 .. 1
+        }
+        "###);
+    }
+}
@@ -3,6 +3,7 @@
 //! see <https://docs.aws.amazon.com/AmazonCloudFront/latest/DeveloperGuide/AccessLogs.html#LogFileFormat>
 //! and <https://www.w3.org/TR/WD-logfile.html>.
 
+use crate::client_versions::parse_client_version;
 use crate::paths::parse_path;
 use crate::DownloadsMap;
 use chrono::NaiveDate;
@@ -18,6 +19,7 @@ const FIELD_DATE: &str = "date";
 const FIELD_METHOD: &str = "cs-method";
 const FIELD_PATH: &str = "cs-uri-stem";
 const FIELD_STATUS: &str = "sc-status";
+const FIELD_USER_AGENT: &str = "cs(User-Agent)";
 
 #[instrument(level = "debug", skip(reader))]
 pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Result<DownloadsMap> {
@@ -26,6 +28,7 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
     let mut method_index = None;
     let mut path_index = None;
     let mut status_index = None;
+    let mut user_agent_index = None;
 
     let mut downloads = DownloadsMap::new();
 
@@ -47,6 +50,7 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
             method_index = fields.iter().position(|f| f == &FIELD_METHOD);
             path_index = fields.iter().position(|f| f == &FIELD_PATH);
             status_index = fields.iter().position(|f| f == &FIELD_STATUS);
+            user_agent_index = fields.iter().position(|f| f == &FIELD_USER_AGENT);
 
             continue;
         }
@@ -99,7 +103,13 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
             }
         };
 
-        downloads.add(name, version, date);
+        // The user agent is optional, so a missing field is not worth a
+        // warning, unlike the other fields.
+        let client_version = user_agent_index
+            .and_then(|index| values.get(index))
+            .and_then(|user_agent| parse_client_version(&decode_path(user_agent)));
+
+        downloads.add_with_client(name, version, date, client_version);
     }
 
     Ok(downloads)
@@ -160,6 +170,38 @@ mod tests {
         "###);
     }
 
+    #[tokio::test]
+    async fn test_client_versions() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../test_data/cloudfront/basic.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads.client_versions(), @r###"
+        ClientVersionsMap {
+            2024-01-16  bindgen  cargo 1.74 .. 1
+            2024-01-16  cumulus-primitives-core  cargo 1.74 .. 1
+            2024-01-16  derive_more  cargo 1.74 .. 1
+            2024-01-16  hash-db  cargo 1.74 .. 1
+            2024-01-16  hyper-rustls  cargo 1.74 .. 1
+            2024-01-16  jsonrpsee-server  cargo 1.74 .. 1
+            2024-01-16  peeking_take_while  cargo 1.74 .. 1
+            2024-01-16  quick-error  cargo 1.74 .. 2
+            2024-01-16  tracing-core  cargo 1.74 .. 1
+            2024-01-17  flatbuffers  cargo 1.71 .. 1
+            2024-01-17  jemallocator  cargo 1.71 .. 1
+            2024-01-17  leveldb-sys  cargo 1.71 .. 1
+            2024-01-17  num_cpus  cargo 1.71 .. 1
+            2024-01-17  paste  cargo 1.71 .. 1
+            2024-01-17  quick-error  cargo 1.74 .. 1
+            2024-01-17  rand  cargo 1.71 .. 1
+            2024-01-17  serde_derive  cargo 1.71 .. 1
+            2024-01-17  smallvec  cargo 1.71 .. 1
+            2024-01-17  tar  cargo 1.71 .. 1
+        }
+        "###);
+    }
+
     #[tokio::test]
     async fn test_percent_encoding() {
         let _guard = enable_tracing_output();
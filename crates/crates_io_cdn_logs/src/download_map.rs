@@ -1,45 +1,87 @@
+use crate::client_versions::ClientVersionsMap;
 use chrono::NaiveDate;
-use derive_deref::Deref;
 use semver::Version;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
+use std::ops::Deref;
 
-#[derive(Clone, Default, Deref)]
-pub struct DownloadsMap(HashMap<(String, Version, NaiveDate), u64>);
+#[derive(Clone, Default)]
+pub struct DownloadsMap {
+    downloads: HashMap<(String, Version, NaiveDate), u64>,
+    client_versions: ClientVersionsMap,
+}
 
 impl DownloadsMap {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
     }
 
     /// Increments the download count for the given crate version on the given date.
     pub fn add(&mut self, name: String, version: Version, date: NaiveDate) {
-        *self.0.entry((name, version, date)).or_default() += 1;
+        *self.downloads.entry((name, version, date)).or_default() += 1;
+    }
+
+    /// Increments the download count for the given crate version on the given
+    /// date, and for the client version if one could be parsed from the
+    /// `User-Agent` header of the request.
+    pub fn add_with_client(
+        &mut self,
+        name: String,
+        version: Version,
+        date: NaiveDate,
+        client_version: Option<String>,
+    ) {
+        if let Some(client_version) = client_version {
+            self.client_versions.add(name.clone(), date, client_version);
+        }
+        self.add(name, version, date);
+    }
+
+    /// Returns the downloads per crate, date and client version.
+    pub fn client_versions(&self) -> &ClientVersionsMap {
+        &self.client_versions
+    }
+
+    /// Removes the downloads per client version from the map, leaving an
+    /// empty [ClientVersionsMap] in its place.
+    pub fn take_client_versions(&mut self) -> ClientVersionsMap {
+        std::mem::take(&mut self.client_versions)
     }
 
     /// Returns a [HashSet] of all crate names in the map.
     pub fn unique_crates(&self) -> HashSet<&str> {
-        self.0.keys().map(|(krate, _, _)| krate.as_str()).collect()
+        self.downloads
+            .keys()
+            .map(|(krate, _, _)| krate.as_str())
+            .collect()
     }
 
     /// Returns the total number of downloads across all crates and versions.
     pub fn sum_downloads(&self) -> u64 {
-        self.0.values().sum()
+        self.downloads.values().sum()
     }
 
     /// Converts the map into a vector of `(crate, version, date, downloads)` tuples.
     pub fn into_vec(self) -> Vec<(String, Version, NaiveDate, u64)> {
-        self.0
+        self.downloads
             .into_iter()
             .map(|((name, version, date), downloads)| (name, version, date, downloads))
             .collect()
     }
 }
 
+impl Deref for DownloadsMap {
+    type Target = HashMap<(String, Version, NaiveDate), u64>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.downloads
+    }
+}
+
 impl Debug for DownloadsMap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut downloads = self
-            .0
+            .downloads
             .iter()
             .map(|((krate, version, date), downloads)| (date, krate, version, downloads))
             .collect::<Vec<_>>();
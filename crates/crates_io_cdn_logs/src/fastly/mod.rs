@@ -4,6 +4,7 @@
 
 mod json;
 
+use crate::client_versions::parse_client_version;
 use crate::paths::parse_path;
 use crate::DownloadsMap;
 use std::borrow::Cow;
@@ -54,7 +55,9 @@ pub async fn count_downloads(reader: impl AsyncBufRead + Unpin) -> anyhow::Resul
 
         let date = json.date_time().date_naive();
 
-        downloads.add(name, version, date);
+        let client_version = json.user_agent().and_then(parse_client_version);
+
+        downloads.add_with_client(name, version, date, client_version);
     }
 
     Ok(downloads)
@@ -124,6 +127,21 @@ mod tests {
         "###);
     }
 
+    #[tokio::test]
+    async fn test_client_versions() {
+        let _guard = enable_tracing_output();
+
+        let mut cursor = Cursor::new(include_bytes!("../../test_data/fastly/user-agent.log"));
+        let downloads = assert_ok!(count_downloads(&mut cursor).await);
+
+        assert_debug_snapshot!(downloads.client_versions(), @r###"
+        ClientVersionsMap {
+            2024-01-16  strsim  cargo 1.74 .. 2
+            2024-01-16  strsim  cargo 1.78 .. 1
+        }
+        "###);
+    }
+
     #[tokio::test]
     async fn test_percent_encoding() {
         let _guard = enable_tracing_output();
@@ -1,3 +1,4 @@
+mod client_versions;
 pub mod cloudfront;
 mod compression;
 mod download_map;
@@ -6,6 +7,7 @@ mod paths;
 #[cfg(test)]
 mod test_utils;
 
+pub use crate::client_versions::{parse_client_version, ClientVersionsMap};
 pub use crate::compression::Decompressor;
 pub use crate::download_map::DownloadsMap;
 use std::io::Cursor;
@@ -0,0 +1,29 @@
+use reqwest::StatusCode;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+
+    #[error("crates.io returned an error ({status}): {}", .errors.join(", "))]
+    Api {
+        status: StatusCode,
+        errors: Vec<String>,
+    },
+
+    #[error("failed to parse the crates.io response: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// Extracts the error details from a failed response. Falls back to the
+    /// raw response body if it is not in the JSON error format of the API.
+    pub(crate) fn from_response(status: StatusCode, bytes: &[u8]) -> Self {
+        let errors = match serde_json::from_slice::<crate::ErrorResponse>(bytes) {
+            Ok(response) => response.into_details(),
+            Err(_) => vec![String::from_utf8_lossy(bytes).into_owned()],
+        };
+
+        Self::Api { status, errors }
+    }
+}
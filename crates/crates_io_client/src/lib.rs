@@ -0,0 +1,233 @@
+#![doc = include_str!("../README.md")]
+
+mod error;
+mod types;
+
+pub use crate::error::Error;
+pub use crate::types::*;
+
+use bytes::Bytes;
+use reqwest::header::{HeaderValue, ACCEPT, AUTHORIZATION};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use secrecy::{ExposeSecret, SecretString};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The base URL of the production instance of crates.io.
+pub const DEFAULT_BASE_URL: &str = "https://crates.io";
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: Url,
+    token: Option<SecretString>,
+}
+
+impl Client {
+    /// Creates a new client for the crates.io instance at `base_url`.
+    ///
+    /// crates.io rejects requests without a `User-Agent` header, so a
+    /// `user_agent` identifying the tool using this client is required.
+    pub fn new(base_url: Url, user_agent: &str) -> Result<Self> {
+        let http = reqwest::Client::builder().user_agent(user_agent).build()?;
+        Ok(Self::with_http_client(base_url, http))
+    }
+
+    /// Creates a new client using a preconfigured [`reqwest::Client`].
+    pub fn with_http_client(base_url: Url, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url,
+            token: None,
+        }
+    }
+
+    /// Uses the given API token to authenticate all requests of this client.
+    pub fn with_token(mut self, token: SecretString) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// `GET /api/v1/crates/:crate_id`
+    ///
+    /// The response includes the versions, keywords and categories of the crate.
+    pub async fn krate(&self, name: &str) -> Result<CrateResponse> {
+        let mut url = self.url(&["api", "v1", "crates", name]);
+        url.set_query(Some("include=versions,keywords,categories"));
+        self.send(self.request(Method::GET, url)).await
+    }
+
+    /// `GET /api/v1/crates/:crate_id/versions`
+    pub async fn versions(&self, name: &str) -> Result<VersionsResponse> {
+        let url = self.url(&["api", "v1", "crates", name, "versions"]);
+        self.send(self.request(Method::GET, url)).await
+    }
+
+    /// `GET /api/v1/crates/:crate_id/:version`
+    pub async fn version(&self, name: &str, version: &str) -> Result<VersionResponse> {
+        let url = self.url(&["api", "v1", "crates", name, version]);
+        self.send(self.request(Method::GET, url)).await
+    }
+
+    /// `GET /api/v1/crates`
+    ///
+    /// The `query` parameters are passed through unchanged, e.g.
+    /// `&[("q", "serde"), ("sort", "downloads")]`.
+    pub async fn search(&self, query: &[(&str, &str)]) -> Result<CrateList> {
+        let mut url = self.url(&["api", "v1", "crates"]);
+        url.query_pairs_mut().extend_pairs(query);
+        self.send(self.request(Method::GET, url)).await
+    }
+
+    /// `GET /api/v1/crates/:crate_id/owners`
+    pub async fn owners(&self, name: &str) -> Result<OwnersResponse> {
+        let url = self.url(&["api", "v1", "crates", name, "owners"]);
+        self.send(self.request(Method::GET, url)).await
+    }
+
+    /// `PUT /api/v1/crates/:crate_id/owners`
+    pub async fn add_owners(&self, name: &str, owners: &[&str]) -> Result<OkResponse> {
+        let url = self.url(&["api", "v1", "crates", name, "owners"]);
+        let body = serde_json::json!({ "owners": owners });
+        self.send(self.request(Method::PUT, url).json(&body)).await
+    }
+
+    /// `DELETE /api/v1/crates/:crate_id/owners`
+    pub async fn remove_owners(&self, name: &str, owners: &[&str]) -> Result<OkResponse> {
+        let url = self.url(&["api", "v1", "crates", name, "owners"]);
+        let body = serde_json::json!({ "owners": owners });
+        self.send(self.request(Method::DELETE, url).json(&body))
+            .await
+    }
+
+    /// `DELETE /api/v1/crates/:crate_id/:version/yank`
+    pub async fn yank(&self, name: &str, version: &str) -> Result<OkResponse> {
+        let url = self.url(&["api", "v1", "crates", name, version, "yank"]);
+        self.send(self.request(Method::DELETE, url)).await
+    }
+
+    /// `PUT /api/v1/crates/:crate_id/:version/unyank`
+    pub async fn unyank(&self, name: &str, version: &str) -> Result<OkResponse> {
+        let url = self.url(&["api", "v1", "crates", name, version, "unyank"]);
+        self.send(self.request(Method::PUT, url)).await
+    }
+
+    /// Returns the URL that `GET /api/v1/crates/:crate_id/:version/download`
+    /// redirects to, without downloading the crate file itself.
+    pub async fn download_url(&self, name: &str, version: &str) -> Result<String> {
+        let url = self.url(&["api", "v1", "crates", name, version, "download"]);
+        let response: DownloadUrlResponse = self.send(self.request(Method::GET, url)).await?;
+        Ok(response.url)
+    }
+
+    /// Downloads the crate file through `GET /api/v1/crates/:crate_id/:version/download`.
+    pub async fn download(&self, name: &str, version: &str) -> Result<Bytes> {
+        let url = self.url(&["api", "v1", "crates", name, version, "download"]);
+
+        let response = self.http.get(url).send().await?;
+        let status = response.status();
+        let bytes = response.bytes().await?;
+        if !status.is_success() {
+            return Err(Error::from_response(status, &bytes));
+        }
+
+        Ok(bytes)
+    }
+
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("base URL must be a valid base")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let mut request = self
+            .http
+            .request(method, url)
+            .header(ACCEPT, HeaderValue::from_static("application/json"));
+
+        if let Some(token) = &self.token {
+            request = request.header(AUTHORIZATION, token.expose_secret());
+        }
+
+        request
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        let bytes = response.bytes().await?;
+        parse_response(status, &bytes)
+    }
+}
+
+/// Deserializes the response body, or returns the errors reported by the API.
+///
+/// Some endpoints that are used by cargo return `200 OK` even for failed
+/// requests, so the body is checked for errors regardless of the status code.
+fn parse_response<T: DeserializeOwned>(status: StatusCode, bytes: &[u8]) -> Result<T> {
+    if !status.is_success() {
+        return Err(Error::from_response(status, bytes));
+    }
+
+    if let Ok(response) = serde_json::from_slice::<ErrorResponse>(bytes) {
+        return Err(Error::Api {
+            status,
+            errors: response.into_details(),
+        });
+    }
+
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_matches, assert_ok};
+
+    #[test]
+    fn url_segments_are_escaped() {
+        let base_url = Url::parse("https://crates.io/").unwrap();
+        let client = assert_ok!(Client::new(base_url, "test"));
+
+        let url = client.url(&["api", "v1", "crates", "foo", "1.0.0+bar/baz"]);
+        assert_eq!(
+            url.as_str(),
+            "https://crates.io/api/v1/crates/foo/1.0.0+bar%2Fbaz"
+        );
+    }
+
+    #[test]
+    fn successful_response() {
+        let body = br#"{ "ok": true }"#;
+        let response: OkResponse = assert_ok!(parse_response(StatusCode::OK, body));
+        assert!(response.ok);
+    }
+
+    #[test]
+    fn error_response() {
+        let body = br#"{ "errors": [{ "detail": "crate `foo` does not exist" }] }"#;
+        let result = parse_response::<OkResponse>(StatusCode::NOT_FOUND, body);
+        assert_matches!(result, Err(Error::Api { status, errors })
+            if status == StatusCode::NOT_FOUND && errors == ["crate `foo` does not exist"]);
+    }
+
+    #[test]
+    fn error_response_with_ok_status() {
+        let body = br#"{ "errors": [{ "detail": "must be logged in" }] }"#;
+        let result = parse_response::<OkResponse>(StatusCode::OK, body);
+        assert_matches!(result, Err(Error::Api { errors, .. }) if errors == ["must be logged in"]);
+    }
+
+    #[test]
+    fn plain_text_error_response() {
+        let body = b"Internal Server Error";
+        let result = parse_response::<OkResponse>(StatusCode::INTERNAL_SERVER_ERROR, body);
+        assert_matches!(result, Err(Error::Api { errors, .. }) if errors == ["Internal Server Error"]);
+    }
+}
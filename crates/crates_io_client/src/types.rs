@@ -0,0 +1,76 @@
+//! Response types of the crates.io API.
+//!
+//! The `Encodable*` views are shared with the crates.io backend through the
+//! `crates_io_api_types` crate, so they can't drift apart from the responses.
+
+use serde::Deserialize;
+
+pub use crates_io_api_types::{
+    EncodableAuditAction, EncodableCategory, EncodableCrate, EncodableCrateLinks, EncodableKeyword,
+    EncodableOwner, EncodablePublicUser, EncodablePublishedVia, EncodableVersion,
+    EncodableVersionLinks,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrateResponse {
+    #[serde(rename = "crate")]
+    pub krate: EncodableCrate,
+    pub versions: Option<Vec<EncodableVersion>>,
+    pub keywords: Option<Vec<EncodableKeyword>>,
+    pub categories: Option<Vec<EncodableCategory>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionsResponse {
+    pub versions: Vec<EncodableVersion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionResponse {
+    pub version: EncodableVersion,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrateList {
+    pub crates: Vec<EncodableCrate>,
+    pub meta: CrateListMeta,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrateListMeta {
+    pub total: i64,
+    pub next_page: Option<String>,
+    pub prev_page: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OwnersResponse {
+    pub users: Vec<EncodableOwner>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OkResponse {
+    pub ok: bool,
+    pub msg: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct DownloadUrlResponse {
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ErrorResponse {
+    errors: Vec<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    detail: String,
+}
+
+impl ErrorResponse {
+    pub(crate) fn into_details(self) -> Vec<String> {
+        self.errors.into_iter().map(|error| error.detail).collect()
+    }
+}
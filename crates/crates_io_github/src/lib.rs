@@ -152,6 +152,10 @@ impl GitHubClient for RealGitHubClient {
 
 #[derive(Debug, thiserror::Error)]
 pub enum GitHubError {
+    /// GitHub rejected the access token, e.g. because the user revoked the
+    /// authorization of the OAuth app.
+    #[error(transparent)]
+    Unauthorized(anyhow::Error),
     #[error(transparent)]
     Permission(anyhow::Error),
     #[error(transparent)]
@@ -165,7 +169,8 @@ impl From<reqwest::Error> for GitHubError {
         use reqwest::StatusCode as Status;
 
         match error.status() {
-            Some(Status::UNAUTHORIZED) | Some(Status::FORBIDDEN) => Self::Permission(error.into()),
+            Some(Status::UNAUTHORIZED) => Self::Unauthorized(error.into()),
+            Some(Status::FORBIDDEN) => Self::Permission(error.into()),
             Some(Status::NOT_FOUND) => Self::NotFound(error.into()),
             _ => Self::Other(error.into()),
         }
@@ -24,6 +24,14 @@ pub struct Crate {
     pub links: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rust_version: Option<String>,
+    /// Whether the crate owners recommend this version, e.g. because it is
+    /// the latest release of a long-term support line.
+    ///
+    /// This auxiliary field is only present on the entry of the recommended
+    /// version, which doesn't have to be the highest version. Cargo ignores
+    /// it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommended: Option<bool>,
     /// The schema version for this entry.
     ///
     /// If this is None, it defaults to version 1. Entries with unknown
@@ -31,6 +31,7 @@ mod tests {
             yanked: None,
             links: None,
             rust_version: None,
+            recommended: None,
             v: None,
         };
         let mut buffer = Vec::new();
@@ -55,6 +56,7 @@ mod tests {
                 yanked: None,
                 links: None,
                 rust_version: None,
+                recommended: None,
                 v: None,
             })
             .collect::<Vec<_>>();
@@ -1,5 +1,7 @@
 //! Render Markdown files to HTML.
 
+mod rst;
+
 use ammonia::{Builder, UrlRelative, UrlRelativeEvaluate};
 use comrak::nodes::{AstNode, NodeValue};
 use htmlescape::encode_minimal;
@@ -7,6 +9,10 @@ use std::borrow::Cow;
 use std::path::Path;
 use url::Url;
 
+/// Rewrites the absolute URL of an image, e.g. to serve the image through a
+/// proxy. See [`text_to_html_with_image_proxy`].
+pub type ImageProxy = Box<dyn Fn(&str) -> String + Send + Sync>;
+
 /// Context for markdown to HTML rendering.
 struct MarkdownRenderer<'a> {
     html_sanitizer: Builder<'a>,
@@ -17,7 +23,11 @@ impl<'a> MarkdownRenderer<'a> {
     ///
     /// Per `text_to_html`, `base_url` is the base URL prepended to any
     /// relative links in the input document.  See that function for more detail.
-    fn new(base_url: Option<&'a str>, base_dir: &'a str) -> MarkdownRenderer<'a> {
+    fn new(
+        base_url: Option<&'a str>,
+        base_dir: &'a str,
+        image_proxy: Option<ImageProxy>,
+    ) -> MarkdownRenderer<'a> {
         let allowed_classes = hashmap(&[
             (
                 "code",
@@ -55,6 +65,32 @@ impl<'a> MarkdownRenderer<'a> {
             .allowed_classes(allowed_classes)
             .url_relative(sanitize_url)
             .id_prefix(Some("user-content-"));
+
+        if let Some(image_proxy) = image_proxy {
+            // Relative image URLs are resolved here as well, since it is not
+            // guaranteed that `url_relative` is applied first.
+            let sanitize_url = SanitizeUrl::new(base_url, base_dir);
+            html_sanitizer.attribute_filter(move |element, attribute, value| {
+                if element != "img" || attribute != "src" {
+                    return Some(Cow::Borrowed(value));
+                }
+
+                let url = match Url::parse(value) {
+                    Ok(url) => url,
+                    Err(url::ParseError::RelativeUrlWithoutBase) => {
+                        Url::parse(&sanitize_url.evaluate(value)?).ok()?
+                    }
+                    Err(_) => return None,
+                };
+
+                if !matches!(url.scheme(), "http" | "https") {
+                    return None;
+                }
+
+                Some(Cow::Owned(image_proxy(url.as_str())))
+            });
+        }
+
         MarkdownRenderer { html_sanitizer }
     }
 
@@ -232,10 +268,16 @@ impl UrlRelativeEvaluate<'_> for SanitizeUrl {
     }
 }
 
-/// Renders Markdown text to sanitized HTML with a given `base_url`.
+/// Renders Markdown text to sanitized HTML with a given `base_url`, and
+/// rewrites the image URLs with `image_proxy`, if any.
 /// See `text_to_html` for the interpretation of `base_url`.
-fn markdown_to_html(text: &str, base_url: Option<&str>, base_dir: &str) -> String {
-    let renderer = MarkdownRenderer::new(base_url, base_dir);
+fn proxied_markdown_to_html(
+    text: &str,
+    base_url: Option<&str>,
+    base_dir: &str,
+    image_proxy: Option<ImageProxy>,
+) -> String {
+    let renderer = MarkdownRenderer::new(base_url, base_dir, image_proxy);
     renderer.to_html(text)
 }
 
@@ -244,8 +286,17 @@ fn markdown_to_html(text: &str, base_url: Option<&str>, base_dir: &str) -> Strin
 static MARKDOWN_EXTENSIONS: [&str; 7] =
     ["md", "markdown", "mdown", "mdwn", "mkd", "mkdn", "mkdown"];
 
+/// Any file with a filename ending in one of these extensions will be converted from
+/// reStructuredText to Markdown before it is rendered.
+static RST_EXTENSIONS: [&str; 2] = ["rst", "rest"];
+
 /// Renders a text file to sanitized HTML.  An appropriate rendering method is chosen depending
-/// on the extension of the supplied `filename`.
+/// on the extension of the supplied `filename`:
+///
+/// - Markdown files, and files without an extension, are rendered as Markdown.
+/// - reStructuredText files are converted to Markdown first, see the `rst` module for the
+///   supported subset.
+/// - All other files, e.g. `README.txt`, are rendered as escaped, pre-formatted plain text.
 ///
 /// The returned text will not contain any harmful HTML tag or attribute (such as iframe,
 /// onclick, onmouseover, etc.).
@@ -269,6 +320,55 @@ pub fn text_to_html<P: AsRef<Path>>(
     readme_path_in_pkg: P,
     base_url: Option<&str>,
     pkg_path_in_vcs: Option<P>,
+) -> String {
+    render_text(text, readme_path_in_pkg, base_url, pkg_path_in_vcs, None)
+}
+
+/// Renders a text file to sanitized HTML like [`text_to_html`], but replaces
+/// the URLs of all images with the URL that `image_proxy` returns for them.
+///
+/// Relative image URLs are resolved against `base_url` first, and images with
+/// URLs that are neither `http` nor `https` URLs are removed. This allows
+/// serving all images through a proxy, so that the browsers of readers don't
+/// load them from third-party servers or over plain HTTP.
+///
+/// # Examples
+///
+/// ```
+/// use crates_io_markdown::text_to_html_with_image_proxy;
+///
+/// let text = "![logo](http://example.com/logo.png)";
+/// let proxy = |url: &str| format!("https://proxy.example/?url={url}");
+/// let rendered = text_to_html_with_image_proxy(text, "README.md", None, None, proxy);
+/// assert_eq!(rendered, "<p><img src=\"https://proxy.example/?url=http://example.com/logo.png\" alt=\"logo\"></p>\n");
+/// ```
+pub fn text_to_html_with_image_proxy<P, F>(
+    text: &str,
+    readme_path_in_pkg: P,
+    base_url: Option<&str>,
+    pkg_path_in_vcs: Option<P>,
+    image_proxy: F,
+) -> String
+where
+    P: AsRef<Path>,
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    let image_proxy: ImageProxy = Box::new(image_proxy);
+    render_text(
+        text,
+        readme_path_in_pkg,
+        base_url,
+        pkg_path_in_vcs,
+        Some(image_proxy),
+    )
+}
+
+fn render_text<P: AsRef<Path>>(
+    text: &str,
+    readme_path_in_pkg: P,
+    base_url: Option<&str>,
+    pkg_path_in_vcs: Option<P>,
+    image_proxy: Option<ImageProxy>,
 ) -> String {
     let path_in_vcs = match pkg_path_in_vcs {
         None => readme_path_in_pkg.as_ref().to_path_buf(),
@@ -277,17 +377,34 @@ pub fn text_to_html<P: AsRef<Path>>(
 
     let base_dir = path_in_vcs.parent().and_then(|p| p.to_str()).unwrap_or("");
 
-    if path_in_vcs.extension().is_none() {
-        return markdown_to_html(text, base_url, base_dir);
+    let Some(ext) = path_in_vcs.extension() else {
+        return proxied_markdown_to_html(text, base_url, base_dir, image_proxy);
+    };
+
+    let ext = ext.to_string_lossy().to_lowercase();
+    if MARKDOWN_EXTENSIONS.contains(&ext.as_str()) {
+        return proxied_markdown_to_html(text, base_url, base_dir, image_proxy);
     }
 
-    if let Some(ext) = path_in_vcs.extension().and_then(|ext| ext.to_str()) {
-        if MARKDOWN_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
-            return markdown_to_html(text, base_url, base_dir);
-        }
+    if RST_EXTENSIONS.contains(&ext.as_str()) {
+        let markdown = rst::rst_to_markdown(text);
+        return proxied_markdown_to_html(&markdown, base_url, base_dir, image_proxy);
+    }
+
+    plain_text_to_html(text)
+}
+
+/// Renders plain text as escaped, pre-formatted HTML, which keeps the
+/// whitespace and line breaks of the text intact.
+fn plain_text_to_html(text: &str) -> String {
+    if text.trim().is_empty() {
+        return String::new();
     }
 
-    encode_minimal(text).replace('\n', "<br>\n")
+    format!(
+        "<pre>{}</pre>\n",
+        encode_minimal(text.trim_end_matches('\n'))
+    )
 }
 
 /// Helper function to build a new `HashSet` from the items slice.
@@ -312,6 +429,10 @@ mod tests {
     use super::*;
     use insta::assert_snapshot;
 
+    fn markdown_to_html(text: &str, base_url: Option<&str>, base_dir: &str) -> String {
+        proxied_markdown_to_html(text, base_url, base_dir, None)
+    }
+
     #[test]
     fn empty_text() {
         let text = "";
@@ -599,12 +720,41 @@ There can also be some text in between!
 
     #[test]
     fn text_to_html_renders_other_things() {
-        for f in &["readme.exe", "readem.org", "blah.adoc"] {
+        for f in &[
+            "readme.exe",
+            "readem.org",
+            "blah.adoc",
+            "README.txt",
+            "s/README.TXT",
+        ] {
             assert_eq!(
-                text_to_html("<script>lobster</script>\n\nis my friend\n", f, None, None),
-                "&lt;script&gt;lobster&lt;/script&gt;<br>\n<br>\nis my friend<br>\n"
+                text_to_html(
+                    "<script>lobster</script>\n\n  is my  friend\n",
+                    f,
+                    None,
+                    None
+                ),
+                "<pre>&lt;script&gt;lobster&lt;/script&gt;\n\n  is my  friend</pre>\n"
             );
         }
+
+        assert_eq!(text_to_html("\n\n", "README.txt", None, None), "");
+    }
+
+    #[test]
+    fn text_to_html_renders_rst() {
+        let text = "Lobster\n=======\n\nSee `the docs <docs/lobster.rst>`_::\n\n    cargo add lobster\n\n<script>alert(1)</script>\n";
+        let base_url = Some("https://github.com/rust-lang/test");
+        let html = text_to_html(text, "README.rst", base_url, None);
+        assert_snapshot!(html, @r###"
+        <h1><a href="#lobster" id="user-content-lobster" rel="nofollow noopener noreferrer"></a>Lobster</h1>
+        <p>See <a href="https://github.com/rust-lang/test/blob/HEAD/docs/lobster.rst" rel="nofollow noopener noreferrer">the docs</a>:</p>
+        <pre><code>cargo add lobster
+        </code></pre>
+        <p>&lt;script&gt;alert(1)&lt;/script&gt;</p>
+        "###);
+
+        assert_eq!(text_to_html(text, "README.REST", base_url, None), html);
     }
 
     #[test]
@@ -651,4 +801,25 @@ There can also be some text in between!
         <p align="center"><img src="https://img.shields.io/crates/v/clap.svg" alt=""></p>
         "###);
     }
+
+    #[test]
+    fn images_are_proxied() {
+        let proxy = |url: &str| format!("https://crates.io/proxy?url={url}");
+        let text = "![badge](http://img.shields.io/badge.svg) ![logo](logo.png)\n\n\
+            <img src=\"data:image/png;base64,AAAA\"> [link](http://example.com/)\n";
+        let repository = Some("https://github.com/foo/bar");
+
+        let html = text_to_html_with_image_proxy(text, "README.md", repository, None, proxy);
+        assert_snapshot!(html, @r###"
+        <p><img src="https://crates.io/proxy?url=http://img.shields.io/badge.svg" alt="badge"> <img src="https://crates.io/proxy?url=https://github.com/foo/bar/raw/HEAD/logo.png" alt="logo"></p>
+        <p><img> <a href="http://example.com/" rel="nofollow noopener noreferrer">link</a></p>
+        "###);
+
+        // Relative images are removed if there is no repository to resolve them against
+        let html =
+            text_to_html_with_image_proxy("![logo](logo.png)", "README.md", None, None, proxy);
+        assert_snapshot!(html, @r###"
+        <p><img alt="logo"></p>
+        "###);
+    }
 }
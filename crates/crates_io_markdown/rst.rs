@@ -0,0 +1,587 @@
+//! Conversion of reStructuredText to Markdown.
+//!
+//! Only the subset of reStructuredText that is commonly found in READMEs is
+//! supported: section titles, paragraphs, bullet and enumerated lists, block
+//! quotes, literal and code blocks, tables, images (including badges that
+//! are defined as substitutions), admonitions and inline markup with
+//! hyperlinks. Other directives and comments are dropped.
+//!
+//! The resulting Markdown is rendered and sanitized like any other README,
+//! so markup that is not supported is at worst displayed verbatim.
+
+use std::collections::HashMap;
+
+/// Characters that can be used to adorn section titles.
+const ADORNMENT_CHARS: &str = "=-`:'\"~^_*+#<>";
+
+/// Directives whose content is rendered as a block quote with a title.
+const ADMONITIONS: [&str; 10] = [
+    "attention",
+    "caution",
+    "danger",
+    "error",
+    "hint",
+    "important",
+    "note",
+    "seealso",
+    "tip",
+    "warning",
+];
+
+/// Converts a reStructuredText document to Markdown.
+pub(crate) fn rst_to_markdown(text: &str) -> String {
+    let lines = text.lines().map(str::trim_end).collect::<Vec<_>>();
+
+    let mut converter = Converter {
+        targets: collect_targets(&lines),
+        substitutions: collect_substitutions(&lines),
+        title_styles: Vec::new(),
+    };
+
+    let mut blocks = Vec::new();
+    converter.convert_lines(&lines, &mut blocks);
+    blocks.join("\n\n") + "\n"
+}
+
+struct Converter {
+    /// URLs of the named hyperlink targets (`.. _name: url`), by their
+    /// lowercase name.
+    targets: HashMap<String, String>,
+    /// Markdown of the image substitution definitions
+    /// (`.. |name| image:: url`), by their name.
+    substitutions: HashMap<String, String>,
+    /// The adornment styles of the section titles in the order of their
+    /// first appearance, which determines the heading levels.
+    title_styles: Vec<(char, bool)>,
+}
+
+impl Converter {
+    fn convert_lines(&mut self, lines: &[&str], blocks: &mut Vec<String>) {
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            if line.is_empty() {
+                i += 1;
+                continue;
+            }
+
+            // Section title with overline and underline
+            if let Some(style) = adornment(line) {
+                if let (Some(title), Some(underline)) = (lines.get(i + 1), lines.get(i + 2)) {
+                    if !title.is_empty() && adornment(underline) == Some(style) {
+                        blocks.push(self.heading(title.trim(), (style, true)));
+                        i += 3;
+                        continue;
+                    }
+                }
+            }
+
+            // Section title with underline only
+            if !line.starts_with(char::is_whitespace) {
+                if let Some(underline) = lines.get(i + 1) {
+                    let is_long_enough = underline.chars().count() >= line.chars().count();
+                    if let Some(style) = adornment(underline).filter(|_| is_long_enough) {
+                        blocks.push(self.heading(line, (style, false)));
+                        i += 2;
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(directive) = line.strip_prefix(".. ").or((line == "..").then_some("")) {
+                let end = indented_block_end(lines, i + 1);
+                let body = dedent(&lines[i + 1..end]);
+                if let Some(block) = self.directive(directive.trim(), &body) {
+                    blocks.push(block);
+                }
+                i = end;
+                continue;
+            }
+
+            let end = lines[i..]
+                .iter()
+                .position(|line| line.is_empty())
+                .map_or(lines.len(), |offset| i + offset);
+
+            let paragraph = &lines[i..end];
+            i = end;
+
+            if is_table(paragraph[0]) {
+                blocks.push(code_block(&paragraph.join("\n"), ""));
+                continue;
+            }
+
+            let (paragraph, is_literal) = strip_literal_marker(paragraph);
+            if !paragraph.is_empty() {
+                blocks.push(self.paragraph(&paragraph));
+            }
+
+            if is_literal {
+                let start = lines[i..]
+                    .iter()
+                    .position(|line| !line.is_empty())
+                    .map_or(lines.len(), |offset| i + offset);
+
+                let end = indented_block_end(lines, start);
+                if end > start {
+                    blocks.push(code_block(&dedent(&lines[start..end]).join("\n"), ""));
+                }
+                i = end;
+            }
+        }
+    }
+
+    fn heading(&mut self, title: &str, style: (char, bool)) -> String {
+        let level = match self.title_styles.iter().position(|s| *s == style) {
+            Some(index) => index + 1,
+            None => {
+                self.title_styles.push(style);
+                self.title_styles.len()
+            }
+        };
+
+        format!("{} {}", "#".repeat(level.min(6)), self.inline(title))
+    }
+
+    fn paragraph(&self, lines: &[String]) -> String {
+        let indentation = indentation(&lines[0]);
+        let is_list_item = list_item_marker(lines[0].trim_start()).is_some();
+
+        let lines = lines.iter().map(|line| {
+            let line = line.trim_start();
+            let line = match list_item_marker(line) {
+                Some((marker, rest)) => format!("{marker} {}", self.inline(rest)),
+                None => self.inline(line),
+            };
+
+            match (indentation, is_list_item) {
+                (0, _) => line,
+                // Nested lists are indented like their parent's content in
+                // both languages
+                (indentation, true) => format!("{}{line}", " ".repeat(indentation)),
+                (_, false) => format!("> {line}"),
+            }
+        });
+
+        lines.collect::<Vec<_>>().join("\n")
+    }
+
+    fn directive(&mut self, directive: &str, body: &[String]) -> Option<String> {
+        let (name, argument) = directive.split_once("::")?;
+        let (name, argument) = (name.trim(), argument.trim());
+        let (options, content) = split_options(body);
+
+        match name {
+            "code" | "code-block" | "sourcecode" => Some(code_block(&content.join("\n"), argument)),
+            "image" | "figure" => Some(image(argument, &options)),
+            name if ADMONITIONS.contains(&name) => {
+                let mut title = name[..1].to_uppercase() + &name[1..];
+                if !argument.is_empty() {
+                    title = format!("{title}: {argument}");
+                }
+
+                let lines = content.iter().map(String::as_str).collect::<Vec<_>>();
+                let mut blocks = Vec::new();
+                self.convert_lines(&lines, &mut blocks);
+
+                let quote = std::iter::once(format!("**{}**", self.inline(&title)))
+                    .chain(blocks)
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+                    .lines()
+                    .map(|line| format!("> {line}").trim_end().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                Some(quote)
+            }
+            // Comments, hyperlink targets, substitution definitions and
+            // unsupported directives
+            _ => None,
+        }
+    }
+
+    /// Converts the inline markup of a line of text.
+    fn inline(&self, text: &str) -> String {
+        let mut output = String::new();
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            if let Some(literal) = rest.strip_prefix("``") {
+                if let Some(end) = literal.find("``") {
+                    output.push_str(&code_span(&literal[..end]));
+                    rest = &literal[end + 2..];
+                    continue;
+                }
+            }
+
+            if let Some(role) = rest.strip_prefix(':') {
+                if let Some((role, interpreted)) = role.split_once(":`") {
+                    let is_role =
+                        !role.is_empty() && role.chars().all(|c| c.is_alphanumeric() || c == '-');
+                    if let Some(end) = interpreted.find('`').filter(|_| is_role) {
+                        let content = &interpreted[..end];
+                        match role {
+                            "code" | "literal" => output.push_str(&code_span(content)),
+                            _ => output.push_str(&escape(content)),
+                        }
+                        rest = &interpreted[end + 1..];
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(interpreted) = rest.strip_prefix('`') {
+                if let Some(end) = interpreted.find('`') {
+                    let content = &interpreted[..end];
+                    let after = &interpreted[end + 1..];
+                    if let Some(after) = after.strip_prefix("__").or(after.strip_prefix('_')) {
+                        output.push_str(&self.reference(content));
+                        rest = after;
+                    } else {
+                        output.push_str(&format!("*{}*", escape(content)));
+                        rest = after;
+                    }
+                    continue;
+                }
+            }
+
+            if let Some(name) = rest.strip_prefix('|') {
+                if let Some((name, after)) = name.split_once('|') {
+                    if let Some(substitution) = self.substitutions.get(name) {
+                        output.push_str(substitution);
+                        rest = after
+                            .strip_prefix("__")
+                            .or(after.strip_prefix('_'))
+                            .unwrap_or(after);
+                        continue;
+                    }
+                }
+            }
+
+            output.push_str(&escape(&rest[..c.len_utf8()]));
+            rest = &rest[c.len_utf8()..];
+        }
+
+        output
+    }
+
+    /// Converts a hyperlink reference like `` `text <url>`_ `` or
+    /// `` `name`_ `` to a Markdown link.
+    fn reference(&self, content: &str) -> String {
+        if let Some((text, url)) = content
+            .strip_suffix('>')
+            .and_then(|content| content.rsplit_once('<'))
+        {
+            let text = text.trim();
+            let text = if text.is_empty() { url } else { text };
+            return format!("[{}]({})", escape(text), url.trim());
+        }
+
+        match self.targets.get(&content.to_lowercase()) {
+            Some(url) => format!("[{}]({url})", escape(content)),
+            None => escape(content),
+        }
+    }
+}
+
+/// Returns the character of a line that only consists of a repeated
+/// adornment character.
+fn adornment(line: &str) -> Option<char> {
+    let mut chars = line.chars();
+    let first = chars.next()?;
+    let is_adornment = ADORNMENT_CHARS.contains(first)
+        && line.len() >= 2
+        && chars.all(|c| c == first)
+        // A line of `::` marks a literal block
+        && line != "::";
+
+    is_adornment.then_some(first)
+}
+
+/// Returns `true` if the line starts a grid table or a simple table, which
+/// are displayed as preformatted text.
+fn is_table(line: &str) -> bool {
+    (line.starts_with("+-") || line.starts_with("+="))
+        || (line.starts_with("==")
+            && line.contains(' ')
+            && line.chars().all(|c| c == '=' || c == ' '))
+}
+
+/// Splits a list item into its Markdown marker and the content.
+fn list_item_marker(line: &str) -> Option<(&str, &str)> {
+    for bullet in ["- ", "* ", "+ ", "• "] {
+        if let Some(rest) = line.strip_prefix(bullet) {
+            return Some(("-", rest));
+        }
+    }
+
+    if let Some(rest) = line.strip_prefix("#. ") {
+        return Some(("1.", rest));
+    }
+
+    let (number, rest) = line.split_once(". ")?;
+    let is_number = !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+    is_number.then(|| (&line[..number.len() + 1], rest))
+}
+
+/// Removes the `::` that marks the following indented block as a literal
+/// block from a paragraph.
+fn strip_literal_marker(paragraph: &[&str]) -> (Vec<String>, bool) {
+    let mut lines = paragraph
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+
+    let Some(last) = lines.last_mut() else {
+        return (lines, false);
+    };
+
+    let Some(text) = last.strip_suffix("::") else {
+        return (lines, false);
+    };
+
+    if text.trim().is_empty() {
+        lines.pop();
+    } else if text.ends_with(char::is_whitespace) {
+        *last = text.trim_end().to_string();
+    } else {
+        *last = format!("{text}:");
+    }
+
+    (lines, true)
+}
+
+/// Returns the index after the indented block that starts at `start`,
+/// excluding trailing blank lines.
+fn indented_block_end(lines: &[&str], start: usize) -> usize {
+    let mut end = start;
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        if line.is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            break;
+        }
+        end = index + 1;
+    }
+    end
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Removes the common indentation of the lines.
+fn dedent(lines: &[&str]) -> Vec<String> {
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| indentation(line))
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indentation..).unwrap_or_default().to_string())
+        .collect()
+}
+
+/// Splits the body of a directive into its options (`:name: value`) and its
+/// content.
+fn split_options(body: &[String]) -> (HashMap<&str, &str>, &[String]) {
+    let mut options = HashMap::new();
+    for (index, line) in body.iter().enumerate() {
+        let option = line
+            .strip_prefix(':')
+            .and_then(|option| option.split_once(':'));
+
+        match option {
+            Some((name, value)) => options.insert(name, value.trim()),
+            None => {
+                let content = &body[index..];
+                let start = content.iter().position(|line| !line.is_empty());
+                return (options, &content[start.unwrap_or(content.len())..]);
+            }
+        };
+    }
+    (options, &[])
+}
+
+fn image(url: &str, options: &HashMap<&str, &str>) -> String {
+    let alt = options.get("alt").copied().unwrap_or_default();
+    let image = format!("![{}]({url})", escape(alt));
+    match options.get("target") {
+        Some(target) => format!("[{image}]({target})"),
+        None => image,
+    }
+}
+
+fn code_block(code: &str, language: &str) -> String {
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{fence}{language}\n{code}\n{fence}")
+}
+
+fn code_span(code: &str) -> String {
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    if code.starts_with('`') || code.ends_with('`') {
+        format!("{fence} {code} {fence}")
+    } else {
+        format!("{fence}{code}{fence}")
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c)
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Escapes the characters that would otherwise be interpreted as HTML by the
+/// Markdown renderer.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;")
+}
+
+/// Collects the named hyperlink targets (`.. _name: url`) of the document.
+fn collect_targets(lines: &[&str]) -> HashMap<String, String> {
+    lines
+        .iter()
+        .filter_map(|line| line.strip_prefix(".. _"))
+        .filter_map(|target| target.split_once(": "))
+        .map(|(name, url)| {
+            (
+                name.trim_matches('`').to_lowercase(),
+                url.trim().to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Collects the image substitution definitions (`.. |name| image:: url`) of
+/// the document.
+fn collect_substitutions(lines: &[&str]) -> HashMap<String, String> {
+    let mut substitutions = HashMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        let Some(definition) = line.strip_prefix(".. |") else {
+            continue;
+        };
+        let Some((name, directive)) = definition.split_once('|') else {
+            continue;
+        };
+        let Some(url) = directive.trim().strip_prefix("image::") else {
+            continue;
+        };
+
+        let end = indented_block_end(lines, index + 1);
+        let body = dedent(&lines[index + 1..end]);
+        let (options, _) = split_options(&body);
+        substitutions.insert(name.to_string(), image(url.trim(), &options));
+    }
+    substitutions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_snapshot;
+
+    #[test]
+    fn titles_and_paragraphs() {
+        let text = "=======\nMy Crate\n=======\n\nIntro with *emphasis*, **strong** and ``code``.\n\nUsage\n-----\n\nSee `the docs <https://docs.rs/foo>`_ or `Rust`_.\n\nDetails\n~~~~~~~\n\nAnother section\n---------------\n\n.. _rust: https://www.rust-lang.org/\n";
+        assert_snapshot!(rst_to_markdown(text), @r###"
+        # My Crate
+
+        Intro with *emphasis*, **strong** and `code`.
+
+        ## Usage
+
+        See [the docs](https://docs.rs/foo) or [Rust](https://www.rust-lang.org/).
+
+        ### Details
+
+        ## Another section
+        "###);
+    }
+
+    #[test]
+    fn literal_and_code_blocks() {
+        let text = "Example::\n\n    let x = 1;\n\n    let y = 2;\n\nMore text ::\n\n    $ cargo build\n\n::\n\n    raw\n\n.. code-block:: rust\n   :linenos:\n\n   fn main() {}\n";
+        assert_snapshot!(rst_to_markdown(text), @r###"
+        Example:
+
+        ```
+        let x = 1;
+
+        let y = 2;
+        ```
+
+        More text
+
+        ```
+        $ cargo build
+        ```
+
+        ```
+        raw
+        ```
+
+        ```rust
+        fn main() {}
+        ```
+        "###);
+    }
+
+    #[test]
+    fn lists_and_quotes() {
+        let text = "* one\n* two\n  continued\n\n  - nested\n\n#. first\n2. second\n\n    A quote\n    with two lines\n";
+        assert_snapshot!(rst_to_markdown(text), @r###"
+        - one
+        - two
+        continued
+
+          - nested
+
+        1. first
+        2. second
+
+        > A quote
+        > with two lines
+        "###);
+    }
+
+    #[test]
+    fn images_and_badges() {
+        let text = "|build| |docs|\n\n.. |build| image:: https://example.com/build.svg\n   :alt: Build Status\n   :target: https://example.com/ci\n\n.. |docs| image:: https://example.com/docs.svg\n\n.. image:: logo.png\n   :alt: Logo\n";
+        assert_snapshot!(rst_to_markdown(text), @r###"
+        [![Build Status](https://example.com/build.svg)](https://example.com/ci) ![](https://example.com/docs.svg)
+
+        ![Logo](logo.png)
+        "###);
+    }
+
+    #[test]
+    fn admonitions_tables_and_comments() {
+        let text = ".. note::\n   Requires *nightly*.\n\n.. This is a comment\n   spanning lines.\n\n=====  =====\nA      B\n=====  =====\n1      2\n=====  =====\n";
+        assert_snapshot!(rst_to_markdown(text), @r###"
+        > **Note**
+        >
+        > Requires *nightly*.
+
+        ```
+        =====  =====
+        A      B
+        =====  =====
+        1      2
+        =====  =====
+        ```
+        "###);
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let text = "<script>alert(1)</script> & ``<b>``";
+        assert_snapshot!(rst_to_markdown(text), @"&lt;script>alert(1)&lt;/script> &amp; `<b>`");
+    }
+}
@@ -0,0 +1,183 @@
+//! Inserts the generated data into the database.
+//!
+//! Users and crates that already exist are skipped, together with everything
+//! that belongs to them, so that seeding the same data twice does not fail or
+//! duplicate anything.
+
+use crate::generate::{SeedCrate, SeedUser};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Date, Integer, Text, Timestamp};
+use std::collections::HashMap;
+
+/// The `owner_kind` of users in the `crate_owners` table.
+const OWNER_KIND_USER: i32 = 0;
+
+/// The `action` of owner additions in the `crate_owner_actions` table.
+const OWNER_ACTION_ADD: i32 = 0;
+/// The `via` of owners that published the first version of a crate.
+const OWNER_ACTION_VIA_PUBLISH: i32 = 1;
+/// The `via` of owners that accepted an invitation.
+const OWNER_ACTION_VIA_INVITATION: i32 = 2;
+
+#[derive(QueryableByName)]
+struct Id {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+}
+
+#[derive(QueryableByName)]
+struct UserId {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+    #[diesel(sql_type = Integer)]
+    gh_id: i32,
+}
+
+/// Inserts the users and returns the IDs of all of them by their GitHub ID,
+/// including the ones that already existed.
+pub fn insert_users(conn: &mut PgConnection, users: &[SeedUser]) -> QueryResult<HashMap<i32, i32>> {
+    conn.transaction(|conn| {
+        for user in users {
+            diesel::sql_query(
+                r#"
+                    INSERT INTO users (gh_access_token, gh_login, name, gh_id)
+                    VALUES ('', $1, $2, $3)
+                    ON CONFLICT (gh_id) WHERE gh_id > 0 DO NOTHING
+                "#,
+            )
+            .bind::<Text, _>(&user.login)
+            .bind::<Text, _>(&user.name)
+            .bind::<Integer, _>(user.gh_id)
+            .execute(conn)?;
+        }
+
+        let gh_ids = users.iter().map(|user| user.gh_id).collect::<Vec<_>>();
+        let ids = diesel::sql_query("SELECT id, gh_id FROM users WHERE gh_id = ANY($1)")
+            .bind::<Array<Integer>, _>(gh_ids)
+            .load::<UserId>(conn)?;
+
+        Ok(ids.into_iter().map(|row| (row.gh_id, row.id)).collect())
+    })
+}
+
+/// Returns `true` if a crate with the given name exists.
+pub fn crate_exists(conn: &mut PgConnection, name: &str) -> QueryResult<bool> {
+    let ids = diesel::sql_query(
+        "SELECT id FROM crates WHERE canon_crate_name(name) = canon_crate_name($1)",
+    )
+    .bind::<Text, _>(name)
+    .load::<Id>(conn)?;
+
+    Ok(!ids.is_empty())
+}
+
+/// Inserts a crate with its owners, versions and downloads.
+///
+/// Returns `false` if the crate already existed, in which case nothing is
+/// changed. The caller is expected to run this in a transaction.
+pub fn insert_crate(
+    conn: &mut PgConnection,
+    krate: &SeedCrate,
+    owner_ids: &[i32],
+) -> QueryResult<bool> {
+    let crate_id = diesel::sql_query(
+        r#"
+            INSERT INTO crates (name, description, created_at, updated_at)
+            VALUES ($1, $2, $3, $3)
+            ON CONFLICT DO NOTHING
+            RETURNING id
+        "#,
+    )
+    .bind::<Text, _>(&krate.name)
+    .bind::<Text, _>(&krate.description)
+    .bind::<Timestamp, _>(krate.created_at)
+    .get_result::<Id>(conn)
+    .optional()?;
+
+    let Some(Id { id: crate_id }) = crate_id else {
+        return Ok(false);
+    };
+
+    let publisher_id = owner_ids[0];
+    for (index, owner_id) in owner_ids.iter().enumerate() {
+        let via = match index {
+            0 => OWNER_ACTION_VIA_PUBLISH,
+            _ => OWNER_ACTION_VIA_INVITATION,
+        };
+
+        diesel::sql_query(
+            r#"
+                INSERT INTO crate_owners (crate_id, owner_id, owner_kind, created_by, created_at)
+                VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind::<Integer, _>(crate_id)
+        .bind::<Integer, _>(*owner_id)
+        .bind::<Integer, _>(OWNER_KIND_USER)
+        .bind::<Integer, _>(publisher_id)
+        .bind::<Timestamp, _>(krate.created_at)
+        .execute(conn)?;
+
+        diesel::sql_query(
+            r#"
+                INSERT INTO crate_owner_actions (crate_id, owner_id, owner_kind, actor_id, action, via, time)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind::<Integer, _>(crate_id)
+        .bind::<Integer, _>(*owner_id)
+        .bind::<Integer, _>(OWNER_KIND_USER)
+        .bind::<Integer, _>(publisher_id)
+        .bind::<Integer, _>(OWNER_ACTION_ADD)
+        .bind::<Integer, _>(via)
+        .bind::<Timestamp, _>(krate.created_at)
+        .execute(conn)?;
+    }
+
+    for version in &krate.versions {
+        let Id { id: version_id } = diesel::sql_query(
+            r#"
+                INSERT INTO versions (crate_id, num, created_at, updated_at, downloads, features, license, crate_size, published_by, checksum)
+                VALUES ($1, $2, $3, $3, $4, '{}', $5, $6, $7, $8)
+                RETURNING id
+            "#,
+        )
+        .bind::<Integer, _>(crate_id)
+        .bind::<Text, _>(&version.num)
+        .bind::<Timestamp, _>(version.created_at)
+        .bind::<Integer, _>(version.total_downloads().min(i32::MAX as i64) as i32)
+        .bind::<Text, _>(&version.license)
+        .bind::<Integer, _>(version.crate_size)
+        .bind::<Integer, _>(publisher_id)
+        .bind::<Text, _>(&version.checksum)
+        .get_result(conn)?;
+
+        let (dates, counts): (Vec<NaiveDate>, Vec<i32>) = version.downloads.iter().copied().unzip();
+        diesel::sql_query(
+            r#"
+                INSERT INTO version_downloads (version_id, date, downloads, counted, processed)
+                SELECT $1, date, downloads, downloads, true
+                FROM unnest($2, $3) AS t(date, downloads)
+            "#,
+        )
+        .bind::<Integer, _>(version_id)
+        .bind::<Array<Date>, _>(dates)
+        .bind::<Array<Integer>, _>(counts)
+        .execute(conn)?;
+    }
+
+    // Inserting versions touches the crate, so the timestamp of the latest
+    // version has to be restored afterwards.
+    diesel::sql_query("UPDATE crates SET updated_at = $2 WHERE id = $1")
+        .bind::<Integer, _>(crate_id)
+        .bind::<Timestamp, _>(krate.updated_at())
+        .execute(conn)?;
+
+    diesel::sql_query("UPDATE crate_downloads SET downloads = $2 WHERE crate_id = $1")
+        .bind::<Integer, _>(crate_id)
+        .bind::<BigInt, _>(krate.total_downloads())
+        .execute(conn)?;
+
+    Ok(true)
+}
@@ -0,0 +1,311 @@
+//! Deterministic generation of the seed data.
+//!
+//! Every user and crate is generated from its own random number generator,
+//! which is seeded from the global seed and the index of the entity. The data
+//! of an entity therefore does not depend on any other entity, which allows
+//! the seeding to be resumed at any batch.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+
+/// The GitHub IDs of the generated users start at this offset, to avoid
+/// conflicts with real accounts.
+const GH_ID_OFFSET: i32 = 1_500_000_000;
+
+/// The maximum age of a generated crate.
+const MAX_CRATE_AGE_DAYS: i64 = 5 * 365;
+
+const ADJECTIVES: &[&str] = &[
+    "async", "blazing", "tiny", "safe", "fast", "lazy", "simple", "zero", "smart", "static",
+    "atomic", "fuzzy", "pure", "tokio", "serde", "embedded", "portable", "minimal",
+];
+
+const NOUNS: &[&str] = &[
+    "parser",
+    "cache",
+    "queue",
+    "logger",
+    "codec",
+    "buffer",
+    "client",
+    "server",
+    "macro",
+    "allocator",
+    "runtime",
+    "channel",
+    "config",
+    "hash",
+    "graph",
+    "tree",
+    "stream",
+    "shell",
+];
+
+const LICENSES: &[(&str, u32)] = &[
+    ("MIT OR Apache-2.0", 60),
+    ("MIT", 25),
+    ("Apache-2.0", 10),
+    ("BSD-3-Clause", 5),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Entity {
+    User = 1,
+    Crate = 2,
+}
+
+#[derive(Debug, Clone)]
+pub struct Generator {
+    pub seed: u64,
+    pub num_users: u32,
+    pub max_versions: u32,
+    pub download_days: u32,
+    pub end_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedUser {
+    pub gh_id: i32,
+    pub login: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedCrate {
+    pub name: String,
+    pub description: String,
+    pub created_at: NaiveDateTime,
+    /// Indexes of the users that own the crate. The first one published the
+    /// crate and all of its versions.
+    pub owners: Vec<u32>,
+    pub versions: Vec<SeedVersion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedVersion {
+    pub num: String,
+    pub license: String,
+    pub crate_size: i32,
+    pub checksum: String,
+    pub created_at: NaiveDateTime,
+    /// Number of downloads per day.
+    pub downloads: Vec<(NaiveDate, i32)>,
+}
+
+impl SeedCrate {
+    pub fn updated_at(&self) -> NaiveDateTime {
+        self.versions
+            .last()
+            .map(|version| version.created_at)
+            .unwrap_or(self.created_at)
+    }
+
+    pub fn total_downloads(&self) -> i64 {
+        self.versions.iter().map(SeedVersion::total_downloads).sum()
+    }
+}
+
+impl SeedVersion {
+    pub fn total_downloads(&self) -> i64 {
+        self.downloads.iter().map(|(_, count)| *count as i64).sum()
+    }
+}
+
+impl Generator {
+    fn rng(&self, entity: Entity, index: u32) -> StdRng {
+        let mut seed = [0; 32];
+        seed[..8].copy_from_slice(&self.seed.to_le_bytes());
+        seed[8..16].copy_from_slice(&(entity as u64).to_le_bytes());
+        seed[16..24].copy_from_slice(&u64::from(index).to_le_bytes());
+        StdRng::from_seed(seed)
+    }
+
+    pub fn user(&self, index: u32) -> SeedUser {
+        let mut rng = self.rng(Entity::User, index);
+
+        let adjective = ADJECTIVES.choose(&mut rng).unwrap();
+        let noun = NOUNS.choose(&mut rng).unwrap();
+
+        SeedUser {
+            gh_id: GH_ID_OFFSET + index as i32,
+            login: format!("{adjective}-{noun}-{index}"),
+            name: format!("Seed User {index}"),
+        }
+    }
+
+    pub fn krate(&self, index: u32) -> SeedCrate {
+        let mut rng = self.rng(Entity::Crate, index);
+
+        let adjective = ADJECTIVES.choose(&mut rng).unwrap();
+        let noun = NOUNS.choose(&mut rng).unwrap();
+        let name = format!("{adjective}-{noun}-{index}");
+        let description = format!("A {adjective} {noun}, generated for load tests");
+
+        let end = self.end_date.and_time(NaiveTime::MIN);
+        let age = Duration::seconds(rng.gen_range(0..MAX_CRATE_AGE_DAYS * 24 * 60 * 60));
+        let created_at = end - age;
+
+        // A few users own most of the crates.
+        let mut owners = vec![power_law(&mut rng, self.num_users, 3)];
+        if rng.gen_bool(0.1) {
+            let co_owner = rng.gen_range(0..self.num_users);
+            if !owners.contains(&co_owner) {
+                owners.push(co_owner);
+            }
+        }
+
+        // Most crates only have a handful of versions.
+        let num_versions = 1 + power_law(&mut rng, self.max_versions, 4);
+
+        // A few crates receive most of the downloads.
+        let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+        let total_downloads = (50.0 * u.powf(-1.5)).min(100_000_000.0) as i64;
+
+        // Newer versions receive more of the downloads.
+        let weights = (1..=num_versions as i64).map(|n| n * n);
+        let total_weight: i64 = weights.clone().sum();
+
+        let mut version = (0, 1, 0);
+        let versions = weights
+            .enumerate()
+            .map(|(n, weight)| {
+                if n > 0 {
+                    version = next_version(&mut rng, version);
+                }
+
+                let num = format!("{}.{}.{}", version.0, version.1, version.2);
+                let created_at = created_at + age * n as i32 / num_versions as i32;
+                let downloads = total_downloads * weight / total_weight;
+
+                SeedVersion {
+                    checksum: checksum(&name, &num),
+                    license: license(&mut rng).to_string(),
+                    crate_size: 2f64.powf(rng.gen_range(11.0..21.0)) as i32,
+                    downloads: self.daily_downloads(&mut rng, created_at.date(), downloads),
+                    created_at,
+                    num,
+                }
+            })
+            .collect();
+
+        SeedCrate {
+            name,
+            description,
+            created_at,
+            owners,
+            versions,
+        }
+    }
+
+    /// Distributes the downloads of a version over the days of the download
+    /// history that the version existed.
+    fn daily_downloads(
+        &self,
+        rng: &mut StdRng,
+        created_at: NaiveDate,
+        downloads: i64,
+    ) -> Vec<(NaiveDate, i32)> {
+        let first_day = self.end_date - Duration::days(self.download_days as i64 - 1);
+        let days = first_day
+            .max(created_at)
+            .iter_days()
+            .take_while(|day| *day <= self.end_date)
+            .collect::<Vec<_>>();
+
+        let weights = days
+            .iter()
+            .map(|_| rng.gen_range(0.5..1.5))
+            .collect::<Vec<f64>>();
+        let total_weight: f64 = weights.iter().sum();
+
+        days.into_iter()
+            .zip(weights)
+            .map(|(day, weight)| {
+                let count = downloads as f64 * weight / total_weight;
+                (day, count.min(i32::MAX as f64) as i32)
+            })
+            .collect()
+    }
+}
+
+/// Returns a random number in `0..max`, with smaller numbers being more
+/// likely the larger the `exponent` is.
+fn power_law(rng: &mut StdRng, max: u32, exponent: i32) -> u32 {
+    let u: f64 = rng.gen();
+    ((max as f64 * u.powi(exponent)) as u32).min(max.saturating_sub(1))
+}
+
+fn next_version(rng: &mut StdRng, (major, minor, patch): (u32, u32, u32)) -> (u32, u32, u32) {
+    match rng.gen_range(0..100) {
+        0..=4 => (major + 1, 0, 0),
+        5..=29 => (major, minor + 1, 0),
+        _ => (major, minor, patch + 1),
+    }
+}
+
+fn license(rng: &mut StdRng) -> &'static str {
+    let total: u32 = LICENSES.iter().map(|(_, weight)| weight).sum();
+    let mut n = rng.gen_range(0..total);
+    for (license, weight) in LICENSES {
+        if n < *weight {
+            return license;
+        }
+        n -= weight;
+    }
+    unreachable!()
+}
+
+/// Returns a fake but stable checksum of a version.
+fn checksum(name: &str, num: &str) -> String {
+    hex::encode(Sha256::digest(format!("{name}-{num}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(seed: u64) -> Generator {
+        Generator {
+            seed,
+            num_users: 100,
+            max_versions: 20,
+            download_days: 30,
+            end_date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        assert_eq!(generator(1).user(42), generator(1).user(42));
+        assert_eq!(generator(1).krate(42), generator(1).krate(42));
+        assert_ne!(generator(1).krate(42), generator(2).krate(42));
+        assert_ne!(generator(1).krate(42), generator(1).krate(43));
+    }
+
+    #[test]
+    fn crates_are_valid() {
+        let generator = generator(0);
+        for index in 0..500 {
+            let krate = generator.krate(index);
+            assert!(krate.name.ends_with(&format!("-{index}")));
+            assert!(krate.owners.iter().all(|owner| *owner < 100));
+            assert!(!krate.versions.is_empty() && krate.versions.len() <= 20);
+
+            let mut nums = krate.versions.iter().map(|v| &v.num).collect::<Vec<_>>();
+            nums.dedup();
+            assert_eq!(nums.len(), krate.versions.len());
+
+            for version in &krate.versions {
+                assert!(version.created_at >= krate.created_at);
+                assert!(version.downloads.len() <= 30);
+                assert!(version
+                    .downloads
+                    .iter()
+                    .all(|(day, _)| *day <= generator.end_date));
+            }
+        }
+    }
+}
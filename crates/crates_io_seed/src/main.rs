@@ -0,0 +1,150 @@
+mod db;
+mod generate;
+
+#[macro_use]
+extern crate tracing;
+
+use crate::generate::Generator;
+use anyhow::{anyhow, Context};
+use chrono::{NaiveDate, Utc};
+use clap::Parser;
+use diesel::{Connection, PgConnection};
+use std::time::Instant;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+#[derive(clap::Parser, Debug)]
+struct Options {
+    /// database that the data is inserted into
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true)]
+    database_url: String,
+
+    /// seed of the random number generator. Runs with the same seed and end
+    /// date generate the same data.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// number of crates to generate
+    #[arg(long, default_value_t = 10_000)]
+    crates: u32,
+
+    /// number of users to generate, who own the crates
+    #[arg(long, default_value_t = 1_000, value_parser = clap::value_parser!(u32).range(1..))]
+    users: u32,
+
+    /// maximum number of versions per crate
+    #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
+    max_versions: u32,
+
+    /// number of days of daily download counts per version
+    #[arg(long, default_value_t = 90)]
+    download_days: u32,
+
+    /// last day of the generated history. Defaults to today, which should be
+    /// overridden to resume a run on another day or to reproduce a run.
+    #[arg(long)]
+    end_date: Option<NaiveDate>,
+
+    /// number of crates that are inserted per transaction
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    batch_size: u32,
+}
+
+fn main() -> anyhow::Result<()> {
+    init_tracing();
+
+    let options = Options::parse();
+    debug!(?options);
+
+    let generator = Generator {
+        seed: options.seed,
+        num_users: options.users,
+        max_versions: options.max_versions,
+        download_days: options.download_days,
+        end_date: options.end_date.unwrap_or_else(|| Utc::now().date_naive()),
+    };
+    info!(seed = generator.seed, end_date = %generator.end_date, "Generating seed data…");
+
+    let mut conn = PgConnection::establish(&options.database_url)
+        .context("Failed to connect to the database")?;
+
+    info!("Inserting {} users…", options.users);
+    let users = (0..options.users)
+        .map(|index| generator.user(index))
+        .collect::<Vec<_>>();
+    let user_ids = db::insert_users(&mut conn, &users).context("Failed to insert users")?;
+    let user_ids = users
+        .iter()
+        .map(|user| {
+            user_ids
+                .get(&user.gh_id)
+                .copied()
+                .ok_or_else(|| anyhow!("User `{}` was not inserted", user.login))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let start = Instant::now();
+    let mut inserted = 0;
+    let mut skipped = 0;
+
+    let batches = (0..options.crates).step_by(options.batch_size as usize);
+    for batch_start in batches {
+        let batch = batch_start..(batch_start + options.batch_size).min(options.crates);
+
+        // Batches are inserted in a single transaction, so if the last crate
+        // of a batch exists, the whole batch was inserted by a previous run.
+        let last_crate = generator.krate(batch.end - 1);
+        if db::crate_exists(&mut conn, &last_crate.name)? {
+            debug!(?batch, "Skipping existing batch");
+            skipped += batch.len();
+            continue;
+        }
+
+        conn.transaction(|conn| {
+            for index in batch.clone() {
+                let krate = generator.krate(index);
+                let owner_ids = krate
+                    .owners
+                    .iter()
+                    .map(|owner| user_ids[*owner as usize])
+                    .collect::<Vec<_>>();
+
+                if db::insert_crate(conn, &krate, &owner_ids)? {
+                    inserted += 1;
+                } else {
+                    skipped += 1;
+                }
+            }
+
+            Ok::<_, diesel::result::Error>(())
+        })
+        .with_context(|| format!("Failed to insert crates {batch:?}"))?;
+
+        info!(
+            inserted,
+            skipped,
+            elapsed = ?start.elapsed(),
+            "Inserted crates {}/{}",
+            batch.end,
+            options.crates
+        );
+    }
+
+    info!(inserted, skipped, elapsed = ?start.elapsed(), "Seeding finished");
+
+    Ok(())
+}
+
+fn init_tracing() {
+    let env_filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy();
+
+    let log_layer = tracing_subscriber::fmt::layer()
+        .compact()
+        .with_filter(env_filter);
+
+    tracing_subscriber::registry().with(log_layer).init();
+}
@@ -1,60 +1,49 @@
 use bytes::Bytes;
+use crates_io_client::{CrateResponse, VersionResponse};
 use crates_io_index::Repository;
 use reqwest::Client;
 use std::fmt::Display;
 
+const USER_AGENT: &str = "crates.io smoke test";
+
 pub struct ApiClient {
     http_client: Client,
+    api_client: crates_io_client::Client,
 }
 
 impl ApiClient {
     pub fn new() -> anyhow::Result<Self> {
-        let http_client = Client::builder()
-            .user_agent("crates.io smoke test")
-            .build()?;
+        let http_client = Client::builder().user_agent(USER_AGENT).build()?;
 
-        Ok(Self { http_client })
-    }
+        let base_url = "https://staging.crates.io".parse()?;
+        let api_client = crates_io_client::Client::with_http_client(base_url, http_client.clone());
 
-    pub async fn load_crate<N: Display>(&self, name: N) -> anyhow::Result<CrateResponse> {
-        let url = format!(
-            "https://staging.crates.io/api/v1/crates/{}?include=versions",
-            name
-        );
+        Ok(Self {
+            http_client,
+            api_client,
+        })
+    }
 
-        let response = self.http_client.get(url).send().await?;
-        let response = response.error_for_status()?;
-        response.json().await.map_err(Into::into)
+    pub async fn load_crate(&self, name: &str) -> anyhow::Result<CrateResponse> {
+        Ok(self.api_client.krate(name).await?)
     }
 
-    pub async fn load_version<N: Display, V: Display>(
+    pub async fn load_version<V: Display>(
         &self,
-        name: N,
+        name: &str,
         version: V,
     ) -> anyhow::Result<VersionResponse> {
-        let url = format!(
-            "https://staging.crates.io/api/v1/crates/{}/{}",
-            name, version
-        );
-
-        let response = self.http_client.get(url).send().await?;
-        let response = response.error_for_status()?;
-        response.json().await.map_err(Into::into)
+        let version = version.to_string();
+        Ok(self.api_client.version(name, &version).await?)
     }
 
-    pub async fn download_crate_file_via_api<N: Display, V: Display>(
+    pub async fn download_crate_file_via_api<V: Display>(
         &self,
-        name: N,
+        name: &str,
         version: V,
     ) -> anyhow::Result<Bytes> {
-        let url = format!(
-            "https://staging.crates.io/api/v1/crates/{}/{}/download",
-            name, version
-        );
-
-        let response = self.http_client.get(url).send().await?;
-        let response = response.error_for_status()?;
-        response.bytes().await.map_err(Into::into)
+        let version = version.to_string();
+        Ok(self.api_client.download(name, &version).await?)
     }
 
     pub async fn download_crate_file_via_cdn<N: Display, V: Display>(
@@ -108,26 +97,3 @@ impl ApiClient {
             .collect()
     }
 }
-
-#[derive(Debug, serde::Deserialize)]
-pub struct CrateResponse {
-    #[serde(rename = "crate")]
-    pub krate: Crate,
-}
-
-#[derive(Debug, serde::Deserialize)]
-pub struct Crate {
-    pub max_version: semver::Version,
-}
-
-#[derive(Debug, serde::Deserialize)]
-pub struct VersionResponse {
-    pub version: Version,
-}
-
-#[derive(Debug, serde::Deserialize)]
-pub struct Version {
-    #[serde(rename = "crate")]
-    pub krate: String,
-    pub num: semver::Version,
-}
@@ -49,7 +49,10 @@ async fn main() -> anyhow::Result<()> {
         .context("Failed to load crate information from staging.crates.io")?
         .krate;
 
-    let old_version = krate.max_version;
+    let old_version: semver::Version = krate
+        .max_version
+        .parse()
+        .context("Failed to parse the highest version number of the crate")?;
     let mut new_version = old_version.clone();
 
     if options.skip_publish {
@@ -89,7 +92,7 @@ async fn main() -> anyhow::Result<()> {
         ));
     }
 
-    if json.version.num != version {
+    if json.version.num != version.to_string() {
         return Err(anyhow!(
             "API returned an unexpected version number; expected `{}` found `{}`",
             version,
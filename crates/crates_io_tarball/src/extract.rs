@@ -0,0 +1,139 @@
+use crate::limit_reader::LimitErrorReader;
+use flate2::read::GzDecoder;
+use std::io::Read;
+use std::path::Path;
+use std::time::Instant;
+use tracing::instrument;
+
+/// The limits that apply when a single file is extracted from a tarball.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractLimits {
+    /// The maximum number of bytes that are decompressed while looking for
+    /// the file.
+    pub max_unpack: u64,
+    /// The maximum size of the extracted file.
+    pub max_file_size: u64,
+    /// The point in time at which the extraction is aborted.
+    pub deadline: Instant,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    #[error("tarball is malformed or too large when decompressed")]
+    Malformed(#[source] std::io::Error),
+    #[error("file is larger than {max} bytes")]
+    FileTooLarge { max: u64 },
+    #[error("extracting the file took too long")]
+    TimedOut,
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+}
+
+/// Extracts the regular file at `path` (e.g. `foo-0.1.0/Cargo.toml`) from a
+/// `.crate` tarball, without unpacking any of the other files.
+///
+/// Returns `None` if the tarball does not contain such a file.
+#[instrument(skip(tarball))]
+pub fn extract_file<R: Read>(
+    tarball: R,
+    path: &Path,
+    limits: ExtractLimits,
+) -> Result<Option<Vec<u8>>, ExtractError> {
+    let decoder = GzDecoder::new(tarball);
+    let decoder = LimitErrorReader::new(decoder, limits.max_unpack);
+    let mut archive = tar::Archive::new(decoder);
+
+    for entry in archive.entries().map_err(ExtractError::Malformed)? {
+        // Skipping over the content of large entries is the expensive part,
+        // so the deadline is checked once per entry.
+        if Instant::now() >= limits.deadline {
+            return Err(ExtractError::TimedOut);
+        }
+
+        let entry = entry.map_err(ExtractError::Malformed)?;
+        if !entry.header().entry_type().is_file() || entry.path()? != path {
+            continue;
+        }
+
+        if entry.size() > limits.max_file_size {
+            return Err(ExtractError::FileTooLarge {
+                max: limits.max_file_size,
+            });
+        }
+
+        let mut content = Vec::with_capacity(entry.size() as usize);
+        entry
+            .take(limits.max_file_size)
+            .read_to_end(&mut content)
+            .map_err(ExtractError::Malformed)?;
+
+        return Ok(Some(content));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TarballBuilder;
+    use std::time::Duration;
+
+    fn limits() -> ExtractLimits {
+        ExtractLimits {
+            max_unpack: 512 * 1024 * 1024,
+            max_file_size: 16,
+            deadline: Instant::now() + Duration::from_secs(60),
+        }
+    }
+
+    fn tarball() -> Vec<u8> {
+        TarballBuilder::new()
+            .add_file("foo-0.0.1/Cargo.toml", b"[package]")
+            .add_file("foo-0.0.1/src/lib.rs", b"")
+            .add_file("foo-0.0.1/LICENSE", b"this license is too long")
+            .build()
+    }
+
+    #[test]
+    fn extract_existing_file() {
+        let path = Path::new("foo-0.0.1/Cargo.toml");
+        let content = assert_some!(assert_ok!(extract_file(&*tarball(), path, limits())));
+        assert_eq!(content, b"[package]");
+
+        let path = Path::new("foo-0.0.1/src/lib.rs");
+        let content = assert_some!(assert_ok!(extract_file(&*tarball(), path, limits())));
+        assert_eq!(content, b"");
+    }
+
+    #[test]
+    fn extract_missing_file() {
+        let path = Path::new("foo-0.0.1/README.md");
+        assert_none!(assert_ok!(extract_file(&*tarball(), path, limits())));
+
+        // Directories are not files
+        let path = Path::new("foo-0.0.1/src");
+        assert_none!(assert_ok!(extract_file(&*tarball(), path, limits())));
+    }
+
+    #[test]
+    fn extract_with_exceeded_limits() {
+        let path = Path::new("foo-0.0.1/LICENSE");
+        let error = assert_err!(extract_file(&*tarball(), path, limits()));
+        assert!(matches!(error, ExtractError::FileTooLarge { max: 16 }));
+
+        let small_unpack = ExtractLimits {
+            max_unpack: 100,
+            ..limits()
+        };
+        let error = assert_err!(extract_file(&*tarball(), path, small_unpack));
+        assert!(matches!(error, ExtractError::Malformed(_)));
+
+        let expired = ExtractLimits {
+            deadline: Instant::now(),
+            ..limits()
+        };
+        let error = assert_err!(extract_file(&*tarball(), path, expired));
+        assert!(matches!(error, ExtractError::TimedOut));
+    }
+}
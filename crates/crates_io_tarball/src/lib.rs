@@ -4,6 +4,7 @@ extern crate claims;
 
 #[cfg(any(feature = "builder", test))]
 pub use crate::builder::TarballBuilder;
+pub use crate::extract::{extract_file, ExtractError, ExtractLimits};
 use crate::limit_reader::LimitErrorReader;
 use crate::manifest::validate_manifest;
 pub use crate::vcs_info::CargoVcsInfo;
@@ -17,6 +18,7 @@ use tracing::instrument;
 
 #[cfg(any(feature = "builder", test))]
 mod builder;
+mod extract;
 mod limit_reader;
 mod manifest;
 mod vcs_info;
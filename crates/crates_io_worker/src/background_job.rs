@@ -1,4 +1,5 @@
 use crate::errors::EnqueueError;
+use crate::request_id::current_request_id;
 use crate::schema::background_jobs;
 use diesel::prelude::*;
 use diesel::PgConnection;
@@ -26,9 +27,39 @@ pub trait BackgroundJob: Serialize + DeserializeOwned + Send + Sync + 'static {
     /// The application data provided to this job at runtime.
     type Context: Clone + Send + 'static;
 
+    /// Returns the key of the lock that the job holds while it is running.
+    ///
+    /// Jobs with the same key never run at the same time, e.g. to keep jobs
+    /// that update the same file from interleaving. If another job with the
+    /// same key is running, the worker waits for it to finish before the job
+    /// is started. If that takes longer than the
+    /// [lock timeout](crate::Runner::lock_timeout), the job is put back into
+    /// the queue instead. Jobs without a key are not locked.
+    fn lock_key(&self) -> Option<String> {
+        None
+    }
+
     /// Execute the task. This method should define its logic.
     fn run(&self, ctx: Self::Context) -> impl Future<Output = anyhow::Result<()>> + Send;
 
+    /// Reports the progress of the running job, with `percent` between 0
+    /// and 100 and a `message` that describes the current step.
+    ///
+    /// Long-running jobs can call this to let operators know how far they
+    /// have progressed. The latest report is stored in the
+    /// `background_job_progress` table until the job has finished
+    /// successfully. Writes are throttled, so it is fine to report frequently.
+    ///
+    /// This has no effect outside of a running job.
+    fn report_progress(percent: u8, message: impl Into<String>) {
+        crate::progress::report_progress(percent, message.into())
+    }
+
+    /// Adds the job to the queue with the default [Self::PRIORITY].
+    ///
+    /// If the job is enqueued while handling an HTTP request, the request id
+    /// (see [`crate::set_request_id()`]) is stored together with the job, so
+    /// that the job logs and Sentry events can be correlated with the request.
     fn enqueue(&self, conn: &mut PgConnection) -> Result<i64, EnqueueError> {
         self.enqueue_with_priority(conn, Self::PRIORITY)
     }
@@ -45,6 +76,7 @@ pub trait BackgroundJob: Serialize + DeserializeOwned + Send + Sync + 'static {
                 background_jobs::job_type.eq(Self::JOB_NAME),
                 background_jobs::data.eq(job_data),
                 background_jobs::priority.eq(job_priority),
+                background_jobs::request_id.eq(current_request_id()),
             ))
             .returning(background_jobs::id)
             .get_result(conn)?;
@@ -6,16 +6,19 @@ use std::sync::Arc;
 
 type RunTaskFnReturn = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
 type RunTaskFn<Context> = dyn Fn(Context, serde_json::Value) -> RunTaskFnReturn + Send + Sync;
+type LockKeyFn = fn(&serde_json::Value) -> anyhow::Result<Option<String>>;
 
 #[derive(Clone)]
 pub struct JobRegistry<Context> {
     entries: HashMap<String, Arc<RunTaskFn<Context>>>,
+    lock_keys: HashMap<String, LockKeyFn>,
 }
 
 impl<Context> Default for JobRegistry<Context> {
     fn default() -> Self {
         Self {
             entries: HashMap::new(),
+            lock_keys: HashMap::new(),
         }
     }
 }
@@ -24,12 +27,27 @@ impl<Context: Clone + Send + Sync + 'static> JobRegistry<Context> {
     pub fn register<J: BackgroundJob<Context = Context>>(&mut self) {
         self.entries
             .insert(J::JOB_NAME.to_string(), Arc::new(runnable::<J>));
+        self.lock_keys
+            .insert(J::JOB_NAME.to_string(), lock_key::<J>);
     }
 
     pub fn get(&self, key: &str) -> Option<&Arc<RunTaskFn<Context>>> {
         self.entries.get(key)
     }
 
+    /// Returns the [`BackgroundJob::lock_key()`] of the job with the given
+    /// type and payload.
+    pub fn lock_key(
+        &self,
+        key: &str,
+        payload: &serde_json::Value,
+    ) -> anyhow::Result<Option<String>> {
+        match self.lock_keys.get(key) {
+            Some(lock_key) => lock_key(payload),
+            None => Ok(None),
+        }
+    }
+
     /// Returns a list of all registered job types.
     pub fn job_types(&self) -> Vec<String> {
         self.entries.keys().cloned().collect()
@@ -43,6 +61,11 @@ fn runnable<J: BackgroundJob>(ctx: J::Context, payload: serde_json::Value) -> Ru
     })
 }
 
+fn lock_key<J: BackgroundJob>(payload: &serde_json::Value) -> anyhow::Result<Option<String>> {
+    let job: J = serde_json::from_value(payload.clone())?;
+    Ok(job.lock_key())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,191 @@
+//! Leases of running jobs.
+//!
+//! A job is protected from being run twice by the row lock that the
+//! transaction of its worker holds. If the worker crashes or hangs without
+//! its database session being closed, that lock is never released and the
+//! job is stuck until someone terminates the session by hand.
+//!
+//! To detect this, every running job holds a lease in the
+//! `background_job_leases` table, which a task of the worker extends in
+//! regular intervals. Like the progress of a job, the lease is written with
+//! its own database connection, since anything written within the
+//! transaction of the job would only become visible once the job has
+//! finished. The reaper in [`crate::reaper`] recovers the jobs whose lease
+//! has expired.
+
+use crate::schema::background_job_leases;
+use anyhow::anyhow;
+use deadpool_diesel::postgres::Pool;
+use diesel::dsl::now;
+use diesel::pg::data_types::PgInterval;
+use diesel::prelude::*;
+use diesel::sql_types::Interval;
+use diesel::upsert::excluded;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+use tracing::{warn, Instrument};
+
+/// Default duration of a lease, after which a job whose worker stopped
+/// sending heartbeats is considered stuck.
+pub(crate) const DEFAULT_LEASE_DURATION: Duration = Duration::from_secs(120);
+
+/// Leases are best effort, so a write is given up if no database connection
+/// becomes available within this time.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Returns the process ID of the database session of the connection.
+pub(crate) fn backend_pid(conn: &mut PgConnection) -> QueryResult<i32> {
+    sql_function!(fn pg_backend_pid() -> Integer);
+
+    diesel::select(pg_backend_pid()).get_result(conn)
+}
+
+/// The lease of a running job.
+pub(crate) struct JobLease {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl JobLease {
+    /// Spawns the task that acquires the lease and extends it until the job
+    /// has finished.
+    ///
+    /// `backend_pid` is the process ID of the database session that locks the
+    /// row of the job.
+    pub(crate) fn start(
+        pool: Pool,
+        job_id: i64,
+        worker: String,
+        backend_pid: i32,
+        duration: Duration,
+    ) -> Self {
+        let (stop, stopped) = oneshot::channel();
+        let lease = Lease {
+            pool,
+            job_id,
+            worker,
+            backend_pid,
+            duration,
+        };
+
+        let task = Handle::current().spawn(lease.hold(stopped).in_current_span());
+
+        Self { stop, task }
+    }
+
+    /// Stops extending the lease and releases it.
+    pub(crate) async fn finish(self) {
+        let _ = self.stop.send(());
+        let _ = self.task.await;
+    }
+}
+
+#[derive(Clone)]
+struct Lease {
+    pool: Pool,
+    job_id: i64,
+    worker: String,
+    backend_pid: i32,
+    duration: Duration,
+}
+
+impl Lease {
+    /// Sends a heartbeat four times per lease duration, so that a few
+    /// failed writes don't let the lease expire.
+    async fn hold(self, mut stopped: oneshot::Receiver<()>) {
+        let heartbeat_interval = self.duration / 4;
+
+        self.write(Self::acquire).await;
+        loop {
+            tokio::select! {
+                _ = sleep(heartbeat_interval) => self.write(Self::extend).await,
+                _ = &mut stopped => break,
+            }
+        }
+        self.write(Self::release).await;
+    }
+
+    async fn write(&self, f: fn(&Self, &mut PgConnection) -> QueryResult<()>) {
+        let lease = self.clone();
+        let write = async move {
+            let conn = lease.pool.get().await?;
+            conn.interact(move |conn| f(&lease, conn))
+                .await
+                .map_err(|err| anyhow!(err.to_string()))??;
+
+            Ok::<_, anyhow::Error>(())
+        };
+
+        match timeout(WRITE_TIMEOUT, write).await {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => warn!("Failed to save job lease: {error:#}"),
+            Err(_) => warn!("Timed out while saving job lease"),
+        }
+    }
+
+    fn interval(&self) -> PgInterval {
+        let micros = self.duration.as_micros().try_into().unwrap_or(i64::MAX);
+        PgInterval::from_microseconds(micros)
+    }
+
+    /// Inserts the lease, replacing the expired lease of a previous run of
+    /// the job that was not reaped yet.
+    fn acquire(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        let expires_at = now + self.interval().into_sql::<Interval>();
+
+        diesel::insert_into(background_job_leases::table)
+            .values((
+                background_job_leases::job_id.eq(self.job_id),
+                background_job_leases::worker.eq(&self.worker),
+                background_job_leases::backend_pid.eq(self.backend_pid),
+                background_job_leases::expires_at.eq(expires_at),
+            ))
+            .on_conflict(background_job_leases::job_id)
+            .do_update()
+            .set((
+                background_job_leases::worker.eq(excluded(background_job_leases::worker)),
+                background_job_leases::backend_pid.eq(excluded(background_job_leases::backend_pid)),
+                background_job_leases::started_at.eq(now),
+                background_job_leases::heartbeat_at.eq(now),
+                background_job_leases::expires_at.eq(excluded(background_job_leases::expires_at)),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    fn extend(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        let expires_at = now + self.interval().into_sql::<Interval>();
+
+        // The lease is only extended as long as it belongs to this run of
+        // the job
+        let lease = background_job_leases::table
+            .find(self.job_id)
+            .filter(background_job_leases::backend_pid.eq(self.backend_pid));
+
+        let updated = diesel::update(lease)
+            .set((
+                background_job_leases::heartbeat_at.eq(now),
+                background_job_leases::expires_at.eq(expires_at),
+            ))
+            .execute(conn)?;
+
+        if updated == 0 {
+            warn!("Job lease was lost, the job might have been recovered by the reaper");
+        }
+
+        Ok(())
+    }
+
+    fn release(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        let lease = background_job_leases::table
+            .find(self.job_id)
+            .filter(background_job_leases::backend_pid.eq(self.backend_pid));
+
+        diesel::delete(lease).execute(conn)?;
+        Ok(())
+    }
+}
@@ -1,12 +1,22 @@
 mod background_job;
 mod errors;
 mod job_registry;
+mod lease;
+mod lock;
+mod progress;
+mod reaper;
+mod request_id;
 mod runner;
 pub mod schema;
+mod shutdown;
 mod storage;
 mod util;
 mod worker;
 
 pub use self::background_job::BackgroundJob;
 pub use self::errors::EnqueueError;
-pub use self::runner::Runner;
+pub use self::reaper::{RecoveredJob, Recovery};
+pub use self::request_id::{current_request_id, set_request_id};
+pub use self::runner::{RunHandle, Runner};
+pub use self::shutdown::ShutdownSignal;
+pub use self::worker::RunningJob;
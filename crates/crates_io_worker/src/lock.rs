@@ -0,0 +1,138 @@
+//! Advisory locks of jobs with a [`BackgroundJob::lock_key()`].
+//!
+//! Before such a job is run, its worker takes a transaction-level advisory
+//! lock on the key, within the transaction that locks the row of the job. If
+//! another job with the same key is running, the worker waits for it to
+//! finish. The lock is released together with the row lock, i.e. when the
+//! job has finished or the reaper terminated the session of a stuck worker.
+//!
+//! The wait is limited by a `lock_timeout`, so that a long-running job can't
+//! block the workers of other jobs with the same key indefinitely. If the
+//! timeout is hit, the waiting job is re-queued and retried later, without
+//! counting as a failure.
+//!
+//! How often and how long workers had to wait is recorded per job type in
+//! the `background_job_lock_waits` table. Like the progress of a job, this is
+//! written with its own database connection.
+//!
+//! [`BackgroundJob::lock_key()`]: crate::BackgroundJob::lock_key
+
+use crate::schema::background_job_lock_waits;
+use anyhow::anyhow;
+use deadpool_diesel::postgres::Pool;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::{BigInt, Bool, Text};
+use diesel::upsert::excluded;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+/// The default of the time that a job waits for another job with the same
+/// lock key to finish, before it is re-queued.
+pub(crate) const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Lock waits are best effort, so a write is given up if no database
+/// connection becomes available within this time.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+sql_function!(fn hashtextextended(key: Text, seed: BigInt) -> BigInt);
+sql_function!(fn pg_try_advisory_xact_lock(key: BigInt) -> Bool);
+sql_function!(fn current_setting(name: Text) -> Text);
+sql_function!(fn set_config(name: Text, value: Text, is_local: Bool) -> Text);
+
+/// The outcome of [`acquire()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lock {
+    /// The lock was taken, after waiting for the given time if it was held
+    /// by another transaction.
+    Acquired(Option<Duration>),
+    /// Another transaction held the lock for longer than the lock timeout.
+    TimedOut,
+}
+
+/// Takes the advisory lock on `key` for the current transaction, waiting up
+/// to `lock_timeout` for other transactions that hold it.
+pub(crate) fn acquire(
+    conn: &mut PgConnection,
+    key: &str,
+    lock_timeout: Duration,
+) -> QueryResult<Lock> {
+    let lock_id = hashtextextended(key, 0);
+    if diesel::select(pg_try_advisory_xact_lock(lock_id)).get_result::<bool>(conn)? {
+        return Ok(Lock::Acquired(None));
+    }
+
+    debug!(
+        lock_key = key,
+        "Waiting for another job with the same lock key…"
+    );
+    let started_at = Instant::now();
+
+    // The savepoint keeps the transaction of the job usable if the lock
+    // timeout is hit. Otherwise, the advisory lock is passed on to the
+    // transaction of the job when the savepoint is released.
+    let result: QueryResult<()> = conn.transaction(|conn| {
+        let previous_timeout: String =
+            diesel::select(current_setting("lock_timeout")).get_result(conn)?;
+        let timeout = format!("{}ms", lock_timeout.as_millis());
+        diesel::select(set_config("lock_timeout", timeout, true)).execute(conn)?;
+
+        diesel::sql_query("SELECT pg_advisory_xact_lock(hashtextextended($1, 0))")
+            .bind::<Text, _>(key)
+            .execute(conn)?;
+
+        diesel::select(set_config("lock_timeout", previous_timeout, true)).execute(conn)?;
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => Ok(Lock::Acquired(Some(started_at.elapsed()))),
+        Err(Error::DatabaseError(_, info)) if info.message().contains("lock timeout") => {
+            Ok(Lock::TimedOut)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Records that a job of the given type acquired its lock after `waited`.
+pub(crate) async fn record(pool: &Pool, job_type: &str, waited: Option<Duration>) {
+    let result = timeout(WRITE_TIMEOUT, save(pool, job_type.to_string(), waited)).await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => warn!("Failed to record job lock wait: {error:#}"),
+        Err(_) => warn!("Timed out while recording job lock wait"),
+    }
+}
+
+async fn save(pool: &Pool, job_type: String, waited: Option<Duration>) -> anyhow::Result<()> {
+    let contentions = i64::from(waited.is_some());
+    let wait_seconds = waited.unwrap_or_default().as_secs_f64();
+
+    let conn = pool.get().await?;
+    conn.interact(move |conn| {
+        use background_job_lock_waits as waits;
+
+        diesel::insert_into(waits::table)
+            .values((
+                waits::job_type.eq(job_type),
+                waits::acquisitions.eq(1),
+                waits::contentions.eq(contentions),
+                waits::wait_seconds.eq(wait_seconds),
+            ))
+            .on_conflict(waits::job_type)
+            .do_update()
+            .set((
+                waits::acquisitions.eq(waits::acquisitions + 1),
+                waits::contentions.eq(waits::contentions + excluded(waits::contentions)),
+                waits::wait_seconds.eq(waits::wait_seconds + excluded(waits::wait_seconds)),
+                waits::updated_at.eq(now),
+            ))
+            .execute(conn)
+    })
+    .await
+    .map_err(|err| anyhow!(err.to_string()))??;
+
+    Ok(())
+}
@@ -0,0 +1,162 @@
+//! Progress reporting of long-running jobs.
+//!
+//! Jobs report their progress with [`BackgroundJob::report_progress()`],
+//! which hands the report to a task of the worker that persists it in the
+//! `background_job_progress` table. The task uses its own database
+//! connection, since the job itself runs within the transaction that locks
+//! its row, and anything written there would only become visible once the
+//! job has finished.
+//!
+//! Like the request id, the reporter is stored in the extensions of the
+//! `tracing` span of the job, which makes it available wherever that span
+//! is propagated to, including blocking threads.
+//!
+//! [`BackgroundJob::report_progress()`]: crate::BackgroundJob::report_progress
+
+use crate::schema::background_job_progress;
+use anyhow::anyhow;
+use deadpool_diesel::postgres::Pool;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout, Instant};
+use tracing::{warn, Span};
+use tracing_subscriber::registry::{LookupSpan, Registry};
+
+/// Minimum time between two writes of the progress of a job.
+const MIN_WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Progress is best effort, so a write is given up if no database
+/// connection becomes available within this time.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+struct Progress {
+    percent: i16,
+    message: String,
+}
+
+type Sender = watch::Sender<Option<Progress>>;
+
+#[derive(Clone, Debug)]
+struct ProgressReporter(Weak<Sender>);
+
+/// The progress reporting of a running job.
+pub(crate) struct JobProgress {
+    sender: Arc<Sender>,
+    task: JoinHandle<()>,
+}
+
+impl JobProgress {
+    /// Attaches a progress reporter to the span of the job and spawns the
+    /// task that persists the reports.
+    pub(crate) fn start(span: &Span, pool: Pool, job_id: i64) -> Self {
+        let (sender, receiver) = watch::channel(None);
+        let sender = Arc::new(sender);
+
+        let reporter = ProgressReporter(Arc::downgrade(&sender));
+        span.with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<Registry>()?;
+            let span = registry.span(id)?;
+            span.extensions_mut().insert(reporter);
+            Some(())
+        });
+
+        let task = Handle::current().spawn(persist_progress(pool, job_id, receiver));
+
+        Self { sender, task }
+    }
+
+    /// Waits until the last report of the job has been persisted.
+    pub(crate) async fn finish(self) {
+        drop(self.sender);
+        let _ = self.task.await;
+    }
+}
+
+/// Reports the progress of the job that is running in the current span.
+pub(crate) fn report_progress(percent: u8, message: String) {
+    let Some(sender) = current_sender() else {
+        return;
+    };
+
+    let percent = percent.min(100).into();
+    sender.send_replace(Some(Progress { percent, message }));
+}
+
+fn current_sender() -> Option<Arc<Sender>> {
+    Span::current()
+        .with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<Registry>()?;
+            let span = registry.span(id)?;
+            span.scope().find_map(|span| {
+                let extensions = span.extensions();
+                extensions.get::<ProgressReporter>()?.0.upgrade()
+            })
+        })
+        .flatten()
+}
+
+/// Persists the latest report until the job has finished, writing at most
+/// once per [`MIN_WRITE_INTERVAL`].
+async fn persist_progress(
+    pool: Pool,
+    job_id: i64,
+    mut receiver: watch::Receiver<Option<Progress>>,
+) {
+    let mut last_write = None;
+    while receiver.changed().await.is_ok() {
+        if let Some(last_write) = last_write {
+            sleep_until_write_allowed(last_write).await;
+        }
+
+        let Some(progress) = receiver.borrow_and_update().clone() else {
+            continue;
+        };
+
+        last_write = Some(Instant::now());
+        let result = timeout(WRITE_TIMEOUT, save_progress(&pool, job_id, progress)).await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => warn!("Failed to save job progress: {error:#}"),
+            Err(_) => warn!("Timed out while saving job progress"),
+        }
+    }
+}
+
+async fn sleep_until_write_allowed(last_write: Instant) {
+    let elapsed = last_write.elapsed();
+    if elapsed < MIN_WRITE_INTERVAL {
+        sleep(MIN_WRITE_INTERVAL - elapsed).await;
+    }
+}
+
+async fn save_progress(pool: &Pool, job_id: i64, progress: Progress) -> anyhow::Result<()> {
+    let conn = pool.get().await?;
+    conn.interact(move |conn| {
+        diesel::insert_into(background_job_progress::table)
+            .values((
+                background_job_progress::job_id.eq(job_id),
+                background_job_progress::percent.eq(progress.percent),
+                background_job_progress::message.eq(progress.message),
+                background_job_progress::updated_at.eq(now),
+            ))
+            .on_conflict(background_job_progress::job_id)
+            .do_update()
+            .set((
+                background_job_progress::percent.eq(excluded(background_job_progress::percent)),
+                background_job_progress::message.eq(excluded(background_job_progress::message)),
+                background_job_progress::updated_at.eq(now),
+            ))
+            .execute(conn)
+    })
+    .await
+    .map_err(|err| anyhow!(err.to_string()))??;
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+//! Recovery of jobs whose lease has expired.
+//!
+//! The reaper terminates the database session of the worker that held the
+//! expired lease, which rolls back its transaction and releases the lock on
+//! the job row. The first time this happens to a job, it is released for an
+//! immediate retry, since the worker might have crashed for unrelated
+//! reasons, like a deployment. If the lease of the job had already expired
+//! before, the job is marked as failed instead, so that the usual backoff
+//! applies and a job that crashes its worker doesn't take down one worker
+//! after the other.
+//!
+//! Every recovered job is recorded in the `background_job_incidents` table.
+
+use crate::schema::{background_job_incidents, background_job_leases, background_jobs};
+use crate::shutdown::ShutdownSignal;
+use crate::storage;
+use anyhow::anyhow;
+use deadpool_diesel::postgres::Pool;
+use diesel::dsl::{count_star, now};
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+/// How a job whose lease expired was recovered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// The job was released for an immediate retry.
+    Retried,
+    /// The job was marked as failed, because its lease had expired before.
+    Failed,
+}
+
+impl Recovery {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Retried => "retried",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// A job that was recovered after the lease of its worker expired.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveredJob {
+    pub id: i64,
+    pub job_type: String,
+    pub worker: String,
+    pub recovery: Recovery,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = background_job_leases, check_for_backend(diesel::pg::Pg))]
+struct ExpiredLease {
+    job_id: i64,
+    worker: String,
+}
+
+/// Looks for expired leases in regular intervals until a shutdown is
+/// requested.
+pub(crate) async fn run(pool: Pool, interval: Duration, mut shutdown: ShutdownSignal) {
+    loop {
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = shutdown.requested() => break,
+        }
+
+        debug!("Looking for background jobs with expired leases…");
+        if let Err(error) = reap(&pool).await {
+            warn!("Failed to recover background jobs with expired leases: {error:#}");
+        }
+    }
+}
+
+pub(crate) async fn reap(pool: &Pool) -> anyhow::Result<Vec<RecoveredJob>> {
+    let conn = pool.get().await?;
+    let recovered = conn
+        .interact(reap_expired_leases)
+        .await
+        .map_err(|err| anyhow!(err.to_string()))??;
+
+    Ok(recovered)
+}
+
+/// Recovers all jobs whose lease has expired.
+///
+/// Jobs whose row is still locked after the session of their worker was
+/// terminated are skipped, and recovered by the next call.
+fn reap_expired_leases(conn: &mut PgConnection) -> QueryResult<Vec<RecoveredJob>> {
+    conn.transaction(|conn| {
+        // Don't wait for long for terminated sessions to release their locks
+        diesel::sql_query("SET LOCAL lock_timeout = '5s'").execute(conn)?;
+
+        let leases: Vec<ExpiredLease> = background_job_leases::table
+            .filter(background_job_leases::expires_at.lt(now))
+            .select(ExpiredLease::as_select())
+            .order(background_job_leases::job_id)
+            .for_update()
+            .skip_locked()
+            .load(conn)?;
+
+        let mut recovered = Vec::new();
+        for lease in leases {
+            if let Some(job) = recover_job(conn, lease)? {
+                warn!(
+                    job.id,
+                    job.typ = %job.job_type,
+                    worker.name = %job.worker,
+                    recovery = job.recovery.as_str(),
+                    "Recovered background job with expired lease"
+                );
+                recovered.push(job);
+            }
+        }
+
+        Ok(recovered)
+    })
+}
+
+fn recover_job(conn: &mut PgConnection, lease: ExpiredLease) -> QueryResult<Option<RecoveredJob>> {
+    terminate_backend(conn, &lease)?;
+
+    // The lock on the job row is released once the terminated session has
+    // exited. The savepoint allows to continue with the other leases if that
+    // takes longer than the lock timeout.
+    let job_type = conn.transaction(|conn| {
+        background_jobs::table
+            .find(lease.job_id)
+            .select(background_jobs::job_type)
+            .for_update()
+            .get_result::<String>(conn)
+            .optional()
+    });
+
+    let job_type = match job_type {
+        Ok(Some(job_type)) => job_type,
+        Ok(None) => {
+            // The job has finished, but its worker failed to release the lease
+            delete_lease(conn, lease.job_id)?;
+            return Ok(None);
+        }
+        Err(error) => {
+            warn!(
+                job.id = lease.job_id,
+                %error,
+                "Failed to lock background job with expired lease"
+            );
+            return Ok(None);
+        }
+    };
+
+    let previous_incidents: i64 = background_job_incidents::table
+        .filter(background_job_incidents::job_id.eq(lease.job_id))
+        .select(count_star())
+        .get_result(conn)?;
+
+    let recovery = if previous_incidents == 0 {
+        Recovery::Retried
+    } else {
+        storage::update_failed_job(conn, lease.job_id);
+        Recovery::Failed
+    };
+
+    diesel::insert_into(background_job_incidents::table)
+        .values((
+            background_job_incidents::job_id.eq(lease.job_id),
+            background_job_incidents::job_type.eq(&job_type),
+            background_job_incidents::worker.eq(&lease.worker),
+            background_job_incidents::action.eq(recovery.as_str()),
+        ))
+        .execute(conn)?;
+
+    delete_lease(conn, lease.job_id)?;
+
+    Ok(Some(RecoveredJob {
+        id: lease.job_id,
+        job_type,
+        worker: lease.worker,
+        recovery,
+    }))
+}
+
+/// Terminates the database session that held the lease, if it still
+/// exists. Sessions that were started after the lease are not terminated,
+/// since their process ID was only reused.
+fn terminate_backend(conn: &mut PgConnection, lease: &ExpiredLease) -> QueryResult<()> {
+    diesel::sql_query(
+        "SELECT pg_terminate_backend(activity.pid) \
+        FROM pg_stat_activity activity \
+        JOIN background_job_leases lease ON lease.backend_pid = activity.pid \
+        WHERE lease.job_id = $1 \
+        AND activity.backend_start <= lease.started_at \
+        AND activity.pid <> pg_backend_pid()",
+    )
+    .bind::<BigInt, _>(lease.job_id)
+    .execute(conn)?;
+
+    Ok(())
+}
+
+fn delete_lease(conn: &mut PgConnection, job_id: i64) -> QueryResult<()> {
+    diesel::delete(background_job_leases::table.find(job_id)).execute(conn)?;
+    Ok(())
+}
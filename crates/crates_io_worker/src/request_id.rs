@@ -0,0 +1,40 @@
+//! Correlation of background jobs with the HTTP requests that enqueued them.
+//!
+//! The request id is stored in the extensions of a `tracing` span, which
+//! makes it available wherever that span is propagated to. This includes the
+//! blocking threads of `interact()` calls on database connections, where most
+//! jobs are enqueued.
+
+use tracing::Span;
+use tracing_subscriber::registry::{LookupSpan, Registry};
+
+#[derive(Clone, Debug)]
+struct RequestId(String);
+
+/// Attaches the request id to the span, so that jobs that are enqueued
+/// within it (or any of its child spans) are associated with the request.
+///
+/// This only has an effect if the span is enabled and the global subscriber
+/// is based on the [`Registry`].
+pub fn set_request_id(span: &Span, request_id: &str) {
+    span.with_subscriber(|(id, dispatch)| {
+        let registry = dispatch.downcast_ref::<Registry>()?;
+        let span = registry.span(id)?;
+        span.extensions_mut()
+            .insert(RequestId(request_id.to_string()));
+        Some(())
+    });
+}
+
+/// Returns the request id that was attached to the current span or one of
+/// its parents by [`set_request_id()`].
+pub fn current_request_id() -> Option<String> {
+    Span::current()
+        .with_subscriber(|(id, dispatch)| {
+            let registry = dispatch.downcast_ref::<Registry>()?;
+            let span = registry.span(id)?;
+            span.scope()
+                .find_map(|span| span.extensions().get::<RequestId>().map(|id| id.0.clone()))
+        })
+        .flatten()
+}
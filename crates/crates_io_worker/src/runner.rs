@@ -1,13 +1,18 @@
 use crate::background_job::DEFAULT_QUEUE;
 use crate::job_registry::JobRegistry;
-use crate::worker::Worker;
+use crate::lease::DEFAULT_LEASE_DURATION;
+use crate::lock::DEFAULT_LOCK_TIMEOUT;
+use crate::reaper::{self, RecoveredJob};
+use crate::shutdown::ShutdownSignal;
+use crate::worker::{RunningJob, Worker};
 use crate::{storage, BackgroundJob};
 use anyhow::anyhow;
 use deadpool_diesel::postgres::Pool;
 use futures_util::future::join_all;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use tracing::{info, info_span, warn, Instrument};
 
@@ -19,6 +24,8 @@ pub struct Runner<Context> {
     queues: HashMap<String, Queue<Context>>,
     context: Context,
     shutdown_when_queue_empty: bool,
+    lease_duration: Duration,
+    lock_timeout: Duration,
 }
 
 impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
@@ -28,6 +35,8 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
             queues: HashMap::new(),
             context,
             shutdown_when_queue_empty: false,
+            lease_duration: DEFAULT_LEASE_DURATION,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
         }
     }
 
@@ -62,24 +71,53 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
         self
     }
 
+    /// Set the duration of the leases of running jobs.
+    ///
+    /// Workers extend the leases of their jobs four times per lease duration.
+    /// If a worker crashes or hangs, the lease of its job expires and the job
+    /// is recovered by the reaper, which checks for expired leases equally
+    /// often.
+    pub fn lease_duration(mut self, lease_duration: Duration) -> Self {
+        self.lease_duration = lease_duration;
+        self
+    }
+
+    /// Set how long a job waits for another job with the same
+    /// [`BackgroundJob::lock_key()`] to finish, before it is put back into the
+    /// queue and retried later.
+    pub fn lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
     /// Start the background workers.
     ///
     /// This returns a `RunningRunner` which can be used to wait for the workers to shutdown.
     pub fn start(&self) -> RunHandle {
+        let (shutdown_sender, shutdown) = ShutdownSignal::new();
+
         let mut handles = Vec::new();
+        let mut current_jobs = Vec::new();
         for (queue_name, queue) in &self.queues {
             for i in 1..=queue.num_workers {
                 let name = format!("background-worker-{queue_name}-{i}");
                 info!(worker.name = %name, "Starting worker…");
 
                 let worker = Worker {
+                    name: name.clone(),
                     connection_pool: self.connection_pool.clone(),
                     context: self.context.clone(),
                     job_registry: Arc::new(queue.job_registry.clone()),
                     shutdown_when_queue_empty: self.shutdown_when_queue_empty,
                     poll_interval: queue.poll_interval,
+                    lease_duration: self.lease_duration,
+                    lock_timeout: self.lock_timeout,
+                    shutdown: shutdown.clone(),
+                    current_job: Arc::new(Mutex::new(None)),
                 };
 
+                current_jobs.push(worker.current_job.clone());
+
                 let span = info_span!("worker", worker.name = %name);
                 let handle = tokio::spawn(async move { worker.run().instrument(span).await });
 
@@ -87,7 +125,30 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
             }
         }
 
-        RunHandle { handles }
+        // A runner that shuts down once the queue is empty doesn't live long
+        // enough for the leases to expire
+        let reaper = (!self.shutdown_when_queue_empty).then(|| {
+            let pool = self.connection_pool.clone();
+            let interval = self.lease_duration / 4;
+            let span = info_span!("reaper");
+            tokio::spawn(reaper::run(pool, interval, shutdown).instrument(span))
+        });
+
+        RunHandle {
+            handles,
+            current_jobs,
+            reaper,
+            shutdown_sender,
+        }
+    }
+
+    /// Recovers the jobs whose lease has expired, because their worker
+    /// crashed or hangs.
+    ///
+    /// This happens automatically in the background while the runner is
+    /// running, unless it shuts down when the queue is empty.
+    pub async fn reap_expired_leases(&self) -> anyhow::Result<Vec<RecoveredJob>> {
+        reaper::reap(&self.connection_pool).await
     }
 
     /// Check if any jobs in the queue have failed.
@@ -111,6 +172,9 @@ impl<Context: Clone + Send + Sync + 'static> Runner<Context> {
 
 pub struct RunHandle {
     handles: Vec<JoinHandle<()>>,
+    current_jobs: Vec<Arc<Mutex<Option<RunningJob>>>>,
+    reaper: Option<JoinHandle<()>>,
+    shutdown_sender: watch::Sender<bool>,
 }
 
 impl RunHandle {
@@ -121,6 +185,49 @@ impl RunHandle {
                 warn!(%error, "Background worker task panicked");
             }
         });
+
+        if let Some(reaper) = self.reaper {
+            reaper.abort();
+        }
+    }
+
+    /// Asks all background workers to shut down after their current job and
+    /// waits up to `timeout` for them to do so.
+    ///
+    /// Jobs can use the [`ShutdownSignal`] to stop early. Returns the jobs
+    /// that were still running after the timeout. Their database
+    /// transactions are rolled back once the process exits, so they will be
+    /// retried by the next runner.
+    pub async fn shutdown(mut self, timeout: Duration) -> Vec<RunningJob> {
+        self.shutdown_sender.send_replace(true);
+        if let Some(reaper) = self.reaper.take() {
+            reaper.abort();
+        }
+
+        let wait = join_all(self.handles.iter_mut());
+        if let Ok(results) = tokio::time::timeout(timeout, wait).await {
+            for result in results {
+                if let Err(error) = result {
+                    warn!(%error, "Background worker task panicked");
+                }
+            }
+            return Vec::new();
+        }
+
+        let mut abandoned = Vec::new();
+        for (handle, current_job) in self.handles.iter().zip(&self.current_jobs) {
+            if handle.is_finished() {
+                continue;
+            }
+
+            handle.abort();
+            if let Some(job) = current_job.lock().unwrap().clone() {
+                warn!(job.id, job.typ = %job.job_type, "Abandoning background job");
+                abandoned.push(job);
+            }
+        }
+
+        abandoned
     }
 }
 
@@ -7,5 +7,47 @@ diesel::table! {
         last_retry -> Timestamp,
         created_at -> Timestamp,
         priority -> Int2,
+        request_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    background_job_leases (job_id) {
+        job_id -> Int8,
+        worker -> Text,
+        backend_pid -> Int4,
+        started_at -> Timestamp,
+        heartbeat_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    background_job_incidents (id) {
+        id -> Int8,
+        job_id -> Int8,
+        job_type -> Text,
+        worker -> Text,
+        action -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    background_job_lock_waits (job_type) {
+        job_type -> Text,
+        acquisitions -> Int8,
+        contentions -> Int8,
+        wait_seconds -> Float8,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    background_job_progress (job_id) {
+        job_id -> Int8,
+        percent -> Int2,
+        message -> Text,
+        updated_at -> Timestamp,
     }
 }
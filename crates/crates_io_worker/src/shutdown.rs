@@ -0,0 +1,71 @@
+use std::future::pending;
+use tokio::sync::watch;
+
+tokio::task_local! {
+    static SHUTDOWN: ShutdownSignal;
+}
+
+/// Allows background jobs to find out whether the [`Runner`](crate::Runner)
+/// is shutting down.
+///
+/// Long-running jobs should regularly check [`ShutdownSignal::is_requested()`]
+/// and return early once it is set, after saving their progress, so that the
+/// remaining work can be picked up by the next run of the job instead of
+/// being abandoned when the process exits.
+#[derive(Clone, Debug)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    pub(crate) fn new() -> (watch::Sender<bool>, Self) {
+        let (sender, receiver) = watch::channel(false);
+        (sender, Self(receiver))
+    }
+
+    /// Returns the signal of the runner that is running the current job.
+    ///
+    /// Outside of background jobs a signal is returned that is never
+    /// triggered.
+    pub fn current() -> Self {
+        SHUTDOWN
+            .try_with(Clone::clone)
+            .unwrap_or_else(|_| Self::new().1)
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once a shutdown has been requested.
+    pub async fn requested(&mut self) {
+        if self.0.wait_for(|requested| *requested).await.is_err() {
+            // The runner is gone without requesting a shutdown, so there
+            // will never be one.
+            pending::<()>().await;
+        }
+    }
+
+    /// Runs the future with this signal as the [current](Self::current)
+    /// signal.
+    pub(crate) async fn scope<F: std::future::Future>(self, f: F) -> F::Output {
+        SHUTDOWN.scope(self, f).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_current() {
+        assert!(!ShutdownSignal::current().is_requested());
+
+        let (sender, signal) = ShutdownSignal::new();
+        let current = signal.clone().scope(async { ShutdownSignal::current() });
+        let current = current.await;
+        assert!(!current.is_requested());
+
+        sender.send_replace(true);
+        assert!(current.is_requested());
+        assert!(signal.is_requested());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::schema::background_jobs;
+use crate::schema::{background_job_progress, background_jobs};
 use diesel::dsl::now;
 use diesel::pg::Pg;
 use diesel::prelude::*;
@@ -10,6 +10,7 @@ pub(super) struct BackgroundJob {
     pub(super) id: i64,
     pub(super) job_type: String,
     pub(super) data: serde_json::Value,
+    pub(super) request_id: Option<String>,
 }
 
 fn retriable() -> Box<dyn BoxableExpression<background_jobs::table, Pg, SqlType = Bool>> {
@@ -47,9 +48,11 @@ pub(super) fn failed_job_count(conn: &mut PgConnection) -> QueryResult<i64> {
         .get_result(conn)
 }
 
-/// Deletes a job that has successfully completed running
+/// Deletes a job that has successfully completed running, together with its
+/// reported progress
 pub(super) fn delete_successful_job(conn: &mut PgConnection, job_id: i64) -> QueryResult<()> {
     delete(background_jobs::table.find(job_id)).execute(conn)?;
+    delete(background_job_progress::table.find(job_id)).execute(conn)?;
     Ok(())
 }
 
@@ -65,3 +68,15 @@ pub(super) fn update_failed_job(conn: &mut PgConnection, job_id: i64) {
         ))
         .execute(conn);
 }
+
+/// Puts a job back into the queue without counting it as a failure, e.g.
+/// when it couldn't get its lock in time.
+///
+/// The job is retried once it's retriable again, so other jobs get a chance
+/// to run in the meantime.
+pub(super) fn requeue_job(conn: &mut PgConnection, job_id: i64) -> QueryResult<()> {
+    update(background_jobs::table.find(job_id))
+        .set(background_jobs::last_retry.eq(now))
+        .execute(conn)?;
+    Ok(())
+}
@@ -6,6 +6,7 @@ use std::panic::PanicInfo;
 
 pub async fn with_sentry_transaction<F, R, E, Fut>(
     transaction_name: &str,
+    request_id: Option<&str>,
     callback: F,
 ) -> Result<R, E>
 where
@@ -18,7 +19,12 @@ where
     let tx_ctx = sentry_core::TransactionContext::new(transaction_name, "swirl.perform");
     let tx = sentry_core::start_transaction(tx_ctx);
 
-    hub.configure_scope(|scope| scope.set_span(Some(tx.clone().into())));
+    hub.configure_scope(|scope| {
+        scope.set_span(Some(tx.clone().into()));
+        if let Some(request_id) = request_id {
+            scope.set_tag("request_id", request_id);
+        }
+    });
 
     let result = callback().await;
 
@@ -1,4 +1,9 @@
 use crate::job_registry::JobRegistry;
+use crate::lease::{self, JobLease};
+use crate::lock::{self, Lock};
+use crate::progress::JobProgress;
+use crate::request_id::set_request_id;
+use crate::shutdown::ShutdownSignal;
 use crate::storage;
 use crate::util::{try_to_extract_panic_info, with_sentry_transaction};
 use anyhow::anyhow;
@@ -7,24 +12,47 @@ use diesel::prelude::*;
 use futures_util::FutureExt;
 use sentry_core::{Hub, SentryFutureExt};
 use std::panic::AssertUnwindSafe;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::runtime::Handle;
 use tokio::time::sleep;
-use tracing::{debug, error, info_span, warn};
+use tracing::{debug, error, field, info_span, warn};
 
 pub struct Worker<Context> {
+    pub(crate) name: String,
     pub(crate) connection_pool: Pool,
     pub(crate) context: Context,
     pub(crate) job_registry: Arc<JobRegistry<Context>>,
     pub(crate) shutdown_when_queue_empty: bool,
     pub(crate) poll_interval: Duration,
+    pub(crate) lease_duration: Duration,
+    pub(crate) lock_timeout: Duration,
+    pub(crate) shutdown: ShutdownSignal,
+    /// The type and ID of the job that is currently running, which is
+    /// reported if the job is abandoned during a shutdown.
+    pub(crate) current_job: Arc<Mutex<Option<RunningJob>>>,
+}
+
+/// A job that was still running when the [`Runner`](crate::Runner) was
+/// shut down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningJob {
+    pub id: i64,
+    pub job_type: String,
 }
 
 impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
     /// Run background jobs forever, or until the queue is empty if `shutdown_when_queue_empty` is set.
+    ///
+    /// Once a shutdown has been requested, no new jobs are started.
     pub async fn run(&self) {
+        let mut shutdown = self.shutdown.clone();
         loop {
+            if shutdown.is_requested() {
+                debug!("Shutdown requested. Shutting down the worker…");
+                break;
+            }
+
             match self.run_next_job().await {
                 Ok(Some(_)) => {}
                 Ok(None) if self.shutdown_when_queue_empty => {
@@ -36,12 +64,18 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
                         "No pending background worker jobs found. Polling again in {:?}…",
                         self.poll_interval
                     );
-                    sleep(self.poll_interval).await;
+                    tokio::select! {
+                        _ = sleep(self.poll_interval) => {}
+                        _ = shutdown.requested() => {}
+                    }
                 }
                 Err(error) => {
                     let error = format!("{error:#}");
                     error!(error, "Failed to run job");
-                    sleep(self.poll_interval).await;
+                    tokio::select! {
+                        _ = sleep(self.poll_interval) => {}
+                        _ = shutdown.requested() => {}
+                    }
                 }
             }
         }
@@ -56,6 +90,12 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
     async fn run_next_job(&self) -> anyhow::Result<Option<i64>> {
         let context = self.context.clone();
         let job_registry = self.job_registry.clone();
+        let shutdown = self.shutdown.clone();
+        let current_job = self.current_job.clone();
+        let pool = self.connection_pool.clone();
+        let name = self.name.clone();
+        let lease_duration = self.lease_duration;
+        let lock_timeout = self.lock_timeout;
         let conn = self.connection_pool.get().await?;
 
         conn.interact(move |conn| {
@@ -67,18 +107,66 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
                     return Ok(None);
                 };
 
-                let span = info_span!("job", job.id = %job.id, job.typ = %job.job_type);
+                let span = info_span!(
+                    "job",
+                    job.id = %job.id,
+                    job.typ = %job.job_type,
+                    request_id = field::Empty,
+                );
+                if let Some(request_id) = &job.request_id {
+                    span.record("request_id", request_id);
+                    // Jobs that are enqueued by this job inherit the request id
+                    set_request_id(&span, request_id);
+                }
                 let _enter = span.enter();
 
                 let job_id = job.id;
                 debug!("Running job…");
 
-                let future = with_sentry_transaction(&job.job_type, || async {
+                let backend_pid = lease::backend_pid(conn)?;
+                let lease =
+                    JobLease::start(pool.clone(), job_id, name, backend_pid, lease_duration);
+                let progress = JobProgress::start(&span, pool.clone(), job_id);
+
+                *current_job.lock().unwrap() = Some(RunningJob {
+                    id: job.id,
+                    job_type: job.job_type.clone(),
+                });
+
+                // Jobs with the same lock key are run one after the other. The
+                // lock is held until the transaction of the job has finished.
+                let locked = match job_registry.lock_key(&job.job_type, &job.data) {
+                    Ok(Some(key)) => lock::acquire(conn, &key, lock_timeout)
+                        .inspect(|lock| {
+                            if let Lock::Acquired(waited) = *lock {
+                                let record = lock::record(&pool, &job.job_type, waited);
+                                Handle::current().block_on(record);
+                            }
+                        })
+                        .map_err(anyhow::Error::from),
+                    Ok(None) => Ok(Lock::Acquired(None)),
+                    Err(error) => Err(error),
+                };
+
+                if let Ok(Lock::TimedOut) = locked {
+                    warn!("Timed out waiting for the lock of the job, re-queueing it…");
+                    Handle::current().block_on(progress.finish());
+                    Handle::current().block_on(lease.finish());
+                    *current_job.lock().unwrap() = None;
+
+                    storage::requeue_job(conn, job_id)?;
+                    return Ok(Some(job_id));
+                }
+
+                let request_id = job.request_id.as_deref();
+                let future = with_sentry_transaction(&job.job_type, request_id, || async {
+                    locked?;
+
                     let run_task_fn = job_registry
                         .get(&job.job_type)
                         .ok_or_else(|| anyhow!("Unknown job type {}", job.job_type))?;
 
-                    AssertUnwindSafe(run_task_fn(context, job.data))
+                    AssertUnwindSafe(shutdown.scope(run_task_fn(context, job.data)))
                         .catch_unwind()
                         .await
                         .map_err(|e| try_to_extract_panic_info(&e))
@@ -87,6 +175,9 @@ impl<Context: Clone + Send + Sync + 'static> Worker<Context> {
                 });
 
                 let result = Handle::current().block_on(future.bind_hub(Hub::current()));
+                Handle::current().block_on(progress.finish());
+                Handle::current().block_on(lease.finish());
+                *current_job.lock().unwrap() = None;
 
                 match result {
                     Ok(_) => {
@@ -1,12 +1,22 @@
 use crates_io_test_db::TestDatabase;
-use crates_io_worker::schema::background_jobs;
-use crates_io_worker::{BackgroundJob, Runner};
+use crates_io_worker::schema::{
+    background_job_incidents, background_job_leases, background_job_lock_waits,
+    background_job_progress, background_jobs,
+};
+use crates_io_worker::{
+    set_request_id, BackgroundJob, RecoveredJob, Recovery, Runner, ShutdownSignal,
+};
 use deadpool_diesel::postgres::{Manager, Pool};
 use deadpool_diesel::Runtime;
+use diesel::dsl::{now, sql, IntervalDsl};
 use diesel::prelude::*;
+use diesel::sql_types::Integer;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Barrier;
+use tracing_subscriber::Registry;
 
 fn job_exists(id: i64, conn: &mut PgConnection) -> bool {
     background_jobs::table
@@ -115,7 +125,7 @@ async fn jobs_are_deleted_when_successfully_run() {
     assert_eq!(remaining_jobs(&mut conn), 0);
 }
 
-#[tokio::test]
+#[tokio::test(flavor = "multi_thread")]
 async fn failed_jobs_do_not_release_lock_before_updating_retry_time() {
     #[derive(Clone)]
     struct TestContext {
@@ -206,6 +216,539 @@ async fn panicking_in_jobs_updates_retry_counter() {
     assert_eq!(tries, 1);
 }
 
+#[tokio::test]
+async fn shutdown_waits_for_running_jobs() {
+    #[derive(Clone)]
+    struct TestContext {
+        job_started_barrier: Arc<Barrier>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            ctx.job_started_barrier.wait().await;
+            ShutdownSignal::current().requested().await;
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let test_context = TestContext {
+        job_started_barrier: Arc::new(Barrier::new(2)),
+    };
+
+    let runner =
+        polling_runner(test_database.url(), test_context.clone()).register_job_type::<TestJob>();
+
+    let mut conn = test_database.connect();
+    let job_id = TestJob.enqueue(&mut conn).unwrap();
+
+    let runner = runner.start();
+    test_context.job_started_barrier.wait().await;
+
+    let abandoned = runner.shutdown(Duration::from_secs(10)).await;
+    assert_eq!(abandoned, vec![]);
+    assert!(!job_exists(job_id, &mut conn));
+}
+
+#[tokio::test]
+async fn shutdown_reports_abandoned_jobs() {
+    #[derive(Clone)]
+    struct TestContext {
+        job_started_barrier: Arc<Barrier>,
+        assertions_finished_barrier: Arc<Barrier>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            ctx.job_started_barrier.wait().await;
+            ctx.assertions_finished_barrier.wait().await;
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+
+    let test_context = TestContext {
+        job_started_barrier: Arc::new(Barrier::new(2)),
+        assertions_finished_barrier: Arc::new(Barrier::new(2)),
+    };
+
+    let runner =
+        polling_runner(test_database.url(), test_context.clone()).register_job_type::<TestJob>();
+
+    let mut conn = test_database.connect();
+    let job_id = TestJob.enqueue(&mut conn).unwrap();
+
+    let runner = runner.start();
+    test_context.job_started_barrier.wait().await;
+
+    let abandoned = runner.shutdown(Duration::from_millis(100)).await;
+    assert_eq!(abandoned.len(), 1);
+    assert_eq!(abandoned[0].id, job_id);
+    assert_eq!(abandoned[0].job_type, "test");
+    assert!(job_is_locked(job_id, &mut conn));
+
+    test_context.assertions_finished_barrier.wait().await;
+}
+
+#[tokio::test]
+async fn jobs_inherit_the_request_id() {
+    #[derive(Serialize, Deserialize)]
+    struct FirstJob;
+
+    impl BackgroundJob for FirstJob {
+        const JOB_NAME: &'static str = "first";
+        type Context = String;
+
+        async fn run(&self, database_url: Self::Context) -> anyhow::Result<()> {
+            let mut conn = PgConnection::establish(&database_url)?;
+            SecondJob.enqueue(&mut conn)?;
+            Ok(())
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SecondJob;
+
+    impl BackgroundJob for SecondJob {
+        const JOB_NAME: &'static str = "second";
+        type Context = String;
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn request_id(job_type: &str, conn: &mut PgConnection) -> Option<String> {
+        background_jobs::table
+            .filter(background_jobs::job_type.eq(job_type))
+            .select(background_jobs::request_id)
+            .first(conn)
+            .unwrap()
+    }
+
+    let _ = tracing::subscriber::set_global_default(Registry::default());
+
+    let test_database = TestDatabase::new();
+
+    // `SecondJob` is not registered, so that it remains in the queue
+    let runner = runner(test_database.url(), test_database.url().to_string())
+        .register_job_type::<FirstJob>();
+
+    let mut conn = test_database.connect();
+
+    let span = tracing::info_span!("request");
+    set_request_id(&span, "1234");
+    span.in_scope(|| FirstJob.enqueue(&mut conn)).unwrap();
+    assert_eq!(request_id("first", &mut conn).as_deref(), Some("1234"));
+
+    let runner = runner.start();
+    runner.wait_for_shutdown().await;
+    assert_eq!(request_id("second", &mut conn).as_deref(), Some("1234"));
+
+    // Jobs that are enqueued outside of a request have no request id
+    diesel::delete(background_jobs::table)
+        .execute(&mut conn)
+        .unwrap();
+    SecondJob.enqueue(&mut conn).unwrap();
+    assert_eq!(request_id("second", &mut conn), None);
+}
+
+#[tokio::test]
+async fn jobs_can_report_progress() {
+    #[derive(Clone)]
+    struct TestContext {
+        job_started_barrier: Arc<Barrier>,
+        assertions_finished_barrier: Arc<Barrier>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob {
+        fail: bool,
+    }
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            Self::report_progress(10, "Starting");
+
+            // Reports from blocking threads are associated with the job too
+            let span = tracing::Span::current();
+            tokio::task::spawn_blocking(move || {
+                span.in_scope(|| Self::report_progress(150, "Almost done"))
+            })
+            .await?;
+
+            ctx.job_started_barrier.wait().await;
+            ctx.assertions_finished_barrier.wait().await;
+
+            if self.fail {
+                anyhow::bail!("failed");
+            }
+            Ok(())
+        }
+    }
+
+    fn progress(job_id: i64, conn: &mut PgConnection) -> Option<(i16, String)> {
+        background_job_progress::table
+            .find(job_id)
+            .select((
+                background_job_progress::percent,
+                background_job_progress::message,
+            ))
+            .get_result(conn)
+            .optional()
+            .unwrap()
+    }
+
+    async fn wait_for_progress(job_id: i64, conn: &mut PgConnection) -> (i16, String) {
+        for _ in 0..100 {
+            if let Some(progress) = progress(job_id, conn) {
+                if progress.0 == 100 {
+                    return progress;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("job progress was not saved");
+    }
+
+    let _ = tracing::subscriber::set_global_default(Registry::default());
+
+    let test_database = TestDatabase::new();
+
+    let test_context = TestContext {
+        job_started_barrier: Arc::new(Barrier::new(3)),
+        assertions_finished_barrier: Arc::new(Barrier::new(3)),
+    };
+
+    let runner = runner(test_database.url(), test_context.clone()).register_job_type::<TestJob>();
+
+    let mut conn = test_database.connect();
+    let job_id = TestJob { fail: false }.enqueue(&mut conn).unwrap();
+    let failing_job_id = TestJob { fail: true }.enqueue(&mut conn).unwrap();
+    assert_eq!(progress(job_id, &mut conn), None);
+
+    let runner = runner.start();
+    test_context.job_started_barrier.wait().await;
+
+    let expected = (100, "Almost done".to_string());
+    assert_eq!(wait_for_progress(job_id, &mut conn).await, expected);
+    assert_eq!(wait_for_progress(failing_job_id, &mut conn).await, expected);
+
+    test_context.assertions_finished_barrier.wait().await;
+    runner.wait_for_shutdown().await;
+
+    // The progress of successful jobs is deleted together with the job, while
+    // the last reported progress of failed jobs is kept
+    assert!(!job_exists(job_id, &mut conn));
+    assert_eq!(progress(job_id, &mut conn), None);
+    assert!(job_exists(failing_job_id, &mut conn));
+    assert_eq!(progress(failing_job_id, &mut conn), Some(expected));
+}
+
+#[tokio::test]
+async fn running_jobs_hold_a_lease() {
+    #[derive(Clone)]
+    struct TestContext {
+        job_started_barrier: Arc<Barrier>,
+        assertions_finished_barrier: Arc<Barrier>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = TestContext;
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            ctx.job_started_barrier.wait().await;
+            ctx.assertions_finished_barrier.wait().await;
+            Ok(())
+        }
+    }
+
+    fn lease(job_id: i64, conn: &mut PgConnection) -> Option<(String, bool)> {
+        background_job_leases::table
+            .find(job_id)
+            .select((
+                background_job_leases::worker,
+                background_job_leases::expires_at.gt(now),
+            ))
+            .get_result(conn)
+            .optional()
+            .unwrap()
+    }
+
+    let test_database = TestDatabase::new();
+
+    let test_context = TestContext {
+        job_started_barrier: Arc::new(Barrier::new(2)),
+        assertions_finished_barrier: Arc::new(Barrier::new(2)),
+    };
+
+    let runner = runner(test_database.url(), test_context.clone()).register_job_type::<TestJob>();
+
+    let mut conn = test_database.connect();
+    let job_id = TestJob.enqueue(&mut conn).unwrap();
+
+    let runner = runner.start();
+    test_context.job_started_barrier.wait().await;
+
+    let mut current_lease = None;
+    for _ in 0..100 {
+        current_lease = lease(job_id, &mut conn);
+        if current_lease.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let (worker, is_valid) = current_lease.expect("job lease was not saved");
+    assert!(worker.starts_with("background-worker-default-"));
+    assert!(is_valid);
+
+    test_context.assertions_finished_barrier.wait().await;
+    runner.wait_for_shutdown().await;
+
+    assert!(!job_exists(job_id, &mut conn));
+    assert_eq!(lease(job_id, &mut conn), None);
+}
+
+#[tokio::test]
+async fn jobs_with_expired_leases_are_recovered() {
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = ();
+
+        async fn run(&self, _ctx: Self::Context) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Simulates a worker that died while its database session, and thus the
+    /// lock on the job row, is still alive.
+    fn crash_while_running(job_id: i64, database_url: &str) -> PgConnection {
+        let mut conn = PgConnection::establish(database_url).unwrap();
+        diesel::sql_query("BEGIN").execute(&mut conn).unwrap();
+        background_jobs::table
+            .find(job_id)
+            .select(background_jobs::id)
+            .for_update()
+            .get_result::<i64>(&mut conn)
+            .unwrap();
+
+        let backend_pid = diesel::select(sql::<Integer>("pg_backend_pid()"))
+            .get_result::<i32>(&mut conn)
+            .unwrap();
+
+        let mut other_conn = PgConnection::establish(database_url).unwrap();
+        diesel::insert_into(background_job_leases::table)
+            .values((
+                background_job_leases::job_id.eq(job_id),
+                background_job_leases::worker.eq("crashed-worker"),
+                background_job_leases::backend_pid.eq(backend_pid),
+                background_job_leases::expires_at.eq(now - 1.minute()),
+            ))
+            .execute(&mut other_conn)
+            .unwrap();
+
+        conn
+    }
+
+    fn retries(job_id: i64, conn: &mut PgConnection) -> i32 {
+        background_jobs::table
+            .find(job_id)
+            .select(background_jobs::retries)
+            .get_result(conn)
+            .unwrap()
+    }
+
+    fn incidents(job_id: i64, conn: &mut PgConnection) -> Vec<String> {
+        background_job_incidents::table
+            .filter(background_job_incidents::job_id.eq(job_id))
+            .select(background_job_incidents::action)
+            .order(background_job_incidents::id)
+            .load(conn)
+            .unwrap()
+    }
+
+    let test_database = TestDatabase::new();
+    let runner = runner(test_database.url(), ()).register_job_type::<TestJob>();
+
+    let mut conn = test_database.connect();
+    let job_id = TestJob.enqueue(&mut conn).unwrap();
+
+    // Leases that have not expired are left alone
+    assert_eq!(runner.reap_expired_leases().await.unwrap(), vec![]);
+
+    let mut crashed_conn = crash_while_running(job_id, test_database.url());
+    assert!(job_is_locked(job_id, &mut conn));
+
+    let recovered = runner.reap_expired_leases().await.unwrap();
+    let expected = RecoveredJob {
+        id: job_id,
+        job_type: "test".into(),
+        worker: "crashed-worker".into(),
+        recovery: Recovery::Retried,
+    };
+    assert_eq!(recovered, vec![expected.clone()]);
+
+    // The session of the crashed worker was terminated, which released the
+    // lock, and the job is available for an immediate retry
+    assert!(diesel::sql_query("SELECT 1")
+        .execute(&mut crashed_conn)
+        .is_err());
+    assert!(!job_is_locked(job_id, &mut conn));
+    assert_eq!(retries(job_id, &mut conn), 0);
+    assert_eq!(incidents(job_id, &mut conn), vec!["retried"]);
+
+    let lease_count: i64 = background_job_leases::table
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(lease_count, 0);
+
+    // If the lease of the job expires again, the job is marked as failed
+    let _crashed_conn = crash_while_running(job_id, test_database.url());
+    let recovered = runner.reap_expired_leases().await.unwrap();
+    let expected = RecoveredJob {
+        recovery: Recovery::Failed,
+        ..expected
+    };
+    assert_eq!(recovered, vec![expected]);
+    assert!(!job_is_locked(job_id, &mut conn));
+    assert_eq!(retries(job_id, &mut conn), 1);
+    assert_eq!(incidents(job_id, &mut conn), vec!["retried", "failed"]);
+}
+
+#[tokio::test]
+async fn jobs_with_the_same_lock_key_run_one_after_the_other() {
+    #[derive(Clone, Default)]
+    struct TestContext {
+        running: Arc<AtomicUsize>,
+        max_running: Arc<AtomicUsize>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob {
+        krate: String,
+    }
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = TestContext;
+
+        fn lock_key(&self) -> Option<String> {
+            Some(format!("index:{}", self.krate))
+        }
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            let running = ctx.running.fetch_add(1, Ordering::SeqCst) + 1;
+            ctx.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            ctx.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn lock_acquisitions(conn: &mut PgConnection) -> Vec<(String, i64)> {
+        background_job_lock_waits::table
+            .select((
+                background_job_lock_waits::job_type,
+                background_job_lock_waits::acquisitions,
+            ))
+            .load(conn)
+            .unwrap()
+    }
+
+    let test_database = TestDatabase::new();
+    let test_context = TestContext::default();
+
+    let runner = runner(test_database.url(), test_context.clone()).register_job_type::<TestJob>();
+
+    let mut conn = test_database.connect();
+    for _ in 0..2 {
+        let job = TestJob {
+            krate: "foo".into(),
+        };
+        job.enqueue(&mut conn).unwrap();
+    }
+
+    let runner = runner.start();
+    runner.wait_for_shutdown().await;
+
+    assert_eq!(test_context.max_running.load(Ordering::SeqCst), 1);
+    assert_eq!(lock_acquisitions(&mut conn), vec![("test".to_string(), 2)]);
+}
+
+#[tokio::test]
+async fn jobs_are_requeued_when_their_lock_times_out() {
+    #[derive(Clone, Default)]
+    struct TestContext {
+        runs: Arc<AtomicUsize>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestJob;
+
+    impl BackgroundJob for TestJob {
+        const JOB_NAME: &'static str = "test";
+        type Context = TestContext;
+
+        fn lock_key(&self) -> Option<String> {
+            Some("index:foo".into())
+        }
+
+        async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
+            ctx.runs.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        }
+    }
+
+    let test_database = TestDatabase::new();
+    let test_context = TestContext::default();
+
+    let runner = runner(test_database.url(), test_context.clone())
+        .register_job_type::<TestJob>()
+        .lock_timeout(Duration::from_millis(100));
+
+    let mut conn = test_database.connect();
+    TestJob.enqueue(&mut conn).unwrap();
+    TestJob.enqueue(&mut conn).unwrap();
+
+    let runner = runner.start();
+    runner.wait_for_shutdown().await;
+
+    // The second job timed out while the first one was running, and is
+    // retried later without counting as a failure
+    assert_eq!(test_context.runs.load(Ordering::SeqCst), 1);
+    let retries = background_jobs::table
+        .select(background_jobs::retries)
+        .load::<i32>(&mut conn)
+        .unwrap();
+    assert_eq!(retries, vec![0]);
+}
+
 fn runner<Context: Clone + Send + Sync + 'static>(
     database_url: &str,
     context: Context,
@@ -217,3 +760,14 @@ fn runner<Context: Clone + Send + Sync + 'static>(
         .configure_default_queue(|queue| queue.num_workers(2))
         .shutdown_when_queue_empty()
 }
+
+/// Like [`runner`], but keeps polling for new jobs until it is shut down.
+fn polling_runner<Context: Clone + Send + Sync + 'static>(
+    database_url: &str,
+    context: Context,
+) -> Runner<Context> {
+    let manager = Manager::new(database_url, Runtime::Tokio1);
+    let deadpool = Pool::builder(manager).max_size(4).build().unwrap();
+
+    Runner::new(deadpool, context).configure_default_queue(|queue| queue.num_workers(2))
+}
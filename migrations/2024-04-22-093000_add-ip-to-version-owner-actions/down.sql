@@ -0,0 +1,2 @@
+alter table version_owner_actions
+    drop column ip;
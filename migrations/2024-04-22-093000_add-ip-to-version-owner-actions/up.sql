@@ -0,0 +1,4 @@
+alter table version_owner_actions
+    add column ip inet;
+
+comment on column version_owner_actions.ip is 'IP address of the client that performed the action. Used to detect publishes from unusual locations.';
@@ -0,0 +1,4 @@
+alter table users
+    add column follow_digest boolean not null default true;
+
+comment on column users.follow_digest is 'Whether the user receives the weekly email digest of new versions of the crates they follow.';
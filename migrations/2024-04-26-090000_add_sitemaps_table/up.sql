@@ -0,0 +1,14 @@
+create table sitemaps
+(
+    path         varchar     not null,
+    content      text        not null,
+    generated_at timestamp   not null default now(),
+    constraint sitemaps_pk
+        primary key (path)
+);
+
+comment on table sitemaps is 'Cache of the generated sitemap documents, to avoid querying all crates for every request by a search engine crawler.';
+
+comment on column sitemaps.path is 'Request path of the sitemap document';
+comment on column sitemaps.content is 'The generated XML document';
+comment on column sitemaps.generated_at is 'Time when the document was generated';
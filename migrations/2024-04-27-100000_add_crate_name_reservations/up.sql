@@ -0,0 +1,25 @@
+create table crate_name_reservations
+(
+    name       varchar   not null,
+    user_id    integer   not null
+        constraint fk_crate_name_reservations_user_id
+            references users
+            on delete cascade,
+    created_at timestamp not null default now(),
+    expires_at timestamp not null,
+    constraint crate_name_reservations_pk
+        primary key (name)
+);
+
+comment on table crate_name_reservations is 'Crate names that have been reserved by a user for a limited period of time. Other users can not publish a crate with a reserved name until the reservation expires.';
+
+comment on column crate_name_reservations.name is 'The reserved crate name';
+comment on column crate_name_reservations.user_id is 'Reference to the user that reserved the crate name';
+comment on column crate_name_reservations.created_at is 'Time when the crate name was reserved';
+comment on column crate_name_reservations.expires_at is 'Time when the reservation expires';
+
+create unique index crate_name_reservations_canon_crate_name_index
+    on crate_name_reservations (canon_crate_name(name));
+
+create index crate_name_reservations_user_id_index
+    on crate_name_reservations (user_id);
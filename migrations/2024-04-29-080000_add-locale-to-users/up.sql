@@ -0,0 +1,4 @@
+alter table users
+    add column locale varchar not null default 'en';
+
+comment on column users.locale is 'The preferred language of the user for emails, e.g. `en` or `de`.';
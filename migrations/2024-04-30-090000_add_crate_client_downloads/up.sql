@@ -0,0 +1,19 @@
+create table crate_client_downloads
+(
+    crate_id       integer not null
+        constraint fk_crate_client_downloads_crate_id
+            references crates
+            on delete cascade,
+    client_version varchar not null,
+    date           date    not null default current_date,
+    downloads      integer not null default 0,
+    constraint crate_client_downloads_pk
+        primary key (crate_id, date, client_version)
+);
+
+comment on table crate_client_downloads is 'Number of downloads per crate, day and version of the cargo client, as counted from the CDN log files.';
+
+comment on column crate_client_downloads.crate_id is 'Reference to the crate that was downloaded';
+comment on column crate_client_downloads.client_version is 'The major and minor version of the cargo client, e.g. `1.74`';
+comment on column crate_client_downloads.date is 'The day on which the downloads happened';
+comment on column crate_client_downloads.downloads is 'The number of downloads on this day with this client version';
@@ -0,0 +1 @@
+alter table version_owner_actions drop column reason;
@@ -0,0 +1,3 @@
+alter table version_owner_actions add column reason text;
+
+comment on column version_owner_actions.reason is 'Reason given by the user for the action, e.g. the advisory that caused a bulk yank.';
@@ -0,0 +1,22 @@
+create table user_sign_ins
+(
+    id         bigserial
+        constraint user_sign_ins_pk
+            primary key,
+    user_id    integer   not null
+        constraint fk_user_sign_ins_user_id
+            references users
+            on delete cascade,
+    ip         inet      not null,
+    created_at timestamp not null default now()
+);
+
+create index user_sign_ins_user_id_created_at_index
+    on user_sign_ins (user_id, created_at);
+
+comment on table user_sign_ins is 'Successful sign-ins of users. Used to detect suspicious sign-ins from unusual locations.';
+
+comment on column user_sign_ins.id is 'Unique identifier of the sign-in';
+comment on column user_sign_ins.user_id is 'Reference to the user that signed in';
+comment on column user_sign_ins.ip is 'IP address of the client that signed in';
+comment on column user_sign_ins.created_at is 'Date and time of the sign-in';
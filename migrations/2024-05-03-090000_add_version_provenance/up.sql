@@ -0,0 +1,28 @@
+create table version_provenance
+(
+    version_id     integer   not null
+        constraint version_provenance_pk
+            primary key
+        constraint fk_version_provenance_version_id
+            references versions
+            on delete cascade,
+    api_token_id   integer
+        constraint fk_version_provenance_api_token_id
+            references api_tokens
+            on delete set null,
+    api_token_name varchar,
+    ci_service     varchar,
+    user_agent     varchar,
+    ip             inet,
+    created_at     timestamp not null default now()
+);
+
+comment on table version_provenance is 'Information about how a version was published, e.g. whether it was published by a human or from a CI workflow.';
+
+comment on column version_provenance.version_id is 'Reference to the published version';
+comment on column version_provenance.api_token_id is 'Reference to the API token that was used to publish the version, or NULL if it was published with a session cookie or the token was deleted since';
+comment on column version_provenance.api_token_name is 'Name of the API token at the time of the publish, since the token might be renamed or deleted later on';
+comment on column version_provenance.ci_service is 'Name of the CI service that the publish request originated from, or NULL if it was not detected as a CI publish';
+comment on column version_provenance.user_agent is 'User-Agent header of the publish request';
+comment on column version_provenance.ip is 'IP address of the client that published the version';
+comment on column version_provenance.created_at is 'Date and time of the publish';
@@ -0,0 +1,36 @@
+create table user_merges
+(
+    id                  bigserial
+        constraint user_merges_pk
+            primary key,
+    source_user_id      integer   not null
+        constraint fk_user_merges_source_user_id
+            references users
+            on delete cascade,
+    target_user_id      integer   not null
+        constraint fk_user_merges_target_user_id
+            references users
+            on delete cascade,
+    crates_transferred  integer   not null,
+    follows_transferred integer   not null,
+    tokens_revoked      integer   not null,
+    email_transferred   boolean   not null,
+    created_at          timestamp not null default now()
+);
+
+create index user_merges_source_user_id_index
+    on user_merges (source_user_id);
+
+create index user_merges_target_user_id_index
+    on user_merges (target_user_id);
+
+comment on table user_merges is 'Audit trail of user accounts that were merged into other accounts by the crates.io team, e.g. after GitHub account changes.';
+
+comment on column user_merges.id is 'Unique identifier of the merge';
+comment on column user_merges.source_user_id is 'Reference to the user that was merged into the target user and then locked';
+comment on column user_merges.target_user_id is 'Reference to the user that received the crate ownerships and follows of the source user';
+comment on column user_merges.crates_transferred is 'Number of crate ownerships that were transferred to the target user';
+comment on column user_merges.follows_transferred is 'Number of followed crates that were transferred to the target user';
+comment on column user_merges.tokens_revoked is 'Number of API tokens of the source user that were revoked';
+comment on column user_merges.email_transferred is 'Whether the email address of the source user was transferred to the target user';
+comment on column user_merges.created_at is 'Date and time of the merge';
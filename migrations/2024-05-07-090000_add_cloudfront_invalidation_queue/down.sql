@@ -0,0 +1 @@
+drop table cloudfront_invalidation_queue;
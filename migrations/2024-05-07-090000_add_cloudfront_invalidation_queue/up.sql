@@ -0,0 +1,14 @@
+create table cloudfront_invalidation_queue
+(
+    id         bigserial
+        constraint cloudfront_invalidation_queue_pk
+            primary key,
+    path       text      not null,
+    created_at timestamp not null default now()
+);
+
+comment on table cloudfront_invalidation_queue is 'Paths that need to be invalidated on CloudFront. The queue is flushed in batches by the `flush_cloudfront_invalidations` background job, since CloudFront charges per path and rate limits the invalidation API.';
+
+comment on column cloudfront_invalidation_queue.id is 'Unique identifier of the queued path, which also determines the order in which the paths are invalidated';
+comment on column cloudfront_invalidation_queue.path is 'Path of the file to invalidate, e.g. `/config.json` or `/re/ge/regex`';
+comment on column cloudfront_invalidation_queue.created_at is 'Date and time when the path was queued for invalidation';
@@ -0,0 +1,38 @@
+create table token_anomalies
+(
+    id                 bigserial
+        constraint token_anomalies_pk
+            primary key,
+    user_id            integer   not null
+        constraint fk_token_anomalies_user_id
+            references users
+            on delete cascade,
+    api_token_id       integer
+        constraint fk_token_anomalies_api_token_id
+            references api_tokens
+            on delete set null,
+    kind               text      not null,
+    details            text      not null,
+    publishes_locked   boolean   not null default false,
+    confirmation_token text      not null default random_string(26),
+    confirmed_at       timestamp,
+    created_at         timestamp not null default now()
+);
+
+create index token_anomalies_user_id_index
+    on token_anomalies (user_id);
+
+create unique index token_anomalies_confirmation_token_uindex
+    on token_anomalies (confirmation_token);
+
+comment on table token_anomalies is 'Unusual API token activity that was detected by the `detect_token_anomalies` background job, e.g. publishes from new networks. Used to notify the users, and to lock their publishes until they confirm the activity.';
+
+comment on column token_anomalies.id is 'Unique identifier of the anomaly';
+comment on column token_anomalies.user_id is 'Reference to the user that owns the API token';
+comment on column token_anomalies.api_token_id is 'Reference to the API token that was used, if the anomaly is specific to a single token';
+comment on column token_anomalies.kind is 'Kind of the anomaly: `new_network`, `impossible_travel` or `publish_burst`';
+comment on column token_anomalies.details is 'Human-readable description of the activity that was flagged, which is included in the notification email';
+comment on column token_anomalies.publishes_locked is 'Whether publishing is blocked for the user until the anomaly is confirmed';
+comment on column token_anomalies.confirmation_token is 'Secret token of the confirmation link in the notification email';
+comment on column token_anomalies.confirmed_at is 'Date and time when the user confirmed the activity, or NULL if it was not confirmed yet';
+comment on column token_anomalies.created_at is 'Date and time when the anomaly was detected';
@@ -0,0 +1 @@
+drop table crate_dependents_history;
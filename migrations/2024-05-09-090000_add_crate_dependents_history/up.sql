@@ -0,0 +1,17 @@
+create table crate_dependents_history
+(
+    crate_id   integer not null
+        constraint fk_crate_dependents_history_crate_id
+            references crates
+            on delete cascade,
+    date       date    not null default current_date,
+    dependents integer not null,
+    constraint crate_dependents_history_pk
+        primary key (crate_id, date)
+);
+
+comment on table crate_dependents_history is 'Weekly snapshots of the number of crates that depend on a crate, as computed by the `snapshot_dependents_counts` background job. Crates without dependents are not included.';
+
+comment on column crate_dependents_history.crate_id is 'Reference to the crate that the other crates depend on';
+comment on column crate_dependents_history.date is 'The day on which the snapshot was taken';
+comment on column crate_dependents_history.dependents is 'The number of crates whose latest version depends on the crate';
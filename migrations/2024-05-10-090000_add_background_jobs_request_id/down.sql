@@ -0,0 +1,2 @@
+alter table background_jobs
+    drop column request_id;
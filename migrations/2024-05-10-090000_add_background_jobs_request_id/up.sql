@@ -0,0 +1,4 @@
+alter table background_jobs
+    add column request_id text;
+
+comment on column background_jobs.request_id is 'ID of the HTTP request that enqueued the job, used to correlate the job logs and Sentry events with the request. `NULL` if the job was not enqueued by a request.';
@@ -0,0 +1,2 @@
+drop table category_migrations;
+drop table category_synonyms;
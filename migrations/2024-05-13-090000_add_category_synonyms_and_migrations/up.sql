@@ -0,0 +1,60 @@
+create table category_synonyms
+(
+    synonym     varchar   not null
+        constraint category_synonyms_pk
+            primary key,
+    category_id integer   not null
+        constraint fk_category_synonyms_category_id
+            references categories
+            on delete cascade,
+    created_by  integer
+        constraint fk_category_synonyms_created_by
+            references users
+            on delete set null,
+    created_at  timestamp not null default now()
+);
+
+create index category_synonyms_category_id_index
+    on category_synonyms (category_id);
+
+comment on table category_synonyms is 'Alternative slugs of categories, e.g. the old slugs of renamed categories. Crates that are published with a synonym are added to the category of the synonym.';
+
+comment on column category_synonyms.synonym is 'The alternative slug, which must not be the slug of an existing category';
+comment on column category_synonyms.category_id is 'Reference to the category that the synonym resolves to';
+comment on column category_synonyms.created_by is 'Reference to the administrator that created the synonym';
+comment on column category_synonyms.created_at is 'Date and time when the synonym was created';
+
+create table category_migrations
+(
+    id                 bigserial
+        constraint category_migrations_pk
+            primary key,
+    source_category_id integer   not null
+        constraint fk_category_migrations_source_category_id
+            references categories
+            on delete cascade,
+    target_category_id integer   not null
+        constraint fk_category_migrations_target_category_id
+            references categories
+            on delete cascade,
+    created_by         integer
+        constraint fk_category_migrations_created_by
+            references users
+            on delete set null,
+    crates_migrated    integer,
+    created_at         timestamp not null default now(),
+    finished_at        timestamp
+);
+
+create index category_migrations_source_category_id_index
+    on category_migrations (source_category_id);
+
+comment on table category_migrations is 'Audit trail of the bulk migrations of crates from one category to another by the crates.io team, e.g. after changes of the category taxonomy. The migrations are performed by the `migrate_category` background job.';
+
+comment on column category_migrations.id is 'Unique identifier of the migration';
+comment on column category_migrations.source_category_id is 'Reference to the category whose crates are moved';
+comment on column category_migrations.target_category_id is 'Reference to the category that the crates are moved to';
+comment on column category_migrations.created_by is 'Reference to the administrator that requested the migration';
+comment on column category_migrations.crates_migrated is 'Number of crates that were moved, or NULL if the migration has not been performed yet';
+comment on column category_migrations.created_at is 'Date and time when the migration was requested';
+comment on column category_migrations.finished_at is 'Date and time when the migration was performed, or NULL if it has not been performed yet';
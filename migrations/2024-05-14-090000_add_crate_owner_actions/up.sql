@@ -0,0 +1,46 @@
+create table crate_owner_actions
+(
+    id         serial
+        constraint crate_owner_actions_pk
+            primary key,
+    crate_id   integer   not null
+        constraint fk_crate_owner_actions_crate_id
+            references crates
+            on delete cascade,
+    owner_id   integer   not null,
+    owner_kind integer   not null,
+    actor_id   integer
+        constraint fk_crate_owner_actions_actor_id
+            references users
+            on delete set null,
+    action     integer   not null,
+    via        integer   not null,
+    time       timestamp not null default now()
+);
+
+create index crate_owner_actions_crate_id_index
+    on crate_owner_actions (crate_id);
+
+comment on table crate_owner_actions is 'Audit log of the owners that were added to or removed from crates. Used to show the ownership history of a crate.';
+
+comment on column crate_owner_actions.id is 'Unique identifier of the action';
+comment on column crate_owner_actions.crate_id is 'Reference to the crate whose owners were changed';
+comment on column crate_owner_actions.owner_id is 'Reference to the user or team that was added or removed, depending on `owner_kind`';
+comment on column crate_owner_actions.owner_kind is 'Kind of the owner: 0 = user, 1 = team';
+comment on column crate_owner_actions.actor_id is 'Reference to the user that performed the action, or NULL if it was performed by the crates.io team or is unknown';
+comment on column crate_owner_actions.action is 'The action: 0 = add, 1 = remove';
+comment on column crate_owner_actions.via is 'How the action was performed: 0 = unknown, 1 = publish of the first version, 2 = accepted invitation, 3 = direct change by an owner, 4 = crates.io team';
+comment on column crate_owner_actions.time is 'Date and time when the action was performed';
+
+-- Reconstruct the history of the existing ownerships as far as possible.
+-- The `created_by` column is only reliable for additions, and it is unknown
+-- who removed owners.
+insert into crate_owner_actions (crate_id, owner_id, owner_kind, actor_id, action, via, time)
+select crate_id, owner_id, owner_kind, users.id, 0, 0, crate_owners.created_at
+from crate_owners
+         left join users on users.id = crate_owners.created_by;
+
+insert into crate_owner_actions (crate_id, owner_id, owner_kind, actor_id, action, via, time)
+select crate_id, owner_id, owner_kind, null, 1, 0, updated_at
+from crate_owners
+where deleted;
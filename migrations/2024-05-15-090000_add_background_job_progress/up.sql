@@ -0,0 +1,16 @@
+create table background_job_progress
+(
+    job_id     bigint    not null
+        constraint background_job_progress_pk
+            primary key,
+    percent    smallint  not null,
+    message    text      not null,
+    updated_at timestamp not null default now()
+);
+
+comment on table background_job_progress is 'Latest progress reported by long-running background jobs. There is intentionally no foreign key to `background_jobs`, since the progress is written while the job row is locked by the worker.';
+
+comment on column background_job_progress.job_id is 'Reference to the job in the `background_jobs` table';
+comment on column background_job_progress.percent is 'Progress of the job in percent, from 0 to 100';
+comment on column background_job_progress.message is 'Description of the current step of the job';
+comment on column background_job_progress.updated_at is 'Date and time when the progress was last reported';
@@ -0,0 +1,21 @@
+create table download_spikes
+(
+    crate_id   integer   not null
+        constraint fk_download_spikes_crate_id
+            references crates
+            on delete cascade,
+    date       date      not null,
+    downloads  bigint    not null,
+    baseline   bigint    not null,
+    created_at timestamp not null default now(),
+    constraint download_spikes_pk
+        primary key (crate_id, date)
+);
+
+comment on table download_spikes is 'Days on which a crate was downloaded unusually often compared to the previous weeks, as detected by the `detect_download_spikes` background job. Such spikes can be caused by dependency confusion attacks or misconfigured mirrors.';
+
+comment on column download_spikes.crate_id is 'Reference to the crate that was downloaded';
+comment on column download_spikes.date is 'The day with the unusually high number of downloads';
+comment on column download_spikes.downloads is 'The number of downloads of all versions of the crate on this day';
+comment on column download_spikes.baseline is 'The median of the daily downloads of the crate in the weeks before, which the downloads were compared to';
+comment on column download_spikes.created_at is 'Date and time when the spike was detected';
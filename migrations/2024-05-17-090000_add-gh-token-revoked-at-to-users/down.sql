@@ -0,0 +1,2 @@
+alter table users
+    drop column gh_token_revoked_at;
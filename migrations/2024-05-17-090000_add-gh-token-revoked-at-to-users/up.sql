@@ -0,0 +1,4 @@
+alter table users
+    add column gh_token_revoked_at timestamp;
+
+comment on column users.gh_token_revoked_at is 'Date and time when GitHub rejected the access token of the user, e.g. because the user revoked the authorization of the OAuth app. The sessions of the user are invalid and publishing is blocked until the user signs in again. NULL if the token was not rejected.';
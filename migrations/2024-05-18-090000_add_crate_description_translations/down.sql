@@ -0,0 +1 @@
+drop table crate_description_translations;
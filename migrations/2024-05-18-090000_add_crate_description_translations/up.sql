@@ -0,0 +1,19 @@
+create table crate_description_translations
+(
+    crate_id    integer   not null
+        constraint fk_crate_description_translations_crate_id
+            references crates
+            on delete cascade,
+    locale      text      not null,
+    description text      not null,
+    updated_at  timestamp not null default now(),
+    constraint crate_description_translations_pk
+        primary key (crate_id, locale)
+);
+
+comment on table crate_description_translations is 'Translations of the descriptions of crates, which are provided by the crate owners. The API returns the translation that best matches the `Accept-Language` header of the request instead of the description of the manifest.';
+
+comment on column crate_description_translations.crate_id is 'Reference to the crate that the description belongs to';
+comment on column crate_description_translations.locale is 'Lowercase language tag of the translation, e.g. `de` or `pt-br`';
+comment on column crate_description_translations.description is 'The translated description';
+comment on column crate_description_translations.updated_at is 'Date and time when the translation was last changed';
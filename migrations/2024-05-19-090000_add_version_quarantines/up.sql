@@ -0,0 +1,43 @@
+create table version_quarantines
+(
+    id             serial
+        constraint version_quarantines_pk
+            primary key,
+    version_id     integer
+        constraint fk_version_quarantines_version_id
+            references versions
+            on delete set null,
+    crate_name     varchar   not null,
+    version_num    varchar   not null,
+    reason         text      not null,
+    quarantined_by integer   not null
+        constraint fk_version_quarantines_quarantined_by
+            references users,
+    quarantined_at timestamp not null default now(),
+    resolution     varchar,
+    resolved_by    integer
+        constraint fk_version_quarantines_resolved_by
+            references users,
+    resolved_at    timestamp,
+    constraint version_quarantines_resolution_check
+        check (resolution in ('released', 'deleted')),
+    constraint version_quarantines_resolved_check
+        check ((resolution is null) = (resolved_at is null) and (resolved_at is null) = (resolved_by is null))
+);
+
+comment on table version_quarantines is 'Versions that were quarantined by an administrator pending a review. Downloads of quarantined versions through the API are rejected and the versions are marked as yanked in the index, until the quarantine is resolved by releasing or deleting the version. Resolved quarantines are kept as an audit trail.';
+
+comment on column version_quarantines.id is 'Unique identifier of the quarantine';
+comment on column version_quarantines.version_id is 'Reference to the quarantined version, or NULL if the version was deleted';
+comment on column version_quarantines.crate_name is 'Name of the crate, which is kept after the version was deleted';
+comment on column version_quarantines.version_num is 'Version number of the quarantined version, which is kept after the version was deleted';
+comment on column version_quarantines.reason is 'Reason for the quarantine, which is sent to the owners of the crate';
+comment on column version_quarantines.quarantined_by is 'Reference to the administrator who quarantined the version';
+comment on column version_quarantines.quarantined_at is 'Date and time when the version was quarantined';
+comment on column version_quarantines.resolution is 'Outcome of the review (`released` or `deleted`), or NULL while the version is quarantined';
+comment on column version_quarantines.resolved_by is 'Reference to the administrator who resolved the quarantine';
+comment on column version_quarantines.resolved_at is 'Date and time when the quarantine was resolved';
+
+create unique index version_quarantines_active_uindex
+    on version_quarantines (version_id)
+    where resolved_at is null;
@@ -0,0 +1,24 @@
+create table trending_stats
+(
+    date                  date    not null default current_date,
+    kind                  varchar not null
+        constraint trending_stats_kind_check
+            check (kind in ('keyword', 'category')),
+    name                  varchar not null,
+    downloads             bigint  not null,
+    previous_downloads    bigint  not null,
+    publishes             integer not null,
+    previous_publishes    integer not null,
+    constraint trending_stats_pk
+        primary key (date, kind, name)
+);
+
+comment on table trending_stats is 'Daily snapshots of the downloads and publishes per keyword and category in the last week and the week before, as computed by the `update_trending_stats` background job. Keywords and categories without any downloads or publishes in both weeks are not included.';
+
+comment on column trending_stats.date is 'The day on which the snapshot was taken. The weeks end on the day before.';
+comment on column trending_stats.kind is 'Whether the row belongs to a keyword or a category (`keyword` or `category`)';
+comment on column trending_stats.name is 'The keyword, or the slug of the category';
+comment on column trending_stats.downloads is 'The number of downloads of the crates with the keyword or in the category in the last week';
+comment on column trending_stats.previous_downloads is 'The number of downloads of the crates with the keyword or in the category in the week before the last week';
+comment on column trending_stats.publishes is 'The number of versions published of the crates with the keyword or in the category in the last week';
+comment on column trending_stats.previous_publishes is 'The number of versions published of the crates with the keyword or in the category in the week before the last week';
@@ -0,0 +1,2 @@
+drop table background_job_incidents;
+drop table background_job_leases;
@@ -0,0 +1,43 @@
+create table background_job_leases
+(
+    job_id       bigint    not null
+        constraint background_job_leases_pk
+            primary key,
+    worker       text      not null,
+    backend_pid  integer   not null,
+    started_at   timestamp not null default now(),
+    heartbeat_at timestamp not null default now(),
+    expires_at   timestamp not null
+);
+
+comment on table background_job_leases is 'Leases of the background jobs that are currently running. The worker of a job regularly extends the lease, so an expired lease means that the worker crashed or hangs while the job is still marked as running. There is intentionally no foreign key to `background_jobs`, since the lease is written while the job row is locked by the worker.';
+
+comment on column background_job_leases.job_id is 'Reference to the job in the `background_jobs` table';
+comment on column background_job_leases.worker is 'Name of the worker that runs the job';
+comment on column background_job_leases.backend_pid is 'Process ID of the database session whose transaction locks the job row';
+comment on column background_job_leases.started_at is 'Date and time when the job was started';
+comment on column background_job_leases.heartbeat_at is 'Date and time of the last heartbeat of the worker';
+comment on column background_job_leases.expires_at is 'Date and time after which the job is considered stuck, unless the lease is extended before';
+
+create table background_job_incidents
+(
+    id          bigserial not null
+        constraint background_job_incidents_pk
+            primary key,
+    job_id      bigint    not null,
+    job_type    text      not null,
+    worker      text      not null,
+    action      text      not null,
+    created_at  timestamp not null default now()
+);
+
+comment on table background_job_incidents is 'Log of background jobs that were recovered after the lease of their worker expired';
+
+comment on column background_job_incidents.id is 'Unique identifier of the incident';
+comment on column background_job_incidents.job_id is 'Reference to the job in the `background_jobs` table, which might not exist anymore';
+comment on column background_job_incidents.job_type is 'Type of the job';
+comment on column background_job_incidents.worker is 'Name of the worker whose lease expired';
+comment on column background_job_incidents.action is 'How the job was recovered: `retried` if it was released for an immediate retry, `failed` if it was marked as failed because its lease had expired before';
+comment on column background_job_incidents.created_at is 'Date and time when the incident was recorded';
+
+create index background_job_incidents_job_id_index on background_job_incidents (job_id);
@@ -0,0 +1,29 @@
+create table version_artifacts
+(
+    id          serial
+        constraint version_artifacts_pk
+            primary key,
+    version_id  integer   not null
+        constraint fk_version_artifacts_version_id
+            references versions
+            on delete cascade,
+    target      varchar   not null,
+    checksum    char(64)  not null,
+    size        bigint    not null,
+    uploaded_by integer   not null
+        constraint fk_version_artifacts_uploaded_by
+            references users,
+    created_at  timestamp not null default now(),
+    constraint version_artifacts_version_id_target_uindex
+        unique (version_id, target)
+);
+
+comment on table version_artifacts is 'Pre-built binary artifacts of versions, one per target platform, which the owners of a crate upload after publishing a version. The files are stored next to the crate files, so that tools like cargo-binstall can download official binaries instead of building the crate.';
+
+comment on column version_artifacts.id is 'Unique identifier of the artifact';
+comment on column version_artifacts.version_id is 'Reference to the version that the artifact was built from';
+comment on column version_artifacts.target is 'Target triple of the platform that the artifact was built for, e.g. `x86_64-unknown-linux-gnu`';
+comment on column version_artifacts.checksum is 'SHA256 checksum of the artifact file, as a hex string';
+comment on column version_artifacts.size is 'Size of the artifact file in bytes';
+comment on column version_artifacts.uploaded_by is 'Reference to the user who uploaded the artifact';
+comment on column version_artifacts.created_at is 'Date and time when the artifact was uploaded';
@@ -0,0 +1,4 @@
+drop table site_stats;
+
+alter table users
+    drop column created_at;
@@ -0,0 +1,30 @@
+alter table users
+    add column created_at timestamp;
+
+-- Only set the default after adding the column, so that the existing users
+-- keep `NULL`, since it is unknown when they signed up.
+alter table users
+    alter column created_at set default now();
+
+comment on column users.created_at is 'Date and time when the user signed up. NULL for users that signed up before the column was added.';
+
+create table site_stats
+(
+    date      date    not null
+        constraint site_stats_pk
+            primary key,
+    crates    bigint  not null,
+    versions  bigint  not null,
+    downloads bigint  not null,
+    publishes integer not null,
+    new_users integer not null
+);
+
+comment on table site_stats is 'Daily rollups of the registry-wide statistics, as computed by the `update_site_stats` background job. Used by the `GET /api/v1/stats/site` endpoint.';
+
+comment on column site_stats.date is 'The day that the rollup covers. The rollup is computed after the end of the day.';
+comment on column site_stats.crates is 'The total number of crates when the rollup was computed';
+comment on column site_stats.versions is 'The total number of versions when the rollup was computed';
+comment on column site_stats.downloads is 'The total number of downloads when the rollup was computed';
+comment on column site_stats.publishes is 'The number of versions that were published on the day';
+comment on column site_stats.new_users is 'The number of users that signed up on the day';
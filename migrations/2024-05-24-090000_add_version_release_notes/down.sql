@@ -0,0 +1 @@
+drop table version_release_notes;
@@ -0,0 +1,23 @@
+create table version_release_notes
+(
+    version_id integer   not null
+        constraint version_release_notes_pk
+            primary key
+        constraint fk_version_release_notes_version_id
+            references versions
+            on delete cascade,
+    markdown   text      not null,
+    html       text      not null,
+    updated_by integer   not null
+        constraint fk_version_release_notes_updated_by
+            references users,
+    updated_at timestamp not null default now()
+);
+
+comment on table version_release_notes is 'Release notes of versions, which the owners of a crate can add or change after publishing a version, so that changelogs can be displayed without parsing files of the repository.';
+
+comment on column version_release_notes.version_id is 'Reference to the version that the release notes belong to';
+comment on column version_release_notes.markdown is 'The release notes as Markdown, as they were submitted by the owner';
+comment on column version_release_notes.html is 'The release notes rendered to HTML';
+comment on column version_release_notes.updated_by is 'Reference to the user who last changed the release notes';
+comment on column version_release_notes.updated_at is 'Date and time when the release notes were last changed';
@@ -0,0 +1,13 @@
+drop trigger trigger_emails_set_first_as_primary on emails;
+drop function emails_set_first_as_primary();
+
+delete from emails where not is_primary;
+
+drop index emails_user_id_email_uindex;
+drop index emails_user_id_primary_uindex;
+
+alter table emails
+    add constraint emails_user_id_key unique (user_id);
+
+alter table emails
+    drop column is_primary;
@@ -0,0 +1,33 @@
+alter table emails
+    add column is_primary boolean not null default false;
+
+-- Until now, users could only have a single email address
+update emails set is_primary = true;
+
+alter table emails
+    drop constraint emails_user_id_key;
+
+create unique index emails_user_id_primary_uindex
+    on emails (user_id)
+    where is_primary;
+
+create unique index emails_user_id_email_uindex
+    on emails (user_id, lower(email));
+
+comment on column emails.is_primary is 'Whether this is the primary email address of the user, which is used for notifications. Every user has at most one primary email address.';
+
+create function emails_set_first_as_primary() returns trigger as $$
+begin
+    if not exists (select 1 from emails where user_id = new.user_id and is_primary) then
+        new.is_primary := true;
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+
+comment on function emails_set_first_as_primary() is 'Makes the first email address of a user the primary email address';
+
+create trigger trigger_emails_set_first_as_primary
+    before insert on emails
+    for each row
+execute procedure emails_set_first_as_primary();
@@ -0,0 +1,26 @@
+create table publish_nonces
+(
+    nonce      bytea     not null
+        constraint publish_nonces_pk
+            primary key,
+    user_id    integer   not null
+        constraint fk_publish_nonces_user_id
+            references users
+            on delete cascade,
+    crate_name varchar   not null,
+    version    varchar   not null,
+    created_at timestamp not null default now(),
+    expires_at timestamp not null
+);
+
+comment on table publish_nonces is 'One-time nonces that authorize a single publish of a crate version, so that captured publish requests can not be replayed.';
+
+comment on column publish_nonces.nonce is 'SHA-256 hash of the nonce';
+comment on column publish_nonces.user_id is 'Reference to the user who requested the nonce';
+comment on column publish_nonces.crate_name is 'Name of the crate that the nonce is bound to';
+comment on column publish_nonces.version is 'Version that the nonce is bound to';
+comment on column publish_nonces.created_at is 'Date and time when the nonce was requested';
+comment on column publish_nonces.expires_at is 'Date and time after which the nonce can no longer be used';
+
+create index publish_nonces_user_id_index
+    on publish_nonces (user_id);
@@ -0,0 +1 @@
+drop table crate_recommended_versions;
@@ -0,0 +1,28 @@
+create table crate_recommended_versions
+(
+    crate_id   integer   not null
+        constraint crate_recommended_versions_pk
+            primary key
+        constraint fk_crate_recommended_versions_crate_id
+            references crates
+            on delete cascade,
+    version_id integer   not null
+        constraint fk_crate_recommended_versions_version_id
+            references versions
+            on delete cascade,
+    updated_by integer
+        constraint fk_crate_recommended_versions_updated_by
+            references users
+            on delete set null,
+    updated_at timestamp not null default now()
+);
+
+comment on table crate_recommended_versions is 'Versions that the crate owners recommend to users, e.g. the latest release of a long-term support line. The recommended version is independent of the highest version of the crate.';
+
+comment on column crate_recommended_versions.crate_id is 'Reference to the crate';
+comment on column crate_recommended_versions.version_id is 'Reference to the recommended version of the crate';
+comment on column crate_recommended_versions.updated_by is 'Reference to the user who last changed the recommended version';
+comment on column crate_recommended_versions.updated_at is 'Date and time when the recommended version was last changed';
+
+create index crate_recommended_versions_version_id_index
+    on crate_recommended_versions (version_id);
@@ -0,0 +1 @@
+drop table cdn_health_probes;
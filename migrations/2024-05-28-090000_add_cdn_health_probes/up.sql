@@ -0,0 +1,19 @@
+create table cdn_health_probes
+(
+    id        bigserial not null
+        constraint cdn_health_probes_pk
+            primary key,
+    probed_at timestamp not null default now(),
+    requests  integer   not null,
+    errors    integer   not null
+);
+
+comment on table cdn_health_probes is 'Results of the `probe_cdn_health` background job, which requests a file from the CDN to measure its error rate. Download redirects fall back to a secondary location while the recent probes show an elevated error rate.';
+
+comment on column cdn_health_probes.id is 'Unique identifier of the probe';
+comment on column cdn_health_probes.probed_at is 'Date and time when the probe was performed';
+comment on column cdn_health_probes.requests is 'Number of requests that were sent to the CDN';
+comment on column cdn_health_probes.errors is 'Number of requests that failed or returned an error status';
+
+create index cdn_health_probes_probed_at_index
+    on cdn_health_probes (probed_at);
@@ -0,0 +1,2 @@
+drop table excluded_crate_names;
+drop table blocked_routes;
@@ -0,0 +1,35 @@
+create table blocked_routes
+(
+    route      varchar   not null
+        constraint blocked_routes_pk
+            primary key,
+    created_by integer
+        constraint fk_blocked_routes_created_by
+            references users
+            on delete set null,
+    created_at timestamp not null default now()
+);
+
+comment on table blocked_routes is 'HTTP route patterns that are temporarily blocked by an administrator, e.g. during an incident. Requests to these routes are answered with a `503 Service Unavailable` response.';
+
+comment on column blocked_routes.route is 'Pattern of the blocked route, e.g. `/api/v1/crates/:crate_id/:version/download`';
+comment on column blocked_routes.created_by is 'Reference to the administrator who blocked the route';
+comment on column blocked_routes.created_at is 'Date and time when the route was blocked';
+
+create table excluded_crate_names
+(
+    name       varchar   not null
+        constraint excluded_crate_names_pk
+            primary key,
+    created_by integer
+        constraint fk_excluded_crate_names_created_by
+            references users
+            on delete set null,
+    created_at timestamp not null default now()
+);
+
+comment on table excluded_crate_names is 'Names of crates that are excluded from the most downloaded lists of the summary endpoint, e.g. because their downloads are inflated by automated systems.';
+
+comment on column excluded_crate_names.name is 'Exact name of the excluded crate';
+comment on column excluded_crate_names.created_by is 'Reference to the administrator who excluded the crate';
+comment on column excluded_crate_names.created_at is 'Date and time when the crate was excluded';
@@ -0,0 +1 @@
+drop table typosquat_flags;
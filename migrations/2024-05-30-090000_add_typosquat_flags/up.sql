@@ -0,0 +1,21 @@
+create table typosquat_flags
+(
+    id             serial primary key,
+    crate_id       integer   not null
+        constraint fk_typosquat_flags_crate_id
+            references crates
+            on delete cascade,
+    squatted_crate varchar   not null,
+    description    text      not null,
+    created_at     timestamp not null default now()
+);
+
+create index typosquat_flags_crate_id_index on typosquat_flags (crate_id);
+
+comment on table typosquat_flags is 'Possible typosquatting of popular crates by new crates, as detected by the `check_typosquat` background job.';
+
+comment on column typosquat_flags.id is 'Unique identifier of the flag';
+comment on column typosquat_flags.crate_id is 'Reference to the new crate that may be typosquatting another crate';
+comment on column typosquat_flags.squatted_crate is 'The name of the popular crate that may be typosquatted';
+comment on column typosquat_flags.description is 'A description of the check that was triggered';
+comment on column typosquat_flags.created_at is 'Date and time when the possible typosquatting was detected';
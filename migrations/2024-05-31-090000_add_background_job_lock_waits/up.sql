@@ -0,0 +1,18 @@
+create table background_job_lock_waits
+(
+    job_type     text             not null
+        constraint background_job_lock_waits_pk
+            primary key,
+    acquisitions bigint           not null default 0,
+    contentions  bigint           not null default 0,
+    wait_seconds double precision not null default 0,
+    updated_at   timestamp        not null default now()
+);
+
+comment on table background_job_lock_waits is 'Statistics of the advisory locks that keep background jobs with the same lock key, e.g. the index syncs of a crate, from running at the same time';
+
+comment on column background_job_lock_waits.job_type is 'Type of the jobs';
+comment on column background_job_lock_waits.acquisitions is 'Number of times a job of this type acquired its lock';
+comment on column background_job_lock_waits.contentions is 'Number of times a job of this type had to wait for another job that held the lock';
+comment on column background_job_lock_waits.wait_seconds is 'Total time in seconds that jobs of this type waited for their lock';
+comment on column background_job_lock_waits.updated_at is 'Date and time when a job of this type last acquired its lock';
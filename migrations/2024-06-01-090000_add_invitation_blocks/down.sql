@@ -0,0 +1,4 @@
+drop table invitation_blocks;
+
+alter table users
+    drop column block_unsolicited_invitations;
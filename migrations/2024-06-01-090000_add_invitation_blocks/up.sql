@@ -0,0 +1,25 @@
+alter table users
+    add column block_unsolicited_invitations boolean not null default false;
+
+comment on column users.block_unsolicited_invitations is 'Whether the user rejects ownership invitations from users that they do not share the ownership of a crate with.';
+
+create table invitation_blocks
+(
+    user_id         integer   not null
+        constraint fk_invitation_blocks_user_id
+            references users
+            on delete cascade,
+    blocked_user_id integer   not null
+        constraint fk_invitation_blocks_blocked_user_id
+            references users
+            on delete cascade,
+    created_at      timestamp not null default now(),
+    constraint invitation_blocks_pk
+        primary key (user_id, blocked_user_id)
+);
+
+comment on table invitation_blocks is 'Users whose crate ownership invitations are rejected by another user.';
+
+comment on column invitation_blocks.user_id is 'Reference to the user who blocks the invitations';
+comment on column invitation_blocks.blocked_user_id is 'Reference to the user whose invitations are blocked';
+comment on column invitation_blocks.created_at is 'Date and time when the user was blocked';
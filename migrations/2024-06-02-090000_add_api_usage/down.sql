@@ -0,0 +1,2 @@
+drop table api_token_usage;
+drop table user_api_usage;
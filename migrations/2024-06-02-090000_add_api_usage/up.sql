@@ -0,0 +1,35 @@
+create table user_api_usage
+(
+    user_id  integer not null
+        constraint fk_user_api_usage_user_id
+            references users
+            on delete cascade,
+    month    date    not null,
+    requests bigint  not null default 0,
+    constraint user_api_usage_pk
+        primary key (user_id, month)
+);
+
+comment on table user_api_usage is 'Approximate number of authenticated API requests of users per month, regardless of whether they were authenticated with a session cookie or an API token.';
+
+comment on column user_api_usage.user_id is 'Reference to the user who sent the requests';
+comment on column user_api_usage.month is 'First day of the month in which the requests were sent';
+comment on column user_api_usage.requests is 'Number of requests';
+
+create table api_token_usage
+(
+    api_token_id integer not null
+        constraint fk_api_token_usage_api_token_id
+            references api_tokens
+            on delete cascade,
+    month        date    not null,
+    requests     bigint  not null default 0,
+    constraint api_token_usage_pk
+        primary key (api_token_id, month)
+);
+
+comment on table api_token_usage is 'Approximate number of API requests that were authenticated with an API token per month.';
+
+comment on column api_token_usage.api_token_id is 'Reference to the API token that the requests were authenticated with';
+comment on column api_token_usage.month is 'First day of the month in which the requests were sent';
+comment on column api_token_usage.requests is 'Number of requests';
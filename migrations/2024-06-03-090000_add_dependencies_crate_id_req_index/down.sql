@@ -0,0 +1 @@
+drop index concurrently if exists dependencies_crate_id_req_version_id_index;
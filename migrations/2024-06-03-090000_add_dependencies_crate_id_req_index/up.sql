@@ -0,0 +1,4 @@
+-- Used by the `depends_on` filter of the crate search, which looks up the
+-- versions that depend on a crate with one of a set of version requirements.
+create index concurrently if not exists dependencies_crate_id_req_version_id_index
+    on dependencies (crate_id, req, version_id);
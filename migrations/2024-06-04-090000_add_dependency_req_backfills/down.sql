@@ -0,0 +1,2 @@
+drop table dependency_req_backfill_rows;
+drop table dependency_req_backfills;
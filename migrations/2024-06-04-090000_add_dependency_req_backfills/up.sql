@@ -0,0 +1,49 @@
+create table dependency_req_backfills
+(
+    id                 bigserial
+        constraint dependency_req_backfills_pk
+            primary key,
+    dry_run            boolean   not null,
+    last_dependency_id integer   not null default 0,
+    checked            bigint    not null default 0,
+    normalized         bigint    not null default 0,
+    irregular          bigint    not null default 0,
+    created_at         timestamp not null default now(),
+    finished_at        timestamp
+);
+
+comment on table dependency_req_backfills is 'Runs of the backfill that rewrites the version requirements of all dependencies in their canonical semver form.';
+
+comment on column dependency_req_backfills.id is 'Unique identifier of the backfill';
+comment on column dependency_req_backfills.dry_run is 'Whether the backfill only reports the requirements that would be changed, without changing them';
+comment on column dependency_req_backfills.last_dependency_id is 'Checkpoint of the backfill: the ID of the last dependency that was processed';
+comment on column dependency_req_backfills.checked is 'Number of dependencies that were processed';
+comment on column dependency_req_backfills.normalized is 'Number of dependencies whose requirement was not in its canonical form';
+comment on column dependency_req_backfills.irregular is 'Number of dependencies whose requirement could not be parsed';
+comment on column dependency_req_backfills.created_at is 'Date and time when the backfill was started';
+comment on column dependency_req_backfills.finished_at is 'Date and time when the backfill processed the last dependency';
+
+create table dependency_req_backfill_rows
+(
+    backfill_id    bigint  not null
+        constraint fk_dependency_req_backfill_rows_backfill_id
+            references dependency_req_backfills
+            on delete cascade,
+    dependency_id  integer not null
+        constraint fk_dependency_req_backfill_rows_dependency_id
+            references dependencies
+            on delete cascade,
+    original_req   varchar not null,
+    normalized_req varchar,
+    error          varchar,
+    constraint dependency_req_backfill_rows_pk
+        primary key (backfill_id, dependency_id)
+);
+
+comment on table dependency_req_backfill_rows is 'Dependencies whose version requirement was changed or could not be parsed by a backfill, which is the report of the backfill.';
+
+comment on column dependency_req_backfill_rows.backfill_id is 'Reference to the backfill';
+comment on column dependency_req_backfill_rows.dependency_id is 'Reference to the dependency';
+comment on column dependency_req_backfill_rows.original_req is 'Version requirement before the backfill';
+comment on column dependency_req_backfill_rows.normalized_req is 'Canonical form of the version requirement, or NULL if it could not be parsed';
+comment on column dependency_req_backfill_rows.error is 'Error of the semver parser if the version requirement could not be parsed';
@@ -0,0 +1,51 @@
+create table scheduled_releases
+(
+    id           serial
+        constraint scheduled_releases_pk
+            primary key,
+    version_id   integer
+        constraint fk_scheduled_releases_version_id
+            references versions
+            on delete set null,
+    crate_name   varchar   not null,
+    version_num  varchar   not null,
+    release_at   timestamp not null,
+    scheduled_by integer   not null
+        constraint fk_scheduled_releases_scheduled_by
+            references users,
+    scheduled_at timestamp not null default now(),
+    upload_id    varchar   not null,
+    readme_job   jsonb,
+    resolution   varchar,
+    resolved_by  integer
+        constraint fk_scheduled_releases_resolved_by
+            references users,
+    resolved_at  timestamp,
+    constraint scheduled_releases_resolution_check
+        check (resolution in ('released', 'cancelled')),
+    constraint scheduled_releases_resolved_check
+        check ((resolution is null) = (resolved_at is null))
+);
+
+comment on table scheduled_releases is 'Versions that were published with a release time. Until the release, the versions are hidden from the index and the API, and their crate files are kept in a private location. Resolved schedules are kept as an audit trail.';
+
+comment on column scheduled_releases.id is 'Unique identifier of the scheduled release';
+comment on column scheduled_releases.version_id is 'Reference to the scheduled version, or NULL if the version was deleted';
+comment on column scheduled_releases.crate_name is 'Name of the crate, which is kept after the version was deleted';
+comment on column scheduled_releases.version_num is 'Version number of the scheduled version, which is kept after the version was deleted';
+comment on column scheduled_releases.release_at is 'Date and time when the version is released';
+comment on column scheduled_releases.scheduled_by is 'Reference to the user who published the version';
+comment on column scheduled_releases.scheduled_at is 'Date and time when the version was published';
+comment on column scheduled_releases.upload_id is 'Random identifier of the private location of the crate file until the release';
+comment on column scheduled_releases.readme_job is 'The job that renders the README of the version, which is only enqueued on release';
+comment on column scheduled_releases.resolution is 'Outcome of the schedule (`released` or `cancelled`), or NULL while the release is pending';
+comment on column scheduled_releases.resolved_by is 'Reference to the owner who released the version early or cancelled the release, or NULL if the version was released at the scheduled time';
+comment on column scheduled_releases.resolved_at is 'Date and time when the version was released or the release was cancelled';
+
+create unique index scheduled_releases_pending_uindex
+    on scheduled_releases (version_id)
+    where resolved_at is null;
+
+create index scheduled_releases_release_at_index
+    on scheduled_releases (release_at)
+    where resolved_at is null;
@@ -0,0 +1,12 @@
+create table audit_export_outbox
+(
+    id         bigserial primary key,
+    event      jsonb     not null,
+    created_at timestamp not null default now()
+);
+
+comment on table audit_export_outbox is 'Audit events that were not exported to the external SIEM yet. The events are written in the transaction of the action that they record, so the events of rolled back actions are never exported.';
+
+comment on column audit_export_outbox.id is 'Unique identifier of the event, in the order in which the events were recorded';
+comment on column audit_export_outbox.event is 'JSON representation of the event';
+comment on column audit_export_outbox.created_at is 'Date and time at which the event was recorded';
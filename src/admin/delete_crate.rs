@@ -102,6 +102,11 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
         if let Err(error) = rt.block_on(store.delete_all_readmes(name)) {
             warn!(%name, ?error, "Failed to delete readme files from S3");
         }
+
+        info!(%name, "Deleting artifacts from S3");
+        if let Err(error) = rt.block_on(store.delete_all_artifacts(name)) {
+            warn!(%name, ?error, "Failed to delete artifacts from S3");
+        }
     }
 
     Ok(())
@@ -94,6 +94,11 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
             }
             Ok(_) => {}
         }
+
+        debug!(%crate_name, %version, "Deleting artifacts from S3");
+        if let Err(error) = rt.block_on(store.delete_artifacts(crate_name, version)) {
+            warn!(%crate_name, %version, ?error, "Failed to delete artifacts from S3");
+        }
     }
 
     Ok(())
@@ -23,21 +23,59 @@ pub enum Command {
         target_name: String,
     },
     DailyDbMaintenance,
+    DataRetention,
+    /// Delete the data exports whose download links have expired
+    DeleteExpiredDataExports,
+    /// Look for unusual numbers of crate downloads on the previous day
+    DetectDownloadSpikes,
+    /// Look for unusual API token activity of the last hour
+    DetectTokenAnomalies,
+    CleanupExpiredInvitations,
+    ExpireCrateNameReservations,
+    /// Send the queued CloudFront invalidations
+    FlushCloudFrontInvalidations,
+    /// Measure the error rate of the CDN for the download fallback
+    ProbeCdnHealth,
+    /// Release the versions whose scheduled release time has passed
+    ReleaseScheduledVersions,
     SquashIndex,
     NormalizeIndex {
         #[arg(long = "dry-run")]
         dry_run: bool,
     },
+    /// Rewrite the version requirements of all dependencies in their
+    /// canonical form, and report the ones that can't be parsed
+    NormalizeDependencyReqs {
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+    },
     CheckTyposquat {
         #[arg()]
         name: String,
     },
     ProcessCdnLogQueue(jobs::ProcessCdnLogQueue),
+    SendFollowDigest,
+    /// Record the current number of dependents of every crate
+    SnapshotDependentsCounts,
+    /// Wipe the staging registry and seed it with the sandbox fixtures
+    ResetSandbox,
     SyncAdmins {
         /// Force a sync even if one is already in progress
         #[arg(long)]
         force: bool,
     },
+    /// Update the storage object tags of the files of a crate version
+    UpdateStorageTags {
+        #[arg()]
+        name: String,
+        #[arg()]
+        version: String,
+    },
+    /// Record the weekly downloads and publishes per keyword and category
+    UpdateTrendingStats,
+    /// Record the registry-wide totals, publishes and new users of the
+    /// previous day
+    UpdateSiteStats,
 }
 
 pub fn run(command: Command) -> Result<()> {
@@ -91,18 +129,66 @@ pub fn run(command: Command) -> Result<()> {
 
             jobs::SyncAdmins.enqueue(conn)?;
         }
+        Command::ResetSandbox => {
+            jobs::ResetSandbox.enqueue(conn)?;
+        }
+        Command::SendFollowDigest => {
+            jobs::SendFollowDigest.enqueue(conn)?;
+        }
+        Command::SnapshotDependentsCounts => {
+            jobs::SnapshotDependentsCounts.enqueue(conn)?;
+        }
         Command::DailyDbMaintenance => {
             jobs::DailyDbMaintenance.enqueue(conn)?;
         }
+        Command::DataRetention => {
+            jobs::DataRetention.enqueue(conn)?;
+        }
+        Command::DeleteExpiredDataExports => {
+            jobs::DeleteExpiredDataExports.enqueue(conn)?;
+        }
+        Command::DetectDownloadSpikes => {
+            jobs::DetectDownloadSpikes.enqueue(conn)?;
+        }
+        Command::DetectTokenAnomalies => {
+            jobs::DetectTokenAnomalies.enqueue(conn)?;
+        }
+        Command::CleanupExpiredInvitations => {
+            jobs::CleanupExpiredInvitations.enqueue(conn)?;
+        }
+        Command::ExpireCrateNameReservations => {
+            jobs::ExpireCrateNameReservations.enqueue(conn)?;
+        }
+        Command::FlushCloudFrontInvalidations => {
+            jobs::FlushCloudFrontInvalidations.enqueue(conn)?;
+        }
+        Command::ProbeCdnHealth => {
+            jobs::ProbeCdnHealth.enqueue(conn)?;
+        }
+        Command::ReleaseScheduledVersions => {
+            jobs::ReleaseScheduledVersions.enqueue(conn)?;
+        }
         Command::ProcessCdnLogQueue(job) => {
             job.enqueue(conn)?;
         }
         Command::SquashIndex => {
             jobs::SquashIndex.enqueue(conn)?;
         }
+        Command::UpdateStorageTags { name, version } => {
+            jobs::UpdateStorageTags::new(name, version).enqueue(conn)?;
+        }
+        Command::UpdateTrendingStats => {
+            jobs::UpdateTrendingStats.enqueue(conn)?;
+        }
+        Command::UpdateSiteStats => {
+            jobs::UpdateSiteStats.enqueue(conn)?;
+        }
         Command::NormalizeIndex { dry_run } => {
             jobs::NormalizeIndex::new(dry_run).enqueue(conn)?;
         }
+        Command::NormalizeDependencyReqs { dry_run } => {
+            jobs::NormalizeDependencyReqs::start(conn, dry_run)?.enqueue(conn)?;
+        }
         Command::CheckTyposquat { name } => {
             // The job will fail if the crate doesn't actually exist, so let's check that up front.
             if crates::table
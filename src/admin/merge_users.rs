@@ -0,0 +1,89 @@
+use crate::admin::dialoguer;
+use crate::db;
+use crate::models::User;
+use crate::schema::users;
+use crate::worker::jobs::{MergeReport, MergeUsers};
+use anyhow::{anyhow, bail, Context};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+#[command(
+    name = "merge-users",
+    about = "Merge one user account into another.",
+    long_about = "Merge one user account into another. The crate ownerships, followed crates \
+        and email address of the \"from\" user are transferred to the \"to\" user, the API \
+        tokens of the \"from\" user are revoked, and the \"from\" user is locked. The merge \
+        itself is performed by a background job."
+)]
+pub struct Opts {
+    /// GitHub login of the "from" user, or `id:<user id>` if the login is
+    /// used by multiple accounts
+    from_user: String,
+    /// GitHub login of the "to" user, or `id:<user id>` if the login is used
+    /// by multiple accounts
+    to_user: String,
+
+    /// Only print what would be changed, without enqueueing the merge.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Don't ask for confirmation: yes, we are sure. Best for scripting.
+    #[arg(short, long)]
+    yes: bool,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = &mut db::oneoff_connection().context("Failed to establish database connection")?;
+
+    let from = find_user(conn, &opts.from_user)?;
+    let to = find_user(conn, &opts.to_user)?;
+    if from.id == to.id {
+        bail!("Cannot merge user {} into itself", from.gh_login);
+    }
+
+    let report = MergeReport::new(conn, &from, &to)?;
+
+    println!(
+        "Merging user {} (id={}, github_id={}) into {} (id={}, github_id={}):",
+        from.gh_login, from.id, from.gh_id, to.gh_login, to.id, to.gh_id
+    );
+    println!();
+    print!("{report}");
+    println!();
+
+    if opts.dry_run {
+        println!("Dry run, nothing was changed.");
+        return Ok(());
+    }
+
+    if !opts.yes && !dialoguer::confirm("Do you want to merge these accounts?") {
+        return Ok(());
+    }
+
+    MergeUsers::new(from.id, to.id).enqueue(conn)?;
+    println!("Enqueued the merge, both users will be notified by email once it is done.");
+
+    Ok(())
+}
+
+fn find_user(conn: &mut PgConnection, user: &str) -> anyhow::Result<User> {
+    if let Some(id) = user.strip_prefix("id:") {
+        let id = id
+            .parse()
+            .with_context(|| format!("Invalid user id: {id}"))?;
+        return User::find(conn, id).with_context(|| format!("Failed to find user {user}"));
+    }
+
+    let mut users: Vec<User> = users::table.filter(users::gh_login.eq(user)).load(conn)?;
+
+    match users.len() {
+        0 => Err(anyhow!("Failed to find user {user}")),
+        1 => Ok(users.remove(0)),
+        _ => {
+            let ids = users.iter().map(|user| format!("id:{}", user.id));
+            let ids = ids.collect::<Vec<_>>().join(", ");
+            bail!("There are multiple users with the login {user}, use one of: {ids}")
+        }
+    }
+}
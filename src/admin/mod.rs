@@ -3,6 +3,7 @@ pub mod delete_version;
 pub mod dialoguer;
 pub mod enqueue_job;
 pub mod git_import;
+pub mod merge_users;
 pub mod migrate;
 pub mod on_call;
 pub mod populate;
@@ -1,7 +1,8 @@
 use crate::{
-    db,
+    config, db,
     models::Version,
     schema::{crates, readme_renderings, versions},
+    util::image_proxy,
 };
 use anyhow::{anyhow, Context};
 use std::path::PathBuf;
@@ -9,7 +10,7 @@ use std::{io::Read, path::Path, sync::Arc, thread};
 
 use crate::storage::Storage;
 use chrono::{NaiveDateTime, Utc};
-use crates_io_markdown::text_to_html;
+use crates_io_markdown::{text_to_html, text_to_html_with_image_proxy};
 use crates_io_tarball::{Manifest, StringOrBool};
 use diesel::prelude::*;
 use flate2::read::GzDecoder;
@@ -40,8 +41,21 @@ pub struct Opts {
     crate_name: Option<String>,
 }
 
+/// The signing key and the domain of the image proxy that the images of the
+/// rendered READMEs are served through.
+#[derive(Clone)]
+struct ImageProxyConfig {
+    key: cookie::Key,
+    domain_name: String,
+}
+
 pub fn run(opts: Opts) -> anyhow::Result<()> {
     let storage = Arc::new(Storage::from_environment());
+    let config = config::Server::from_environment()?;
+    let image_proxy = config.readme_image_proxy.then(|| ImageProxyConfig {
+        key: config.session_key.clone(),
+        domain_name: config.domain_name.clone(),
+    });
     let conn = &mut db::oneoff_connection()?;
 
     let start_time = Utc::now();
@@ -109,9 +123,10 @@ pub fn run(opts: Opts) -> anyhow::Result<()> {
 
             let client = client.clone();
             let storage = storage.clone();
+            let image_proxy = image_proxy.clone();
             let handle = thread::spawn::<_, anyhow::Result<()>>(move || {
                 println!("[{}-{}] Rendering README...", krate_name, version.num);
-                let readme = get_readme(&storage, &client, &version, &krate_name)?;
+                let readme = get_readme(&storage, &client, &version, &krate_name, image_proxy)?;
                 if !readme.is_empty() {
                     let rt = tokio::runtime::Builder::new_current_thread()
                         .enable_all()
@@ -144,6 +159,7 @@ fn get_readme(
     client: &Client,
     version: &Version,
     krate_name: &str,
+    image_proxy: Option<ImageProxyConfig>,
 ) -> anyhow::Result<String> {
     let pkg_name = format!("{}-{}", krate_name, version.num);
 
@@ -166,10 +182,14 @@ fn get_readme(
 
     let reader = GzDecoder::new(response);
     let archive = Archive::new(reader);
-    render_pkg_readme(archive, &pkg_name)
+    render_pkg_readme(archive, &pkg_name, image_proxy)
 }
 
-fn render_pkg_readme<R: Read>(mut archive: Archive<R>, pkg_name: &str) -> anyhow::Result<String> {
+fn render_pkg_readme<R: Read>(
+    mut archive: Archive<R>,
+    pkg_name: &str,
+    image_proxy: Option<ImageProxyConfig>,
+) -> anyhow::Result<String> {
     let mut entries = archive.entries().context("Invalid tar archive entries")?;
 
     let manifest: Manifest = {
@@ -211,7 +231,16 @@ fn render_pkg_readme<R: Read>(mut archive: Archive<R>, pkg_name: &str) -> anyhow
             .and_then(|r| r.as_ref().as_local())
             .map(|s| s.as_str());
 
-        text_to_html(&contents, &readme_path, repository, pkg_path_in_vcs)
+        match image_proxy {
+            Some(ImageProxyConfig { key, domain_name }) => text_to_html_with_image_proxy(
+                &contents,
+                &readme_path,
+                repository,
+                pkg_path_in_vcs,
+                move |url: &str| image_proxy::proxy_url(&key, &domain_name, url),
+            ),
+            None => text_to_html(&contents, &readme_path, repository, pkg_path_in_vcs),
+        }
     };
     Ok(rendered)
 }
@@ -240,7 +269,7 @@ fn find_file_by_path<R: Read>(
 pub mod tests {
     use crates_io_tarball::TarballBuilder;
 
-    use super::render_pkg_readme;
+    use super::{render_pkg_readme, ImageProxyConfig};
 
     #[test]
     fn test_render_pkg_readme() {
@@ -258,7 +287,7 @@ readme = "README.md"
             .build_unzipped();
 
         let result =
-            render_pkg_readme(tar::Archive::new(&*serialized_archive), "foo-0.0.1").unwrap();
+            render_pkg_readme(tar::Archive::new(&*serialized_archive), "foo-0.0.1", None).unwrap();
         assert!(result.contains("readme"))
     }
 
@@ -275,7 +304,8 @@ readme = "README.md"
 
         assert_err!(render_pkg_readme(
             tar::Archive::new(&*serialized_archive),
-            "foo-0.0.1"
+            "foo-0.0.1",
+            None
         ));
     }
 
@@ -294,7 +324,7 @@ version = "0.0.1"
             .build_unzipped();
 
         let result =
-            render_pkg_readme(tar::Archive::new(&*serialized_archive), "foo-0.0.1").unwrap();
+            render_pkg_readme(tar::Archive::new(&*serialized_archive), "foo-0.0.1", None).unwrap();
         assert!(result.contains("readme"))
     }
 
@@ -315,7 +345,7 @@ repository = "https://github.com/foo/foo"
             .build_unzipped();
 
         let result =
-            render_pkg_readme(tar::Archive::new(&*serialized_archive), "foo-0.0.1").unwrap();
+            render_pkg_readme(tar::Archive::new(&*serialized_archive), "foo-0.0.1", None).unwrap();
         assert!(result.contains("\"https://github.com/foo/foo/blob/HEAD/./Other.md\""))
     }
 
@@ -339,8 +369,40 @@ repository = "https://github.com/foo/foo"
             .build_unzipped();
 
         let result =
-            render_pkg_readme(tar::Archive::new(&*serialized_archive), "foo-0.0.1").unwrap();
+            render_pkg_readme(tar::Archive::new(&*serialized_archive), "foo-0.0.1", None).unwrap();
         assert!(result.contains("docs/readme"));
         assert!(result.contains("\"https://github.com/foo/foo/blob/HEAD/docs/./Other.md\""))
     }
+
+    #[test]
+    fn test_render_pkg_readme_w_image_proxy() {
+        let serialized_archive = TarballBuilder::new()
+            .add_file(
+                "foo-0.0.1/Cargo.toml",
+                br#"
+[package]
+name = "foo"
+version = "0.0.1"
+readme = "README.md"
+"#,
+            )
+            .add_file(
+                "foo-0.0.1/README.md",
+                b"![logo](http://example.com/logo.png)",
+            )
+            .build_unzipped();
+
+        let image_proxy = ImageProxyConfig {
+            key: cookie::Key::derive_from(&[0; 32]),
+            domain_name: "crates.io".into(),
+        };
+
+        let result = render_pkg_readme(
+            tar::Archive::new(&*serialized_archive),
+            "foo-0.0.1",
+            Some(image_proxy),
+        )
+        .unwrap();
+        assert!(result.contains("\"https://crates.io/api/v1/readme_images?url=http%3A%2F%2Fexample.com%2Flogo.png&amp;sig="));
+    }
 }
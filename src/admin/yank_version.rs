@@ -3,6 +3,7 @@ use crate::db;
 use crate::models::{Crate, Version};
 use crate::schema::versions;
 use crate::worker::jobs;
+use crates_io_worker::BackgroundJob;
 use diesel::prelude::*;
 
 #[derive(clap::Parser, Debug)]
@@ -58,6 +59,7 @@ fn yank(opts: Opts, conn: &mut PgConnection) -> anyhow::Result<()> {
         .execute(conn)?;
 
     jobs::enqueue_sync_to_index(&krate.name, conn)?;
+    jobs::UpdateStorageTags::new(&krate.name, &v.num).enqueue(conn)?;
 
     Ok(())
 }
@@ -0,0 +1,291 @@
+//! Monthly accounting of the authenticated API requests.
+//!
+//! Every authenticated request is counted for its user and, if it was
+//! authenticated with an API token, for the token. The counts are kept in
+//! memory and are periodically added to the `user_api_usage` and
+//! `api_token_usage` tables by [`ApiUsage::flush()`], so that counting a
+//! request does not need a database write.
+//!
+//! The counts are approximate: the totals of a server only include the
+//! requests to the other servers as of its last flush, and the counts that
+//! could not be flushed are dropped instead of being retried.
+//!
+//! Exceeding the quotas of the [`ApiQuotaConfig`] is not enforced yet, the
+//! responses only carry an `X-Api-Quota-Warning` header (see
+//! [`crate::middleware::api_quota`]).
+
+use crate::config::ApiQuotaConfig;
+use crate::schema::{api_token_usage, user_api_usage};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::mem;
+
+/// Number of rows that are written with a single query, which keeps the
+/// number of bind parameters below the limit of PostgreSQL.
+const FLUSH_BATCH_SIZE: usize = 10_000;
+
+#[derive(Debug)]
+pub struct ApiUsage {
+    config: ApiQuotaConfig,
+    store: Mutex<Store>,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    /// The month of the totals in `flushed`.
+    month: Option<NaiveDate>,
+    users: Counters,
+    tokens: Counters,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    /// The requests that were not written to the database yet, by month and
+    /// user or token ID.
+    pending: HashMap<(NaiveDate, i32), u64>,
+    /// The totals of the current month as of the last flush.
+    flushed: HashMap<i32, u64>,
+}
+
+impl Counters {
+    fn record(&mut self, month: NaiveDate, id: i32) -> u64 {
+        let pending = self.pending.entry((month, id)).or_default();
+        *pending += 1;
+        *pending + self.flushed.get(&id).copied().unwrap_or_default()
+    }
+
+    fn pending(&self, month: NaiveDate, id: i32) -> u64 {
+        self.pending.get(&(month, id)).copied().unwrap_or_default()
+    }
+}
+
+impl ApiUsage {
+    pub fn new(config: ApiQuotaConfig) -> Self {
+        Self {
+            config,
+            store: Mutex::new(Store::default()),
+        }
+    }
+
+    pub fn config(&self) -> &ApiQuotaConfig {
+        &self.config
+    }
+
+    /// Counts a request of the user, made with the API token if given.
+    ///
+    /// Returns a warning if the approximate total of the month exceeds the
+    /// quota of the user or the token.
+    pub fn record(
+        &self,
+        user_id: i32,
+        api_token_id: Option<i32>,
+        now: NaiveDateTime,
+    ) -> Option<String> {
+        let month = month_of(now);
+
+        let mut store = self.store.lock();
+        if store.month != Some(month) {
+            store.month = Some(month);
+            store.users.flushed.clear();
+            store.tokens.flushed.clear();
+        }
+
+        let user_requests = store.users.record(month, user_id);
+        let token_requests = api_token_id.map(|id| store.tokens.record(month, id));
+        drop(store);
+
+        if let Some(quota) = self.config.user_monthly_requests {
+            if user_requests > quota {
+                return Some(format!(
+                    "the monthly quota of {quota} requests per user is exceeded"
+                ));
+            }
+        }
+
+        if let (Some(quota), Some(requests)) = (self.config.token_monthly_requests, token_requests)
+        {
+            if requests > quota {
+                return Some(format!(
+                    "the monthly quota of {quota} requests per API token is exceeded"
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Returns the requests of the user in the month that were counted by
+    /// this server but not written to the database yet.
+    pub fn pending_user_requests(&self, month: NaiveDate, user_id: i32) -> u64 {
+        self.store.lock().users.pending(month, user_id)
+    }
+
+    /// Returns the requests with the API token in the month that were
+    /// counted by this server but not written to the database yet.
+    pub fn pending_token_requests(&self, month: NaiveDate, api_token_id: i32) -> u64 {
+        self.store.lock().tokens.pending(month, api_token_id)
+    }
+
+    /// Adds the pending counts to the database and refreshes the totals of
+    /// the current month.
+    pub fn flush(&self, conn: &mut PgConnection) {
+        let (users, tokens) = {
+            let mut store = self.store.lock();
+            let users = mem::take(&mut store.users.pending);
+            let tokens = mem::take(&mut store.tokens.pending);
+            (users, tokens)
+        };
+
+        match flush_user_usage(conn, &users) {
+            Ok(totals) => self.update_totals(totals, |store| &mut store.users),
+            Err(error) => warn!(?error, "Failed to flush the API usage of users"),
+        }
+
+        match flush_token_usage(conn, &tokens) {
+            Ok(totals) => self.update_totals(totals, |store| &mut store.tokens),
+            Err(error) => warn!(?error, "Failed to flush the API usage of API tokens"),
+        }
+    }
+
+    fn update_totals(
+        &self,
+        totals: Vec<(i32, NaiveDate, i64)>,
+        counters: impl Fn(&mut Store) -> &mut Counters,
+    ) {
+        let mut store = self.store.lock();
+        let month = store.month;
+        let counters = counters(&mut store);
+        for (id, row_month, requests) in totals {
+            if Some(row_month) == month {
+                counters.flushed.insert(id, requests as u64);
+            }
+        }
+    }
+}
+
+/// Returns the first day of the month of `now`.
+pub fn month_of(now: NaiveDateTime) -> NaiveDate {
+    let date = now.date();
+    date.with_day(1).unwrap_or(date)
+}
+
+fn flush_user_usage(
+    conn: &mut PgConnection,
+    pending: &HashMap<(NaiveDate, i32), u64>,
+) -> QueryResult<Vec<(i32, NaiveDate, i64)>> {
+    let rows = pending
+        .iter()
+        .map(|(&(month, user_id), &requests)| {
+            (
+                user_api_usage::user_id.eq(user_id),
+                user_api_usage::month.eq(month),
+                user_api_usage::requests.eq(requests as i64),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut totals = Vec::with_capacity(rows.len());
+    for rows in rows.chunks(FLUSH_BATCH_SIZE) {
+        let batch: Vec<(i32, NaiveDate, i64)> = diesel::insert_into(user_api_usage::table)
+            .values(rows)
+            .on_conflict((user_api_usage::user_id, user_api_usage::month))
+            .do_update()
+            .set(
+                user_api_usage::requests
+                    .eq(user_api_usage::requests + excluded(user_api_usage::requests)),
+            )
+            .returning((
+                user_api_usage::user_id,
+                user_api_usage::month,
+                user_api_usage::requests,
+            ))
+            .get_results(conn)?;
+
+        totals.extend(batch);
+    }
+
+    Ok(totals)
+}
+
+fn flush_token_usage(
+    conn: &mut PgConnection,
+    pending: &HashMap<(NaiveDate, i32), u64>,
+) -> QueryResult<Vec<(i32, NaiveDate, i64)>> {
+    let rows = pending
+        .iter()
+        .map(|(&(month, api_token_id), &requests)| {
+            (
+                api_token_usage::api_token_id.eq(api_token_id),
+                api_token_usage::month.eq(month),
+                api_token_usage::requests.eq(requests as i64),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut totals = Vec::with_capacity(rows.len());
+    for rows in rows.chunks(FLUSH_BATCH_SIZE) {
+        let batch: Vec<(i32, NaiveDate, i64)> = diesel::insert_into(api_token_usage::table)
+            .values(rows)
+            .on_conflict((api_token_usage::api_token_id, api_token_usage::month))
+            .do_update()
+            .set(
+                api_token_usage::requests
+                    .eq(api_token_usage::requests + excluded(api_token_usage::requests)),
+            )
+            .returning((
+                api_token_usage::api_token_id,
+                api_token_usage::month,
+                api_token_usage::requests,
+            ))
+            .get_results(conn)?;
+
+        totals.extend(batch);
+    }
+
+    Ok(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+
+    fn at(date: &str) -> NaiveDateTime {
+        let date: NaiveDate = date.parse().unwrap();
+        date.and_time(NaiveTime::MIN)
+    }
+
+    #[test]
+    fn test_month_of() {
+        let month: NaiveDate = "2024-06-01".parse().unwrap();
+        assert_eq!(month_of(at("2024-06-01")), month);
+        assert_eq!(month_of(at("2024-06-30")), month);
+    }
+
+    #[test]
+    fn test_record_warns_above_quota() {
+        let usage = ApiUsage::new(ApiQuotaConfig {
+            user_monthly_requests: Some(3),
+            token_monthly_requests: Some(1),
+            ..Default::default()
+        });
+
+        let now = at("2024-06-15");
+        assert_none!(usage.record(1, Some(10), now));
+        assert_some!(usage.record(1, Some(10), now));
+        assert_none!(usage.record(1, None, now));
+        assert_some!(usage.record(1, None, now));
+
+        // Users and months are counted separately
+        assert_none!(usage.record(2, None, now));
+        assert_none!(usage.record(1, None, at("2024-07-01")));
+
+        let month = month_of(now);
+        assert_eq!(usage.pending_user_requests(month, 1), 4);
+        assert_eq!(usage.pending_token_requests(month, 10), 2);
+        assert_eq!(usage.pending_user_requests(month, 3), 0);
+    }
+}
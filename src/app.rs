@@ -1,21 +1,51 @@
 //! Application-wide components in a struct accessible from each request
 
-use crate::config;
-use crate::db::{connection_url, ConnectionConfig};
+use crate::api_usage::ApiUsage;
+use crate::audit::{AuditLog, SiemExporter};
+use crate::cdn_fallback::CdnCircuitBreaker;
+use crate::challenge::ChallengeProvider;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{self, ConfigChange, ReloadableConfig};
+use crate::controllers::readme_image::ProxiedImage;
+use crate::db::{connection_url, ConnectionConfig, DbConnection};
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::email::Emails;
+use crate::lookup_cache::{
+    CrateIdCache, LookupCache, QuarantinedVersionsCache, RuntimeSettingsCache, VersionIdCache,
+};
 use crate::metrics::{InstanceMetrics, ServiceMetrics};
+use crate::middleware::concurrency_limit::RouteConcurrencyLimits;
+use crate::rate_limiter::auth_failures::AuthFailureLimiter;
+use crate::rate_limiter::downloads::DownloadRateLimiter;
 use crate::rate_limiter::RateLimiter;
+use crate::safe_fetch::SafeFetchClient;
 use crate::storage::Storage;
+use crate::upstream::UpstreamRegistry;
 use axum::extract::{FromRef, FromRequestParts, State};
+use bytes::Bytes;
 use crates_io_github::GitHubClient;
 use deadpool_diesel::postgres::{Manager as DeadpoolManager, Pool as DeadpoolPool};
 use deadpool_diesel::Runtime;
 use oauth2::basic::BasicClient;
+use parking_lot::RwLock;
+use reqwest::Client;
 
-type DeadpoolResult = Result<deadpool_diesel::postgres::Connection, deadpool_diesel::PoolError>;
+type DeadpoolResult = Result<DbConnection, deadpool_diesel::PoolError>;
+
+/// The maximum number of files in the [`App::file_preview_cache`].
+const FILE_PREVIEW_CACHE_SIZE: u64 = 1000;
+/// The files of published versions never change, so they can be cached for
+/// a long time.
+const FILE_PREVIEW_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The maximum number of images in the [`App::readme_image_cache`].
+const README_IMAGE_CACHE_SIZE: u64 = 200;
+/// Images like badges are updated regularly, so they are only cached for a
+/// short time.
+const README_IMAGE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
 
 /// The `App` struct holds the main components of the application like
 /// the database connection pool and configurations
@@ -47,8 +77,68 @@ pub struct App {
     /// Metrics related to this specific instance of the service
     pub instance_metrics: InstanceMetrics,
 
+    /// Caches the ids of crates by their name.
+    pub crate_id_cache: CrateIdCache,
+
+    /// Caches the ids of versions by their crate name and version number.
+    pub version_id_cache: VersionIdCache,
+
+    /// Caches the set of versions that are quarantined pending a review.
+    pub quarantined_versions: QuarantinedVersionsCache,
+
+    /// Caches the settings that administrators can change at runtime.
+    pub runtime_settings: RuntimeSettingsCache,
+
+    /// Caches whether crate downloads fall back to another location because
+    /// the CDN is unavailable.
+    pub cdn_circuit_breaker: CdnCircuitBreaker,
+
+    /// Caches the files that were extracted from crate files for previews,
+    /// by their crate name, version number and path.
+    pub file_preview_cache: LookupCache<(String, String, String), Option<Bytes>>,
+
+    /// Caches the images that were fetched by the README image proxy, by
+    /// their URL.
+    pub readme_image_cache: LookupCache<String, ProxiedImage>,
+
+    /// Counts the authenticated API requests per user and API token.
+    pub api_usage: ApiUsage,
+
+    /// The sinks of the audit events, see [`crate::audit`]
+    pub audit_log: AuditLog,
+
+    /// Sends the audit events of the outbox to the external SIEM, if
+    /// configured
+    pub audit_exporter: Option<Arc<SiemExporter>>,
+
     /// Rate limit select actions.
     pub rate_limiter: RateLimiter,
+
+    /// Rate limit anonymous downloads by client IP address.
+    pub download_rate_limiter: Option<DownloadRateLimiter>,
+
+    /// Slow down and block clients with many failed authentication attempts.
+    pub auth_failure_limiter: AuthFailureLimiter,
+
+    /// Limit the in-flight requests of expensive routes.
+    pub route_concurrency_limits: RouteConcurrencyLimits,
+
+    /// Verifies the challenges of suspicious authentication flows, if enabled.
+    pub challenge_provider: Option<Box<dyn ChallengeProvider>>,
+
+    /// The upstream registry whose crates are served through this registry,
+    /// if the upstream-proxy mode is enabled.
+    pub upstream: Option<Box<dyn UpstreamRegistry + Send + Sync>>,
+
+    /// HTTP client for requests to user-supplied URLs.
+    pub safe_fetch: SafeFetchClient,
+
+    /// The source of the current time for expiry and rate limit checks.
+    pub clock: Arc<dyn Clock>,
+
+    /// The current values of the settings that can be reloaded without a
+    /// restart, see [`App::reload_config()`].
+    reloadable_config: RwLock<Arc<ReloadableConfig>>,
 }
 
 impl App {
@@ -65,6 +155,24 @@ impl App {
         let instance_metrics =
             InstanceMetrics::new().expect("could not initialize instance metrics");
 
+        let crate_id_cache = CrateIdCache::new(&config, &instance_metrics);
+        let version_id_cache = VersionIdCache::new(&config, &instance_metrics);
+        let quarantined_versions = QuarantinedVersionsCache::new(&instance_metrics);
+        let runtime_settings = RuntimeSettingsCache::new(&instance_metrics);
+        let cdn_circuit_breaker = CdnCircuitBreaker::new(&instance_metrics);
+        let file_preview_cache = LookupCache::new(
+            "file_preview",
+            FILE_PREVIEW_CACHE_SIZE,
+            FILE_PREVIEW_CACHE_TTL,
+            &instance_metrics,
+        );
+        let readme_image_cache = LookupCache::new(
+            "readme_image",
+            README_IMAGE_CACHE_SIZE,
+            README_IMAGE_CACHE_TTL,
+            &instance_metrics,
+        );
+
         let github_oauth = BasicClient::new(
             config.gh_client_id.clone(),
             Some(config.gh_client_secret.clone()),
@@ -78,7 +186,7 @@ impl App {
             use secrecy::ExposeSecret;
 
             let primary_db_connection_config = ConnectionConfig {
-                statement_timeout: config.db.statement_timeout,
+                statement_timeout: config.db.primary.statement_timeout,
                 read_only: config.db.primary.read_only_mode,
             };
 
@@ -98,7 +206,7 @@ impl App {
             use secrecy::ExposeSecret;
 
             let replica_db_connection_config = ConnectionConfig {
-                statement_timeout: config.db.statement_timeout,
+                statement_timeout: pool_config.statement_timeout,
                 read_only: pool_config.read_only_mode,
             };
 
@@ -118,6 +226,16 @@ impl App {
             None
         };
 
+        let rate_limiter = match &config.rate_limiter_redis_url {
+            Some(url) => {
+                use secrecy::ExposeSecret;
+
+                RateLimiter::with_redis(config.reloadable.rate_limiter.clone(), url.expose_secret())
+                    .expect("could not initialize rate limiter")
+            }
+            None => RateLimiter::new(config.reloadable.rate_limiter.clone()),
+        };
+
         App {
             primary_database,
             replica_database,
@@ -127,11 +245,94 @@ impl App {
             storage: Arc::new(Storage::from_config(&config.storage)),
             service_metrics: ServiceMetrics::new().expect("could not initialize service metrics"),
             instance_metrics,
-            rate_limiter: RateLimiter::new(config.rate_limiter.clone()),
+            crate_id_cache,
+            version_id_cache,
+            quarantined_versions,
+            runtime_settings,
+            cdn_circuit_breaker,
+            file_preview_cache,
+            readme_image_cache,
+            rate_limiter,
+            download_rate_limiter: config.download_rate_limiter.as_ref().map(|config| {
+                DownloadRateLimiter::from_config(config)
+                    .expect("could not initialize download rate limiter")
+            }),
+            auth_failure_limiter: AuthFailureLimiter::new(config.auth_failure_limiter.clone()),
+            api_usage: ApiUsage::new(config.api_quotas.clone()),
+            audit_log: AuditLog::from_config(config.audit_export.as_ref()),
+            audit_exporter: config
+                .audit_export
+                .clone()
+                .map(|config| Arc::new(SiemExporter::new(config, Client::new()))),
+            route_concurrency_limits: RouteConcurrencyLimits::from_config(
+                &config.route_concurrency_limits,
+            ),
+            challenge_provider: config
+                .challenge
+                .as_ref()
+                .map(|config| <dyn ChallengeProvider>::from_config(config, Client::new())),
+            upstream: config
+                .upstream
+                .as_ref()
+                .map(|config| <dyn UpstreamRegistry>::from_config(config, Client::new())),
+            safe_fetch: SafeFetchClient::new(config.safe_fetch.clone())
+                .expect("could not initialize safe fetch client"),
+            clock: Arc::new(SystemClock),
+            reloadable_config: RwLock::new(Arc::new(config.reloadable.clone())),
             config: Arc::new(config),
         }
     }
 
+    /// Returns the current values of the settings that can be reloaded
+    /// without a restart. These take precedence over the initial values in
+    /// [`config::Server::reloadable`].
+    pub fn reloadable_config(&self) -> Arc<ReloadableConfig> {
+        self.reloadable_config.read().clone()
+    }
+
+    /// Re-reads the [reloadable settings](ReloadableConfig) from the
+    /// environment, including the `.env` file, and applies them.
+    ///
+    /// This is triggered by a SIGHUP or the
+    /// `POST /api/private/admin/config/reload` endpoint.
+    pub fn reload_config(&self) -> anyhow::Result<Vec<ConfigChange>> {
+        // Values from the `.env` file are only loaded once by default.
+        let _ = dotenvy::dotenv_override();
+
+        let config = ReloadableConfig::from_env()?;
+        Ok(self.apply_config(config))
+    }
+
+    /// Swaps the [reloadable settings](ReloadableConfig) for the given ones,
+    /// logs the settings that changed and returns them.
+    pub fn apply_config(&self, config: ReloadableConfig) -> Vec<ConfigChange> {
+        let mut current = self.reloadable_config.write();
+
+        let changes = current.changes(&config);
+        for change in &changes {
+            info!(
+                setting = %change.setting,
+                old = %change.old,
+                new = %change.new,
+                "Configuration setting changed"
+            );
+        }
+
+        if current.rate_limiter != config.rate_limiter {
+            self.rate_limiter.set_config(config.rate_limiter.clone());
+        }
+
+        if current.log_filter != config.log_filter {
+            let filter = config.log_filter.as_deref();
+            if let Err(error) = crate::util::tracing::set_log_filter(filter) {
+                warn!(%error, "Failed to change the log filter");
+            }
+        }
+
+        *current = Arc::new(config);
+        changes
+    }
+
     /// A unique key to generate signed cookies
     pub fn session_key(&self) -> &cookie::Key {
         &self.config.session_key
@@ -140,7 +341,7 @@ impl App {
     /// Obtain a read/write database connection from the async primary pool
     #[instrument(skip_all)]
     pub async fn db_write(&self) -> DeadpoolResult {
-        self.primary_database.get().await
+        self.primary().await
     }
 
     /// Obtain a readonly database connection from the replica pool
@@ -150,10 +351,10 @@ impl App {
     pub async fn db_read(&self) -> DeadpoolResult {
         let Some(read_only_pool) = self.replica_database.as_ref() else {
             // Replica is disabled, but primary might be available
-            return self.primary_database.get().await;
+            return self.primary().await;
         };
 
-        match read_only_pool.get().await {
+        match self.replica(read_only_pool).await {
             // Replica is available
             Ok(connection) => Ok(connection),
 
@@ -166,7 +367,7 @@ impl App {
                     .map(|metric| metric.inc());
 
                 warn!("Replica is unavailable, falling back to primary ({error})");
-                self.primary_database.get().await
+                self.primary().await
             }
 
             // Replica failed
@@ -180,10 +381,10 @@ impl App {
     #[instrument(skip_all)]
     pub async fn db_read_prefer_primary(&self) -> DeadpoolResult {
         let Some(read_only_pool) = self.replica_database.as_ref() else {
-            return self.primary_database.get().await;
+            return self.primary().await;
         };
 
-        match self.primary_database.get().await {
+        match self.primary().await {
             // Primary is available
             Ok(connection) => Ok(connection),
 
@@ -196,13 +397,52 @@ impl App {
                     .map(|metric| metric.inc());
 
                 warn!("Primary is unavailable, falling back to replica ({error})");
-                read_only_pool.get().await
+                self.replica(read_only_pool).await
             }
 
             // Primary failed
             Err(error) => Err(error),
         }
     }
+
+    async fn primary(&self) -> DeadpoolResult {
+        let conn = self.primary_database.get().await?;
+        let statement_timeout = self.config.db.primary.statement_timeout;
+        Ok(self.instrument(conn, "primary", statement_timeout))
+    }
+
+    async fn replica(&self, pool: &DeadpoolPool) -> DeadpoolResult {
+        let conn = pool.get().await?;
+        let statement_timeout = self
+            .config
+            .db
+            .replica
+            .as_ref()
+            .map_or(self.config.db.primary.statement_timeout, |replica| {
+                replica.statement_timeout
+            });
+        Ok(self.instrument(conn, "follower", statement_timeout))
+    }
+
+    fn instrument(
+        &self,
+        conn: deadpool_diesel::postgres::Connection,
+        pool: &'static str,
+        statement_timeout: Duration,
+    ) -> DbConnection {
+        let interact_duration = self
+            .instance_metrics
+            .database_interact_duration
+            .with_label_values(&[pool]);
+
+        DbConnection::new(
+            conn,
+            pool,
+            statement_timeout,
+            self.config.db.slow_query_threshold,
+            interact_duration,
+        )
+    }
 }
 
 #[derive(Clone, FromRequestParts)]
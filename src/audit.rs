@@ -0,0 +1,193 @@
+//! Recording of the audit events, like publishes, owner changes, new API
+//! tokens and admin actions.
+//!
+//! The events are passed to every [`AuditSink`] of the [`AuditLog`] of the
+//! application. The [`DatabaseSink`] is always part of it and writes the
+//! events to the tables that the rest of the application reads, like the
+//! ownership history of a crate. If an export is configured (see
+//! [`AuditExportConfig`]), the events are additionally written to an outbox
+//! by the [`OutboxSink`], from which the [`SiemExporter`] of the server
+//! streams them to an external SIEM. The background worker uses the same
+//! audit log, so that the owner changes of jobs like the merging of user
+//! accounts are exported too.
+
+mod export;
+
+pub use self::export::{ExportedEvent, OutboxSink, SiemExporter};
+
+use crate::config::AuditExportConfig;
+use crate::models::{insert_version_owner_action, NewCrateOwnerAction, VersionAction};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// A version was published, yanked or unyanked.
+    VersionAction {
+        version_id: i32,
+        user_id: i32,
+        api_token_id: Option<i32>,
+        action: VersionAction,
+        ip: Option<IpAddr>,
+        reason: Option<String>,
+    },
+    /// An owner was added to or removed from a crate.
+    OwnerAction(NewCrateOwnerAction),
+    /// A user created an API token.
+    TokenCreated {
+        api_token_id: i32,
+        user_id: i32,
+        name: String,
+        crate_scopes: Vec<String>,
+        endpoint_scopes: Vec<String>,
+        expired_at: Option<NaiveDateTime>,
+    },
+    /// An administrator used one of the admin endpoints, e.g. to quarantine
+    /// a version. The `subject` is what the action was applied to.
+    AdminAction {
+        user_id: i32,
+        action: &'static str,
+        subject: String,
+    },
+}
+
+pub trait AuditSink: Send + Sync {
+    /// Records the event.
+    ///
+    /// This is called within the transaction of the action, so an error
+    /// aborts the action. Since the transaction can still be rolled back
+    /// afterwards, sinks must not pass the event to external services right
+    /// away, but write it to the database like the [`OutboxSink`].
+    fn record(&self, conn: &mut PgConnection, event: &AuditEvent) -> QueryResult<()>;
+}
+
+/// Writes the version and owner actions to the `version_owner_actions` and
+/// `crate_owner_actions` tables.
+///
+/// New API tokens and admin actions are not written, since the rows they
+/// create, like the API token or the quarantine of a version, already record
+/// who performed them.
+pub struct DatabaseSink;
+
+impl AuditSink for DatabaseSink {
+    fn record(&self, conn: &mut PgConnection, event: &AuditEvent) -> QueryResult<()> {
+        match event {
+            AuditEvent::VersionAction {
+                version_id,
+                user_id,
+                api_token_id,
+                action,
+                ip,
+                reason,
+            } => {
+                insert_version_owner_action(
+                    conn,
+                    *version_id,
+                    *user_id,
+                    *api_token_id,
+                    *action,
+                    *ip,
+                    reason.as_deref(),
+                )?;
+            }
+            AuditEvent::OwnerAction(action) => action.insert(conn)?,
+            AuditEvent::TokenCreated { .. } | AuditEvent::AdminAction { .. } => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// The sinks to which the audit events of the application are passed.
+///
+/// The default audit log only contains the [`DatabaseSink`].
+pub struct AuditLog {
+    sinks: Vec<Arc<dyn AuditSink>>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self {
+            sinks: vec![Arc::new(DatabaseSink)],
+        }
+    }
+}
+
+impl AuditLog {
+    /// Creates the audit log with the [`DatabaseSink`] and, if an export is
+    /// configured, the [`OutboxSink`].
+    pub fn from_config(config: Option<&AuditExportConfig>) -> Self {
+        let audit_log = Self::default();
+        match config {
+            Some(_) => audit_log.with_sink(Arc::new(OutboxSink)),
+            None => audit_log,
+        }
+    }
+
+    /// Adds a sink to which the events are passed after the existing sinks.
+    pub fn with_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Passes the event to all sinks, stopping at the first error.
+    pub fn record(&self, conn: &mut PgConnection, event: AuditEvent) -> QueryResult<()> {
+        for sink in &self.sinks {
+            sink.record(conn, &event)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::Emails;
+    use crate::models::{CrateOwnerAction, NewCrate, NewUser, OwnerActionVia};
+    use crate::test_util::test_db_connection;
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct RecordingSink(Mutex<Vec<AuditEvent>>);
+
+    impl AuditSink for RecordingSink {
+        fn record(&self, _conn: &mut PgConnection, event: &AuditEvent) -> QueryResult<()> {
+            self.0.lock().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn events_are_passed_to_all_sinks() {
+        let (_test_db, conn) = &mut test_db_connection();
+        let user = NewUser::new(1, "foo", None, None, "access_token")
+            .create_or_update(None, &Emails::new_in_memory(), conn)
+            .unwrap();
+
+        let sink = Arc::new(RecordingSink::default());
+        let audit_log = AuditLog::default().with_sink(sink.clone());
+
+        let krate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create(conn, user.id, &audit_log)
+        .unwrap();
+
+        // The database sink wrote the ownership history
+        let actions = CrateOwnerAction::by_crate(conn, krate.id).unwrap();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].via, OwnerActionVia::Publish);
+
+        let events = sink.0.lock();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0],
+            AuditEvent::OwnerAction(NewCrateOwnerAction { owner_id, .. }) if owner_id == user.id
+        ));
+    }
+}
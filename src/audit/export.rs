@@ -0,0 +1,340 @@
+//! Export of the audit events to an external SIEM.
+//!
+//! The [`OutboxSink`] writes the events to the `audit_export_outbox` table in
+//! the transaction of the action that they record, so the events of actions
+//! that are rolled back are never exported. Since the table is shared, this
+//! also covers the events of the background worker.
+//!
+//! The [`SiemExporter`] of the server moves the events from the outbox into
+//! an in-memory buffer with [`SiemExporter::claim()`] and sends them in
+//! batches with [`SiemExporter::flush()`]. The server calls both periodically
+//! and on shutdown. Requests that fail are retried, and the events are kept in
+//! the buffer for the next flush if the endpoint stays unavailable. Only as
+//! many events are claimed as fit into the buffer, the rest stays in the
+//! outbox until there is room.
+//!
+//! To make the exported log tamper-evident, the events of a server process
+//! form a hash chain: every event carries the ID of the chain, its sequence
+//! number and the SHA-256 `hash` of the `previous_hash` followed by the JSON
+//! of the event without its `hash`. Removed, reordered or modified events
+//! therefore break the chain, and lost events leave a gap in the sequence
+//! numbers.
+
+use crate::audit::{AuditEvent, AuditSink};
+use crate::config::AuditExportConfig;
+use crate::schema::audit_export_outbox;
+use crate::util::retry::RetryPolicy;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use parking_lot::Mutex;
+use reqwest::Client;
+use secrecy::ExposeSecret;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+
+/// The `previous_hash` of the first event of a chain.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Writes the events to the `audit_export_outbox` table, from which the
+/// [`SiemExporter`] of the server exports them.
+pub struct OutboxSink;
+
+impl AuditSink for OutboxSink {
+    fn record(&self, conn: &mut PgConnection, event: &AuditEvent) -> QueryResult<()> {
+        let event = serde_json::to_value(event).expect("audit events can be serialized");
+
+        diesel::insert_into(audit_export_outbox::table)
+            .values(audit_export_outbox::event.eq(event))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedEvent {
+    #[serde(flatten)]
+    entry: Entry,
+    hash: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Entry {
+    chain: String,
+    sequence: u64,
+    time: NaiveDateTime,
+    event: Value,
+    previous_hash: String,
+}
+
+pub struct SiemExporter {
+    config: AuditExportConfig,
+    client: Client,
+    chain: String,
+    state: Mutex<State>,
+}
+
+struct State {
+    sequence: u64,
+    previous_hash: String,
+    buffer: VecDeque<ExportedEvent>,
+}
+
+impl SiemExporter {
+    pub fn new(config: AuditExportConfig, client: Client) -> Self {
+        Self {
+            config,
+            client,
+            chain: hex::encode(rand::random::<[u8; 16]>()),
+            state: Mutex::new(State {
+                sequence: 0,
+                previous_hash: GENESIS_HASH.into(),
+                buffer: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Moves the oldest events of the outbox into the buffer, as many as it
+    /// has room for, and returns their number.
+    ///
+    /// The events are locked with `SKIP LOCKED`, so multiple server processes
+    /// can claim events at the same time without exporting them twice.
+    pub fn claim(&self, conn: &mut PgConnection) -> QueryResult<usize> {
+        let room = self.config.buffer_size.saturating_sub(self.pending());
+        if room == 0 {
+            return Ok(0);
+        }
+
+        let events = conn.transaction(|conn| {
+            let events: Vec<(i64, Value, NaiveDateTime)> = audit_export_outbox::table
+                .select((
+                    audit_export_outbox::id,
+                    audit_export_outbox::event,
+                    audit_export_outbox::created_at,
+                ))
+                .order(audit_export_outbox::id)
+                .limit(room as i64)
+                .for_update()
+                .skip_locked()
+                .load(conn)?;
+
+            let ids = events.iter().map(|(id, _, _)| *id).collect::<Vec<_>>();
+            diesel::delete(audit_export_outbox::table)
+                .filter(audit_export_outbox::id.eq_any(ids))
+                .execute(conn)?;
+
+            Ok::<_, diesel::result::Error>(events)
+        })?;
+
+        let claimed = events.len();
+        for (_, event, time) in events {
+            self.push(event, time);
+        }
+
+        Ok(claimed)
+    }
+
+    /// Appends the event to the hash chain and to the buffer.
+    fn push(&self, event: Value, time: NaiveDateTime) {
+        let mut state = self.state.lock();
+
+        let entry = Entry {
+            chain: self.chain.clone(),
+            sequence: state.sequence,
+            time,
+            event,
+            previous_hash: state.previous_hash.clone(),
+        };
+
+        let hash = hash(&entry);
+        state.sequence += 1;
+        state.previous_hash = hash.clone();
+
+        state.buffer.push_back(ExportedEvent { entry, hash });
+    }
+
+    /// Returns the number of claimed events that were not exported yet.
+    pub fn pending(&self) -> usize {
+        self.state.lock().buffer.len()
+    }
+
+    /// Sends the buffered events to the SIEM.
+    ///
+    /// If a batch can't be sent, it's put back into the buffer and the
+    /// remaining events are kept for the next flush.
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let mut retry_policy = RetryPolicy::default();
+        retry_policy.max_attempts = self.config.max_retries + 1;
+
+        loop {
+            let batch = {
+                let mut state = self.state.lock();
+                let len = state.buffer.len().min(self.config.batch_size);
+                state.buffer.drain(..len).collect::<Vec<_>>()
+            };
+
+            if batch.is_empty() {
+                return Ok(());
+            }
+
+            let result = retry_policy.run("audit_export", || self.send(&batch)).await;
+
+            if let Err(error) = result {
+                let mut state = self.state.lock();
+                for event in batch.into_iter().rev() {
+                    state.buffer.push_front(event);
+                }
+
+                return Err(error);
+            }
+        }
+    }
+
+    async fn send(&self, events: &[ExportedEvent]) -> anyhow::Result<()> {
+        let mut request = self
+            .client
+            .post(&self.config.url)
+            .json(&json!({ "events": events }));
+
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token.expose_secret());
+        }
+
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Returns the hex-encoded SHA-256 of the previous hash followed by the JSON
+/// of the entry.
+fn hash(entry: &Entry) -> String {
+    let json = serde_json::to_vec(entry).expect("audit events can be serialized");
+
+    let mut hasher = Sha256::new();
+    hasher.update(entry.previous_hash.as_bytes());
+    hasher.update(&json);
+    hex::encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{NewCrateOwnerAction, OwnerAction, OwnerActionVia, OwnerKind};
+    use crate::test_util::test_db_connection;
+    use chrono::Utc;
+    use std::time::Duration;
+
+    fn exporter(buffer_size: usize) -> SiemExporter {
+        let config = AuditExportConfig {
+            url: "https://siem.example.com/events".into(),
+            token: None,
+            buffer_size,
+            batch_size: 10,
+            flush_interval: Duration::from_secs(10),
+            max_retries: 0,
+        };
+
+        SiemExporter::new(config, Client::new())
+    }
+
+    fn event(owner_id: i32) -> AuditEvent {
+        AuditEvent::OwnerAction(NewCrateOwnerAction {
+            crate_id: 1,
+            owner_id,
+            owner_kind: OwnerKind::User,
+            actor_id: Some(1),
+            action: OwnerAction::Add,
+            via: OwnerActionVia::Direct,
+        })
+    }
+
+    fn push(exporter: &SiemExporter, event: &AuditEvent) {
+        let event = serde_json::to_value(event).unwrap();
+        exporter.push(event, Utc::now().naive_utc());
+    }
+
+    fn buffer(exporter: &SiemExporter) -> Vec<ExportedEvent> {
+        exporter.state.lock().buffer.iter().cloned().collect()
+    }
+
+    fn owner_ids(exporter: &SiemExporter) -> Vec<i64> {
+        buffer(exporter)
+            .iter()
+            .filter_map(|event| event.entry.event["owner_id"].as_i64())
+            .collect()
+    }
+
+    #[test]
+    fn events_form_a_hash_chain() {
+        let exporter = exporter(10);
+        for owner_id in 1..=3 {
+            push(&exporter, &event(owner_id));
+        }
+
+        let events = buffer(&exporter);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].entry.previous_hash, GENESIS_HASH);
+        for (sequence, event) in events.iter().enumerate() {
+            assert_eq!(event.entry.sequence, sequence as u64);
+            assert_eq!(event.hash, hash(&event.entry));
+        }
+        assert_eq!(events[1].entry.previous_hash, events[0].hash);
+        assert_eq!(events[2].entry.previous_hash, events[1].hash);
+
+        // Modifying an event changes its hash
+        let mut modified = events[1].entry.clone();
+        modified.event = serde_json::to_value(event(4)).unwrap();
+        assert_ne!(hash(&modified), events[1].hash);
+    }
+
+    #[test]
+    fn exported_event_format() {
+        let exporter = exporter(10);
+        push(&exporter, &event(2));
+
+        let json = serde_json::to_value(&buffer(&exporter)[0]).unwrap();
+        assert_eq!(json["sequence"], 0);
+        assert_eq!(json["event"]["type"], "owner_action");
+        assert_eq!(json["event"]["owner_id"], 2);
+        assert_eq!(json["event"]["via"], "direct");
+        assert!(json["hash"].is_string());
+    }
+
+    #[test]
+    fn events_of_rolled_back_actions_are_not_claimed() {
+        let (_test_db, conn) = &mut test_db_connection();
+
+        OutboxSink.record(conn, &event(1)).unwrap();
+        let _ = conn.transaction(|conn| {
+            OutboxSink.record(conn, &event(2))?;
+            Err::<(), _>(diesel::result::Error::RollbackTransaction)
+        });
+        OutboxSink.record(conn, &event(3)).unwrap();
+
+        let exporter = exporter(10);
+        assert_eq!(exporter.claim(conn).unwrap(), 2);
+        assert_eq!(owner_ids(&exporter), vec![1, 3]);
+
+        // Claimed events are removed from the outbox
+        assert_eq!(exporter.claim(conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn claim_is_limited_by_the_buffer_size() {
+        let (_test_db, conn) = &mut test_db_connection();
+        for owner_id in 1..=3 {
+            OutboxSink.record(conn, &event(owner_id)).unwrap();
+        }
+
+        let exporter = exporter(2);
+        assert_eq!(exporter.claim(conn).unwrap(), 2);
+        assert_eq!(exporter.claim(conn).unwrap(), 0);
+        assert_eq!(owner_ids(&exporter), vec![1, 2]);
+
+        // The remaining event stays in the outbox until there is room
+        exporter.state.lock().buffer.pop_front();
+        assert_eq!(exporter.claim(conn).unwrap(), 1);
+        assert_eq!(owner_ids(&exporter), vec![2, 3]);
+    }
+}
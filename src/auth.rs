@@ -1,15 +1,22 @@
 use crate::controllers;
 use crate::controllers::util::RequestPartsExt;
+use crate::middleware::api_quota::ApiQuotaWarning;
+use crate::middleware::app::RequestApp;
 use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::real_ip::RealIp;
+use crate::middleware::sentry_context::SentryUser;
 use crate::middleware::session::RequestSession;
 use crate::models::token::{CrateScope, EndpointScope};
 use crate::models::{ApiToken, User};
+use crate::rate_limiter::auth_failures::AuthFailureKey;
 use crate::util::errors::{
     account_locked, forbidden, internal, AppResult, InsecurelyGeneratedTokenRevoked,
+    TooManyAuthFailures,
 };
-use chrono::Utc;
+use chrono::NaiveDateTime;
 use diesel::PgConnection;
 use http::header;
+use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub struct AuthCheck {
@@ -96,6 +103,18 @@ impl AuthCheck {
         Ok(auth)
     }
 
+    /// Returns whether an already authenticated request would pass this
+    /// check, without failing the request if it does not.
+    pub fn allows(&self, auth: &Authentication) -> bool {
+        let Some(token) = auth.api_token() else {
+            return true;
+        };
+
+        self.allow_token
+            && self.endpoint_scope_matches(token.endpoint_scopes.as_ref())
+            && self.crate_scope_matches(token.crate_scopes.as_ref())
+    }
+
     fn endpoint_scope_matches(&self, token_scopes: Option<&Vec<EndpointScope>>) -> bool {
         match (&token_scopes, &self.endpoint_scope) {
             // The token is a legacy token.
@@ -185,12 +204,25 @@ fn authenticate_via_cookie<T: RequestPartsExt>(
 
     let user = User::find(conn, id).map_err(|err| {
         req.request_log().add("cause", err);
+        record_auth_failure(req);
         internal("user_id from cookie not found in database")
     })?;
 
-    ensure_not_locked(&user)?;
+    ensure_not_locked(&user, req.app().clock.naive_now())?;
+
+    // Sessions end when GitHub rejects the access token of the user, so that
+    // the user has to authorize crates.io on GitHub again.
+    if user.gh_token_revoked_at.is_some() {
+        req.session().remove("user_id");
+        req.request_log()
+            .add("cause", "GitHub access token was revoked");
+        return Err(forbidden(
+            "GitHub rejected your authorization of crates.io. Please sign in again.",
+        ));
+    }
 
     req.request_log().add("uid", id);
+    record_sentry_user(req, id);
 
     Ok(Some(CookieAuthentication { user }))
 }
@@ -209,12 +241,27 @@ fn authenticate_via_token<T: RequestPartsExt>(
         return Ok(None);
     };
 
-    let token = ApiToken::find_by_api_token(conn, header_value).map_err(|e| {
+    let metric = &req.app().instance_metrics.token_auth_header_forms_total;
+    let (form, token) = match parse_authorization_header(header_value) {
+        Ok(parsed) => parsed,
+        Err(detail) => {
+            metric.with_label_values(&["invalid"]).inc();
+            req.request_log()
+                .add("cause", "malformed authorization header");
+            return Err(forbidden(detail));
+        }
+    };
+
+    metric.with_label_values(&[form.as_str()]).inc();
+
+    let now = req.app().clock.naive_now();
+    let token = ApiToken::find_by_api_token(conn, token, now).map_err(|e| {
         if e.is::<InsecurelyGeneratedTokenRevoked>() {
             e
         } else {
             let cause = format!("invalid token caused by {e}");
             req.request_log().add("cause", cause);
+            record_auth_failure(req);
 
             forbidden("authentication failed")
         }
@@ -225,27 +272,97 @@ fn authenticate_via_token<T: RequestPartsExt>(
         internal("user_id from token not found in database")
     })?;
 
-    ensure_not_locked(&user)?;
+    ensure_not_locked(&user, now)?;
 
     req.request_log().add("uid", token.user_id);
     req.request_log().add("tokenid", token.id);
+    record_sentry_user(req, token.user_id);
 
     Ok(Some(TokenAuthentication { user, token }))
 }
 
+/// Records the authenticated user for the Sentry events of the request.
+fn record_sentry_user<T: RequestPartsExt>(req: &T, user_id: i32) {
+    if let Some(user) = req.extensions().get::<SentryUser>() {
+        user.set(user_id);
+    }
+}
+
+/// The form in which the API token was sent in the `Authorization` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenHeaderForm {
+    /// `Authorization: Bearer <token>`, as specified by RFC 6750.
+    Bearer,
+    /// `Authorization: <token>`, which cargo and most other clients send.
+    Bare,
+}
+
+impl TokenHeaderForm {
+    /// The label of the form in the `token_auth_header_forms_total` metric.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Bearer => "bearer",
+            Self::Bare => "bare",
+        }
+    }
+}
+
+/// Extracts the API token from the value of the `Authorization` header,
+/// which is either the bare token or the token with the `Bearer` scheme.
+fn parse_authorization_header(value: &str) -> Result<(TokenHeaderForm, &str), String> {
+    let value = value.trim();
+
+    let Some((scheme, token)) = value.split_once(char::is_whitespace) else {
+        if value.eq_ignore_ascii_case("bearer") {
+            return Err("the `Authorization` header is missing the token after `Bearer`".into());
+        }
+
+        return Ok((TokenHeaderForm::Bare, value));
+    };
+
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return Err(format!(
+            "unsupported authorization scheme `{scheme}`, expected `Bearer <token>`"
+        ));
+    }
+
+    let token = token.trim_start();
+    if token.contains(char::is_whitespace) {
+        return Err("malformed `Authorization` header, expected `Bearer <token>`".into());
+    }
+
+    Ok((TokenHeaderForm::Bearer, token))
+}
+
+/// Authenticates the request via cookie or API token, without checking the
+/// scopes of the token.
+///
+/// Most endpoints should use [`AuthCheck::check()`] instead.
 #[instrument(skip_all)]
-fn authenticate<T: RequestPartsExt>(req: &T, conn: &mut PgConnection) -> AppResult<Authentication> {
+pub fn authenticate<T: RequestPartsExt>(
+    req: &T,
+    conn: &mut PgConnection,
+) -> AppResult<Authentication> {
     controllers::util::verify_origin(req)?;
+    ensure_not_rate_limited(req)?;
 
     match authenticate_via_cookie(req, conn) {
         Ok(None) => {}
-        Ok(Some(auth)) => return Ok(Authentication::Cookie(auth)),
+        Ok(Some(auth)) => {
+            let auth = Authentication::Cookie(auth);
+            record_api_usage(req, &auth);
+            return Ok(auth);
+        }
         Err(err) => return Err(err),
     }
 
     match authenticate_via_token(req, conn) {
         Ok(None) => {}
-        Ok(Some(auth)) => return Ok(Authentication::Token(auth)),
+        Ok(Some(auth)) => {
+            let auth = Authentication::Token(auth);
+            record_api_usage(req, &auth);
+            return Ok(auth);
+        }
         Err(err) => return Err(err),
     }
 
@@ -256,11 +373,72 @@ fn authenticate<T: RequestPartsExt>(req: &T, conn: &mut PgConnection) -> AppResu
     return Err(forbidden("this action requires authentication"));
 }
 
-fn ensure_not_locked(user: &User) -> AppResult<()> {
+/// Counts the request towards the monthly API usage of the user and the
+/// token, and attaches a warning to the response if a quota is exceeded.
+fn record_api_usage<T: RequestPartsExt>(req: &T, auth: &Authentication) {
+    let app = req.app();
+    let now = app.clock.naive_now();
+    let Some(warning) = app
+        .api_usage
+        .record(auth.user_id(), auth.api_token_id(), now)
+    else {
+        return;
+    };
+
+    req.request_log().add("quota_warning", &warning);
+    if let Some(slot) = req.extensions().get::<ApiQuotaWarning>() {
+        slot.set(warning);
+    }
+}
+
+/// Returns the keys under which failed authentication attempts of the
+/// request are counted: the client IP address and, if the request contains
+/// an API token, the prefix of the token.
+fn auth_failure_keys<T: RequestPartsExt>(req: &T) -> Vec<AuthFailureKey> {
+    let real_ip = req.extensions().get::<RealIp>();
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|value| parse_authorization_header(value).ok())
+        .map(|(_, token)| token);
+
+    let ip_key = real_ip.map(|ip| AuthFailureKey::ip(**ip));
+    let token_key = token.map(AuthFailureKey::token_prefix);
+    ip_key.into_iter().chain(token_key).collect()
+}
+
+fn ensure_not_rate_limited<T: RequestPartsExt>(req: &T) -> AppResult<()> {
+    let app = req.app();
+    let keys = auth_failure_keys(req);
+
+    if let Err((key, retry_after)) = app.auth_failure_limiter.check(&keys, Instant::now()) {
+        req.request_log()
+            .add("cause", "too many failed authentication attempts");
+
+        app.instance_metrics
+            .auth_failures_rate_limited_total
+            .with_label_values(&[key.kind()])
+            .inc();
+
+        return Err(Box::new(TooManyAuthFailures { retry_after }));
+    }
+
+    Ok(())
+}
+
+fn record_auth_failure<T: RequestPartsExt>(req: &T) {
+    let app = req.app();
+    app.instance_metrics.auth_failures_total.inc();
+    app.auth_failure_limiter
+        .record_failure(&auth_failure_keys(req), Instant::now());
+}
+
+fn ensure_not_locked(user: &User, now: NaiveDateTime) -> AppResult<()> {
     if let Some(reason) = &user.account_lock_reason {
         let still_locked = user
             .account_lock_until
-            .map(|until| until > Utc::now().naive_utc())
+            .map(|until| until > now)
             .unwrap_or(true);
 
         if still_locked {
@@ -369,4 +547,33 @@ mod tests {
         assert!(!auth_check.crate_scope_matches(Some(&vec![cs("anyhow")])));
         assert!(!auth_check.crate_scope_matches(Some(&vec![cs("actix-*")])));
     }
+
+    #[test]
+    fn authorization_header_forms() {
+        use TokenHeaderForm::*;
+
+        let parse = parse_authorization_header;
+        assert_ok_eq!(parse("cio1234"), (Bare, "cio1234"));
+        assert_ok_eq!(parse(" cio1234 "), (Bare, "cio1234"));
+        assert_ok_eq!(parse("Bearer cio1234"), (Bearer, "cio1234"));
+        assert_ok_eq!(parse("bearer  cio1234"), (Bearer, "cio1234"));
+        assert_ok_eq!(parse("BEARER\tcio1234"), (Bearer, "cio1234"));
+
+        assert_err_eq!(
+            parse("Bearer"),
+            "the `Authorization` header is missing the token after `Bearer`"
+        );
+        assert_err_eq!(
+            parse("Bearer "),
+            "the `Authorization` header is missing the token after `Bearer`"
+        );
+        assert_err_eq!(
+            parse("Basic dXNlcjpwYXNz"),
+            "unsupported authorization scheme `Basic`, expected `Bearer <token>`"
+        );
+        assert_err_eq!(
+            parse("Bearer cio1234 cio5678"),
+            "malformed `Authorization` header, expected `Bearer <token>`"
+        );
+    }
 }
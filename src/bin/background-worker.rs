@@ -7,6 +7,10 @@
 //! the worker thread), we will rebuild the runner and try again up to 5 times.
 //! After the 5th occurrence, we will panic.
 //!
+//! On SIGINT or SIGTERM the workers stop picking up new jobs and the binary
+//! waits for the running jobs to finish. Jobs that are still running after
+//! the timeout are abandoned and retried by the next runner.
+//!
 //! Usage:
 //!      cargo run --bin background-worker
 
@@ -14,8 +18,10 @@
 extern crate tracing;
 
 use anyhow::Context;
+use crates_io::audit::AuditLog;
 use crates_io::cloudfront::CloudFront;
 use crates_io::fastly::Fastly;
+use crates_io::shutdown::SHUTDOWN_TIMEOUT;
 use crates_io::storage::Storage;
 use crates_io::team_repo::TeamRepoImpl;
 use crates_io::worker::{Environment, RunnerExt};
@@ -82,6 +88,8 @@ fn main() -> anyhow::Result<()> {
     let manager = DeadpoolManager::new(db_url, Runtime::Tokio1);
     let deadpool = DeadpoolPool::builder(manager).max_size(10).build().unwrap();
 
+    let audit_log = AuditLog::from_config(config.audit_export.as_ref());
+
     let environment = Environment::builder()
         .config(Arc::new(config))
         .repository_config(repository_config)
@@ -91,6 +99,7 @@ fn main() -> anyhow::Result<()> {
         .deadpool(deadpool.clone())
         .emails(emails)
         .team_repo(Box::new(team_repo))
+        .audit_log(audit_log)
         .build()?;
 
     let environment = Arc::new(environment);
@@ -114,8 +123,25 @@ fn main() -> anyhow::Result<()> {
         let handle = runner.start();
 
         info!("Runner booted, running jobs");
-        handle.wait_for_shutdown().await
+        crates_io::shutdown::signal().await;
+
+        info!("Shutting down, waiting for running jobs to finish…");
+        let abandoned = handle.shutdown(SHUTDOWN_TIMEOUT).await;
+        if !abandoned.is_empty() {
+            let jobs = abandoned
+                .iter()
+                .map(|job| format!("{} ({})", job.job_type, job.id))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            warn!(
+                "Abandoned {} running jobs, they will be retried: {jobs}",
+                abandoned.len()
+            );
+        }
     });
 
+    info!("Runner has shutdown!");
+
     Ok(())
 }
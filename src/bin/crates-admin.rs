@@ -2,8 +2,8 @@
 extern crate tracing;
 
 use crates_io::admin::{
-    delete_crate, delete_version, enqueue_job, git_import, migrate, populate, render_readmes,
-    test_pagerduty, transfer_crates, upload_index, verify_token, yank_version,
+    delete_crate, delete_version, enqueue_job, git_import, merge_users, migrate, populate,
+    render_readmes, test_pagerduty, transfer_crates, upload_index, verify_token, yank_version,
 };
 
 #[derive(clap::Parser, Debug)]
@@ -15,6 +15,7 @@ enum Command {
     RenderReadmes(render_readmes::Opts),
     TestPagerduty(test_pagerduty::Opts),
     TransferCrates(transfer_crates::Opts),
+    MergeUsers(merge_users::Opts),
     VerifyToken(verify_token::Opts),
     Migrate(migrate::Opts),
     UploadIndex(upload_index::Opts),
@@ -43,6 +44,7 @@ fn main() -> anyhow::Result<()> {
         Command::RenderReadmes(opts) => render_readmes::run(opts),
         Command::TestPagerduty(opts) => test_pagerduty::run(opts),
         Command::TransferCrates(opts) => transfer_crates::run(opts),
+        Command::MergeUsers(opts) => merge_users::run(opts),
         Command::VerifyToken(opts) => verify_token::run(opts),
         Command::Migrate(opts) => migrate::run(opts),
         Command::UploadIndex(opts) => upload_index::run(opts),
@@ -5,14 +5,13 @@ use crates_io::middleware::normalize_path::normalize_path;
 use crates_io::{metrics::LogEncoder, App, Emails};
 use std::{sync::Arc, time::Duration};
 
-use axum::ServiceExt;
 use crates_io_github::RealGitHubClient;
 use prometheus::Encoder;
 use reqwest::Client;
 use std::io::Write;
-use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
 use tower::Layer;
 
 const CORE_THREADS: usize = 4;
@@ -55,8 +54,6 @@ fn main() -> anyhow::Result<()> {
 
     let rt = builder.build().unwrap();
 
-    let make_service = axum_router.into_make_service_with_connect_info::<SocketAddr>();
-
     // Block the main thread until the server has shutdown
     rt.block_on(async {
         // Create a `TcpListener` using tokio.
@@ -64,38 +61,145 @@ fn main() -> anyhow::Result<()> {
 
         let addr = listener.local_addr()?;
 
-        // Do not change this line! Removing the line or changing its contents in any way will break
-        // the test suite :)
-        info!("Listening at http://{addr}");
+        let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+        tokio::spawn(async move {
+            crates_io::shutdown::signal().await;
+            info!("Shutting down, waiting for in-flight requests to finish…");
+            shutdown_sender.send_replace(true);
+        });
+
+        // Reload the settings that can be changed without a restart on SIGHUP.
+        let reload_app = app.clone();
+        tokio::spawn(async move {
+            let mut hangup =
+                signal(SignalKind::hangup()).expect("failed to install signal handler");
+            while hangup.recv().await.is_some() {
+                info!("Received SIGHUP, reloading the configuration…");
+                if let Err(error) = reload_app.reload_config() {
+                    error!(?error, "Failed to reload the configuration");
+                }
+            }
+        });
+
+        // Periodically write the API request counts to the database.
+        let usage_app = app.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(usage_app.config.api_quotas.flush_interval);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                flush_api_usage(usage_app.clone()).await;
+            }
+        });
+
+        // Periodically send the audit events of the outbox to the SIEM.
+        if let Some(config) = &app.config.audit_export {
+            let export_app = app.clone();
+            let mut interval = tokio::time::interval(config.flush_interval);
+            tokio::spawn(async move {
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    flush_audit_events(&export_app).await;
+                }
+            });
+        }
 
-        // Run the server with graceful shutdown
-        axum::serve(listener, make_service)
-            .with_graceful_shutdown(shutdown_signal())
-            .await
+        let http_config = &app.config.http;
+
+        // Run the server with graceful shutdown. Once the signal was received,
+        // no new connections are accepted and the server waits for the
+        // in-flight requests to finish, but only up to the configured grace
+        // period.
+        let server = async {
+            let tls = match &app.config.tls {
+                Some(tls) => Some(crates_io::tls::acceptor(tls, http_config.http2)?),
+                None => None,
+            };
+
+            // Do not change these lines! Removing the lines or changing their contents in any way
+            // will break the test suite :)
+            let scheme = if tls.is_some() { "https" } else { "http" };
+            info!("Listening at {scheme}://{addr}");
+
+            let shutdown = shutdown_receiver.clone();
+            crates_io::http_server::serve(listener, tls, axum_router, http_config, shutdown).await
+        };
+
+        let grace_period = http_config.shutdown_grace_period;
+        let mut shutdown = shutdown_receiver.clone();
+        let timeout = async move {
+            let _ = shutdown.wait_for(|requested| *requested).await;
+            tokio::time::sleep(grace_period).await;
+        };
+
+        let result = tokio::select! {
+            result = server => result,
+            _ = timeout => {
+                let abandoned = app.instance_metrics.requests_in_flight.get();
+                warn!(abandoned, "In-flight requests did not finish in time, abandoning them");
+                Ok(())
+            },
+        };
+
+        // Write the request counts of the last interval before exiting.
+        flush_api_usage(app.clone()).await;
+        flush_audit_events(&app).await;
+
+        result
     })?;
 
-    info!("Server has gracefully shutdown!");
+    info!("Server has shutdown!");
     Ok(())
 }
 
-async fn shutdown_signal() {
-    let interrupt = async {
-        signal(SignalKind::interrupt())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
+async fn flush_api_usage(app: Arc<App>) {
+    let conn = match app.db_write().await {
+        Ok(conn) => conn,
+        Err(error) => {
+            warn!(
+                ?error,
+                "Failed to get a database connection to flush the API usage"
+            );
+            return;
+        }
     };
 
-    let terminate = async {
-        signal(SignalKind::terminate())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
+    let flush_app = app.clone();
+    if let Err(error) = conn
+        .interact(move |conn| flush_app.api_usage.flush(conn))
+        .await
+    {
+        warn!(?error, "Failed to flush the API usage");
+    }
+}
+
+async fn flush_audit_events(app: &App) {
+    let Some(exporter) = app.audit_exporter.clone() else {
+        return;
     };
 
-    tokio::select! {
-        _ = interrupt => {},
-        _ = terminate => {},
+    match app.db_write().await {
+        Ok(conn) => {
+            let claim_exporter = exporter.clone();
+            let result = conn.interact(move |conn| claim_exporter.claim(conn)).await;
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(error)) => warn!(?error, "Failed to claim the audit events"),
+                Err(error) => warn!(?error, "Failed to claim the audit events"),
+            }
+        }
+        Err(error) => {
+            warn!(
+                ?error,
+                "Failed to get a database connection to claim the audit events"
+            );
+        }
+    }
+
+    if let Err(error) = exporter.flush().await {
+        let pending = exporter.pending();
+        warn!(?error, pending, "Failed to export the audit events");
     }
 }
 
@@ -0,0 +1,128 @@
+//! Fallback of crate downloads during CDN incidents
+//!
+//! The `probe_cdn_health` background job periodically requests a file from
+//! the CDN and records the share of failed requests in the
+//! `cdn_health_probes` table. While the recent probes show an elevated error
+//! rate, the circuit breaker is open and downloads are redirected to the
+//! secondary CDN, or to presigned URLs of the storage backend if no secondary
+//! CDN is configured. Once the CDN is healthy again for a few probes in a
+//! row, downloads are redirected to the CDN again.
+
+use crate::app::AppState;
+use crate::lookup_cache::LookupCache;
+use crate::metrics::InstanceMetrics;
+use crate::models::CdnHealthProbe;
+use crate::util::errors::AppResult;
+use diesel::prelude::*;
+use prometheus::IntGauge;
+use std::time::Duration;
+
+/// How long the state of the circuit breaker is cached before the probes are
+/// checked again.
+const STATE_TTL: Duration = Duration::from_secs(30);
+
+/// Caches whether the CDN is considered unavailable.
+pub struct CdnCircuitBreaker {
+    state: LookupCache<(), bool>,
+    active: IntGauge,
+}
+
+impl CdnCircuitBreaker {
+    pub fn new(metrics: &InstanceMetrics) -> Self {
+        Self {
+            state: LookupCache::new("cdn_circuit_breaker", 1, STATE_TTL, metrics),
+            active: metrics.cdn_fallback_active.clone(),
+        }
+    }
+
+    /// Returns whether downloads have to fall back to another location, or
+    /// `None` if the recent probes have to be loaded with [`Self::is_open`]
+    /// first.
+    pub fn get(&self) -> Option<bool> {
+        self.state.get(&())
+    }
+
+    /// Returns whether downloads have to fall back to another location,
+    /// loading the recent probes if necessary.
+    pub fn is_open(
+        &self,
+        conn: &mut PgConnection,
+        error_rate: f64,
+        recovery_probes: u32,
+    ) -> QueryResult<bool> {
+        self.state.get_or_try_insert_with((), || {
+            let open = CdnHealthProbe::is_cdn_unavailable(conn, error_rate, recovery_probes)?;
+            self.active.set(open as i64);
+            Ok(open)
+        })
+    }
+
+    pub fn invalidate(&self) {
+        self.state.invalidate(&());
+    }
+}
+
+/// Returns the URL that a download of the crate file is redirected to.
+///
+/// This is the location from [`crate::storage::Storage::crate_location`],
+/// unless the circuit breaker is open. If the state of the circuit breaker
+/// can't be determined, or no fallback location is available, the CDN is
+/// used.
+pub async fn crate_location(app: &AppState, crate_name: &str, version: &str) -> String {
+    let location = app.storage.crate_location(crate_name, version);
+
+    let config = &app.config.cdn_fallback;
+    if !config.is_enabled() {
+        return location;
+    }
+
+    match is_open(app).await {
+        Ok(false) => return location,
+        Ok(true) => {}
+        Err(error) => {
+            warn!(%error, "Failed to check the state of the CDN circuit breaker");
+            return location;
+        }
+    }
+
+    let redirects = &app.instance_metrics.cdn_fallback_redirects_total;
+
+    if let Some(cdn_prefix) = &config.secondary_cdn_prefix {
+        redirects.with_label_values(&["secondary_cdn"]).inc();
+        return app
+            .storage
+            .crate_location_on_cdn(cdn_prefix, crate_name, version);
+    }
+
+    match app.storage.signed_crate_location(crate_name, version).await {
+        Ok(Some(url)) => {
+            redirects.with_label_values(&["presigned_url"]).inc();
+            url
+        }
+        Ok(None) => location,
+        Err(error) => {
+            warn!(%error, "Failed to create a presigned download URL");
+            location
+        }
+    }
+}
+
+async fn is_open(app: &AppState) -> AppResult<bool> {
+    if let Some(open) = app.cdn_circuit_breaker.get() {
+        return Ok(open);
+    }
+
+    let config = &app.config.cdn_fallback;
+    let (error_rate, recovery_probes) = (config.error_rate, config.recovery_probes);
+
+    let conn = app.db_read().await?;
+    let app = app.clone();
+    let open = conn
+        .interact(move |conn| {
+            app.cdn_circuit_breaker
+                .is_open(conn, error_rate, recovery_probes)
+        })
+        .await??;
+
+    Ok(open)
+}
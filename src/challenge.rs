@@ -0,0 +1,286 @@
+//! Challenges (CAPTCHAs) for suspicious authentication flows
+//!
+//! If a sign-in or the acceptance of an ownership invitation comes from an
+//! unusual network, or if a user signs in unusually often, they have to solve
+//! a challenge of the configured [`ChallengeProvider`] before the action is
+//! completed. The frontend renders the challenge and sends the response in
+//! the [`CHALLENGE_RESPONSE_HEADER`].
+
+use crate::app::App;
+use crate::config::ChallengeConfig;
+use crate::controllers::util::RequestPartsExt;
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::real_ip::RealIp;
+use crate::schema::user_sign_ins;
+use crate::util::errors::{custom, server_error, AppResult, BoxedAppError};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use http::StatusCode;
+use ipnetwork::IpNetwork;
+use reqwest::Client;
+use secrecy::{ExposeSecret, SecretString};
+use std::net::IpAddr;
+use tokio::runtime::Handle;
+
+/// The request header that contains the response to the challenge.
+pub const CHALLENGE_RESPONSE_HEADER: &str = "challenge-response";
+
+/// Users signing in more often than this within one hour have to solve a
+/// challenge.
+const MAX_SIGN_INS_PER_HOUR: i64 = 5;
+
+/// Only the sign-ins of the last 90 days are considered as known networks.
+const SIGN_IN_HISTORY_DAYS: i64 = 90;
+
+#[async_trait]
+pub trait ChallengeProvider: Send + Sync {
+    /// Verifies the challenge response that was submitted by the client.
+    async fn verify(&self, response: &str, remote_ip: Option<IpAddr>) -> anyhow::Result<bool>;
+}
+
+impl dyn ChallengeProvider {
+    pub fn from_config(config: &ChallengeConfig, client: Client) -> Box<dyn ChallengeProvider> {
+        match config {
+            ChallengeConfig::Turnstile { secret_key } => {
+                Box::new(Turnstile::new(client, secret_key.clone()))
+            }
+            ChallengeConfig::HCaptcha { secret_key } => {
+                Box::new(HCaptcha::new(client, secret_key.clone()))
+            }
+            ChallengeConfig::Mock => Box::new(MockChallengeProvider),
+        }
+    }
+}
+
+/// Verifies challenges with [Cloudflare Turnstile](https://developers.cloudflare.com/turnstile/).
+pub struct Turnstile {
+    client: Client,
+    secret_key: SecretString,
+}
+
+impl Turnstile {
+    const VERIFY_URL: &'static str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+
+    pub fn new(client: Client, secret_key: SecretString) -> Self {
+        Self { client, secret_key }
+    }
+}
+
+#[async_trait]
+impl ChallengeProvider for Turnstile {
+    async fn verify(&self, response: &str, remote_ip: Option<IpAddr>) -> anyhow::Result<bool> {
+        let secret_key = self.secret_key.expose_secret();
+        site_verify(
+            &self.client,
+            Self::VERIFY_URL,
+            secret_key,
+            response,
+            remote_ip,
+        )
+        .await
+    }
+}
+
+/// Verifies challenges with [hCaptcha](https://docs.hcaptcha.com/).
+pub struct HCaptcha {
+    client: Client,
+    secret_key: SecretString,
+}
+
+impl HCaptcha {
+    const VERIFY_URL: &'static str = "https://api.hcaptcha.com/siteverify";
+
+    pub fn new(client: Client, secret_key: SecretString) -> Self {
+        Self { client, secret_key }
+    }
+}
+
+#[async_trait]
+impl ChallengeProvider for HCaptcha {
+    async fn verify(&self, response: &str, remote_ip: Option<IpAddr>) -> anyhow::Result<bool> {
+        let secret_key = self.secret_key.expose_secret();
+        site_verify(
+            &self.client,
+            Self::VERIFY_URL,
+            secret_key,
+            response,
+            remote_ip,
+        )
+        .await
+    }
+}
+
+/// Turnstile and hCaptcha share the same verification API.
+async fn site_verify(
+    client: &Client,
+    url: &str,
+    secret_key: &str,
+    response: &str,
+    remote_ip: Option<IpAddr>,
+) -> anyhow::Result<bool> {
+    #[derive(Deserialize)]
+    struct SiteVerifyResponse {
+        success: bool,
+    }
+
+    let remote_ip = remote_ip.map(|ip| ip.to_string()).unwrap_or_default();
+    let params = [
+        ("secret", secret_key),
+        ("response", response),
+        ("remoteip", &remote_ip),
+    ];
+
+    let response = client.post(url).form(&params).send().await?;
+    let response: SiteVerifyResponse = response.error_for_status()?.json().await?;
+
+    Ok(response.success)
+}
+
+/// A challenge provider for tests, which only accepts
+/// [`MockChallengeProvider::VALID_RESPONSE`].
+pub struct MockChallengeProvider;
+
+impl MockChallengeProvider {
+    pub const VALID_RESPONSE: &'static str = "valid-challenge-response";
+}
+
+#[async_trait]
+impl ChallengeProvider for MockChallengeProvider {
+    async fn verify(&self, response: &str, _remote_ip: Option<IpAddr>) -> anyhow::Result<bool> {
+        Ok(response == Self::VALID_RESPONSE)
+    }
+}
+
+/// Returns whether the user has to solve a challenge before signing in or
+/// accepting an ownership invitation from the given IP address.
+///
+/// This is the case if the user has signed in before, but never from the
+/// network of the IP address, or if they signed in unusually often recently.
+pub fn is_suspicious(conn: &mut PgConnection, user_id: i32, ip: IpAddr) -> QueryResult<bool> {
+    let recent_sign_ins = user_sign_ins::table
+        .filter(user_sign_ins::user_id.eq(user_id))
+        .filter(user_sign_ins::created_at.gt(Utc::now().naive_utc() - Duration::hours(1)))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    if recent_sign_ins >= MAX_SIGN_INS_PER_HOUR {
+        return Ok(true);
+    }
+
+    let known_ips = user_sign_ins::table
+        .filter(user_sign_ins::user_id.eq(user_id))
+        .filter(
+            user_sign_ins::created_at
+                .gt(Utc::now().naive_utc() - Duration::days(SIGN_IN_HISTORY_DAYS)),
+        )
+        .select(user_sign_ins::ip)
+        .distinct()
+        .load::<IpNetwork>(conn)?;
+
+    if known_ips.is_empty() {
+        return Ok(false);
+    }
+
+    let network = network_of(ip);
+    Ok(!known_ips.iter().any(|known| network.contains(known.ip())))
+}
+
+/// Returns the network that is considered "the same location" as the IP
+/// address: the `/16` range for IPv4 and the `/48` range for IPv6 addresses.
+pub(crate) fn network_of(ip: IpAddr) -> IpNetwork {
+    let prefix = match ip {
+        IpAddr::V4(_) => 16,
+        IpAddr::V6(_) => 48,
+    };
+
+    // The prefix is always valid for the address family
+    IpNetwork::new(ip, prefix).unwrap()
+}
+
+pub fn record_sign_in(conn: &mut PgConnection, user_id: i32, ip: IpAddr) -> QueryResult<()> {
+    diesel::insert_into(user_sign_ins::table)
+        .values((
+            user_sign_ins::user_id.eq(user_id),
+            user_sign_ins::ip.eq(IpNetwork::from(ip)),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Checks the challenge response of the request, if the request is
+/// [suspicious](is_suspicious) and challenges are enabled.
+pub fn check_challenge<T: RequestPartsExt>(
+    app: &App,
+    req: &T,
+    user_id: i32,
+    conn: &mut PgConnection,
+) -> AppResult<()> {
+    if app.challenge_provider.is_none() {
+        return Ok(());
+    }
+
+    let Some(ip) = req.extensions().get::<RealIp>().map(|ip| **ip) else {
+        return Ok(());
+    };
+
+    if !is_suspicious(conn, user_id, ip)? {
+        return Ok(());
+    }
+
+    verify_challenge(app, req)
+}
+
+/// Verifies the challenge response of the request with the configured
+/// provider. Does nothing if challenges are disabled.
+pub fn verify_challenge<T: RequestPartsExt>(app: &App, req: &T) -> AppResult<()> {
+    let Some(provider) = app.challenge_provider.as_deref() else {
+        return Ok(());
+    };
+
+    let response = req
+        .headers()
+        .get(CHALLENGE_RESPONSE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .ok_or_else(challenge_required)?;
+
+    let ip = req.extensions().get::<RealIp>().map(|ip| **ip);
+    let verified = Handle::current()
+        .block_on(provider.verify(response, ip))
+        .map_err(|error| {
+            req.request_log().add("cause", error);
+            server_error("failed to verify the challenge response")
+        })?;
+
+    match verified {
+        true => Ok(()),
+        false => Err(challenge_required()),
+    }
+}
+
+pub fn challenge_required() -> BoxedAppError {
+    custom(
+        StatusCode::FORBIDDEN,
+        "this action requires solving a challenge",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_of() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+        let network = network_of(ip("192.168.1.1"));
+        assert!(network.contains(ip("192.168.200.3")));
+        assert!(!network.contains(ip("192.169.1.1")));
+
+        let network = network_of(ip("2001:db8:1::1"));
+        assert!(network.contains(ip("2001:db8:1:ffff::1")));
+        assert!(!network.contains(ip("2001:db8:2::1")));
+    }
+}
@@ -0,0 +1,30 @@
+//! Abstraction over the current time
+//!
+//! Code that compares timestamps with the current time, e.g. to decide
+//! whether a rate limit bucket was refilled or an API token expired, reads
+//! the time from [`App::clock`](crate::app::App::clock) instead of calling
+//! [`Utc::now()`] directly. This allows the tests to fast-forward time
+//! instead of sleeping or moving timestamps in the database.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Returns the current time in UTC without a timezone, as it is stored
+    /// in the database.
+    fn naive_now(&self) -> NaiveDateTime {
+        self.now().naive_utc()
+    }
+}
+
+/// The clock of the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -35,17 +35,35 @@ impl CloudFront {
     /// Invalidate a file on CloudFront
     ///
     /// `path` is the path to the file to invalidate, such as `config.json`, or `re/ge/regex`
-    #[instrument(skip(self))]
     pub async fn invalidate(&self, path: &str) -> anyhow::Result<()> {
-        let path = if path.starts_with('/') {
-            path.to_string()
-        } else {
-            format!("/{path}")
-        };
+        self.invalidate_many(vec![path.to_string()]).await
+    }
+
+    /// Invalidate multiple files on CloudFront with a single invalidation
+    /// request
+    ///
+    /// CloudFront charges per invalidated path, regardless of how many paths
+    /// are part of the same request, but it only allows a limited number of
+    /// invalidations to be in progress at the same time.
+    #[instrument(skip_all, fields(paths.count = paths.len()))]
+    pub async fn invalidate_many(&self, paths: Vec<String>) -> anyhow::Result<()> {
+        let paths = paths
+            .into_iter()
+            .map(|path| {
+                if path.starts_with('/') {
+                    path
+                } else {
+                    format!("/{path}")
+                }
+            })
+            .collect::<Vec<_>>();
 
         let now = chrono::offset::Utc::now().timestamp_micros();
 
-        let paths = Paths::builder().quantity(1).items(path).build()?;
+        let paths = Paths::builder()
+            .quantity(paths.len() as i32)
+            .set_items(Some(paths))
+            .build()?;
 
         let invalidation_batch = InvalidationBatch::builder()
             .caller_reference(format!("{now}"))
@@ -1,13 +1,51 @@
+mod api_deprecations;
+mod api_quotas;
+mod audit_export;
+mod auth_failure_limiter;
 mod base;
+mod cdn_fallback;
 mod cdn_log_queue;
 mod cdn_log_storage;
+mod challenge;
+mod client_ip;
 mod database_pools;
+mod download_rate_limiter;
+mod download_spikes;
+mod http_server;
+mod metrics;
+mod publish_nonces;
+mod reloadable;
+mod request_timeouts;
+mod safe_fetch;
+mod search_ranking;
 mod sentry;
 mod server;
+mod tls;
+mod token_anomalies;
+mod upstream;
 
+pub use self::api_deprecations::{ApiDeprecation, ApiDeprecationConfig};
+pub use self::api_quotas::ApiQuotaConfig;
+pub use self::audit_export::AuditExportConfig;
+pub use self::auth_failure_limiter::AuthFailureLimiterConfig;
 pub use self::base::Base;
+pub use self::cdn_fallback::CdnFallbackConfig;
 pub use self::cdn_log_queue::CdnLogQueueConfig;
 pub use self::cdn_log_storage::CdnLogStorageConfig;
+pub use self::challenge::ChallengeConfig;
+pub use self::client_ip::{ClientIpConfig, ForwardedHeader};
 pub use self::database_pools::{DatabasePools, DbPoolConfig};
+pub use self::download_rate_limiter::DownloadRateLimiterConfig;
+pub use self::download_spikes::DownloadSpikeConfig;
+pub use self::http_server::HttpServerConfig;
+pub use self::metrics::MetricsToken;
+pub use self::publish_nonces::PublishNonceConfig;
+pub use self::reloadable::{ConfigChange, ReloadableConfig};
+pub use self::request_timeouts::RequestTimeoutConfig;
+pub use self::safe_fetch::SafeFetchConfig;
+pub use self::search_ranking::{RankingWeights, SearchRankingConfig};
 pub use self::sentry::SentryConfig;
 pub use self::server::Server;
+pub use self::tls::TlsConfig;
+pub use self::token_anomalies::TokenAnomalyConfig;
+pub use self::upstream::UpstreamConfig;
@@ -0,0 +1,156 @@
+use anyhow::{anyhow, Context};
+use chrono::NaiveDate;
+use crates_io_env_vars::{list_parsed, var};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The API routes and query parameters that are slated for removal. The
+/// responses to requests that use them carry the `Deprecation`, `Sunset` and
+/// `Link` headers, and the requests are logged, so that the remaining users
+/// can be found before the removal.
+///
+/// - `API_DEPRECATIONS`: Comma-separated list of deprecations in the form
+///   `ROUTE[?PARAMETER]=DEPRECATED_AT[/SUNSET_AT]`, with ISO 8601 dates, e.g.
+///   `/api/v1/crates/:crate_id/:version/authors=2024-06-01/2024-12-01`. The
+///   route is the route pattern, as in `ROUTE_CONCURRENCY_LIMITS`. With a
+///   parameter, only the requests that use the query parameter are
+///   deprecated.
+/// - `API_DEPRECATION_INFO_URL`: The URL of the documentation of the
+///   deprecations, which is linked from the responses.
+#[derive(Debug, Clone, Default)]
+pub struct ApiDeprecationConfig {
+    pub deprecations: Vec<ApiDeprecation>,
+    pub info_url: Option<String>,
+}
+
+impl ApiDeprecationConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            deprecations: list_parsed("API_DEPRECATIONS", ApiDeprecation::from_str)?,
+            info_url: var("API_DEPRECATION_INFO_URL")?,
+        })
+    }
+
+    /// Returns the first deprecation that applies to a request of the route
+    /// with the given query string.
+    pub fn find(&self, route: &str, query: Option<&str>) -> Option<&ApiDeprecation> {
+        self.deprecations
+            .iter()
+            .find(|deprecation| deprecation.applies_to(route, query))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiDeprecation {
+    pub route: String,
+    pub parameter: Option<String>,
+    pub deprecated_at: NaiveDate,
+    pub sunset_at: Option<NaiveDate>,
+}
+
+impl ApiDeprecation {
+    fn applies_to(&self, route: &str, query: Option<&str>) -> bool {
+        if self.route != route {
+            return false;
+        }
+
+        let Some(parameter) = &self.parameter else {
+            return true;
+        };
+
+        query.is_some_and(|query| {
+            url::form_urlencoded::parse(query.as_bytes()).any(|(key, _)| key == *parameter)
+        })
+    }
+}
+
+impl FromStr for ApiDeprecation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, dates) = s
+            .split_once('=')
+            .context("API_DEPRECATIONS must be in the form ROUTE[?PARAMETER]=DATE[/DATE]")?;
+
+        let (route, parameter) = match target.trim().split_once('?') {
+            Some((route, parameter)) => (route, Some(parameter.to_string())),
+            None => (target.trim(), None),
+        };
+
+        if !route.starts_with('/') || parameter.as_deref() == Some("") {
+            return Err(anyhow!("invalid deprecated route `{target}`"));
+        }
+
+        let (deprecated_at, sunset_at) = match dates.trim().split_once('/') {
+            Some((deprecated_at, sunset_at)) => (deprecated_at, Some(sunset_at.parse()?)),
+            None => (dates.trim(), None),
+        };
+
+        Ok(Self {
+            route: route.to_string(),
+            parameter,
+            deprecated_at: deprecated_at.parse()?,
+            sunset_at,
+        })
+    }
+}
+
+impl Display for ApiDeprecation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.parameter {
+            Some(parameter) => write!(f, "{}?{parameter}", self.route),
+            None => f.write_str(&self.route),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTHORS: &str = "/api/v1/crates/:crate_id/:version/authors";
+
+    #[test]
+    fn parse_deprecations() {
+        let deprecation = assert_ok!(ApiDeprecation::from_str(&format!("{AUTHORS}=2024-06-01")));
+        assert_eq!(deprecation.route, AUTHORS);
+        assert_none!(deprecation.parameter);
+        assert_eq!(deprecation.deprecated_at.to_string(), "2024-06-01");
+        assert_none!(deprecation.sunset_at);
+
+        let deprecation = assert_ok!(ApiDeprecation::from_str(
+            "/api/v1/crates?letter=2024-06-01/2024-12-01"
+        ));
+        assert_eq!(deprecation.route, "/api/v1/crates");
+        assert_some_eq!(deprecation.parameter.as_deref(), "letter");
+        assert_some_eq!(deprecation.sunset_at.map(|d| d.to_string()), "2024-12-01");
+        assert_eq!(deprecation.to_string(), "/api/v1/crates?letter");
+
+        assert_err!(ApiDeprecation::from_str(AUTHORS));
+        assert_err!(ApiDeprecation::from_str("authors=2024-06-01"));
+        assert_err!(ApiDeprecation::from_str("/api/v1/crates?=2024-06-01"));
+        assert_err!(ApiDeprecation::from_str(&format!("{AUTHORS}=June")));
+        assert_err!(ApiDeprecation::from_str(&format!("{AUTHORS}=2024-06-01/")));
+    }
+
+    #[test]
+    fn find_deprecations() {
+        let config = ApiDeprecationConfig {
+            deprecations: vec![
+                assert_ok!(format!("{AUTHORS}=2024-06-01").parse()),
+                assert_ok!("/api/v1/crates?letter=2024-06-01".parse()),
+            ],
+            info_url: None,
+        };
+
+        assert_some!(config.find(AUTHORS, None));
+        assert_some!(config.find(AUTHORS, Some("page=2")));
+        assert_none!(config.find("/api/v1/crates/:crate_id", None));
+
+        assert_some!(config.find("/api/v1/crates", Some("letter=a")));
+        assert_some!(config.find("/api/v1/crates", Some("page=2&letter=")));
+        assert_none!(config.find("/api/v1/crates", Some("page=2")));
+        assert_none!(config.find("/api/v1/crates", Some("letters=a")));
+        assert_none!(config.find("/api/v1/crates", None));
+    }
+}
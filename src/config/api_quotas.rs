@@ -0,0 +1,46 @@
+use crates_io_env_vars::var_parsed;
+use std::time::Duration;
+
+/// Monthly quotas of the authenticated API requests (see
+/// [`crate::api_usage`]).
+///
+/// - `API_QUOTA_USER_MONTHLY_REQUESTS`: The number of requests per month
+///   after which the responses to a user carry a quota warning. Unlimited if
+///   unset.
+/// - `API_QUOTA_TOKEN_MONTHLY_REQUESTS`: The same for the requests that are
+///   authenticated with a single API token.
+/// - `API_USAGE_FLUSH_INTERVAL_SECONDS`: How often the request counters of a
+///   server are written to the database. Defaults to 60 seconds.
+///
+/// The quotas are not enforced yet, exceeding them only adds the
+/// `X-Api-Quota-Warning` header to the responses.
+#[derive(Debug, Clone)]
+pub struct ApiQuotaConfig {
+    pub user_monthly_requests: Option<u64>,
+    pub token_monthly_requests: Option<u64>,
+    pub flush_interval: Duration,
+}
+
+impl Default for ApiQuotaConfig {
+    fn default() -> Self {
+        Self {
+            user_monthly_requests: None,
+            token_monthly_requests: None,
+            flush_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ApiQuotaConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        Ok(Self {
+            user_monthly_requests: var_parsed("API_QUOTA_USER_MONTHLY_REQUESTS")?,
+            token_monthly_requests: var_parsed("API_QUOTA_TOKEN_MONTHLY_REQUESTS")?,
+            flush_interval: var_parsed("API_USAGE_FLUSH_INTERVAL_SECONDS")?
+                .map(Duration::from_secs)
+                .unwrap_or(default.flush_interval),
+        })
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::bail;
+use crates_io_env_vars::{var, var_parsed};
+use secrecy::SecretString;
+use std::time::Duration;
+
+/// Configuration of the export of the audit events to an external SIEM (see
+/// [`crate::audit`]).
+///
+/// - `AUDIT_EXPORT_URL`: The HTTPS endpoint to which the audit events are
+///   sent as JSON. The export is disabled if this is not set.
+/// - `AUDIT_EXPORT_TOKEN`: The bearer token that is sent with the events,
+///   if the endpoint requires one.
+/// - `AUDIT_EXPORT_BUFFER_SIZE`: The maximum number of events that a server
+///   keeps in memory while the endpoint is unavailable. Further events stay
+///   in the database outbox until there is room. Defaults to 10000.
+/// - `AUDIT_EXPORT_BATCH_SIZE`: The maximum number of events per request.
+///   Defaults to 500.
+/// - `AUDIT_EXPORT_FLUSH_INTERVAL_SECONDS`: How often the events of the
+///   outbox are sent. Defaults to 10 seconds.
+/// - `AUDIT_EXPORT_MAX_RETRIES`: How often a failed request is retried
+///   before the events are kept for the next flush. Defaults to 3.
+#[derive(Debug, Clone)]
+pub struct AuditExportConfig {
+    pub url: String,
+    pub token: Option<SecretString>,
+    pub buffer_size: usize,
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+    pub max_retries: u32,
+}
+
+impl AuditExportConfig {
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(url) = var("AUDIT_EXPORT_URL")? else {
+            return Ok(None);
+        };
+
+        if !url.starts_with("https://") {
+            bail!("`AUDIT_EXPORT_URL` must be an HTTPS URL");
+        }
+
+        Ok(Some(Self {
+            url,
+            token: var("AUDIT_EXPORT_TOKEN")?.map(SecretString::from),
+            buffer_size: var_parsed("AUDIT_EXPORT_BUFFER_SIZE")?.unwrap_or(10_000),
+            batch_size: var_parsed("AUDIT_EXPORT_BATCH_SIZE")?.unwrap_or(500),
+            flush_interval: var_parsed("AUDIT_EXPORT_FLUSH_INTERVAL_SECONDS")?
+                .map(Duration::from_secs)
+                .unwrap_or(Duration::from_secs(10)),
+            max_retries: var_parsed("AUDIT_EXPORT_MAX_RETRIES")?.unwrap_or(3),
+        }))
+    }
+}
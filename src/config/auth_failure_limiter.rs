@@ -0,0 +1,61 @@
+use crates_io_env_vars::var_parsed;
+use std::time::Duration;
+
+/// Configuration of the rate limit for failed authentication attempts, see
+/// [`crate::rate_limiter::auth_failures`].
+///
+/// - `AUTH_FAILURE_FREE_ATTEMPTS`: Number of failed attempts per client IP
+///   address or token prefix before further attempts are delayed. Defaults
+///   to 10.
+/// - `AUTH_FAILURE_BASE_DELAY`: Seconds that clients have to wait after the
+///   first delayed failure. The delay doubles with every further failure.
+///   Defaults to 1 second.
+/// - `AUTH_FAILURE_MAX_DELAY`: Upper bound of the delay in seconds. Defaults
+///   to 60 seconds.
+/// - `AUTH_FAILURE_BLOCK_AFTER`: Number of failed attempts after which the
+///   client is blocked completely. Defaults to 50.
+/// - `AUTH_FAILURE_BLOCK_DURATION`: Seconds that blocked clients have to
+///   wait. Defaults to one hour.
+/// - `AUTH_FAILURE_WINDOW`: Seconds after the first failed attempt after
+///   which the failures are forgotten, unless the client is still delayed or
+///   blocked. Defaults to one hour.
+#[derive(Debug, Clone)]
+pub struct AuthFailureLimiterConfig {
+    pub free_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub block_after: u32,
+    pub block_duration: Duration,
+    pub window: Duration,
+}
+
+impl Default for AuthFailureLimiterConfig {
+    fn default() -> Self {
+        Self {
+            free_attempts: 10,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            block_after: 50,
+            block_duration: Duration::from_secs(60 * 60),
+            window: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl AuthFailureLimiterConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        let secs = |key| Ok::<_, anyhow::Error>(var_parsed(key)?.map(Duration::from_secs));
+
+        Ok(Self {
+            free_attempts: var_parsed("AUTH_FAILURE_FREE_ATTEMPTS")?
+                .unwrap_or(default.free_attempts),
+            base_delay: secs("AUTH_FAILURE_BASE_DELAY")?.unwrap_or(default.base_delay),
+            max_delay: secs("AUTH_FAILURE_MAX_DELAY")?.unwrap_or(default.max_delay),
+            block_after: var_parsed("AUTH_FAILURE_BLOCK_AFTER")?.unwrap_or(default.block_after),
+            block_duration: secs("AUTH_FAILURE_BLOCK_DURATION")?.unwrap_or(default.block_duration),
+            window: secs("AUTH_FAILURE_WINDOW")?.unwrap_or(default.window),
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use crates_io_env_vars::{var, var_parsed};
+
+/// Configuration of the download fallback for CDN incidents (see
+/// [`crate::cdn_fallback`]).
+///
+/// - `CDN_HEALTH_PROBE_PATH`: Path of a file on the CDN that the
+///   `probe_cdn_health` background job requests, e.g.
+///   `crates/serde/serde-1.0.0.crate`. The fallback is disabled if this is not
+///   set.
+/// - `CDN_HEALTH_PROBE_REQUESTS`: Number of requests of each probe. Defaults
+///   to 10.
+/// - `CDN_FALLBACK_ERROR_RATE`: Share of failed probe requests at which
+///   downloads are redirected to the fallback. Defaults to 0.5.
+/// - `CDN_FALLBACK_RECOVERY_PROBES`: Number of consecutive healthy probes
+///   after which downloads are redirected to the CDN again. Defaults to 3.
+/// - `CDN_FALLBACK_PREFIX`: Host of a secondary CDN that serves the same
+///   files. If this is not set, downloads are redirected to presigned S3 URLs
+///   instead.
+#[derive(Debug, Clone)]
+pub struct CdnFallbackConfig {
+    pub probe_path: Option<String>,
+    pub probe_requests: u32,
+    pub error_rate: f64,
+    pub recovery_probes: u32,
+    pub secondary_cdn_prefix: Option<String>,
+}
+
+impl Default for CdnFallbackConfig {
+    fn default() -> Self {
+        Self {
+            probe_path: None,
+            probe_requests: 10,
+            error_rate: 0.5,
+            recovery_probes: 3,
+            secondary_cdn_prefix: None,
+        }
+    }
+}
+
+impl CdnFallbackConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        Ok(Self {
+            probe_path: var("CDN_HEALTH_PROBE_PATH")?,
+            probe_requests: var_parsed("CDN_HEALTH_PROBE_REQUESTS")?
+                .unwrap_or(default.probe_requests),
+            error_rate: var_parsed("CDN_FALLBACK_ERROR_RATE")?.unwrap_or(default.error_rate),
+            recovery_probes: var_parsed("CDN_FALLBACK_RECOVERY_PROBES")?
+                .unwrap_or(default.recovery_probes),
+            secondary_cdn_prefix: var("CDN_FALLBACK_PREFIX")?,
+        })
+    }
+
+    /// Returns whether the CDN is probed and downloads may fall back to
+    /// another location.
+    pub fn is_enabled(&self) -> bool {
+        self.probe_path.is_some()
+    }
+}
@@ -0,0 +1,33 @@
+use crates_io_env_vars::{required_var, var};
+use secrecy::SecretString;
+
+/// Configuration of the challenge (CAPTCHA) that users have to solve when
+/// their authentication looks suspicious.
+///
+/// - `CHALLENGE_PROVIDER`: Either `turnstile` or `hcaptcha`. Challenges are
+///   disabled if this is not set.
+/// - `CHALLENGE_SECRET_KEY`: The secret key used to verify the responses
+///   with the provider.
+#[derive(Debug, Clone)]
+pub enum ChallengeConfig {
+    Turnstile { secret_key: SecretString },
+    HCaptcha { secret_key: SecretString },
+    Mock,
+}
+
+impl ChallengeConfig {
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(provider) = var("CHALLENGE_PROVIDER")? else {
+            return Ok(None);
+        };
+
+        let secret_key = required_var("CHALLENGE_SECRET_KEY")?.into();
+        match provider.as_str() {
+            "turnstile" => Ok(Some(Self::Turnstile { secret_key })),
+            "hcaptcha" => Ok(Some(Self::HCaptcha { secret_key })),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported `CHALLENGE_PROVIDER`: {provider}"
+            )),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crates_io_env_vars::var_parsed;
+use std::str::FromStr;
+
+/// How the IP address of the client is determined from the headers that the
+/// proxies in front of the server add to the request.
+///
+/// - `CLIENT_IP_HEADER`: The header that the proxies append the addresses of
+///   their peers to, either `x-forwarded-for` (the default) or `forwarded`.
+/// - `TRUSTED_PROXY_DEPTH`: The number of proxies in front of the server that
+///   append to the header. The client address is the address that the
+///   outermost of them appended. If unset, the right-most address that does
+///   not belong to AWS CloudFront is used instead.
+/// - `TRUST_CLOUDFRONT_VIEWER_ADDRESS`: Whether to use the
+///   `CloudFront-Viewer-Address` header of requests that came in through AWS
+///   CloudFront. Enabled by default.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIpConfig {
+    pub header: ForwardedHeader,
+    pub trusted_proxy_depth: Option<usize>,
+    pub trust_cloudfront_viewer_address: bool,
+}
+
+impl Default for ClientIpConfig {
+    fn default() -> Self {
+        Self {
+            header: ForwardedHeader::XForwardedFor,
+            trusted_proxy_depth: None,
+            trust_cloudfront_viewer_address: true,
+        }
+    }
+}
+
+impl ClientIpConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        Ok(Self {
+            header: var_parsed("CLIENT_IP_HEADER")?.unwrap_or(default.header),
+            trusted_proxy_depth: var_parsed("TRUSTED_PROXY_DEPTH")?,
+            trust_cloudfront_viewer_address: var_parsed("TRUST_CLOUDFRONT_VIEWER_ADDRESS")?
+                .unwrap_or(default.trust_cloudfront_viewer_address),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardedHeader {
+    /// The de-facto standard `X-Forwarded-For` header.
+    XForwardedFor,
+    /// The `Forwarded` header of RFC 7239.
+    Forwarded,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid client IP header `{0}`, expected `x-forwarded-for` or `forwarded`")]
+pub struct InvalidForwardedHeader(String);
+
+impl FromStr for ForwardedHeader {
+    type Err = InvalidForwardedHeader;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "x-forwarded-for" => Ok(Self::XForwardedFor),
+            "forwarded" => Ok(Self::Forwarded),
+            _ => Err(InvalidForwardedHeader(s.to_string())),
+        }
+    }
+}
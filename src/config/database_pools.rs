@@ -10,6 +10,12 @@
 //!   If set to `follower` then act as if `READ_ONLY_REPLICA_URL` was unset.
 //! - `READ_ONLY_MODE`: If defined (even as empty) then force all connections to be read-only.
 //! - `DB_TCP_TIMEOUT_MS`: TCP timeout in milliseconds. See the doc comment for more details.
+//! - `DB_PRIMARY_STATEMENT_TIMEOUT`: Statement timeout of the primary database in seconds.
+//!   Defaults to `DB_TIMEOUT`.
+//! - `DB_REPLICA_STATEMENT_TIMEOUT`: Statement timeout of the read-only / replica database in
+//!   seconds. Defaults to `DB_TIMEOUT`.
+//! - `DB_SLOW_QUERY_THRESHOLD_MS`: Database interactions taking longer than this number of
+//!   milliseconds are logged. Defaults to 1000.
 
 use crate::config::Base;
 use crate::Env;
@@ -33,9 +39,8 @@ pub struct DatabasePools {
     /// Time to wait for a connection to become available from the connection
     /// pool before returning an error.
     pub connection_timeout: Duration,
-    /// Time to wait for a query response before canceling the query and
-    /// returning an error.
-    pub statement_timeout: Duration,
+    /// Database interactions taking longer than this are logged as slow.
+    pub slow_query_threshold: Duration,
     /// Number of threads to use for asynchronous operations such as connection
     /// creation.
     pub helper_threads: usize,
@@ -49,6 +54,9 @@ pub struct DbPoolConfig {
     pub read_only_mode: bool,
     pub pool_size: usize,
     pub min_idle: Option<u32>,
+    /// Time to wait for a query response before canceling the query and
+    /// returning an error.
+    pub statement_timeout: Duration,
 }
 
 impl DatabasePools {
@@ -83,9 +91,17 @@ impl DatabasePools {
         let connection_timeout = var_parsed("DB_TIMEOUT")?.unwrap_or(30);
         let connection_timeout = Duration::from_secs(connection_timeout);
 
-        // `DB_TIMEOUT` currently configures both the connection timeout and
-        // the statement timeout, so we can copy the parsed connection timeout.
-        let statement_timeout = connection_timeout;
+        // `DB_TIMEOUT` configures both the connection timeout and the default
+        // statement timeout of the pools.
+        let primary_statement_timeout = var_parsed("DB_PRIMARY_STATEMENT_TIMEOUT")?
+            .map(Duration::from_secs)
+            .unwrap_or(connection_timeout);
+        let replica_statement_timeout = var_parsed("DB_REPLICA_STATEMENT_TIMEOUT")?
+            .map(Duration::from_secs)
+            .unwrap_or(connection_timeout);
+
+        let slow_query_threshold = var_parsed("DB_SLOW_QUERY_THRESHOLD_MS")?.unwrap_or(1000);
+        let slow_query_threshold = Duration::from_millis(slow_query_threshold);
 
         let helper_threads = var_parsed("DB_HELPER_THREADS")?.unwrap_or(3);
 
@@ -102,11 +118,12 @@ impl DatabasePools {
                     read_only_mode: true,
                     pool_size: primary_async_pool_size,
                     min_idle: primary_min_idle,
+                    statement_timeout: primary_statement_timeout,
                 },
                 replica: None,
                 tcp_timeout_ms,
                 connection_timeout,
-                statement_timeout,
+                slow_query_threshold,
                 helper_threads,
                 enforce_tls,
             },
@@ -117,11 +134,12 @@ impl DatabasePools {
                     read_only_mode,
                     pool_size: primary_async_pool_size,
                     min_idle: primary_min_idle,
+                    statement_timeout: primary_statement_timeout,
                 },
                 replica: None,
                 tcp_timeout_ms,
                 connection_timeout,
-                statement_timeout,
+                slow_query_threshold,
                 helper_threads,
                 enforce_tls,
             },
@@ -131,6 +149,7 @@ impl DatabasePools {
                     read_only_mode,
                     pool_size: primary_async_pool_size,
                     min_idle: primary_min_idle,
+                    statement_timeout: primary_statement_timeout,
                 },
                 replica: follower_url.map(|url| DbPoolConfig {
                     url,
@@ -140,10 +159,11 @@ impl DatabasePools {
                     read_only_mode: true,
                     pool_size: replica_async_pool_size,
                     min_idle: replica_min_idle,
+                    statement_timeout: replica_statement_timeout,
                 }),
                 tcp_timeout_ms,
                 connection_timeout,
-                statement_timeout,
+                slow_query_threshold,
                 helper_threads,
                 enforce_tls,
             },
@@ -0,0 +1,39 @@
+use crates_io_env_vars::{var, var_parsed};
+use secrecy::SecretString;
+use std::time::Duration;
+
+const DEFAULT_RATE_MILLIS: u64 = 100;
+
+#[derive(Debug, Clone)]
+pub struct DownloadRateLimiterConfig {
+    /// The time it takes for a single token to be added back to a bucket.
+    pub rate: Duration,
+    /// The maximum number of tokens in a bucket.
+    pub burst: u32,
+    /// Optional Redis connection URL. If set, the buckets are shared between
+    /// all server instances instead of being kept in memory.
+    pub redis_url: Option<SecretString>,
+}
+
+impl DownloadRateLimiterConfig {
+    /// Reads the download rate limiter configuration from the environment.
+    ///
+    /// Returns `None` if `DOWNLOAD_RATE_LIMITER_BURST` is not set, which
+    /// disables the rate limiter completely.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(burst) = var_parsed("DOWNLOAD_RATE_LIMITER_BURST")? else {
+            return Ok(None);
+        };
+
+        let rate = var_parsed("DOWNLOAD_RATE_LIMITER_RATE_MS")?.unwrap_or(DEFAULT_RATE_MILLIS);
+        let rate = Duration::from_millis(rate);
+
+        let redis_url = var("DOWNLOAD_RATE_LIMITER_REDIS_URL")?.map(Into::into);
+
+        Ok(Some(Self {
+            rate,
+            burst,
+            redis_url,
+        }))
+    }
+}
@@ -0,0 +1,47 @@
+use crates_io_env_vars::{list, var_parsed};
+
+/// Configuration of the `detect_download_spikes` background job.
+///
+/// - `DOWNLOAD_SPIKE_FACTOR`: How many times the median of the daily
+///   downloads of the previous weeks a crate has to be downloaded on a day to
+///   be considered a spike. Defaults to 10.
+/// - `DOWNLOAD_SPIKE_MIN_DOWNLOADS`: Number of daily downloads below which
+///   spikes are ignored, so that rarely downloaded crates are not reported
+///   for every handful of downloads. Defaults to 10,000.
+/// - `DOWNLOAD_SPIKE_NOTIFY_OWNERS`: Notify the owners of the crates about
+///   spikes. Disabled by default.
+/// - `DOWNLOAD_SPIKE_NOTIFICATION_EMAILS`: Comma-separated list of addresses
+///   of the crates.io team that are notified about spikes.
+#[derive(Debug, Clone)]
+pub struct DownloadSpikeConfig {
+    pub factor: f64,
+    pub min_downloads: i64,
+    pub notify_owners: bool,
+    pub notification_emails: Vec<String>,
+}
+
+impl Default for DownloadSpikeConfig {
+    fn default() -> Self {
+        Self {
+            factor: 10.,
+            min_downloads: 10_000,
+            notify_owners: false,
+            notification_emails: vec![],
+        }
+    }
+}
+
+impl DownloadSpikeConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        Ok(Self {
+            factor: var_parsed("DOWNLOAD_SPIKE_FACTOR")?.unwrap_or(default.factor),
+            min_downloads: var_parsed("DOWNLOAD_SPIKE_MIN_DOWNLOADS")?
+                .unwrap_or(default.min_downloads),
+            notify_owners: var_parsed("DOWNLOAD_SPIKE_NOTIFY_OWNERS")?
+                .unwrap_or(default.notify_owners),
+            notification_emails: list("DOWNLOAD_SPIKE_NOTIFICATION_EMAILS")?,
+        })
+    }
+}
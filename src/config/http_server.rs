@@ -0,0 +1,70 @@
+use crate::shutdown::SHUTDOWN_TIMEOUT;
+use crates_io_env_vars::var_parsed;
+use std::time::Duration;
+
+/// Connection-level configuration of the HTTP server, which allows tuning
+/// its behavior to the load balancer in front of it.
+///
+/// - `HTTP2_ENABLED`: Accept HTTP/2 connections with prior knowledge (and
+///   via ALPN, if TLS is terminated in the server process). Disabled by
+///   default.
+/// - `HTTP_KEEP_ALIVE`: Whether HTTP/1 connections are kept alive between
+///   requests. Enabled by default.
+/// - `HTTP_HEADER_READ_TIMEOUT`: Seconds to wait for the headers of a
+///   request, which also limits how long idle HTTP/1 connections are kept
+///   open. Defaults to 30 seconds.
+/// - `HTTP2_KEEP_ALIVE_INTERVAL`: Seconds between HTTP/2 keep-alive pings.
+///   No pings are sent by default.
+/// - `HTTP2_KEEP_ALIVE_TIMEOUT`: Seconds to wait for the response to an
+///   HTTP/2 keep-alive ping before closing the connection. Defaults to 20
+///   seconds.
+/// - `HTTP2_MAX_CONCURRENT_STREAMS`: Maximum number of concurrent streams
+///   per HTTP/2 connection. Defaults to 200.
+/// - `SHUTDOWN_GRACE_PERIOD`: Seconds to wait for in-flight requests to
+///   finish during a shutdown. Defaults to 25 seconds.
+#[derive(Debug, Clone)]
+pub struct HttpServerConfig {
+    pub http2: bool,
+    pub keep_alive: bool,
+    pub header_read_timeout: Duration,
+    pub http2_keep_alive_interval: Option<Duration>,
+    pub http2_keep_alive_timeout: Duration,
+    pub http2_max_concurrent_streams: u32,
+    pub shutdown_grace_period: Duration,
+}
+
+impl Default for HttpServerConfig {
+    fn default() -> Self {
+        Self {
+            http2: false,
+            keep_alive: true,
+            header_read_timeout: Duration::from_secs(30),
+            http2_keep_alive_interval: None,
+            http2_keep_alive_timeout: Duration::from_secs(20),
+            http2_max_concurrent_streams: 200,
+            shutdown_grace_period: SHUTDOWN_TIMEOUT,
+        }
+    }
+}
+
+impl HttpServerConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        let secs = |key| Ok::<_, anyhow::Error>(var_parsed(key)?.map(Duration::from_secs));
+
+        Ok(Self {
+            http2: var_parsed("HTTP2_ENABLED")?.unwrap_or(default.http2),
+            keep_alive: var_parsed("HTTP_KEEP_ALIVE")?.unwrap_or(default.keep_alive),
+            header_read_timeout: secs("HTTP_HEADER_READ_TIMEOUT")?
+                .unwrap_or(default.header_read_timeout),
+            http2_keep_alive_interval: secs("HTTP2_KEEP_ALIVE_INTERVAL")?,
+            http2_keep_alive_timeout: secs("HTTP2_KEEP_ALIVE_TIMEOUT")?
+                .unwrap_or(default.http2_keep_alive_timeout),
+            http2_max_concurrent_streams: var_parsed("HTTP2_MAX_CONCURRENT_STREAMS")?
+                .unwrap_or(default.http2_max_concurrent_streams),
+            shutdown_grace_period: secs("SHUTDOWN_GRACE_PERIOD")?
+                .unwrap_or(default.shutdown_grace_period),
+        })
+    }
+}
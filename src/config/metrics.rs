@@ -0,0 +1,105 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use crates_io_env_vars::{list_parsed, var};
+use secrecy::{ExposeSecret, SecretString};
+use std::str::FromStr;
+
+/// A token that grants access to the `/api/private/metrics/:kind` endpoints.
+///
+/// Tokens can have an expiry date, which allows rotating them without gaps
+/// in the scraping: the new token is added while the old one is still valid,
+/// and the scrapers can switch over before the old token expires.
+#[derive(Debug, Clone)]
+pub struct MetricsToken {
+    token: SecretString,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl MetricsToken {
+    pub fn new(token: impl Into<String>, expires_at: Option<DateTime<Utc>>) -> Self {
+        let token = SecretString::new(token.into());
+        Self { token, expires_at }
+    }
+
+    /// Reads the metrics tokens from the `METRICS_AUTHORIZATION_TOKEN` and
+    /// `METRICS_AUTHORIZATION_TOKENS` environment variables.
+    ///
+    /// `METRICS_AUTHORIZATION_TOKENS` is a comma-separated list of tokens,
+    /// each optionally followed by `@` and an RFC 3339 expiry date
+    /// (e.g. `secret@2024-06-01T00:00:00Z`).
+    pub fn from_env() -> anyhow::Result<Vec<Self>> {
+        let mut tokens = list_parsed("METRICS_AUTHORIZATION_TOKENS", Self::from_str)?;
+        if let Some(token) = var("METRICS_AUTHORIZATION_TOKEN")? {
+            tokens.push(Self::new(token, None));
+        }
+
+        Ok(tokens)
+    }
+
+    /// Returns whether the provided token matches this token and has not
+    /// expired yet.
+    pub fn matches(&self, provided_token: &str, now: DateTime<Utc>) -> bool {
+        let expired = self.expires_at.is_some_and(|expires_at| now >= expires_at);
+        !expired && self.token.expose_secret() == provided_token
+    }
+}
+
+impl FromStr for MetricsToken {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, expires_at) = match s.split_once('@') {
+            Some((token, expires_at)) => {
+                let expires_at = DateTime::parse_from_rfc3339(expires_at)
+                    .context("Failed to parse the expiry date")?;
+
+                (token, Some(expires_at.with_timezone(&Utc)))
+            }
+            None => (s, None),
+        };
+
+        if token.is_empty() {
+            anyhow::bail!("Metrics tokens must not be empty");
+        }
+
+        Ok(Self::new(token, expires_at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse() {
+        let token = assert_ok!(MetricsToken::from_str("secret"));
+        assert_eq!(token.token.expose_secret(), "secret");
+        assert_none!(token.expires_at);
+
+        let token = assert_ok!(MetricsToken::from_str("secret@2024-06-01T00:00:00Z"));
+        assert_eq!(token.token.expose_secret(), "secret");
+        let expected = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        assert_some_eq!(token.expires_at, expected);
+
+        assert_err!(MetricsToken::from_str(""));
+        assert_err!(MetricsToken::from_str("@2024-06-01T00:00:00Z"));
+        assert_err!(MetricsToken::from_str("secret@tomorrow"));
+    }
+
+    #[test]
+    fn test_matches() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+
+        let token = MetricsToken::new("secret", None);
+        assert!(token.matches("secret", now));
+        assert!(!token.matches("foobar", now));
+
+        let token = MetricsToken::new("secret", Some(now + chrono::Duration::hours(1)));
+        assert!(token.matches("secret", now));
+        assert!(!token.matches("foobar", now));
+
+        let token = MetricsToken::new("secret", Some(now));
+        assert!(!token.matches("secret", now));
+    }
+}
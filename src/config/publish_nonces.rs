@@ -0,0 +1,38 @@
+use crates_io_env_vars::var_parsed;
+use std::time::Duration;
+
+/// Replay protection of publish requests (see
+/// [`crate::models::PublishNonce`]).
+///
+/// - `PUBLISH_NONCE_REQUIRED`: Require all publish requests to include a
+///   one-time nonce from `POST /api/v1/publish_nonce`. Disabled by default,
+///   in which case nonces are only checked if a request includes one.
+/// - `PUBLISH_NONCE_LIFETIME_SECONDS`: Seconds after which an unused nonce
+///   expires. Defaults to 5 minutes.
+#[derive(Debug, Clone)]
+pub struct PublishNonceConfig {
+    pub required: bool,
+    pub lifetime: Duration,
+}
+
+impl Default for PublishNonceConfig {
+    fn default() -> Self {
+        Self {
+            required: false,
+            lifetime: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl PublishNonceConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        Ok(Self {
+            required: var_parsed("PUBLISH_NONCE_REQUIRED")?.unwrap_or(default.required),
+            lifetime: var_parsed("PUBLISH_NONCE_LIFETIME_SECONDS")?
+                .map(Duration::from_secs)
+                .unwrap_or(default.lifetime),
+        })
+    }
+}
@@ -0,0 +1,188 @@
+use crate::rate_limiter::{LimitedAction, RateLimiterConfig};
+use crates_io_env_vars::{var, var_parsed};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::time::Duration;
+
+const DEFAULT_CDN_USER_AGENT: &str = "Amazon CloudFront";
+
+/// The settings that can be changed without restarting the server, by sending
+/// a SIGHUP to the server process or through the
+/// `POST /api/private/admin/config/reload` endpoint (see
+/// [`App::reload_config()`](crate::app::App::reload_config)).
+///
+/// - `RATE_LIMITER_{ACTION}_RATE_SECONDS` and `RATE_LIMITER_{ACTION}_BURST`:
+///   The rate limits of the actions in [`LimitedAction`].
+/// - `BLOCKED_TRAFFIC`: A list of headers and environment variables to use
+///   for blocking traffic. See the `block_traffic` module for more
+///   documentation.
+/// - `WEB_CDN_USER_AGENT`: The user agent of the CDN, which is treated like
+///   a missing user agent. Defaults to `Amazon CloudFront`.
+/// - `RUST_LOG`: The filter of the regular log output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReloadableConfig {
+    pub rate_limiter: HashMap<LimitedAction, RateLimiterConfig>,
+    pub blocked_traffic: Vec<(String, Vec<String>)>,
+    pub cdn_user_agent: String,
+    pub log_filter: Option<String>,
+}
+
+impl Default for ReloadableConfig {
+    fn default() -> Self {
+        Self {
+            rate_limiter: HashMap::new(),
+            blocked_traffic: vec![],
+            cdn_user_agent: DEFAULT_CDN_USER_AGENT.into(),
+            log_filter: None,
+        }
+    }
+}
+
+impl ReloadableConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        // Dynamically load the configuration for all the rate limiting actions. See
+        // `src/rate_limiter.rs` for their definition.
+        let mut rate_limiter = HashMap::new();
+        for action in LimitedAction::VARIANTS {
+            let env_var_key = action.env_var_key();
+            rate_limiter.insert(
+                *action,
+                RateLimiterConfig {
+                    rate: Duration::from_secs(
+                        var_parsed(&format!("RATE_LIMITER_{env_var_key}_RATE_SECONDS"))?
+                            .unwrap_or_else(|| action.default_rate_seconds()),
+                    ),
+                    burst: var_parsed(&format!("RATE_LIMITER_{env_var_key}_BURST"))?
+                        .unwrap_or_else(|| action.default_burst()),
+                },
+            );
+        }
+
+        Ok(Self {
+            rate_limiter,
+            blocked_traffic: blocked_traffic(),
+            cdn_user_agent: var("WEB_CDN_USER_AGENT")?
+                .unwrap_or_else(|| DEFAULT_CDN_USER_AGENT.into()),
+            log_filter: var("RUST_LOG")?,
+        })
+    }
+
+    /// Returns the settings that differ between `self` and `other`.
+    pub fn changes(&self, other: &Self) -> Vec<ConfigChange> {
+        let mut changes = vec![];
+
+        for action in LimitedAction::VARIANTS {
+            let old = self.rate_limiter.get(action);
+            let new = other.rate_limiter.get(action);
+            if old != new {
+                let setting = format!("RATE_LIMITER_{}", action.env_var_key());
+                changes.push(ConfigChange::new(setting, old, new));
+            }
+        }
+
+        if self.blocked_traffic != other.blocked_traffic {
+            let (old, new) = (&self.blocked_traffic, &other.blocked_traffic);
+            changes.push(ConfigChange::new("BLOCKED_TRAFFIC", old, new));
+        }
+
+        if self.cdn_user_agent != other.cdn_user_agent {
+            let (old, new) = (&self.cdn_user_agent, &other.cdn_user_agent);
+            changes.push(ConfigChange::new("WEB_CDN_USER_AGENT", old, new));
+        }
+
+        if self.log_filter != other.log_filter {
+            let (old, new) = (&self.log_filter, &other.log_filter);
+            changes.push(ConfigChange::new("RUST_LOG", old, new));
+        }
+
+        changes
+    }
+}
+
+/// A setting that was changed by a configuration reload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    pub setting: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl ConfigChange {
+    fn new(setting: impl Into<String>, old: impl Debug, new: impl Debug) -> Self {
+        Self {
+            setting: setting.into(),
+            old: format!("{old:?}"),
+            new: format!("{new:?}"),
+        }
+    }
+}
+
+fn blocked_traffic() -> Vec<(String, Vec<String>)> {
+    let pattern_list = dotenvy::var("BLOCKED_TRAFFIC").unwrap_or_default();
+    parse_traffic_patterns(&pattern_list)
+        .map(|(header, value_env_var)| {
+            let value_list = dotenvy::var(value_env_var).unwrap_or_default();
+            let values = value_list.split(',').map(String::from).collect();
+            (header.into(), values)
+        })
+        .collect()
+}
+
+fn parse_traffic_patterns(patterns: &str) -> impl Iterator<Item = (&str, &str)> {
+    patterns.split_terminator(',').map(|pattern| {
+        pattern.split_once('=').unwrap_or_else(|| {
+            panic!(
+                "BLOCKED_TRAFFIC must be in the form HEADER=VALUE_ENV_VAR, \
+                 got invalid pattern {pattern}"
+            )
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_traffic_patterns_splits_on_comma_and_looks_for_equal_sign() {
+        let pattern_string_1 = "Foo=BAR,Bar=BAZ";
+        let pattern_string_2 = "Baz=QUX";
+        let pattern_string_3 = "";
+
+        let patterns_1 = parse_traffic_patterns(pattern_string_1).collect::<Vec<_>>();
+        assert_eq!(vec![("Foo", "BAR"), ("Bar", "BAZ")], patterns_1);
+
+        let patterns_2 = parse_traffic_patterns(pattern_string_2).collect::<Vec<_>>();
+        assert_eq!(vec![("Baz", "QUX")], patterns_2);
+
+        assert_none!(parse_traffic_patterns(pattern_string_3).next());
+    }
+
+    #[test]
+    fn changes() {
+        let old = ReloadableConfig::default();
+        assert_eq!(old.changes(&old.clone()), vec![]);
+
+        let mut new = old.clone();
+        new.cdn_user_agent = "Fastly".into();
+        new.rate_limiter.insert(
+            LimitedAction::PublishNew,
+            RateLimiterConfig {
+                rate: Duration::from_secs(60),
+                burst: 10,
+            },
+        );
+
+        let changes = old.changes(&new);
+        let settings = changes
+            .iter()
+            .map(|c| c.setting.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            settings,
+            vec!["RATE_LIMITER_PUBLISH_NEW", "WEB_CDN_USER_AGENT"]
+        );
+        assert_eq!(changes[1].old, "\"Amazon CloudFront\"");
+        assert_eq!(changes[1].new, "\"Fastly\"");
+    }
+}
@@ -0,0 +1,43 @@
+use crates_io_env_vars::var_parsed;
+use std::time::Duration;
+
+/// The maximum time a request may take, by the kind of the route. Requests
+/// that take longer are aborted with a `503 Service Unavailable` response.
+///
+/// - `REQUEST_TIMEOUT`: Seconds for all routes that are not uploads or
+///   downloads, which are mostly quick JSON reads. Defaults to 30 seconds.
+/// - `UPLOAD_REQUEST_TIMEOUT`: Seconds for the publish route, which has to
+///   receive and process crate files of up to 10 MB. Defaults to 120
+///   seconds.
+/// - `DOWNLOAD_REQUEST_TIMEOUT`: Seconds for the crate and database dump
+///   download routes. Defaults to 300 seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimeoutConfig {
+    pub read: Duration,
+    pub upload: Duration,
+    pub download: Duration,
+}
+
+impl Default for RequestTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            read: Duration::from_secs(30),
+            upload: Duration::from_secs(120),
+            download: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RequestTimeoutConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        let secs = |key| Ok::<_, anyhow::Error>(var_parsed(key)?.map(Duration::from_secs));
+
+        Ok(Self {
+            read: secs("REQUEST_TIMEOUT")?.unwrap_or(default.read),
+            upload: secs("UPLOAD_REQUEST_TIMEOUT")?.unwrap_or(default.upload),
+            download: secs("DOWNLOAD_REQUEST_TIMEOUT")?.unwrap_or(default.download),
+        })
+    }
+}
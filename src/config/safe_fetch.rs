@@ -0,0 +1,50 @@
+use crates_io_env_vars::{list, var_parsed};
+use std::time::Duration;
+
+/// Limits of the HTTP requests to user-supplied URLs (see
+/// [`crate::safe_fetch`]).
+///
+/// - `SAFE_FETCH_ALLOWED_DOMAINS`: Comma-separated list of domains that may
+///   be fetched, including their subdomains. If unset, all domains that
+///   resolve to public IP addresses may be fetched.
+/// - `SAFE_FETCH_MAX_RESPONSE_SIZE`: The maximum size of a response body in
+///   bytes. Defaults to 1 MiB.
+/// - `SAFE_FETCH_TIMEOUT_SECONDS`: The maximum duration of a request,
+///   including all redirects and the download of the response body. Defaults
+///   to 10 seconds.
+#[derive(Debug, Clone)]
+pub struct SafeFetchConfig {
+    pub allowed_domains: Vec<String>,
+    pub max_response_size: u64,
+    pub timeout: Duration,
+}
+
+impl Default for SafeFetchConfig {
+    fn default() -> Self {
+        Self {
+            allowed_domains: vec![],
+            max_response_size: 1024 * 1024,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl SafeFetchConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        let allowed_domains = list("SAFE_FETCH_ALLOWED_DOMAINS")?
+            .into_iter()
+            .map(|domain| domain.trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+
+        Ok(Self {
+            allowed_domains,
+            max_response_size: var_parsed("SAFE_FETCH_MAX_RESPONSE_SIZE")?
+                .unwrap_or(default.max_response_size),
+            timeout: var_parsed("SAFE_FETCH_TIMEOUT_SECONDS")?
+                .map(Duration::from_secs)
+                .unwrap_or(default.timeout),
+        })
+    }
+}
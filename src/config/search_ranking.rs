@@ -0,0 +1,175 @@
+use anyhow::{anyhow, bail, Context};
+use crates_io_env_vars::var;
+use std::collections::HashMap;
+
+/// The weights of the factors that make up the relevance score of a crate in
+/// the search results.
+///
+/// The defaults rank exact name matches first, followed by the full-text
+/// search rank, which matches the ranking before the weights were
+/// configurable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankingWeights {
+    /// Weight of the exact match factor, which is `1` if the crate name
+    /// matches the query and `0` otherwise.
+    pub exact_match: f64,
+    /// Weight of the full-text search rank of the crate.
+    pub text: f64,
+    /// Weight of the decimal logarithm of the total downloads of the crate.
+    pub downloads: f64,
+    /// Weight of the time of the last update of the crate, in years.
+    pub recency: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            exact_match: 1000.,
+            text: 1.,
+            downloads: 0.,
+            recency: 0.,
+        }
+    }
+}
+
+impl RankingWeights {
+    /// Returns a copy of these weights, with the factors in `overrides`
+    /// replaced.
+    ///
+    /// The overrides are a comma-separated list of `<factor>:<weight>` pairs,
+    /// e.g. `downloads:0.5,recency:0.1`.
+    fn with_overrides(mut self, overrides: &str) -> anyhow::Result<Self> {
+        for pair in overrides
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            let (factor, weight) = pair
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Expected `<factor>:<weight>`, got `{pair}`"))?;
+
+            let weight = weight
+                .trim()
+                .parse()
+                .with_context(|| format!("Failed to parse the weight of `{factor}`"))?;
+
+            match factor.trim() {
+                "exact_match" => self.exact_match = weight,
+                "text" => self.text = weight,
+                "downloads" => self.downloads = weight,
+                "recency" => self.recency = weight,
+                factor => bail!("Unknown search ranking factor: `{factor}`"),
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+/// Configuration of the relevance ranking of the crate search.
+///
+/// - `SEARCH_RANKING_WEIGHTS`: Overrides of the default weights, e.g.
+///   `downloads:0.5,recency:0.1`.
+/// - `SEARCH_RANKING_EXPERIMENTS`: A semicolon-separated list of alternative
+///   weightings that can be selected with the `experiment` query parameter,
+///   e.g. `popular=downloads:0.5;fresh=recency:1`. The weights of an
+///   experiment are applied on top of `SEARCH_RANKING_WEIGHTS`.
+#[derive(Debug, Clone, Default)]
+pub struct SearchRankingConfig {
+    pub weights: RankingWeights,
+    pub experiments: HashMap<String, RankingWeights>,
+}
+
+impl SearchRankingConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let weights = var("SEARCH_RANKING_WEIGHTS")?;
+        let experiments = var("SEARCH_RANKING_EXPERIMENTS")?;
+        Self::parse(weights.as_deref(), experiments.as_deref())
+    }
+
+    fn parse(weights: Option<&str>, experiments: Option<&str>) -> anyhow::Result<Self> {
+        let weights = RankingWeights::default()
+            .with_overrides(weights.unwrap_or_default())
+            .context("Failed to parse `SEARCH_RANKING_WEIGHTS`")?;
+
+        let experiments = experiments
+            .unwrap_or_default()
+            .split(';')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|experiment| {
+                let (name, overrides) = experiment
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Expected `<name>=<weights>`, got `{experiment}`"))?;
+
+                let overrides = weights
+                    .with_overrides(overrides)
+                    .with_context(|| format!("Failed to parse the `{name}` experiment"))?;
+
+                Ok((name.trim().to_string(), overrides))
+            })
+            .collect::<anyhow::Result<_>>()
+            .context("Failed to parse `SEARCH_RANKING_EXPERIMENTS`")?;
+
+        Ok(Self {
+            weights,
+            experiments,
+        })
+    }
+
+    /// Returns the weights of the given experiment, or `None` if there is no
+    /// such experiment.
+    pub fn experiment(&self, name: &str) -> Option<&RankingWeights> {
+        self.experiments.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_defaults() {
+        let config = assert_ok!(SearchRankingConfig::parse(None, None));
+        assert_eq!(config.weights, RankingWeights::default());
+        assert!(config.experiments.is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        let weights = Some("downloads:0.5, recency:0.1");
+        let experiments = Some("popular=downloads:2;fresh=recency:1,text:2");
+        let config = assert_ok!(SearchRankingConfig::parse(weights, experiments));
+
+        let expected = RankingWeights {
+            downloads: 0.5,
+            recency: 0.1,
+            ..Default::default()
+        };
+        assert_eq!(config.weights, expected);
+
+        let popular = RankingWeights {
+            downloads: 2.,
+            ..expected
+        };
+        assert_some_eq!(config.experiment("popular"), &popular);
+
+        let fresh = RankingWeights {
+            recency: 1.,
+            text: 2.,
+            ..expected
+        };
+        assert_some_eq!(config.experiment("fresh"), &fresh);
+
+        assert_none!(config.experiment("unknown"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_err!(SearchRankingConfig::parse(Some("downloads"), None));
+        assert_err!(SearchRankingConfig::parse(Some("downloads:lots"), None));
+        assert_err!(SearchRankingConfig::parse(Some("stars:1"), None));
+        assert_err!(SearchRankingConfig::parse(None, Some("popular")));
+        assert_err!(SearchRankingConfig::parse(None, Some("popular=stars:1")));
+    }
+}
@@ -1,25 +1,35 @@
 use anyhow::{anyhow, Context};
 use ipnetwork::IpNetwork;
 use oauth2::{ClientId, ClientSecret};
+use secrecy::SecretString;
 
-use crate::rate_limiter::{LimitedAction, RateLimiterConfig};
+use crate::index_signing::IndexSigningKey;
 use crate::Env;
 
 use super::base::Base;
 use super::database_pools::DatabasePools;
 use crate::config::cdn_log_storage::CdnLogStorageConfig;
-use crate::config::CdnLogQueueConfig;
+use crate::config::{
+    ApiDeprecationConfig, ApiQuotaConfig, AuditExportConfig, AuthFailureLimiterConfig,
+    CdnFallbackConfig, CdnLogQueueConfig, ChallengeConfig, ClientIpConfig,
+    DownloadRateLimiterConfig, DownloadSpikeConfig, HttpServerConfig, MetricsToken,
+    PublishNonceConfig, ReloadableConfig, RequestTimeoutConfig, SafeFetchConfig,
+    SearchRankingConfig, TlsConfig, TokenAnomalyConfig, UpstreamConfig,
+};
 use crate::middleware::cargo_compat::StatusCodeConfig;
 use crate::storage::StorageConfig;
+use crate::util::ip_blocklist::IpBlocklist;
 use crates_io_env_vars::{list, list_parsed, required_var, var, var_parsed};
 use http::HeaderValue;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::net::IpAddr;
 use std::str::FromStr;
 use std::time::Duration;
 
 const DEFAULT_VERSION_ID_CACHE_SIZE: u64 = 10_000;
 const DEFAULT_VERSION_ID_CACHE_TTL: u64 = 5 * 60; // 5 minutes
+const DEFAULT_CRATE_ID_CACHE_SIZE: u64 = 10_000;
+const DEFAULT_CRATE_ID_CACHE_TTL: u64 = 5 * 60; // 5 minutes
 
 /// Maximum number of features a crate can have or that a feature itself can
 /// enable. This value can be overridden in the database on a per-crate basis.
@@ -28,6 +38,12 @@ const DEFAULT_MAX_FEATURES: usize = 300;
 /// Maximum number of dependencies a crate can have.
 const DEFAULT_MAX_DEPENDENCIES: usize = 500;
 
+/// Concurrency limits of routes that are always capped, since each of their
+/// requests can poll the primary database for a long time. They can be
+/// overridden through `ROUTE_CONCURRENCY_LIMITS`.
+const DEFAULT_ROUTE_CONCURRENCY_LIMITS: &[(&str, usize)] =
+    &[("/api/v1/crates/:crate_id/:version/status", 50)];
+
 pub struct Server {
     pub base: Base,
     pub ip: IpAddr,
@@ -44,24 +60,44 @@ pub struct Server {
     pub max_unpack_size: u64,
     pub max_dependencies: usize,
     pub max_features: usize,
-    pub rate_limiter: HashMap<LimitedAction, RateLimiterConfig>,
+    /// The settings that can be changed without a restart. The current
+    /// values are available through
+    /// [`App::reloadable_config()`](crate::app::App::reloadable_config).
+    pub reloadable: ReloadableConfig,
+    /// Optional Redis connection URL. If set, the rate limiter buckets are
+    /// kept in Redis instead of the database.
+    pub rate_limiter_redis_url: Option<SecretString>,
     pub new_version_rate_limit: Option<u32>,
-    pub blocked_traffic: Vec<(String, Vec<String>)>,
-    pub blocked_ips: HashSet<IpAddr>,
+    pub download_rate_limiter: Option<DownloadRateLimiterConfig>,
+    /// Delays and blocks clients with too many failed authentication
+    /// attempts.
+    pub auth_failure_limiter: AuthFailureLimiterConfig,
+    /// Clients whose IP address is in one of these IPv4 or IPv6 CIDR blocks
+    /// are blocked completely.
+    pub blocked_ips: IpBlocklist,
+    /// How the IP address of the client is determined from the
+    /// `X-Forwarded-For`, `Forwarded` and `CloudFront-Viewer-Address` headers.
+    pub client_ip: ClientIpConfig,
     pub max_allowed_page_offset: u32,
     pub page_offset_ua_blocklist: Vec<String>,
-    pub page_offset_cidr_blocklist: Vec<IpNetwork>,
-    pub excluded_crate_names: Vec<String>,
+    pub page_offset_cidr_blocklist: IpBlocklist,
     pub domain_name: String,
     pub allowed_origins: AllowedOrigins,
     pub downloads_persist_interval: Duration,
     pub ownership_invitations_expiration_days: u64,
-    pub metrics_authorization_token: Option<String>,
+    /// Tokens that grant access to the metrics endpoints. Metrics are
+    /// disabled if there are no tokens and client certificates are not
+    /// verified.
+    pub metrics_authorization_tokens: Vec<MetricsToken>,
     pub instance_metrics_log_every_seconds: Option<u64>,
-    pub blocked_routes: HashSet<String>,
+    /// The maximum number of in-flight requests per HTTP route pattern.
+    /// Requests above the limit are rejected with a `503 Service Unavailable`
+    /// response.
+    pub route_concurrency_limits: HashMap<String, usize>,
     pub version_id_cache_size: u64,
     pub version_id_cache_ttl: Duration,
-    pub cdn_user_agent: String,
+    pub crate_id_cache_size: u64,
+    pub crate_id_cache_ttl: Duration,
 
     /// Instructs the `cargo_compat` middleware whether to adjust response
     /// status codes to `200 OK` for all endpoints that are relevant for cargo.
@@ -75,6 +111,76 @@ pub struct Server {
     pub serve_html: bool,
 
     pub content_security_policy: Option<HeaderValue>,
+
+    /// Allows the `reset_sandbox` background job to replace all crates, users
+    /// and API tokens with the sandbox fixtures. Must only be enabled for
+    /// staging environments.
+    pub sandbox_reset_enabled: bool,
+
+    /// The challenge that users have to solve if their sign-in or the
+    /// acceptance of an ownership invitation looks suspicious. Disabled if
+    /// `None`.
+    pub challenge: Option<ChallengeConfig>,
+
+    /// Terminate TLS in the server process, optionally verifying client
+    /// certificates for the metrics endpoints.
+    pub tls: Option<TlsConfig>,
+
+    /// Connection-level settings of the HTTP server, like HTTP/2 support and
+    /// keep-alive timeouts.
+    pub http: HttpServerConfig,
+
+    /// The maximum time a request may take, which is longer for uploads and
+    /// downloads than for all other routes.
+    pub request_timeouts: RequestTimeoutConfig,
+
+    /// Keys for signing the files of the sparse index. Index files are not
+    /// signed if this is empty.
+    pub index_signing_keys: Vec<IndexSigningKey>,
+
+    /// The weights of the relevance ranking of the crate search, and the
+    /// alternative weightings that are being evaluated.
+    pub search_ranking: SearchRankingConfig,
+
+    /// Recipients of the weekly crate owner invitation report. The report is
+    /// not sent if this is empty.
+    pub invitation_report_emails: Vec<String>,
+
+    /// Thresholds of the detection of unusual API token activity, and whether
+    /// publishing is locked for the affected users.
+    pub token_anomalies: TokenAnomalyConfig,
+
+    /// Thresholds of the detection of unusual daily crate downloads, and who
+    /// is notified about them.
+    pub download_spikes: DownloadSpikeConfig,
+
+    /// The upstream registry whose crates are served through this registry,
+    /// for private registry deployments. Disabled if `None`.
+    pub upstream: Option<UpstreamConfig>,
+
+    /// Limits of the HTTP requests to user-supplied URLs.
+    pub safe_fetch: SafeFetchConfig,
+
+    /// Serves the images of rendered READMEs through the image proxy, see
+    /// [`crate::util::image_proxy`].
+    pub readme_image_proxy: bool,
+
+    /// Monthly quotas of the authenticated API requests.
+    pub api_quotas: ApiQuotaConfig,
+
+    /// The external SIEM to which the audit events are exported, in
+    /// addition to the database. Disabled if `None`.
+    pub audit_export: Option<AuditExportConfig>,
+
+    /// The API routes and query parameters that are slated for removal.
+    pub api_deprecations: ApiDeprecationConfig,
+
+    /// Replay protection of publish requests with one-time nonces.
+    pub publish_nonces: PublishNonceConfig,
+
+    /// Health probes of the CDN, and the fallback of downloads during CDN
+    /// incidents.
+    pub cdn_fallback: CdnFallbackConfig,
 }
 
 impl Server {
@@ -90,8 +196,8 @@ impl Server {
     /// - `SESSION_KEY`: The key used to sign and encrypt session cookies.
     /// - `GH_CLIENT_ID`: The client ID of the associated GitHub application.
     /// - `GH_CLIENT_SECRET`: The client secret of the associated GitHub application.
-    /// - `BLOCKED_TRAFFIC`: A list of headers and environment variables to use for blocking
-    ///   traffic. See the `block_traffic` module for more documentation.
+    /// - `BLOCKED_IPS`: A comma separated list of IPv4 or IPv6 addresses or CIDR blocks, e.g.
+    ///   `192.168.1.0/24` or `2001:db8::/64`, whose requests are blocked completely.
     /// - `DOWNLOADS_PERSIST_INTERVAL_MS`: how frequent to persist download counts (in ms).
     /// - `METRICS_AUTHORIZATION_TOKEN`: authorization token needed to query metrics. If missing,
     ///   querying metrics will be completely disabled.
@@ -101,14 +207,31 @@ impl Server {
     ///   be blocked if `WEB_MAX_ALLOWED_PAGE_OFFSET` is exceeded. Including an empty string in the
     ///   list will block *all* user-agents exceeding the offset. If not set or empty, no blocking
     ///   will occur.
-    /// - `WEB_PAGE_OFFSET_CIDR_BLOCKLIST`: A comma separated list of CIDR blocks that will be used
-    ///   to block IP addresses, e.g. `192.168.1.0/24`. If not set or empty, no blocking will occur.
+    /// - `WEB_PAGE_OFFSET_CIDR_BLOCKLIST`: A comma separated list of IPv4 or IPv6 CIDR blocks that
+    ///   will be used to block IP addresses, e.g. `192.168.1.0/24` or `2001:db8::/64`. If not set
+    ///   or empty, no blocking will occur.
     /// - `INSTANCE_METRICS_LOG_EVERY_SECONDS`: How frequently should instance metrics be logged.
     ///   If the environment variable is not present instance metrics are not logged.
     /// - `FORCE_UNCONDITIONAL_REDIRECTS`: Whether to force unconditional redirects in the download
     ///   endpoint even with a healthy database pool.
-    /// - `BLOCKED_ROUTES`: A comma separated list of HTTP route patterns that are manually blocked
-    ///   by an operator (e.g. `/crates/:crate_id/:version/download`).
+    /// - `ROUTE_CONCURRENCY_LIMITS`: A comma separated list of HTTP route patterns and the maximum
+    ///   number of their in-flight requests (e.g. `/api/v1/crates/:crate_id/reverse_dependencies=10`).
+    ///   The long-polling version status route is limited to 50 requests unless configured here.
+    /// - `RATE_LIMITER_REDIS_URL`: If set, the state of the publish, yank and name reservation
+    ///   rate limits is kept in Redis instead of the database.
+    /// - `DOWNLOAD_RATE_LIMITER_BURST`: Enables IP-based rate limiting of the download endpoint
+    ///   with the given number of requests that can be performed in a burst.
+    /// - `DOWNLOAD_RATE_LIMITER_RATE_MS`: How often (in ms) a client regains a download request.
+    ///   Defaults to 100.
+    /// - `DOWNLOAD_RATE_LIMITER_REDIS_URL`: If set, the download rate limiter state is shared
+    ///   between server instances through Redis instead of being kept in memory.
+    /// - `SANDBOX_RESET_ENABLED`: Allows resetting the registry to the sandbox fixtures. Must only
+    ///   be set for staging environments.
+    /// - `README_IMAGE_PROXY_ENABLED`: Serves the images of READMEs that are rendered from now on
+    ///   through the image proxy of this domain.
+    ///
+    /// The settings that can be reloaded without a restart are documented on
+    /// [`ReloadableConfig`].
     ///
     /// # Panics
     ///
@@ -125,36 +248,28 @@ impl Server {
 
         let port = var_parsed("PORT")?.unwrap_or(8888);
 
-        let blocked_ips = HashSet::from_iter(list_parsed("BLOCKED_IPS", IpAddr::from_str)?);
+        let blocked_ips = IpBlocklist::from_iter(list_parsed("BLOCKED_IPS", parse_cidr_block)?);
 
         let allowed_origins = AllowedOrigins::from_default_env()?;
         let page_offset_ua_blocklist = list("WEB_PAGE_OFFSET_UA_BLOCKLIST")?;
-        let page_offset_cidr_blocklist =
-            list_parsed("WEB_PAGE_OFFSET_CIDR_BLOCKLIST", parse_cidr_block)?;
+        let page_offset_cidr_blocklist = IpBlocklist::from_iter(list_parsed(
+            "WEB_PAGE_OFFSET_CIDR_BLOCKLIST",
+            parse_cidr_block,
+        )?);
+
+        let mut route_concurrency_limits: HashMap<String, usize> = DEFAULT_ROUTE_CONCURRENCY_LIMITS
+            .iter()
+            .map(|(route, limit)| (route.to_string(), *limit))
+            .collect();
+        route_concurrency_limits.extend(list_parsed(
+            "ROUTE_CONCURRENCY_LIMITS",
+            parse_route_concurrency_limit,
+        )?);
 
         let base = Base::from_environment()?;
-        let excluded_crate_names = list("EXCLUDED_CRATE_NAMES")?;
 
         let max_blocking_threads = var_parsed("SERVER_THREADS")?;
 
-        // Dynamically load the configuration for all the rate limiting actions. See
-        // `src/rate_limiter.rs` for their definition.
-        let mut rate_limiter = HashMap::new();
-        for action in LimitedAction::VARIANTS {
-            let env_var_key = action.env_var_key();
-            rate_limiter.insert(
-                *action,
-                RateLimiterConfig {
-                    rate: Duration::from_secs(
-                        var_parsed(&format!("RATE_LIMITER_{env_var_key}_RATE_SECONDS"))?
-                            .unwrap_or_else(|| action.default_rate_seconds()),
-                    ),
-                    burst: var_parsed(&format!("RATE_LIMITER_{env_var_key}_BURST"))?
-                        .unwrap_or_else(|| action.default_burst()),
-                },
-            );
-        }
-
         let storage = StorageConfig::from_environment();
 
         // `sha256-dbf9FMl76C7BnK1CC3eWb3pvsQAUaTYSHAlBy9tNTG0=` refers to
@@ -186,35 +301,58 @@ impl Server {
             max_unpack_size: 512 * 1024 * 1024, // 512 MB max when decompressed
             max_dependencies: DEFAULT_MAX_DEPENDENCIES,
             max_features: DEFAULT_MAX_FEATURES,
-            rate_limiter,
+            reloadable: ReloadableConfig::from_env()?,
+            rate_limiter_redis_url: var("RATE_LIMITER_REDIS_URL")?.map(Into::into),
             new_version_rate_limit: var_parsed("MAX_NEW_VERSIONS_DAILY")?,
-            blocked_traffic: blocked_traffic(),
+            download_rate_limiter: DownloadRateLimiterConfig::from_env()?,
+            auth_failure_limiter: AuthFailureLimiterConfig::from_env()?,
             blocked_ips,
+            client_ip: ClientIpConfig::from_env()?,
             max_allowed_page_offset: var_parsed("WEB_MAX_ALLOWED_PAGE_OFFSET")?.unwrap_or(200),
             page_offset_ua_blocklist,
             page_offset_cidr_blocklist,
-            excluded_crate_names,
             domain_name: dotenvy::var("DOMAIN_NAME").unwrap_or_else(|_| "crates.io".into()),
             allowed_origins,
             downloads_persist_interval: var_parsed("DOWNLOADS_PERSIST_INTERVAL_MS")?
                 .map(Duration::from_millis)
                 .unwrap_or(Duration::from_secs(60)),
             ownership_invitations_expiration_days: 30,
-            metrics_authorization_token: var("METRICS_AUTHORIZATION_TOKEN")?,
+            metrics_authorization_tokens: MetricsToken::from_env()?,
             instance_metrics_log_every_seconds: var_parsed("INSTANCE_METRICS_LOG_EVERY_SECONDS")?,
-            blocked_routes: HashSet::from_iter(list("BLOCKED_ROUTES")?),
+            route_concurrency_limits,
             version_id_cache_size: var_parsed("VERSION_ID_CACHE_SIZE")?
                 .unwrap_or(DEFAULT_VERSION_ID_CACHE_SIZE),
             version_id_cache_ttl: Duration::from_secs(
                 var_parsed("VERSION_ID_CACHE_TTL")?.unwrap_or(DEFAULT_VERSION_ID_CACHE_TTL),
             ),
-            cdn_user_agent: var("WEB_CDN_USER_AGENT")?
-                .unwrap_or_else(|| "Amazon CloudFront".into()),
+            crate_id_cache_size: var_parsed("CRATE_ID_CACHE_SIZE")?
+                .unwrap_or(DEFAULT_CRATE_ID_CACHE_SIZE),
+            crate_id_cache_ttl: Duration::from_secs(
+                var_parsed("CRATE_ID_CACHE_TTL")?.unwrap_or(DEFAULT_CRATE_ID_CACHE_TTL),
+            ),
             cargo_compat_status_code_config: var_parsed("CARGO_COMPAT_STATUS_CODES")?
                 .unwrap_or(StatusCodeConfig::AdjustAll),
             serve_dist: true,
             serve_html: true,
             content_security_policy: Some(content_security_policy.parse()?),
+            sandbox_reset_enabled: var("SANDBOX_RESET_ENABLED")?.is_some(),
+            challenge: ChallengeConfig::from_env()?,
+            tls: TlsConfig::from_env()?,
+            http: HttpServerConfig::from_env()?,
+            request_timeouts: RequestTimeoutConfig::from_env()?,
+            index_signing_keys: list_parsed("INDEX_SIGNING_KEYS", IndexSigningKey::from_str)?,
+            search_ranking: SearchRankingConfig::from_env()?,
+            invitation_report_emails: list("INVITATION_REPORT_EMAILS")?,
+            token_anomalies: TokenAnomalyConfig::from_env()?,
+            download_spikes: DownloadSpikeConfig::from_env()?,
+            upstream: UpstreamConfig::from_env()?,
+            safe_fetch: SafeFetchConfig::from_env()?,
+            readme_image_proxy: var("README_IMAGE_PROXY_ENABLED")?.is_some(),
+            api_quotas: ApiQuotaConfig::from_env()?,
+            audit_export: AuditExportConfig::from_env()?,
+            api_deprecations: ApiDeprecationConfig::from_env()?,
+            publish_nonces: PublishNonceConfig::from_env()?,
+            cdn_fallback: CdnFallbackConfig::from_env()?,
         })
     }
 }
@@ -227,7 +365,9 @@ impl Server {
 
 /// Parses a CIDR block string to a valid `IpNetwork` struct.
 ///
-/// The purpose is to be able to block IP ranges that overload the API that uses pagination.
+/// The purpose is to be able to block IP ranges that overload the API, e.g.
+/// the API that uses pagination. Single IPv4 or IPv6 addresses are parsed as
+/// blocks that only contain that address.
 ///
 /// The minimum number of bits for a host prefix must be
 ///
@@ -237,7 +377,7 @@ impl Server {
 fn parse_cidr_block(block: &str) -> anyhow::Result<IpNetwork> {
     let cidr = block
         .parse()
-        .context("WEB_PAGE_OFFSET_CIDR_BLOCKLIST must contain IPv4 or IPv6 CIDR blocks.")?;
+        .context("Expected an IPv4 or IPv6 address or CIDR block")?;
 
     let host_prefix = match cidr {
         IpNetwork::V4(_) => 16,
@@ -245,32 +385,25 @@ fn parse_cidr_block(block: &str) -> anyhow::Result<IpNetwork> {
     };
 
     if cidr.prefix() < host_prefix {
-        return Err(anyhow!("WEB_PAGE_OFFSET_CIDR_BLOCKLIST only allows CIDR blocks with a host prefix of at least 16 bits (IPv4) or 64 bits (IPv6)."));
+        return Err(anyhow!("Only CIDR blocks with a host prefix of at least 16 bits (IPv4) or 64 bits (IPv6) are allowed"));
     }
 
     Ok(cidr)
 }
 
-fn blocked_traffic() -> Vec<(String, Vec<String>)> {
-    let pattern_list = dotenvy::var("BLOCKED_TRAFFIC").unwrap_or_default();
-    parse_traffic_patterns(&pattern_list)
-        .map(|(header, value_env_var)| {
-            let value_list = dotenvy::var(value_env_var).unwrap_or_default();
-            let values = value_list.split(',').map(String::from).collect();
-            (header.into(), values)
-        })
-        .collect()
-}
+/// Parses a `ROUTE=LIMIT` pair of the `ROUTE_CONCURRENCY_LIMITS` environment
+/// variable.
+fn parse_route_concurrency_limit(pair: &str) -> anyhow::Result<(String, usize)> {
+    let (route, limit) = pair
+        .split_once('=')
+        .context("ROUTE_CONCURRENCY_LIMITS must be in the form ROUTE=LIMIT")?;
 
-fn parse_traffic_patterns(patterns: &str) -> impl Iterator<Item = (&str, &str)> {
-    patterns.split_terminator(',').map(|pattern| {
-        pattern.split_once('=').unwrap_or_else(|| {
-            panic!(
-                "BLOCKED_TRAFFIC must be in the form HEADER=VALUE_ENV_VAR, \
-                 got invalid pattern {pattern}"
-            )
-        })
-    })
+    let limit = limit
+        .trim()
+        .parse()
+        .context("ROUTE_CONCURRENCY_LIMITS must contain numeric limits")?;
+
+    Ok((route.trim().to_string(), limit))
 }
 
 #[derive(Clone, Debug, Default)]
@@ -296,18 +429,16 @@ mod tests {
     use super::*;
 
     #[test]
-    fn parse_traffic_patterns_splits_on_comma_and_looks_for_equal_sign() {
-        let pattern_string_1 = "Foo=BAR,Bar=BAZ";
-        let pattern_string_2 = "Baz=QUX";
-        let pattern_string_3 = "";
-
-        let patterns_1 = parse_traffic_patterns(pattern_string_1).collect::<Vec<_>>();
-        assert_eq!(vec![("Foo", "BAR"), ("Bar", "BAZ")], patterns_1);
-
-        let patterns_2 = parse_traffic_patterns(pattern_string_2).collect::<Vec<_>>();
-        assert_eq!(vec![("Baz", "QUX")], patterns_2);
-
-        assert_none!(parse_traffic_patterns(pattern_string_3).next());
+    fn parse_route_concurrency_limits() {
+        assert_ok_eq!(
+            parse_route_concurrency_limit("/api/v1/crates/:crate_id/reverse_dependencies=10"),
+            (
+                "/api/v1/crates/:crate_id/reverse_dependencies".to_string(),
+                10
+            )
+        );
+        assert_err!(parse_route_concurrency_limit("/api/v1/crates"));
+        assert_err!(parse_route_concurrency_limit("/api/v1/crates=many"));
     }
 
     #[test]
@@ -322,6 +453,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_single_addresses_as_cidr_blocks() {
+        assert_ok_eq!(
+            parse_cidr_block("192.168.0.1"),
+            "192.168.0.1/32".parse::<IpNetwork>().unwrap()
+        );
+        assert_ok_eq!(
+            parse_cidr_block("2001:db8::1"),
+            "2001:db8::1/128".parse::<IpNetwork>().unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_invalid_cidr_blocks() {
+        assert_err!(parse_cidr_block(""));
+        assert_err!(parse_cidr_block("192.168.0.0/33"));
+        assert_err!(parse_cidr_block("2001:db8::/129"));
+        assert_err!(parse_cidr_block("2001:db8::/sixty-four"));
+        assert_err!(parse_cidr_block("crates.io"));
+    }
+
     #[test]
     fn parse_cidr_blocks_panics_when_host_ipv4_prefix_is_too_low() {
         assert_err!(parse_cidr_block("127.0.0.1/8"));
@@ -0,0 +1,43 @@
+use crates_io_env_vars::{required_var, var};
+use std::path::PathBuf;
+
+/// Configuration for terminating TLS in the server process itself, instead
+/// of in a load balancer or reverse proxy in front of it.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// Path to the PEM encoded certificate chain of the server.
+    pub cert_path: PathBuf,
+    /// Path to the PEM encoded private key of the server.
+    pub key_path: PathBuf,
+    /// Optional path to PEM encoded CA certificates. If set, clients may
+    /// present a certificate signed by one of these CAs, which grants them
+    /// access to the metrics endpoints.
+    pub client_ca_path: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Reads the TLS configuration from the environment.
+    ///
+    /// Returns `None` if `TLS_CERT_PATH` is not set, in which case the server
+    /// accepts plain HTTP connections.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(cert_path) = var("TLS_CERT_PATH")? else {
+            return Ok(None);
+        };
+
+        let key_path = required_var("TLS_KEY_PATH")?;
+        let client_ca_path = var("TLS_CLIENT_CA_PATH")?;
+
+        Ok(Some(Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            client_ca_path: client_ca_path.map(Into::into),
+        }))
+    }
+
+    /// Returns whether clients are allowed to authenticate with a client
+    /// certificate.
+    pub fn verifies_client_certificates(&self) -> bool {
+        self.client_ca_path.is_some()
+    }
+}
@@ -0,0 +1,45 @@
+use crates_io_env_vars::var_parsed;
+use std::time::Duration;
+
+/// Configuration of the `detect_token_anomalies` background job.
+///
+/// - `TOKEN_ANOMALY_LOCK_PUBLISHES`: Block publishing for users with unusual
+///   token activity until they confirm the activity via email. Disabled by
+///   default, in which case the users are only notified.
+/// - `TOKEN_ANOMALY_MAX_PUBLISHES_PER_HOUR`: Number of publishes within one
+///   hour above which the activity is considered a publish burst. Defaults
+///   to 50.
+/// - `TOKEN_ANOMALY_MIN_TRAVEL_TIME`: Seconds that have to pass between
+///   token usages from two different networks. Defaults to 5 minutes.
+#[derive(Debug, Clone)]
+pub struct TokenAnomalyConfig {
+    pub lock_publishes: bool,
+    pub max_publishes_per_hour: usize,
+    pub min_travel_time: Duration,
+}
+
+impl Default for TokenAnomalyConfig {
+    fn default() -> Self {
+        Self {
+            lock_publishes: false,
+            max_publishes_per_hour: 50,
+            min_travel_time: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl TokenAnomalyConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let default = Self::default();
+
+        Ok(Self {
+            lock_publishes: var_parsed("TOKEN_ANOMALY_LOCK_PUBLISHES")?
+                .unwrap_or(default.lock_publishes),
+            max_publishes_per_hour: var_parsed("TOKEN_ANOMALY_MAX_PUBLISHES_PER_HOUR")?
+                .unwrap_or(default.max_publishes_per_hour),
+            min_travel_time: var_parsed("TOKEN_ANOMALY_MIN_TRAVEL_TIME")?
+                .map(Duration::from_secs)
+                .unwrap_or(default.min_travel_time),
+        })
+    }
+}
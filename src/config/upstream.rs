@@ -0,0 +1,32 @@
+use crates_io_env_vars::var;
+
+const DEFAULT_DL_URL: &str = "https://static.crates.io/crates";
+
+/// Configuration of the upstream-proxy mode for private registry deployments
+/// (see [`crate::upstream`]).
+///
+/// - `UPSTREAM_INDEX_URL`: The URL of the sparse index of the upstream
+///   registry, e.g. `https://index.crates.io`. The upstream-proxy mode is
+///   disabled if this is not set.
+/// - `UPSTREAM_DL_URL`: The base URL of the crate files of the upstream
+///   registry. Defaults to `https://static.crates.io/crates`.
+#[derive(Debug, Clone)]
+pub struct UpstreamConfig {
+    pub index_url: String,
+    pub dl_url: String,
+}
+
+impl UpstreamConfig {
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(index_url) = var("UPSTREAM_INDEX_URL")? else {
+            return Ok(None);
+        };
+
+        let dl_url = var("UPSTREAM_DL_URL")?.unwrap_or_else(|| DEFAULT_DL_URL.into());
+
+        Ok(Some(Self {
+            index_url: index_url.trim_end_matches('/').to_string(),
+            dl_url: dl_url.trim_end_matches('/').to_string(),
+        }))
+    }
+}
@@ -63,16 +63,24 @@ mod prelude {
 pub mod helpers;
 pub mod util;
 
+pub mod admin;
+pub mod build_info;
 pub mod category;
 pub mod crate_owner_invitation;
+pub mod dependency;
 pub mod git;
 pub mod github;
+pub mod index_signing;
 pub mod keyword;
 pub mod krate;
 pub mod metrics;
+pub mod readme_image;
 pub mod site_metadata;
+pub mod sitemap;
+pub mod stats;
 pub mod summary;
 pub mod team;
 pub mod token;
+pub mod upstream;
 pub mod user;
 pub mod version;
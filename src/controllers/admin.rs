@@ -0,0 +1,1010 @@
+//! Endpoints that are only available to crates.io administrators.
+
+use crate::app::App;
+use crate::audit::AuditEvent;
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::util::RequestPartsExt;
+use crate::email::Email;
+use crate::models::{
+    BlockedRoute, Category, Crate, CrateOwnerAction, ExcludedCrateName, NewCategoryMigration,
+    NewCategorySynonym, NewVersionQuarantine, OwnerAction, OwnerActionVia, OwnerKind,
+    QuarantineResolution, User, Version, VersionAction, VersionQuarantine,
+};
+use crate::schema::{
+    background_job_progress, background_jobs, crate_owner_actions, crate_owners, download_spikes,
+    emails, typosquat_flags, users, version_owner_actions, version_quarantines, versions,
+};
+use crate::util::errors::{crate_not_found, custom};
+use crate::views::EncodableOwner;
+use crate::worker::jobs::{self, CheckCrateFiles, MigrateCategory};
+use chrono::{NaiveDate, NaiveDateTime};
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::{count_star, sql};
+use diesel::sql_types::BigInt;
+use ipnetwork::IpNetwork;
+
+/// Handles the `POST /api/private/admin/crates/:crate_id/resync` route.
+///
+/// Enqueues jobs that regenerate the git and sparse index files of the crate
+/// from the database. If the `check_files` query parameter is set to `true`,
+/// the crate files and READMEs of all versions are checked as well, see
+/// [`CheckCrateFiles`].
+pub async fn resync_crate(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let check_files = req.query().get("check_files").is_some_and(|v| v == "true");
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user = authorize_admin(&req, conn, "resync crates")?;
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        warn!(
+            "Admin {} is resyncing crate `{}`",
+            user.gh_login, krate.name
+        );
+        record_admin_action(&app, conn, &user, "resync_crate", krate.name.clone())?;
+
+        jobs::enqueue_sync_to_index(&krate.name, conn)?;
+        if check_files {
+            CheckCrateFiles::new(&krate.name).enqueue(conn)?;
+        }
+
+        ok_true()
+    })
+    .await?
+}
+
+/// The number of entries of the overview sections with recent events.
+const OVERVIEW_LIMIT: i64 = 20;
+
+/// Handles the `GET /api/private/admin/crates/:crate_id/overview` route.
+///
+/// Collects everything the crates.io team usually looks at when a crate is
+/// reported: its owners, the most recent publishes, unusual download spikes,
+/// possible typosquatting, quarantines of its versions, and the most recent
+/// ownership changes and version actions.
+pub async fn crate_overview(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    #[derive(Queryable, Serialize)]
+    struct Publish {
+        num: String,
+        created_at: NaiveDateTime,
+        yanked: bool,
+        published_by: Option<String>,
+    }
+
+    #[derive(Queryable, Serialize)]
+    struct DownloadSpike {
+        date: NaiveDate,
+        downloads: i64,
+        baseline: i64,
+        created_at: NaiveDateTime,
+    }
+
+    #[derive(Queryable, Serialize)]
+    struct TyposquatFlag {
+        squatted_crate: String,
+        description: String,
+        created_at: NaiveDateTime,
+    }
+
+    #[derive(Serialize)]
+    struct OwnerChange {
+        action: OwnerAction,
+        via: OwnerActionVia,
+        owner_kind: &'static str,
+        owner_id: i32,
+        actor: Option<String>,
+        time: NaiveDateTime,
+    }
+
+    #[derive(Serialize)]
+    struct VersionChange {
+        version: String,
+        action: &'static str,
+        user: String,
+        ip: Option<String>,
+        reason: Option<String>,
+        time: NaiveDateTime,
+    }
+
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        authorize_admin(&req, conn, "view crate overviews")?;
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let owners = krate
+            .owners(conn)?
+            .into_iter()
+            .map(EncodableOwner::from)
+            .collect::<Vec<_>>();
+
+        let publishes: Vec<Publish> = versions::table
+            .left_join(users::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .select((
+                versions::num,
+                versions::created_at,
+                versions::yanked,
+                users::gh_login.nullable(),
+            ))
+            .order(versions::created_at.desc())
+            .limit(OVERVIEW_LIMIT)
+            .load(conn)?;
+
+        let download_spikes: Vec<DownloadSpike> = download_spikes::table
+            .filter(download_spikes::crate_id.eq(krate.id))
+            .select((
+                download_spikes::date,
+                download_spikes::downloads,
+                download_spikes::baseline,
+                download_spikes::created_at,
+            ))
+            .order(download_spikes::date.desc())
+            .limit(OVERVIEW_LIMIT)
+            .load(conn)?;
+
+        let typosquat_flags: Vec<TyposquatFlag> = typosquat_flags::table
+            .filter(typosquat_flags::crate_id.eq(krate.id))
+            .select((
+                typosquat_flags::squatted_crate,
+                typosquat_flags::description,
+                typosquat_flags::created_at,
+            ))
+            .order(typosquat_flags::id)
+            .load(conn)?;
+
+        let quarantines: Vec<VersionQuarantine> = version_quarantines::table
+            .filter(version_quarantines::crate_name.eq(&krate.name))
+            .select(VersionQuarantine::as_select())
+            .order(version_quarantines::id.desc())
+            .load(conn)?;
+
+        let owner_changes = crate_owner_actions::table
+            .left_join(users::table)
+            .filter(crate_owner_actions::crate_id.eq(krate.id))
+            .select((CrateOwnerAction::as_select(), users::gh_login.nullable()))
+            .order(crate_owner_actions::id.desc())
+            .limit(OVERVIEW_LIMIT)
+            .load::<(CrateOwnerAction, Option<String>)>(conn)?
+            .into_iter()
+            .map(|(action, actor)| OwnerChange {
+                action: action.action,
+                via: action.via,
+                owner_kind: match action.owner_kind {
+                    OwnerKind::User => "user",
+                    OwnerKind::Team => "team",
+                },
+                owner_id: action.owner_id,
+                actor,
+                time: action.time,
+            })
+            .collect::<Vec<_>>();
+
+        let version_changes = version_owner_actions::table
+            .inner_join(versions::table)
+            .inner_join(users::table)
+            .filter(versions::crate_id.eq(krate.id))
+            .select((
+                versions::num,
+                version_owner_actions::action,
+                users::gh_login,
+                version_owner_actions::ip,
+                version_owner_actions::reason,
+                version_owner_actions::time,
+            ))
+            .order(version_owner_actions::id.desc())
+            .limit(OVERVIEW_LIMIT)
+            .load::<(
+                String,
+                VersionAction,
+                String,
+                Option<IpNetwork>,
+                Option<String>,
+                NaiveDateTime,
+            )>(conn)?
+            .into_iter()
+            .map(|(version, action, user, ip, reason, time)| VersionChange {
+                version,
+                action: action.into(),
+                user,
+                ip: ip.map(|ip| ip.ip().to_string()),
+                reason,
+                time,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "crate": {
+                "id": krate.id,
+                "name": krate.name,
+                "created_at": krate.created_at,
+            },
+            "owners": owners,
+            "publishes": publishes,
+            "download_spikes": download_spikes,
+            "typosquat_flags": typosquat_flags,
+            "quarantines": quarantines,
+            "owner_actions": owner_changes,
+            "version_actions": version_changes,
+        })))
+    })
+    .await?
+}
+
+/// Handles the `POST /api/private/admin/categories/:category_id/synonyms`
+/// route.
+///
+/// Adds an alternative slug for the category, e.g. the old slug of a renamed
+/// category. Crates that are published with the synonym are added to the
+/// category.
+pub async fn add_category_synonym(
+    app: AppState,
+    Path(slug): Path<String>,
+    req: BytesRequest,
+) -> AppResult<Json<Value>> {
+    #[derive(Deserialize)]
+    struct NewSynonym {
+        synonym: String,
+    }
+
+    let body = serde_json::from_slice::<NewSynonym>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?;
+
+    let synonym = body.synonym.trim().to_lowercase();
+    if synonym.is_empty() {
+        return Err(bad_request("the synonym must not be empty"));
+    }
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user = authorize_admin(&req, conn, "manage categories")?;
+        let category = find_category(conn, &slug)?;
+
+        let is_category: bool =
+            diesel::select(diesel::dsl::exists(Category::by_slug(&synonym))).get_result(conn)?;
+        if is_category {
+            let detail = format!("`{synonym}` is already the slug of a category");
+            return Err(bad_request(detail));
+        }
+
+        let new_synonym = NewCategorySynonym {
+            synonym: &synonym,
+            category_id: category.id,
+            created_by: user.id,
+        };
+
+        let synonym = new_synonym.insert(conn)?.ok_or_else(|| {
+            let detail = format!("the synonym `{synonym}` already exists");
+            custom(StatusCode::CONFLICT, detail)
+        })?;
+
+        warn!(
+            "Admin {} added the synonym `{}` for category `{}`",
+            user.gh_login, synonym.synonym, category.slug
+        );
+        let subject = format!("{} => {}", synonym.synonym, category.slug);
+        record_admin_action(&app, conn, &user, "add_category_synonym", subject)?;
+
+        Ok(Json(json!({ "synonym": synonym })))
+    })
+    .await?
+}
+
+/// Handles the `POST /api/private/admin/categories/:category_id/migrations`
+/// route.
+///
+/// Records the migration of all crates of the category to the `target`
+/// category and enqueues a [`MigrateCategory`] job that performs it. The
+/// `category_migrations` table serves as the audit trail of the migrations.
+pub async fn migrate_category(
+    app: AppState,
+    Path(slug): Path<String>,
+    req: BytesRequest,
+) -> AppResult<Json<Value>> {
+    #[derive(Deserialize)]
+    struct NewMigration {
+        target: String,
+    }
+
+    let body = serde_json::from_slice::<NewMigration>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user = authorize_admin(&req, conn, "manage categories")?;
+        let source = find_category(conn, &slug)?;
+        let target = find_category(conn, &body.target)?;
+        if source.id == target.id {
+            return Err(bad_request("a category cannot be migrated to itself"));
+        }
+
+        let migration = conn.transaction(|conn| {
+            let new_migration = NewCategoryMigration {
+                source_category_id: source.id,
+                target_category_id: target.id,
+                created_by: user.id,
+            };
+
+            let migration = new_migration.insert(conn)?;
+            MigrateCategory::new(migration.id).enqueue(conn)?;
+
+            Ok::<_, BoxedAppError>(migration)
+        })?;
+
+        warn!(
+            "Admin {} is migrating the crates of category `{}` to `{}`",
+            user.gh_login, source.slug, target.slug
+        );
+        let subject = format!("{} => {}", source.slug, target.slug);
+        record_admin_action(&app, conn, &user, "migrate_category", subject)?;
+
+        Ok(Json(json!({ "migration": migration })))
+    })
+    .await?
+}
+
+/// Handles the `GET /api/private/admin/jobs` route.
+///
+/// Returns the number of queued and failed jobs per job type, and the
+/// progress that long-running jobs have reported via
+/// [`BackgroundJob::report_progress()`].
+pub async fn job_status(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    #[derive(Queryable, Serialize)]
+    struct QueuedJobs {
+        job_type: String,
+        count: i64,
+        failed: i64,
+    }
+
+    #[derive(Queryable, Serialize)]
+    struct JobProgress {
+        id: i64,
+        job_type: String,
+        retries: i32,
+        created_at: NaiveDateTime,
+        percent: i16,
+        message: String,
+        updated_at: NaiveDateTime,
+    }
+
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        authorize_admin(&req, conn, "view the job queue")?;
+
+        let queue: Vec<QueuedJobs> = background_jobs::table
+            .group_by(background_jobs::job_type)
+            .select((
+                background_jobs::job_type,
+                count_star(),
+                sql::<BigInt>("count(*) filter (where retries > 0)"),
+            ))
+            .order(background_jobs::job_type)
+            .load(conn)?;
+
+        let progress: Vec<JobProgress> = background_job_progress::table
+            .inner_join(
+                background_jobs::table.on(background_jobs::id.eq(background_job_progress::job_id)),
+            )
+            .select((
+                background_jobs::id,
+                background_jobs::job_type,
+                background_jobs::retries,
+                background_jobs::created_at,
+                background_job_progress::percent,
+                background_job_progress::message,
+                background_job_progress::updated_at,
+            ))
+            .order(background_jobs::id)
+            .load(conn)?;
+
+        Ok(Json(json!({ "queue": queue, "progress": progress })))
+    })
+    .await?
+}
+
+/// Handles the `POST /api/private/admin/crates/:crate_id/:version/quarantine`
+/// route.
+///
+/// Quarantines the version pending a review: its downloads are rejected with
+/// a `451 Unavailable For Legal Reasons` response, it is marked as yanked in
+/// the index, and the owners of the crate are notified. The quarantine is
+/// lifted with [`release_quarantine`] or [`delete_quarantined_version`].
+pub async fn quarantine_version(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: BytesRequest,
+) -> AppResult<Json<Value>> {
+    #[derive(Deserialize)]
+    struct NewQuarantine {
+        reason: String,
+    }
+
+    let body = serde_json::from_slice::<NewQuarantine>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?;
+
+    let reason = body.reason.trim();
+    if reason.is_empty() {
+        return Err(bad_request("the reason must not be empty"));
+    }
+    let reason = reason.to_string();
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user = authorize_admin(&req, conn, "quarantine versions")?;
+        let (version, krate) = find_version(conn, &crate_name, &version)?;
+
+        let quarantine = conn.transaction(|conn| {
+            let new_quarantine = NewVersionQuarantine {
+                version_id: version.id,
+                crate_name: &krate.name,
+                version_num: &version.num,
+                reason: &reason,
+                quarantined_by: user.id,
+            };
+
+            let quarantine = new_quarantine.insert(conn)?.ok_or_else(|| {
+                let detail = format!(
+                    "version `{}` of crate `{}` is already quarantined",
+                    version.num, krate.name
+                );
+                custom(StatusCode::CONFLICT, detail)
+            })?;
+
+            jobs::enqueue_sync_to_index(&krate.name, conn)?;
+
+            Ok::<_, BoxedAppError>(quarantine)
+        })?;
+
+        app.quarantined_versions.invalidate();
+
+        warn!(
+            "Admin {} quarantined version `{}` of crate `{}`: {}",
+            user.gh_login, version.num, krate.name, reason
+        );
+        let subject = format!("{}@{}", krate.name, version.num);
+        record_admin_action(&app, conn, &user, "quarantine_version", subject)?;
+
+        let email = VersionQuarantinedEmail {
+            crate_name: &krate.name,
+            version: &version.num,
+            reason: &reason,
+        };
+        notify_owners(&app, conn, krate.id, email)?;
+
+        Ok(Json(json!({ "quarantine": quarantine })))
+    })
+    .await?
+}
+
+/// Handles the `POST
+/// /api/private/admin/crates/:crate_id/:version/quarantine/release` route.
+///
+/// Lifts the quarantine of a version that was found to be harmless, making
+/// it available again.
+pub async fn release_quarantine(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user = authorize_admin(&req, conn, "review quarantined versions")?;
+        let (version, krate) = find_version(conn, &crate_name, &version)?;
+        let quarantine = find_quarantine(conn, &krate, &version)?;
+
+        let quarantine = conn.transaction(|conn| {
+            let quarantine = quarantine.resolve(conn, QuarantineResolution::Released, user.id)?;
+            jobs::enqueue_sync_to_index(&krate.name, conn)?;
+            Ok::<_, BoxedAppError>(quarantine)
+        })?;
+
+        app.quarantined_versions.invalidate();
+
+        warn!(
+            "Admin {} released version `{}` of crate `{}` from quarantine",
+            user.gh_login, version.num, krate.name
+        );
+        let subject = format!("{}@{}", krate.name, version.num);
+        record_admin_action(&app, conn, &user, "release_quarantine", subject)?;
+
+        let email = QuarantineReleasedEmail {
+            crate_name: &krate.name,
+            version: &version.num,
+        };
+        notify_owners(&app, conn, krate.id, email)?;
+
+        Ok(Json(json!({ "quarantine": quarantine })))
+    })
+    .await?
+}
+
+/// Handles the `POST
+/// /api/private/admin/crates/:crate_id/:version/quarantine/delete` route.
+///
+/// Permanently deletes a quarantined version that was found to be harmful,
+/// including its crate file and README. The quarantine is kept as the audit
+/// trail of the deletion.
+pub async fn delete_quarantined_version(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    let (quarantine, crate_name, version_num) = conn
+        .interact({
+            let app = app.clone();
+            move |conn| {
+                let user = authorize_admin(&req, conn, "review quarantined versions")?;
+                let (version, krate) = find_version(conn, &crate_name, &version)?;
+                let quarantine = find_quarantine(conn, &krate, &version)?;
+
+                let quarantine = conn.transaction(|conn| {
+                    let quarantine =
+                        quarantine.resolve(conn, QuarantineResolution::Deleted, user.id)?;
+                    diesel::delete(versions::table.find(version.id)).execute(conn)?;
+                    jobs::enqueue_sync_to_index(&krate.name, conn)?;
+                    Ok::<_, BoxedAppError>(quarantine)
+                })?;
+
+                app.quarantined_versions.invalidate();
+                app.version_id_cache.invalidate(&krate.name, &version.num);
+                app.file_preview_cache
+                    .invalidate_matching(|(name, num, _)| {
+                        *name == krate.name && *num == version.num
+                    });
+
+                warn!(
+                    "Admin {} deleted quarantined version `{}` of crate `{}`",
+                    user.gh_login, version.num, krate.name
+                );
+                let subject = format!("{}@{}", krate.name, version.num);
+                record_admin_action(&app, conn, &user, "delete_quarantined_version", subject)?;
+
+                let email = QuarantinedVersionDeletedEmail {
+                    crate_name: &krate.name,
+                    version: &version.num,
+                };
+                notify_owners(&app, conn, krate.id, email)?;
+
+                Ok::<_, BoxedAppError>((quarantine, krate.name, version.num))
+            }
+        })
+        .await??;
+
+    if let Err(error) = app
+        .storage
+        .delete_crate_file(&crate_name, &version_num)
+        .await
+    {
+        warn!(%crate_name, version = %version_num, ?error, "Failed to delete crate file");
+    }
+
+    match app.storage.delete_readme(&crate_name, &version_num).await {
+        Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+        Err(error) => {
+            warn!(%crate_name, version = %version_num, ?error, "Failed to delete readme file")
+        }
+    }
+
+    if let Err(error) = app
+        .storage
+        .delete_artifacts(&crate_name, &version_num)
+        .await
+    {
+        warn!(%crate_name, version = %version_num, ?error, "Failed to delete artifacts");
+    }
+
+    Ok(Json(json!({ "quarantine": quarantine })))
+}
+
+/// Handles the `GET /api/private/admin/quarantines` route.
+///
+/// Returns the versions that are currently quarantined and await a review,
+/// oldest first.
+pub async fn list_quarantines(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        authorize_admin(&req, conn, "review quarantined versions")?;
+        let quarantines = VersionQuarantine::all_active(conn)?;
+        Ok(Json(json!({ "quarantines": quarantines })))
+    })
+    .await?
+}
+
+/// Handles the `GET /api/private/admin/blocked_routes` route.
+pub async fn list_blocked_routes(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        authorize_admin(&req, conn, "manage blocked routes")?;
+        let blocked_routes = BlockedRoute::all(conn)?;
+        Ok(Json(json!({ "blocked_routes": blocked_routes })))
+    })
+    .await?
+}
+
+/// Handles the `POST /api/private/admin/blocked_routes` route.
+///
+/// Blocks the HTTP route pattern (e.g. `/api/v1/crates/:crate_id/downloads`)
+/// on all instances once their cached runtime settings expire.
+pub async fn block_route(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
+    #[derive(Deserialize)]
+    struct NewBlockedRoute {
+        route: String,
+    }
+
+    let body = serde_json::from_slice::<NewBlockedRoute>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?;
+
+    let route = body.route.trim().to_string();
+    if !route.starts_with('/') {
+        return Err(bad_request("the route must start with `/`"));
+    }
+    if route.starts_with("/api/private/admin") {
+        return Err(bad_request("the admin routes cannot be blocked"));
+    }
+
+    let audit_app = app.clone();
+    let conn = app.db_write().await?;
+    let blocked_route = conn
+        .interact(move |conn| {
+            let user = authorize_admin(&req, conn, "manage blocked routes")?;
+
+            let blocked_route = BlockedRoute::insert(conn, &route, user.id)?.ok_or_else(|| {
+                let detail = format!("the route `{route}` is already blocked");
+                custom(StatusCode::CONFLICT, detail)
+            })?;
+
+            warn!("Admin {} blocked the route `{}`", user.gh_login, route);
+            record_admin_action(&audit_app, conn, &user, "block_route", route)?;
+
+            Ok::<_, BoxedAppError>(blocked_route)
+        })
+        .await??;
+
+    app.runtime_settings.invalidate();
+
+    Ok(Json(json!({ "blocked_route": blocked_route })))
+}
+
+/// Handles the `DELETE /api/private/admin/blocked_routes?route=...` route.
+pub async fn unblock_route(app: AppState, req: Parts) -> AppResult<Response> {
+    let route = req
+        .query()
+        .get("route")
+        .cloned()
+        .ok_or_else(|| bad_request("missing `route` query parameter"))?;
+
+    let audit_app = app.clone();
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user = authorize_admin(&req, conn, "manage blocked routes")?;
+
+        if !BlockedRoute::delete(conn, &route)? {
+            let detail = format!("the route `{route}` is not blocked");
+            return Err(custom(StatusCode::NOT_FOUND, detail));
+        }
+
+        warn!("Admin {} unblocked the route `{}`", user.gh_login, route);
+        record_admin_action(&audit_app, conn, &user, "unblock_route", route)?;
+
+        Ok::<_, BoxedAppError>(())
+    })
+    .await??;
+
+    app.runtime_settings.invalidate();
+
+    ok_true()
+}
+
+/// Handles the `GET /api/private/admin/excluded_crate_names` route.
+pub async fn list_excluded_crate_names(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        authorize_admin(&req, conn, "manage excluded crate names")?;
+        let excluded_crate_names = ExcludedCrateName::all(conn)?;
+        Ok(Json(
+            json!({ "excluded_crate_names": excluded_crate_names }),
+        ))
+    })
+    .await?
+}
+
+/// Handles the `POST /api/private/admin/excluded_crate_names` route.
+///
+/// Excludes the crate from the most downloaded lists of the summary endpoint,
+/// e.g. because its downloads are inflated by a misconfigured CI setup.
+pub async fn exclude_crate_name(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
+    #[derive(Deserialize)]
+    struct NewExcludedCrateName {
+        name: String,
+    }
+
+    let body = serde_json::from_slice::<NewExcludedCrateName>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?;
+
+    let name = body.name.trim().to_string();
+    if name.is_empty() {
+        return Err(bad_request("the crate name must not be empty"));
+    }
+
+    let audit_app = app.clone();
+    let conn = app.db_write().await?;
+    let excluded_crate_name = conn
+        .interact(move |conn| {
+            let user = authorize_admin(&req, conn, "manage excluded crate names")?;
+
+            let excluded = ExcludedCrateName::insert(conn, &name, user.id)?.ok_or_else(|| {
+                let detail = format!("the crate name `{name}` is already excluded");
+                custom(StatusCode::CONFLICT, detail)
+            })?;
+
+            warn!("Admin {} excluded the crate name `{}`", user.gh_login, name);
+            record_admin_action(&audit_app, conn, &user, "exclude_crate_name", name)?;
+
+            Ok::<_, BoxedAppError>(excluded)
+        })
+        .await??;
+
+    app.runtime_settings.invalidate();
+
+    Ok(Json(json!({ "excluded_crate_name": excluded_crate_name })))
+}
+
+/// Handles the `DELETE /api/private/admin/excluded_crate_names?name=...`
+/// route.
+pub async fn include_crate_name(app: AppState, req: Parts) -> AppResult<Response> {
+    let name = req
+        .query()
+        .get("name")
+        .cloned()
+        .ok_or_else(|| bad_request("missing `name` query parameter"))?;
+
+    let audit_app = app.clone();
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user = authorize_admin(&req, conn, "manage excluded crate names")?;
+
+        if !ExcludedCrateName::delete(conn, &name)? {
+            let detail = format!("the crate name `{name}` is not excluded");
+            return Err(custom(StatusCode::NOT_FOUND, detail));
+        }
+
+        warn!(
+            "Admin {} removed the exclusion of the crate name `{}`",
+            user.gh_login, name
+        );
+        record_admin_action(&audit_app, conn, &user, "include_crate_name", name)?;
+
+        Ok::<_, BoxedAppError>(())
+    })
+    .await??;
+
+    app.runtime_settings.invalidate();
+
+    ok_true()
+}
+
+/// Handles the `POST /api/private/admin/config/reload` route.
+///
+/// Re-reads the settings that can be changed without a restart, like a
+/// SIGHUP to the server process does, and returns the settings that changed.
+/// Only the instance that handles the request is reloaded.
+pub async fn reload_config(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let audit_app = app.clone();
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let user = authorize_admin(&req, conn, "reload the configuration")?;
+        warn!("Admin {} is reloading the configuration", user.gh_login);
+        record_admin_action(&audit_app, conn, &user, "reload_config", String::new())?;
+        Ok::<_, BoxedAppError>(())
+    })
+    .await??;
+
+    let changes = app.reload_config().map_err(server_error)?;
+    let changed = changes
+        .into_iter()
+        .map(|change| change.setting)
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({ "changed": changed })))
+}
+
+/// Returns the authenticated user, if they are an administrator.
+fn authorize_admin<T: RequestPartsExt>(
+    req: &T,
+    conn: &mut PgConnection,
+    action: &str,
+) -> AppResult<User> {
+    let auth = AuthCheck::only_cookie().check(req, conn)?;
+    let user = auth.user();
+    if !user.is_admin {
+        let detail = format!("only administrators can {action}");
+        return Err(custom(StatusCode::FORBIDDEN, detail));
+    }
+
+    Ok(user.clone())
+}
+
+/// Records the admin action in the [audit log](crate::audit). The `subject`
+/// is what the action was applied to, e.g. `serde@1.0.0` for a version.
+fn record_admin_action(
+    app: &App,
+    conn: &mut PgConnection,
+    user: &User,
+    action: &'static str,
+    subject: String,
+) -> QueryResult<()> {
+    let event = AuditEvent::AdminAction {
+        user_id: user.id,
+        action,
+        subject,
+    };
+
+    app.audit_log.record(conn, event)
+}
+
+fn find_category(conn: &mut PgConnection, slug: &str) -> AppResult<Category> {
+    Category::by_slug(slug)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| {
+            let detail = format!("category `{slug}` does not exist");
+            custom(StatusCode::NOT_FOUND, detail)
+        })
+}
+
+fn find_version(
+    conn: &mut PgConnection,
+    crate_name: &str,
+    version: &str,
+) -> AppResult<(Version, Crate)> {
+    let krate: Crate = Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))?;
+
+    let version = krate.find_version(conn, version)?;
+
+    Ok((version, krate))
+}
+
+fn find_quarantine(
+    conn: &mut PgConnection,
+    krate: &Crate,
+    version: &Version,
+) -> AppResult<VersionQuarantine> {
+    VersionQuarantine::active(conn, version.id)?.ok_or_else(|| {
+        let detail = format!(
+            "version `{}` of crate `{}` is not quarantined",
+            version.num, krate.name
+        );
+        custom(StatusCode::NOT_FOUND, detail)
+    })
+}
+
+/// Sends the email to all user owners of the crate with a verified email
+/// address.
+///
+/// Quarantines concern the security of the crate, so the emails are sent
+/// even if the owners opted out of other notifications.
+fn notify_owners<E: Email + Clone>(
+    app: &App,
+    conn: &mut PgConnection,
+    crate_id: i32,
+    email: E,
+) -> QueryResult<()> {
+    let recipients: Vec<String> = crate_owners::table
+        .inner_join(emails::table.on(emails::user_id.eq(crate_owners::owner_id)))
+        .filter(crate_owners::crate_id.eq(crate_id))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(emails::is_primary)
+        .filter(emails::verified.eq(true))
+        .select(emails::email)
+        .load(conn)?;
+
+    for recipient in &recipients {
+        if let Err(error) = app.emails.send(recipient, email.clone()) {
+            warn!(?error, ?recipient, "Failed to send quarantine notification");
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Clone)]
+struct VersionQuarantinedEmail<'a> {
+    crate_name: &'a str,
+    version: &'a str,
+    reason: &'a str,
+}
+
+impl Email for VersionQuarantinedEmail<'_> {
+    const SUBJECT: &'static str = "crates.io: A version of your crate was quarantined";
+
+    fn body(&self) -> String {
+        let VersionQuarantinedEmail {
+            crate_name,
+            version,
+            reason,
+        } = self;
+
+        format!(
+            "Hello!
+
+The crates.io team quarantined version {version} of your crate {crate_name} pending a review, for the following reason:
+
+{reason}
+
+While the version is quarantined, it cannot be downloaded and is marked as yanked in the index. You will be notified once the review is complete.
+
+If you have any questions, please contact us at help@crates.io."
+        )
+    }
+}
+
+#[derive(Clone)]
+struct QuarantineReleasedEmail<'a> {
+    crate_name: &'a str,
+    version: &'a str,
+}
+
+impl Email for QuarantineReleasedEmail<'_> {
+    const SUBJECT: &'static str = "crates.io: A version of your crate was released from quarantine";
+
+    fn body(&self) -> String {
+        let QuarantineReleasedEmail {
+            crate_name,
+            version,
+        } = self;
+
+        format!(
+            "Hello!
+
+The crates.io team reviewed version {version} of your crate {crate_name} and released it from quarantine. It can be downloaded again.
+
+If you have any questions, please contact us at help@crates.io."
+        )
+    }
+}
+
+#[derive(Clone)]
+struct QuarantinedVersionDeletedEmail<'a> {
+    crate_name: &'a str,
+    version: &'a str,
+}
+
+impl Email for QuarantinedVersionDeletedEmail<'_> {
+    const SUBJECT: &'static str = "crates.io: A quarantined version of your crate was deleted";
+
+    fn body(&self) -> String {
+        let QuarantinedVersionDeletedEmail {
+            crate_name,
+            version,
+        } = self;
+
+        format!(
+            "Hello!
+
+The crates.io team reviewed version {version} of your crate {crate_name} and deleted it from the registry.
+
+If you have any questions, please contact us at help@crates.io."
+        )
+    }
+}
@@ -0,0 +1,87 @@
+use crate::config::Server;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::metrics;
+use chrono::DateTime;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// The version of the compiler that built the application, see `build.rs`.
+const RUSTC_VERSION: &str = env!("CRATES_IO_RUSTC_VERSION");
+/// The UNIX timestamp of the build of the application, see `build.rs`.
+const BUILD_TIMESTAMP: &str = env!("CRATES_IO_BUILD_TIMESTAMP");
+
+/// Handles the `GET /api/private/build_info` endpoint.
+///
+/// Returns which code and which configuration is serving traffic, so that
+/// deploy tooling can verify a deployment. The endpoint requires the same
+/// authorization as the metrics endpoints.
+pub async fn build_info(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    metrics::authorize(&app.config, &req)?;
+
+    let commit = dotenvy::var("HEROKU_SLUG_COMMIT").unwrap_or_else(|_| String::from("unknown"));
+
+    let build_timestamp = BUILD_TIMESTAMP
+        .parse()
+        .ok()
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+        .map(|timestamp| timestamp.to_rfc3339());
+
+    Ok(Json(json!({
+        "commit": commit,
+        "build_timestamp": build_timestamp,
+        "rustc_version": RUSTC_VERSION,
+        "features": active_features(&app.config),
+        "config_digest": config_digest(&app.config),
+    })))
+}
+
+/// Returns the names of the optional features that are enabled by the
+/// configuration.
+fn active_features(config: &Server) -> Vec<&'static str> {
+    let features = [
+        ("challenge", config.challenge.is_some()),
+        (
+            "download_rate_limit",
+            config.download_rate_limiter.is_some(),
+        ),
+        ("index_signing", !config.index_signing_keys.is_empty()),
+        ("read_only", config.db.are_all_read_only()),
+        ("sandbox_reset", config.sandbox_reset_enabled),
+        ("serve_dist", config.serve_dist),
+        ("serve_html", config.serve_html),
+        ("tls", config.tls.is_some()),
+        ("upstream_proxy", config.upstream.is_some()),
+    ];
+
+    features
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Returns a SHA-256 digest of the non-secret settings of the configuration,
+/// so that deployments can be compared without exposing the settings
+/// themselves.
+fn config_digest(config: &Server) -> String {
+    let route_concurrency_limits = config
+        .route_concurrency_limits
+        .iter()
+        .collect::<BTreeMap<_, _>>();
+
+    let settings = json!({
+        "domain_name": config.domain_name,
+        "max_upload_size": config.max_upload_size,
+        "max_unpack_size": config.max_unpack_size,
+        "max_dependencies": config.max_dependencies,
+        "max_features": config.max_features,
+        "new_version_rate_limit": config.new_version_rate_limit,
+        "max_allowed_page_offset": config.max_allowed_page_offset,
+        "ownership_invitations_expiration_days": config.ownership_invitations_expiration_days,
+        "downloads_persist_interval": config.downloads_persist_interval.as_secs(),
+        "route_concurrency_limits": route_concurrency_limits,
+        "features": active_features(config),
+    });
+
+    hex::encode(Sha256::digest(settings.to_string()))
+}
@@ -2,6 +2,7 @@ use super::frontend_prelude::*;
 
 use crate::auth::AuthCheck;
 use crate::auth::Authentication;
+use crate::challenge::check_challenge;
 use crate::controllers::helpers::pagination::{Page, PaginationOptions};
 use crate::models::{Crate, CrateOwnerInvitation, Rights, User};
 use crate::schema::{crate_owner_invitations, crates, users};
@@ -10,7 +11,7 @@ use crate::views::{
     EncodableCrateOwnerInvitation, EncodableCrateOwnerInvitationV1, EncodablePublicUser,
     InvitationResponse,
 };
-use chrono::{Duration, Utc};
+use chrono::Duration;
 use diesel::{pg::Pg, sql_types::Bool};
 use indexmap::IndexMap;
 use std::collections::{HashMap, HashSet};
@@ -130,7 +131,7 @@ fn prepare_list(
     let expire_cutoff = Duration::days(config.ownership_invitations_expiration_days as i64);
     let query = crate_owner_invitations::table
         .filter(sql_filter)
-        .filter(crate_owner_invitations::created_at.gt((Utc::now() - expire_cutoff).naive_utc()))
+        .filter(crate_owner_invitations::created_at.gt(state.clock.naive_now() - expire_cutoff))
         .order_by((
             crate_owner_invitations::crate_id,
             crate_owner_invitations::invited_user_id,
@@ -276,7 +277,8 @@ pub async fn handle_invite(state: AppState, req: BytesRequest) -> AppResult<Json
 
         let invitation = CrateOwnerInvitation::find_by_id(user_id, crate_invite.crate_id, conn)?;
         if crate_invite.accepted {
-            invitation.accept(conn, config)?;
+            check_challenge(&state, &req, user_id, conn)?;
+            invitation.accept(conn, &state.audit_log, config, state.clock.naive_now())?;
         } else {
             invitation.decline(conn)?;
         }
@@ -290,6 +292,7 @@ pub async fn handle_invite(state: AppState, req: BytesRequest) -> AppResult<Json
 pub async fn handle_invite_with_token(
     state: AppState,
     Path(token): Path<String>,
+    req: Parts,
 ) -> AppResult<Json<Value>> {
     let conn = state.db_write().await?;
     conn.interact(move |conn| {
@@ -297,7 +300,8 @@ pub async fn handle_invite_with_token(
 
         let invitation = CrateOwnerInvitation::find_by_token(&token, conn)?;
         let crate_id = invitation.crate_id;
-        invitation.accept(conn, config)?;
+        check_challenge(&state, &req, invitation.invited_user_id, conn)?;
+        invitation.accept(conn, &state.audit_log, config, state.clock.naive_now())?;
 
         Ok(Json(json!({
             "crate_owner_invitation": {
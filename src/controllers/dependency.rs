@@ -0,0 +1,122 @@
+//! Endpoint for checking dependency declarations against the registry
+//!
+//! Editor plugins and CI checks can validate all dependencies of a
+//! `Cargo.toml` file with a single request, instead of fetching the versions
+//! of each crate separately.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::Version;
+use crate::schema::{crates, versions};
+use crate::views::EncodableDependencyValidation;
+use std::collections::HashMap;
+
+/// The maximum number of dependencies that can be validated with a single
+/// request.
+const MAX_DEPENDENCIES: usize = 200;
+
+#[derive(Deserialize)]
+pub struct ValidateRequest {
+    dependencies: Vec<DependencyDeclaration>,
+}
+
+#[derive(Deserialize)]
+pub struct DependencyDeclaration {
+    name: String,
+    req: String,
+}
+
+/// Handles the `POST /dependencies/validate` route.
+///
+/// Responds with one entry per dependency of the request, in the same order.
+/// Yanked versions are not considered, since cargo does not select them for
+/// new lockfiles either.
+pub async fn validate(
+    state: AppState,
+    Json(body): Json<ValidateRequest>,
+) -> AppResult<Json<Value>> {
+    if body.dependencies.len() > MAX_DEPENDENCIES {
+        return Err(bad_request(format_args!(
+            "at most {MAX_DEPENDENCIES} dependencies can be validated at once"
+        )));
+    }
+
+    let dependencies = body
+        .dependencies
+        .into_iter()
+        .map(|dep| {
+            let req = semver::VersionReq::parse(&dep.req).map_err(|_| {
+                bad_request(format_args!(
+                    "invalid version requirement `{}` for crate `{}`",
+                    dep.req, dep.name
+                ))
+            })?;
+
+            Ok((dep, req))
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    let conn = state.db_read().await?;
+    conn.interact(move |conn| {
+        let names = dependencies
+            .iter()
+            .map(|(dep, _)| dep.name.as_str())
+            .collect::<Vec<_>>();
+        let candidates = load_candidates(conn, &names)?;
+
+        let results = dependencies
+            .into_iter()
+            .map(|(dep, req)| {
+                let versions = candidates.get(&dep.name);
+                let latest_matching = versions
+                    .and_then(|versions| versions.iter().find(|num| req.matches(num)))
+                    .map(ToString::to_string);
+
+                EncodableDependencyValidation {
+                    name: dep.name,
+                    req: dep.req,
+                    exists: versions.is_some(),
+                    matches: latest_matching.is_some(),
+                    latest_matching,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "dependencies": results })))
+    })
+    .await?
+}
+
+/// Loads the non-yanked versions of the crates with the given names, sorted
+/// from the highest to the lowest version.
+///
+/// Crates without any non-yanked versions are included with an empty list.
+fn load_candidates(
+    conn: &mut PgConnection,
+    names: &[&str],
+) -> QueryResult<HashMap<String, Vec<semver::Version>>> {
+    let rows: Vec<(String, Option<String>)> = crates::table
+        .left_join(
+            versions::table.on(versions::crate_id
+                .eq(crates::id)
+                .and(versions::yanked.eq(false))
+                .and(Version::is_released())),
+        )
+        .filter(crates::name.eq_any(names))
+        .select((crates::name, versions::num.nullable()))
+        .load(conn)?;
+
+    let mut candidates: HashMap<_, Vec<_>> = HashMap::new();
+    for (name, num) in rows {
+        let versions = candidates.entry(name).or_default();
+        if let Some(num) = num.and_then(|num| semver::Version::parse(&num).ok()) {
+            versions.push(num);
+        }
+    }
+
+    for versions in candidates.values_mut() {
+        versions.sort_by(|a, b| b.cmp(a));
+    }
+
+    Ok(candidates)
+}
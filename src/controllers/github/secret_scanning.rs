@@ -1,6 +1,6 @@
 use crate::app::AppState;
 use crate::controllers::frontend_prelude::*;
-use crate::email::Email;
+use crate::email::{Locale, LocalizedEmail};
 use crate::models::{ApiToken, User};
 use crate::schema::api_tokens;
 use crate::util::token::HashedToken;
@@ -189,7 +189,8 @@ fn send_notification_email(
         url: &alert.url,
     };
 
-    state.emails.send(&recipient, email)?;
+    let locale = Locale::from_preference(&user.locale);
+    state.emails.send_localized(&recipient, locale, email)?;
 
     Ok(())
 }
@@ -202,29 +203,17 @@ struct TokenExposedEmail<'a> {
     url: &'a str,
 }
 
-impl Email for TokenExposedEmail<'_> {
-    const SUBJECT: &'static str = "Exposed API token found";
-
-    fn body(&self) -> String {
-        let mut body = format!(
-            "{reporter} has notified us that your crates.io API token {token_name}\n
-has been exposed publicly. We have revoked this token as a precaution.\n
-Please review your account at https://{domain} to confirm that no\n
-unexpected changes have been made to your settings or crates.\n
-\n
-Source type: {source}\n",
-            domain = self.domain,
-            reporter = self.reporter,
-            source = self.source,
-            token_name = self.token_name,
-        );
-        if self.url.is_empty() {
-            body.push_str("\nWe were not informed of the URL where the token was found.\n");
-        } else {
-            body.push_str(&format!("\nURL where the token was found: {}\n", self.url));
-        }
+impl LocalizedEmail for TokenExposedEmail<'_> {
+    const TEMPLATE: &'static str = "token_exposed";
 
-        body
+    fn context(&self) -> minijinja::Value {
+        minijinja::context! {
+            domain => self.domain,
+            reporter => self.reporter,
+            source => self.source,
+            token_name => self.token_name,
+            url => self.url,
+        }
     }
 }
 
@@ -3,6 +3,7 @@ use axum::response::IntoResponse;
 use axum::Json;
 
 pub(crate) mod pagination;
+pub(crate) mod yanked;
 
 pub(crate) use self::pagination::Paginate;
 
@@ -30,6 +30,9 @@ pub(crate) enum Page {
 pub(crate) struct PaginationOptions {
     pub(crate) page: Page,
     pub(crate) per_page: i64,
+    /// Whether the total number of results is counted, which clients can
+    /// opt out of with `?meta=minimal` if they only follow `next_page`.
+    pub(crate) include_total: bool,
 }
 
 impl PaginationOptions {
@@ -139,7 +142,17 @@ impl PaginationOptionsBuilder {
             )));
         }
 
-        Ok(PaginationOptions { page, per_page })
+        let include_total = match params.get("meta").map(String::as_str) {
+            None | Some("full") => true,
+            Some("minimal") => false,
+            Some(_) => return Err(bad_request("invalid meta, expected `full` or `minimal`")),
+        };
+
+        Ok(PaginationOptions {
+            page,
+            per_page,
+            include_total,
+        })
     }
 }
 
@@ -166,17 +179,48 @@ pub(crate) trait Paginate: Sized {
 
 impl<T> Paginate for T {}
 
+/// The `meta` object of paginated responses.
+///
+/// The `total` field is left out if the client opted out of counting the
+/// results with `?meta=minimal`.
+#[derive(Debug, Serialize)]
+pub(crate) struct PaginationMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) total: Option<i64>,
+    pub(crate) next_page: Option<String>,
+    pub(crate) prev_page: Option<String>,
+}
+
 pub struct Paginated<T> {
     records_and_total: Vec<WithCount<T>>,
     options: PaginationOptions,
 }
 
 impl<T> Paginated<T> {
-    pub(crate) fn total(&self) -> i64 {
-        self.records_and_total
+    /// Wraps the rows of a query that was paginated by hand, e.g. a raw SQL
+    /// query, which has to leave the total at zero if `include_total` is not
+    /// set.
+    pub(crate) fn new(records_and_total: Vec<WithCount<T>>, options: PaginationOptions) -> Self {
+        Self {
+            records_and_total,
+            options,
+        }
+    }
+
+    /// Returns the total number of results, or `None` if the client opted
+    /// out of counting them.
+    pub(crate) fn total(&self) -> Option<i64> {
+        if !self.options.include_total {
+            return None;
+        }
+
+        let total = self
+            .records_and_total
             .first()
             .map(|row| row.total)
-            .unwrap_or_default() // If there is no first row, then the total is zero.
+            .unwrap_or_default(); // If there is no first row, then the total is zero.
+
+        Some(total)
     }
 
     pub(crate) fn next_page_params(&self) -> Option<IndexMap<String, String>> {
@@ -277,7 +321,13 @@ where
     T: QueryFragment<Pg>,
 {
     fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
-        out.push_sql("SELECT *, COUNT(*) OVER () FROM (");
+        // Counting all rows requires scanning the complete result of the
+        // query, so the total is left at zero if the client opted out of it.
+        if self.options.include_total {
+            out.push_sql("SELECT *, COUNT(*) OVER () FROM (");
+        } else {
+            out.push_sql("SELECT *, 0::bigint FROM (");
+        }
         self.query.walk_ast(out.reborrow())?;
         out.push_sql(") t LIMIT ");
         out.push_bind_param::<BigInt, _>(&self.options.per_page)?;
@@ -314,13 +364,9 @@ fn is_useragent_or_ip_blocked<T: RequestPartsExt>(config: &Server, req: &T) -> b
         return true;
     }
 
-    // check if client ip is blocked, needs to be an IPv4 address
+    // check if client ip is blocked
     if let Some(client_ip) = client_ip {
-        if config
-            .page_offset_cidr_blocklist
-            .iter()
-            .any(|blocked| blocked.contains(**client_ip))
-        {
+        if config.page_offset_cidr_blocklist.contains(**client_ip) {
             return true;
         }
     }
@@ -372,9 +418,13 @@ where
     C: QueryFragment<Pg>,
 {
     fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
-        out.push_sql("SELECT *, (");
-        self.count_query.walk_ast(out.reborrow())?;
-        out.push_sql(") FROM (");
+        if self.options.include_total {
+            out.push_sql("SELECT *, (");
+            self.count_query.walk_ast(out.reborrow())?;
+            out.push_sql(") FROM (");
+        } else {
+            out.push_sql("SELECT *, 0::bigint FROM (");
+        }
         self.query.walk_ast(out.reborrow())?;
         out.push_sql(") t LIMIT ");
         out.push_bind_param::<BigInt, _>(&self.options.per_page)?;
@@ -592,6 +642,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn meta_param_parsing() {
+        assert_pagination_error(
+            PaginationOptions::builder(),
+            "meta=none",
+            "invalid meta, expected `full` or `minimal`",
+        );
+
+        let pagination = PaginationOptions::builder().gather(&mock("")).unwrap();
+        assert!(pagination.include_total);
+
+        let pagination = PaginationOptions::builder()
+            .gather(&mock("meta=full"))
+            .unwrap();
+        assert!(pagination.include_total);
+
+        let pagination = PaginationOptions::builder()
+            .gather(&mock("meta=minimal"))
+            .unwrap();
+        assert!(!pagination.include_total);
+    }
+
     #[test]
     fn test_seek_encode_and_decode() {
         // Encoding produces the results we expect
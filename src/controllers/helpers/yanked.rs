@@ -0,0 +1,48 @@
+//! The `include_yanked` query parameter of the endpoints that list crates,
+//! versions or dependencies.
+//!
+//! Endpoints differ in whether they include yanked versions by default, but
+//! the parameter has the same values everywhere, and an explicit value
+//! applies to everything the endpoint computes from the versions, e.g. also
+//! to the `max_version` of the crates in the search results.
+
+use crate::controllers::prelude::RequestUtils;
+use crate::controllers::util::RequestPartsExt;
+use crate::schema::versions;
+use crate::util::errors::{bad_request, AppResult};
+use diesel::dsl;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+
+/// A condition on the `versions` table that matches the non-yanked versions,
+/// and also the yanked versions if `include_yanked` is set.
+pub(crate) type YankedFilter = dsl::Or<dsl::Eq<versions::yanked, bool>, dsl::AsExprOf<bool, Bool>>;
+
+/// Parses the `include_yanked` query parameter, which is either `true` or
+/// `false`. The `yes`/`no` and `y`/`n` values of older clients are accepted
+/// too.
+///
+/// Returns `None` if the parameter is missing, so that the endpoint can
+/// apply its default.
+pub(crate) fn include_yanked<T: RequestPartsExt>(req: &T) -> AppResult<Option<bool>> {
+    let query = req.query();
+    let Some(value) = query.get("include_yanked") else {
+        return Ok(None);
+    };
+
+    match value.as_str() {
+        "true" | "yes" | "y" => Ok(Some(true)),
+        "false" | "no" | "n" => Ok(Some(false)),
+        _ => Err(bad_request(format!(
+            "invalid `include_yanked` value: `{value}`, expected `true` or `false`"
+        ))),
+    }
+}
+
+/// Returns the condition that filters the versions of a listing according
+/// to the `include_yanked` parameter.
+pub(crate) fn yanked_filter(include_yanked: bool) -> YankedFilter {
+    versions::yanked
+        .eq(false)
+        .or(include_yanked.into_sql::<Bool>())
+}
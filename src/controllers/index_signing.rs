@@ -0,0 +1,28 @@
+use crate::app::AppState;
+use crate::index_signing::ALGORITHM;
+use axum::response::IntoResponse;
+use axum::Json;
+
+/// Handles the `GET /.well-known/index-signing-keys.json` route.
+///
+/// Lists the public keys that are used to sign the files of the sparse
+/// index. Keys with an expiry date are still listed after they expired, until
+/// they are removed from the configuration, so that clients can verify files
+/// that were signed before a rotation.
+pub async fn list_keys(state: AppState) -> impl IntoResponse {
+    let keys = state
+        .config
+        .index_signing_keys
+        .iter()
+        .map(|key| {
+            json!({
+                "key_id": key.key_id,
+                "algorithm": ALGORITHM,
+                "public_key": key.public_key(),
+                "expires_at": key.expires_at,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Json(json!({ "keys": keys }))
+}
@@ -3,8 +3,8 @@ use crate::app::AppState;
 use axum::extract::{Path, Query};
 use axum::Json;
 
-use crate::controllers::helpers::pagination::PaginationOptions;
-use crate::controllers::helpers::{pagination::Paginated, Paginate};
+use crate::controllers::helpers::pagination::{Paginated, PaginationMeta, PaginationOptions};
+use crate::controllers::helpers::Paginate;
 use crate::models::Keyword;
 use crate::views::EncodableKeyword;
 
@@ -29,7 +29,11 @@ pub async fn index(state: AppState, qp: Query<IndexQuery>, req: Parts) -> AppRes
     let conn = state.db_read().await?;
     conn.interact(move |conn| {
         let data: Paginated<Keyword> = query.load(conn)?;
-        let total = data.total();
+        let meta = PaginationMeta {
+            total: data.total(),
+            next_page: data.next_page_params().map(|p| req.query_with_params(p)),
+            prev_page: data.prev_page_params().map(|p| req.query_with_params(p)),
+        };
         let kws = data
             .into_iter()
             .map(Keyword::into)
@@ -37,7 +41,7 @@ pub async fn index(state: AppState, qp: Query<IndexQuery>, req: Parts) -> AppRes
 
         Ok(Json(json!({
             "keywords": kws,
-            "meta": { "total": total },
+            "meta": meta,
         })))
     })
     .await?
@@ -1,7 +1,14 @@
+pub mod availability;
+pub mod descriptions;
+pub mod diff;
 pub mod downloads;
 pub mod follow;
 pub mod metadata;
 pub mod owners;
+pub mod permissions;
 pub mod publish;
+pub mod recommended_version;
+pub mod reserve;
+pub mod scheduled_releases;
 pub mod search;
 pub mod versions;
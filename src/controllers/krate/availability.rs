@@ -0,0 +1,114 @@
+//! Endpoint for checking whether a crate name can be used for a new crate
+//!
+//! The checks are the same that reject the name when a new crate is
+//! published, so that tooling can find out before attempting a publish.
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::publish::{find_upstream_conflict, is_reserved_name};
+use crate::models::{Crate, CrateNameReservation};
+use crate::schema::crates;
+
+/// Why a crate name is not available.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Reason {
+    /// The name is not a valid crate name.
+    Invalid,
+    /// A crate with this name exists already.
+    Taken,
+    /// A crate exists whose name only differs in case or in `-` and `_`.
+    TooSimilar,
+    /// The name is reserved by the policy of the registry.
+    Blocked,
+    /// The name was reserved by a user for publishing the crate later.
+    Reserved,
+    /// A crate with this name exists in the upstream registry.
+    Upstream,
+}
+
+/// Handles the `GET /crates/:crate_id/availability` route.
+pub async fn availability(app: AppState, Path(name): Path<String>) -> AppResult<Json<Value>> {
+    if let Err(error) = Crate::validate_crate_name("crate", &name) {
+        return Ok(unavailable(&name, Reason::Invalid, error.to_string()));
+    }
+
+    let conn = app.db_read().await?;
+    let unavailable_locally = conn
+        .interact({
+            let name = name.clone();
+            move |conn| check_local(conn, &name)
+        })
+        .await??;
+    drop(conn);
+
+    if let Some(response) = unavailable_locally {
+        return Ok(response);
+    }
+
+    // The upstream registry is checked without holding on to the database
+    // connection, since it can take a while to respond
+    if let Some(upstream) = app.upstream.as_ref() {
+        if let Some(candidate) = find_upstream_conflict(upstream.as_ref(), &name).await? {
+            let detail = format!(
+                "crate name `{name}` conflicts with the crate `{candidate}` of the upstream registry"
+            );
+            return Ok(unavailable(&name, Reason::Upstream, detail));
+        }
+    }
+
+    Ok(Json(json!({
+        "availability": {
+            "name": name,
+            "available": true,
+            "reason": null,
+            "detail": null,
+        }
+    })))
+}
+
+/// Checks the name against the crates and reservations of this registry.
+fn check_local(conn: &mut PgConnection, name: &str) -> AppResult<Option<Json<Value>>> {
+    let existing_name: Option<String> = crates::table
+        .filter(Crate::with_name(name))
+        .select(crates::name)
+        .first(conn)
+        .optional()?;
+
+    if let Some(existing_name) = existing_name {
+        return Ok(Some(if existing_name == name {
+            let detail = format!("crate `{name}` already exists");
+            unavailable(name, Reason::Taken, detail)
+        } else {
+            let detail = format!(
+                "crate name `{name}` is too similar to the existing crate `{existing_name}`"
+            );
+            unavailable(name, Reason::TooSimilar, detail)
+        }));
+    }
+
+    if is_reserved_name(name, conn)? {
+        let detail = format!("crate name `{name}` is reserved");
+        return Ok(Some(unavailable(name, Reason::Blocked, detail)));
+    }
+
+    if let Some(reservation) = CrateNameReservation::find_active(conn, name)? {
+        let detail = format!(
+            "crate name `{name}` has been reserved by a user until {}",
+            reservation.expires_at.format("%Y-%m-%d")
+        );
+        return Ok(Some(unavailable(name, Reason::Reserved, detail)));
+    }
+
+    Ok(None)
+}
+
+fn unavailable(name: &str, reason: Reason, detail: String) -> Json<Value> {
+    Json(json!({
+        "availability": {
+            "name": name,
+            "available": false,
+            "reason": reason,
+            "detail": detail,
+        }
+    }))
+}
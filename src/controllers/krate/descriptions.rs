@@ -0,0 +1,169 @@
+//! Endpoints for managing the translated descriptions of a crate
+//!
+//! The translation that best matches the `Accept-Language` header of a request
+//! replaces the description of the manifest in the crate detail and search
+//! responses.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, DescriptionTranslation, Rights};
+use crate::schema::crate_description_translations;
+use crate::util::accept_language::is_valid_language_tag;
+use crate::util::errors::{crate_not_found, custom};
+use crate::views::EncodableDescriptionTranslation;
+use diesel::dsl::now;
+use tokio::runtime::Handle;
+
+/// Maximum number of characters of a translated description.
+const MAX_DESCRIPTION_LENGTH: usize = 1000;
+
+/// Handles the `GET /crates/:crate_id/descriptions` route.
+pub async fn list(app: AppState, Path(crate_name): Path<String>) -> AppResult<Json<Value>> {
+    let conn = app.db_read().await?;
+    conn.interact(move |conn| {
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let translations = DescriptionTranslation::belonging_to(&krate)
+            .select(DescriptionTranslation::as_select())
+            .order(crate_description_translations::locale)
+            .load(conn)?
+            .into_iter()
+            .map(EncodableDescriptionTranslation::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "descriptions": translations })))
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct UpdateDescriptionRequest {
+    description: String,
+}
+
+/// Handles the `PUT /crates/:crate_id/descriptions/:locale` route.
+///
+/// Adds or replaces the description of the crate in the language of the
+/// `locale` tag, e.g. `de` or `pt-BR`. Only users that may publish the crate
+/// may change its descriptions.
+pub async fn update(
+    app: AppState,
+    Path((crate_name, locale)): Path<(String, String)>,
+    req: Parts,
+    Json(body): Json<UpdateDescriptionRequest>,
+) -> AppResult<Json<Value>> {
+    let locale = parse_locale(&locale)?;
+
+    let description = body.description.trim().to_string();
+    if description.is_empty() {
+        return Err(bad_request("the description must not be empty"));
+    }
+    if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        return Err(bad_request(format_args!(
+            "the description must not be longer than {MAX_DESCRIPTION_LENGTH} characters"
+        )));
+    }
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let crate_id = authorize(&app, &req, conn, &crate_name)?;
+
+        let translation = diesel::insert_into(crate_description_translations::table)
+            .values((
+                crate_description_translations::crate_id.eq(crate_id),
+                crate_description_translations::locale.eq(&locale),
+                crate_description_translations::description.eq(&description),
+            ))
+            .on_conflict((
+                crate_description_translations::crate_id,
+                crate_description_translations::locale,
+            ))
+            .do_update()
+            .set((
+                crate_description_translations::description.eq(&description),
+                crate_description_translations::updated_at.eq(now),
+            ))
+            .returning(DescriptionTranslation::as_returning())
+            .get_result(conn)?;
+
+        let translation = EncodableDescriptionTranslation::from(translation);
+        Ok(Json(json!({ "description": translation })))
+    })
+    .await?
+}
+
+/// Handles the `DELETE /crates/:crate_id/descriptions/:locale` route.
+pub async fn delete(
+    app: AppState,
+    Path((crate_name, locale)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Response> {
+    let locale = parse_locale(&locale)?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let crate_id = authorize(&app, &req, conn, &crate_name)?;
+
+        let deleted = diesel::delete(
+            crate_description_translations::table
+                .filter(crate_description_translations::crate_id.eq(crate_id))
+                .filter(crate_description_translations::locale.eq(&locale)),
+        )
+        .execute(conn)?;
+
+        if deleted == 0 {
+            return Err(custom(
+                StatusCode::NOT_FOUND,
+                format!("crate `{crate_name}` has no description for locale `{locale}`"),
+            ));
+        }
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Language tags are case-insensitive, so they are stored in lowercase.
+fn parse_locale(locale: &str) -> AppResult<String> {
+    if !is_valid_language_tag(locale) {
+        return Err(bad_request(format_args!(
+            "`{locale}` is not a valid language tag, e.g. `de` or `pt-BR`"
+        )));
+    }
+
+    Ok(locale.to_ascii_lowercase())
+}
+
+/// Checks that the authenticated user may publish the crate, and returns the
+/// ID of the crate.
+fn authorize(
+    app: &AppState,
+    req: &Parts,
+    conn: &mut PgConnection,
+    crate_name: &str,
+) -> AppResult<i32> {
+    let auth = AuthCheck::default()
+        .with_endpoint_scope(EndpointScope::PublishUpdate)
+        .for_crate(crate_name)
+        .check(req, conn)?;
+
+    let krate: Crate = Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))?;
+
+    let owners = krate.owners(conn)?;
+    let rights = Handle::current().block_on(auth.user().rights(app, &owners))?;
+    if rights < Rights::Publish {
+        return Err(custom(
+            StatusCode::FORBIDDEN,
+            "only owners have permission to change the descriptions of a crate",
+        ));
+    }
+
+    Ok(krate.id)
+}
@@ -0,0 +1,150 @@
+//! Endpoint for comparing two versions of a crate
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Crate, Dependency, Version};
+use crate::util::errors::crate_not_found;
+use crate::views::{
+    EncodableDependenciesDiff, EncodableDependency, EncodableDependencyChange,
+    EncodableFeatureChange, EncodableFeaturesDiff, EncodableLicenseChange, EncodableVersionDiff,
+};
+use std::collections::BTreeMap;
+
+/// Handles the `GET /crates/:crate_id/diff?from=:from&to=:to` route.
+///
+/// Returns the license, feature and dependency changes between the two
+/// versions of the crate.
+pub async fn diff(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let params = req.query();
+    let from = required_param(&params, "from")?;
+    let to = required_param(&params, "to")?;
+
+    let conn = state.db_read().await?;
+    conn.interact(move |conn| {
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let from = krate.find_version(conn, &from)?;
+        let to = krate.find_version(conn, &to)?;
+
+        let license = diff_license(&from, &to);
+        let features = diff_features(&from, &to);
+        let dependencies = diff_dependencies(from.dependencies(conn)?, to.dependencies(conn)?);
+
+        let diff = EncodableVersionDiff {
+            from: from.num,
+            to: to.num,
+            license,
+            features,
+            dependencies,
+        };
+
+        Ok(Json(json!({ "diff": diff })))
+    })
+    .await?
+}
+
+fn required_param(params: &indexmap::IndexMap<String, String>, name: &str) -> AppResult<String> {
+    params
+        .get(name)
+        .cloned()
+        .ok_or_else(|| bad_request(format!("missing `{name}` query parameter")))
+}
+
+fn diff_license(from: &Version, to: &Version) -> Option<EncodableLicenseChange> {
+    (from.license != to.license).then(|| EncodableLicenseChange {
+        from: from.license.clone(),
+        to: to.license.clone(),
+    })
+}
+
+fn diff_features(from: &Version, to: &Version) -> EncodableFeaturesDiff {
+    let mut from = parse_features(&from.features);
+    let to = parse_features(&to.features);
+
+    let mut diff = EncodableFeaturesDiff::default();
+    for (name, to_values) in to {
+        match from.remove(&name) {
+            None => {
+                diff.added.insert(name, to_values);
+            }
+            Some(from_values) if from_values != to_values => {
+                let change = EncodableFeatureChange {
+                    from: from_values,
+                    to: to_values,
+                };
+                diff.changed.insert(name, change);
+            }
+            Some(_) => {}
+        }
+    }
+
+    diff.removed = from;
+    diff
+}
+
+fn parse_features(features: &Value) -> BTreeMap<String, Vec<String>> {
+    serde_json::from_value(features.clone()).unwrap_or_default()
+}
+
+/// Dependencies are matched by the name they are used under in the crate, and
+/// by their kind and target. A dependency that only changed any of these is
+/// reported as removed and added.
+type DependencyKey = (String, i32, Option<String>);
+
+fn dependency_key(dependency: &Dependency, crate_name: &str) -> DependencyKey {
+    let name = dependency.explicit_name.as_deref().unwrap_or(crate_name);
+    let kind = dependency.kind as i32;
+    (name.to_string(), kind, dependency.target.clone())
+}
+
+fn diff_dependencies(
+    from: Vec<(Dependency, String)>,
+    to: Vec<(Dependency, String)>,
+) -> EncodableDependenciesDiff {
+    let mut from = from
+        .into_iter()
+        .map(|(dep, crate_name)| (dependency_key(&dep, &crate_name), (dep, crate_name)))
+        .collect::<BTreeMap<_, _>>();
+
+    let to = to
+        .into_iter()
+        .map(|(dep, crate_name)| (dependency_key(&dep, &crate_name), (dep, crate_name)))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut diff = EncodableDependenciesDiff::default();
+    for (key, (to_dep, to_crate_name)) in to {
+        match from.remove(&key) {
+            None => {
+                let to = EncodableDependency::from_dep(to_dep, &to_crate_name);
+                diff.added.push(to);
+            }
+            Some((from_dep, from_crate_name)) => {
+                let is_updated = from_crate_name != to_crate_name
+                    || from_dep.req != to_dep.req
+                    || from_dep.optional != to_dep.optional
+                    || from_dep.default_features != to_dep.default_features
+                    || from_dep.features != to_dep.features;
+
+                if is_updated {
+                    let from = EncodableDependency::from_dep(from_dep, &from_crate_name);
+                    let to = EncodableDependency::from_dep(to_dep, &to_crate_name);
+                    diff.updated.push(EncodableDependencyChange { from, to });
+                }
+            }
+        }
+    }
+
+    diff.removed = from
+        .into_values()
+        .map(|(dep, crate_name)| EncodableDependency::from_dep(dep, &crate_name))
+        .collect();
+
+    diff
+}
@@ -3,15 +3,18 @@
 //! The endpoint for downloading a crate and exposing version specific
 //! download counts are located in `version::downloads`.
 
+use chrono::NaiveDate;
 use std::cmp;
 
+use crate::auth::AuthCheck;
 use crate::controllers::frontend_prelude::*;
+use tokio::runtime::Handle;
 
-use crate::models::{Crate, Version, VersionDownload};
-use crate::schema::{crates, version_downloads, versions};
+use crate::models::{Crate, Rights, Version, VersionDownload};
+use crate::schema::{crate_client_downloads, version_downloads, versions};
 use crate::sql::to_char;
-use crate::util::errors::crate_not_found;
-use crate::views::EncodableVersionDownload;
+use crate::util::errors::{crate_not_found, custom};
+use crate::views::{EncodableClientDownload, EncodableVersionDownload};
 
 /// Handles the `GET /crates/:crate_id/downloads` route.
 pub async fn downloads(state: AppState, Path(crate_name): Path<String>) -> AppResult<Json<Value>> {
@@ -20,14 +23,11 @@ pub async fn downloads(state: AppState, Path(crate_name): Path<String>) -> AppRe
         use diesel::dsl::*;
         use diesel::sql_types::BigInt;
 
-        let crate_id: i32 = Crate::by_name(&crate_name)
-            .select(crates::id)
-            .first(conn)
-            .optional()?
-            .ok_or_else(|| crate_not_found(&crate_name))?;
+        let crate_id = state.crate_id_cache.get_or_load(conn, &crate_name)?;
 
         let mut versions: Vec<Version> = versions::table
             .filter(versions::crate_id.eq(crate_id))
+            .filter(Version::is_released())
             .load(conn)?;
         versions
             .sort_by_cached_key(|version| cmp::Reverse(semver::Version::parse(&version.num).ok()));
@@ -70,3 +70,56 @@ pub async fn downloads(state: AppState, Path(crate_name): Path<String>) -> AppRe
     })
     .await?
 }
+
+/// Handles the `GET /crates/:crate_id/client_downloads` route.
+///
+/// Returns the downloads of the last 90 days per day and cargo version,
+/// which can help owners to decide on a minimum supported Rust version or
+/// on the deprecation of old releases. Only owners of the crate have access
+/// to these statistics.
+pub async fn client_downloads(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        use diesel::dsl::*;
+
+        let auth = AuthCheck::default().check(&req, conn)?;
+        let user = auth.user();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let owners = krate.owners(conn)?;
+        if Handle::current().block_on(user.rights(&state, &owners))? < Rights::Publish {
+            return Err(custom(
+                StatusCode::FORBIDDEN,
+                "must be an owner of the crate to view its client download statistics",
+            ));
+        }
+
+        let downloads = crate_client_downloads::table
+            .filter(crate_client_downloads::crate_id.eq(krate.id))
+            .filter(crate_client_downloads::date.gt(date(now - 90.days())))
+            .select((
+                crate_client_downloads::date,
+                crate_client_downloads::client_version,
+                crate_client_downloads::downloads,
+            ))
+            .order((
+                crate_client_downloads::date.asc(),
+                crate_client_downloads::client_version.asc(),
+            ))
+            .load::<(NaiveDate, String, i32)>(conn)?
+            .into_iter()
+            .map(EncodableClientDownload::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "client_downloads": downloads })))
+    })
+    .await?
+}
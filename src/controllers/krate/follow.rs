@@ -8,6 +8,10 @@ use crate::models::{Crate, Follow};
 use crate::schema::*;
 use crate::util::errors::crate_not_found;
 
+/// Maximum number of crates that can be followed or unfollowed with a single
+/// `PUT /me/following` request.
+const MAX_FOLLOWING_UPDATES: usize = 100;
+
 fn follow_target(crate_name: &str, conn: &mut PgConnection, user_id: i32) -> AppResult<Follow> {
     let crate_id = Crate::by_name(crate_name)
         .select(crates::id)
@@ -74,3 +78,74 @@ pub async fn following(
     })
     .await?
 }
+
+/// Handles the `PUT /me/following` route.
+///
+/// Follows and unfollows multiple crates at once, which is a lot faster than
+/// calling the per-crate endpoints when importing a watchlist. The changes are
+/// applied atomically, so if any of the crates does not exist, nothing is
+/// changed. Returns the names of all crates that the user follows afterwards.
+pub async fn update_following(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
+    #[derive(Deserialize)]
+    struct FollowingUpdate {
+        #[serde(default)]
+        follow: Vec<String>,
+        #[serde(default)]
+        unfollow: Vec<String>,
+    }
+
+    let update = serde_json::from_slice::<FollowingUpdate>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?;
+
+    if update.follow.len() + update.unfollow.len() > MAX_FOLLOWING_UPDATES {
+        return Err(bad_request(format!(
+            "at most {MAX_FOLLOWING_UPDATES} crates can be followed or unfollowed at once"
+        )));
+    }
+
+    if let Some(name) = update
+        .follow
+        .iter()
+        .find(|name| update.unfollow.contains(name))
+    {
+        return Err(bad_request(format!(
+            "crate `{name}` can't be followed and unfollowed at the same time"
+        )));
+    }
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::default().check(&req, conn)?.user_id();
+
+        conn.transaction(|conn| {
+            let new_follows = (update.follow.iter())
+                .map(|name| follow_target(name, conn, user_id))
+                .collect::<AppResult<Vec<_>>>()?;
+
+            let removed_follows = (update.unfollow.iter())
+                .map(|name| follow_target(name, conn, user_id))
+                .collect::<AppResult<Vec<_>>>()?;
+
+            diesel::insert_into(follows::table)
+                .values(&new_follows)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            let unfollowed_ids = removed_follows.iter().map(|follow| follow.crate_id);
+            diesel::delete(follows::table)
+                .filter(follows::user_id.eq(user_id))
+                .filter(follows::crate_id.eq_any(unfollowed_ids))
+                .execute(conn)?;
+
+            let following: Vec<String> = follows::table
+                .inner_join(crates::table)
+                .filter(follows::user_id.eq(user_id))
+                .select(crates::name)
+                .order(crates::name)
+                .load(conn)?;
+
+            Ok(Json(json!({ "following": following })))
+        })
+    })
+    .await?
+}
@@ -4,29 +4,38 @@
 //! index or cached metadata which was extracted (client side) from the
 //! `Cargo.toml` file.
 
+use chrono::NaiveDate;
 use std::cmp::Reverse;
 use std::str::FromStr;
 
 use crate::controllers::frontend_prelude::*;
-use crate::controllers::helpers::pagination::PaginationOptions;
+use crate::controllers::helpers::pagination::{PaginationMeta, PaginationOptions};
+use crate::controllers::helpers::yanked;
 
 use crate::models::{
-    Category, Crate, CrateCategory, CrateKeyword, CrateVersions, Keyword, RecentCrateDownloads,
-    User, Version, VersionOwnerAction,
+    Category, Crate, CrateCategory, CrateKeyword, CrateVersions, DescriptionTranslation, Keyword,
+    Owner, RecentCrateDownloads, RecommendedVersion, User, Version, VersionOwnerAction,
+    VersionProvenance,
 };
 use crate::schema::*;
+use crate::util::accept_language::AcceptLanguage;
 use crate::util::errors::crate_not_found;
+use crate::views::json_api::{self, Document, Fieldsets, Resource};
 use crate::views::{
-    EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword, EncodableVersion,
+    encodable_crate, encodable_version, EncodableCategory, EncodableDependency,
+    EncodableDependentsCount, EncodableKeyword, EncodableOwner,
 };
 
 /// Handles the `GET /crates/new` special case.
-pub async fn show_new(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+pub async fn show_new(app: AppState, req: Parts) -> AppResult<Response> {
     show(app, Path("new".to_string()), req).await
 }
 
 /// Handles the `GET /crates/:crate_id` route.
-pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppResult<Json<Value>> {
+///
+/// Responds with a JSON:API document if the client requests it, see
+/// [`json_api`].
+pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppResult<Response> {
     let conn = app.db_read().await?;
     conn.interact(move |conn| {
         let include = req
@@ -62,7 +71,8 @@ pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppRes
                 versions_and_publishers
                     .into_iter()
                     .zip(VersionOwnerAction::for_versions(conn, &versions)?)
-                    .map(|((v, pb), aas)| (v, pb, aas))
+                    .zip(VersionProvenance::for_versions(conn, &versions)?)
+                    .map(|(((v, pb), aas), p)| (v, pb, aas, p))
                     .collect::<Vec<_>>(),
             )
         } else {
@@ -109,7 +119,18 @@ pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppRes
             None
         };
 
-        let encodable_crate = EncodableCrate::from(
+        let owners = if include.owners {
+            Some(krate.owners(conn)?)
+        } else {
+            None
+        };
+
+        let accept_language = AcceptLanguage::from_headers(&req.headers);
+        let mut translations =
+            DescriptionTranslation::best_matches(conn, &[krate.id], &accept_language)?;
+        let mut recommended_versions = RecommendedVersion::for_crates(conn, &[krate.id])?;
+
+        let mut encodable_crate = encodable_crate(
             krate.clone(),
             top_versions.as_ref(),
             ids,
@@ -120,9 +141,14 @@ pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppRes
             downloads,
             recent_downloads,
         );
+        if let Some(description) = translations.remove(&krate.id) {
+            encodable_crate.description = Some(description);
+        }
+        encodable_crate.recommended_version = recommended_versions.remove(&krate.id);
+
         let encodable_versions = versions_publishers_and_audit_actions.map(|vpa| {
             vpa.into_iter()
-                .map(|(v, pb, aas)| EncodableVersion::from(v, &krate.name, pb, aas))
+                .map(|(v, pb, aas, p)| encodable_version(v, &krate.name, pb, aas, p))
                 .collect::<Vec<_>>()
         });
         let encodable_keywords = kws.map(|kws| {
@@ -135,12 +161,43 @@ pub async fn show(app: AppState, Path(name): Path<String>, req: Parts) -> AppRes
                 .map(Category::into)
                 .collect::<Vec<EncodableCategory>>()
         });
-        Ok(Json(json!({
+        if json_api::is_requested(&req.headers) {
+            let fieldsets = Fieldsets::from_query(&req.query());
+            let resource = Resource::from_crate(encodable_crate);
+
+            let versions = encodable_versions.into_iter().flatten();
+            let keywords = encodable_keywords.into_iter().flatten();
+            let categories = encodable_cats.into_iter().flatten();
+            let owners = owners.into_iter().flatten().map(EncodableOwner::from);
+
+            let document = Document::single(resource, fieldsets)
+                .include(versions.map(Resource::from_version))
+                .include(keywords.map(|keyword| Resource::new("keywords", keyword)))
+                .include(categories.map(|category| Resource::new("categories", category)))
+                .include(owners.map(Resource::from_owner));
+
+            return Ok(document.into_response());
+        }
+
+        let mut response = json!({
             "crate": encodable_crate,
             "versions": encodable_versions,
             "keywords": encodable_keywords,
             "categories": encodable_cats,
-        })))
+        });
+
+        // The owners are not part of the legacy response, so the field is
+        // only added if they were requested explicitly.
+        if let Some(owners) = owners {
+            let owners = owners
+                .into_iter()
+                .map(Owner::into)
+                .collect::<Vec<EncodableOwner>>();
+
+            response["owners"] = json!(owners);
+        }
+
+        Ok(Json(response).into_response())
     })
     .await?
 }
@@ -152,6 +209,7 @@ struct ShowIncludeMode {
     categories: bool,
     badges: bool,
     downloads: bool,
+    owners: bool,
 }
 
 impl Default for ShowIncludeMode {
@@ -163,13 +221,14 @@ impl Default for ShowIncludeMode {
             categories: true,
             badges: true,
             downloads: true,
+            owners: false,
         }
     }
 }
 
 impl ShowIncludeMode {
     const INVALID_COMPONENT: &'static str =
-        "invalid component for ?include= (expected 'versions', 'keywords', 'categories', 'badges', 'downloads', or 'full')";
+        "invalid component for ?include= (expected 'versions', 'keywords', 'categories', 'badges', 'downloads', 'owners', or 'full')";
 }
 
 impl FromStr for ShowIncludeMode {
@@ -182,17 +241,15 @@ impl FromStr for ShowIncludeMode {
             categories: false,
             badges: false,
             downloads: false,
+            owners: false,
         };
         for component in s.split(',') {
             match component {
                 "" => {}
                 "full" => {
                     mode = Self {
-                        versions: true,
-                        keywords: true,
-                        categories: true,
-                        badges: true,
-                        downloads: true,
+                        owners: mode.owners,
+                        ..Self::default()
                     }
                 }
                 "versions" => mode.versions = true,
@@ -200,6 +257,7 @@ impl FromStr for ShowIncludeMode {
                 "categories" => mode.categories = true,
                 "badges" => mode.badges = true,
                 "downloads" => mode.downloads = true,
+                "owners" => mode.owners = true,
                 _ => return Err(bad_request(Self::INVALID_COMPONENT)),
             }
         }
@@ -236,7 +294,18 @@ pub async fn reverse_dependencies(
             .optional()?
             .ok_or_else(|| crate_not_found(&name))?;
 
-        let (rev_deps, total) = krate.reverse_dependencies(conn, pagination_options)?;
+        let include_yanked = yanked::include_yanked(&req)?.unwrap_or(false);
+        let rev_deps = krate.reverse_dependencies(conn, pagination_options, include_yanked)?;
+        let meta = PaginationMeta {
+            total: rev_deps.total(),
+            next_page: rev_deps
+                .next_page_params()
+                .map(|p| req.query_with_params(p)),
+            prev_page: rev_deps
+                .prev_page_params()
+                .map(|p| req.query_with_params(p)),
+        };
+
         let rev_deps: Vec<_> = rev_deps
             .into_iter()
             .map(|dep| EncodableDependency::from_reverse_dep(dep, &krate.name))
@@ -262,16 +331,50 @@ pub async fn reverse_dependencies(
         let versions = versions_and_publishers
             .into_iter()
             .zip(VersionOwnerAction::for_versions(conn, &versions)?)
-            .map(|((version, krate_name, published_by), actions)| {
-                EncodableVersion::from(version, &krate_name, published_by, actions)
-            })
+            .zip(VersionProvenance::for_versions(conn, &versions)?)
+            .map(
+                |(((version, krate_name, published_by), actions), provenance)| {
+                    encodable_version(version, &krate_name, published_by, actions, provenance)
+                },
+            )
             .collect::<Vec<_>>();
 
         Ok(Json(json!({
             "dependencies": rev_deps,
             "versions": versions,
-            "meta": { "total": total },
+            "meta": meta,
         })))
     })
     .await?
 }
+
+/// Handles the `GET /crates/:crate_id/dependents_history` route.
+///
+/// Returns the weekly snapshots of the number of crates that depend on the
+/// crate, oldest first. Weeks in which the crate had no dependents are
+/// missing from the list.
+pub async fn dependents_history(app: AppState, Path(name): Path<String>) -> AppResult<Json<Value>> {
+    let conn = app.db_read().await?;
+    conn.interact(move |conn| {
+        let crate_id: i32 = Crate::by_name(&name)
+            .select(crates::id)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&name))?;
+
+        let history = crate_dependents_history::table
+            .filter(crate_dependents_history::crate_id.eq(crate_id))
+            .select((
+                crate_dependents_history::date,
+                crate_dependents_history::dependents,
+            ))
+            .order(crate_dependents_history::date.asc())
+            .load::<(NaiveDate, i32)>(conn)?
+            .into_iter()
+            .map(EncodableDependentsCount::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "dependents_history": history })))
+    })
+    .await?
+}
@@ -3,13 +3,23 @@
 use crate::auth::AuthCheck;
 use crate::controllers::prelude::*;
 use crate::models::token::EndpointScope;
-use crate::models::{Crate, Owner, Rights, Team, User};
+use crate::models::{Crate, CrateOwnerAction, Owner, OwnerKind, Rights, Team, User};
+use crate::schema::{teams, users};
 use crate::util::errors::{bad_request, crate_not_found, custom};
-use crate::views::EncodableOwner;
+use crate::views::json_api::{self, Document, Fieldsets, Resource};
+use crate::views::{EncodableCrateOwnerAction, EncodableOwner};
+use std::collections::HashMap;
 use tokio::runtime::Handle;
 
 /// Handles the `GET /crates/:crate_id/owners` route.
-pub async fn owners(state: AppState, Path(crate_name): Path<String>) -> AppResult<Json<Value>> {
+///
+/// Responds with a JSON:API document if the client requests it, see
+/// [`json_api`].
+pub async fn owners(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
     let conn = state.db_read().await?;
     conn.interact(move |conn| {
         let krate: Crate = Crate::by_name(&crate_name)
@@ -23,7 +33,80 @@ pub async fn owners(state: AppState, Path(crate_name): Path<String>) -> AppResul
             .map(Owner::into)
             .collect::<Vec<EncodableOwner>>();
 
-        Ok(Json(json!({ "users": owners })))
+        if json_api::is_requested(&req.headers) {
+            let fieldsets = Fieldsets::from_query(&req.query());
+            let owners = owners.into_iter().map(Resource::from_owner).collect();
+            return Ok(Document::collection(owners, fieldsets).into_response());
+        }
+
+        Ok(Json(json!({ "users": owners })).into_response())
+    })
+    .await?
+}
+
+/// Handles the `GET /crates/:crate_id/owners/history` route.
+///
+/// Responds with all owner additions and removals of the crate in
+/// chronological order, including who performed them and how.
+pub async fn owner_history(
+    state: AppState,
+    Path(crate_name): Path<String>,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read().await?;
+    conn.interact(move |conn| {
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let actions = CrateOwnerAction::by_crate(conn, krate.id)?;
+
+        let owner_ids = |kind| {
+            actions
+                .iter()
+                .filter(move |action| action.owner_kind == kind)
+                .map(|action| action.owner_id)
+        };
+
+        let actor_ids = actions.iter().filter_map(|action| action.actor_id);
+        let user_ids = owner_ids(OwnerKind::User)
+            .chain(actor_ids)
+            .collect::<Vec<_>>();
+        let users: HashMap<i32, User> = users::table
+            .filter(users::id.eq_any(user_ids))
+            .load::<User>(conn)?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        let team_ids = owner_ids(OwnerKind::Team).collect::<Vec<_>>();
+        let teams: HashMap<i32, Team> = teams::table
+            .filter(teams::id.eq_any(team_ids))
+            .load::<Team>(conn)?
+            .into_iter()
+            .map(|team| (team.id, team))
+            .collect();
+
+        let actions = actions
+            .into_iter()
+            .map(|action| {
+                let owner = match action.owner_kind {
+                    OwnerKind::User => users.get(&action.owner_id).cloned().map(Owner::User),
+                    OwnerKind::Team => teams.get(&action.owner_id).cloned().map(Owner::Team),
+                };
+                let actor = action.actor_id.and_then(|id| users.get(&id)).cloned();
+
+                EncodableCrateOwnerAction {
+                    action: action.action,
+                    via: action.via,
+                    owner: owner.map(Owner::into),
+                    actor: actor.map(User::into),
+                    time: action.time,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "actions": actions })))
     })
     .await?
 }
@@ -149,7 +232,7 @@ async fn modify_owners(
                 msgs.join(",")
             } else {
                 for login in &logins {
-                    krate.owner_remove(conn, login)?;
+                    krate.owner_remove(&app, conn, user, login)?;
                 }
                 if User::owning(&krate, conn)?.is_empty() {
                     return Err(bad_request(
@@ -0,0 +1,55 @@
+//! Endpoint for checking what the authenticated user may do with a crate
+
+use crate::auth::{authenticate, AuthCheck};
+use crate::controllers::frontend_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, Rights};
+use crate::util::errors::crate_not_found;
+use crate::views::EncodableCratePermissions;
+use tokio::runtime::Handle;
+
+/// Handles the `GET /crates/:crate_id/permissions` route.
+///
+/// Returns whether the authenticated user may publish new versions, yank
+/// versions or change the owners of the crate. If the request is
+/// authenticated with an API token, the scopes of the token are taken into
+/// account too. This allows the frontend and cargo plugins to disable these
+/// actions upfront, instead of running into a `403 Forbidden` error.
+pub async fn permissions(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = state.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = authenticate(&req, conn)?;
+        let user = auth.user();
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let owners = krate.owners(conn)?;
+        let rights = Handle::current().block_on(user.rights(&state, &owners))?;
+        let has_verified_email = user.verified_email(conn)?.is_some();
+
+        let token_allows = |endpoint_scope| {
+            AuthCheck::default()
+                .with_endpoint_scope(endpoint_scope)
+                .for_crate(&krate.name)
+                .allows(&auth)
+        };
+
+        let permissions = EncodableCratePermissions {
+            publish: rights >= Rights::Publish
+                && has_verified_email
+                && token_allows(EndpointScope::PublishUpdate),
+            yank: (rights >= Rights::Publish || user.is_admin) && token_allows(EndpointScope::Yank),
+            change_owners: rights == Rights::Full && token_allows(EndpointScope::ChangeOwners),
+        };
+
+        Ok(Json(json!({ "permissions": permissions })))
+    })
+    .await?
+}
@@ -1,38 +1,47 @@
 //! Functionality related to publishing a new crate or version of a crate.
 
+use crate::audit::AuditEvent;
 use crate::auth::AuthCheck;
-use crate::worker::jobs::{self, CheckTyposquat};
-use axum::body::Bytes;
+use crate::ci::CiService;
+use crate::worker::jobs::{self, CheckTyposquat, SendPublishNotifications};
 use cargo_manifest::{Dependency, DepsSet, TargetDepsSet};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
 use crates_io_tarball::{process_tarball, TarballError};
 use crates_io_worker::BackgroundJob;
 use diesel::connection::DefaultLoadingMode;
 use diesel::dsl::{exists, select};
-use hex::ToHex;
-use hyper::body::Buf;
-use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use tokio::runtime::Handle;
 use url::Url;
 
+pub mod nonce;
+mod upload;
+
+pub use self::upload::{PublishRequest, Tarball};
+
 use crate::controllers::cargo_prelude::*;
 use crate::models::{
-    insert_version_owner_action, Category, Crate, DependencyKind, Keyword, NewCrate, NewVersion,
-    Rights, VersionAction,
+    Category, Crate, CrateNameReservation, DependencyKind, Keyword, NewCrate, NewScheduledRelease,
+    NewVersion, NewVersionProvenance, Rights, TokenAnomaly, VersionAction,
 };
 
 use crate::licenses::parse_license_expr;
 use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::real_ip::RealIp;
 use crate::models::token::EndpointScope;
 use crate::rate_limiter::LimitedAction;
 use crate::schema::*;
 use crate::sql::canon_crate_name;
-use crate::util::errors::{bad_request, custom, internal, AppResult};
+use crate::upstream::UpstreamRegistry;
+use crate::util::errors::{bad_request, custom, forbidden, internal, AppResult, ValidationErrors};
 use crate::util::Maximums;
 use crate::views::{
-    EncodableCrate, EncodableCrateDependency, GoodCrate, PublishMetadata, PublishWarnings,
+    encodable_crate_minimal, EncodableCrateDependency, GoodCrate, PublishMetadata, PublishWarnings,
 };
 
+/// How far in the future the release of a version can be scheduled.
+const MAX_RELEASE_DELAY_DAYS: i64 = 90;
+
 const MISSING_RIGHTS_ERROR_MESSAGE: &str = "this crate exists but you don't seem to be an owner. \
      If you believe this is a mistake, perhaps you need \
      to accept an invitation to be an owner before \
@@ -42,17 +51,29 @@ const MISSING_RIGHTS_ERROR_MESSAGE: &str = "this crate exists but you don't seem
 /// Used by `cargo publish` to publish a new crate or to publish a new version of an
 /// existing crate.
 ///
+/// Besides the binary request body format used by `cargo`, this endpoint also
+/// accepts `multipart/form-data` uploads. See the [`upload`] module for details.
+///
 /// Currently blocks the HTTP thread, perhaps some function calls can spawn new
 /// threads and return completion or error through other methods  a `cargo publish
 /// --status` command, via crates.io's front end, or email.
-pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCrate>> {
-    let (req, bytes) = req.0.into_parts();
-    let (json_bytes, tarball_bytes) = split_body(bytes)?;
-
-    let metadata: PublishMetadata = serde_json::from_slice(&json_bytes)
+pub async fn publish(
+    app: AppState,
+    ci_service: Option<CiService>,
+    req: PublishRequest,
+) -> AppResult<Json<GoodCrate>> {
+    let PublishRequest {
+        parts: req,
+        metadata,
+        mut tarball,
+    } = req;
+
+    let metadata: PublishMetadata = serde_json::from_slice(&metadata)
         .map_err(|e| bad_request(format_args!("invalid upload request: {e}")))?;
 
-    Crate::validate_crate_name("crate", &metadata.name).map_err(bad_request)?;
+    let mut errors = ValidationErrors::new();
+    errors.check("name", Crate::validate_crate_name("crate", &metadata.name));
+    errors.into_result()?;
 
     let version = match semver::Version::parse(&metadata.vers) {
         Ok(parsed) => parsed,
@@ -91,6 +112,16 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             .for_crate(&metadata.name)
             .check(&req, conn)?;
 
+        if let Some(token) = auth.api_token() {
+            if !token.allows_publish_of(&metadata.name, &version) {
+                req.request_log().add("cause", "Crate scope version mismatch");
+
+                return Err(forbidden(format!(
+                    "this token does not have the required permissions to publish version {version_string} of this crate"
+                )));
+            }
+        }
+
         let api_token_id = auth.api_token_id();
         let user = auth.user();
 
@@ -103,15 +134,43 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             ))
         })?;
 
+        if user.gh_token_revoked_at.is_some() {
+            return Err(forbidden(format!(
+                "Publishing is blocked because GitHub rejected your authorization of crates.io. \
+                 Please sign in to https://{} again to unblock it.",
+                app.config.domain_name,
+            )));
+        }
+
+        if TokenAnomaly::publishes_locked(conn, user.id)? {
+            return Err(forbidden(
+                "Publishing is locked because of unusual activity of your API tokens. \
+                 Please confirm the activity via the link in the email that was sent to you, \
+                 or contact help@crates.io.",
+            ));
+        }
+
+        let publish_nonce = nonce::nonce_from_request(&req, app.config.publish_nonces.required)?;
+
+        if let Some(release_at) = metadata.release_at {
+            validate_release_at(release_at, app.clock.naive_now(), existing_crate.is_some())?;
+        }
+
         // Use a different rate limit whether this is a new or an existing crate.
         let rate_limit_action = match existing_crate {
             Some(_) => LimitedAction::PublishUpdate,
             None => LimitedAction::PublishNew,
         };
         app.rate_limiter
-            .check_rate_limit(user.id, rate_limit_action, conn)?;
+            .check_rate_limit(user.id, rate_limit_action, app.clock.naive_now(), conn)?;
 
-        let content_length = tarball_bytes.len() as u64;
+        if existing_crate.is_none() {
+            if let Some(upstream) = app.upstream.as_ref() {
+                check_upstream_conflict(upstream.as_ref(), &metadata.name)?;
+            }
+        }
+
+        let content_length = tarball.size();
 
         let maximums = Maximums::new(
             existing_crate.as_ref().and_then(|c| c.max_upload_size),
@@ -127,7 +186,7 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
         }
 
         let pkg_name = format!("{}-{}", &*metadata.name, &version_string);
-        let tarball_info = process_tarball(&pkg_name, &*tarball_bytes, maximums.max_unpack_size)?;
+        let tarball_info = process_tarball(&pkg_name, tarball.reader()?, maximums.max_unpack_size)?;
 
         // `unwrap()` is safe here since `process_tarball()` validates that
         // we only accept manifests with a `package` section and without
@@ -142,36 +201,36 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
         let repository = package.repository.map(|it| it.as_local().unwrap());
         let rust_version = package.rust_version.map(|rv| rv.as_local().unwrap());
 
+        // Collect all validation errors of the metadata, so that they can be
+        // fixed at once instead of one publish attempt at a time.
+        let mut errors = ValidationErrors::new();
+
         // Make sure required fields are provided
         fn empty(s: Option<&String>) -> bool {
             s.map_or(true, String::is_empty)
         }
 
-        // It can have up to three elements per below conditions.
-        let mut missing = Vec::with_capacity(3);
         if empty(description.as_ref()) {
-            missing.push("description");
+            errors.add("description", missing_metadata_error_message(&["description"]));
         }
         if empty(license.as_ref()) && empty(license_file.as_ref()) {
-            missing.push("license");
-        }
-        if !missing.is_empty() {
-            let message = missing_metadata_error_message(&missing);
-            return Err(bad_request(&message));
+            errors.add("license", missing_metadata_error_message(&["license"]));
         }
 
         if let Some(ref license) = license {
-            parse_license_expr(license).map_err(|e| bad_request(format_args!(
-                "unknown or invalid license expression; \
-                see http://opensource.org/licenses for options, \
-                and http://spdx.org/licenses/ for their identifiers\n\
-                Note: If you have a non-standard license that is not listed by SPDX, \
-                use the license-file field to specify the path to a file containing \
-                the text of the license.\n\
-                See https://doc.rust-lang.org/cargo/reference/manifest.html#the-license-and-license-file-fields \
-                for more information.\n\
-                {e}"
-            )))?;
+            if let Err(e) = parse_license_expr(license) {
+                errors.add("license", format_args!(
+                    "unknown or invalid license expression; \
+                    see http://opensource.org/licenses for options, \
+                    and http://spdx.org/licenses/ for their identifiers\n\
+                    Note: If you have a non-standard license that is not listed by SPDX, \
+                    use the license-file field to specify the path to a file containing \
+                    the text of the license.\n\
+                    See https://doc.rust-lang.org/cargo/reference/manifest.html#the-license-and-license-file-fields \
+                    for more information.\n\
+                    {e}"
+                ));
+            }
         } else if license_file.is_some() {
             // If no license is given, but a license file is given, flag this
             // crate as having a nonstandard license. Note that we don't
@@ -179,11 +238,11 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             license = Some(String::from("non-standard"));
         }
 
-        validate_url(homepage.as_deref(), "homepage")?;
-        validate_url(documentation.as_deref(), "documentation")?;
-        validate_url(repository.as_deref(), "repository")?;
+        errors.check("homepage", validate_url(homepage.as_deref(), "homepage"));
+        errors.check("documentation", validate_url(documentation.as_deref(), "documentation"));
+        errors.check("repository", validate_url(repository.as_deref(), "repository"));
         if let Some(ref rust_version) =  rust_version {
-            validate_rust_version(rust_version)?;
+            errors.check("rust-version", validate_rust_version(rust_version));
         }
 
         let keywords = package
@@ -192,16 +251,16 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             .unwrap_or_default();
 
         if keywords.len() > 5 {
-            return Err(bad_request("expected at most 5 keywords per crate"));
+            errors.add("keywords", "expected at most 5 keywords per crate");
         }
 
         for keyword in keywords.iter() {
             if keyword.len() > 20 {
-                return Err(bad_request(format!(
+                errors.add("keywords", format!(
                     "\"{keyword}\" is an invalid keyword (keywords must have less than 20 characters)"
-                )));
+                ));
             } else if !Keyword::valid_name(keyword) {
-                return Err(bad_request(format!("\"{keyword}\" is an invalid keyword")));
+                errors.add("keywords", format!("\"{keyword}\" is an invalid keyword"));
             }
         }
 
@@ -211,7 +270,22 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
             .unwrap_or_default();
 
         if categories.len() > 5 {
-            return Err(bad_request("expected at most 5 categories per crate"));
+            errors.add("categories", "expected at most 5 categories per crate");
+        } else {
+            let slugs = categories.iter().map(String::as_str).collect::<Vec<_>>();
+            for unknown in Category::find_unknown(conn, &slugs)? {
+                let detail = match unknown.suggestions.first() {
+                    Some(suggestion) => format!(
+                        "unknown category \"{}\", did you mean \"{suggestion}\"? ",
+                        unknown.slug
+                    ),
+                    None => format!("unknown category \"{}\". ", unknown.slug),
+                };
+                errors.add_with_suggestions("categories", &unknown.slug, format!(
+                    "{detail}See https://{}/category_slugs for a list of valid categories.",
+                    app.config.domain_name
+                ), unknown.suggestions);
+            }
         }
 
         let max_features = existing_crate.as_ref()
@@ -221,7 +295,7 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
         let features = tarball_info.manifest.features.unwrap_or_default();
         let num_features = features.len();
         if num_features > max_features {
-            return Err(bad_request(format!(
+            errors.add("features", format!(
                 "crates.io only allows a maximum number of {max_features} \
                 features, but your crate is declaring {num_features} features.\n\
                 \n\
@@ -230,15 +304,15 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
                 \n\
                 If you have a use case that requires an increase of this limit, \
                 please send us an email to help@crates.io to discuss the details."
-            )));
+            ));
         }
 
         for (key, values) in features.iter() {
-            Crate::validate_feature_name(key).map_err(bad_request)?;
+            errors.check("features", Crate::validate_feature_name(key));
 
             let num_features = values.len();
             if num_features > max_features {
-                return Err(bad_request(format!(
+                errors.add("features", format!(
                     "crates.io only allows a maximum number of {max_features} \
                     features or dependencies that another feature can enable, \
                     but the \"{key}\" feature of your crate is enabling \
@@ -249,11 +323,11 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
                     \n\
                     If you have a use case that requires an increase of this limit, \
                     please send us an email to help@crates.io to discuss the details."
-                )));
+                ));
             }
 
             for value in values.iter() {
-                Crate::validate_feature(value).map_err(bad_request)?;
+                errors.check("features", Crate::validate_feature(value));
             }
         }
 
@@ -266,22 +340,31 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
 
         let max_dependencies = app.config.max_dependencies;
         if deps.len() > max_dependencies {
-            return Err(bad_request(format!(
+            errors.add("dependencies", format!(
                 "crates.io only allows a maximum number of {max_dependencies} dependencies.\n\
                 \n\
                 If you have a use case that requires an increase of this limit, \
                 please send us an email to help@crates.io to discuss the details."
-            )));
+            ));
         }
 
         for dep in &deps {
-            validate_dependency(dep)?;
+            errors.check("dependencies", validate_dependency(dep));
         }
 
+        errors.into_result()?;
+
         // Create a transaction on the database, if there are no errors,
         // commit the transactions to record a new or updated crate.
         conn.transaction(|conn| {
             let name = metadata.name;
+
+            // Consuming the nonce as part of the transaction keeps it valid
+            // if the publish fails and has to be retried.
+            if let Some(nonce) = &publish_nonce {
+                nonce::consume_nonce(conn, nonce, user.id, &name, &version_string)?;
+            }
+
             let keywords = keywords.iter().map(|s| s.as_str()).collect::<Vec<_>>();
             let categories = categories.iter().map(|s| s.as_str()).collect::<Vec<_>>();
 
@@ -301,10 +384,23 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
                 return Err(bad_request("cannot upload a crate with a reserved name"));
             }
 
+            if let Some(reservation) = CrateNameReservation::find_active(conn, persist.name)? {
+                if reservation.user_id != user.id {
+                    return Err(bad_request(format_args!(
+                        "crate name `{}` has been reserved by another user",
+                        persist.name
+                    )));
+                }
+            }
+
             // To avoid race conditions, we try to insert
             // first so we know whether to add an owner
-            let krate = match persist.create(conn, user.id).optional()? {
-                Some(krate) => krate,
+            let krate = match persist.create(conn, user.id, &app.audit_log).optional()? {
+                Some(krate) => {
+                    // The reservation is not needed anymore once the crate exists
+                    CrateNameReservation::delete(conn, &krate.name)?;
+                    krate
+                }
                 None => persist.update(conn)?,
             };
 
@@ -330,9 +426,6 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
                 }
             }
 
-            // Read tarball from request
-            let hex_cksum: String = Sha256::digest(&tarball_bytes).encode_hex();
-
             // Persist the new version of this crate
             let version = NewVersion::new(
                 krate.id,
@@ -343,28 +436,46 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
                 // to get here, and max upload sizes are way less than i32 max
                 content_length as i32,
                 user.id,
-                hex_cksum,
+                tarball.hex_cksum().to_string(),
                 package.links,
                 rust_version,
             )?
             .save(conn, &verified_email_address)?;
 
-            insert_version_owner_action(
+            app.audit_log.record(
                 conn,
-                version.id,
-                user.id,
-                api_token_id,
-                VersionAction::Publish,
+                AuditEvent::VersionAction {
+                    version_id: version.id,
+                    user_id: user.id,
+                    api_token_id,
+                    action: VersionAction::Publish,
+                    ip: req.extensions.get::<RealIp>().map(|ip| **ip),
+                    reason: None,
+                },
             )?;
 
+            // Remember how the version was published, so that consumers can
+            // distinguish human publishes from CI publishes
+            let user_agent = req.headers.get(header::USER_AGENT);
+            NewVersionProvenance {
+                version_id: version.id,
+                api_token_id,
+                api_token_name: auth.api_token().map(|token| token.name.as_str()),
+                ci_service: ci_service.map(|ci_service| ci_service.to_string()),
+                user_agent: user_agent.and_then(|value| value.to_str().ok()),
+                ip: req.extensions.get::<RealIp>().map(|ip| (**ip).into()),
+            }
+            .insert(conn)?;
+
             // Link this new version to all dependencies
             add_dependencies(conn, &deps, version.id)?;
 
             // Update all keywords for this crate
             Keyword::update_crate(conn, &krate, &keywords)?;
 
-            // Update all categories for this crate, collecting any invalid categories
-            // in order to be able to warn about them
+            // Update all categories for this crate. Unknown categories were
+            // already rejected above, but the list of invalid categories is
+            // still returned for compatibility with older clients.
             let ignored_invalid_categories = Category::update_crate(conn, &krate, &categories)?;
 
             let top_versions = krate.top_versions(conn)?;
@@ -375,8 +486,10 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
 
             let pkg_path_in_vcs = tarball_info.vcs_info.map(|info| info.path_in_vcs);
 
-            if let Some(readme) = metadata.readme {
-                if !readme.is_empty() {
+            let readme_job = metadata
+                .readme
+                .filter(|readme| !readme.is_empty())
+                .map(|readme| {
                     jobs::RenderAndUploadReadme::new(
                         version.id,
                         readme,
@@ -386,37 +499,73 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
                         repository,
                         pkg_path_in_vcs,
                     )
-                    .enqueue(conn)?;
+                });
+
+            let mut other_warnings = vec![];
+            match metadata.release_at {
+                Some(release_at) => {
+                    // The crate file is kept in a private location and the
+                    // README is only rendered on release, so that nothing of
+                    // the version is published early. The index does not
+                    // change until the release either.
+                    let upload_id = hex::encode(rand::random::<[u8; 16]>());
+                    NewScheduledRelease {
+                        version_id: version.id,
+                        crate_name: &krate.name,
+                        version_num: &version_string,
+                        release_at,
+                        scheduled_by: user.id,
+                        upload_id: &upload_id,
+                        readme_job: readme_job.map(serde_json::to_value).transpose()?,
+                    }
+                    .insert(conn)?;
+
+                    Handle::current()
+                        .block_on(tarball.upload_scheduled(&app.storage, &upload_id, &krate.name, &version_string))
+                        .map_err(|e| internal(format!("failed to upload crate: {e}")))?;
+
+                    let release_at = DateTime::<Utc>::from_naive_utc_and_offset(release_at, Utc);
+                    other_warnings.push(format!(
+                        "version {version_string} is hidden until its scheduled release at {}",
+                        release_at.to_rfc3339()
+                    ));
                 }
-            }
+                None => {
+                    if let Some(readme_job) = readme_job {
+                        readme_job.enqueue(conn)?;
+                    }
 
-            // Upload crate tarball
-            Handle::current()
-                .block_on(app.storage.upload_crate_file(
-                    &krate.name,
-                    &version_string,
-                    tarball_bytes,
-                ))
-                .map_err(|e| internal(format!("failed to upload crate: {e}")))?;
+                    // Upload crate tarball
+                    Handle::current()
+                        .block_on(tarball.upload(&app.storage, &krate.name, &version_string))
+                        .map_err(|e| internal(format!("failed to upload crate: {e}")))?;
+
+                    jobs::enqueue_sync_to_index(&krate.name, conn)?;
+                }
+            }
 
-            jobs::enqueue_sync_to_index(&krate.name, conn)?;
+            app.crate_id_cache.invalidate(&krate.name);
+            app.version_id_cache.invalidate(&krate.name, &version_string);
 
             // Experiment: check new crates for potential typosquatting.
+            // Scheduled releases notify the owners when they are released.
             if existing_crate.is_none() {
                 CheckTyposquat::new(&krate.name).enqueue(conn)?;
+            } else if metadata.release_at.is_none() {
+                SendPublishNotifications::new(version.id).enqueue(conn)?;
             }
 
             // The `other` field on `PublishWarnings` was introduced to handle a temporary warning
-            // that is no longer needed. As such, crates.io currently does not return any `other`
-            // warnings at this time, but if we need to, the field is available.
+            // that is no longer needed. It is now used to confirm scheduled releases, since older
+            // clients display these warnings without having to know about them.
             let warnings = PublishWarnings {
                 invalid_categories: ignored_invalid_categories,
                 invalid_badges: vec![],
-                other: vec![],
+                other: other_warnings,
             };
 
             Ok(Json(GoodCrate {
-                krate: EncodableCrate::from_minimal(krate, Some(&top_versions), None, false, downloads, None),
+                krate: encodable_crate_minimal(krate, Some(&top_versions), None, false, downloads, None),
                 warnings,
             }))
         })
@@ -424,6 +573,34 @@ pub async fn publish(app: AppState, req: BytesRequest) -> AppResult<Json<GoodCra
     .await?
 }
 
+/// Validates the scheduled release time of a publish request.
+///
+/// Only new versions of existing crates can be scheduled, since a new crate
+/// would be visible without any versions until the release.
+fn validate_release_at(
+    release_at: NaiveDateTime,
+    now: NaiveDateTime,
+    existing_crate: bool,
+) -> AppResult<()> {
+    if !existing_crate {
+        return Err(bad_request(
+            "scheduled releases are only supported for new versions of existing crates",
+        ));
+    }
+
+    if release_at <= now {
+        return Err(bad_request("`release_at` must be in the future"));
+    }
+
+    if release_at > now + TimeDelta::days(MAX_RELEASE_DELAY_DAYS) {
+        return Err(bad_request(format!(
+            "`release_at` must be at most {MAX_RELEASE_DELAY_DAYS} days in the future"
+        )));
+    }
+
+    Ok(())
+}
+
 /// Counts the number of versions for `crate_id` that were published within
 /// the last 24 hours.
 fn count_versions_published_today(crate_id: i32, conn: &mut PgConnection) -> QueryResult<i64> {
@@ -436,51 +613,69 @@ fn count_versions_published_today(crate_id: i32, conn: &mut PgConnection) -> Que
         .get_result(conn)
 }
 
-#[instrument(skip_all)]
-fn split_body(mut bytes: Bytes) -> AppResult<(Bytes, Bytes)> {
-    // The format of the req.body() of a publish request is as follows:
-    //
-    // metadata length
-    // metadata in JSON about the crate being published
-    // .crate tarball length
-    // .crate tarball file
-
-    if bytes.len() < 4 {
-        // Avoid panic in `get_u32_le()` if there is not enough remaining data
-        return Err(bad_request("invalid metadata length"));
-    }
+pub(crate) fn is_reserved_name(name: &str, conn: &mut PgConnection) -> QueryResult<bool> {
+    select(exists(reserved_crate_names::table.filter(
+        canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name)),
+    )))
+    .get_result(conn)
+}
 
-    let json_len = bytes.get_u32_le() as usize;
-    if json_len > bytes.len() {
-        return Err(bad_request(format!(
-            "invalid metadata length for remaining payload: {json_len}"
+/// Rejects new crates whose name is already used in the upstream registry,
+/// since publishing them would shadow the upstream crate for all users of
+/// this registry.
+///
+/// Like crates.io, names that only differ by `-` and `_` are considered to be
+/// the same name. The sparse index can't be searched by the canonical name
+/// though, so only the name itself and the variants with all `-` replaced by
+/// `_` and vice versa are looked up (the index paths are case-insensitive).
+/// An upstream crate whose name mixes `-` and `_` differently, e.g.
+/// `foo_bar-baz` for `foo-bar_baz`, is not detected, since checking every
+/// combination would take exponentially many requests.
+fn check_upstream_conflict(
+    upstream: &(dyn UpstreamRegistry + Send + Sync),
+    name: &str,
+) -> AppResult<()> {
+    let conflict = Handle::current().block_on(find_upstream_conflict(upstream, name))?;
+    if let Some(candidate) = conflict {
+        return Err(bad_request(format_args!(
+            "crate name `{name}` conflicts with the crate `{candidate}` of the upstream \
+             registry. Crates of the upstream registry are served through this registry, \
+             so a crate with the same name can't be published here."
         )));
     }
 
-    let json_bytes = bytes.split_to(json_len);
+    Ok(())
+}
 
-    if bytes.len() < 4 {
-        // Avoid panic in `get_u32_le()` if there is not enough remaining data
-        return Err(bad_request("invalid tarball length"));
-    }
+/// Returns the name of the crate in the upstream registry that conflicts
+/// with `name`, if there is one.
+pub(crate) async fn find_upstream_conflict(
+    upstream: &(dyn UpstreamRegistry + Send + Sync),
+    name: &str,
+) -> AppResult<Option<String>> {
+    let mut candidates = vec![
+        name.to_string(),
+        name.replace('-', "_"),
+        name.replace('_', "-"),
+    ];
+    candidates.sort();
+    candidates.dedup();
+
+    for candidate in candidates {
+        let index_file = upstream.index_file(&candidate).await.map_err(|error| {
+            warn!(%name, "Failed to check the upstream registry for conflicts: {error}");
+            custom(
+                StatusCode::BAD_GATEWAY,
+                "failed to check the upstream registry for conflicting crate names",
+            )
+        })?;
 
-    let tarball_len = bytes.get_u32_le() as usize;
-    if tarball_len > bytes.len() {
-        return Err(bad_request(format!(
-            "invalid tarball length for remaining payload: {tarball_len}"
-        )));
+        if index_file.is_some() {
+            return Ok(Some(candidate));
+        }
     }
 
-    let tarball_bytes = bytes.split_to(tarball_len);
-
-    Ok((json_bytes, tarball_bytes))
-}
-
-fn is_reserved_name(name: &str, conn: &mut PgConnection) -> QueryResult<bool> {
-    select(exists(reserved_crate_names::table.filter(
-        canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name)),
-    )))
-    .get_result(conn)
+    Ok(None)
 }
 
 fn validate_url(url: Option<&str>, field: &str) -> AppResult<()> {
@@ -0,0 +1,123 @@
+//! One-time nonces that protect publish requests against replays.
+//!
+//! In untrusted CI environments, publish requests might be captured by
+//! network middleboxes and sent again later. To prevent this, a client can
+//! request a nonce for a crate version with `POST /api/v1/publish_nonce` right
+//! before publishing, and include it in the `Publish-Nonce` header of the
+//! publish request. The nonce is consumed by the publish and expires quickly
+//! if it isn't used. With `PUBLISH_NONCE_REQUIRED`, publish requests without
+//! a nonce are rejected.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, PublishNonce};
+use crate::util::errors::forbidden;
+use crate::util::rfc3339;
+use chrono::NaiveDateTime;
+use http::HeaderName;
+use secrecy::ExposeSecret;
+
+/// The header of publish requests that contains the nonce.
+pub static PUBLISH_NONCE: HeaderName = HeaderName::from_static("publish-nonce");
+
+#[derive(Deserialize)]
+pub struct CreateNonceRequest {
+    #[serde(rename = "crate")]
+    krate: String,
+    version: String,
+}
+
+#[derive(Serialize)]
+pub struct CreateNonceResponse {
+    nonce: String,
+    #[serde(with = "rfc3339")]
+    expires_at: NaiveDateTime,
+}
+
+/// Handles the `POST /publish_nonce` route.
+///
+/// The request is authenticated like the publish request itself, so that a
+/// token that can't publish the crate can't request nonces for it either.
+pub async fn create(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<CreateNonceRequest>,
+) -> AppResult<Json<CreateNonceResponse>> {
+    Crate::validate_crate_name("crate", &body.krate).map_err(bad_request)?;
+
+    let version = semver::Version::parse(&body.version).map_err(|_| {
+        bad_request(format_args!(
+            "\"{}\" is an invalid semver version",
+            body.version
+        ))
+    })?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let existing_crate = Crate::by_name(&body.krate)
+            .first::<Crate>(conn)
+            .optional()?;
+
+        let endpoint_scope = match existing_crate {
+            Some(_) => EndpointScope::PublishUpdate,
+            None => EndpointScope::PublishNew,
+        };
+
+        let auth = AuthCheck::default()
+            .with_endpoint_scope(endpoint_scope)
+            .for_crate(&body.krate)
+            .check(&req, conn)?;
+
+        let lifetime = app.config.publish_nonces.lifetime;
+        let nonce = PublishNonce::create(
+            conn,
+            auth.user_id(),
+            &body.krate,
+            &version.to_string(),
+            lifetime,
+        )?;
+
+        Ok(Json(CreateNonceResponse {
+            nonce: nonce.nonce.expose_secret().clone(),
+            expires_at: nonce.expires_at,
+        }))
+    })
+    .await?
+}
+
+/// Returns the nonce of the publish request, or an error if the request
+/// doesn't include one although nonces are required.
+pub fn nonce_from_request(req: &Parts, required: bool) -> AppResult<Option<String>> {
+    let nonce = req
+        .headers
+        .get(&PUBLISH_NONCE)
+        .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+
+    if nonce.is_none() && required {
+        return Err(bad_request(
+            "publishing requires a nonce from `POST /api/v1/publish_nonce` \
+             in the `Publish-Nonce` header",
+        ));
+    }
+
+    Ok(nonce)
+}
+
+/// Consumes the nonce of a publish request for the given crate version.
+pub fn consume_nonce(
+    conn: &mut PgConnection,
+    nonce: &str,
+    user_id: i32,
+    crate_name: &str,
+    version: &str,
+) -> AppResult<()> {
+    if !PublishNonce::consume(conn, nonce, user_id, crate_name, version)? {
+        return Err(forbidden(
+            "the publish nonce is invalid, expired, already used, \
+             or was requested for a different crate version",
+        ));
+    }
+
+    Ok(())
+}
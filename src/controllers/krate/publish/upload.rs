@@ -0,0 +1,286 @@
+//! Extraction of the crate metadata and the `.crate` file from the body of a
+//! publish request.
+//!
+//! Two request body formats are supported:
+//!
+//! - The binary format used by `cargo publish` (see [`split_body()`]), which
+//!   requires the full request body to be buffered in memory.
+//! - A `multipart/form-data` body with a `metadata` part containing the JSON
+//!   metadata and a `tarball` part containing the `.crate` file. The tarball
+//!   is streamed into a temporary file and its checksum is calculated on the
+//!   fly, so it never has to be buffered in memory as a whole.
+
+use crate::storage::Storage;
+use crate::util::errors::{bad_request, custom, AppResult, BoxedAppError};
+use crate::util::BytesRequest;
+use axum::body::{Body, Bytes};
+use axum::extract::{FromRequest, Request};
+use axum::response::{IntoResponse, Response};
+use axum::{async_trait, RequestExt};
+use hex::ToHex;
+use http::header::CONTENT_TYPE;
+use http::request::Parts;
+use http::{HeaderMap, StatusCode};
+use http_body_util::LengthLimitError;
+use hyper::body::Buf;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Seek};
+use tokio::io::AsyncWriteExt;
+
+const METADATA_FIELD: &str = "metadata";
+const TARBALL_FIELD: &str = "tarball";
+
+/// The body of a publish request, split into the JSON metadata and the
+/// `.crate` file.
+pub struct PublishRequest {
+    pub parts: Parts,
+    pub metadata: Bytes,
+    pub tarball: Tarball,
+}
+
+#[async_trait]
+impl<S> FromRequest<S> for PublishRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(boundary) = multipart_boundary(req.headers()) {
+            let req = req.with_limited_body();
+            let (parts, body) = req.into_parts();
+
+            let (metadata, tarball) = read_multipart(body, boundary)
+                .await
+                .map_err(IntoResponse::into_response)?;
+
+            return Ok(Self {
+                parts,
+                metadata,
+                tarball,
+            });
+        }
+
+        let (parts, bytes) = BytesRequest::from_request(req, state).await?.0.into_parts();
+        let (metadata, tarball) = split_body(bytes).map_err(IntoResponse::into_response)?;
+
+        Ok(Self {
+            parts,
+            metadata,
+            tarball: Tarball::from_bytes(tarball),
+        })
+    }
+}
+
+/// The `.crate` file of a publish request, together with its size and
+/// SHA256 checksum.
+pub struct Tarball {
+    content: TarballContent,
+    size: u64,
+    hex_cksum: String,
+}
+
+enum TarballContent {
+    Bytes(Bytes),
+    File(File),
+}
+
+impl Tarball {
+    fn from_bytes(bytes: Bytes) -> Self {
+        Self {
+            size: bytes.len() as u64,
+            hex_cksum: Sha256::digest(&bytes).encode_hex(),
+            content: TarballContent::Bytes(bytes),
+        }
+    }
+
+    /// Streams the content of a multipart field into a temporary file,
+    /// calculating the checksum of the content on the way.
+    async fn from_field(mut field: multer::Field<'_>) -> AppResult<Self> {
+        let mut file = tokio::fs::File::from_std(tempfile::tempfile()?);
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+
+        while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+            file.write_all(&chunk).await?;
+        }
+
+        file.flush().await?;
+
+        Ok(Self {
+            content: TarballContent::File(file.into_std().await),
+            size,
+            hex_cksum: hasher.finalize().encode_hex(),
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn hex_cksum(&self) -> &str {
+        &self.hex_cksum
+    }
+
+    /// Returns a reader for the content of the tarball, starting at the
+    /// beginning of the file.
+    pub fn reader(&mut self) -> std::io::Result<Box<dyn Read + '_>> {
+        match &mut self.content {
+            TarballContent::Bytes(bytes) => Ok(Box::new(&bytes[..])),
+            TarballContent::File(file) => {
+                file.rewind()?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+
+    pub async fn upload(self, storage: &Storage, name: &str, version: &str) -> anyhow::Result<()> {
+        match self.content {
+            TarballContent::Bytes(bytes) => {
+                storage.upload_crate_file(name, version, bytes).await?;
+                Ok(())
+            }
+            TarballContent::File(mut file) => {
+                file.rewind()?;
+                let mut file = tokio::fs::File::from_std(file);
+                storage
+                    .upload_crate_file_stream(name, version, &mut file)
+                    .await
+            }
+        }
+    }
+
+    /// Uploads the tarball of a version whose release is scheduled to its
+    /// private location (see [`Storage::upload_scheduled_crate_file()`]).
+    pub async fn upload_scheduled(
+        self,
+        storage: &Storage,
+        upload_id: &str,
+        name: &str,
+        version: &str,
+    ) -> anyhow::Result<()> {
+        match self.content {
+            TarballContent::Bytes(bytes) => {
+                storage
+                    .upload_scheduled_crate_file(upload_id, name, version, bytes)
+                    .await?;
+                Ok(())
+            }
+            TarballContent::File(mut file) => {
+                file.rewind()?;
+                let mut file = tokio::fs::File::from_std(file);
+                storage
+                    .upload_scheduled_crate_file_stream(upload_id, name, version, &mut file)
+                    .await
+            }
+        }
+    }
+}
+
+/// Returns the multipart boundary if the request has a `multipart/form-data`
+/// content type.
+fn multipart_boundary(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    multer::parse_boundary(content_type).ok()
+}
+
+async fn read_multipart(body: Body, boundary: String) -> AppResult<(Bytes, Tarball)> {
+    let mut multipart = multer::Multipart::new(body.into_data_stream(), boundary);
+
+    let mut metadata = None;
+    let mut tarball = None;
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        let name = field.name().unwrap_or_default().to_string();
+        match name.as_str() {
+            METADATA_FIELD if metadata.is_none() => {
+                metadata = Some(field.bytes().await.map_err(multipart_error)?);
+            }
+            TARBALL_FIELD if tarball.is_none() => {
+                tarball = Some(Tarball::from_field(field).await?);
+            }
+            METADATA_FIELD | TARBALL_FIELD => {
+                return Err(bad_request(format!(
+                    "duplicate `{name}` field in multipart upload"
+                )));
+            }
+            _ => {
+                return Err(bad_request(format!(
+                    "unexpected `{name}` field in multipart upload"
+                )));
+            }
+        }
+    }
+
+    let metadata = metadata.ok_or_else(|| {
+        bad_request(format!(
+            "missing `{METADATA_FIELD}` field in multipart upload"
+        ))
+    })?;
+
+    let tarball = tarball.ok_or_else(|| {
+        bad_request(format!(
+            "missing `{TARBALL_FIELD}` field in multipart upload"
+        ))
+    })?;
+
+    Ok((metadata, tarball))
+}
+
+fn multipart_error(error: multer::Error) -> BoxedAppError {
+    if let multer::Error::StreamReadFailed(error) = &error {
+        let is_length_limit_error = error
+            .downcast_ref::<axum::Error>()
+            .and_then(|error| error.source())
+            .is_some_and(|error| error.is::<LengthLimitError>());
+
+        if is_length_limit_error {
+            return custom(StatusCode::PAYLOAD_TOO_LARGE, "request body is too large");
+        }
+    }
+
+    bad_request(format!("invalid multipart upload: {error}"))
+}
+
+#[instrument(skip_all)]
+fn split_body(mut bytes: Bytes) -> AppResult<(Bytes, Bytes)> {
+    // The format of the req.body() of a publish request is as follows:
+    //
+    // metadata length
+    // metadata in JSON about the crate being published
+    // .crate tarball length
+    // .crate tarball file
+
+    if bytes.len() < 4 {
+        // Avoid panic in `get_u32_le()` if there is not enough remaining data
+        return Err(bad_request("invalid metadata length"));
+    }
+
+    let json_len = bytes.get_u32_le() as usize;
+    if json_len > bytes.len() {
+        return Err(bad_request(format!(
+            "invalid metadata length for remaining payload: {json_len}"
+        )));
+    }
+
+    let json_bytes = bytes.split_to(json_len);
+
+    if bytes.len() < 4 {
+        // Avoid panic in `get_u32_le()` if there is not enough remaining data
+        return Err(bad_request("invalid tarball length"));
+    }
+
+    let tarball_len = bytes.get_u32_le() as usize;
+    if tarball_len > bytes.len() {
+        return Err(bad_request(format!(
+            "invalid tarball length for remaining payload: {tarball_len}"
+        )));
+    }
+
+    let tarball_bytes = bytes.split_to(tarball_len);
+
+    Ok((json_bytes, tarball_bytes))
+}
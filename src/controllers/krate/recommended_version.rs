@@ -0,0 +1,113 @@
+//! Endpoints for managing the recommended version of a crate
+//!
+//! Crates that maintain multiple release lines can recommend a version that
+//! is not the highest one, e.g. the latest release of a long-term support
+//! line. The recommended version is included in the crate detail and search
+//! responses, and marked as `recommended` in the index.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, RecommendedVersion, Rights};
+use crate::util::errors::{crate_not_found, custom};
+use crate::worker::jobs;
+use tokio::runtime::Handle;
+
+#[derive(Deserialize)]
+pub struct UpdateRecommendedVersionRequest {
+    version: String,
+}
+
+/// Handles the `PUT /crates/:crate_id/recommended_version` route.
+///
+/// Replaces the recommended version of the crate. Yanked versions can't be
+/// recommended. Only users that may publish the crate may change its
+/// recommended version.
+pub async fn update(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<UpdateRecommendedVersionRequest>,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let (krate, user_id) = authorize(&app, &req, conn, &crate_name)?;
+        let version = krate.find_version(conn, &body.version)?;
+
+        if version.yanked {
+            return Err(bad_request(format_args!(
+                "version `{}` is yanked and can't be recommended",
+                version.num
+            )));
+        }
+
+        conn.transaction::<_, BoxedAppError, _>(|conn| {
+            RecommendedVersion::set(conn, krate.id, version.id, user_id)?;
+            jobs::enqueue_sync_to_index(&krate.name, conn)?;
+            Ok(())
+        })?;
+
+        Ok(Json(json!({ "recommended_version": version.num })))
+    })
+    .await?
+}
+
+/// Handles the `DELETE /crates/:crate_id/recommended_version` route.
+pub async fn delete(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let (krate, _) = authorize(&app, &req, conn, &crate_name)?;
+
+        let removed = conn.transaction::<_, BoxedAppError, _>(|conn| {
+            let removed = RecommendedVersion::remove(conn, krate.id)?;
+            if removed {
+                jobs::enqueue_sync_to_index(&krate.name, conn)?;
+            }
+            Ok(removed)
+        })?;
+
+        if !removed {
+            return Err(custom(
+                StatusCode::NOT_FOUND,
+                format!("crate `{crate_name}` has no recommended version"),
+            ));
+        }
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Checks that the authenticated user may publish the crate, and returns the
+/// crate and the ID of the user.
+fn authorize(
+    app: &AppState,
+    req: &Parts,
+    conn: &mut PgConnection,
+    crate_name: &str,
+) -> AppResult<(Crate, i32)> {
+    let auth = AuthCheck::default()
+        .with_endpoint_scope(EndpointScope::PublishUpdate)
+        .for_crate(crate_name)
+        .check(req, conn)?;
+
+    let krate: Crate = Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))?;
+
+    let owners = krate.owners(conn)?;
+    let rights = Handle::current().block_on(auth.user().rights(app, &owners))?;
+    if rights < Rights::Publish {
+        return Err(custom(
+            StatusCode::FORBIDDEN,
+            "only owners have permission to change the recommended version of a crate",
+        ));
+    }
+
+    Ok((krate, auth.user_id()))
+}
@@ -0,0 +1,104 @@
+//! Endpoint for reserving the name of a crate that has not been published yet
+
+use crate::auth::AuthCheck;
+use diesel::dsl::{now, IntervalDsl};
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::krate::publish::is_reserved_name;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, CrateNameReservation};
+use crate::rate_limiter::LimitedAction;
+use crate::schema::crate_name_reservations;
+use crate::util::errors::custom;
+use crate::views::EncodableCrateNameReservation;
+
+/// The number of days until a reservation expires.
+const RESERVATION_DAYS: i32 = 30;
+
+/// The maximum number of reservations a user can have at the same time.
+const MAX_ACTIVE_RESERVATIONS: i64 = 3;
+
+/// Handles the `POST /crates/:crate_id/reserve` route.
+///
+/// Reserves the crate name for the authenticated user for
+/// [`RESERVATION_DAYS`], which blocks other users from publishing a crate
+/// with this name. Expired reservations are deleted by the
+/// [`ExpireCrateNameReservations`](crate::worker::jobs::ExpireCrateNameReservations)
+/// background job.
+pub async fn reserve(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    Crate::validate_crate_name("crate", &crate_name).map_err(bad_request)?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        // Reserving a name is only useful for publishing a new crate later,
+        // so tokens need the same scopes as for publishing a new crate.
+        let auth = AuthCheck::default()
+            .with_endpoint_scope(EndpointScope::PublishNew)
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let user = auth.user();
+        if user.verified_email(conn)?.is_none() {
+            return Err(bad_request(format!(
+                "A verified email address is required to reserve crate names. \
+                 Visit https://{}/settings/profile to set and verify your email address.",
+                app.config.domain_name,
+            )));
+        }
+
+        conn.transaction(|conn| {
+            let existing_crate: Option<Crate> =
+                Crate::by_name(&crate_name).first(conn).optional()?;
+
+            if existing_crate.is_some() || is_reserved_name(&crate_name, conn)? {
+                return Err(bad_request(format!(
+                    "crate name `{crate_name}` is not available"
+                )));
+            }
+
+            if let Some(reservation) = CrateNameReservation::find_active(conn, &crate_name)? {
+                let detail = if reservation.user_id == user.id {
+                    format!("you have already reserved the crate name `{crate_name}`")
+                } else {
+                    format!("crate name `{crate_name}` is already reserved")
+                };
+                return Err(custom(StatusCode::CONFLICT, detail));
+            }
+
+            if CrateNameReservation::count_active(conn, user.id)? >= MAX_ACTIVE_RESERVATIONS {
+                return Err(bad_request(format!(
+                    "you can not have more than {MAX_ACTIVE_RESERVATIONS} \
+                     active crate name reservations"
+                )));
+            }
+
+            app.rate_limiter.check_rate_limit(
+                user.id,
+                LimitedAction::ReserveName,
+                app.clock.naive_now(),
+                conn,
+            )?;
+
+            // Any remaining reservation for this name has already expired
+            CrateNameReservation::delete(conn, &crate_name)?;
+
+            let reservation: CrateNameReservation =
+                diesel::insert_into(crate_name_reservations::table)
+                    .values((
+                        crate_name_reservations::name.eq(&crate_name),
+                        crate_name_reservations::user_id.eq(user.id),
+                        crate_name_reservations::expires_at.eq(now + RESERVATION_DAYS.days()),
+                    ))
+                    .returning(CrateNameReservation::as_returning())
+                    .get_result(conn)?;
+
+            let reservation = EncodableCrateNameReservation::from(reservation);
+            Ok(Json(json!({ "reservation": reservation })))
+        })
+    })
+    .await?
+}
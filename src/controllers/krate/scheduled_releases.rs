@@ -0,0 +1,159 @@
+//! Endpoints for managing the scheduled releases of a crate
+//!
+//! Versions that are published with a `release_at` time stay hidden until
+//! the [`ReleaseScheduledVersions`](jobs::ReleaseScheduledVersions) job
+//! releases them. Until then, the owners of the crate can release them early
+//! or cancel the release, which deletes the version.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{Crate, Rights, ScheduledRelease, ScheduledReleaseResolution, User};
+use crate::schema::versions;
+use crate::util::errors::{crate_not_found, custom};
+use crate::worker::jobs;
+use crates_io_worker::BackgroundJob;
+use tokio::runtime::Handle;
+
+/// Handles the `GET /crates/:crate_id/scheduled_releases` route.
+///
+/// Returns the pending releases of the crate, in the order of their release.
+pub async fn list(
+    app: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let (krate, _) = authorize_owner(&app, &req, conn, &crate_name)?;
+        let scheduled_releases = ScheduledRelease::pending_for_crate(conn, &krate.name)?;
+        Ok(Json(json!({ "scheduled_releases": scheduled_releases })))
+    })
+    .await?
+}
+
+/// Handles the `PUT /crates/:crate_id/:version/scheduled_release/release`
+/// route.
+///
+/// Releases the version by the next run of the release job, which is
+/// enqueued right away.
+pub async fn release(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let (krate, _) = authorize_owner(&app, &req, conn, &crate_name)?;
+        let scheduled_release = find_pending(conn, &krate, &version)?;
+
+        let scheduled_release = conn.transaction(|conn| {
+            let scheduled_release = scheduled_release.release_now(conn)?;
+            jobs::ReleaseScheduledVersions.enqueue(conn)?;
+            Ok::<_, BoxedAppError>(scheduled_release)
+        })?;
+
+        Ok(Json(json!({ "scheduled_release": scheduled_release })))
+    })
+    .await?
+}
+
+/// Handles the `DELETE /crates/:crate_id/:version/scheduled_release` route.
+///
+/// Cancels the release and deletes the version, so that the version number
+/// can be published again. The schedule is kept as the audit trail of the
+/// cancellation.
+pub async fn cancel(
+    app: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    let scheduled_release = conn
+        .interact({
+            let app = app.clone();
+            move |conn| {
+                let (krate, user) = authorize_owner(&app, &req, conn, &crate_name)?;
+                let scheduled_release = find_pending(conn, &krate, &version)?;
+
+                let scheduled_release = conn.transaction(|conn| {
+                    // The release job might have released the version in the
+                    // meantime
+                    let scheduled_release = scheduled_release.lock(conn)?;
+                    let Some(version_id) = scheduled_release.version_id else {
+                        return Err(not_pending(&krate.name, &version));
+                    };
+                    if scheduled_release.outcome().is_some() {
+                        return Err(not_pending(&krate.name, &version));
+                    }
+
+                    let scheduled_release = scheduled_release.resolve(
+                        conn,
+                        ScheduledReleaseResolution::Cancelled,
+                        Some(user.id),
+                    )?;
+                    diesel::delete(versions::table.find(version_id)).execute(conn)?;
+                    Ok::<_, BoxedAppError>(scheduled_release)
+                })?;
+
+                app.version_id_cache.invalidate(&krate.name, &version);
+
+                Ok::<_, BoxedAppError>(scheduled_release)
+            }
+        })
+        .await??;
+
+    if let Err(error) = app
+        .storage
+        .delete_scheduled_crate_file(&scheduled_release.upload_id)
+        .await
+    {
+        let crate_name = &scheduled_release.crate_name;
+        let version = &scheduled_release.version_num;
+        warn!(%crate_name, %version, ?error, "Failed to delete scheduled crate file");
+    }
+
+    Ok(Json(json!({ "scheduled_release": scheduled_release })))
+}
+
+/// Checks that the authenticated user is an owner of the crate with the
+/// rights to publish new versions.
+fn authorize_owner(
+    app: &AppState,
+    req: &Parts,
+    conn: &mut PgConnection,
+    crate_name: &str,
+) -> AppResult<(Crate, User)> {
+    let auth = AuthCheck::default()
+        .with_endpoint_scope(EndpointScope::PublishUpdate)
+        .for_crate(crate_name)
+        .check(req, conn)?;
+
+    let krate: Crate = Crate::by_name(crate_name)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| crate_not_found(crate_name))?;
+
+    let user = auth.user();
+    let owners = krate.owners(conn)?;
+    if Handle::current().block_on(user.rights(app, &owners))? < Rights::Publish {
+        let detail = "must already be an owner to manage scheduled releases";
+        return Err(custom(StatusCode::FORBIDDEN, detail));
+    }
+
+    Ok((krate, user.clone()))
+}
+
+fn find_pending(
+    conn: &mut PgConnection,
+    krate: &Crate,
+    version: &str,
+) -> AppResult<ScheduledRelease> {
+    ScheduledRelease::pending(conn, &krate.name, version)?
+        .ok_or_else(|| not_pending(&krate.name, version))
+}
+
+fn not_pending(crate_name: &str, version: &str) -> BoxedAppError {
+    let detail = format!("version `{version}` of crate `{crate_name}` has no pending release");
+    custom(StatusCode::NOT_FOUND, detail)
+}
@@ -1,28 +1,49 @@
 //! Endpoint for searching and discovery functionality
 
 use crate::auth::AuthCheck;
+use bigdecimal::BigDecimal;
 use diesel::dsl::*;
-use diesel::sql_types::{Array, Bool, Text};
+use diesel::sql_types::{Array, Bool, Double, Numeric, Text};
 use diesel_full_text_search::*;
 use once_cell::sync::OnceCell;
 
+use crate::config::RankingWeights;
 use crate::controllers::cargo_prelude::*;
+use crate::controllers::helpers::yanked::{self, yanked_filter};
 use crate::controllers::helpers::Paginate;
-use crate::models::{Crate, CrateOwner, CrateVersions, OwnerKind, TopVersions, Version};
+use crate::models::{
+    Crate, CrateOwner, CrateVersions, DescriptionTranslation, OwnerKind, RecommendedVersion,
+    TopVersions, Version,
+};
 use crate::schema::*;
+use crate::util::accept_language::AcceptLanguage;
 use crate::util::errors::bad_request;
-use crate::views::EncodableCrate;
+use crate::views::encodable_crate_minimal;
 
-use crate::controllers::helpers::pagination::{Page, Paginated, PaginationOptions};
+use crate::controllers::helpers::pagination::{Page, Paginated, PaginationMeta, PaginationOptions};
+use crate::middleware::log_request::RequestLogExt;
 use crate::models::krate::ALL_COLUMNS;
 use crate::sql::{array_agg, canon_crate_name, lower};
 
+mod scoring;
+
+use self::scoring::Score;
+
 /// Handles the `GET /crates` route.
 /// Returns a list of crates. Called in a variety of scenarios in the
 /// front end, including:
 /// - Alphabetical listing of crates
 /// - List of crates under a specific owner
 /// - Listing a user's followed crates
+/// - Listing crates compatible with a specific Rust toolchain (`?msrv=1.70`)
+/// - Listing crates that depend on a crate, optionally in a version range
+///   (`?depends_on=serde&req=^1`)
+///
+/// Search results are sorted by their relevance score by default (see the
+/// `scoring` module). The `experiment` query parameter selects an alternative
+/// weighting of the score that is configured in
+/// [`SearchRankingConfig`](crate::config::SearchRankingConfig), and its
+/// exposure is logged so that the ranking changes can be evaluated.
 ///
 /// Notes:
 /// The different use cases this function covers is handled through passing
@@ -41,24 +62,34 @@ use crate::sql::{array_agg, canon_crate_name, lower};
 pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
     let conn = app.db_read().await?;
     conn.interact(move |conn| {
-        use diesel::sql_types::Float;
         use seek::*;
 
         let params = req.query();
         let option_param = |s| params.get(s).map(|v| v.as_str());
         let sort = option_param("sort");
-        let include_yanked = option_param("include_yanked")
-            .map(|s| s == "yes")
-            .unwrap_or(true);
+        // Crates whose versions were all yanked are listed unless they are
+        // excluded explicitly, but the yanked versions are only considered
+        // for the `max_version` of the crates if they are included explicitly.
+        let include_yanked = yanked::include_yanked(&req)?;
 
         // Remove 0x00 characters from the query string because Postgres can not
         // handle them and will return an error, which would cause us to throw
         // an Internal Server Error ourselves.
         let q_string = option_param("q").map(|q| q.replace('\u{0}', ""));
 
+        let msrv = option_param("msrv").map(parse_msrv).transpose()?;
+        let depends_on = option_param("depends_on")
+            .map(|name| DependsOn::load(conn, name, option_param("req")))
+            .transpose()?;
+
+        let ranking = &app.config.search_ranking;
+        let experiment = option_param("experiment");
+        let experiment_weights = experiment.and_then(|name| ranking.experiment(name));
+        let ranking_weights = *experiment_weights.unwrap_or(&ranking.weights);
+
         let filter_params = FilterParams {
             q_string: q_string.as_deref(),
-            include_yanked,
+            include_yanked: include_yanked.unwrap_or(true),
             category: option_param("category"),
             all_keywords: option_param("all_keywords"),
             keyword: option_param("keyword"),
@@ -67,6 +98,9 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             team_id: option_param("team_id").and_then(|s| s.parse::<i32>().ok()),
             following: option_param("following").is_some(),
             has_ids: option_param("ids[]").is_some(),
+            msrv,
+            depends_on,
+            ranking_weights,
             ..Default::default()
         };
 
@@ -75,10 +109,11 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             false.into_sql::<Bool>(),
             crate_downloads::downloads,
             recent_crate_downloads::downloads.nullable(),
-            0_f32.into_sql::<Float>(),
+            0_f64.into_sql::<Double>(),
         );
 
         let mut seek: Option<Seek> = None;
+        let mut ranked = false;
         let mut query = filter_params
             .make_query(&req, conn)?
             .inner_join(crate_downloads::table)
@@ -92,26 +127,24 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
                 query = query.order(Crate::with_name(q_string).desc());
 
                 if sort == "relevance" {
-                    let q = sql::<TsQuery>("plainto_tsquery('english', ")
-                        .bind::<Text, _>(q_string)
-                        .sql(")");
-                    let rank = ts_rank_cd(crates::textsearchable_index_col, q);
+                    let score = Score::new(q_string, ranking_weights);
                     query = query.select((
                         ALL_COLUMNS,
                         Crate::with_name(q_string),
                         crate_downloads::downloads,
                         recent_crate_downloads::downloads.nullable(),
-                        rank.clone(),
+                        score,
                     ));
                     seek = Some(Seek::Relevance);
-                    query = query.then_order_by(rank.desc())
+                    ranked = true;
+                    query = query.order(score.desc())
                 } else {
                     query = query.select((
                         ALL_COLUMNS,
                         Crate::with_name(q_string),
                         crate_downloads::downloads,
                         recent_crate_downloads::downloads.nullable(),
-                        0_f32.into_sql::<Float>(),
+                        0_f64.into_sql::<Double>(),
                     ));
                     seek = Some(Seek::Query);
                 }
@@ -155,7 +188,7 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
         // To avoid breaking existing users, seek-based pagination is only used if an explicit page has
         // not been provided. This way clients relying on meta.next_page will use the faster seek-based
         // paginations, while client hardcoding pages handling will use the slower offset-based code.
-        let (total, next_page, prev_page, data, conn) = if !explicit_page && seek.is_some() {
+        let (meta, data, conn) = if !explicit_page && seek.is_some() {
             let seek = seek.unwrap();
             if let Some(condition) = seek
                 .after(&pagination.page)?
@@ -168,41 +201,64 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             // published. Unfortunately on PostgreSQL counting the rows in a table requires scanning
             // the table, and the `total` field is part of the stable registries API.
             //
-            // If this becomes a problem in the future the crates count could be denormalized, at least
-            // for the filterless happy path.
+            // Clients that only follow `meta.next_page` can skip the count with `?meta=minimal`.
             let query = query.pages_pagination_with_count_query(
                 pagination,
                 filter_params.make_query(&req, conn)?.count(),
             );
-            let data: Paginated<(Crate, bool, i64, Option<i64>, f32)> =
+            let data: Paginated<(Crate, bool, i64, Option<i64>, f64)> =
                 info_span!("db.query", message = "SELECT ..., COUNT(*) FROM crates")
                     .in_scope(|| query.load(conn))?;
 
-            (
-                data.total(),
-                data.next_seek_params(|last| seek.to_payload(last))?
+            let meta = PaginationMeta {
+                total: data.total(),
+                next_page: data
+                    .next_seek_params(|last| seek.to_payload(last))?
                     .map(|p| req.query_with_params(p)),
-                None,
-                data.into_iter().collect::<Vec<_>>(),
-                conn,
-            )
+                prev_page: None,
+            };
+
+            (meta, data.into_iter().collect::<Vec<_>>(), conn)
         } else {
             let query = query.pages_pagination_with_count_query(
                 pagination,
                 filter_params.make_query(&req, conn)?.count(),
             );
-            let data: Paginated<(Crate, bool, i64, Option<i64>, f32)> =
+            let data: Paginated<(Crate, bool, i64, Option<i64>, f64)> =
                 info_span!("db.query", message = "SELECT ..., COUNT(*) FROM crates")
                     .in_scope(|| query.load(conn))?;
-            (
-                data.total(),
-                data.next_page_params().map(|p| req.query_with_params(p)),
-                data.prev_page_params().map(|p| req.query_with_params(p)),
-                data.into_iter().collect::<Vec<_>>(),
-                conn,
-            )
+            let meta = PaginationMeta {
+                total: data.total(),
+                next_page: data.next_page_params().map(|p| req.query_with_params(p)),
+                prev_page: data.prev_page_params().map(|p| req.query_with_params(p)),
+            };
+
+            (meta, data.into_iter().collect::<Vec<_>>(), conn)
         };
 
+        if let Some(experiment) = experiment.filter(|_| ranked) {
+            let variant = match experiment_weights {
+                Some(_) => experiment,
+                None => "default",
+            };
+
+            req.request_log().add("search_experiment", experiment);
+            req.request_log().add("search_variant", variant);
+
+            let results = data
+                .iter()
+                .map(|(krate, ..)| &krate.name)
+                .collect::<Vec<_>>();
+            info!(
+                target: "search_experiment",
+                experiment,
+                variant,
+                query = q_string.as_deref().unwrap_or_default(),
+                ?results,
+                "Search experiment exposure"
+            );
+        }
+
         let perfect_matches = data.iter().map(|&(_, b, _, _, _)| b).collect::<Vec<_>>();
         let downloads = data
             .iter()
@@ -213,8 +269,16 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             .map(|(c, _, _, _, _)| c)
             .collect::<Vec<_>>();
 
-        let versions: Vec<Version> = info_span!("db.query", message = "SELECT ... FROM versions")
-            .in_scope(|| crates.versions().load(conn))?;
+        let accept_language = AcceptLanguage::from_headers(&req.headers);
+        let crate_ids = crates.iter().map(|krate| krate.id).collect::<Vec<_>>();
+        let mut translations =
+            DescriptionTranslation::best_matches(conn, &crate_ids, &accept_language)?;
+        let mut recommended_versions = RecommendedVersion::for_crates(conn, &crate_ids)?;
+
+        let version_filter = yanked_filter(include_yanked == Some(true));
+        let versions: Vec<Version> =
+            info_span!("db.query", message = "SELECT ... FROM versions")
+                .in_scope(|| crates.all_versions().filter(version_filter).load(conn))?;
         let versions = versions
             .grouped_by(&crates)
             .into_iter()
@@ -225,24 +289,28 @@ pub async fn search(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             .zip(perfect_matches)
             .zip(downloads)
             .map(|(((max_version, krate), perfect_match), (total, recent))| {
-                EncodableCrate::from_minimal(
+                let translated_description = translations.remove(&krate.id);
+                let recommended_version = recommended_versions.remove(&krate.id);
+
+                let mut krate = encodable_crate_minimal(
                     krate,
                     Some(&max_version),
                     Some(vec![]),
                     perfect_match,
                     total,
                     Some(recent),
-                )
+                );
+                if let Some(description) = translated_description {
+                    krate.description = Some(description);
+                }
+                krate.recommended_version = recommended_version;
+                krate
             })
             .collect::<Vec<_>>();
 
         Ok(Json(json!({
             "crates": crates,
-            "meta": {
-                "total": total,
-                "next_page": next_page,
-                "prev_page": prev_page,
-            },
+            "meta": meta,
         })))
     })
     .await?
@@ -260,6 +328,9 @@ struct FilterParams<'a> {
     team_id: Option<i32>,
     following: bool,
     has_ids: bool,
+    msrv: Option<Vec<BigDecimal>>,
+    depends_on: Option<DependsOn>,
+    ranking_weights: RankingWeights,
     _auth_user_id: OnceCell<i32>,
     _ids: OnceCell<Option<Vec<String>>>,
 }
@@ -394,10 +465,48 @@ impl<'a> FilterParams<'a> {
             query = query.filter(exists(
                 versions::table
                     .filter(versions::crate_id.eq(crates::id))
-                    .filter(versions::yanked.eq(false)),
+                    .filter(versions::yanked.eq(false))
+                    .filter(Version::is_released()),
             ));
         }
 
+        if let Some(msrv) = &self.msrv {
+            // Only crates with at least one non-yanked version that declares
+            // a compatible `rust-version` are included. `rust-version` values
+            // may omit the minor and patch parts, so they are padded with
+            // zeros before being compared.
+            let rust_version = sql::<Array<Numeric>>(
+                "(string_to_array(versions.rust_version || '.0.0', '.')::numeric[])[1:3]",
+            );
+
+            query = query.filter(exists(
+                versions::table
+                    .filter(versions::crate_id.eq(crates::id))
+                    .filter(versions::yanked.eq(false))
+                    .filter(Version::is_released())
+                    .filter(versions::rust_version.is_not_null())
+                    .filter(rust_version.le(msrv.clone().into_sql::<Array<Numeric>>())),
+            ));
+        }
+
+        if let Some(depends_on) = &self.depends_on {
+            // Like the `msrv` filter, any non-yanked version that declares
+            // the dependency includes the crate.
+            let mut dependents = dependencies::table
+                .inner_join(versions::table)
+                .filter(dependencies::crate_id.eq(depends_on.crate_id))
+                .filter(versions::yanked.eq(false))
+                .filter(Version::is_released())
+                .select(versions::crate_id)
+                .into_boxed();
+
+            if let Some(reqs) = &depends_on.reqs {
+                dependents = dependents.filter(dependencies::req.eq_any(reqs));
+            }
+
+            query = query.filter(crates::id.eq_any(dependents));
+        }
+
         Ok(query)
     }
 
@@ -510,30 +619,21 @@ impl<'a> FilterParams<'a> {
                 ]
             }
             SeekPayload::Relevance(Relevance {
-                exact_match: exact,
-                rank: rank_in,
+                score: score_in,
                 id,
             }) => {
                 // Equivalent of:
-                // `WHERE (exact_match = exact_match' AND rank = rank' AND name > name')
-                //      OR (exact_match = exact_match' AND rank < rank')
-                //      OR exact_match < exact_match'`
+                // `WHERE (score = score' AND name > name') OR score < score'`
                 let q_string = self.q_string.expect("q_string should not be None");
-                let q = sql::<TsQuery>("plainto_tsquery('english', ")
-                    .bind::<Text, _>(q_string)
-                    .sql(")");
-                let rank = ts_rank_cd(crates::textsearchable_index_col, q);
-                let name_exact_match = Crate::with_name(q_string);
+                let score = Score::new(q_string, self.ranking_weights);
                 vec![
                     Box::new(
-                        name_exact_match
-                            .eq(exact)
-                            .and(rank.clone().eq(rank_in))
+                        score
+                            .eq(score_in)
                             .and(crates::name.nullable().gt(crate_name_by_id(id)))
                             .nullable(),
                     ),
-                    Box::new(name_exact_match.eq(exact).and(rank.lt(rank_in)).nullable()),
-                    Box::new(name_exact_match.lt(exact).nullable()),
+                    Box::new(score.lt(score_in).nullable()),
                 ]
             }
         };
@@ -553,6 +653,100 @@ impl<'a> FilterParams<'a> {
     }
 }
 
+/// Parses the value of the `msrv` query parameter (e.g. `1.70` or `1.70.1`)
+/// into its `[major, minor, patch]` components, with missing components
+/// defaulting to zero.
+fn parse_msrv(value: &str) -> AppResult<Vec<BigDecimal>> {
+    let invalid = || bad_request(format!("invalid `msrv` value: `{value}`"));
+
+    let parts = value.split('.').collect::<Vec<_>>();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+
+    let mut components = parts
+        .into_iter()
+        .map(|part| match part.parse::<u64>() {
+            Ok(number) if part.chars().all(|c| c.is_ascii_digit()) => Ok(number.into()),
+            _ => Err(invalid()),
+        })
+        .collect::<AppResult<Vec<BigDecimal>>>()?;
+
+    components.resize(3, BigDecimal::from(0));
+    Ok(components)
+}
+
+/// The crate of the `depends_on` query parameter, and the version
+/// requirements of its dependents that match the `req` query parameter.
+struct DependsOn {
+    crate_id: i32,
+    reqs: Option<Vec<String>>,
+}
+
+impl DependsOn {
+    fn load(conn: &mut PgConnection, name: &str, req: Option<&str>) -> AppResult<Self> {
+        let crate_id = Crate::by_name(name)
+            .select(crates::id)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| bad_request(format!("crate `{name}` does not exist")))?;
+
+        let Some(req) = req else {
+            return Ok(Self {
+                crate_id,
+                reqs: None,
+            });
+        };
+
+        let req = semver::VersionReq::parse(req)
+            .map_err(|_| bad_request(format!("invalid `req` value: `{req}`")))?;
+
+        // There are far fewer distinct requirements than dependents, so the
+        // requirements are matched here instead of in the database.
+        let reqs = dependencies::table
+            .filter(dependencies::crate_id.eq(crate_id))
+            .select(dependencies::req)
+            .distinct()
+            .load::<String>(conn)?
+            .into_iter()
+            .filter(|dependency_req| {
+                minimum_version(dependency_req).is_some_and(|version| req.matches(&version))
+            })
+            .collect();
+
+        Ok(Self {
+            crate_id,
+            reqs: Some(reqs),
+        })
+    }
+}
+
+/// Returns the lowest version that is allowed by the version requirement of
+/// a dependency, e.g. `1.2.0` for `^1.2` or `>=1.2, <2`.
+fn minimum_version(req: &str) -> Option<semver::Version> {
+    use semver::{Op, Version};
+
+    let req = semver::VersionReq::parse(req).ok()?;
+
+    let lower_bounds = req.comparators.iter().filter_map(|comparator| {
+        let major = comparator.major;
+        let minor = comparator.minor.unwrap_or(0);
+        let patch = comparator.patch.unwrap_or(0);
+
+        match comparator.op {
+            Op::Greater => Some(match (comparator.minor, comparator.patch) {
+                (Some(minor), Some(patch)) => Version::new(major, minor, patch + 1),
+                (Some(minor), None) => Version::new(major, minor + 1, 0),
+                _ => Version::new(major + 1, 0, 0),
+            }),
+            Op::Less | Op::LessEq => None,
+            _ => Some(Version::new(major, minor, patch)),
+        }
+    });
+
+    Some(lower_bounds.max().unwrap_or(Version::new(0, 0, 0)))
+}
+
 mod seek {
     use crate::controllers::helpers::pagination::seek;
     use crate::models::Crate;
@@ -586,8 +780,7 @@ mod seek {
                 id: i32,
             },
             Relevance {
-                exact_match: bool,
-                rank: f32,
+                score: f64,
                 id: i32,
             },
         }
@@ -596,7 +789,7 @@ mod seek {
     impl Seek {
         pub(crate) fn to_payload(
             &self,
-            record: &(Crate, bool, i64, Option<i64>, f32),
+            record: &(Crate, bool, i64, Option<i64>, f64),
         ) -> SeekPayload {
             let (
                 Crate {
@@ -608,7 +801,7 @@ mod seek {
                 exact_match,
                 downloads,
                 recent_downloads,
-                rank,
+                score,
             ) = *record;
 
             match *self {
@@ -621,11 +814,7 @@ mod seek {
                 }),
                 Seek::Downloads => SeekPayload::Downloads(Downloads { downloads, id }),
                 Seek::Query => SeekPayload::Query(Query { exact_match, id }),
-                Seek::Relevance => SeekPayload::Relevance(Relevance {
-                    exact_match,
-                    rank,
-                    id,
-                }),
+                Seek::Relevance => SeekPayload::Relevance(Relevance { score, id }),
             }
         }
     }
@@ -0,0 +1,174 @@
+//! The relevance score of crates in the search results
+//!
+//! The score is the weighted sum of a set of named [`Factor`]s. The weights
+//! are configured in [`RankingWeights`], which allows tuning the ranking (and
+//! evaluating alternative weightings) without changing the SQL.
+
+use crate::config::RankingWeights;
+use diesel::expression::{AppearsOnTable, Expression, SelectableExpression, ValidGrouping};
+use diesel::pg::Pg;
+use diesel::query_builder::{AstPass, QueryFragment, QueryId};
+use diesel::sql_types::{Double, Text};
+use diesel::QueryResult;
+
+/// A named input of the relevance score.
+#[derive(Debug, Clone, Copy)]
+enum Factor {
+    /// `1` if the crate name matches the query, ignoring case and the
+    /// difference between `-` and `_`, and `0` otherwise.
+    ExactMatch,
+    /// The full-text search rank of the crate for the query.
+    Text,
+    /// The decimal logarithm of the total downloads of the crate.
+    Downloads,
+    /// The time of the last update of the crate, in years since the Unix
+    /// epoch. Only the differences between crates affect the ranking, and
+    /// unlike an age relative to `now()` the value is stable across requests,
+    /// which is required for seek-based pagination.
+    Recency,
+}
+
+impl Factor {
+    const ALL: [Factor; 4] = [
+        Factor::ExactMatch,
+        Factor::Text,
+        Factor::Downloads,
+        Factor::Recency,
+    ];
+
+    fn weight(self, weights: &RankingWeights) -> &f64 {
+        match self {
+            Factor::ExactMatch => &weights.exact_match,
+            Factor::Text => &weights.text,
+            Factor::Downloads => &weights.downloads,
+            Factor::Recency => &weights.recency,
+        }
+    }
+
+    fn walk_ast<'b>(self, query: &'b str, out: &mut AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        match self {
+            Factor::ExactMatch => {
+                out.push_sql("(canon_crate_name(crates.name) = canon_crate_name(");
+                out.push_bind_param::<Text, _>(query)?;
+                out.push_sql("))::int");
+            }
+            Factor::Text => {
+                out.push_sql("ts_rank_cd(crates.textsearchable_index_col, ");
+                out.push_sql("plainto_tsquery('english', ");
+                out.push_bind_param::<Text, _>(query)?;
+                out.push_sql("))");
+            }
+            Factor::Downloads => {
+                out.push_sql("log(crate_downloads.downloads::float8 + 1)");
+            }
+            Factor::Recency => {
+                out.push_sql("extract(epoch from crates.updated_at)::float8 / 31557600");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The relevance score of a crate for a search query.
+///
+/// The expression refers to the `crates` and `crate_downloads` tables, so it
+/// can only be used in queries that join both of them.
+#[derive(Debug, Clone, Copy)]
+pub struct Score<'a> {
+    query: &'a str,
+    weights: RankingWeights,
+}
+
+impl<'a> Score<'a> {
+    pub fn new(query: &'a str, weights: RankingWeights) -> Self {
+        Self { query, weights }
+    }
+}
+
+impl Expression for Score<'_> {
+    type SqlType = Double;
+}
+
+impl<QS> AppearsOnTable<QS> for Score<'_> {}
+
+impl<QS> SelectableExpression<QS> for Score<'_> {}
+
+impl ValidGrouping<()> for Score<'_> {
+    type IsAggregate = diesel::expression::is_aggregate::Never;
+}
+
+impl QueryId for Score<'_> {
+    const HAS_STATIC_QUERY_ID: bool = false;
+    type QueryId = ();
+}
+
+impl QueryFragment<Pg> for Score<'_> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        // Factors without weight are skipped, so that they don't have to be
+        // computed at all.
+        let mut factors = Factor::ALL
+            .into_iter()
+            .filter(|factor| *factor.weight(&self.weights) != 0.)
+            .peekable();
+
+        if factors.peek().is_none() {
+            out.push_sql("0::float8");
+            return Ok(());
+        }
+
+        out.push_sql("(");
+        for (i, factor) in factors.enumerate() {
+            if i > 0 {
+                out.push_sql(" + ");
+            }
+
+            out.push_bind_param::<Double, _>(factor.weight(&self.weights))?;
+            out.push_sql(" * ");
+            factor.walk_ast(self.query, &mut out)?;
+        }
+        out.push_sql(")");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::debug_query;
+    use diesel::dsl::select;
+
+    fn sql(weights: RankingWeights) -> String {
+        let query = select(Score::new("foo", weights));
+        debug_query::<Pg, _>(&query).to_string()
+    }
+
+    #[test]
+    fn test_default_weights() {
+        insta::assert_snapshot!(sql(RankingWeights::default()), @r###"SELECT ($1 * (canon_crate_name(crates.name) = canon_crate_name($2))::int + $3 * ts_rank_cd(crates.textsearchable_index_col, plainto_tsquery('english', $4))) -- binds: [1000.0, "foo", 1.0, "foo"]"###);
+    }
+
+    #[test]
+    fn test_all_factors() {
+        let weights = RankingWeights {
+            downloads: 0.5,
+            recency: 0.1,
+            ..Default::default()
+        };
+
+        insta::assert_snapshot!(sql(weights), @r###"SELECT ($1 * (canon_crate_name(crates.name) = canon_crate_name($2))::int + $3 * ts_rank_cd(crates.textsearchable_index_col, plainto_tsquery('english', $4)) + $5 * log(crate_downloads.downloads::float8 + 1) + $6 * extract(epoch from crates.updated_at)::float8 / 31557600) -- binds: [1000.0, "foo", 1.0, "foo", 0.5, 0.1]"###);
+    }
+
+    #[test]
+    fn test_without_weights() {
+        let weights = RankingWeights {
+            exact_match: 0.,
+            text: 0.,
+            downloads: 0.,
+            recency: 0.,
+        };
+
+        insta::assert_snapshot!(sql(weights), @"SELECT 0::float8 -- binds: []");
+    }
+}
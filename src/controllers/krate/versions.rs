@@ -6,12 +6,14 @@ use diesel::connection::DefaultLoadingMode;
 use indexmap::IndexMap;
 
 use crate::controllers::frontend_prelude::*;
-use crate::controllers::helpers::pagination::{encode_seek, Page, PaginationOptions};
+use crate::controllers::helpers::pagination::{
+    encode_seek, Page, PaginationMeta, PaginationOptions,
+};
+use crate::controllers::helpers::yanked::{self, yanked_filter};
 
-use crate::models::{Crate, User, Version, VersionOwnerAction};
-use crate::schema::{crates, users, versions};
-use crate::util::errors::crate_not_found;
-use crate::views::EncodableVersion;
+use crate::models::{User, Version, VersionOwnerAction, VersionProvenance};
+use crate::schema::{users, versions};
+use crate::views::encodable_version;
 
 /// Handles the `GET /crates/:crate_id/versions` route.
 pub async fn versions(
@@ -21,11 +23,8 @@ pub async fn versions(
 ) -> AppResult<Json<Value>> {
     let conn = state.db_read().await?;
     conn.interact(move |conn| {
-        let crate_id: i32 = Crate::by_name(&crate_name)
-            .select(crates::id)
-            .first(conn)
-            .optional()?
-            .ok_or_else(|| crate_not_found(&crate_name))?;
+        let crate_id = state.crate_id_cache.get_or_load(conn, &crate_name)?;
+        let include_yanked = yanked::include_yanked(&req)?.unwrap_or(true);
 
         let mut pagination = None;
         let params = req.query();
@@ -42,8 +41,10 @@ pub async fn versions(
         // Sort by semver by default
         let versions_and_publishers = match params.get("sort").map(|s| s.to_lowercase()).as_deref()
         {
-            Some("date") => list_by_date(crate_id, pagination.as_ref(), &req, conn)?,
-            _ => list_by_semver(crate_id, pagination.as_ref(), &req, conn)?,
+            Some("date") => {
+                list_by_date(crate_id, include_yanked, pagination.as_ref(), &req, conn)?
+            }
+            _ => list_by_semver(crate_id, include_yanked, pagination.as_ref(), &req, conn)?,
         };
 
         let versions = versions_and_publishers
@@ -56,7 +57,8 @@ pub async fn versions(
             .data
             .into_iter()
             .zip(VersionOwnerAction::for_versions(conn, &versions)?)
-            .map(|((v, pb), aas)| EncodableVersion::from(v, &crate_name, pb, aas))
+            .zip(VersionProvenance::for_versions(conn, &versions)?)
+            .map(|(((v, pb), aas), p)| encodable_version(v, &crate_name, pb, aas, p))
             .collect::<Vec<_>>();
 
         Ok(Json(match pagination {
@@ -74,6 +76,7 @@ pub async fn versions(
 /// This function will panic if `option` is built with `enable_pages` set to true.
 fn list_by_date(
     crate_id: i32,
+    include_yanked: bool,
     options: Option<&PaginationOptions>,
     req: &Parts,
     conn: &mut PgConnection,
@@ -82,6 +85,8 @@ fn list_by_date(
 
     let mut query = versions::table
         .filter(versions::crate_id.eq(crate_id))
+        .filter(yanked_filter(include_yanked))
+        .filter(Version::is_released())
         .left_outer_join(users::table)
         .select((versions::all_columns, users::all_columns.nullable()))
         .into_boxed();
@@ -113,18 +118,28 @@ fn list_by_date(
 
     // Since the total count is retrieved through an additional query, to maintain consistency
     // with other pagination methods, we only make a count query while data is not empty.
-    let total = if !data.is_empty() {
-        versions::table
+    let include_total = options.map_or(true, |options| options.include_total);
+    let total = if !include_total {
+        None
+    } else if !data.is_empty() {
+        let total = versions::table
             .filter(versions::crate_id.eq(crate_id))
+            .filter(yanked_filter(include_yanked))
+            .filter(Version::is_released())
             .count()
-            .get_result(conn)?
+            .get_result(conn)?;
+        Some(total)
     } else {
-        0
+        Some(0)
     };
 
     Ok(PaginatedVersionsAndPublishers {
         data,
-        meta: ResponseMeta { total, next_page },
+        meta: PaginationMeta {
+            total,
+            next_page,
+            prev_page: None,
+        },
     })
 }
 
@@ -138,6 +153,7 @@ fn list_by_date(
 // Therefore, we need to perform both sorting and pagination manually on the server.
 fn list_by_semver(
     crate_id: i32,
+    include_yanked: bool,
     options: Option<&PaginationOptions>,
     req: &Parts,
     conn: &mut PgConnection,
@@ -155,6 +171,8 @@ fn list_by_semver(
         let mut sorted_versions = IndexMap::new();
         for result in versions::table
             .filter(versions::crate_id.eq(crate_id))
+            .filter(yanked_filter(include_yanked))
+            .filter(Version::is_released())
             .select((versions::id, versions::num))
             .load_iter::<(i32, String), DefaultLoadingMode>(conn)?
         {
@@ -202,6 +220,8 @@ fn list_by_semver(
     } else {
         let mut data: Vec<(Version, Option<User>)> = versions::table
             .filter(versions::crate_id.eq(crate_id))
+            .filter(yanked_filter(include_yanked))
+            .filter(Version::is_released())
             .left_outer_join(users::table)
             .select((versions::all_columns, users::all_columns.nullable()))
             .load(conn)?;
@@ -216,11 +236,15 @@ fn list_by_semver(
             .map(|p| req.query_with_params(p))
     };
 
+    // The versions are sorted on the app server, so the total is known anyway
+    let include_total = options.map_or(true, |options| options.include_total);
+
     Ok(PaginatedVersionsAndPublishers {
         data,
-        meta: ResponseMeta {
-            total: total as i64,
+        meta: PaginationMeta {
+            total: include_total.then_some(total as i64),
             next_page,
+            prev_page: None,
         },
     })
 }
@@ -283,11 +307,5 @@ where
 
 struct PaginatedVersionsAndPublishers {
     data: Vec<(Version, Option<User>)>,
-    meta: ResponseMeta,
-}
-
-#[derive(Serialize)]
-struct ResponseMeta {
-    total: i64,
-    next_page: Option<String>,
+    meta: PaginationMeta,
 }
@@ -1,30 +1,18 @@
+use crate::config::Server;
 use crate::controllers::frontend_prelude::*;
+use crate::tls::VerifiedClientCertificate;
 use crate::util::errors::{custom, forbidden, not_found};
+use chrono::Utc;
 use prometheus::TextEncoder;
 
 /// Handles the `GET /api/private/metrics/:kind` endpoint.
 pub async fn prometheus(app: AppState, Path(kind): Path<String>, req: Parts) -> AppResult<String> {
-    if let Some(expected_token) = &app.config.metrics_authorization_token {
-        let provided_token = req
-            .headers
-            .get(header::AUTHORIZATION)
-            .and_then(|value| value.to_str().ok())
-            .and_then(|value| value.strip_prefix("Bearer "));
-
-        if provided_token != Some(expected_token.as_str()) {
-            return Err(forbidden("invalid or missing authorization token"));
-        }
-    } else {
-        // To avoid accidentally leaking metrics if the environment variable is not set, prevent
-        // access to any metrics endpoint if the authorization token is not configured.
-        let detail = "Metrics are disabled on this crates.io instance";
-        return Err(custom(StatusCode::NOT_FOUND, detail));
-    }
+    authorize(&app.config, &req)?;
 
     let metrics = match kind.as_str() {
         "service" => {
             let conn = app.db_read().await?;
-            conn.interact(move |conn| app.service_metrics.gather(conn))
+            conn.interact(move |conn| app.service_metrics.gather(&app.config, conn))
                 .await??
         }
         "instance" => {
@@ -36,3 +24,40 @@ pub async fn prometheus(app: AppState, Path(kind): Path<String>, req: Parts) ->
 
     Ok(TextEncoder::new().encode_to_string(&metrics)?)
 }
+
+/// Checks that the request is authorized to access the private metrics
+/// endpoints, either with a verified client certificate or with one of the
+/// configured metrics authorization tokens.
+pub(crate) fn authorize(config: &Server, req: &Parts) -> AppResult<()> {
+    let verifies_client_certificates = config
+        .tls
+        .as_ref()
+        .is_some_and(|tls| tls.verifies_client_certificates());
+
+    if config.metrics_authorization_tokens.is_empty() && !verifies_client_certificates {
+        // To avoid accidentally leaking metrics if the environment variable is not set, prevent
+        // access to any metrics endpoint if no authorization method is configured.
+        let detail = "Metrics are disabled on this crates.io instance";
+        return Err(custom(StatusCode::NOT_FOUND, detail));
+    }
+
+    if req.extensions.get::<VerifiedClientCertificate>().is_none() {
+        let provided_token = req
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        let now = Utc::now();
+        let is_valid = provided_token.is_some_and(|provided_token| {
+            (config.metrics_authorization_tokens.iter())
+                .any(|token| token.matches(provided_token, now))
+        });
+
+        if !is_valid {
+            return Err(forbidden("invalid or missing authorization token"));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Image proxy for the images in rendered READMEs, see
+//! [`crate::util::image_proxy`].
+
+use crate::controllers::frontend_prelude::*;
+use crate::safe_fetch::SafeFetchError;
+use crate::util::errors::{custom, forbidden};
+use crate::util::image_proxy;
+use bytes::Bytes;
+use http::HeaderValue;
+
+/// The content types of the images that are served through the proxy.
+const IMAGE_CONTENT_TYPES: &[&str] = &[
+    "image/apng",
+    "image/avif",
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/svg+xml",
+    "image/webp",
+];
+
+/// Browsers and the CDN may cache the images for a day, since the images of
+/// badges and the like change from time to time.
+const CACHE_CONTROL: &str = "public,max-age=86400";
+
+/// Prevents scripts in SVG images from running if the image URL is opened
+/// directly instead of being embedded in a README.
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; style-src 'unsafe-inline'; sandbox";
+
+/// An image that was fetched by the proxy, and its content type.
+pub type ProxiedImage = (HeaderValue, Bytes);
+
+/// Handles the `GET /readme_images` route.
+///
+/// Fetches the image at the signed `url` query parameter and returns it, if
+/// it's an image. The images are cached, and they are fetched with the
+/// limits of the [`SafeFetchClient`](crate::safe_fetch::SafeFetchClient).
+pub async fn proxy(app: AppState, req: Parts) -> AppResult<Response> {
+    let query = req.query();
+    let (Some(url), Some(signature)) = (query.get("url"), query.get("sig")) else {
+        return Err(bad_request("missing `url` or `sig` query parameter"));
+    };
+
+    if !image_proxy::verify(app.session_key(), url, signature) {
+        return Err(forbidden("invalid image signature"));
+    }
+
+    let (content_type, body) = match app.readme_image_cache.get(url) {
+        Some(image) => image,
+        None => {
+            let image = fetch(&app, url).await?;
+            app.readme_image_cache.insert(url.clone(), image.clone());
+            image
+        }
+    };
+
+    let headers = [
+        (header::CONTENT_TYPE, content_type),
+        (
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(CACHE_CONTROL),
+        ),
+        (
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static(CONTENT_SECURITY_POLICY),
+        ),
+        (
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ),
+    ];
+
+    Ok((headers, body).into_response())
+}
+
+async fn fetch(app: &AppState, url: &str) -> AppResult<ProxiedImage> {
+    let response = match app.safe_fetch.get(url).await {
+        Ok(response) => response,
+        Err(SafeFetchError::ResponseTooLarge(max_size)) => {
+            let detail = format!("the image is larger than {max_size} bytes");
+            return Err(custom(StatusCode::BAD_GATEWAY, detail));
+        }
+        Err(error) => {
+            warn!(%url, "Failed to fetch README image: {error}");
+            let detail = "failed to fetch the image";
+            return Err(custom(StatusCode::BAD_GATEWAY, detail));
+        }
+    };
+
+    if !response.status.is_success() {
+        let detail = format!("the image server responded with {}", response.status);
+        return Err(custom(StatusCode::BAD_GATEWAY, detail));
+    }
+
+    let content_type = response
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .and_then(|value| {
+            IMAGE_CONTENT_TYPES
+                .iter()
+                .find(|allowed| **allowed == value)
+                .copied()
+        });
+
+    let Some(content_type) = content_type else {
+        return Err(custom(StatusCode::BAD_GATEWAY, "the URL is not an image"));
+    };
+
+    let content_type = HeaderValue::from_static(content_type);
+    Ok((content_type, response.body))
+}
@@ -0,0 +1,205 @@
+//! Endpoints for search engine crawlers
+//!
+//! The `/sitemap.xml` document is a sitemap index that points to the
+//! paginated sitemaps of all crates and to the sitemap of recently published
+//! versions. Generating these documents requires loading large parts of the
+//! `crates` table, so they are cached in the `sitemaps` table for
+//! [`CACHE_HOURS`].
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::Version;
+use crate::schema::{crates, sitemaps, versions};
+use crate::util::errors::not_found;
+use chrono::NaiveDateTime;
+use diesel::dsl::{now, IntervalDsl};
+use std::fmt::Write;
+
+/// The maximum number of URLs in a single sitemap, as defined by the
+/// sitemap protocol.
+const SITEMAP_PAGE_SIZE: i64 = 50_000;
+
+/// Versions published in this many days are included in the sitemap of
+/// recently published versions.
+const RECENT_VERSIONS_DAYS: i32 = 30;
+
+/// How many hours a generated sitemap is served from the cache before it is
+/// generated again.
+const CACHE_HOURS: i32 = 6;
+
+/// Handles the `GET /robots.txt` route.
+pub async fn robots_txt(state: AppState) -> impl IntoResponse {
+    let domain = &state.config.domain_name;
+    let body = format!(
+        "# http://www.robotstxt.org
+User-agent: *
+Disallow:
+
+Sitemap: https://{domain}/sitemap.xml
+"
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body)
+}
+
+/// Handles the `GET /sitemap.xml` route.
+pub async fn index(state: AppState, req: Parts) -> AppResult<Response> {
+    let domain = state.config.domain_name.clone();
+
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        let content = cached(conn, req.uri.path(), |conn| {
+            let num_crates: i64 = crates::table.count().get_result(conn)?;
+            let num_pages = (num_crates + SITEMAP_PAGE_SIZE - 1) / SITEMAP_PAGE_SIZE;
+
+            let mut xml = String::from(XML_HEADER);
+            xml.push_str(r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+            xml.push('\n');
+            for page in 1..=num_pages.max(1) {
+                let loc = format!("https://{domain}/sitemaps/crates/{page}");
+                push_entry(&mut xml, "sitemap", &loc, None);
+            }
+            let loc = format!("https://{domain}/sitemaps/versions");
+            push_entry(&mut xml, "sitemap", &loc, None);
+            xml.push_str("</sitemapindex>\n");
+
+            Ok(xml)
+        })?;
+
+        Ok(xml_response(content))
+    })
+    .await?
+}
+
+/// Handles the `GET /sitemaps/crates/:page` route.
+pub async fn crate_list(state: AppState, Path(page): Path<i64>, req: Parts) -> AppResult<Response> {
+    if page < 1 {
+        return Err(not_found());
+    }
+
+    let domain = state.config.domain_name.clone();
+
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        let content = cached(conn, req.uri.path(), |conn| {
+            let crates: Vec<(String, NaiveDateTime)> = crates::table
+                .select((crates::name, crates::updated_at))
+                .order(crates::id)
+                .offset((page - 1) * SITEMAP_PAGE_SIZE)
+                .limit(SITEMAP_PAGE_SIZE)
+                .load(conn)?;
+
+            if crates.is_empty() && page > 1 {
+                return Err(not_found());
+            }
+
+            let urls = crates
+                .iter()
+                .map(|(name, updated_at)| (format!("https://{domain}/crates/{name}"), *updated_at));
+
+            Ok(urlset(urls))
+        })?;
+
+        Ok(xml_response(content))
+    })
+    .await?
+}
+
+/// Handles the `GET /sitemaps/versions` route.
+pub async fn recent_versions(state: AppState, req: Parts) -> AppResult<Response> {
+    let domain = state.config.domain_name.clone();
+
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        let content = cached(conn, req.uri.path(), |conn| {
+            let versions: Vec<(String, String, NaiveDateTime)> = versions::table
+                .inner_join(crates::table)
+                .filter(versions::created_at.gt(now - RECENT_VERSIONS_DAYS.days()))
+                .filter(versions::yanked.eq(false))
+                .filter(Version::is_released())
+                .select((crates::name, versions::num, versions::created_at))
+                .order(versions::created_at.desc())
+                .limit(SITEMAP_PAGE_SIZE)
+                .load(conn)?;
+
+            let urls = versions.iter().map(|(name, num, created_at)| {
+                (format!("https://{domain}/crates/{name}/{num}"), *created_at)
+            });
+
+            Ok(urlset(urls))
+        })?;
+
+        Ok(xml_response(content))
+    })
+    .await?
+}
+
+/// Returns the cached document for `path`, or generates and caches a new
+/// one if there is no cached document or if it has expired.
+fn cached(
+    conn: &mut PgConnection,
+    path: &str,
+    generate: impl FnOnce(&mut PgConnection) -> AppResult<String>,
+) -> AppResult<String> {
+    let cached: Option<String> = sitemaps::table
+        .find(path)
+        .filter(sitemaps::generated_at.gt(now - CACHE_HOURS.hours()))
+        .select(sitemaps::content)
+        .first(conn)
+        .optional()?;
+
+    if let Some(content) = cached {
+        return Ok(content);
+    }
+
+    let content = generate(conn)?;
+
+    diesel::insert_into(sitemaps::table)
+        .values((sitemaps::path.eq(path), sitemaps::content.eq(&content)))
+        .on_conflict(sitemaps::path)
+        .do_update()
+        .set((
+            sitemaps::content.eq(&content),
+            sitemaps::generated_at.eq(now),
+        ))
+        .execute(conn)?;
+
+    Ok(content)
+}
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+/// Builds a `<urlset>` document from the given URLs and their last
+/// modification times.
+fn urlset(urls: impl Iterator<Item = (String, NaiveDateTime)>) -> String {
+    let mut xml = String::from(XML_HEADER);
+    xml.push_str(r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    xml.push('\n');
+    for (loc, lastmod) in urls {
+        push_entry(&mut xml, "url", &loc, Some(lastmod));
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// Crate names and version numbers can not contain any characters that would
+/// have to be escaped in XML, so the URLs are inserted as they are.
+fn push_entry(xml: &mut String, tag: &str, loc: &str, lastmod: Option<NaiveDateTime>) {
+    let _ = write!(xml, "  <{tag}><loc>{loc}</loc>");
+    if let Some(lastmod) = lastmod {
+        let _ = write!(
+            xml,
+            "<lastmod>{}</lastmod>",
+            lastmod.format("%Y-%m-%dT%H:%M:%SZ")
+        );
+    }
+    let _ = writeln!(xml, "</{tag}>");
+}
+
+fn xml_response(content: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        content,
+    )
+        .into_response()
+}
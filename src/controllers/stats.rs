@@ -0,0 +1,142 @@
+//! Endpoints for registry-wide statistics
+
+use super::helpers::pagination::PaginationOptions;
+use crate::controllers::frontend_prelude::*;
+
+use crate::schema::{site_stats, trending_stats};
+use crate::views::{EncodableSiteStatsDay, EncodableTrendingStat};
+use chrono::{NaiveDate, TimeDelta};
+use diesel::dsl::max;
+
+/// The number of days of the daily publishes and new users in the response
+/// of the `GET /stats/site` route.
+const SITE_STATS_DAYS: i64 = 90;
+
+type TrendingStat = (String, i64, i64, i32, i32);
+
+/// Handles the `GET /stats/trending` route.
+///
+/// Returns the keywords and categories whose downloads (or, with
+/// `sort=publishes`, publishes) grew the most in the last week compared to
+/// the week before, based on the latest snapshot of the `trending_stats`
+/// table. The `per_page` query parameter limits the number of keywords and
+/// categories each.
+pub async fn trending(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let options = PaginationOptions::builder().gather(&req)?;
+
+    let sort_by_publishes = match req.query().get("sort").map(String::as_str) {
+        None | Some("downloads") => false,
+        Some("publishes") => true,
+        Some(_) => {
+            return Err(bad_request(
+                "invalid sort, expected `downloads` or `publishes`",
+            ))
+        }
+    };
+
+    let conn = app.db_read().await?;
+    conn.interact(move |conn| {
+        let date: Option<NaiveDate> = trending_stats::table
+            .select(max(trending_stats::date))
+            .get_result(conn)?;
+
+        let Some(date) = date else {
+            return Ok(Json(
+                json!({ "date": null, "keywords": [], "categories": [] }),
+            ));
+        };
+
+        let mut top_movers = |kind: &str| -> QueryResult<Vec<EncodableTrendingStat>> {
+            let query = trending_stats::table
+                .filter(trending_stats::date.eq(date))
+                .filter(trending_stats::kind.eq(kind))
+                .select((
+                    trending_stats::name,
+                    trending_stats::downloads,
+                    trending_stats::previous_downloads,
+                    trending_stats::publishes,
+                    trending_stats::previous_publishes,
+                ))
+                .limit(options.per_page)
+                .into_boxed();
+
+            let query = if sort_by_publishes {
+                let growth = trending_stats::publishes - trending_stats::previous_publishes;
+                query
+                    .filter(growth.gt(0))
+                    .order((growth.desc(), trending_stats::name))
+            } else {
+                let growth = trending_stats::downloads - trending_stats::previous_downloads;
+                query
+                    .filter(growth.gt(0))
+                    .order((growth.desc(), trending_stats::name))
+            };
+
+            Ok(query
+                .load::<TrendingStat>(conn)?
+                .into_iter()
+                .map(EncodableTrendingStat::from)
+                .collect())
+        };
+
+        let keywords = top_movers("keyword")?;
+        let categories = top_movers("category")?;
+
+        Ok(Json(json!({
+            "date": date.to_string(),
+            "keywords": keywords,
+            "categories": categories,
+        })))
+    })
+    .await?
+}
+
+/// Handles the `GET /stats/site` route.
+///
+/// Returns the total number of crates, versions and downloads, and the
+/// publishes and new users per day for the last 90 days, based on the daily
+/// rollups in the `site_stats` table.
+pub async fn site(app: AppState) -> AppResult<Json<Value>> {
+    let conn = app.db_read().await?;
+    conn.interact(move |conn| {
+        let latest: Option<(NaiveDate, i64, i64, i64)> = site_stats::table
+            .select((
+                site_stats::date,
+                site_stats::crates,
+                site_stats::versions,
+                site_stats::downloads,
+            ))
+            .order(site_stats::date.desc())
+            .first(conn)
+            .optional()?;
+
+        let Some((date, crates, versions, downloads)) = latest else {
+            return Ok(Json(json!({ "date": null, "totals": null, "daily": [] })));
+        };
+
+        let cutoff = date - TimeDelta::days(SITE_STATS_DAYS);
+        let daily = site_stats::table
+            .filter(site_stats::date.gt(cutoff))
+            .select((
+                site_stats::date,
+                site_stats::publishes,
+                site_stats::new_users,
+            ))
+            .order(site_stats::date)
+            .load::<(NaiveDate, i32, i32)>(conn)?
+            .into_iter()
+            .map(EncodableSiteStatsDay::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "date": date.to_string(),
+            "totals": {
+                "crates": crates,
+                "versions": versions,
+                "downloads": downloads,
+            },
+            "daily": daily,
+        })))
+    })
+    .await?
+}
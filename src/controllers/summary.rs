@@ -2,7 +2,7 @@ use crate::app::AppState;
 use crate::controllers::cargo_prelude::AppResult;
 use crate::models::{Category, Crate, CrateVersions, Keyword, TopVersions, Version};
 use crate::schema::{crate_downloads, crates, keywords, metadata, recent_crate_downloads};
-use crate::views::{EncodableCategory, EncodableCrate, EncodableKeyword};
+use crate::views::{encodable_crate_minimal, EncodableCategory, EncodableCrate, EncodableKeyword};
 use axum::Json;
 use diesel::prelude::*;
 use serde_json::Value;
@@ -11,7 +11,8 @@ use serde_json::Value;
 pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
     let conn = state.db_read().await?;
     conn.interact(move |conn| {
-        let config = &state.config;
+        let settings = state.runtime_settings.get_or_load(conn)?;
+        let excluded_crate_names = &settings.excluded_crate_names;
 
         let num_crates: i64 = crates::table.count().get_result(conn)?;
         let num_downloads: i64 = metadata::table
@@ -37,7 +38,7 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
                 .zip(krates)
                 .zip(downloads)
                 .map(|((top_versions, krate), (total, recent))| {
-                    Ok(EncodableCrate::from_minimal(
+                    Ok(encodable_crate_minimal(
                         krate,
                         Some(&top_versions),
                         None,
@@ -75,9 +76,9 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
             .inner_join(crate_downloads::table)
             .left_join(recent_crate_downloads::table)
             .into_boxed();
-        if !config.excluded_crate_names.is_empty() {
+        if !excluded_crate_names.is_empty() {
             most_downloaded_query =
-                most_downloaded_query.filter(crates::name.ne_all(&config.excluded_crate_names));
+                most_downloaded_query.filter(crates::name.ne_all(excluded_crate_names));
         }
         let most_downloaded = most_downloaded_query
             .then_order_by(crate_downloads::downloads.desc())
@@ -89,9 +90,9 @@ pub async fn summary(state: AppState) -> AppResult<Json<Value>> {
             .inner_join(crate_downloads::table)
             .inner_join(recent_crate_downloads::table)
             .into_boxed();
-        if !config.excluded_crate_names.is_empty() {
-            most_recently_downloaded_query = most_recently_downloaded_query
-                .filter(crates::name.ne_all(&config.excluded_crate_names));
+        if !excluded_crate_names.is_empty() {
+            most_recently_downloaded_query =
+                most_recently_downloaded_query.filter(crates::name.ne_all(excluded_crate_names));
         }
         let most_recently_downloaded = most_recently_downloaded_query
             .then_order_by(recent_crate_downloads::downloads.desc())
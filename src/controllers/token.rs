@@ -1,10 +1,13 @@
 use super::frontend_prelude::*;
 
-use crate::models::ApiToken;
+use crate::email::{Locale, LocalizedEmail};
+use crate::middleware::real_ip::RealIp;
+use crate::models::{ApiToken, User};
 use crate::schema::api_tokens;
-use crate::util::rfc3339;
+use crate::util::{rfc3339, token_revocation};
 use crate::views::EncodableApiTokenWithToken;
 
+use crate::audit::AuditEvent;
 use crate::auth::AuthCheck;
 use crate::models::token::{CrateScope, EndpointScope};
 use axum::extract::Query;
@@ -35,7 +38,7 @@ pub async fn list(
     Query(params): Query<GetParams>,
     req: Parts,
 ) -> AppResult<Json<Value>> {
-    let conn = &mut *app.db_read_prefer_primary().await?;
+    let conn = app.db_read_prefer_primary().await?;
     conn.interact(move |conn| {
         let auth = AuthCheck::only_cookie().check(&req, conn)?;
         let user = auth.user();
@@ -58,7 +61,7 @@ pub async fn list(
 
 /// Handles the `PUT /me/tokens` route.
 pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
-    let conn = &mut *app.db_write().await?;
+    let conn = app.db_write().await?;
     conn.interact(move |conn| {
         /// The incoming serialization format for the `ApiToken` model.
         #[derive(Deserialize)]
@@ -101,6 +104,9 @@ pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
             )));
         }
 
+        let crate_scope_names = new.api_token.crate_scopes.clone().unwrap_or_default();
+        let endpoint_scope_names = new.api_token.endpoint_scopes.clone().unwrap_or_default();
+
         let crate_scopes = new
             .api_token
             .crate_scopes
@@ -133,6 +139,45 @@ pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
             endpoint_scopes,
             new.api_token.expired_at,
         )?;
+
+        let event = AuditEvent::TokenCreated {
+            api_token_id: api_token.model.id,
+            user_id: user.id,
+            name: name.clone(),
+            crate_scopes: crate_scope_names.clone(),
+            endpoint_scopes: endpoint_scope_names.clone(),
+            expired_at: api_token.model.expired_at,
+        };
+        app.audit_log.record(conn, event)?;
+
+        let email = TokenCreatedEmail {
+            domain: &app.config.domain_name,
+            user_name: &user.gh_login,
+            token_name: name,
+            crate_scopes: &crate_scope_names,
+            endpoint_scopes: &endpoint_scope_names,
+            ip: req
+                .extensions()
+                .get::<RealIp>()
+                .map(|ip| (**ip).to_string()),
+            user_agent: req
+                .headers()
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok()),
+            revoke_token: token_revocation::generate_token(
+                app.session_key(),
+                user.id,
+                api_token.model.id,
+            ),
+        };
+
+        if let Err(error) = send_creation_email(&app, user, conn, email) {
+            warn!(
+                token_id = %api_token.model.id, user_id = %user.id, ?error,
+                "Failed to send email notification about new API token",
+            );
+        }
+
         let api_token = EncodableApiTokenWithToken::from(api_token);
 
         Ok(Json(json!({ "api_token": api_token })))
@@ -142,7 +187,7 @@ pub async fn new(app: AppState, req: BytesRequest) -> AppResult<Json<Value>> {
 
 /// Handles the `DELETE /me/tokens/:id` route.
 pub async fn revoke(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult<Json<Value>> {
-    let conn = &mut *app.db_write().await?;
+    let conn = app.db_write().await?;
     conn.interact(move |conn| {
         let auth = AuthCheck::default().check(&req, conn)?;
         let user = auth.user();
@@ -155,9 +200,33 @@ pub async fn revoke(app: AppState, Path(id): Path<i32>, req: Parts) -> AppResult
     .await?
 }
 
+/// Handles the `PUT /tokens/revoke/:token` route.
+///
+/// The token is sent in the revoke link of the email about a new API token,
+/// so that the API token can be revoked with one click, without being logged
+/// in.
+pub async fn revoke_with_link(app: AppState, Path(token): Path<String>) -> AppResult<Response> {
+    let (user_id, api_token_id) = token_revocation::verify_token(app.session_key(), &token)
+        .ok_or_else(|| bad_request("invalid revocation token"))?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let api_token = api_tokens::table
+            .find(api_token_id)
+            .filter(api_tokens::user_id.eq(user_id));
+
+        diesel::update(api_token)
+            .set(api_tokens::revoked.eq(true))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
 /// Handles the `DELETE /tokens/current` route.
 pub async fn revoke_current(app: AppState, req: Parts) -> AppResult<Response> {
-    let conn = &mut *app.db_write().await?;
+    let conn = app.db_write().await?;
     conn.interact(move |conn| {
         let auth = AuthCheck::default().check(&req, conn)?;
         let api_token_id = auth
@@ -172,3 +241,47 @@ pub async fn revoke_current(app: AppState, req: Parts) -> AppResult<Response> {
     })
     .await?
 }
+
+fn send_creation_email(
+    app: &AppState,
+    user: &User,
+    conn: &mut PgConnection,
+    email: TokenCreatedEmail<'_>,
+) -> anyhow::Result<()> {
+    let Some(recipient) = user.verified_email(conn)? else {
+        return Ok(());
+    };
+
+    let locale = Locale::from_preference(&user.locale);
+    app.emails.send_localized(&recipient, locale, email)?;
+
+    Ok(())
+}
+
+struct TokenCreatedEmail<'a> {
+    domain: &'a str,
+    user_name: &'a str,
+    token_name: &'a str,
+    crate_scopes: &'a [String],
+    endpoint_scopes: &'a [String],
+    ip: Option<String>,
+    user_agent: Option<&'a str>,
+    revoke_token: String,
+}
+
+impl LocalizedEmail for TokenCreatedEmail<'_> {
+    const TEMPLATE: &'static str = "token_created";
+
+    fn context(&self) -> minijinja::Value {
+        minijinja::context! {
+            domain => self.domain,
+            user_name => self.user_name,
+            token_name => self.token_name,
+            crate_scopes => self.crate_scopes,
+            endpoint_scopes => self.endpoint_scopes,
+            ip => self.ip,
+            user_agent => self.user_agent,
+            revoke_token => self.revoke_token,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Endpoints of the upstream-proxy mode (see [`crate::upstream`])
+
+use crate::controllers::prelude::*;
+
+use crate::util::errors::{custom, internal, not_found};
+use bytes::Bytes;
+
+const CONTENT_TYPE_INDEX: &str = "text/plain";
+
+/// Handles the `GET /index/*path` route.
+///
+/// Serves the sparse index, with the index files of crates that were
+/// published to this registry taking precedence over the index files of the
+/// upstream registry. The `config.json` file points cargo to the API and
+/// download endpoints of this registry, so that crate files of the upstream
+/// registry are downloaded through this registry too.
+pub async fn index(app: AppState, Path(path): Path<String>) -> AppResult<Response> {
+    let Some(upstream) = app.upstream.as_ref() else {
+        return Err(not_found());
+    };
+
+    if path == "config.json" {
+        let domain = &app.config.domain_name;
+        return Ok(Json(json!({
+            "dl": format!("https://{domain}/api/v1/crates"),
+            "api": format!("https://{domain}"),
+        }))
+        .into_response());
+    }
+
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let is_valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_valid_name || crates_io_index::Repository::relative_index_file_for_url(name) != path {
+        return Err(not_found());
+    }
+
+    if let Some(content) = app.storage.read_index(name).await.map_err(internal)? {
+        return Ok(index_file_response(content));
+    }
+
+    match upstream.index_file(name).await {
+        Ok(Some(content)) => Ok(index_file_response(content)),
+        Ok(None) => Err(not_found()),
+        Err(error) => {
+            warn!(%name, "Failed to fetch index file from the upstream registry: {error}");
+            let detail = "failed to fetch the index file from the upstream registry";
+            Err(custom(StatusCode::BAD_GATEWAY, detail))
+        }
+    }
+}
+
+fn index_file_response(content: Bytes) -> Response {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE_INDEX)], content).into_response()
+}
@@ -1,3 +1,6 @@
+pub mod emails;
+pub mod invitation_settings;
 pub mod me;
 pub mod other;
 pub mod session;
+pub mod usage;
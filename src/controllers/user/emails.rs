@@ -0,0 +1,208 @@
+//! Endpoints for managing the email addresses of the authenticated user
+//!
+//! Users can add several email addresses to their account. Every address has
+//! to be verified through the link in the confirmation email, and only
+//! verified addresses can be chosen as the primary address, which receives
+//! all notifications.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::user::me::UserConfirmEmail;
+use crate::email::Locale;
+use crate::models::{Email, NewEmail, User};
+use crate::schema::emails;
+use crate::sql::lower;
+use crate::util::errors::not_found;
+use crate::views::EncodableEmail;
+use diesel::dsl::{exists, select, sql};
+
+/// The maximum number of email addresses of a user.
+const MAX_EMAILS: i64 = 5;
+
+/// Handles the `GET /me/emails` route.
+pub async fn list(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let emails = Email::for_user(conn, user_id)?
+            .into_iter()
+            .map(EncodableEmail::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "emails": emails })))
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct AddEmailRequest {
+    email: String,
+}
+
+/// Handles the `POST /me/emails` route.
+///
+/// Adds an email address to the account of the user and sends a
+/// confirmation email to it. The first address of a user becomes the
+/// primary address.
+pub async fn add(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<AddEmailRequest>,
+) -> AppResult<Json<Value>> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let address = body.email.trim();
+        if address.is_empty() {
+            return Err(bad_request("empty email rejected"));
+        }
+
+        conn.transaction::<_, BoxedAppError, _>(|conn| {
+            let existing: i64 = Email::belonging_to(user).count().get_result(conn)?;
+            if existing >= MAX_EMAILS {
+                let detail = format!("an account can have at most {MAX_EMAILS} email addresses");
+                return Err(bad_request(detail));
+            }
+
+            let duplicate =
+                Email::belonging_to(user).filter(lower(emails::email).eq(lower(address)));
+            if select(exists(duplicate)).get_result(conn)? {
+                return Err(bad_request("this email address was already added"));
+            }
+
+            let new_email = NewEmail {
+                user_id: user.id,
+                email: address,
+            };
+
+            let email: Email = diesel::insert_into(emails::table)
+                .values(&new_email)
+                .get_result(conn)?;
+
+            send_confirmation_email(&app, user, email).map(|email| Json(json!({ "email": email })))
+        })
+    })
+    .await?
+}
+
+/// Handles the `DELETE /me/emails/:email_id` route.
+///
+/// The primary address can't be removed. Another address has to be made the
+/// primary address first.
+pub async fn delete(app: AppState, Path(email_id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let email = find_email(conn, user_id, email_id)?;
+        if email.is_primary {
+            return Err(bad_request(
+                "the primary email address can't be removed, choose another primary address first",
+            ));
+        }
+
+        diesel::delete(&email).execute(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `PUT /me/emails/:email_id/primary` route.
+///
+/// Makes the email address the primary address of the user, which receives
+/// all notifications from now on.
+pub async fn set_primary(
+    app: AppState,
+    Path(email_id): Path<i32>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let email = find_email(conn, user_id, email_id)?;
+        if !email.verified {
+            return Err(bad_request(
+                "the email address must be verified before it can become the primary address",
+            ));
+        }
+
+        email.make_primary(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `PUT /me/emails/:email_id/resend` route.
+///
+/// Generates a new confirmation token for the email address and sends the
+/// confirmation email again.
+pub async fn resend(app: AppState, Path(email_id): Path<i32>, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let email = find_email(conn, user.id, email_id)?;
+        if email.verified {
+            return Err(bad_request("the email address is already verified"));
+        }
+
+        conn.transaction(|conn| {
+            let email: Email = diesel::update(&email)
+                .set(emails::token.eq(sql("DEFAULT")))
+                .get_result(conn)?;
+
+            send_confirmation_email(&app, user, email)
+        })?;
+
+        ok_true()
+    })
+    .await?
+}
+
+fn find_email(conn: &mut PgConnection, user_id: i32, email_id: i32) -> AppResult<Email> {
+    emails::table
+        .find(email_id)
+        .filter(emails::user_id.eq(user_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(not_found)
+}
+
+fn send_confirmation_email(
+    state: &AppState,
+    user: &User,
+    email: Email,
+) -> AppResult<EncodableEmail> {
+    let Email {
+        id,
+        email,
+        verified,
+        token,
+        is_primary,
+        ..
+    } = email;
+
+    let confirm_email = UserConfirmEmail {
+        user_name: &user.gh_login,
+        domain: &state.emails.domain,
+        token,
+    };
+
+    let locale = Locale::from_preference(&user.locale);
+    state.emails.send_localized(&email, locale, confirm_email)?;
+
+    Ok(EncodableEmail {
+        id,
+        email,
+        verified,
+        verification_email_sent: true,
+        primary: is_primary,
+    })
+}
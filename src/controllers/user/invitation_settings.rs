@@ -0,0 +1,110 @@
+//! Endpoints for managing which crate ownership invitations the authenticated
+//! user accepts
+//!
+//! Users can block the invitations of specific users, or reject all
+//! unsolicited invitations, i.e. invitations from users that they do not own
+//! a crate together with. Blocked invitations are rejected when they are
+//! created, so the user is never notified about them.
+
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{InvitationBlock, User};
+use crate::schema::users;
+use crate::views::EncodablePublicUser;
+
+/// Handles the `GET /me/invitation_settings` route.
+pub async fn show(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        let blocked_users = InvitationBlock::blocked_users(conn, user.id)?
+            .into_iter()
+            .map(EncodablePublicUser::from)
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "invitation_settings": {
+                "block_unsolicited": user.block_unsolicited_invitations,
+                "blocked_users": blocked_users,
+            }
+        })))
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct InvitationSettingsRequest {
+    block_unsolicited: bool,
+}
+
+/// Handles the `PUT /me/invitation_settings` route.
+pub async fn update(
+    app: AppState,
+    req: Parts,
+    Json(body): Json<InvitationSettingsRequest>,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        diesel::update(users::table.find(user_id))
+            .set(users::block_unsolicited_invitations.eq(body.block_unsolicited))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `PUT /me/invitation_settings/blocked_users/:login` route.
+///
+/// Pending invitations from the blocked user are removed.
+pub async fn block_user(
+    app: AppState,
+    Path(login): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let blocked_user = find_user(conn, &login)?;
+        if blocked_user.id == user_id {
+            return Err(bad_request("you can't block yourself"));
+        }
+
+        InvitationBlock::block(conn, user_id, blocked_user.id)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `DELETE /me/invitation_settings/blocked_users/:login` route.
+pub async fn unblock_user(
+    app: AppState,
+    Path(login): Path<String>,
+    req: Parts,
+) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let blocked_user = find_user(conn, &login)?;
+        if !InvitationBlock::unblock(conn, user_id, blocked_user.id)? {
+            let detail = format!("user `{login}` is not blocked");
+            return Err(bad_request(detail));
+        }
+
+        ok_true()
+    })
+    .await?
+}
+
+fn find_user(conn: &mut PgConnection, login: &str) -> AppResult<User> {
+    User::find_by_login(conn, login)
+        .optional()?
+        .ok_or_else(|| bad_request(format_args!("could not find user with login `{login}`")))
+}
@@ -1,17 +1,31 @@
 use crate::auth::AuthCheck;
+use chrono::Utc;
 use secrecy::{ExposeSecret, SecretString};
 use std::collections::HashMap;
 
 use crate::controllers::frontend_prelude::*;
 
 use crate::controllers::helpers::*;
+use crate::email::{Locale, LocalizedEmail};
 
-use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::pagination::{Paginated, PaginationMeta, PaginationOptions};
+use crate::models::krate::ALL_COLUMNS;
 use crate::models::{
-    CrateOwner, Email, Follow, NewEmail, OwnerKind, User, Version, VersionOwnerAction,
+    Crate, CrateOwner, CrateVersions, Email, Follow, NewEmail, OwnerKind, TokenAnomaly,
+    TopVersions, User, Version, VersionOwnerAction, VersionProvenance,
+};
+use crate::rate_limiter::LimitedAction;
+use crate::schema::{
+    crate_downloads, crate_owners, crates, emails, follows, recent_crate_downloads, users, versions,
+};
+use crate::util::errors::{internal, not_found};
+use crate::util::{data_export, unsubscribe};
+use crate::worker::jobs::ExportUserData;
+use crates_io_worker::BackgroundJob;
+
+use crate::views::{
+    encodable_crate_minimal, encodable_version, EncodableMe, EncodablePrivateUser, OwnedCrate,
 };
-use crate::schema::{crate_owners, crates, emails, follows, users, versions};
-use crate::views::{EncodableMe, EncodablePrivateUser, EncodableVersion, OwnedCrate};
 
 /// Handles the `GET /me` route.
 pub async fn me(app: AppState, req: Parts) -> AppResult<Json<EncodableMe>> {
@@ -22,7 +36,7 @@ pub async fn me(app: AppState, req: Parts) -> AppResult<Json<EncodableMe>> {
         let (user, verified, email, verification_sent): (User, Option<bool>, Option<String>, bool) =
             users::table
                 .find(user_id)
-                .left_join(emails::table)
+                .left_join(emails::table.on(emails::user_id.eq(users::id).and(emails::is_primary)))
                 .select((
                     users::all_columns,
                     emails::verified.nullable(),
@@ -67,6 +81,7 @@ pub async fn updates(app: AppState, req: Parts) -> AppResult<Json<Value>> {
             .inner_join(crates::table)
             .left_outer_join(users::table)
             .filter(crates::id.eq_any(followed_crates))
+            .filter(Version::is_released())
             .order(versions::created_at.desc())
             .select((
                 versions::all_columns,
@@ -80,12 +95,13 @@ pub async fn updates(app: AppState, req: Parts) -> AppResult<Json<Value>> {
         let data = data
             .into_iter()
             .zip(VersionOwnerAction::for_versions(conn, &versions)?)
-            .map(|((v, cn, pb), voas)| (v, cn, pb, voas));
+            .zip(VersionProvenance::for_versions(conn, &versions)?)
+            .map(|(((v, cn, pb), voas), p)| (v, cn, pb, voas, p));
 
         let versions = data
             .into_iter()
-            .map(|(version, crate_name, published_by, actions)| {
-                EncodableVersion::from(version, &crate_name, published_by, actions)
+            .map(|(version, crate_name, published_by, actions, provenance)| {
+                encodable_version(version, &crate_name, published_by, actions, provenance)
             })
             .collect::<Vec<_>>();
 
@@ -97,6 +113,107 @@ pub async fn updates(app: AppState, req: Parts) -> AppResult<Json<Value>> {
     .await?
 }
 
+/// Handles the `GET /me/crates` route.
+///
+/// Returns the crates that the user owns or follows, together with their
+/// total and recent download counts, in a single paginated response.
+///
+/// The `filter` query parameter can be used to only return the `owned` or the
+/// `following` crates. The `sort` query parameter can be `recent_downloads`
+/// (the default), `downloads`, `recent_updates` or `alpha`.
+pub async fn crates(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::only_cookie().check(&req, conn)?.user_id();
+
+        let params = req.query();
+
+        let owned_crates = CrateOwner::by_owner_kind(OwnerKind::User)
+            .select(crate_owners::crate_id)
+            .filter(crate_owners::owner_id.eq(user_id));
+
+        let followed_crates = follows::table
+            .select(follows::crate_id)
+            .filter(follows::user_id.eq(user_id));
+
+        let mut query = crates::table
+            .inner_join(crate_downloads::table)
+            .left_join(recent_crate_downloads::table)
+            .select((
+                ALL_COLUMNS,
+                crate_downloads::downloads,
+                recent_crate_downloads::downloads.nullable(),
+            ))
+            .into_boxed();
+
+        query = match params.get("filter").map(String::as_str) {
+            None | Some("all") => query.filter(
+                crates::id
+                    .eq_any(owned_crates)
+                    .or(crates::id.eq_any(followed_crates)),
+            ),
+            Some("owned") => query.filter(crates::id.eq_any(owned_crates)),
+            Some("following") => query.filter(crates::id.eq_any(followed_crates)),
+            Some(filter) => return Err(bad_request(format!("invalid filter: `{filter}`"))),
+        };
+
+        query = match params.get("sort").map(String::as_str) {
+            None | Some("recent_downloads") => query.order((
+                recent_crate_downloads::downloads.desc().nulls_last(),
+                crates::id.desc(),
+            )),
+            Some("downloads") => {
+                query.order((crate_downloads::downloads.desc(), crates::id.desc()))
+            }
+            Some("recent_updates") => query.order((crates::updated_at.desc(), crates::id.desc())),
+            Some("alpha") => query.order(crates::name.asc()),
+            Some(sort) => return Err(bad_request(format!("invalid sort: `{sort}`"))),
+        };
+
+        let pagination = PaginationOptions::builder().gather(&req)?;
+        let data: Paginated<(Crate, i64, Option<i64>)> =
+            query.pages_pagination(pagination).load(conn)?;
+
+        let meta = PaginationMeta {
+            total: data.total(),
+            next_page: data.next_page_params().map(|p| req.query_with_params(p)),
+            prev_page: data.prev_page_params().map(|p| req.query_with_params(p)),
+        };
+
+        let (crates, downloads): (Vec<_>, Vec<_>) = data
+            .into_iter()
+            .map(|(krate, total, recent)| (krate, (total, recent.unwrap_or(0))))
+            .unzip();
+
+        let versions: Vec<Version> = crates.versions().load(conn)?;
+        let versions = versions
+            .grouped_by(&crates)
+            .into_iter()
+            .map(TopVersions::from_versions);
+
+        let crates = versions
+            .zip(crates)
+            .zip(downloads)
+            .map(|((top_versions, krate), (total, recent))| {
+                encodable_crate_minimal(
+                    krate,
+                    Some(&top_versions),
+                    None,
+                    false,
+                    total,
+                    Some(recent),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "crates": crates,
+            "meta": meta,
+        })))
+    })
+    .await?
+}
+
 /// Handles the `PUT /users/:user_id` route.
 pub async fn update_user(
     state: AppState,
@@ -106,7 +223,7 @@ pub async fn update_user(
     let conn = state.db_write().await?;
     conn.interact(move |conn| {
         use self::emails::user_id;
-        use diesel::insert_into;
+        use diesel::{insert_into, update};
 
         let auth = AuthCheck::default().check(&req, conn)?;
         let user = auth.user();
@@ -144,13 +261,22 @@ pub async fn update_user(
                 email: user_email,
             };
 
-            let token = insert_into(emails::table)
-                .values(&new_email)
-                .on_conflict(user_id)
-                .do_update()
-                .set(&new_email)
+            // Changing the email address replaces the primary address, or
+            // adds the first address if the user doesn't have one yet.
+            let token = update(emails::table)
+                .filter(user_id.eq(user.id))
+                .filter(emails::is_primary)
+                .set(emails::email.eq(user_email))
                 .returning(emails::token)
                 .get_result(conn)
+                .optional()
+                .and_then(|token| match token {
+                    Some(token) => Ok(token),
+                    None => insert_into(emails::table)
+                        .values(&new_email)
+                        .returning(emails::token)
+                        .get_result(conn),
+                })
                 .map(SecretString::new)
                 .map_err(|_| server_error("Error in creating token"))?;
 
@@ -164,7 +290,8 @@ pub async fn update_user(
                 token,
             };
 
-            let _ = state.emails.send(user_email, email);
+            let locale = Locale::from_preference(&user.locale);
+            let _ = state.emails.send_localized(user_email, locale, email);
 
             Ok(())
         })?;
@@ -193,6 +320,22 @@ pub async fn confirm_user_email(state: AppState, Path(token): Path<String>) -> A
     .await?
 }
 
+/// Handles the `PUT /confirm_publishes/:token` route
+///
+/// The token is sent in the notification email about unusual API token
+/// activity. Confirming the activity unlocks publishing for the user again.
+pub async fn confirm_publishes(state: AppState, Path(token): Path<String>) -> AppResult<Response> {
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        if TokenAnomaly::confirm(conn, &token)? == 0 {
+            return Err(bad_request("invalid or already used confirmation token"));
+        }
+
+        ok_true()
+    })
+    .await?
+}
+
 /// Handles `PUT /user/:user_id/resend` route
 pub async fn regenerate_token_and_send(
     state: AppState,
@@ -214,6 +357,7 @@ pub async fn regenerate_token_and_send(
 
         conn.transaction(|conn| -> AppResult<_> {
             let email: Email = update(Email::belonging_to(user))
+                .filter(emails::is_primary)
                 .set(emails::token.eq(sql("DEFAULT")))
                 .get_result(conn)
                 .optional()?
@@ -225,7 +369,11 @@ pub async fn regenerate_token_and_send(
                 token: email.token,
             };
 
-            state.emails.send(&email.email, email1).map_err(Into::into)
+            let locale = Locale::from_preference(&user.locale);
+            state
+                .emails
+                .send_localized(&email.email, locale, email1)
+                .map_err(Into::into)
         })?;
 
         ok_true()
@@ -296,27 +444,167 @@ pub async fn update_email_notifications(app: AppState, req: BytesRequest) -> App
     .await?
 }
 
+/// Handles the `PUT /me/follow_digest` route
+pub async fn update_follow_digest(app: AppState, req: BytesRequest) -> AppResult<Response> {
+    #[derive(Deserialize)]
+    struct FollowDigest {
+        follow_digest: bool,
+    }
+
+    let update = serde_json::from_slice::<FollowDigest>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::default().check(&req, conn)?.user_id();
+
+        diesel::update(users::table.find(user_id))
+            .set(users::follow_digest.eq(update.follow_digest))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `PUT /me/locale` route
+///
+/// The locale determines the language of the emails that are sent to the
+/// user.
+pub async fn update_locale(app: AppState, req: BytesRequest) -> AppResult<Response> {
+    #[derive(Deserialize)]
+    struct UserLocale {
+        locale: String,
+    }
+
+    let update = serde_json::from_slice::<UserLocale>(req.body())
+        .map_err(|_| bad_request("invalid json request"))?;
+
+    let locale = update.locale.parse::<Locale>().map_err(|_| {
+        let supported = Locale::ALL.iter().map(Locale::as_str).collect::<Vec<_>>();
+        bad_request(format!(
+            "unsupported locale `{}`, expected one of: {}",
+            update.locale,
+            supported.join(", ")
+        ))
+    })?;
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::default().check(&req, conn)?.user_id();
+
+        diesel::update(users::table.find(user_id))
+            .set(users::locale.eq(locale.as_str()))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `PUT /unsubscribe/follow_digest/:token` route
+///
+/// The token is sent in the unsubscribe link of the digest emails, so that
+/// users can unsubscribe without being logged in.
+pub async fn unsubscribe_follow_digest(
+    state: AppState,
+    Path(token): Path<String>,
+) -> AppResult<Response> {
+    let user_id =
+        unsubscribe::verify_token(state.session_key(), unsubscribe::FOLLOW_DIGEST, &token)
+            .ok_or_else(|| bad_request("invalid unsubscribe token"))?;
+
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        diesel::update(users::table.find(user_id))
+            .set(users::follow_digest.eq(false))
+            .execute(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `GET /me/export` route
+///
+/// Enqueues a background job that assembles the personal data of the user
+/// into an archive and emails them a download link once it is ready. Since
+/// every export sends an email, the exports are rate limited.
+pub async fn export_data(app: AppState, req: Parts) -> AppResult<Response> {
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::only_cookie().check(&req, conn)?;
+        let user = auth.user();
+
+        if user.verified_email(conn)?.is_none() {
+            let detail = "A verified email address is required to export your data. \
+                          The download link is sent to this address.";
+            return Err(bad_request(detail));
+        }
+
+        app.rate_limiter.check_rate_limit(
+            user.id,
+            LimitedAction::DataExport,
+            app.clock.naive_now(),
+            conn,
+        )?;
+
+        ExportUserData::new(user.id).enqueue(conn)?;
+
+        ok_true()
+    })
+    .await?
+}
+
+/// Handles the `GET /me/export/:token` route
+///
+/// The token is sent in the download link of the data export email and
+/// identifies the export, so that no login is required to download it. The
+/// link expires after a few days.
+pub async fn download_data_export(
+    state: AppState,
+    Path(token): Path<String>,
+) -> AppResult<Response> {
+    let now = Utc::now().timestamp();
+    let (user_id, export_id) = data_export::verify_token(state.session_key(), &token, now)
+        .ok_or_else(|| bad_request("invalid or expired download link"))?;
+
+    let archive = state
+        .storage
+        .read_data_export(user_id, export_id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(not_found)?;
+
+    let headers = [
+        (header::CONTENT_TYPE, "application/gzip"),
+        (
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"crates-io-data-export.tar.gz\"",
+        ),
+    ];
+
+    Ok((headers, archive).into_response())
+}
+
 pub struct UserConfirmEmail<'a> {
     pub user_name: &'a str,
     pub domain: &'a str,
     pub token: SecretString,
 }
 
-impl crate::email::Email for UserConfirmEmail<'_> {
-    const SUBJECT: &'static str = "Please confirm your email address";
+impl LocalizedEmail for UserConfirmEmail<'_> {
+    const TEMPLATE: &'static str = "user_confirm";
 
-    fn body(&self) -> String {
+    fn context(&self) -> minijinja::Value {
         // Create a URL with token string as path to send to user
         // If user clicks on path, look email/user up in database,
         // make sure tokens match
 
-        format!(
-            "Hello {user_name}! Welcome to crates.io. Please click the
-link below to verify your email address. Thank you!\n
-https://{domain}/confirm/{token}",
-            user_name = self.user_name,
-            domain = self.domain,
-            token = self.token.expose_secret(),
-        )
+        minijinja::context! {
+            user_name => self.user_name,
+            domain => self.domain,
+            token => self.token.expose_secret(),
+        }
     }
 }
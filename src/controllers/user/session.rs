@@ -3,10 +3,13 @@ use crate::controllers::frontend_prelude::*;
 use axum::extract::{FromRequestParts, Query};
 use oauth2::reqwest::http_client;
 use oauth2::{AuthorizationCode, CsrfToken, Scope, TokenResponse};
+use std::net::IpAddr;
 use tokio::runtime::Handle;
 
+use crate::challenge::{self, challenge_required, verify_challenge};
 use crate::email::Emails;
 use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::real_ip::RealIp;
 use crate::middleware::session::SessionExtension;
 use crate::models::{NewUser, User};
 use crate::schema::users;
@@ -56,6 +59,11 @@ pub struct AuthorizeQuery {
 /// to exchange the temporary `code` for an API token. The API token is returned together with
 /// the corresponding user information.
 ///
+/// If the sign-in looks suspicious and challenges are enabled, the user is
+/// not signed in yet and a `403 Forbidden` error is returned instead. The
+/// sign-in is then completed by the `PUT /api/private/session/challenge`
+/// route once the user has solved the challenge.
+///
 /// see <https://developer.github.com/v3/oauth/#github-redirects-back-to-your-site>
 ///
 /// ## Query Parameters
@@ -85,6 +93,7 @@ pub async fn authorize(
 ) -> AppResult<Json<EncodableMe>> {
     let app_clone = app.clone();
     let request_log = req.request_log().clone();
+    let ip = req.extensions.get::<RealIp>().map(|ip| **ip);
 
     let conn = app.db_write().await?;
     conn.interact(move |conn| {
@@ -111,8 +120,16 @@ pub async fn authorize(
         let ghuser = Handle::current().block_on(app.github.current_user(token))?;
         let user = save_user_to_database(&ghuser, token.secret(), &app.emails, conn)?;
 
-        // Log in by setting a cookie and the middleware authentication
-        session.insert("user_id".to_string(), user.id.to_string());
+        if let Some(ip) = ip {
+            if app.challenge_provider.is_some() && challenge::is_suspicious(conn, user.id, ip)? {
+                // The authorization code can only be used once, so the sign-in
+                // has to be completed by the `challenge` route instead.
+                session.insert("challenge_user_id".to_string(), user.id.to_string());
+                return Err(challenge_required());
+            }
+        }
+
+        sign_in(&session, user.id, ip, conn);
 
         Ok(())
     })
@@ -121,6 +138,54 @@ pub async fn authorize(
     super::me::me(app_clone, req).await
 }
 
+/// Handles the `PUT /api/private/session/challenge` route.
+///
+/// Completes a sign-in that was interrupted by the `authorize` route because
+/// it looked suspicious. The response to the challenge has to be sent in the
+/// `Challenge-Response` header.
+pub async fn challenge(
+    app: AppState,
+    session: SessionExtension,
+    req: Parts,
+) -> AppResult<Json<EncodableMe>> {
+    let app_clone = app.clone();
+    let parts = req.clone();
+
+    let conn = app.db_write().await?;
+    conn.interact(move |conn| {
+        let user_id = session
+            .get("challenge_user_id")
+            .and_then(|user_id| user_id.parse::<i32>().ok())
+            .ok_or_else(|| bad_request("there is no sign-in waiting for a challenge"))?;
+
+        verify_challenge(&app, &parts)?;
+
+        session.remove("challenge_user_id");
+
+        let ip = parts.extensions.get::<RealIp>().map(|ip| **ip);
+        sign_in(&session, user_id, ip, conn);
+
+        Ok::<_, BoxedAppError>(())
+    })
+    .await??;
+
+    super::me::me(app_clone, req).await
+}
+
+/// Log in by setting a cookie and the middleware authentication, and
+/// remember where the user signed in from.
+fn sign_in(session: &SessionExtension, user_id: i32, ip: Option<IpAddr>, conn: &mut PgConnection) {
+    session.insert("user_id".to_string(), user_id.to_string());
+
+    if let Some(ip) = ip {
+        // Recording the sign-in fails in read-only mode, which should not
+        // prevent users from signing in.
+        if let Err(error) = challenge::record_sign_in(conn, user_id, ip) {
+            warn!(%error, "Failed to record sign-in");
+        }
+    }
+}
+
 fn save_user_to_database(
     user: &GithubUser,
     access_token: &str,
@@ -0,0 +1,73 @@
+//! Endpoint for the monthly API usage of the authenticated user
+//!
+//! The numbers are approximate, see [`crate::api_usage`].
+
+use crate::api_usage::month_of;
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::schema::{api_token_usage, api_tokens, user_api_usage};
+
+/// Handles the `GET /me/usage` route.
+///
+/// Returns the requests of the user in the current month, and the requests
+/// with each of the user's API tokens, together with the quotas.
+pub async fn show(app: AppState, req: Parts) -> AppResult<Json<Value>> {
+    let conn = app.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let user_id = AuthCheck::default().check(&req, conn)?.user_id();
+
+        let month = month_of(app.clock.naive_now());
+        let usage = &app.api_usage;
+        let quotas = usage.config();
+
+        let user_requests: Option<i64> = user_api_usage::table
+            .find((user_id, month))
+            .select(user_api_usage::requests)
+            .first(conn)
+            .optional()?;
+
+        let user_requests =
+            user_requests.unwrap_or_default() as u64 + usage.pending_user_requests(month, user_id);
+
+        let token_usage: Vec<(i32, String, Option<i64>)> = api_tokens::table
+            .left_join(
+                api_token_usage::table.on(api_token_usage::api_token_id
+                    .eq(api_tokens::id)
+                    .and(api_token_usage::month.eq(month))),
+            )
+            .filter(api_tokens::user_id.eq(user_id))
+            .filter(api_tokens::revoked.eq(false))
+            .select((
+                api_tokens::id,
+                api_tokens::name,
+                api_token_usage::requests.nullable(),
+            ))
+            .order(api_tokens::id)
+            .load(conn)?;
+
+        let tokens = token_usage
+            .into_iter()
+            .map(|(id, name, requests)| {
+                let requests =
+                    requests.unwrap_or_default() as u64 + usage.pending_token_requests(month, id);
+
+                json!({
+                    "id": id,
+                    "name": name,
+                    "requests": requests,
+                    "quota": quotas.token_monthly_requests,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({
+            "usage": {
+                "month": month,
+                "requests": user_requests,
+                "quota": quotas.user_monthly_requests,
+                "tokens": tokens,
+            }
+        })))
+    })
+    .await?
+}
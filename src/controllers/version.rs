@@ -1,5 +1,11 @@
+pub mod artifacts;
+pub mod dependency_graph;
 pub mod downloads;
+pub mod files;
+pub mod license_summary;
 pub mod metadata;
+pub mod release_notes;
+pub mod status;
 pub mod yank;
 
 use super::prelude::*;
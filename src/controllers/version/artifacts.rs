@@ -0,0 +1,135 @@
+//! Endpoints for pre-built binary artifacts of crate versions
+//!
+//! Owners of a crate can attach one artifact per target platform to each
+//! version, e.g. from the CI workflow that published the version. The
+//! artifacts are stored next to the crate files, so that tools like
+//! `cargo-binstall` can download the official binaries of a crate.
+
+use super::version_and_crate;
+use crate::auth::AuthCheck;
+use crate::controllers::cargo_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{NewVersionArtifact, Rights, VersionArtifact};
+use crate::util::errors::{bad_request, custom, internal, version_not_found};
+use crate::views::EncodableVersionArtifact;
+use sha2::{Digest, Sha256};
+use tokio::runtime::Handle;
+
+/// The magic bytes at the start of gzip files.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Handles the `GET /crates/:crate_id/:version/artifacts` route.
+pub async fn list(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = state.db_read().await?;
+    conn.interact(move |conn| {
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        let artifacts = VersionArtifact::by_version(conn, &version)?
+            .into_iter()
+            .map(|artifact| {
+                let url =
+                    state
+                        .storage
+                        .artifact_location(&krate.name, &version.num, &artifact.target);
+                EncodableVersionArtifact::from(artifact, url)
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Json(json!({ "artifacts": artifacts })))
+    })
+    .await?
+}
+
+/// Handles the `PUT /crates/:crate_id/:version/artifacts/:target` route.
+///
+/// The request body is the artifact file, which has to be a gzip-compressed
+/// tarball. Artifacts can't be replaced once they are uploaded, like the
+/// crate files themselves.
+pub async fn upload(
+    state: AppState,
+    Path((crate_name, version, target)): Path<(String, String, String)>,
+    req: BytesRequest,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    if !VersionArtifact::is_valid_target(&target) {
+        return Err(bad_request(format!("invalid target triple `{target}`")));
+    }
+
+    let (req, bytes) = req.0.into_parts();
+    if !bytes.starts_with(GZIP_MAGIC) {
+        return Err(bad_request("artifacts must be gzip-compressed tarballs"));
+    }
+
+    let checksum = hex::encode(Sha256::digest(&bytes));
+    let size = bytes.len() as i64;
+
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::default()
+            .with_endpoint_scope(EndpointScope::PublishUpdate)
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        let user = auth.user();
+
+        let owners = krate.owners(conn)?;
+        if Handle::current().block_on(user.rights(&state, &owners))? < Rights::Publish {
+            let detail = "must already be an owner to upload artifacts";
+            return Err(custom(StatusCode::FORBIDDEN, detail));
+        }
+
+        if version.yanked {
+            return Err(bad_request("cannot upload artifacts for yanked versions"));
+        }
+
+        let conflict = || {
+            let detail = format!(
+                "crate `{}` version `{}` already has an artifact for target `{target}`",
+                krate.name, version.num
+            );
+            custom(StatusCode::CONFLICT, detail)
+        };
+
+        let exists = VersionArtifact::by_version(conn, &version)?
+            .iter()
+            .any(|artifact| artifact.target == target);
+        if exists {
+            return Err(conflict());
+        }
+
+        Handle::current()
+            .block_on(
+                state
+                    .storage
+                    .upload_artifact(&krate.name, &version.num, &target, bytes),
+            )
+            .map_err(|error| internal(format!("failed to upload artifact: {error}")))?;
+
+        let artifact = NewVersionArtifact {
+            version_id: version.id,
+            target: &target,
+            checksum: &checksum,
+            size,
+            uploaded_by: user.id,
+        };
+
+        let artifact = artifact.insert(conn)?.ok_or_else(conflict)?;
+        let url = state
+            .storage
+            .artifact_location(&krate.name, &version.num, &artifact.target);
+
+        let artifact = EncodableVersionArtifact::from(artifact, url);
+        Ok(Json(json!({ "artifact": artifact })))
+    })
+    .await?
+}
@@ -0,0 +1,248 @@
+//! Endpoint for exporting the transitive dependency graph of a version
+//!
+//! The graph is resolved on the server by picking the latest non-yanked
+//! version matching each dependency requirement, similar to what cargo would
+//! do for a fresh lockfile. Feature and target specific resolution is not
+//! taken into account, so the graph is an approximation that is meant for
+//! visualization tooling. Dev-dependencies are not part of the graph.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Dependency, DependencyKind, Version};
+use crate::schema::{crates, dependencies, versions};
+use crate::util::errors::version_not_found;
+use crate::views::{
+    EncodableDependencyGraph, EncodableDependencyGraphEdge, EncodableDependencyGraphNode,
+    EncodableUnresolvedDependency,
+};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::version_and_crate;
+
+/// The number of dependency levels that are resolved if no `depth` query
+/// parameter is given.
+const DEFAULT_DEPTH: u32 = 3;
+
+const MAX_DEPTH: u32 = 10;
+
+/// The maximum number of nodes in a graph. Larger graphs are truncated.
+const MAX_NODES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Dot,
+}
+
+/// Handles the `GET /crates/:crate_id/:version/dependency_graph` route.
+///
+/// Supports the `depth` (1 to 10) and `format` (`json` or `dot`) query
+/// parameters.
+pub async fn dependency_graph(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Response> {
+    let params = req.query();
+    let depth = match params.get("depth") {
+        None => DEFAULT_DEPTH,
+        Some(depth) => depth
+            .parse()
+            .ok()
+            .filter(|depth| (1..=MAX_DEPTH).contains(depth))
+            .ok_or_else(|| {
+                bad_request(format!(
+                    "`depth` must be an integer between 1 and {MAX_DEPTH}"
+                ))
+            })?,
+    };
+    let format = match params.get("format").map(String::as_str) {
+        None | Some("json") => Format::Json,
+        Some("dot") => Format::Dot,
+        Some(_) => return Err(bad_request("`format` must be either `json` or `dot`")),
+    };
+
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = state.db_read().await?;
+    conn.interact(move |conn| {
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        let graph = resolve_graph(conn, &krate.name, version, depth)?;
+
+        Ok(match format {
+            Format::Json => Json(json!({ "dependency_graph": graph })).into_response(),
+            Format::Dot => (
+                [(header::CONTENT_TYPE, "text/vnd.graphviz; charset=utf-8")],
+                to_dot(&graph),
+            )
+                .into_response(),
+        })
+    })
+    .await?
+}
+
+fn node_id(name: &str, version: &str) -> String {
+    format!("{name}@{version}")
+}
+
+/// Resolves the dependency graph level by level, so that each level only
+/// needs two queries.
+///
+/// Every version is only visited once, which also takes care of cycles
+/// (e.g. through optional dependencies).
+fn resolve_graph(
+    conn: &mut PgConnection,
+    crate_name: &str,
+    root: Version,
+    depth: u32,
+) -> AppResult<EncodableDependencyGraph> {
+    let root_id = node_id(crate_name, &root.num);
+
+    let mut graph = EncodableDependencyGraph {
+        root: root_id.clone(),
+        depth,
+        truncated: false,
+        nodes: vec![EncodableDependencyGraphNode {
+            id: root_id.clone(),
+            name: crate_name.to_string(),
+            version: root.num,
+        }],
+        edges: vec![],
+        unresolved: vec![],
+    };
+
+    let mut visited = HashMap::from([(root.id, root_id)]);
+    let mut frontier = vec![root.id];
+
+    for _ in 0..depth {
+        if frontier.is_empty() {
+            break;
+        }
+
+        let deps: Vec<(Dependency, String)> = dependencies::table
+            .inner_join(crates::table)
+            .filter(dependencies::version_id.eq_any(&frontier))
+            .filter(dependencies::kind.ne(DependencyKind::Dev))
+            .select((dependencies::all_columns, crates::name))
+            .order(dependencies::id)
+            .load(conn)?;
+
+        let crate_ids = deps.iter().map(|(dep, _)| dep.crate_id).collect::<Vec<_>>();
+        let candidates = load_candidates(conn, &crate_ids)?;
+
+        let mut next_frontier = Vec::new();
+        for (dep, name) in deps {
+            let from = visited[&dep.version_id].clone();
+
+            let resolved = semver::VersionReq::parse(&dep.req).ok().and_then(|req| {
+                candidates
+                    .get(&dep.crate_id)?
+                    .iter()
+                    .find(|(_, num)| req.matches(num))
+            });
+
+            let Some((version_id, num)) = resolved else {
+                graph.unresolved.push(EncodableUnresolvedDependency {
+                    from,
+                    crate_name: name,
+                    req: dep.req,
+                    kind: dep.kind,
+                    optional: dep.optional,
+                });
+                continue;
+            };
+
+            let to = match visited.get(version_id) {
+                Some(to) => to.clone(),
+                None if graph.nodes.len() >= MAX_NODES => {
+                    graph.truncated = true;
+                    continue;
+                }
+                None => {
+                    let version = num.to_string();
+                    let to = node_id(&name, &version);
+                    graph.nodes.push(EncodableDependencyGraphNode {
+                        id: to.clone(),
+                        name,
+                        version,
+                    });
+                    visited.insert(*version_id, to.clone());
+                    next_frontier.push(*version_id);
+                    to
+                }
+            };
+
+            graph.edges.push(EncodableDependencyGraphEdge {
+                from,
+                to,
+                req: dep.req,
+                kind: dep.kind,
+                optional: dep.optional,
+            });
+        }
+
+        frontier = next_frontier;
+    }
+
+    Ok(graph)
+}
+
+/// Loads the non-yanked versions of the given crates, sorted from the
+/// highest to the lowest version.
+pub(super) fn load_candidates(
+    conn: &mut PgConnection,
+    crate_ids: &[i32],
+) -> QueryResult<HashMap<i32, Vec<(i32, semver::Version)>>> {
+    let versions: Vec<(i32, i32, String)> = versions::table
+        .filter(versions::crate_id.eq_any(crate_ids))
+        .filter(versions::yanked.eq(false))
+        .filter(Version::is_released())
+        .select((versions::id, versions::crate_id, versions::num))
+        .load(conn)?;
+
+    let mut candidates: HashMap<_, Vec<_>> = HashMap::new();
+    for (id, crate_id, num) in versions {
+        if let Ok(num) = semver::Version::parse(&num) {
+            candidates.entry(crate_id).or_default().push((id, num));
+        }
+    }
+
+    for versions in candidates.values_mut() {
+        versions.sort_by(|(_, a), (_, b)| b.cmp(a));
+    }
+
+    Ok(candidates)
+}
+
+/// Renders the graph in the Graphviz DOT format.
+///
+/// Optional dependencies are drawn with dashed edges and build dependencies
+/// with gray edges. Unresolved dependencies are not included.
+fn to_dot(graph: &EncodableDependencyGraph) -> String {
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph {:?} {{", graph.root);
+    for node in &graph.nodes {
+        let _ = writeln!(dot, "  {:?};", node.id);
+    }
+    for edge in &graph.edges {
+        let mut attrs = vec![format!("label={:?}", edge.req)];
+        if edge.optional {
+            attrs.push("style=dashed".into());
+        }
+        if edge.kind == DependencyKind::Build {
+            attrs.push("color=gray".into());
+        }
+        let _ = writeln!(
+            dot,
+            "  {:?} -> {:?} [{}];",
+            edge.from,
+            edge.to,
+            attrs.join(", ")
+        );
+    }
+    dot.push_str("}\n");
+    dot
+}
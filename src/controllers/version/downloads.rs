@@ -2,23 +2,94 @@
 //!
 //! Crate level functionality is located in `krate::downloads`.
 
-use super::version_and_crate;
+use crate::cdn_fallback;
 use crate::controllers::prelude::*;
-use crate::models::VersionDownload;
+use crate::models::{Crate, VersionDownload};
 use crate::schema::*;
-use crate::util::errors::version_not_found;
+use crate::util::errors::{bad_request, custom, internal, version_not_found};
 use crate::views::EncodableVersionDownload;
 use chrono::{Duration, NaiveDate, Utc};
+use diesel::dsl::{exists, select};
+use sha2::{Digest, Sha256};
 
 /// Handles the `GET /crates/:crate_id/:version/download` route.
 /// This returns a URL to the location where the crate is stored.
+///
+/// If the `expected_sha256` query parameter is provided, the checksum that was
+/// recorded when the version was published is compared to it first, and a
+/// `409 Conflict` response is returned if they differ. Without the parameter,
+/// the redirect is performed without accessing the database, except for
+/// periodically reloading the set of quarantined versions.
+///
+/// Versions that were quarantined by an administrator pending a review can't
+/// be downloaded, and result in a `451 Unavailable For Legal Reasons`
+/// response. If the set of quarantined versions can't be loaded, e.g. because
+/// the database is unavailable, the download is allowed, since downloads have
+/// to keep working during database outages.
+///
+/// In upstream-proxy mode, the crate file of a crate that was not published
+/// to this registry is fetched from the upstream registry and cached in the
+/// storage before the redirect is performed.
+///
+/// While the CDN is unavailable, the redirect points to a fallback location,
+/// see [`cdn_fallback`].
 pub async fn download(
     app: AppState,
     Path((crate_name, version)): Path<(String, String)>,
     req: Parts,
 ) -> AppResult<Response> {
+    if let Some(expected_sha256) = req.query().get("expected_sha256") {
+        let expected_sha256 = expected_sha256.to_ascii_lowercase();
+        if expected_sha256.len() != 64 || !expected_sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(bad_request(
+                "invalid `expected_sha256` value, expected a hex-encoded SHA256 checksum",
+            ));
+        }
+
+        let conn = app.db_read().await?;
+        let crate_name = crate_name.clone();
+        let version = version.clone();
+        let app = app.clone();
+        let checksum = conn
+            .interact(move |conn| {
+                let version_id = app
+                    .version_id_cache
+                    .get_or_load(conn, &crate_name, &version)?;
+                let checksum = versions::table
+                    .find(version_id)
+                    .select(versions::checksum)
+                    .first::<String>(conn)?;
+                Ok::<_, BoxedAppError>(checksum)
+            })
+            .await??;
+
+        if checksum != expected_sha256 {
+            let detail = format!(
+                "checksum mismatch: expected `{expected_sha256}`, but the recorded checksum is `{checksum}`"
+            );
+            return Err(custom(StatusCode::CONFLICT, detail));
+        }
+    }
+
+    match is_quarantined(&app, &crate_name, &version).await {
+        Ok(true) => {
+            let detail = format!(
+                "version `{version}` of crate `{crate_name}` is quarantined pending review"
+            );
+            return Err(custom(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS, detail));
+        }
+        Ok(false) => {}
+        Err(error) => {
+            warn!(%crate_name, %version, "Failed to check the quarantine of the version: {error}");
+        }
+    }
+
+    if app.upstream.is_some() {
+        cache_upstream_crate_file(&app, &crate_name, &version).await?;
+    }
+
     let wants_json = req.wants_json();
-    let redirect_url = app.storage.crate_location(&crate_name, &version);
+    let redirect_url = cdn_fallback::crate_location(&app, &crate_name, &version).await;
     if wants_json {
         Ok(Json(json!({ "url": redirect_url })).into_response())
     } else {
@@ -26,6 +97,123 @@ pub async fn download(
     }
 }
 
+/// Returns whether the version is quarantined, using the cached set of
+/// quarantined versions if possible.
+pub(super) async fn is_quarantined(
+    app: &AppState,
+    crate_name: &str,
+    version: &str,
+) -> AppResult<bool> {
+    if let Some(quarantined) = app.quarantined_versions.get(crate_name, version) {
+        return Ok(quarantined);
+    }
+
+    let conn = app.db_read().await?;
+    let app = app.clone();
+    let crate_name = crate_name.to_string();
+    let version = version.to_string();
+    let quarantined = conn
+        .interact(move |conn| {
+            app.quarantined_versions
+                .is_quarantined(conn, &crate_name, &version)
+        })
+        .await??;
+
+    Ok(quarantined)
+}
+
+/// Fetches the crate file of a crate version from the upstream registry and
+/// uploads it to the storage, unless the crate was published to this registry
+/// or the crate file was cached already.
+///
+/// The crate file is only cached if its SHA-256 checksum matches the `cksum`
+/// of the version in the upstream index file, so that a corrupted or
+/// tampered download is not served to all users of this registry.
+async fn cache_upstream_crate_file(
+    app: &AppState,
+    crate_name: &str,
+    version: &str,
+) -> AppResult<()> {
+    let Some(upstream) = app.upstream.as_ref() else {
+        return Ok(());
+    };
+
+    let conn = app.db_read().await?;
+    let name = crate_name.to_string();
+    let is_local = conn
+        .interact(move |conn| select(exists(Crate::by_name(&name))).get_result::<bool>(conn))
+        .await??;
+
+    // Crates that were published to this registry take precedence
+    if is_local {
+        return Ok(());
+    }
+
+    let storage = &app.storage;
+    if storage
+        .crate_file_exists(crate_name, version)
+        .await
+        .map_err(internal)?
+    {
+        return Ok(());
+    }
+
+    let index_file = match upstream.index_file(crate_name).await {
+        Ok(index_file) => index_file,
+        Err(error) => {
+            warn!(%crate_name, %version, "Failed to fetch upstream index file: {error}");
+            let detail = "failed to fetch the index file from the upstream registry";
+            return Err(custom(StatusCode::BAD_GATEWAY, detail));
+        }
+    };
+
+    let Some(cksum) = index_file.and_then(|index_file| upstream_cksum(&index_file, version)) else {
+        return Err(version_not_found(crate_name, version));
+    };
+
+    let bytes = match upstream.crate_file(crate_name, version).await {
+        Ok(Some(bytes)) => bytes,
+        Ok(None) => return Err(version_not_found(crate_name, version)),
+        Err(error) => {
+            warn!(%crate_name, %version, "Failed to fetch upstream crate file: {error}");
+            let detail = "failed to fetch the crate file from the upstream registry";
+            return Err(custom(StatusCode::BAD_GATEWAY, detail));
+        }
+    };
+
+    let checksum = hex::encode(Sha256::digest(&bytes));
+    if checksum != cksum {
+        warn!(%crate_name, %version, %checksum, %cksum, "Upstream crate file has an unexpected checksum");
+        let detail = "the checksum of the crate file from the upstream registry does not match its index entry";
+        return Err(custom(StatusCode::BAD_GATEWAY, detail));
+    }
+
+    storage
+        .upload_crate_file(crate_name, version, bytes)
+        .await
+        .map_err(internal)?;
+
+    Ok(())
+}
+
+/// The fields of an upstream index entry that are needed to verify a crate
+/// file.
+#[derive(Deserialize)]
+struct UpstreamIndexEntry {
+    vers: String,
+    cksum: String,
+}
+
+/// Returns the `cksum` of the version in the content of an index file, or
+/// `None` if the index file has no entry for the version.
+fn upstream_cksum(index_file: &[u8], version: &str) -> Option<String> {
+    index_file
+        .split(|&byte| byte == b'\n')
+        .filter_map(|line| serde_json::from_slice::<UpstreamIndexEntry>(line).ok())
+        .find(|entry| entry.vers == version)
+        .map(|entry| entry.cksum)
+}
+
 /// Handles the `GET /crates/:crate_id/:version/downloads` route.
 pub async fn downloads(
     app: AppState,
@@ -38,7 +226,9 @@ pub async fn downloads(
 
     let conn = app.db_read().await?;
     conn.interact(move |conn| {
-        let (version, _) = version_and_crate(conn, &crate_name, &version)?;
+        let version_id = app
+            .version_id_cache
+            .get_or_load(conn, &crate_name, &version)?;
 
         let cutoff_end_date = req
             .query()
@@ -47,10 +237,11 @@ pub async fn downloads(
             .unwrap_or_else(|| Utc::now().date_naive());
         let cutoff_start_date = cutoff_end_date - Duration::days(89);
 
-        let downloads = VersionDownload::belonging_to(&version)
+        let downloads = version_downloads::table
+            .filter(version_downloads::version_id.eq(version_id))
             .filter(version_downloads::date.between(cutoff_start_date, cutoff_end_date))
             .order(version_downloads::date)
-            .load(conn)?
+            .load::<VersionDownload>(conn)?
             .into_iter()
             .map(VersionDownload::into)
             .collect::<Vec<EncodableVersionDownload>>();
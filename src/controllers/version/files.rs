@@ -0,0 +1,112 @@
+//! Endpoint for previewing single files of a crate version, e.g. to render
+//! the `Cargo.toml` manifest without downloading the whole crate file.
+
+use super::downloads::is_quarantined;
+use super::version_and_crate;
+use crate::controllers::prelude::*;
+use crate::util::errors::{bad_request, custom, internal, not_found};
+use bytes::Bytes;
+use crates_io_tarball::{extract_file, ExtractError, ExtractLimits};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// The maximum size of a file that can be previewed.
+const MAX_FILE_SIZE: u64 = 512 * 1024;
+/// The maximum size of a crate file whose files can be previewed.
+const MAX_CRATE_FILE_SIZE: i32 = 20 * 1024 * 1024;
+/// The maximum time that is spent on extracting a file from a crate file.
+const EXTRACT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Handles the `GET /crates/:crate_id/:version/file/*path` route.
+///
+/// Extracts the file at `path` (relative to the package root) from the
+/// stored crate file and returns its content. Extracted files are cached,
+/// since the crate files of published versions never change.
+pub async fn file(
+    app: AppState,
+    Path((crate_name, version, path)): Path<(String, String, String)>,
+) -> AppResult<Response> {
+    let is_invalid_segment = |segment: &str| matches!(segment, "" | "." | "..");
+    if path.split('/').any(is_invalid_segment) {
+        return Err(bad_request(format!("invalid file path `{path}`")));
+    }
+
+    let conn = app.db_read().await?;
+    let (crate_name, version, crate_size) = conn
+        .interact(move |conn| {
+            let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+            Ok::<_, BoxedAppError>((krate.name, version.num, version.crate_size))
+        })
+        .await??;
+
+    if crate_size.unwrap_or_default() > MAX_CRATE_FILE_SIZE {
+        let detail = "the crate file of this version is too large for file previews";
+        return Err(bad_request(detail));
+    }
+
+    if is_quarantined(&app, &crate_name, &version).await? {
+        let detail =
+            format!("version `{version}` of crate `{crate_name}` is quarantined pending review");
+        return Err(custom(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS, detail));
+    }
+
+    let key = (crate_name.clone(), version.clone(), path.clone());
+    let content = match app.file_preview_cache.get(&key) {
+        Some(content) => content,
+        None => {
+            let content = extract(&app, &crate_name, &version, &path).await?;
+            app.file_preview_cache.insert(key, content.clone());
+            content
+        }
+    };
+
+    let Some(content) = content else {
+        let detail = format!("crate `{crate_name}` version `{version}` has no file `{path}`");
+        return Err(custom(StatusCode::NOT_FOUND, detail));
+    };
+
+    let content_type = match std::str::from_utf8(&content) {
+        Ok(_) => "text/plain; charset=utf-8",
+        Err(_) => "application/octet-stream",
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], content).into_response())
+}
+
+/// Extracts a file from the stored crate file of a version, or returns `None`
+/// if the crate file does not contain the file.
+async fn extract(
+    app: &AppState,
+    crate_name: &str,
+    version: &str,
+    path: &str,
+) -> AppResult<Option<Bytes>> {
+    let tarball = app
+        .storage
+        .read_crate_file(crate_name, version)
+        .await
+        .map_err(internal)?
+        .ok_or_else(not_found)?;
+
+    let entry_path = PathBuf::from(format!("{crate_name}-{version}/{path}"));
+    let limits = ExtractLimits {
+        max_unpack: app.config.max_unpack_size,
+        max_file_size: MAX_FILE_SIZE,
+        deadline: Instant::now() + EXTRACT_TIMEOUT,
+    };
+
+    let path = path.to_string();
+    spawn_blocking(move || match extract_file(&*tarball, &entry_path, limits) {
+        Ok(content) => Ok(content.map(Bytes::from)),
+        Err(ExtractError::FileTooLarge { max }) => Err(bad_request(format!(
+            "file `{path}` is too large to be previewed, the limit is {} KiB",
+            max / 1024
+        ))),
+        Err(ExtractError::TimedOut) => Err(custom(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "extracting the file took too long, please try again later",
+        )),
+        Err(error) => Err(internal(format!("failed to extract `{path}`: {error}"))),
+    })
+    .await
+}
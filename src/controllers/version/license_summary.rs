@@ -0,0 +1,121 @@
+//! Endpoint for summarizing the licenses of the dependencies of a version
+//!
+//! Like the [dependency graph](super::dependency_graph), each dependency is
+//! resolved to the latest non-yanked version matching its requirement, so
+//! the summary can differ from the licenses of a lockfile. Only the direct
+//! dependencies are included, and dev-dependencies are left out since they
+//! are not part of the builds of dependent crates.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Dependency, DependencyKind};
+use crate::schema::{crates, dependencies, versions};
+use crate::util::errors::version_not_found;
+use crate::views::{EncodableDependencyLicense, EncodableLicenseSummary, EncodableLicenseUsage};
+use std::collections::{BTreeMap, HashMap};
+
+use super::dependency_graph::load_candidates;
+use super::version_and_crate;
+
+/// A dependency with the name of its crate, and the id and number of the
+/// version it resolves to, if any.
+type ResolvedDependency = (Dependency, String, Option<(i32, semver::Version)>);
+
+/// Handles the `GET /crates/:crate_id/:version/license_summary` route.
+pub async fn license_summary(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = state.db_read().await?;
+    conn.interact(move |conn| {
+        let (version, _) = version_and_crate(conn, &crate_name, &version)?;
+
+        let deps: Vec<(Dependency, String)> = dependencies::table
+            .inner_join(crates::table)
+            .filter(dependencies::version_id.eq(version.id))
+            .filter(dependencies::kind.ne(DependencyKind::Dev))
+            .select((dependencies::all_columns, crates::name))
+            .order((dependencies::optional, crates::name))
+            .load(conn)?;
+
+        let crate_ids = deps.iter().map(|(dep, _)| dep.crate_id).collect::<Vec<_>>();
+        let candidates = load_candidates(conn, &crate_ids)?;
+
+        let mut resolved = Vec::with_capacity(deps.len());
+        for (dep, name) in deps {
+            let num = semver::VersionReq::parse(&dep.req).ok().and_then(|req| {
+                candidates
+                    .get(&dep.crate_id)?
+                    .iter()
+                    .find(|(_, num)| req.matches(num))
+            });
+            resolved.push((dep, name, num.cloned()));
+        }
+
+        let version_ids = resolved
+            .iter()
+            .filter_map(|(_, _, num)| num.as_ref().map(|(id, _)| *id))
+            .collect::<Vec<_>>();
+
+        let licenses: HashMap<i32, Option<String>> = versions::table
+            .filter(versions::id.eq_any(&version_ids))
+            .select((versions::id, versions::license))
+            .load::<(i32, Option<String>)>(conn)?
+            .into_iter()
+            .collect();
+
+        let summary = summarize(resolved, &licenses);
+        Ok(Json(json!({ "license_summary": summary })))
+    })
+    .await?
+}
+
+fn summarize(
+    resolved: Vec<ResolvedDependency>,
+    licenses: &HashMap<i32, Option<String>>,
+) -> EncodableLicenseSummary {
+    let mut usages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut unknown = Vec::new();
+    let mut dependencies = Vec::with_capacity(resolved.len());
+
+    for (dep, name, num) in resolved {
+        let license = num
+            .as_ref()
+            .and_then(|(id, _)| licenses.get(id).cloned().flatten());
+        let version = num.map(|(_, num)| num.to_string());
+
+        match (&license, &version) {
+            (Some(license), Some(version)) => {
+                let crates = usages.entry(license.clone()).or_default();
+                let id = format!("{name}@{version}");
+                if !crates.contains(&id) {
+                    crates.push(id);
+                }
+            }
+            _ if !unknown.contains(&name) => unknown.push(name.clone()),
+            _ => {}
+        }
+
+        dependencies.push(EncodableDependencyLicense {
+            crate_name: name,
+            req: dep.req,
+            kind: dep.kind,
+            optional: dep.optional,
+            version,
+            license,
+        });
+    }
+
+    EncodableLicenseSummary {
+        licenses: usages
+            .into_iter()
+            .map(|(license, crates)| EncodableLicenseUsage { license, crates })
+            .collect(),
+        dependencies,
+        unknown,
+    }
+}
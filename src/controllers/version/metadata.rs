@@ -6,9 +6,12 @@
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::VersionOwnerAction;
-use crate::util::errors::version_not_found;
-use crate::views::{EncodableDependency, EncodableVersion};
+use crate::auth::authenticate;
+use crate::models::{Rights, VersionOwnerAction, VersionProvenance};
+use crate::util::errors::{custom, version_not_found};
+use crate::views::json_api::{self, Document, Fieldsets, Resource};
+use crate::views::{encodable_version, EncodableDependency, EncodableVersionProvenance};
+use tokio::runtime::Handle;
 
 use super::version_and_crate;
 
@@ -56,10 +59,14 @@ pub async fn authors() -> Json<Value> {
 ///
 /// The frontend doesn't appear to hit this endpoint, but our tests do, and it seems to be a useful
 /// API route to have.
+///
+/// Responds with a JSON:API document if the client requests it, see
+/// [`json_api`].
 pub async fn show(
     state: AppState,
     Path((crate_name, version)): Path<(String, String)>,
-) -> AppResult<Json<Value>> {
+    req: Parts,
+) -> AppResult<Response> {
     if semver::Version::parse(&version).is_err() {
         return Err(version_not_found(&crate_name, &version));
     }
@@ -69,9 +76,50 @@ pub async fn show(
         let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
         let published_by = version.published_by(conn);
         let actions = VersionOwnerAction::by_version(conn, &version)?;
+        let provenance = VersionProvenance::by_version(conn, &version)?;
+
+        let version = encodable_version(version, &krate.name, published_by, actions, provenance);
+
+        if json_api::is_requested(&req.headers) {
+            let fieldsets = Fieldsets::from_query(&req.query());
+            let resource = Resource::from_version(version);
+            return Ok(Document::single(resource, fieldsets).into_response());
+        }
+
+        Ok(Json(json!({ "version": version })).into_response())
+    })
+    .await?
+}
+
+/// Handles the `GET /crates/:crate_id/:version/provenance` route.
+///
+/// Returns the full provenance record of the version, including the name of
+/// the API token, the user agent and the IP address that were used to publish
+/// it. The version API only exposes a redacted form of this record, so this
+/// endpoint is only available to the owners of the crate.
+pub async fn provenance(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = state.db_read_prefer_primary().await?;
+    conn.interact(move |conn| {
+        let auth = authenticate(&req, conn)?;
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+
+        let owners = krate.owners(conn)?;
+        if Handle::current().block_on(auth.user().rights(&state, &owners))? < Rights::Publish {
+            let detail = "only owners of this crate can view the provenance of its versions";
+            return Err(custom(StatusCode::FORBIDDEN, detail));
+        }
 
-        let version = EncodableVersion::from(version, &krate.name, published_by, actions);
-        Ok(Json(json!({ "version": version })))
+        let provenance = VersionProvenance::by_version(conn, &version)?;
+        let provenance = provenance.map(EncodableVersionProvenance::from);
+        Ok(Json(json!({ "provenance": provenance })))
     })
     .await?
 }
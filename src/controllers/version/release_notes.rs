@@ -0,0 +1,103 @@
+//! Endpoints for the release notes of crate versions
+//!
+//! Owners of a crate can add release notes to a version after publishing it,
+//! so that changelogs can be displayed without parsing files of the
+//! repository. The release notes are submitted as Markdown and rendered with
+//! the same pipeline as the READMEs.
+
+use super::version_and_crate;
+use crate::auth::AuthCheck;
+use crate::controllers::frontend_prelude::*;
+use crate::models::token::EndpointScope;
+use crate::models::{NewReleaseNotes, ReleaseNotes, Rights};
+use crate::util::errors::{custom, version_not_found};
+use crate::views::EncodableReleaseNotes;
+use crates_io_markdown::text_to_html;
+use tokio::runtime::Handle;
+
+/// The maximum size of the release notes of a version in bytes.
+const MAX_RELEASE_NOTES_SIZE: usize = 64 * 1024;
+
+/// Handles the `GET /crates/:crate_id/:version/release_notes` route.
+pub async fn show(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let conn = state.db_read().await?;
+    conn.interact(move |conn| {
+        let (version, _) = version_and_crate(conn, &crate_name, &version)?;
+        let release_notes = ReleaseNotes::by_version(conn, &version)?;
+        let release_notes = release_notes.map(EncodableReleaseNotes::from);
+        Ok(Json(json!({ "release_notes": release_notes })))
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct UpdateReleaseNotesRequest {
+    release_notes: String,
+}
+
+/// Handles the `PUT /crates/:crate_id/:version/release_notes` route.
+///
+/// Replaces the release notes of the version. Empty release notes remove
+/// the existing release notes.
+pub async fn update(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    parts: Parts,
+    Json(body): Json<UpdateReleaseNotesRequest>,
+) -> AppResult<Json<Value>> {
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let markdown = body.release_notes;
+    if markdown.len() > MAX_RELEASE_NOTES_SIZE {
+        return Err(bad_request(format!(
+            "release notes must not be larger than {MAX_RELEASE_NOTES_SIZE} bytes"
+        )));
+    }
+
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::default()
+            .with_endpoint_scope(EndpointScope::PublishUpdate)
+            .for_crate(&crate_name)
+            .check(&parts, conn)?;
+
+        let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+        let user = auth.user();
+
+        let owners = krate.owners(conn)?;
+        if Handle::current().block_on(user.rights(&state, &owners))? < Rights::Publish {
+            let detail = "must already be an owner to change release notes";
+            return Err(custom(StatusCode::FORBIDDEN, detail));
+        }
+
+        if markdown.trim().is_empty() {
+            ReleaseNotes::delete(conn, &version)?;
+            return Ok(Json(json!({ "release_notes": null })));
+        }
+
+        // Relative links are resolved against the repository, like in the
+        // READMEs, since the release notes usually refer to files in it.
+        let html = text_to_html(&markdown, "CHANGELOG.md", krate.repository.as_deref(), None);
+
+        let release_notes = NewReleaseNotes {
+            version_id: version.id,
+            markdown: &markdown,
+            html: &html,
+            updated_by: user.id,
+        };
+
+        let release_notes = release_notes.save(conn)?;
+        let release_notes = EncodableReleaseNotes::from(release_notes);
+        Ok(Json(json!({ "release_notes": release_notes })))
+    })
+    .await?
+}
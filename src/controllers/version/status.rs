@@ -0,0 +1,138 @@
+//! Endpoint for waiting until a published version is available
+//!
+//! After a publish, the index is updated by background jobs, so clients like
+//! CI pipelines can't rely on the version being resolvable right away. This
+//! endpoint reports whether the index sync of the crate is still pending and
+//! can long-poll until it completes.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::middleware::deadline::Deadline;
+use crate::util::errors::version_not_found;
+use crate::worker::jobs;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::version_and_crate;
+
+/// The longest time a request can wait for the index sync.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// How often the background job queue is checked while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The time that is left before the request timeout when the wait ends, so
+/// that the last check of the job queue can still be answered.
+const DEADLINE_MARGIN: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum IndexSyncState {
+    /// The index sync jobs of the crate are queued, running or retried.
+    Pending,
+    /// The index contains the current state of the crate.
+    Synced,
+}
+
+/// Handles the `GET /crates/:crate_id/:version/status` route.
+///
+/// Supports the `wait` query parameter (e.g. `30s`, at most 60 seconds), in
+/// which case the response is delayed until the index sync completes or the
+/// time is up. The wait is shortened to end before the request timeout. Since the sync jobs are per crate, the state also reflects
+/// syncs for other changes of the crate, like yanks.
+pub async fn status(
+    state: AppState,
+    Path((crate_name, version)): Path<(String, String)>,
+    req: Parts,
+) -> AppResult<Json<Value>> {
+    let wait = match req.query().get("wait") {
+        None => Duration::ZERO,
+        Some(wait) => parse_wait(wait).ok_or_else(|| {
+            bad_request(format!(
+                "`wait` must be a number of seconds between 0 and {}, e.g. `30s`",
+                MAX_WAIT.as_secs()
+            ))
+        })?,
+    };
+
+    if semver::Version::parse(&version).is_err() {
+        return Err(version_not_found(&crate_name, &version));
+    }
+
+    let wait = cap_wait(wait, Deadline::current());
+    let deadline = Instant::now() + wait;
+    loop {
+        // The job queue is only up to date on the primary database
+        let conn = state.db_read_prefer_primary().await?;
+        let (crate_name, version) = (crate_name.clone(), version.clone());
+        let (crate_name, version, index_sync) = conn
+            .interact(move |conn| {
+                let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
+                let index_sync = match jobs::index_sync_pending(&krate.name, conn)? {
+                    true => IndexSyncState::Pending,
+                    false => IndexSyncState::Synced,
+                };
+                Ok::<_, BoxedAppError>((krate.name, version.num, index_sync))
+            })
+            .await??;
+        drop(conn);
+
+        let now = Instant::now();
+        if index_sync == IndexSyncState::Synced || now >= deadline {
+            return Ok(Json(json!({
+                "status": {
+                    "crate": crate_name,
+                    "version": version,
+                    "index_sync": index_sync,
+                }
+            })));
+        }
+
+        tokio::time::sleep(POLL_INTERVAL.min(deadline - now)).await;
+    }
+}
+
+/// Parses a number of seconds with an optional `s` suffix.
+fn parse_wait(wait: &str) -> Option<Duration> {
+    let seconds = wait.strip_suffix('s').unwrap_or(wait).parse().ok()?;
+    Some(Duration::from_secs(seconds)).filter(|wait| *wait <= MAX_WAIT)
+}
+
+/// Shortens the wait so that the response is sent before the request
+/// deadline.
+fn cap_wait(wait: Duration, deadline: Option<Deadline>) -> Duration {
+    match deadline {
+        Some(deadline) => wait.min(deadline.remaining().saturating_sub(DEADLINE_MARGIN)),
+        None => wait,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wait() {
+        assert_eq!(parse_wait("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_wait("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_wait("0s"), Some(Duration::ZERO));
+        assert_eq!(parse_wait("60s"), Some(MAX_WAIT));
+        assert_eq!(parse_wait("61s"), None);
+        assert_eq!(parse_wait("-1s"), None);
+        assert_eq!(parse_wait("1m"), None);
+        assert_eq!(parse_wait(""), None);
+    }
+
+    #[test]
+    fn test_cap_wait() {
+        let wait = Duration::from_secs(60);
+        assert_eq!(cap_wait(wait, None), wait);
+
+        let capped = cap_wait(wait, Some(Deadline::after(Duration::from_secs(30))));
+        assert!(capped <= Duration::from_secs(28));
+        assert!(capped > Duration::from_secs(27));
+
+        let deadline = Deadline::after(Duration::from_secs(1));
+        assert_eq!(cap_wait(wait, Some(deadline)), Duration::ZERO);
+    }
+}
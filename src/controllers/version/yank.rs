@@ -1,15 +1,18 @@
 //! Endpoints for yanking and unyanking specific versions of crates
 
 use super::version_and_crate;
+use crate::audit::AuditEvent;
 use crate::auth::AuthCheck;
 use crate::controllers::cargo_prelude::*;
+use crate::middleware::real_ip::RealIp;
 use crate::models::token::EndpointScope;
-use crate::models::Rights;
-use crate::models::{insert_version_owner_action, VersionAction};
+use crate::models::VersionAction;
+use crate::models::{Crate, Rights, Version};
 use crate::rate_limiter::LimitedAction;
 use crate::schema::versions;
-use crate::util::errors::{custom, version_not_found};
+use crate::util::errors::{bad_request, crate_not_found, custom, version_not_found};
 use crate::worker::jobs;
+use crates_io_worker::BackgroundJob;
 use tokio::runtime::Handle;
 
 /// Handles the `DELETE /crates/:crate_id/:version/yank` route.
@@ -60,9 +63,12 @@ async fn modify_yank(
             .for_crate(&crate_name)
             .check(&req, conn)?;
 
-        state
-            .rate_limiter
-            .check_rate_limit(auth.user_id(), LimitedAction::YankUnyank, conn)?;
+        state.rate_limiter.check_rate_limit(
+            auth.user_id(),
+            LimitedAction::YankUnyank,
+            state.clock.naive_now(),
+            conn,
+        )?;
 
         let (version, krate) = version_and_crate(conn, &crate_name, &version)?;
         let api_token_id = auth.api_token_id();
@@ -92,6 +98,7 @@ async fn modify_yank(
         diesel::update(&version)
             .set(versions::yanked.eq(yanked))
             .execute(conn)?;
+        state.version_id_cache.invalidate(&krate.name, &version.num);
 
         let action = if yanked {
             VersionAction::Yank
@@ -99,11 +106,152 @@ async fn modify_yank(
             VersionAction::Unyank
         };
 
-        insert_version_owner_action(conn, version.id, user.id, api_token_id, action)?;
+        let ip = req.extensions.get::<RealIp>().map(|ip| **ip);
+        let event = AuditEvent::VersionAction {
+            version_id: version.id,
+            user_id: user.id,
+            api_token_id,
+            action,
+            ip,
+            reason: None,
+        };
+        state.audit_log.record(conn, event)?;
 
         jobs::enqueue_sync_to_index(&krate.name, conn)?;
+        jobs::UpdateStorageTags::new(&krate.name, &version.num).enqueue(conn)?;
 
         ok_true()
     })
     .await?
 }
+
+#[derive(Deserialize)]
+pub struct BulkYankRequest {
+    /// Version requirements like `<0.2.5`. All versions matching any of
+    /// them are yanked.
+    versions: Vec<String>,
+    /// Why the versions are yanked, e.g. a link to a security advisory.
+    reason: String,
+}
+
+/// Handles the `POST /crates/:crate_id/yank_bulk` route.
+///
+/// Yanks all versions of the crate that match any of the given version
+/// requirements in a single transaction, which is useful when responding to
+/// vulnerabilities that affect many versions. Each yanked version gets an
+/// audit action with the given reason, and the index is only synced once.
+pub async fn bulk_yank(
+    state: AppState,
+    Path(crate_name): Path<String>,
+    req: Parts,
+    Json(body): Json<BulkYankRequest>,
+) -> AppResult<Json<Value>> {
+    let reason = body.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(bad_request("a reason is required to yank versions in bulk"));
+    }
+
+    if body.versions.is_empty() {
+        return Err(bad_request("at least one version requirement is required"));
+    }
+
+    let requirements = body
+        .versions
+        .iter()
+        .map(|requirement| {
+            semver::VersionReq::parse(requirement)
+                .map_err(|_| bad_request(format!("invalid version requirement: `{requirement}`")))
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    let conn = state.db_write().await?;
+    conn.interact(move |conn| {
+        let auth = AuthCheck::default()
+            .with_endpoint_scope(EndpointScope::Yank)
+            .for_crate(&crate_name)
+            .check(&req, conn)?;
+
+        state.rate_limiter.check_rate_limit(
+            auth.user_id(),
+            LimitedAction::YankUnyank,
+            state.clock.naive_now(),
+            conn,
+        )?;
+
+        let krate: Crate = Crate::by_name(&crate_name)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| crate_not_found(&crate_name))?;
+
+        let api_token_id = auth.api_token_id();
+        let user = auth.user();
+        let owners = krate.owners(conn)?;
+
+        if Handle::current().block_on(user.rights(&state, &owners))? < Rights::Publish {
+            if user.is_admin {
+                warn!(
+                    "Admin {} is bulk yanking {} ({reason})",
+                    user.gh_login, krate.name
+                );
+            } else {
+                return Err(custom(
+                    StatusCode::FORBIDDEN,
+                    "must already be an owner to yank or unyank",
+                ));
+            }
+        }
+
+        let ip = req.extensions.get::<RealIp>().map(|ip| **ip);
+
+        conn.transaction(|conn| {
+            let versions: Vec<Version> = Version::belonging_to(&krate)
+                .filter(versions::yanked.eq(false))
+                .order(versions::id)
+                .for_update()
+                .load(conn)?;
+
+            let versions = versions
+                .into_iter()
+                .filter(|version| {
+                    semver::Version::parse(&version.num)
+                        .map(|num| requirements.iter().any(|req| req.matches(&num)))
+                        .unwrap_or(false)
+                })
+                .collect::<Vec<_>>();
+
+            if versions.is_empty() {
+                return Ok(Json(json!({ "yanked": Vec::<String>::new() })));
+            }
+
+            let version_ids = versions.iter().map(|version| version.id);
+            diesel::update(versions::table)
+                .filter(versions::id.eq_any(version_ids))
+                .set(versions::yanked.eq(true))
+                .execute(conn)?;
+
+            for version in &versions {
+                state.version_id_cache.invalidate(&krate.name, &version.num);
+                jobs::UpdateStorageTags::new(&krate.name, &version.num).enqueue(conn)?;
+                let event = AuditEvent::VersionAction {
+                    version_id: version.id,
+                    user_id: user.id,
+                    api_token_id,
+                    action: VersionAction::Yank,
+                    ip,
+                    reason: Some(reason.clone()),
+                };
+                state.audit_log.record(conn, event)?;
+            }
+
+            jobs::enqueue_sync_to_index(&krate.name, conn)?;
+
+            let yanked = versions
+                .into_iter()
+                .map(|version| version.num)
+                .collect::<Vec<_>>();
+
+            Ok(Json(json!({ "yanked": yanked })))
+        })
+    })
+    .await?
+}
@@ -1,13 +1,24 @@
+use axum::extract::MatchedPath;
 use deadpool_diesel::postgres::{Hook, HookError};
+use deadpool_diesel::InteractError;
 use diesel::prelude::*;
+use prometheus::Histogram;
 use secrecy::ExposeSecret;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use url::Url;
 
 use crate::config;
+use crate::middleware::deadline::Deadline;
+use crate::middleware::request_timeout::current_route;
 
 pub mod sql_types;
 
+/// The statement timeout of a connection is only lowered to the remaining
+/// time budget of a request if that is shorter than the configured timeout by
+/// at least this much, to avoid two extra round trips per interaction for
+/// a negligible difference.
+const STATEMENT_TIMEOUT_SLACK: Duration = Duration::from_millis(500);
+
 pub fn oneoff_connection_with_config(
     config: &config::DatabasePools,
 ) -> ConnectionResult<PgConnection> {
@@ -44,6 +55,114 @@ fn maybe_append_url_param(url: &mut Url, key: &str, value: &str) {
     }
 }
 
+/// A connection from one of the database pools of the [`App`](crate::App).
+///
+/// Diesel does not offer a way to instrument individual queries, so the
+/// time that the closure of each [`interact()`](Self::interact) call runs
+/// for is recorded instead, and calls that take longer than the configured
+/// `slow_query_threshold` are logged. Besides the queries, this includes any
+/// other work done in the closure, but not the time spent waiting for a
+/// blocking thread.
+///
+/// If the connection was obtained while handling a request whose remaining
+/// time budget is shorter than the configured `statement_timeout` of the
+/// pool, the timeout is lowered for the duration of each `interact()` call.
+pub struct DbConnection {
+    conn: deadpool_diesel::postgres::Connection,
+    pool: &'static str,
+    statement_timeout: Duration,
+    deadline: Option<Deadline>,
+    slow_query_threshold: Duration,
+    interact_duration: Histogram,
+}
+
+impl DbConnection {
+    /// Wraps a connection from the pool, capturing the [`Deadline`] of the
+    /// current request, if there is one.
+    pub(crate) fn new(
+        conn: deadpool_diesel::postgres::Connection,
+        pool: &'static str,
+        statement_timeout: Duration,
+        slow_query_threshold: Duration,
+        interact_duration: Histogram,
+    ) -> Self {
+        Self {
+            conn,
+            pool,
+            statement_timeout,
+            deadline: Deadline::current(),
+            slow_query_threshold,
+            interact_duration,
+        }
+    }
+
+    /// Returns the statement timeout that the next interaction has to use
+    /// instead of the configured one, if the remaining time budget of the
+    /// request is significantly shorter.
+    fn lowered_statement_timeout(&self) -> Option<Duration> {
+        let remaining = self.deadline?.remaining();
+        if remaining + STATEMENT_TIMEOUT_SLACK >= self.statement_timeout {
+            return None;
+        }
+
+        // A `statement_timeout` of zero would disable the timeout
+        Some(remaining.max(Duration::from_millis(1)))
+    }
+
+    /// Runs the closure with the connection on a blocking thread.
+    ///
+    /// Slow calls are logged with the matched route of the current request,
+    /// but without any of the query parameters, since they might contain
+    /// sensitive data.
+    pub async fn interact<F, R>(&self, f: F) -> Result<R, InteractError>
+    where
+        F: FnOnce(&mut PgConnection) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let lowered_timeout = self.lowered_statement_timeout();
+        let statement_timeout = self.statement_timeout;
+
+        let (result, duration) = self
+            .conn
+            .interact(move |conn| {
+                if let Some(timeout) = lowered_timeout {
+                    if let Err(error) = set_statement_timeout(timeout, conn) {
+                        warn!("Failed to lower the statement timeout: {error}");
+                    }
+                }
+
+                let start = Instant::now();
+                let result = f(conn);
+                let duration = start.elapsed();
+
+                // If `f` panics, the connection is discarded instead of being
+                // returned to the pool, so there is nothing to restore then.
+                if lowered_timeout.is_some() {
+                    if let Err(error) = set_statement_timeout(statement_timeout, conn) {
+                        warn!("Failed to restore the statement timeout: {error}");
+                    }
+                }
+
+                (result, duration)
+            })
+            .await?;
+
+        self.interact_duration.observe(duration.as_secs_f64());
+        if duration > self.slow_query_threshold {
+            let route = current_route();
+            let route = route.as_ref().map(MatchedPath::as_str);
+            warn!(
+                pool = self.pool,
+                ?duration,
+                route,
+                "Slow database interaction"
+            );
+        }
+
+        Ok(result)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ConnectionConfig {
     pub statement_timeout: Duration,
@@ -52,8 +171,7 @@ pub struct ConnectionConfig {
 
 impl ConnectionConfig {
     fn apply(&self, conn: &mut PgConnection) -> QueryResult<()> {
-        let statement_timeout = self.statement_timeout.as_millis();
-        diesel::sql_query(format!("SET statement_timeout = {statement_timeout}")).execute(conn)?;
+        set_statement_timeout(self.statement_timeout, conn)?;
 
         if self.read_only {
             diesel::sql_query("SET default_transaction_read_only = 't'").execute(conn)?;
@@ -63,6 +181,12 @@ impl ConnectionConfig {
     }
 }
 
+fn set_statement_timeout(statement_timeout: Duration, conn: &mut PgConnection) -> QueryResult<()> {
+    let statement_timeout = statement_timeout.as_millis();
+    diesel::sql_query(format!("SET statement_timeout = {statement_timeout}")).execute(conn)?;
+    Ok(())
+}
+
 impl From<ConnectionConfig> for Hook {
     fn from(config: ConnectionConfig) -> Self {
         Hook::async_fn(move |conn, _| {
@@ -10,11 +10,24 @@ use lettre::transport::stub::StubTransport;
 use lettre::{Message, Transport};
 use rand::distributions::{Alphanumeric, DistString};
 
+mod locale;
+mod templates;
+
+pub use self::locale::{Locale, UnsupportedLocale};
+
 pub trait Email {
     const SUBJECT: &'static str;
     fn body(&self) -> String;
 }
 
+/// An email that is rendered from the templates in `src/email/templates/`
+/// in the preferred language of the recipient.
+pub trait LocalizedEmail {
+    /// The name of the template, e.g. `user_confirm`.
+    const TEMPLATE: &'static str;
+    fn context(&self) -> minijinja::Value;
+}
+
 #[derive(Debug, Clone)]
 pub struct Emails {
     backend: EmailBackend,
@@ -84,6 +97,22 @@ impl Emails {
     }
 
     pub fn send<E: Email>(&self, recipient: &str, email: E) -> Result<(), EmailError> {
+        self.send_message(recipient, E::SUBJECT, email.body())
+    }
+
+    /// Sends the email in the given language, or in English if the email
+    /// has not been translated to that language yet.
+    pub fn send_localized<E: LocalizedEmail>(
+        &self,
+        recipient: &str,
+        locale: Locale,
+        email: E,
+    ) -> Result<(), EmailError> {
+        let (subject, body) = templates::render(E::TEMPLATE, locale, email.context())?;
+        self.send_message(recipient, &subject, body)
+    }
+
+    fn send_message(&self, recipient: &str, subject: &str, body: String) -> Result<(), EmailError> {
         // The message ID is normally generated by the SMTP server, but if we let it generate the
         // ID there will be no way for the crates.io application to know the ID of the message it
         // just sent, as it's not included in the SMTP response.
@@ -97,9 +126,6 @@ impl Emails {
             self.domain,
         );
 
-        let subject = E::SUBJECT;
-        let body = email.body();
-
         let email = Message::builder()
             .message_id(Some(message_id.clone()))
             .to(recipient.parse()?)
@@ -119,6 +145,8 @@ pub enum EmailError {
     #[error(transparent)]
     MessageBuilderError(#[from] lettre::error::Error),
     #[error(transparent)]
+    TemplateError(#[from] minijinja::Error),
+    #[error(transparent)]
     TransportError(anyhow::Error),
 }
 
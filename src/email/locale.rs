@@ -0,0 +1,68 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The languages that emails can be sent in.
+///
+/// Templates that have not been translated to a language yet are rendered
+/// in English instead.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+}
+
+impl Locale {
+    pub const ALL: &'static [Locale] = &[Locale::En, Locale::De, Locale::Fr];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+        }
+    }
+
+    /// Parses the `users.locale` preference of a user, falling back to
+    /// English for unknown values.
+    pub fn from_preference(preference: &str) -> Self {
+        preference.parse().unwrap_or_default()
+    }
+}
+
+impl FromStr for Locale {
+    type Err = UnsupportedLocale;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Locale::ALL
+            .iter()
+            .find(|locale| locale.as_str() == s)
+            .copied()
+            .ok_or(UnsupportedLocale)
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unsupported locale")]
+pub struct UnsupportedLocale;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_preference() {
+        assert_eq!(Locale::from_preference("en"), Locale::En);
+        assert_eq!(Locale::from_preference("de"), Locale::De);
+        assert_eq!(Locale::from_preference("fr"), Locale::Fr);
+        assert_eq!(Locale::from_preference("xx"), Locale::En);
+        assert_eq!(Locale::from_preference(""), Locale::En);
+    }
+}
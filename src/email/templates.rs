@@ -0,0 +1,95 @@
+//! Templates of the localized emails
+//!
+//! Each email has one template per language in the `templates/<locale>/`
+//! directory, which defines a `subject` and a `body` block. The English
+//! templates are required, all other languages are optional and fall back
+//! to English if they are missing.
+
+use crate::email::Locale;
+use minijinja::{Environment, ErrorKind, UndefinedBehavior, Value};
+use once_cell::sync::Lazy;
+
+macro_rules! templates {
+    ($($locale:literal => [$($name:literal),* $(,)?]),* $(,)?) => {
+        &[$($((
+            concat!($locale, "/", $name),
+            include_str!(concat!("templates/", $locale, "/", $name, ".txt.j2")),
+        ),)*)*]
+    };
+}
+
+static TEMPLATES: &[(&str, &str)] = templates! {
+    "en" => ["owner_invite", "publish_notification", "token_created", "token_exposed", "user_confirm"],
+    "de" => ["owner_invite", "publish_notification", "token_created", "token_exposed", "user_confirm"],
+    "fr" => ["owner_invite", "publish_notification", "token_created", "token_exposed", "user_confirm"],
+};
+
+static ENVIRONMENT: Lazy<Environment<'static>> = Lazy::new(|| {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    for (name, source) in TEMPLATES {
+        env.add_template(name, source)
+            .unwrap_or_else(|error| panic!("Failed to load email template {name}: {error}"));
+    }
+    env
+});
+
+/// Renders the `subject` and `body` blocks of the template with the given
+/// name in the given language.
+pub fn render(
+    name: &str,
+    locale: Locale,
+    context: Value,
+) -> Result<(String, String), minijinja::Error> {
+    let template = match ENVIRONMENT.get_template(&format!("{locale}/{name}")) {
+        Err(error) if error.kind() == ErrorKind::TemplateNotFound => {
+            ENVIRONMENT.get_template(&format!("{}/{name}", Locale::En))?
+        }
+        result => result?,
+    };
+
+    let mut state = template.eval_to_state(context)?;
+    let subject = state.render_block("subject")?;
+    let body = state.render_block("body")?;
+
+    Ok((subject, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minijinja::context;
+
+    #[test]
+    fn test_templates_have_english_fallback() {
+        for (name, _) in TEMPLATES {
+            let (_, name) = name.split_once('/').unwrap();
+            let fallback = format!("{}/{name}", Locale::En);
+            assert_ok!(ENVIRONMENT.get_template(&fallback));
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let context = context! {
+            user_name => "foo",
+            domain => "crates.io",
+            token => "secret123",
+        };
+
+        let (subject, body) = render("user_confirm", Locale::En, context.clone()).unwrap();
+        assert_eq!(subject, "Please confirm your email address");
+        assert_eq!(
+            body,
+            "Hello foo! Welcome to crates.io. Please click the\nlink below to verify your email address. Thank you!\n\nhttps://crates.io/confirm/secret123"
+        );
+
+        let (subject, _) = render("user_confirm", Locale::De, context).unwrap();
+        assert_eq!(subject, "Bitte bestätige deine E-Mail-Adresse");
+    }
+
+    #[test]
+    fn test_render_missing_variable() {
+        assert_err!(render("user_confirm", Locale::En, context! {}));
+    }
+}
@@ -0,0 +1,213 @@
+//! Serving HTTP connections
+//!
+//! `axum::serve()` does not allow configuring the connections, so the server
+//! binary uses this instead, which also takes care of terminating TLS if it
+//! is configured (see [`crate::tls`]).
+
+use crate::config::HttpServerConfig;
+use crate::tls::VerifiedClientCertificate;
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use http::{Request, Response};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio_rustls::TlsAcceptor;
+use tower::{Service, ServiceExt};
+
+/// How long to wait before accepting connections again after an error.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Accepts connections on the listener and serves them with the given
+/// service until `shutdown` becomes `true`.
+///
+/// If a [`TlsAcceptor`] is passed, TLS is terminated for all connections.
+///
+/// Once the shutdown was requested, no new connections are accepted and the
+/// function waits for the in-flight requests to finish.
+pub async fn serve<S>(
+    listener: TcpListener,
+    tls: Option<TlsAcceptor>,
+    service: S,
+    config: &HttpServerConfig,
+    mut shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()>
+where
+    S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>,
+    S: Clone + Send + 'static,
+    S::Future: Send,
+{
+    let builder = ConnectionBuilder::new(config);
+    let mut connections = JoinSet::new();
+
+    loop {
+        // Remove the tasks of closed connections, so that the set doesn't
+        // grow for the lifetime of the server
+        while connections.try_join_next().is_some() {}
+
+        let (stream, remote_addr) = tokio::select! {
+            result = listener.accept() => match result {
+                Ok(connection) => connection,
+                Err(error) => {
+                    // Errors like running out of file descriptors usually
+                    // persist for a moment, so retrying right away would spin
+                    warn!(%error, "Failed to accept connection");
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    continue;
+                }
+            },
+            _ = shutdown_requested(&mut shutdown) => break,
+        };
+
+        let tls = tls.clone();
+        let builder = builder.clone();
+        let service = service.clone();
+        let shutdown = shutdown.clone();
+
+        connections.spawn(async move {
+            let Some(tls) = tls else {
+                return builder
+                    .serve(stream, service, remote_addr, false, shutdown)
+                    .await;
+            };
+
+            let stream = match tls.accept(stream).await {
+                Ok(stream) => stream,
+                Err(error) => {
+                    debug!(%error, %remote_addr, "TLS handshake failed");
+                    return;
+                }
+            };
+
+            // With `allow_unauthenticated()` the handshake fails for invalid
+            // certificates, so any certificate that is present was verified.
+            let verified = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .is_some_and(|certs| !certs.is_empty());
+
+            builder
+                .serve(stream, service, remote_addr, verified, shutdown)
+                .await
+        });
+    }
+
+    while connections.join_next().await.is_some() {}
+
+    Ok(())
+}
+
+/// Serves HTTP/1 connections, or HTTP/1 and HTTP/2 connections if HTTP/2 is
+/// enabled.
+#[derive(Clone)]
+enum ConnectionBuilder {
+    Http1(http1::Builder),
+    Auto(auto::Builder<TokioExecutor>),
+}
+
+impl ConnectionBuilder {
+    fn new(config: &HttpServerConfig) -> Self {
+        if !config.http2 {
+            let mut builder = http1::Builder::new();
+            builder
+                .timer(TokioTimer::new())
+                .keep_alive(config.keep_alive)
+                .header_read_timeout(config.header_read_timeout);
+
+            return Self::Http1(builder);
+        }
+
+        let mut builder = auto::Builder::new(TokioExecutor::new());
+        builder
+            .http1()
+            .timer(TokioTimer::new())
+            .keep_alive(config.keep_alive)
+            .header_read_timeout(config.header_read_timeout);
+        builder
+            .http2()
+            .timer(TokioTimer::new())
+            .keep_alive_interval(config.http2_keep_alive_interval)
+            .keep_alive_timeout(config.http2_keep_alive_timeout)
+            .max_concurrent_streams(config.http2_max_concurrent_streams);
+
+        Self::Auto(builder)
+    }
+
+    /// Serves a single connection.
+    ///
+    /// `verified` is whether the client presented a valid TLS client
+    /// certificate, which is passed on as a [`VerifiedClientCertificate`]
+    /// request extension.
+    async fn serve<I, S>(
+        &self,
+        io: I,
+        service: S,
+        remote_addr: SocketAddr,
+        verified: bool,
+        mut shutdown: watch::Receiver<bool>,
+    ) where
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        S: Service<Request<Body>, Response = Response<Body>, Error = Infallible>,
+        S: Clone + Send + 'static,
+        S::Future: Send,
+    {
+        let service = service.map_request(move |req: Request<Incoming>| {
+            let mut req = req.map(Body::new);
+            req.extensions_mut().insert(ConnectInfo(remote_addr));
+            if verified {
+                req.extensions_mut().insert(VerifiedClientCertificate);
+            }
+            req
+        });
+
+        let io = TokioIo::new(io);
+        let service = TowerToHyperService::new(service);
+
+        // Once the shutdown was requested, the connections finish their
+        // in-flight requests, but don't accept any new requests.
+        let result = match self {
+            Self::Http1(builder) => {
+                let connection = builder.serve_connection(io, service).with_upgrades();
+                tokio::pin!(connection);
+
+                tokio::select! {
+                    result = connection.as_mut() => result.map_err(Into::into),
+                    _ = shutdown_requested(&mut shutdown) => {
+                        connection.as_mut().graceful_shutdown();
+                        connection.await.map_err(Into::into)
+                    }
+                }
+            }
+            Self::Auto(builder) => {
+                let connection = builder.serve_connection_with_upgrades(io, service);
+                tokio::pin!(connection);
+
+                tokio::select! {
+                    result = connection.as_mut() => result,
+                    _ = shutdown_requested(&mut shutdown) => {
+                        connection.as_mut().graceful_shutdown();
+                        connection.await
+                    }
+                }
+            }
+        };
+
+        if let Err::<_, Box<dyn std::error::Error + Send + Sync>>(error) = result {
+            debug!(%error, %remote_addr, "Failed to serve connection");
+        }
+    }
+}
+
+async fn shutdown_requested(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|requested| *requested).await;
+}
@@ -0,0 +1,205 @@
+//! Signatures for the files of the sparse index
+//!
+//! Every index file that is uploaded to the sparse index is accompanied by a
+//! `.sig` file, which contains Ed25519 signatures of the file content. The
+//! public keys are published at the `/.well-known/index-signing-keys.json`
+//! endpoint, so that clients and mirrors can verify the integrity of the
+//! index.
+//!
+//! To rotate a key, the new key is added to the configuration while the old
+//! key is still in use, and the old key gets an expiry date. Until then, all
+//! files are signed with both keys, which gives clients time to pick up the
+//! new public key.
+
+use anyhow::{anyhow, bail, Context};
+use base64::{engine::general_purpose, Engine};
+use chrono::{DateTime, Utc};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use std::fmt::{Debug, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+pub const ALGORITHM: &str = "ed25519";
+
+/// An Ed25519 key that is used to sign index files.
+#[derive(Clone)]
+pub struct IndexSigningKey {
+    pub key_id: String,
+    key_pair: Arc<Ed25519KeyPair>,
+    /// The key is not used for signing anymore after this date.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl IndexSigningKey {
+    pub fn from_seed(
+        key_id: impl Into<String>,
+        seed: &[u8],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Self> {
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(seed)
+            .map_err(|_| anyhow!("Invalid Ed25519 seed, expected 32 bytes"))?;
+
+        Ok(Self {
+            key_id: key_id.into(),
+            key_pair: Arc::new(key_pair),
+            expires_at,
+        })
+    }
+
+    /// Returns the base64 encoded public key.
+    pub fn public_key(&self) -> String {
+        general_purpose::STANDARD.encode(self.key_pair.public_key())
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        let expired = self.expires_at.is_some_and(|expires_at| now >= expires_at);
+        !expired
+    }
+
+    /// Returns the base64 encoded signature of the content.
+    pub fn sign(&self, content: &[u8]) -> String {
+        general_purpose::STANDARD.encode(self.key_pair.sign(content))
+    }
+}
+
+impl Debug for IndexSigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexSigningKey")
+            .field("key_id", &self.key_id)
+            .field("public_key", &self.public_key())
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// Parses keys in the `<key id>:<base64 encoded seed>[@<RFC 3339 expiry date>]`
+/// format, e.g. `2024-05:AAAA...AAAA=@2024-06-01T00:00:00Z`.
+impl FromStr for IndexSigningKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, expires_at) = match s.split_once('@') {
+            Some((key, expires_at)) => {
+                let expires_at = DateTime::parse_from_rfc3339(expires_at)
+                    .context("Failed to parse the expiry date")?;
+
+                (key, Some(expires_at.with_timezone(&Utc)))
+            }
+            None => (s, None),
+        };
+
+        let Some((key_id, seed)) = key.split_once(':') else {
+            bail!("Expected `<key id>:<seed>`");
+        };
+
+        if key_id.is_empty() {
+            bail!("The key id must not be empty");
+        }
+
+        let seed = general_purpose::STANDARD
+            .decode(seed)
+            .context("Failed to decode the seed")?;
+
+        Self::from_seed(key_id, &seed, expires_at)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexSignatures {
+    pub signatures: Vec<IndexSignature>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IndexSignature {
+    pub key_id: String,
+    pub algorithm: String,
+    pub signature: String,
+}
+
+/// Signs the content of an index file with all keys that have not expired
+/// yet and returns the content of the corresponding `.sig` file.
+///
+/// Returns `None` if there are no active keys.
+pub fn sign_index_file(
+    keys: &[IndexSigningKey],
+    content: &str,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Option<String>> {
+    let signatures = keys
+        .iter()
+        .filter(|key| key.is_active(now))
+        .map(|key| IndexSignature {
+            key_id: key.key_id.clone(),
+            algorithm: ALGORITHM.to_string(),
+            signature: key.sign(content.as_bytes()),
+        })
+        .collect::<Vec<_>>();
+
+    if signatures.is_empty() {
+        return Ok(None);
+    }
+
+    let signatures = serde_json::to_string(&IndexSignatures { signatures })?;
+    Ok(Some(signatures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use ring::signature::{UnparsedPublicKey, ED25519};
+
+    fn key(key_id: &str, seed: u8, expires_at: Option<DateTime<Utc>>) -> IndexSigningKey {
+        IndexSigningKey::from_seed(key_id, &[seed; 32], expires_at).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let seed = general_purpose::STANDARD.encode([1; 32]);
+
+        let key = assert_ok!(IndexSigningKey::from_str(&format!("foo:{seed}")));
+        assert_eq!(key.key_id, "foo");
+        assert_none!(key.expires_at);
+
+        let s = format!("foo:{seed}@2024-06-01T00:00:00Z");
+        let key = assert_ok!(IndexSigningKey::from_str(&s));
+        let expected = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        assert_some_eq!(key.expires_at, expected);
+
+        assert_err!(IndexSigningKey::from_str(&seed));
+        assert_err!(IndexSigningKey::from_str(&format!(":{seed}")));
+        assert_err!(IndexSigningKey::from_str("foo:not-base64"));
+        assert_err!(IndexSigningKey::from_str("foo:AAAA"));
+    }
+
+    #[test]
+    fn test_sign_index_file() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let content = "{\"name\":\"foo\"}\n";
+
+        assert_none!(sign_index_file(&[], content, now).unwrap());
+
+        let keys = [
+            key("new", 1, None),
+            key("old", 2, Some(now + chrono::Duration::days(1))),
+            key("expired", 3, Some(now)),
+        ];
+
+        let signatures = sign_index_file(&keys, content, now).unwrap().unwrap();
+        let signatures: IndexSignatures = serde_json::from_str(&signatures).unwrap();
+
+        let key_ids = signatures.signatures.iter().map(|s| s.key_id.as_str());
+        assert_eq!(key_ids.collect::<Vec<_>>(), ["new", "old"]);
+
+        for (signature, key) in signatures.signatures.iter().zip(&keys) {
+            assert_eq!(signature.algorithm, ALGORITHM);
+
+            let public_key = general_purpose::STANDARD.decode(key.public_key()).unwrap();
+            let signature = general_purpose::STANDARD
+                .decode(&signature.signature)
+                .unwrap();
+            let public_key = UnparsedPublicKey::new(&ED25519, public_key);
+            assert_ok!(public_key.verify(content.as_bytes(), &signature));
+        }
+    }
+}
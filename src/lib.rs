@@ -29,11 +29,16 @@ use tikv_jemallocator::Jemalloc;
 static ALLOC: Jemalloc = Jemalloc;
 
 pub mod admin;
+pub mod api_usage;
 mod app;
+pub mod audit;
 pub mod auth;
 pub mod boot;
+pub mod cdn_fallback;
 pub mod certs;
+pub mod challenge;
 pub mod ci;
+pub mod clock;
 pub mod cloudfront;
 pub mod config;
 pub mod controllers;
@@ -42,15 +47,20 @@ pub mod email;
 pub mod external_urls;
 pub mod fastly;
 pub mod headers;
+pub mod http_server;
+pub mod index_signing;
 mod licenses;
+pub mod lookup_cache;
 pub mod metrics;
 pub mod middleware;
 pub mod models;
 pub mod rate_limiter;
 mod real_ip;
 mod router;
+pub mod safe_fetch;
 pub mod schema;
 pub mod sentry;
+pub mod shutdown;
 pub mod sql;
 pub mod sqs;
 pub mod ssh;
@@ -58,7 +68,9 @@ pub mod storage;
 pub mod tasks;
 pub mod team_repo;
 mod test_util;
+pub mod tls;
 pub mod typosquat;
+pub mod upstream;
 pub mod util;
 pub mod views;
 pub mod worker;
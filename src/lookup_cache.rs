@@ -0,0 +1,494 @@
+//! Read-through caches for the database ids of crates and versions
+//!
+//! Many endpoints only need the id of the crate or version in their path to
+//! do their work, and looking these ids up by name is the most frequent
+//! query on the download path. The ids never change for a given name (unless
+//! the crate or version is deleted), so they are cached in memory for a
+//! configurable amount of time.
+//!
+//! Only successful lookups are cached. The entries are invalidated when a
+//! crate or version is published or yanked through this instance; other
+//! changes (like deletions through the admin tools) are picked up once the
+//! entries expire.
+//!
+//! The set of quarantined versions is cached the same way, since it has to be
+//! checked for every download. The same goes for the runtime settings, which
+//! are checked for every request.
+
+use crate::config;
+use crate::metrics::InstanceMetrics;
+use crate::models::{BlockedRoute, Crate, ExcludedCrateName, Version, VersionQuarantine};
+use crate::schema::{crates, versions};
+use crate::util::errors::{crate_not_found, version_not_found, AppResult};
+use diesel::prelude::*;
+use parking_lot::Mutex;
+use prometheus::IntCounter;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A size-bounded cache whose entries expire after a fixed amount of time.
+///
+/// When the cache is full, the oldest entries are evicted first.
+pub struct LookupCache<K, V> {
+    ttl: Duration,
+    max_size: usize,
+    inner: Mutex<Inner<K, V>>,
+    hits: IntCounter,
+    misses: IntCounter,
+}
+
+struct Inner<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    /// The keys in insertion order, which is also the order in which they
+    /// expire. May contain keys that were invalidated or re-inserted since,
+    /// which is why the insertion time is stored too.
+    queue: VecDeque<(K, Instant)>,
+}
+
+struct Entry<V> {
+    value: V,
+    inserted_at: Instant,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LookupCache<K, V> {
+    /// Creates a new cache, which records its hits and misses in the
+    /// `lookup_cache_*` instance metrics with the given `name` label.
+    pub fn new(name: &str, max_size: u64, ttl: Duration, metrics: &InstanceMetrics) -> Self {
+        Self {
+            ttl,
+            max_size: max_size as usize,
+            inner: Mutex::new(Inner {
+                entries: HashMap::new(),
+                queue: VecDeque::new(),
+            }),
+            hits: metrics.lookup_cache_hits.with_label_values(&[name]),
+            misses: metrics.lookup_cache_misses.with_label_values(&[name]),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let now = Instant::now();
+        let inner = self.inner.lock();
+
+        let value = inner
+            .entries
+            .get(key)
+            .filter(|entry| now.duration_since(entry.inserted_at) < self.ttl)
+            .map(|entry| entry.value.clone());
+
+        match value {
+            Some(_) => self.hits.inc(),
+            None => self.misses.inc(),
+        }
+
+        value
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        if self.max_size == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut inner = self.inner.lock();
+        inner.evict(now, self.ttl, self.max_size - 1);
+        inner.queue.push_back((key.clone(), now));
+        inner.entries.insert(
+            key,
+            Entry {
+                value,
+                inserted_at: now,
+            },
+        );
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        self.inner.lock().entries.remove(key);
+    }
+
+    /// Removes all entries whose key matches the `predicate`.
+    pub fn invalidate_matching(&self, predicate: impl Fn(&K) -> bool) {
+        self.inner.lock().entries.retain(|key, _| !predicate(key));
+    }
+
+    /// Returns the cached value for `key`, or loads it with `load` and caches
+    /// it if the lookup was successful.
+    pub fn get_or_try_insert_with<E>(
+        &self,
+        key: K,
+        load: impl FnOnce() -> Result<V, E>,
+    ) -> Result<V, E> {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+
+        let value = load()?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// Returns the number of entries, including expired entries that were
+    /// not evicted yet.
+    pub fn len(&self) -> usize {
+        self.inner.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Hash + Eq, V> Inner<K, V> {
+    /// Removes expired entries, and the oldest entries until there are at
+    /// most `max_len` entries left.
+    fn evict(&mut self, now: Instant, ttl: Duration, max_len: usize) {
+        while let Some((_, inserted_at)) = self.queue.front() {
+            let expired = now.duration_since(*inserted_at) >= ttl;
+            if !expired && self.entries.len() <= max_len {
+                break;
+            }
+
+            let Some((key, inserted_at)) = self.queue.pop_front() else {
+                break;
+            };
+
+            let is_current = self
+                .entries
+                .get(&key)
+                .is_some_and(|entry| entry.inserted_at == inserted_at);
+
+            if is_current {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+/// Crate names are matched ignoring case and the difference between `-` and
+/// `_`, like in [`Crate::by_name`].
+fn canonical_crate_name(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+/// Caches the ids of crates by their name.
+pub struct CrateIdCache(LookupCache<String, i32>);
+
+impl CrateIdCache {
+    pub fn new(config: &config::Server, metrics: &InstanceMetrics) -> Self {
+        let size = config.crate_id_cache_size;
+        let ttl = config.crate_id_cache_ttl;
+        Self(LookupCache::new("crate_id", size, ttl, metrics))
+    }
+
+    /// Returns the id of the crate, or a "crate not found" error.
+    pub fn get_or_load(&self, conn: &mut PgConnection, crate_name: &str) -> AppResult<i32> {
+        self.0
+            .get_or_try_insert_with(canonical_crate_name(crate_name), || {
+                Crate::by_name(crate_name)
+                    .select(crates::id)
+                    .first(conn)
+                    .optional()?
+                    .ok_or_else(|| crate_not_found(crate_name))
+            })
+    }
+
+    pub fn invalidate(&self, crate_name: &str) {
+        self.0.invalidate(&canonical_crate_name(crate_name));
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Caches the ids of versions by their crate name and version number.
+pub struct VersionIdCache(LookupCache<(String, String), i32>);
+
+impl VersionIdCache {
+    pub fn new(config: &config::Server, metrics: &InstanceMetrics) -> Self {
+        let size = config.version_id_cache_size;
+        let ttl = config.version_id_cache_ttl;
+        Self(LookupCache::new("version_id", size, ttl, metrics))
+    }
+
+    /// Returns the id of the version, or a "crate not found" or "version not
+    /// found" error. Versions whose scheduled release is still pending are
+    /// not found.
+    pub fn get_or_load(
+        &self,
+        conn: &mut PgConnection,
+        crate_name: &str,
+        version: &str,
+    ) -> AppResult<i32> {
+        let key = (canonical_crate_name(crate_name), version.to_string());
+        self.0.get_or_try_insert_with(key, || {
+            let crate_id: i32 = Crate::by_name(crate_name)
+                .select(crates::id)
+                .first(conn)
+                .optional()?
+                .ok_or_else(|| crate_not_found(crate_name))?;
+
+            versions::table
+                .filter(versions::crate_id.eq(crate_id))
+                .filter(versions::num.eq(version))
+                .filter(Version::is_released())
+                .select(versions::id)
+                .first(conn)
+                .optional()?
+                .ok_or_else(|| version_not_found(crate_name, version))
+        })
+    }
+
+    pub fn invalidate(&self, crate_name: &str, version: &str) {
+        let key = (canonical_crate_name(crate_name), version.to_string());
+        self.0.invalidate(&key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The quarantine is checked on every download, but quarantines are rare and
+/// usually urgent, so the set is reloaded frequently.
+const QUARANTINED_VERSIONS_TTL: Duration = Duration::from_secs(60);
+
+/// Caches the crate names and version numbers of all quarantined versions.
+pub struct QuarantinedVersionsCache(LookupCache<(), Arc<HashSet<(String, String)>>>);
+
+impl QuarantinedVersionsCache {
+    pub fn new(metrics: &InstanceMetrics) -> Self {
+        let ttl = QUARANTINED_VERSIONS_TTL;
+        Self(LookupCache::new("quarantined_versions", 1, ttl, metrics))
+    }
+
+    /// Returns whether the version is quarantined, or `None` if the set of
+    /// quarantined versions has to be loaded with [`Self::load`] first.
+    pub fn get(&self, crate_name: &str, version: &str) -> Option<bool> {
+        let quarantined = self.0.get(&())?;
+        let key = (canonical_crate_name(crate_name), version.to_string());
+        Some(quarantined.contains(&key))
+    }
+
+    /// Loads the set of quarantined versions from the database.
+    pub fn load(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        let quarantined = VersionQuarantine::all_active(conn)?
+            .into_iter()
+            .map(|q| (canonical_crate_name(&q.crate_name), q.version_num))
+            .collect();
+
+        self.0.insert((), Arc::new(quarantined));
+        Ok(())
+    }
+
+    /// Returns whether the version is quarantined, loading the set of
+    /// quarantined versions if necessary.
+    pub fn is_quarantined(
+        &self,
+        conn: &mut PgConnection,
+        crate_name: &str,
+        version: &str,
+    ) -> QueryResult<bool> {
+        if let Some(quarantined) = self.get(crate_name, version) {
+            return Ok(quarantined);
+        }
+
+        self.load(conn)?;
+        Ok(self.get(crate_name, version).unwrap_or_default())
+    }
+
+    pub fn invalidate(&self) {
+        self.0.invalidate(&());
+    }
+}
+
+/// Runtime settings are changed rarely, but have to take effect on all
+/// instances quickly during incidents.
+const RUNTIME_SETTINGS_TTL: Duration = Duration::from_secs(30);
+
+/// The settings that administrators can change at runtime through the admin
+/// API.
+#[derive(Debug, Default)]
+pub struct RuntimeSettings {
+    /// The HTTP route patterns that are temporarily blocked.
+    pub blocked_routes: HashSet<String>,
+    /// The names of the crates that are excluded from the most downloaded
+    /// lists of the summary endpoint.
+    pub excluded_crate_names: Vec<String>,
+}
+
+/// Caches the [`RuntimeSettings`].
+///
+/// Unlike the other caches, the expired settings are kept, so that they can
+/// still be used if reloading them fails, e.g. because the database is
+/// overloaded. Otherwise, blocked routes would be unblocked during exactly
+/// the incidents they were blocked for.
+pub struct RuntimeSettingsCache {
+    ttl: Duration,
+    inner: Mutex<Option<(Arc<RuntimeSettings>, Instant)>>,
+    hits: IntCounter,
+    misses: IntCounter,
+}
+
+impl RuntimeSettingsCache {
+    pub fn new(metrics: &InstanceMetrics) -> Self {
+        let name = "runtime_settings";
+        Self {
+            ttl: RUNTIME_SETTINGS_TTL,
+            inner: Mutex::new(None),
+            hits: metrics.lookup_cache_hits.with_label_values(&[name]),
+            misses: metrics.lookup_cache_misses.with_label_values(&[name]),
+        }
+    }
+
+    /// Returns the settings, or `None` if they have to be loaded with
+    /// [`Self::load`] first.
+    pub fn get(&self) -> Option<Arc<RuntimeSettings>> {
+        let now = Instant::now();
+        let settings = self
+            .inner
+            .lock()
+            .as_ref()
+            .filter(|(_, loaded_at)| now.duration_since(*loaded_at) < self.ttl)
+            .map(|(settings, _)| settings.clone());
+
+        match settings {
+            Some(_) => self.hits.inc(),
+            None => self.misses.inc(),
+        }
+
+        settings
+    }
+
+    /// Loads the settings from the database.
+    pub fn load(&self, conn: &mut PgConnection) -> QueryResult<Arc<RuntimeSettings>> {
+        let blocked_routes = BlockedRoute::all(conn)?
+            .into_iter()
+            .map(|blocked_route| blocked_route.route)
+            .collect();
+
+        let excluded_crate_names = ExcludedCrateName::all(conn)?
+            .into_iter()
+            .map(|excluded| excluded.name)
+            .collect();
+
+        let settings = Arc::new(RuntimeSettings {
+            blocked_routes,
+            excluded_crate_names,
+        });
+
+        *self.inner.lock() = Some((settings.clone(), Instant::now()));
+        Ok(settings)
+    }
+
+    /// Returns the settings, loading them if necessary.
+    pub fn get_or_load(&self, conn: &mut PgConnection) -> QueryResult<Arc<RuntimeSettings>> {
+        match self.get() {
+            Some(settings) => Ok(settings),
+            None => self.load(conn),
+        }
+    }
+
+    /// Returns the expired settings after they could not be reloaded, and
+    /// keeps them for another TTL, so that the database is not queried for
+    /// every request in the meantime.
+    pub fn keep_expired(&self) -> Option<Arc<RuntimeSettings>> {
+        let mut inner = self.inner.lock();
+        let (settings, loaded_at) = inner.as_mut()?;
+        *loaded_at = Instant::now();
+        Some(settings.clone())
+    }
+
+    pub fn invalidate(&self) {
+        *self.inner.lock() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_size: u64, ttl: Duration) -> LookupCache<&'static str, i32> {
+        let metrics = InstanceMetrics::new().unwrap();
+        LookupCache::new("test", max_size, ttl, &metrics)
+    }
+
+    #[test]
+    fn test_get_or_try_insert_with() {
+        let cache = cache(10, Duration::from_secs(60));
+
+        let result = cache.get_or_try_insert_with("foo", || Err::<i32, _>("not found"));
+        assert_err_eq!(result, "not found");
+        assert!(cache.is_empty());
+
+        let result = cache.get_or_try_insert_with("foo", || Ok::<_, ()>(1));
+        assert_ok_eq!(result, 1);
+
+        let result = cache.get_or_try_insert_with("foo", || Ok::<_, ()>(2));
+        assert_ok_eq!(result, 1);
+
+        assert_eq!(cache.hits.get(), 1);
+        assert_eq!(cache.misses.get(), 2);
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let cache = cache(10, Duration::from_secs(60));
+
+        cache.insert("foo", 1);
+        cache.insert("bar", 2);
+        cache.invalidate(&"foo");
+
+        assert_none!(cache.get(&"foo"));
+        assert_some_eq!(cache.get(&"bar"), 2);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let cache = cache(10, Duration::ZERO);
+
+        cache.insert("foo", 1);
+        assert_none!(cache.get(&"foo"));
+
+        cache.insert("bar", 2);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_max_size() {
+        let cache = cache(2, Duration::from_secs(60));
+
+        cache.insert("foo", 1);
+        cache.insert("bar", 2);
+        cache.insert("baz", 3);
+
+        assert_eq!(cache.len(), 2);
+        assert_none!(cache.get(&"foo"));
+        assert_some_eq!(cache.get(&"bar"), 2);
+        assert_some_eq!(cache.get(&"baz"), 3);
+
+        // Re-inserting a key moves it to the back of the eviction queue
+        cache.invalidate(&"bar");
+        cache.insert("bar", 4);
+        cache.insert("qux", 5);
+
+        assert_none!(cache.get(&"baz"));
+        assert_some_eq!(cache.get(&"bar"), 4);
+        assert_some_eq!(cache.get(&"qux"), 5);
+    }
+
+    #[test]
+    fn test_canonical_crate_name() {
+        assert_eq!(canonical_crate_name("Foo-Bar_baz"), "foo_bar_baz");
+    }
+}
@@ -34,6 +34,8 @@ metrics! {
         pub database_time_to_obtain_connection: HistogramVec["pool"],
         /// Number of times the database pool was unavailable and the fallback was used
         pub database_fallback_used: IntGaugeVec["pool"],
+        /// Time spent in the closures that run with a connection from the pool
+        pub database_interact_duration: HistogramVec["pool"],
 
         /// Number of requests processed by this instance
         pub requests_total: IntCounter,
@@ -44,6 +46,28 @@ metrics! {
         pub response_times: HistogramVec["endpoint"],
         /// Nmber of responses per status code
         pub responses_by_status_code_total: IntCounterVec["status"],
+        /// Number of requests that were aborted because they took too long, per route group
+        pub request_timeouts_total: IntCounterVec["group"],
+        /// Number of requests that used a deprecated route or query parameter, per deprecation
+        pub deprecated_requests_total: IntCounterVec["deprecation"],
+        /// Number of requests with an API token, per form of the `Authorization` header
+        pub token_auth_header_forms_total: IntCounterVec["form"],
+        /// Number of failed authentication attempts
+        pub auth_failures_total: IntCounter,
+        /// Number of authentication attempts that were rejected because of previous failures, per key kind
+        pub auth_failures_rate_limited_total: IntCounterVec["key"],
+
+        /// Number of lookups that were answered from an in-memory lookup cache
+        pub lookup_cache_hits: IntCounterVec["cache"],
+        /// Number of lookups that were not found in an in-memory lookup cache
+        pub lookup_cache_misses: IntCounterVec["cache"],
+        /// Number of entries in an in-memory lookup cache
+        lookup_cache_entries: IntGaugeVec["cache"],
+
+        /// Whether crate downloads are redirected to a fallback location because the CDN is unavailable
+        pub cdn_fallback_active: IntGauge,
+        /// Number of crate downloads that were redirected to a fallback location, per target
+        pub cdn_fallback_redirects_total: IntCounterVec["target"],
     }
 
     // All instance metrics will be prefixed with this namespace.
@@ -58,7 +82,19 @@ impl InstanceMetrics {
             self.refresh_pool_stats("async_follower", follower)?;
         }
 
-        Ok(self.registry.gather())
+        // Lookup cache stats
+        self.lookup_cache_entries
+            .get_metric_with_label_values(&["crate_id"])?
+            .set(app.crate_id_cache.len() as i64);
+        self.lookup_cache_entries
+            .get_metric_with_label_values(&["version_id"])?
+            .set(app.version_id_cache.len() as i64);
+
+        // Metrics of shared utilities like `util::retry` are registered in
+        // the default registry.
+        let mut metrics = self.registry.gather();
+        metrics.extend(prometheus::gather());
+        Ok(metrics)
     }
 
     fn refresh_pool_stats(&self, name: &str, pool: &Pool) -> prometheus::Result<()> {
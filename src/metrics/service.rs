@@ -10,11 +10,21 @@
 //! As a rule of thumb, if the metric is not straight up fetched from the database it's probably an
 //! instance-level metric, and you should add it to `src/metrics/instance.rs`.
 
+use crate::config;
 use crate::metrics::macros::metrics;
-use crate::schema::{background_jobs, crates, versions};
+use crate::models::CrateOwnerInvitation;
+use crate::schema::{
+    background_job_incidents, background_job_leases, background_job_lock_waits, background_jobs,
+    cloudfront_invalidation_queue, crates, versions,
+};
 use crate::util::errors::AppResult;
-use diesel::{dsl::count_star, prelude::*, PgConnection};
-use prometheus::{proto::MetricFamily, IntGauge, IntGaugeVec};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    dsl::{count_star, now},
+    prelude::*,
+    PgConnection,
+};
+use prometheus::{proto::MetricFamily, GaugeVec, IntGauge, IntGaugeVec};
 
 metrics! {
     pub struct ServiceMetrics {
@@ -24,6 +34,22 @@ metrics! {
         versions_total: IntGauge,
         /// Number of queued up background jobs
         background_jobs: IntGaugeVec["priority", "job"],
+        /// Number of running background jobs whose lease has expired
+        background_job_expired_leases: IntGauge,
+        /// Number of background jobs that were recovered after their lease expired, by job and action
+        background_job_incidents_total: IntGaugeVec["job", "action"],
+        /// Number of times background jobs acquired their lock, by job
+        background_job_lock_acquisitions_total: IntGaugeVec["job"],
+        /// Number of times background jobs had to wait for their lock, by job
+        background_job_lock_contentions_total: IntGaugeVec["job"],
+        /// Total time in seconds that background jobs waited for their lock, by job
+        background_job_lock_wait_seconds_total: GaugeVec["job"],
+        /// Number of crate owner invitations, by state (pending or expired)
+        crate_owner_invitations: IntGaugeVec["state"],
+        /// Number of paths waiting to be invalidated on CloudFront
+        cloudfront_invalidation_queue_length: IntGauge,
+        /// Age in seconds of the oldest path waiting to be invalidated on CloudFront
+        cloudfront_invalidation_queue_age_seconds: IntGauge,
     }
 
     // All service metrics will be prefixed with this namespace.
@@ -31,7 +57,11 @@ metrics! {
 }
 
 impl ServiceMetrics {
-    pub(crate) fn gather(&self, conn: &mut PgConnection) -> AppResult<Vec<MetricFamily>> {
+    pub(crate) fn gather(
+        &self,
+        config: &config::Server,
+        conn: &mut PgConnection,
+    ) -> AppResult<Vec<MetricFamily>> {
         self.crates_total
             .set(crates::table.select(count_star()).first(conn)?);
         self.versions_total
@@ -52,6 +82,72 @@ impl ServiceMetrics {
                 .set(count);
         }
 
+        self.background_job_expired_leases.set(
+            background_job_leases::table
+                .filter(background_job_leases::expires_at.lt(now))
+                .select(count_star())
+                .first(conn)?,
+        );
+
+        let incidents = background_job_incidents::table
+            .group_by((
+                background_job_incidents::job_type,
+                background_job_incidents::action,
+            ))
+            .select((
+                background_job_incidents::job_type,
+                background_job_incidents::action,
+                count_star(),
+            ))
+            .load::<(String, String, i64)>(conn)?;
+        for (job, action, count) in incidents {
+            self.background_job_incidents_total
+                .get_metric_with_label_values(&[&job, &action])?
+                .set(count);
+        }
+
+        let lock_waits = background_job_lock_waits::table
+            .select((
+                background_job_lock_waits::job_type,
+                background_job_lock_waits::acquisitions,
+                background_job_lock_waits::contentions,
+                background_job_lock_waits::wait_seconds,
+            ))
+            .load::<(String, i64, i64, f64)>(conn)?;
+        for (job, acquisitions, contentions, wait_seconds) in lock_waits {
+            self.background_job_lock_acquisitions_total
+                .get_metric_with_label_values(&[&job])?
+                .set(acquisitions);
+            self.background_job_lock_contentions_total
+                .get_metric_with_label_values(&[&job])?
+                .set(contentions);
+            self.background_job_lock_wait_seconds_total
+                .get_metric_with_label_values(&[&job])?
+                .set(wait_seconds);
+        }
+
+        let invitations = CrateOwnerInvitation::counts(conn, config, Utc::now().naive_utc())?;
+        self.crate_owner_invitations
+            .get_metric_with_label_values(&["pending"])?
+            .set(invitations.pending);
+        self.crate_owner_invitations
+            .get_metric_with_label_values(&["expired"])?
+            .set(invitations.expired);
+
+        let queue_length = cloudfront_invalidation_queue::table
+            .select(count_star())
+            .first(conn)?;
+        let queue_age = cloudfront_invalidation_queue::table
+            .select(cloudfront_invalidation_queue::created_at)
+            .order(cloudfront_invalidation_queue::id)
+            .first::<NaiveDateTime>(conn)
+            .optional()?
+            .map(|created_at| (Utc::now().naive_utc() - created_at).num_seconds())
+            .unwrap_or_default();
+        self.cloudfront_invalidation_queue_length.set(queue_length);
+        self.cloudfront_invalidation_queue_age_seconds
+            .set(queue_age);
+
         Ok(self.registry.gather())
     }
 }
@@ -1,13 +1,22 @@
+pub mod api_quota;
 pub mod app;
 mod block_traffic;
 pub mod cargo_compat;
 mod common_headers;
+pub mod concurrency_limit;
+pub mod deadline;
 mod debug;
+mod deprecation;
+pub mod download_rate_limit;
 mod ember_html;
 pub mod log_request;
 pub mod normalize_path;
+pub mod problem_json;
 pub mod real_ip;
+mod request_id;
+pub mod request_timeout;
 mod require_user_agent;
+pub mod sentry_context;
 pub mod session;
 mod static_or_continue;
 mod update_metrics;
@@ -17,12 +26,10 @@ use axum::middleware::{from_fn, from_fn_with_state};
 use axum::Router;
 use axum_extra::either::Either;
 use axum_extra::middleware::option_layer;
-use std::time::Duration;
 use tower::layer::util::Identity;
 use tower_http::add_extension::AddExtensionLayer;
 use tower_http::catch_panic::CatchPanicLayer;
 use tower_http::compression::{CompressionLayer, CompressionLevel};
-use tower_http::timeout::{RequestBodyTimeoutLayer, TimeoutLayer};
 
 use crate::app::AppState;
 use crate::Env;
@@ -40,8 +47,17 @@ pub fn apply_axum_middleware(state: AppState, router: Router<()>) -> Router {
 
     let middlewares_1 = tower::ServiceBuilder::new()
         .layer(sentry_tower::NewSentryLayer::new_from_top())
+        .layer(from_fn(sentry_context::scrub_sensitive_headers))
         .layer(sentry_tower::SentryHttpLayer::with_transaction())
-        .layer(from_fn(self::real_ip::middleware))
+        .layer(from_fn(sentry_context::restore_sensitive_headers))
+        .layer(from_fn(sentry_context::middleware))
+        .layer(from_fn(request_id::middleware))
+        // Wraps the other middlewares, so that their errors are negotiated too
+        .layer(from_fn(problem_json::middleware))
+        .layer(from_fn_with_state(
+            state.config.client_ip,
+            self::real_ip::middleware,
+        ))
         .layer(from_fn(log_request::log_requests))
         .layer(CatchPanicLayer::new())
         .layer(from_fn_with_state(
@@ -65,6 +81,12 @@ pub fn apply_axum_middleware(state: AppState, router: Router<()>) -> Router {
             require_user_agent::require_user_agent,
         ))
         .layer(from_fn_with_state(state.clone(), block_traffic::middleware))
+        .layer(from_fn_with_state(
+            state.clone(),
+            concurrency_limit::middleware,
+        ))
+        .layer(from_fn_with_state(state.clone(), deprecation::middleware))
+        .layer(from_fn(api_quota::middleware))
         .layer(from_fn_with_state(
             state.clone(),
             common_headers::add_common_headers,
@@ -83,8 +105,10 @@ pub fn apply_axum_middleware(state: AppState, router: Router<()>) -> Router {
     router
         .layer(middlewares_2)
         .layer(middlewares_1)
-        .layer(TimeoutLayer::new(Duration::from_secs(30)))
-        .layer(RequestBodyTimeoutLayer::new(Duration::from_secs(30)))
+        .layer(from_fn_with_state(
+            state.clone(),
+            request_timeout::middleware,
+        ))
         .layer(CompressionLayer::new().quality(CompressionLevel::Fastest))
 }
 
@@ -0,0 +1,44 @@
+//! Warnings for clients that exceed their monthly API quota
+//!
+//! The quotas are counted while authenticating the request (see
+//! [`crate::api_usage`]), so this middleware only provides the
+//! [`ApiQuotaWarning`] request extension in which the authentication stores
+//! the warning, and copies the warning into the `X-Api-Quota-Warning`
+//! response header.
+
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use http::{HeaderName, HeaderValue};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+static X_API_QUOTA_WARNING: HeaderName = HeaderName::from_static("x-api-quota-warning");
+
+/// The quota warning of the current request, if any.
+#[derive(Clone, Debug, Default)]
+pub struct ApiQuotaWarning(Arc<Mutex<Option<String>>>);
+
+impl ApiQuotaWarning {
+    pub fn set(&self, warning: String) {
+        *self.0.lock() = Some(warning);
+    }
+}
+
+pub async fn middleware(mut req: Request, next: Next) -> Response {
+    let warning = ApiQuotaWarning::default();
+    req.extensions_mut().insert(warning.clone());
+
+    let mut response = next.run(req).await;
+
+    let warning = warning.0.lock().take();
+    if let Some(warning) = warning {
+        if let Ok(value) = HeaderValue::try_from(warning) {
+            response
+                .headers_mut()
+                .insert(X_API_QUOTA_WARNING.clone(), value);
+        }
+    }
+
+    response
+}
@@ -1,11 +1,13 @@
 use crate::app::AppState;
+use crate::lookup_cache::RuntimeSettings;
 use crate::middleware::log_request::RequestLogExt;
 use crate::middleware::real_ip::RealIp;
-use crate::util::errors::custom;
+use crate::util::errors::{custom, AppResult};
 use axum::extract::{Extension, MatchedPath, Request};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
 use http::{HeaderMap, StatusCode};
+use std::sync::Arc;
 
 pub async fn middleware(
     Extension(real_ip): Extension<RealIp>,
@@ -16,7 +18,7 @@ pub async fn middleware(
 ) -> Result<impl IntoResponse, Response> {
     block_by_ip(&real_ip, &state, req.headers())?;
     block_by_header(&state, &req)?;
-    block_routes(matched_path.as_ref(), &state)?;
+    block_routes(matched_path.as_ref(), &state).await?;
 
     Ok(next.run(req).await)
 }
@@ -30,9 +32,9 @@ pub async fn middleware(
 /// to block requests from the versions of curl or Cargo specified (values are nonsensical examples).
 /// Values of the headers must match exactly.
 pub fn block_by_header(state: &AppState, req: &Request) -> Result<(), Response> {
-    let blocked_traffic = &state.config.blocked_traffic;
+    let config = state.reloadable_config();
 
-    for (header_name, blocked_values) in blocked_traffic {
+    for (header_name, blocked_values) in &config.blocked_traffic {
         let has_blocked_value = req
             .headers()
             .get_all(header_name)
@@ -54,7 +56,7 @@ pub fn block_by_ip(
     state: &AppState,
     headers: &HeaderMap,
 ) -> Result<(), Response> {
-    if state.config.blocked_ips.contains(real_ip) {
+    if state.config.blocked_ips.contains(**real_ip) {
         return Err(rejection_response_from(state, headers));
     }
 
@@ -82,11 +84,15 @@ fn rejection_response_from(state: &AppState, headers: &HeaderMap) -> Response {
     (StatusCode::FORBIDDEN, body).into_response()
 }
 
-/// Allow blocking individual routes by their pattern through the `BLOCKED_ROUTES`
-/// environment variable.
-pub fn block_routes(matched_path: Option<&MatchedPath>, state: &AppState) -> Result<(), Response> {
+/// Allow blocking individual routes by their pattern through the
+/// `/api/private/admin/blocked_routes` endpoints.
+pub async fn block_routes(
+    matched_path: Option<&MatchedPath>,
+    state: &AppState,
+) -> Result<(), Response> {
     if let Some(matched_path) = matched_path {
-        if state.config.blocked_routes.contains(matched_path.as_str()) {
+        let settings = runtime_settings(state).await;
+        if settings.blocked_routes.contains(matched_path.as_str()) {
             let body = "This route is temporarily blocked. See https://status.crates.io.";
             let error = custom(StatusCode::SERVICE_UNAVAILABLE, body);
             return Err(error.into_response());
@@ -95,3 +101,31 @@ pub fn block_routes(matched_path: Option<&MatchedPath>, state: &AppState) -> Res
 
     Ok(())
 }
+
+/// Returns the cached runtime settings, or reloads them if they expired.
+///
+/// If the settings can't be reloaded, the expired settings are used, or no
+/// routes are blocked if the settings were never loaded.
+async fn runtime_settings(state: &AppState) -> Arc<RuntimeSettings> {
+    if let Some(settings) = state.runtime_settings.get() {
+        return settings;
+    }
+
+    match load_runtime_settings(state).await {
+        Ok(settings) => settings,
+        Err(error) => {
+            warn!(%error, "Failed to load the runtime settings");
+            state.runtime_settings.keep_expired().unwrap_or_default()
+        }
+    }
+}
+
+async fn load_runtime_settings(state: &AppState) -> AppResult<Arc<RuntimeSettings>> {
+    let conn = state.db_read().await?;
+    let state = state.clone();
+    let settings = conn
+        .interact(move |conn| state.runtime_settings.load(conn))
+        .await??;
+
+    Ok(settings)
+}
@@ -49,7 +49,10 @@ pub async fn add_common_headers(
         if let Some(ref csp) = state.config.content_security_policy {
             headers.insert(header::CONTENT_SECURITY_POLICY, csp.clone());
         }
-        headers.insert(header::VARY, v("Accept, Accept-Encoding, Cookie"));
+        headers.insert(
+            header::VARY,
+            v("Accept, Accept-Encoding, Accept-Language, Cookie"),
+        );
     }
 
     (headers, response)
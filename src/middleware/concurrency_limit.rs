@@ -0,0 +1,70 @@
+//! Limits the number of in-flight requests of expensive routes
+//!
+//! Routes like the reverse dependencies or the search can keep the database
+//! and the blocking thread pool busy for a long time. To prevent a thundering
+//! herd on one of these routes from exhausting the resources of all other
+//! routes, the number of concurrent requests per route pattern can be capped
+//! through the `ROUTE_CONCURRENCY_LIMITS` environment variable. Requests above
+//! the cap are rejected with a `503 Service Unavailable` response.
+
+use crate::app::AppState;
+use crate::middleware::log_request::RequestLogExt;
+use crate::util::errors::custom_with_code;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http::{header, HeaderValue, StatusCode};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// The number of seconds clients are asked to wait before retrying a
+/// rejected request.
+const RETRY_AFTER_SECONDS: u64 = 5;
+
+/// The semaphores that limit the in-flight requests of the configured route
+/// patterns.
+#[derive(Debug, Default)]
+pub struct RouteConcurrencyLimits(HashMap<String, Arc<Semaphore>>);
+
+impl RouteConcurrencyLimits {
+    pub fn from_config(limits: &HashMap<String, usize>) -> Self {
+        let semaphores = limits
+            .iter()
+            .map(|(route, limit)| (route.clone(), Arc::new(Semaphore::new(*limit))))
+            .collect();
+
+        Self(semaphores)
+    }
+}
+
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Response {
+    let semaphore = matched_path
+        .as_ref()
+        .and_then(|matched_path| state.route_concurrency_limits.0.get(matched_path.as_str()));
+
+    let Some(semaphore) = semaphore else {
+        return next.run(req).await;
+    };
+
+    // The permit is held until the response has been produced
+    let Ok(_permit) = semaphore.clone().try_acquire_owned() else {
+        req.request_log()
+            .add("cause", "route concurrency limit reached");
+
+        let detail = "This route is currently overloaded. Please try again later.";
+        let status = StatusCode::SERVICE_UNAVAILABLE;
+        let mut response = custom_with_code(status, "route_overloaded", detail).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECONDS));
+        return response;
+    };
+
+    next.run(req).await
+}
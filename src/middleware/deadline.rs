@@ -0,0 +1,91 @@
+//! Per-request deadlines
+//!
+//! The [`request_timeout`](crate::middleware::request_timeout) middleware
+//! responds to the client once a request takes too long, but any work that
+//! has already been moved to a blocking thread keeps running until it is
+//! done. The middleware therefore also records the point in time at which the
+//! client will have given up in the request extensions and in a task-local,
+//! so that [`spawn_blocking`] can refuse to start new work and the
+//! [`DbConnection`]s can limit the `statement_timeout` of their interactions
+//! to the remaining time budget.
+//!
+//! [`spawn_blocking`]: crate::tasks::spawn_blocking
+//! [`DbConnection`]: crate::db::DbConnection
+
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    static DEADLINE: Deadline;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn after(duration: Duration) -> Self {
+        Self(Instant::now() + duration)
+    }
+
+    /// Returns the deadline of the request that is handled by the current
+    /// task, if there is one.
+    pub fn current() -> Option<Self> {
+        DEADLINE.try_with(|deadline| *deadline).ok()
+    }
+
+    /// Runs the future with this deadline as the [current](Self::current)
+    /// deadline.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        DEADLINE.scope(self, f).await
+    }
+
+    /// Returns the remaining time budget, which is zero if the deadline has
+    /// already passed.
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    pub fn check(&self) -> Result<(), DeadlineExceeded> {
+        match self.remaining() {
+            Duration::ZERO => Err(DeadlineExceeded),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DeadlineExceeded;
+
+impl fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request deadline exceeded")
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_current() {
+        assert_eq!(Deadline::current(), None);
+
+        let deadline = Deadline::after(Duration::from_secs(30));
+        let current = deadline.scope(async { Deadline::current() }).await;
+        assert_eq!(current, Some(deadline));
+    }
+
+    #[test]
+    fn test_check() {
+        let deadline = Deadline::after(Duration::from_secs(30));
+        assert!(deadline.remaining() > Duration::ZERO);
+        assert!(deadline.check().is_ok());
+
+        let deadline = Deadline::after(Duration::ZERO);
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+        assert!(deadline.check().is_err());
+    }
+}
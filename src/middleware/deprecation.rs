@@ -0,0 +1,111 @@
+//! Machine-readable warnings for deprecated API routes and query parameters
+//!
+//! The responses to requests that use one of the deprecations from
+//! [`ApiDeprecationConfig`](crate::config::ApiDeprecationConfig) carry the
+//! `Deprecation` header of RFC 9745, the `Sunset` header of RFC 8594 if a
+//! removal date is known, and a `Link` to the documentation of the
+//! deprecation. The requests are also marked in the request log, which
+//! includes the user agent, and counted in the `deprecated_requests_total`
+//! metric, so that the remaining users can be found before the removal.
+
+use crate::app::AppState;
+use crate::config::ApiDeprecation;
+use crate::middleware::log_request::RequestLogExt;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use chrono::{NaiveDate, NaiveTime};
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+
+static DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+static SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Response {
+    let config = &state.config.api_deprecations;
+
+    let deprecation = matched_path
+        .as_ref()
+        .and_then(|matched_path| config.find(matched_path.as_str(), req.uri().query()));
+
+    let Some(deprecation) = deprecation else {
+        return next.run(req).await;
+    };
+
+    req.request_log().add("deprecated", deprecation);
+    state
+        .instance_metrics
+        .deprecated_requests_total
+        .with_label_values(&[deprecation.to_string().as_str()])
+        .inc();
+
+    let mut response = next.run(req).await;
+    add_headers(
+        response.headers_mut(),
+        deprecation,
+        config.info_url.as_deref(),
+    );
+    response
+}
+
+fn add_headers(headers: &mut HeaderMap, deprecation: &ApiDeprecation, info_url: Option<&str>) {
+    let deprecated_at = deprecation.deprecated_at.and_time(NaiveTime::MIN);
+    let value = format!("@{}", deprecated_at.and_utc().timestamp());
+    if let Ok(value) = HeaderValue::try_from(value) {
+        headers.insert(DEPRECATION.clone(), value);
+    }
+
+    if let Some(sunset_at) = deprecation.sunset_at {
+        if let Ok(value) = HeaderValue::try_from(http_date(sunset_at)) {
+            headers.insert(SUNSET.clone(), value);
+        }
+    }
+
+    if let Some(info_url) = info_url {
+        let value = format!("<{info_url}>; rel=\"deprecation\"; type=\"text/html\"");
+        if let Ok(value) = HeaderValue::try_from(value) {
+            headers.append(header::LINK, value);
+        }
+    }
+}
+
+/// Formats the start of the day in the HTTP date format of RFC 9110.
+fn http_date(date: NaiveDate) -> String {
+    date.format("%a, %d %b %Y 00:00:00 GMT").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deprecation_headers() {
+        let deprecation: ApiDeprecation =
+            assert_ok!("/api/v1/crates?letter=2024-06-01/2024-12-01".parse());
+
+        let mut headers = HeaderMap::new();
+        add_headers(
+            &mut headers,
+            &deprecation,
+            Some("https://crates.io/deprecations"),
+        );
+        assert_eq!(headers[&DEPRECATION], "@1717200000");
+        assert_eq!(headers[&SUNSET], "Sun, 01 Dec 2024 00:00:00 GMT");
+        assert_eq!(
+            headers[header::LINK],
+            "<https://crates.io/deprecations>; rel=\"deprecation\"; type=\"text/html\""
+        );
+
+        let deprecation: ApiDeprecation = assert_ok!("/api/v1/crates=2024-06-01".parse());
+
+        let mut headers = HeaderMap::new();
+        add_headers(&mut headers, &deprecation, None);
+        assert_eq!(headers[&DEPRECATION], "@1717200000");
+        assert!(!headers.contains_key(&SUNSET));
+        assert!(!headers.contains_key(header::LINK));
+    }
+}
@@ -0,0 +1,46 @@
+//! Rate limit anonymous download requests by client IP address.
+//!
+//! See the [`crate::rate_limiter::downloads`] module for the implementation
+//! of the rate limiter itself.
+
+use crate::app::AppState;
+use crate::middleware::log_request::RequestLogExt;
+use crate::middleware::real_ip::RealIp;
+use crate::util::errors::custom;
+use axum::extract::{Extension, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http::{header, HeaderValue, StatusCode};
+
+pub async fn middleware(
+    Extension(real_ip): Extension<RealIp>,
+    state: AppState,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(rate_limiter) = &state.download_rate_limiter else {
+        return next.run(req).await;
+    };
+
+    if let Err(retry_after) = rate_limiter.check(*real_ip).await {
+        req.request_log()
+            .add("cause", "download rate limit exceeded");
+
+        // `Retry-After` only supports full seconds, so we round up
+        let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+        let detail = format!(
+            "You have downloaded too many crates in a short period of time. \
+             Please try again after {retry_after} seconds."
+        );
+
+        let mut response = custom(StatusCode::TOO_MANY_REQUESTS, detail).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+
+        return response;
+    }
+
+    next.run(req).await
+}
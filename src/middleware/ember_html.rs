@@ -14,11 +14,20 @@ use http::{header, StatusCode};
 use tower::ServiceExt;
 use tower_http::services::ServeFile;
 
+/// Paths outside of `/api/` that are served by the backend for search engine
+/// crawlers.
+const CRAWLER_PATHS: [&str; 2] = ["/robots.txt", "/sitemap.xml"];
+
 pub async fn serve_html(request: Request, next: Next) -> Response {
     let path = &request.uri().path();
 
     // The "/git/" prefix is only used in development (when within a docker container)
-    if path.starts_with("/api/") || path.starts_with("/git/") {
+    if path.starts_with("/api/")
+        || path.starts_with("/git/")
+        || path.starts_with("/sitemaps/")
+        || path.starts_with("/.well-known/")
+        || CRAWLER_PATHS.contains(path)
+    {
         next.run(request).await
     } else if request
         .headers()
@@ -0,0 +1,148 @@
+//! Serialize the error responses of the API as problem documents (see
+//! [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457)) for clients that
+//! request them with an `Accept: application/problem+json` header.
+//!
+//! The errors don't have access to the request, so this middleware records
+//! the negotiated format in a task-local for the duration of the request.
+//! The [`AppError`](crate::util::errors::AppError) implementations check it
+//! with [`ProblemJson::current()`] when they build their response. Responses
+//! that are not produced by an `AppError`, like the rejections of the axum
+//! extractors, keep the default `{"errors": [{"detail": ...}]}` format.
+
+use crate::headers::XRequestId;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use axum_extra::TypedHeader;
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use serde_json::Value;
+
+const PROBLEM_JSON: &str = "application/problem+json";
+
+tokio::task_local! {
+    static PROBLEM_JSON_REQUESTED: ProblemJson;
+}
+
+/// Marks a request whose errors are serialized as problem documents.
+#[derive(Clone, Debug)]
+pub struct ProblemJson {
+    request_id: Option<String>,
+}
+
+impl ProblemJson {
+    /// Returns the problem document format of the request that is handled by
+    /// the current task, if the client requested it.
+    pub fn current() -> Option<Self> {
+        PROBLEM_JSON_REQUESTED.try_with(Clone::clone).ok()
+    }
+
+    /// Builds the problem document of an error response.
+    ///
+    /// The `code` identifies the kind of error and is used for the `type` URI.
+    /// The `errors` of the default format are kept as an extension member,
+    /// since they can contain details like the invalid `field` of a
+    /// validation error.
+    pub fn response(&self, status: StatusCode, code: &str, errors: Vec<Value>) -> Response {
+        let detail = errors
+            .iter()
+            .filter_map(|error| error.get("detail").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        let mut problem = json!({
+            "type": format!("urn:crates-io:error:{code}"),
+            "title": status.canonical_reason().unwrap_or("Unknown Error"),
+            "status": status.as_u16(),
+            "detail": detail,
+            "code": code,
+            "errors": errors,
+        });
+
+        if let Some(request_id) = &self.request_id {
+            problem["request_id"] = request_id.as_str().into();
+        }
+
+        let mut response = (status, Json(problem)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        headers.append(header::VARY, HeaderValue::from_static("Accept"));
+        response
+    }
+}
+
+pub async fn middleware(
+    request_id: Option<TypedHeader<XRequestId>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let wants_problem_json =
+        req.uri().path().starts_with("/api/") && accepts_problem_json(req.headers());
+
+    if !wants_problem_json {
+        return next.run(req).await;
+    }
+
+    let request_id = request_id.map(|TypedHeader(request_id)| request_id.as_str().to_string());
+    let problem_json = ProblemJson { request_id };
+    PROBLEM_JSON_REQUESTED
+        .scope(problem_json, next.run(req))
+        .await
+}
+
+/// Returns `true` if one of the media types of the `Accept` header is
+/// `application/problem+json` and it was not excluded with `q=0`.
+fn accepts_problem_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_range| {
+            let mut params = media_range.split(';').map(str::trim);
+            let is_problem_json = params
+                .next()
+                .is_some_and(|media_type| media_type.eq_ignore_ascii_case(PROBLEM_JSON));
+
+            let is_excluded = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.)
+            });
+
+            is_problem_json && !is_excluded
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_problem_json() {
+        let accepts = |accept: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::ACCEPT, accept.parse().unwrap());
+            accepts_problem_json(&headers)
+        };
+
+        assert!(accepts("application/problem+json"));
+        assert!(accepts("application/json, Application/Problem+JSON; q=0.5"));
+        assert!(!accepts("application/json"));
+        assert!(!accepts("*/*"));
+        assert!(!accepts("application/problem+json;q=0"));
+        assert!(!accepts("application/problem+json; q=0.0"));
+    }
+
+    #[tokio::test]
+    async fn test_current() {
+        assert!(ProblemJson::current().is_none());
+
+        let problem_json = ProblemJson { request_id: None };
+        let current = PROBLEM_JSON_REQUESTED
+            .scope(problem_json, async { ProblemJson::current() })
+            .await;
+        assert!(current.is_some());
+    }
+}
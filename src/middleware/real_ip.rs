@@ -1,5 +1,6 @@
-use crate::real_ip::process_xff_headers;
-use axum::extract::{ConnectInfo, Request};
+use crate::config::ClientIpConfig;
+use crate::real_ip::client_ip;
+use axum::extract::{ConnectInfo, Request, State};
 use axum::middleware::Next;
 use axum::response::IntoResponse;
 use std::net::{IpAddr, SocketAddr};
@@ -8,12 +9,12 @@ use std::net::{IpAddr, SocketAddr};
 pub struct RealIp(IpAddr);
 
 pub async fn middleware(
+    State(config): State<ClientIpConfig>,
     ConnectInfo(socket_addr): ConnectInfo<SocketAddr>,
     mut req: Request,
     next: Next,
 ) -> impl IntoResponse {
-    let xff_ip = process_xff_headers(req.headers());
-    let real_ip = xff_ip.unwrap_or_else(|| socket_addr.ip());
+    let real_ip = client_ip(req.headers(), socket_addr.ip(), &config);
 
     req.extensions_mut().insert(RealIp(real_ip));
 
@@ -0,0 +1,29 @@
+//! Run the request handling within a `tracing` span that carries the request
+//! id, so that background jobs that are enqueued by the request can be
+//! correlated with it.
+//!
+//! The span is propagated to the `interact()` calls on database connections,
+//! which is where the jobs are usually enqueued. See
+//! [`crates_io_worker::set_request_id()`] for details.
+
+use crate::headers::XRequestId;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::TypedHeader;
+use tracing::Instrument;
+
+pub async fn middleware(
+    request_id: Option<TypedHeader<XRequestId>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(TypedHeader(request_id)) = request_id else {
+        return next.run(req).await;
+    };
+
+    let span = info_span!("request", request_id = %request_id.as_str());
+    crates_io_worker::set_request_id(&span, request_id.as_str());
+
+    next.run(req).instrument(span).await
+}
@@ -0,0 +1,149 @@
+//! Per-route-group request timeouts
+//!
+//! Most routes return small JSON documents and should be aborted quickly if
+//! they hang, while publishing has to receive and process a crate file and
+//! downloads may have to stream large files to slow clients. This middleware
+//! sorts each request into one of these groups, applies the timeout of the
+//! group from [`RequestTimeoutConfig`](crate::config::RequestTimeoutConfig)
+//! and records the resulting [`Deadline`] for the rest of the request.
+//!
+//! Requests that exceed their timeout are aborted with a
+//! `503 Service Unavailable` response and counted in the
+//! `request_timeouts_total` metric.
+//!
+//! The matched route of the request is recorded in a task-local as well, so
+//! that slow database interactions can be attributed to it.
+
+use crate::app::AppState;
+use crate::middleware::deadline::Deadline;
+use crate::util::errors::custom;
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use http::{Method, StatusCode};
+use std::time::Duration;
+use tracing::warn;
+
+/// The route of the publish endpoint, which receives crate files.
+const PUBLISH_ROUTE: &str = "/api/v1/crates/new";
+/// The route of the crate download endpoint.
+const DOWNLOAD_ROUTE: &str = "/api/v1/crates/:crate_id/:version/download";
+/// The path of the database dump, which is served from the local uploads in
+/// development mode.
+const DB_DUMP_PATH: &str = "/db-dump.tar.gz";
+
+tokio::task_local! {
+    static ROUTE: Option<MatchedPath>;
+}
+
+/// Returns the matched route of the request that is handled by the current
+/// task, if there is one.
+pub fn current_route() -> Option<MatchedPath> {
+    ROUTE.try_with(Clone::clone).ok().flatten()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RouteGroup {
+    Read,
+    Upload,
+    Download,
+}
+
+impl RouteGroup {
+    fn of(method: &Method, matched_path: Option<&str>, path: &str) -> Self {
+        match (method, matched_path) {
+            (&Method::PUT, Some(PUBLISH_ROUTE)) => Self::Upload,
+            (_, Some(DOWNLOAD_ROUTE)) => Self::Download,
+            (&Method::GET | &Method::HEAD, None) if path == DB_DUMP_PATH => Self::Download,
+            _ => Self::Read,
+        }
+    }
+
+    fn timeout(self, state: &AppState) -> Duration {
+        let timeouts = &state.config.request_timeouts;
+        match self {
+            Self::Read => timeouts.read,
+            Self::Upload => timeouts.upload,
+            Self::Download => timeouts.download,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Upload => "upload",
+            Self::Download => "download",
+        }
+    }
+}
+
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    state: AppState,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let route = matched_path.as_ref().map(MatchedPath::as_str);
+    let group = RouteGroup::of(req.method(), route, req.uri().path());
+    let timeout = group.timeout(&state);
+
+    let deadline = Deadline::after(timeout);
+    req.extensions_mut().insert(deadline);
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let future = ROUTE.scope(matched_path, deadline.scope(next.run(req)));
+    match tokio::time::timeout(timeout, future).await {
+        Ok(response) => response,
+        Err(_) => {
+            // This middleware wraps the request logging middleware, which
+            // therefore never sees the aborted requests.
+            warn!(%method, %path, group = group.as_str(), "Request timed out");
+            state
+                .instance_metrics
+                .request_timeouts_total
+                .with_label_values(&[group.as_str()])
+                .inc();
+
+            let detail = format!(
+                "The request took longer than the limit of {} seconds and was aborted. \
+                Please try again later.",
+                timeout.as_secs()
+            );
+            custom(StatusCode::SERVICE_UNAVAILABLE, detail).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_groups() {
+        let group = |method, matched_path, path| RouteGroup::of(&method, matched_path, path);
+
+        let path = "/api/v1/crates/new";
+        assert_eq!(
+            group(Method::PUT, Some(PUBLISH_ROUTE), path),
+            RouteGroup::Upload
+        );
+        assert_eq!(
+            group(Method::GET, Some(PUBLISH_ROUTE), path),
+            RouteGroup::Read
+        );
+
+        let path = "/api/v1/crates/foo/1.0.0/download";
+        assert_eq!(
+            group(Method::GET, Some(DOWNLOAD_ROUTE), path),
+            RouteGroup::Download
+        );
+
+        assert_eq!(group(Method::GET, None, DB_DUMP_PATH), RouteGroup::Download);
+        assert_eq!(group(Method::GET, None, "/crates"), RouteGroup::Read);
+
+        let route = "/api/v1/crates/:crate_id";
+        let path = "/api/v1/crates/foo";
+        assert_eq!(group(Method::GET, Some(route), path), RouteGroup::Read);
+    }
+}
@@ -22,7 +22,8 @@ pub async fn require_user_agent(
     req: Request,
     next: Next,
 ) -> axum::response::Response {
-    let cdn_user_agent = &state.config.cdn_user_agent;
+    let config = state.reloadable_config();
+    let cdn_user_agent = &config.cdn_user_agent;
 
     let agent = match user_agent {
         Some(ref header) => header.as_str(),
@@ -0,0 +1,252 @@
+//! Attach request context to the Sentry scope, and keep secrets out of the
+//! request data that is sent to Sentry.
+//!
+//! The authentication runs on the blocking threads of the database pool,
+//! which don't have access to the Sentry hub of the request. It records the
+//! id of the authenticated user in the [`SentryUser`] request extension
+//! instead, which the [`middleware()`] then attaches to the events of the
+//! request. Only the user id is sent to Sentry, to avoid leaking any personal
+//! information like usernames or email addresses.
+
+use crate::headers::XRequestId;
+use axum::extract::{MatchedPath, RawPathParams, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::TypedHeader;
+use http::header::{Entry, AUTHORIZATION, COOKIE};
+use http::{HeaderName, HeaderValue};
+use std::sync::{Arc, OnceLock};
+
+/// Request headers that must never be sent to Sentry.
+const SENSITIVE_HEADERS: [HeaderName; 2] = [AUTHORIZATION, COOKIE];
+
+const FILTERED: &str = "[Filtered]";
+
+/// The original values of the [`SENSITIVE_HEADERS`], while they are replaced
+/// by placeholders in the request.
+#[derive(Clone, Debug)]
+struct SensitiveHeaders(Vec<(HeaderName, HeaderValue)>);
+
+/// The id of the authenticated user of a request, as recorded by the
+/// authentication.
+#[derive(Clone, Debug, Default)]
+pub struct SentryUser(Arc<OnceLock<i32>>);
+
+impl SentryUser {
+    pub fn set(&self, user_id: i32) {
+        let _ = self.0.set(user_id);
+    }
+
+    fn user(&self) -> Option<sentry::User> {
+        let user_id = self.0.get()?;
+        Some(sentry::User {
+            id: Some(user_id.to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+/// Replaces the values of the [`SENSITIVE_HEADERS`] with placeholders.
+///
+/// The `SentryHttpLayer` copies all request headers into the Sentry events and
+/// transactions, so this middleware has to run right before that layer, and
+/// [`restore_sensitive_headers()`] has to run right after it.
+pub async fn scrub_sensitive_headers(mut req: Request, next: Next) -> Response {
+    let headers = req.headers_mut();
+
+    let mut sensitive_headers = Vec::new();
+    for name in SENSITIVE_HEADERS {
+        if let Entry::Occupied(entry) = headers.entry(&name) {
+            let (_, values) = entry.remove_entry_mult();
+            sensitive_headers.extend(values.map(|value| (name.clone(), value)));
+            headers.insert(name, HeaderValue::from_static(FILTERED));
+        }
+    }
+
+    req.extensions_mut()
+        .insert(SensitiveHeaders(sensitive_headers));
+
+    next.run(req).await
+}
+
+/// Restores the original values of the headers that were replaced by
+/// [`scrub_sensitive_headers()`].
+pub async fn restore_sensitive_headers(mut req: Request, next: Next) -> Response {
+    if let Some(SensitiveHeaders(sensitive_headers)) = req.extensions_mut().remove() {
+        let headers = req.headers_mut();
+        for name in SENSITIVE_HEADERS {
+            headers.remove(name);
+        }
+        for (name, value) in sensitive_headers {
+            headers.append(name, value);
+        }
+    }
+
+    next.run(req).await
+}
+
+/// Attaches the matched route, the request id, the crate name and version
+/// path parameters and the authenticated user to the Sentry scope of the
+/// request.
+pub async fn middleware(
+    matched_path: Option<MatchedPath>,
+    path_params: Option<RawPathParams>,
+    request_id: Option<TypedHeader<XRequestId>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let user = SentryUser::default();
+    req.extensions_mut().insert(user.clone());
+
+    sentry::configure_scope(|scope| {
+        // The user is only known once the request has been authenticated, so
+        // it's added to the events when they are captured.
+        let event_user = user.clone();
+        scope.add_event_processor(move |mut event| {
+            if event.user.is_none() {
+                event.user = event_user.user();
+            }
+            Some(event)
+        });
+
+        if let Some(matched_path) = &matched_path {
+            scope.set_tag("http.route", matched_path.as_str());
+        }
+
+        if let Some(TypedHeader(request_id)) = &request_id {
+            scope.set_tag("request_id", request_id.as_str());
+        }
+
+        for (key, value) in path_params.iter().flatten() {
+            if let Some(tag) = path_param_tag(key) {
+                scope.set_tag(tag, value);
+            }
+        }
+    });
+
+    let response = next.run(req).await;
+
+    // The transaction of the request is finished after this middleware
+    if let Some(user) = user.user() {
+        sentry::configure_scope(|scope| scope.set_user(Some(user)));
+    }
+
+    response
+}
+
+/// Returns the name of the Sentry tag for a route path parameter.
+///
+/// Only an explicit list of path parameters is attached to the Sentry scope,
+/// since some routes contain secrets in their path (e.g. invitation tokens).
+fn path_param_tag(key: &str) -> Option<&'static str> {
+    match key {
+        "crate_id" => Some("crate_name"),
+        "version" => Some("crate_version"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::middleware::from_fn;
+    use axum::routing::get;
+    use axum::{Extension, Router};
+    use http::HeaderMap;
+    use parking_lot::Mutex;
+    use sentry::integrations::tower::NewSentryLayer;
+    use sentry::test::TestTransport;
+    use sentry::{ClientOptions, Hub, Level, SentryFutureExt};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn sensitive_headers_are_scrubbed_and_restored() {
+        let scrubbed_headers = Arc::new(Mutex::new(HeaderMap::new()));
+
+        // Captures the headers that the `SentryHttpLayer` would see
+        let capture_headers = {
+            let scrubbed_headers = scrubbed_headers.clone();
+            move |req: Request, next: Next| {
+                *scrubbed_headers.lock() = req.headers().clone();
+                next.run(req)
+            }
+        };
+
+        async fn handler(headers: HeaderMap) -> String {
+            let values = headers.get_all(AUTHORIZATION).iter();
+            let values = values.chain(headers.get_all(COOKIE).iter());
+            let values = values.map(|value| value.to_str().unwrap());
+            values.collect::<Vec<_>>().join(", ")
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(from_fn(restore_sensitive_headers))
+            .layer(from_fn(capture_headers))
+            .layer(from_fn(scrub_sensitive_headers));
+
+        let request = http::Request::get("/")
+            .header(AUTHORIZATION, "secret-token")
+            .header(COOKIE, "cargo_session=secret")
+            .header(COOKIE, "other=secret")
+            .header("x-request-id", "1234")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "secret-token, cargo_session=secret, other=secret");
+
+        let scrubbed_headers = scrubbed_headers.lock();
+        let authorization = scrubbed_headers.get_all(AUTHORIZATION);
+        assert_eq!(authorization.iter().collect::<Vec<_>>(), [FILTERED]);
+        let cookies = scrubbed_headers.get_all(COOKIE);
+        assert_eq!(cookies.iter().collect::<Vec<_>>(), [FILTERED]);
+        assert_eq!(scrubbed_headers.get("x-request-id").unwrap(), "1234");
+    }
+
+    #[tokio::test]
+    async fn authenticated_user_is_attached_to_events() {
+        let transport = TestTransport::new();
+        let options = ClientOptions {
+            dsn: Some("https://public@sentry.invalid/1".parse().unwrap()),
+            transport: Some(Arc::new(transport.clone())),
+            ..Default::default()
+        };
+        let hub = Arc::new(Hub::new(Some(Arc::new(options.into())), Default::default()));
+
+        async fn handler(Extension(user): Extension<SentryUser>) {
+            // The authentication runs on a blocking thread, like in `conn.interact()`
+            tokio::task::spawn_blocking(move || user.set(42))
+                .await
+                .unwrap();
+
+            sentry::capture_message("something went wrong", Level::Error);
+        }
+
+        let app = Router::new()
+            .route("/", get(handler))
+            .layer(from_fn(middleware))
+            .layer(NewSentryLayer::new_from_top());
+
+        let request = http::Request::get("/").body(Body::empty()).unwrap();
+        app.oneshot(request).bind_hub(hub).await.unwrap();
+
+        let events = transport.fetch_and_clear_events();
+        assert_eq!(events.len(), 1);
+        let user = events[0].user.as_ref().unwrap();
+        assert_eq!(user.id.as_deref(), Some("42"));
+        assert_eq!(user.username, None);
+        assert_eq!(user.email, None);
+    }
+
+    #[test]
+    fn only_allowed_path_params_are_tagged() {
+        assert_eq!(path_param_tag("crate_id"), Some("crate_name"));
+        assert_eq!(path_param_tag("version"), Some("crate_version"));
+        assert_eq!(path_param_tag("token"), None);
+        assert_eq!(path_param_tag("email_token"), None);
+    }
+}
@@ -1,33 +1,69 @@
-pub use self::action::{insert_version_owner_action, VersionAction, VersionOwnerAction};
-pub use self::category::{Category, CrateCategory, NewCategory};
-pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitationOutcome};
+pub use self::action::{
+    insert_version_owner_action, CrateOwnerAction, NewCrateOwnerAction, OwnerAction,
+    OwnerActionVia, VersionAction, VersionOwnerAction,
+};
+pub use self::category::{
+    Category, CategoryMigration, CategorySynonym, CrateCategory, NewCategory, NewCategoryMigration,
+    NewCategorySynonym, UnknownCategory,
+};
+pub use self::cdn_health_probe::CdnHealthProbe;
+pub use self::crate_name_reservation::CrateNameReservation;
+pub use self::crate_owner_invitation::{
+    CrateOwnerInvitation, InvitationCounts, NewCrateOwnerInvitationOutcome,
+};
 pub use self::dependency::{Dependency, DependencyKind, ReverseDependency};
+pub use self::description_translation::DescriptionTranslation;
 pub use self::download::VersionDownload;
 pub use self::email::{Email, NewEmail};
 pub use self::follow::Follow;
+pub use self::invitation_block::InvitationBlock;
 pub use self::keyword::{CrateKeyword, Keyword};
 pub use self::krate::{Crate, CrateVersions, NewCrate, RecentCrateDownloads};
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::provenance::{NewVersionProvenance, VersionProvenance};
+pub use self::publish_nonce::PublishNonce;
+pub use self::recommended_version::RecommendedVersion;
+pub use self::release_notes::{NewReleaseNotes, ReleaseNotes};
 pub use self::rights::Rights;
+pub use self::runtime_settings::{BlockedRoute, ExcludedCrateName};
+pub use self::scheduled_release::{
+    NewScheduledRelease, ScheduledRelease, ScheduledReleaseResolution,
+};
 pub use self::team::{NewTeam, Team};
 pub use self::token::{ApiToken, CreatedApiToken};
+pub use self::token_anomaly::{NewTokenAnomaly, TokenAnomaly, TokenAnomalyKind};
 pub use self::user::{NewUser, User};
 pub use self::version::{NewVersion, TopVersions, Version};
+pub use self::version_artifact::{NewVersionArtifact, VersionArtifact};
+pub use self::version_quarantine::{NewVersionQuarantine, QuarantineResolution, VersionQuarantine};
 
 pub mod helpers;
 
 mod action;
 pub mod category;
+mod cdn_health_probe;
+mod crate_name_reservation;
 mod crate_owner_invitation;
 pub mod dependency;
+mod description_translation;
 mod download;
 mod email;
 mod follow;
+mod invitation_block;
 mod keyword;
 pub mod krate;
 mod owner;
+mod provenance;
+mod publish_nonce;
+mod recommended_version;
+mod release_notes;
 mod rights;
+mod runtime_settings;
+mod scheduled_release;
 mod team;
 pub mod token;
+mod token_anomaly;
 pub mod user;
 pub mod version;
+mod version_artifact;
+mod version_quarantine;
@@ -1,8 +1,10 @@
-use crate::models::{ApiToken, User, Version};
+use crate::models::{ApiToken, Crate, OwnerKind, User, Version};
 use crate::schema::*;
 use crate::sql::pg_enum;
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
+use ipnetwork::IpNetwork;
+use std::net::IpAddr;
 
 pg_enum! {
     pub enum VersionAction {
@@ -30,7 +32,7 @@ impl From<VersionAction> for String {
     }
 }
 
-#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
 #[diesel(
     table_name = version_owner_actions,
     check_for_backend(diesel::pg::Pg),
@@ -45,6 +47,8 @@ pub struct VersionOwnerAction {
     pub api_token_id: Option<i32>,
     pub action: VersionAction,
     pub time: NaiveDateTime,
+    pub ip: Option<IpNetwork>,
+    pub reason: Option<String>,
 }
 
 impl VersionOwnerAction {
@@ -83,8 +87,10 @@ pub fn insert_version_owner_action(
     user_id_: i32,
     api_token_id_: Option<i32>,
     action_: VersionAction,
+    ip_: Option<IpAddr>,
+    reason_: Option<&str>,
 ) -> QueryResult<VersionOwnerAction> {
-    use version_owner_actions::dsl::{action, api_token_id, user_id, version_id};
+    use version_owner_actions::dsl::{action, api_token_id, ip, reason, user_id, version_id};
 
     diesel::insert_into(version_owner_actions::table)
         .values((
@@ -92,6 +98,82 @@ pub fn insert_version_owner_action(
             user_id.eq(user_id_),
             api_token_id.eq(api_token_id_),
             action.eq(action_),
+            ip.eq(ip_.map(IpNetwork::from)),
+            reason.eq(reason_),
         ))
         .get_result(conn)
 }
+
+pg_enum! {
+    pub enum OwnerAction {
+        Add = 0,
+        Remove = 1,
+    }
+}
+
+// How an owner was added to or removed from a crate.
+pg_enum! {
+    pub enum OwnerActionVia {
+        // The action was reconstructed from the ownerships that existed
+        // before the actions were recorded.
+        Unknown = 0,
+        // The publisher of the first version of a crate became its owner.
+        Publish = 1,
+        // A user accepted an invitation of an existing owner.
+        Invitation = 2,
+        // An owner added or removed another owner directly, e.g. a team.
+        Direct = 3,
+        // The crates.io team changed the owners, e.g. by merging accounts.
+        Admin = 4,
+    }
+}
+
+/// An entry of the ownership history of a crate.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Associations)]
+#[diesel(
+    table_name = crate_owner_actions,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(Crate),
+)]
+pub struct CrateOwnerAction {
+    pub id: i32,
+    pub crate_id: i32,
+    pub owner_id: i32,
+    pub owner_kind: OwnerKind,
+    pub actor_id: Option<i32>,
+    pub action: OwnerAction,
+    pub via: OwnerActionVia,
+    pub time: NaiveDateTime,
+}
+
+impl CrateOwnerAction {
+    /// Returns the ownership history of a crate in chronological order.
+    pub fn by_crate(conn: &mut PgConnection, crate_id: i32) -> QueryResult<Vec<Self>> {
+        crate_owner_actions::table
+            .filter(crate_owner_actions::crate_id.eq(crate_id))
+            .order((crate_owner_actions::time, crate_owner_actions::id))
+            .select(Self::as_select())
+            .load(conn)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Insertable, Serialize)]
+#[diesel(table_name = crate_owner_actions, check_for_backend(diesel::pg::Pg))]
+pub struct NewCrateOwnerAction {
+    pub crate_id: i32,
+    pub owner_id: i32,
+    pub owner_kind: OwnerKind,
+    pub actor_id: Option<i32>,
+    pub action: OwnerAction,
+    pub via: OwnerActionVia,
+}
+
+impl NewCrateOwnerAction {
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        diesel::insert_into(crate_owner_actions::table)
+            .values(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use diesel::{self, *};
 
 use crate::models::Crate;
 use crate::schema::*;
+use crate::util::rfc3339;
 
 #[derive(Clone, Identifiable, Queryable, QueryableByName, Debug)]
 #[diesel(table_name = categories, check_for_backend(diesel::pg::Pg))]
@@ -46,15 +47,27 @@ impl Category {
         slugs: &[&str],
     ) -> QueryResult<Vec<String>> {
         conn.transaction(|conn| {
-            let categories: Vec<Category> = categories::table
+            let mut categories: Vec<Category> = categories::table
                 .filter(categories::slug.eq_any(slugs))
                 .load(conn)?;
+            let synonyms: Vec<(String, Category)> = category_synonyms::table
+                .inner_join(categories::table)
+                .filter(category_synonyms::synonym.eq_any(slugs))
+                .select((category_synonyms::synonym, categories::all_columns))
+                .load(conn)?;
             let invalid_categories = slugs
                 .iter()
                 .cloned()
                 .filter(|s| !categories.iter().any(|c| c.slug == *s))
+                .filter(|s| !synonyms.iter().any(|(synonym, _)| synonym == s))
                 .map(ToString::to_string)
                 .collect();
+
+            // A synonym and the slug of its category might both be listed
+            categories.extend(synonyms.into_iter().map(|(_, category)| category));
+            categories.sort_by_key(|c| c.id);
+            categories.dedup_by_key(|c| c.id);
+
             let crate_categories = categories
                 .iter()
                 .map(|c| CrateCategory {
@@ -71,6 +84,70 @@ impl Category {
         })
     }
 
+    /// Returns the given slugs that are neither the slug of a category nor
+    /// one of its synonyms, together with similar valid slugs.
+    pub fn find_unknown(
+        conn: &mut PgConnection,
+        slugs: &[&str],
+    ) -> QueryResult<Vec<UnknownCategory>> {
+        if slugs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut known: Vec<String> = categories::table.select(categories::slug).load(conn)?;
+        let synonyms: Vec<String> = category_synonyms::table
+            .select(category_synonyms::synonym)
+            .load(conn)?;
+
+        let unknown = slugs
+            .iter()
+            .filter(|slug| !known.iter().chain(&synonyms).any(|known| known == *slug))
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        if unknown.is_empty() {
+            return Ok(vec![]);
+        }
+
+        known.sort();
+        Ok(unknown
+            .into_iter()
+            .map(|slug| {
+                let suggestions = suggest_categories(&slug, &known);
+                UnknownCategory { slug, suggestions }
+            })
+            .collect())
+    }
+
+    /// Moves all crates of the `source` category to the `target` category,
+    /// and returns the number of moved crates.
+    pub fn migrate_crates(
+        conn: &mut PgConnection,
+        source_id: i32,
+        target_id: i32,
+    ) -> QueryResult<usize> {
+        use diesel::sql_types::Integer;
+
+        conn.transaction(|conn| {
+            // Crates that are already in the target category keep their entry.
+            // They are skipped explicitly instead of with `ON CONFLICT DO
+            // NOTHING`, since the `BEFORE INSERT` trigger would still count
+            // them in `crates_cnt`.
+            sql_query(
+                "INSERT INTO crates_categories (crate_id, category_id) \
+                SELECT crate_id, $2 FROM crates_categories WHERE category_id = $1 \
+                AND crate_id NOT IN \
+                (SELECT crate_id FROM crates_categories WHERE category_id = $2)",
+            )
+            .bind::<Integer, _>(source_id)
+            .bind::<Integer, _>(target_id)
+            .execute(conn)?;
+
+            delete(crates_categories::table.filter(crates_categories::category_id.eq(source_id)))
+                .execute(conn)
+        })
+    }
+
     pub fn count_toplevel(conn: &mut PgConnection) -> QueryResult<i64> {
         categories::table
             .filter(categories::category.not_like("%::%"))
@@ -120,6 +197,78 @@ impl Category {
     }
 }
 
+/// A category slug of a crate that does not match any category.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownCategory {
+    pub slug: String,
+    /// Similar category slugs, most similar first.
+    pub suggestions: Vec<String>,
+}
+
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns up to three of the `known` slugs that are similar to `slug`.
+///
+/// Both slugs are compared after normalizing their case and separators, so
+/// that e.g. `Web_Programming :: HTTP-Client` matches
+/// `web-programming::http-client`. The last segment of subcategory paths is
+/// compared on its own too, so that a subcategory is found even if it was
+/// listed without or with the wrong parent category.
+fn suggest_categories(slug: &str, known: &[String]) -> Vec<String> {
+    let normalized = normalize_slug(slug);
+    let leaf = leaf_segment(&normalized);
+
+    let mut candidates = known
+        .iter()
+        .filter_map(|candidate| {
+            let candidate_normalized = normalize_slug(candidate);
+            let distance = levenshtein(&normalized, &candidate_normalized)
+                .min(levenshtein(leaf, leaf_segment(&candidate_normalized)));
+
+            // Allow roughly one typo per four characters
+            let max_distance = (leaf.chars().count() / 4).max(1);
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect::<Vec<_>>();
+
+    candidates.sort_by_key(|(distance, candidate)| (*distance, candidate.len()));
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+fn normalize_slug(slug: &str) -> String {
+    slug.split("::")
+        .map(|segment| segment.trim().to_lowercase().replace(['_', ' '], "-"))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+fn leaf_segment(slug: &str) -> &str {
+    slug.rsplit("::").next().unwrap_or(slug)
+}
+
+/// Returns the number of single character insertions, deletions and
+/// substitutions that are needed to turn `a` into `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
 /// Struct for inserting categories; only used in tests. Actual categories are inserted
 /// in src/boot/categories.rs.
 #[derive(Insertable, AsChangeset, Default, Debug)]
@@ -142,11 +291,119 @@ impl<'a> NewCategory<'a> {
     }
 }
 
+/// An alternative slug of a category, e.g. the old slug of a renamed
+/// category.
+#[derive(Clone, Queryable, Identifiable, Selectable, Debug, Serialize)]
+#[diesel(
+    table_name = category_synonyms,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(synonym)
+)]
+pub struct CategorySynonym {
+    pub synonym: String,
+    pub category_id: i32,
+    #[serde(skip)]
+    pub created_by: Option<i32>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = category_synonyms, check_for_backend(diesel::pg::Pg))]
+pub struct NewCategorySynonym<'a> {
+    pub synonym: &'a str,
+    pub category_id: i32,
+    pub created_by: i32,
+}
+
+impl<'a> NewCategorySynonym<'a> {
+    /// Inserts the synonym, or returns `None` if the synonym already exists.
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<Option<CategorySynonym>> {
+        insert_into(category_synonyms::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .returning(CategorySynonym::as_returning())
+            .get_result(conn)
+            .optional()
+    }
+}
+
+/// A bulk migration of the crates of one category to another, which is
+/// performed by the `migrate_category` background job.
+#[derive(Clone, Queryable, Identifiable, Selectable, Debug, Serialize)]
+#[diesel(table_name = category_migrations, check_for_backend(diesel::pg::Pg))]
+pub struct CategoryMigration {
+    pub id: i64,
+    pub source_category_id: i32,
+    pub target_category_id: i32,
+    #[serde(skip)]
+    pub created_by: Option<i32>,
+    pub crates_migrated: Option<i32>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = category_migrations, check_for_backend(diesel::pg::Pg))]
+pub struct NewCategoryMigration {
+    pub source_category_id: i32,
+    pub target_category_id: i32,
+    pub created_by: i32,
+}
+
+impl NewCategoryMigration {
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<CategoryMigration> {
+        insert_into(category_migrations::table)
+            .values(self)
+            .returning(CategoryMigration::as_returning())
+            .get_result(conn)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test_util::test_db_connection;
 
+    #[test]
+    fn levenshtein_distance() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("parsing", "parsing"), 0);
+        assert_eq!(levenshtein("parser", "parsing"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn category_suggestions() {
+        let known = [
+            "api-bindings",
+            "parser-implementations",
+            "parsing",
+            "web-programming",
+            "web-programming::http-client",
+            "web-programming::http-server",
+        ]
+        .map(String::from);
+
+        assert_eq!(suggest_categories("parsng", &known), vec!["parsing"]);
+        assert_eq!(
+            suggest_categories("Web_Programming :: HTTP Client", &known),
+            vec!["web-programming::http-client"]
+        );
+        assert_eq!(
+            suggest_categories("http-client", &known),
+            vec!["web-programming::http-client"]
+        );
+        assert_eq!(
+            suggest_categories("network-programming::http-server", &known),
+            vec!["web-programming::http-server"]
+        );
+        assert_eq!(suggest_categories("database", &known), Vec::<String>::new());
+    }
+
     #[test]
     fn category_toplevel_excludes_subcategories() {
         use self::categories;
@@ -0,0 +1,72 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::schema::cdn_health_probes;
+
+/// Probes older than this are ignored, so that downloads are redirected to the
+/// CDN again if the `probe_cdn_health` job stops running.
+const MAX_PROBE_AGE: chrono::Duration = chrono::Duration::minutes(15);
+
+/// Probes are only kept for a day, since only the most recent ones are used.
+const PROBE_RETENTION: chrono::Duration = chrono::Duration::days(1);
+
+/// The result of a health probe of the CDN.
+#[derive(Queryable, Selectable, Identifiable, Clone, Debug)]
+#[diesel(table_name = cdn_health_probes, check_for_backend(diesel::pg::Pg))]
+pub struct CdnHealthProbe {
+    pub id: i64,
+    pub probed_at: NaiveDateTime,
+    pub requests: i32,
+    pub errors: i32,
+}
+
+impl CdnHealthProbe {
+    /// Records the result of a probe, and removes the probes that are no
+    /// longer needed.
+    pub fn record(conn: &mut PgConnection, requests: i32, errors: i32) -> QueryResult<()> {
+        let retention_start = Utc::now().naive_utc() - PROBE_RETENTION;
+        diesel::delete(cdn_health_probes::table)
+            .filter(cdn_health_probes::probed_at.lt(retention_start))
+            .execute(conn)?;
+
+        diesel::insert_into(cdn_health_probes::table)
+            .values((
+                cdn_health_probes::requests.eq(requests),
+                cdn_health_probes::errors.eq(errors),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Returns the share of failed requests of the probe.
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.;
+        }
+
+        self.errors as f64 / self.requests as f64
+    }
+
+    /// Returns whether the CDN is considered unavailable.
+    ///
+    /// A single probe at or above the `error_rate` threshold marks the CDN as
+    /// unavailable, and it recovers once the last `recovery_probes` probes
+    /// were all below the threshold.
+    pub fn is_cdn_unavailable(
+        conn: &mut PgConnection,
+        error_rate: f64,
+        recovery_probes: u32,
+    ) -> QueryResult<bool> {
+        let min_probed_at = Utc::now().naive_utc() - MAX_PROBE_AGE;
+
+        let probes: Vec<CdnHealthProbe> = cdn_health_probes::table
+            .filter(cdn_health_probes::probed_at.ge(min_probed_at))
+            .order(cdn_health_probes::id.desc())
+            .limit(recovery_probes.max(1) as i64)
+            .select(CdnHealthProbe::as_select())
+            .load(conn)?;
+
+        Ok(probes.iter().any(|probe| probe.error_rate() >= error_rate))
+    }
+}
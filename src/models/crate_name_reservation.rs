@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::crate_name_reservations;
+use crate::sql::canon_crate_name;
+
+/// A crate name that has been reserved by a user, which blocks other users
+/// from publishing a crate with this name until the reservation expires.
+#[derive(Queryable, Selectable, Identifiable, Associations, Clone, Debug)]
+#[diesel(
+    table_name = crate_name_reservations,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(name),
+    belongs_to(User),
+)]
+pub struct CrateNameReservation {
+    pub name: String,
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl CrateNameReservation {
+    /// Returns the reservation for the given name, if there is one that has
+    /// not expired yet.
+    ///
+    /// Like crate names, reserved names are compared in their canonical form,
+    /// so a reservation for `foo-bar` also reserves `foo_bar`.
+    pub fn find_active(conn: &mut PgConnection, name: &str) -> QueryResult<Option<Self>> {
+        crate_name_reservations::table
+            .filter(canon_crate_name(crate_name_reservations::name).eq(canon_crate_name(name)))
+            .filter(crate_name_reservations::expires_at.gt(now))
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the number of reservations of the user that have not expired
+    /// yet.
+    pub fn count_active(conn: &mut PgConnection, user_id: i32) -> QueryResult<i64> {
+        crate_name_reservations::table
+            .filter(crate_name_reservations::user_id.eq(user_id))
+            .filter(crate_name_reservations::expires_at.gt(now))
+            .count()
+            .get_result(conn)
+    }
+
+    /// Deletes the reservation for the given name, regardless of whether it
+    /// has expired or not.
+    pub fn delete(conn: &mut PgConnection, name: &str) -> QueryResult<usize> {
+        let query = crate_name_reservations::table
+            .filter(canon_crate_name(crate_name_reservations::name).eq(canon_crate_name(name)));
+
+        diesel::delete(query).execute(conn)
+    }
+
+    pub fn delete_expired(conn: &mut PgConnection) -> QueryResult<usize> {
+        let query =
+            crate_name_reservations::table.filter(crate_name_reservations::expires_at.le(now));
+
+        diesel::delete(query).execute(conn)
+    }
+}
@@ -1,13 +1,23 @@
-use chrono::{NaiveDateTime, Utc};
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use http::StatusCode;
 use secrecy::SecretString;
 
+use crate::audit::{AuditEvent, AuditLog};
 use crate::config;
-use crate::models::{CrateOwner, OwnerKind};
+use crate::models::{CrateOwner, NewCrateOwnerAction, OwnerAction, OwnerActionVia, OwnerKind};
 use crate::schema::{crate_owner_invitations, crate_owners, crates};
 use crate::util::errors::{custom, AppResult};
 
+/// The number of invitations in the database, by state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InvitationCounts {
+    /// Invitations that can still be accepted.
+    pub pending: i64,
+    /// Invitations that have expired, but were not deleted yet.
+    pub expired: i64,
+}
+
 #[derive(Debug)]
 pub enum NewCrateOwnerInvitationOutcome {
     AlreadyExists,
@@ -34,6 +44,7 @@ impl CrateOwnerInvitation {
         crate_id: i32,
         conn: &mut PgConnection,
         config: &config::Server,
+        now: NaiveDateTime,
     ) -> QueryResult<NewCrateOwnerInvitationOutcome> {
         #[derive(Insertable, Clone, Copy, Debug)]
         #[diesel(table_name = crate_owner_invitations, check_for_backend(diesel::pg::Pg))]
@@ -56,7 +67,7 @@ impl CrateOwnerInvitation {
                 .optional()?;
 
             if let Some(existing) = existing {
-                if existing.is_expired(config) {
+                if existing.is_expired(config, now) {
                     diesel::delete(&existing).execute(conn)?;
                 }
             }
@@ -96,8 +107,14 @@ impl CrateOwnerInvitation {
             .first::<Self>(conn)
     }
 
-    pub fn accept(self, conn: &mut PgConnection, config: &config::Server) -> AppResult<()> {
-        if self.is_expired(config) {
+    pub fn accept(
+        self,
+        conn: &mut PgConnection,
+        audit_log: &AuditLog,
+        config: &config::Server,
+        now: NaiveDateTime,
+    ) -> AppResult<()> {
+        if self.is_expired(config, now) {
             let crate_name: String = crates::table
                 .find(self.crate_id)
                 .select(crates::name)
@@ -125,6 +142,16 @@ impl CrateOwnerInvitation {
                 .set(crate_owners::deleted.eq(false))
                 .execute(conn)?;
 
+            let event = AuditEvent::OwnerAction(NewCrateOwnerAction {
+                crate_id: self.crate_id,
+                owner_id: self.invited_user_id,
+                owner_kind: OwnerKind::User,
+                actor_id: Some(self.invited_by_user_id),
+                action: OwnerAction::Add,
+                via: OwnerActionVia::Invitation,
+            });
+            audit_log.record(conn, event)?;
+
             diesel::delete(&self).execute(conn)?;
 
             Ok(())
@@ -140,8 +167,30 @@ impl CrateOwnerInvitation {
         Ok(())
     }
 
-    pub fn is_expired(&self, config: &config::Server) -> bool {
-        self.expires_at(config) <= Utc::now().naive_utc()
+    /// Counts the pending and the expired invitations at `now`.
+    pub fn counts(
+        conn: &mut PgConnection,
+        config: &config::Server,
+        now: NaiveDateTime,
+    ) -> QueryResult<InvitationCounts> {
+        let days = chrono::Duration::days(config.ownership_invitations_expiration_days as i64);
+        let expiry_cut_off = now - days;
+
+        let pending = crate_owner_invitations::table
+            .filter(crate_owner_invitations::created_at.gt(expiry_cut_off))
+            .count()
+            .get_result(conn)?;
+
+        let expired = crate_owner_invitations::table
+            .filter(crate_owner_invitations::created_at.le(expiry_cut_off))
+            .count()
+            .get_result(conn)?;
+
+        Ok(InvitationCounts { pending, expired })
+    }
+
+    pub fn is_expired(&self, config: &config::Server, now: NaiveDateTime) -> bool {
+        self.expires_at(config) <= now
     }
 
     pub fn expires_at(&self, config: &config::Server) -> NaiveDateTime {
@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use crate::models::Crate;
+use crate::schema::crate_description_translations;
+use crate::util::accept_language::AcceptLanguage;
+
+/// A translation of the description of a crate, provided by its owners.
+#[derive(Queryable, Selectable, Identifiable, Associations, Clone, Debug)]
+#[diesel(
+    table_name = crate_description_translations,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(crate_id, locale),
+    belongs_to(Crate),
+)]
+pub struct DescriptionTranslation {
+    pub crate_id: i32,
+    pub locale: String,
+    pub description: String,
+    pub updated_at: NaiveDateTime,
+}
+
+impl DescriptionTranslation {
+    /// Returns the translated descriptions of the crates that best match the
+    /// `Accept-Language` header, by crate ID.
+    ///
+    /// Crates without a matching translation are left out, so that the
+    /// description of their manifest is used instead.
+    pub fn best_matches(
+        conn: &mut PgConnection,
+        crate_ids: &[i32],
+        accept_language: &AcceptLanguage,
+    ) -> QueryResult<HashMap<i32, String>> {
+        if accept_language.is_empty() || crate_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let translations: Vec<DescriptionTranslation> = crate_description_translations::table
+            .filter(crate_description_translations::crate_id.eq_any(crate_ids))
+            .select(DescriptionTranslation::as_select())
+            .load(conn)?;
+
+        let mut by_crate = HashMap::<i32, Vec<DescriptionTranslation>>::new();
+        for translation in translations {
+            by_crate
+                .entry(translation.crate_id)
+                .or_default()
+                .push(translation);
+        }
+
+        let best_matches = by_crate
+            .into_iter()
+            .filter_map(|(crate_id, mut translations)| {
+                let locales = translations
+                    .iter()
+                    .map(|t| t.locale.as_str())
+                    .collect::<Vec<_>>();
+
+                let locale = accept_language.best_match(&locales)?.to_string();
+                let index = translations.iter().position(|t| t.locale == locale)?;
+                Some((crate_id, translations.swap_remove(index).description))
+            })
+            .collect();
+
+        Ok(best_matches)
+    }
+}
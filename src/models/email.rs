@@ -1,4 +1,5 @@
 use chrono::NaiveDateTime;
+use diesel::prelude::*;
 use secrecy::SecretString;
 
 use crate::models::User;
@@ -14,8 +15,39 @@ pub struct Email {
     #[diesel(deserialize_as = String, serialize_as = String)]
     pub token: SecretString,
     pub token_generated_at: Option<NaiveDateTime>,
+    pub is_primary: bool,
 }
 
+impl Email {
+    /// Returns all email addresses of the user, starting with the primary
+    /// email address.
+    pub fn for_user(conn: &mut PgConnection, user_id: i32) -> QueryResult<Vec<Email>> {
+        emails::table
+            .filter(emails::user_id.eq(user_id))
+            .order((emails::is_primary.desc(), emails::id.asc()))
+            .load(conn)
+    }
+
+    /// Makes this email address the primary email address of its user.
+    pub fn make_primary(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        conn.transaction(|conn| {
+            diesel::update(emails::table)
+                .filter(emails::user_id.eq(self.user_id))
+                .filter(emails::is_primary)
+                .set(emails::is_primary.eq(false))
+                .execute(conn)?;
+
+            diesel::update(self)
+                .set(emails::is_primary.eq(true))
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+}
+
+/// A new email address. The first email address of a user automatically
+/// becomes the primary email address.
 #[derive(Debug, Insertable, AsChangeset)]
 #[diesel(table_name = emails, check_for_backend(diesel::pg::Pg))]
 pub struct NewEmail<'a> {
@@ -0,0 +1,106 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{exists, select};
+use diesel::prelude::*;
+
+use crate::models::{OwnerKind, User};
+use crate::schema::{crate_owner_invitations, crate_owners, invitation_blocks, users};
+
+/// A user whose crate ownership invitations are rejected by another user.
+#[derive(Clone, Debug, Queryable, Identifiable)]
+#[diesel(primary_key(user_id, blocked_user_id))]
+pub struct InvitationBlock {
+    pub user_id: i32,
+    pub blocked_user_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl InvitationBlock {
+    /// Rejects all future invitations from `blocked_user_id` to `user_id`,
+    /// and removes the pending invitations between the two users.
+    pub fn block(conn: &mut PgConnection, user_id: i32, blocked_user_id: i32) -> QueryResult<()> {
+        conn.transaction(|conn| {
+            diesel::insert_into(invitation_blocks::table)
+                .values((
+                    invitation_blocks::user_id.eq(user_id),
+                    invitation_blocks::blocked_user_id.eq(blocked_user_id),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            diesel::delete(crate_owner_invitations::table)
+                .filter(crate_owner_invitations::invited_user_id.eq(user_id))
+                .filter(crate_owner_invitations::invited_by_user_id.eq(blocked_user_id))
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    /// Accepts invitations from `blocked_user_id` to `user_id` again, and
+    /// returns whether the user was blocked before.
+    pub fn unblock(
+        conn: &mut PgConnection,
+        user_id: i32,
+        blocked_user_id: i32,
+    ) -> QueryResult<bool> {
+        let deleted = diesel::delete(invitation_blocks::table.find((user_id, blocked_user_id)))
+            .execute(conn)?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Returns the users that are blocked by `user_id`, ordered by login.
+    pub fn blocked_users(conn: &mut PgConnection, user_id: i32) -> QueryResult<Vec<User>> {
+        invitation_blocks::table
+            .inner_join(users::table.on(users::id.eq(invitation_blocks::blocked_user_id)))
+            .filter(invitation_blocks::user_id.eq(user_id))
+            .select(users::all_columns)
+            .order(users::gh_login)
+            .load(conn)
+    }
+
+    /// Returns whether `invited_user` rejects ownership invitations from
+    /// `inviter_id`.
+    ///
+    /// Invitations are rejected if the inviter was blocked explicitly, or if
+    /// the invited user blocks unsolicited invitations and the two users do
+    /// not own a crate together.
+    pub fn rejects(
+        conn: &mut PgConnection,
+        invited_user: &User,
+        inviter_id: i32,
+    ) -> QueryResult<bool> {
+        let blocked = invitation_blocks::table.find((invited_user.id, inviter_id));
+        if select(exists(blocked)).get_result(conn)? {
+            return Ok(true);
+        }
+
+        if !invited_user.block_unsolicited_invitations {
+            return Ok(false);
+        }
+
+        let invited_owners = alias!(crate_owners as invited_owners);
+        let invited_user_crates = invited_owners
+            .filter(invited_owners.field(crate_owners::deleted).eq(false))
+            .filter(
+                invited_owners
+                    .field(crate_owners::owner_kind)
+                    .eq(OwnerKind::User),
+            )
+            .filter(
+                invited_owners
+                    .field(crate_owners::owner_id)
+                    .eq(invited_user.id),
+            )
+            .select(invited_owners.field(crate_owners::crate_id));
+
+        let shared_crates = crate_owners::table
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+            .filter(crate_owners::owner_id.eq(inviter_id))
+            .filter(crate_owners::crate_id.eq_any(invited_user_crates));
+
+        let owns_crate_together: bool = select(exists(shared_crates)).get_result(conn)?;
+        Ok(!owns_crate_together)
+    }
+}
@@ -8,14 +8,16 @@ use diesel::sql_types::{Bool, Text};
 use secrecy::{ExposeSecret, SecretString};
 
 use crate::app::App;
+use crate::audit::{AuditEvent, AuditLog};
 use crate::controllers::helpers::pagination::*;
-use crate::email::Email;
+use crate::email::{Locale, LocalizedEmail};
 use crate::models::version::TopVersions;
 use crate::models::{
-    CrateOwner, CrateOwnerInvitation, Dependency, NewCrateOwnerInvitationOutcome, Owner, OwnerKind,
-    ReverseDependency, User, Version,
+    CrateOwner, CrateOwnerInvitation, Dependency, InvitationBlock, NewCrateOwnerAction,
+    NewCrateOwnerInvitationOutcome, Owner, OwnerAction, OwnerActionVia, OwnerKind,
+    RecommendedVersion, ReverseDependency, User, Version, VersionQuarantine,
 };
-use crate::util::errors::{version_not_found, AppResult};
+use crate::util::errors::{forbidden, version_not_found, AppResult};
 
 use crate::models::helpers::with_count::*;
 use crate::schema::*;
@@ -118,7 +120,12 @@ impl<'a> NewCrate<'a> {
             .get_result(conn)
     }
 
-    pub fn create(&self, conn: &mut PgConnection, user_id: i32) -> QueryResult<Crate> {
+    pub fn create(
+        &self,
+        conn: &mut PgConnection,
+        user_id: i32,
+        audit_log: &AuditLog,
+    ) -> QueryResult<Crate> {
         conn.transaction(|conn| {
             let krate: Crate = diesel::insert_into(crates::table)
                 .values(self)
@@ -138,6 +145,16 @@ impl<'a> NewCrate<'a> {
                 .values(&owner)
                 .execute(conn)?;
 
+            let event = AuditEvent::OwnerAction(NewCrateOwnerAction {
+                crate_id: krate.id,
+                owner_id: user_id,
+                owner_kind: OwnerKind::User,
+                actor_id: Some(user_id),
+                action: OwnerAction::Add,
+                via: OwnerActionVia::Publish,
+            });
+            audit_log.record(conn, event)?;
+
             Ok(krate)
         })
     }
@@ -364,8 +381,23 @@ impl Crate {
         match owner {
             // Users are invited and must accept before being added
             Owner::User(user) => {
+                if InvitationBlock::rejects(conn, &user, req_user.id)? {
+                    return Err(forbidden(format!(
+                        "user {} does not accept ownership invitations from you",
+                        user.gh_login
+                    )));
+                }
+
                 let config = &app.config;
-                match CrateOwnerInvitation::create(user.id, req_user.id, self.id, conn, config)? {
+                let now = app.clock.naive_now();
+                match CrateOwnerInvitation::create(
+                    user.id,
+                    req_user.id,
+                    self.id,
+                    conn,
+                    config,
+                    now,
+                )? {
                     NewCrateOwnerInvitationOutcome::InviteCreated { plaintext_token } => {
                         if let Ok(Some(recipient)) = user.verified_email(conn) {
                             // Swallow any error. Whether or not the email is sent, the invitation
@@ -378,7 +410,8 @@ impl Crate {
                                 token: plaintext_token,
                             };
 
-                            let _ = app.emails.send(&recipient, email);
+                            let locale = Locale::from_preference(&user.locale);
+                            let _ = app.emails.send_localized(&recipient, locale, email);
                         }
 
                         Ok(format!(
@@ -407,6 +440,16 @@ impl Crate {
                     .set(crate_owners::deleted.eq(false))
                     .execute(conn)?;
 
+                let event = AuditEvent::OwnerAction(NewCrateOwnerAction {
+                    crate_id: self.id,
+                    owner_id: owner.id(),
+                    owner_kind: OwnerKind::Team,
+                    actor_id: Some(req_user.id),
+                    action: OwnerAction::Add,
+                    via: OwnerActionVia::Direct,
+                });
+                app.audit_log.record(conn, event)?;
+
                 Ok(format!(
                     "team {} has been added as an owner of crate {}",
                     owner.login(),
@@ -416,23 +459,48 @@ impl Crate {
         }
     }
 
-    pub fn owner_remove(&self, conn: &mut PgConnection, login: &str) -> AppResult<()> {
+    pub fn owner_remove(
+        &self,
+        app: &App,
+        conn: &mut PgConnection,
+        req_user: &User,
+        login: &str,
+    ) -> AppResult<()> {
         let owner = Owner::find_by_login(conn, login)?;
 
         let target = crate_owners::table.find((self.id(), owner.id(), owner.kind()));
-        diesel::update(target)
+        let removed = diesel::update(target)
+            .filter(crate_owners::deleted.eq(false))
             .set(crate_owners::deleted.eq(true))
             .execute(conn)?;
+
+        if removed > 0 {
+            let event = AuditEvent::OwnerAction(NewCrateOwnerAction {
+                crate_id: self.id,
+                owner_id: owner.id(),
+                owner_kind: owner.owner_kind(),
+                actor_id: Some(req_user.id),
+                action: OwnerAction::Remove,
+                via: OwnerActionVia::Direct,
+            });
+            app.audit_log.record(conn, event)?;
+        }
+
         Ok(())
     }
 
     /// Returns (dependency, dependent crate name, dependent crate downloads)
+    ///
+    /// The dependents are the crates whose highest version depends on this
+    /// crate, where yanked versions are only considered if `include_yanked`
+    /// is set.
     #[instrument(skip_all, fields(krate.name = %self.name))]
     pub(crate) fn reverse_dependencies(
         &self,
         conn: &mut PgConnection,
         options: PaginationOptions,
-    ) -> QueryResult<(Vec<ReverseDependency>, i64)> {
+        include_yanked: bool,
+    ) -> QueryResult<Paginated<ReverseDependency>> {
         use diesel::sql_query;
         use diesel::sql_types::{BigInt, Integer};
 
@@ -442,12 +510,19 @@ impl Crate {
                 .bind::<Integer, _>(self.id)
                 .bind::<BigInt, _>(offset)
                 .bind::<BigInt, _>(options.per_page)
+                .bind::<Bool, _>(options.include_total)
+                .bind::<Bool, _>(include_yanked)
                 .load(conn)?;
 
-        Ok(rows.records_and_total())
+        Ok(Paginated::new(rows, options))
     }
 
     /// Gather all the necessary data to write an index metadata file
+    ///
+    /// Quarantined versions are marked as yanked, so that cargo does not
+    /// select them for new lockfiles while they are reviewed. The entry of the
+    /// recommended version is marked as `recommended`, unless it is yanked.
+    /// Versions whose scheduled release is still pending are not included.
     pub fn index_metadata(
         &self,
         conn: &mut PgConnection,
@@ -466,6 +541,10 @@ impl Crate {
 
         let deps = deps.grouped_by(&versions);
 
+        let version_ids = versions.iter().map(|v| v.id).collect::<Vec<_>>();
+        let quarantined = VersionQuarantine::quarantined_version_ids(conn, &version_ids)?;
+        let recommended_id = RecommendedVersion::version_id_for_crate(conn, self.id)?;
+
         versions
             .into_iter()
             .zip(deps)
@@ -513,15 +592,19 @@ impl Crate {
                     (Some(features2), Some(2))
                 };
 
+                let yanked = version.yanked || quarantined.contains(&version.id);
+                let recommended = (recommended_id == Some(version.id) && !yanked).then_some(true);
+
                 let krate = crates_io_index::Crate {
                     name: self.name.clone(),
                     vers: version.num.to_string(),
                     cksum: version.checksum,
-                    yanked: Some(version.yanked),
+                    yanked: Some(yanked),
                     deps,
                     features,
                     links: version.links,
                     rust_version: version.rust_version,
+                    recommended,
                     features2,
                     v,
                 };
@@ -539,19 +622,16 @@ struct OwnerInviteEmail<'a> {
     token: SecretString,
 }
 
-impl Email for OwnerInviteEmail<'_> {
-    const SUBJECT: &'static str = "Crate ownership invitation";
-
-    fn body(&self) -> String {
-        format!(
-            "{user_name} has invited you to become an owner of the crate {crate_name}!\n
-Visit https://{domain}/accept-invite/{token} to accept this invitation,
-or go to https://{domain}/me/pending-invites to manage all of your crate ownership invitations.",
-            user_name = self.user_name,
-            domain = self.domain,
-            crate_name = self.crate_name,
-            token = self.token.expose_secret(),
-        )
+impl LocalizedEmail for OwnerInviteEmail<'_> {
+    const TEMPLATE: &'static str = "owner_invite";
+
+    fn context(&self) -> minijinja::Value {
+        minijinja::context! {
+            inviter => self.user_name,
+            domain => self.domain,
+            crate_name => self.crate_name,
+            token => self.token.expose_secret(),
+        }
     }
 }
 
@@ -560,12 +640,16 @@ pub trait CrateVersions {
         self.all_versions().filter(versions::yanked.eq(false))
     }
 
+    /// Returns the versions of the crate, including the yanked versions but
+    /// not the versions whose scheduled release is still pending.
     fn all_versions(&self) -> versions::BoxedQuery<'_, Pg>;
 }
 
 impl CrateVersions for Crate {
     fn all_versions(&self) -> versions::BoxedQuery<'_, Pg> {
-        Version::belonging_to(self).into_boxed()
+        Version::belonging_to(self)
+            .filter(Version::is_released())
+            .into_boxed()
     }
 }
 
@@ -577,7 +661,9 @@ impl CrateVersions for Vec<Crate> {
 
 impl CrateVersions for [Crate] {
     fn all_versions(&self) -> versions::BoxedQuery<'_, Pg> {
-        Version::belonging_to(self).into_boxed()
+        Version::belonging_to(self)
+            .filter(Version::is_released())
+            .into_boxed()
     }
 }
 
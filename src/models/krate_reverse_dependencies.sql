@@ -1,34 +1,45 @@
+WITH dependents AS (
+    SELECT
+        crate_downloads.downloads AS crate_downloads,
+        crates.name AS crate_name,
+        versions.id AS version_id
+    FROM
+    -- We only want the crates whose *max* version is dependent, so we join on a
+    -- subselect that includes the versions with their ordinal position. Yanked
+    -- versions are only considered if they are included explicitly, and
+    -- versions whose scheduled release is still pending are never considered.
+    (
+        SELECT DISTINCT ON (crate_id)
+           crate_id, semver_no_prerelease, id
+        FROM versions
+        WHERE (NOT yanked OR $5)
+          AND id NOT IN (
+              SELECT version_id FROM scheduled_releases
+              WHERE resolved_at IS NULL AND version_id IS NOT NULL
+          )
+        ORDER BY
+            crate_id,
+            semver_no_prerelease DESC NULLS LAST,
+            id DESC
+    ) versions
+    INNER JOIN crates
+      ON crates.id = versions.crate_id
+    INNER JOIN crate_downloads
+      ON crate_downloads.crate_id = crates.id
+    WHERE versions.id IN (SELECT version_id FROM dependencies WHERE crate_id = $1)
+)
 SELECT
-    dependencies.*, crate_downloads, crate_name, total
+    dependencies.*, crate_downloads, crate_name,
+    -- The count is only evaluated if it was requested, see `?meta=minimal`
+    CASE WHEN $4 THEN (SELECT COUNT(*) FROM dependents) ELSE 0 END AS total
 FROM (
     -- Apply pagination to the crates
-    SELECT *, COUNT(*) OVER () as total FROM (
-        SELECT
-            crate_downloads.downloads AS crate_downloads,
-            crates.name AS crate_name,
-            versions.id AS version_id
-        FROM
-        -- We only want the crates whose *max* version is dependent, so we join on a
-        -- subselect that includes the versions with their ordinal position
-        (
-            SELECT DISTINCT ON (crate_id)
-               crate_id, semver_no_prerelease, id
-            FROM versions
-            WHERE NOT yanked
-            ORDER BY
-                crate_id,
-                semver_no_prerelease DESC NULLS LAST,
-                id DESC
-        ) versions
-        INNER JOIN crates
-          ON crates.id = versions.crate_id
-        INNER JOIN crate_downloads
-          ON crate_downloads.crate_id = crates.id
-        WHERE versions.id IN (SELECT version_id FROM dependencies WHERE crate_id = $1)
-    ) c
+    SELECT * FROM dependents
     ORDER BY
         crate_downloads DESC,
         crate_name ASC
+    OFFSET $2
+    LIMIT $3
 ) crates
 -- Multiple dependencies can exist, we only want first one
 CROSS JOIN LATERAL (
@@ -38,5 +49,6 @@ CROSS JOIN LATERAL (
     ORDER BY id ASC
     LIMIT 1
 ) dependencies
-OFFSET $2
-LIMIT $3
+ORDER BY
+    crate_downloads DESC,
+    crate_name ASC
@@ -99,9 +99,13 @@ impl Owner {
     }
 
     pub fn kind(&self) -> i32 {
+        self.owner_kind() as i32
+    }
+
+    pub fn owner_kind(&self) -> OwnerKind {
         match self {
-            Owner::User(_) => OwnerKind::User as i32,
-            Owner::Team(_) => OwnerKind::Team as i32,
+            Owner::User(_) => OwnerKind::User,
+            Owner::Team(_) => OwnerKind::Team,
         }
     }
 
@@ -0,0 +1,77 @@
+use crate::models::Version;
+use crate::schema::version_provenance;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use ipnetwork::IpNetwork;
+
+/// Records how a version was published: with which API token, from which CI
+/// service, client and IP address.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Selectable)]
+#[diesel(
+    table_name = version_provenance,
+    primary_key(version_id),
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(Version),
+)]
+pub struct VersionProvenance {
+    pub version_id: i32,
+    pub api_token_id: Option<i32>,
+    pub api_token_name: Option<String>,
+    pub ci_service: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<IpNetwork>,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionProvenance {
+    pub fn by_version(conn: &mut PgConnection, version: &Version) -> QueryResult<Option<Self>> {
+        Self::belonging_to(version)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Loads the provenance records of the given versions, in the same order
+    /// as the versions.
+    pub fn for_versions(
+        conn: &mut PgConnection,
+        versions: &[Version],
+    ) -> QueryResult<Vec<Option<Self>>> {
+        Ok(Self::belonging_to(versions)
+            .select(Self::as_select())
+            .load(conn)?
+            .grouped_by(versions)
+            .into_iter()
+            .map(|provenances| provenances.into_iter().next())
+            .collect())
+    }
+
+    /// Returns whether the version was published with an API token, as
+    /// opposed to a session cookie.
+    ///
+    /// This is also `true` if the token was deleted since.
+    pub fn published_with_token(&self) -> bool {
+        self.api_token_id.is_some() || self.api_token_name.is_some()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = version_provenance, check_for_backend(diesel::pg::Pg))]
+pub struct NewVersionProvenance<'a> {
+    pub version_id: i32,
+    pub api_token_id: Option<i32>,
+    pub api_token_name: Option<&'a str>,
+    pub ci_service: Option<String>,
+    pub user_agent: Option<&'a str>,
+    pub ip: Option<IpNetwork>,
+}
+
+impl NewVersionProvenance<'_> {
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<()> {
+        diesel::insert_into(version_provenance::table)
+            .values(self)
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::now;
+use diesel::prelude::*;
+use secrecy::SecretString;
+use std::time::Duration;
+
+use crate::schema::publish_nonces;
+use crate::util::token::{generate_secure_alphanumeric_string, HashedToken};
+
+const NONCE_LENGTH: usize = 32;
+
+/// A one-time nonce that authorizes a single publish of a crate version.
+///
+/// Publish requests that include the nonce can't be replayed, since the
+/// nonce is consumed by the first successful publish. Only a hash of the
+/// nonce is stored in the database.
+pub struct PublishNonce {
+    pub nonce: SecretString,
+    pub expires_at: NaiveDateTime,
+}
+
+impl PublishNonce {
+    /// Generates a new nonce for the given crate version, and removes the
+    /// expired nonces of the user.
+    pub fn create(
+        conn: &mut PgConnection,
+        user_id: i32,
+        crate_name: &str,
+        version: &str,
+        lifetime: Duration,
+    ) -> QueryResult<Self> {
+        diesel::delete(publish_nonces::table)
+            .filter(publish_nonces::user_id.eq(user_id))
+            .filter(publish_nonces::expires_at.le(now))
+            .execute(conn)?;
+
+        let nonce = generate_secure_alphanumeric_string(NONCE_LENGTH);
+        let lifetime = chrono::Duration::seconds(lifetime.as_secs() as i64);
+        let expires_at = Utc::now().naive_utc() + lifetime;
+
+        diesel::insert_into(publish_nonces::table)
+            .values((
+                publish_nonces::nonce.eq(HashedToken::hash(&nonce)),
+                publish_nonces::user_id.eq(user_id),
+                publish_nonces::crate_name.eq(crate_name),
+                publish_nonces::version.eq(version),
+                publish_nonces::expires_at.eq(expires_at),
+            ))
+            .execute(conn)?;
+
+        Ok(Self {
+            nonce: nonce.into(),
+            expires_at,
+        })
+    }
+
+    /// Consumes the nonce, and returns whether it was a valid nonce of the
+    /// user for the given crate version.
+    pub fn consume(
+        conn: &mut PgConnection,
+        nonce: &str,
+        user_id: i32,
+        crate_name: &str,
+        version: &str,
+    ) -> QueryResult<bool> {
+        let deleted = diesel::delete(publish_nonces::table.find(HashedToken::hash(nonce)))
+            .filter(publish_nonces::user_id.eq(user_id))
+            .filter(publish_nonces::crate_name.eq(crate_name))
+            .filter(publish_nonces::version.eq(version))
+            .filter(publish_nonces::expires_at.gt(now))
+            .execute(conn)?;
+
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use crate::models::Crate;
+use crate::schema::{crate_recommended_versions, versions};
+
+/// A version of a crate that its owners recommend, e.g. the latest release of
+/// a long-term support line.
+///
+/// Yanked versions are never recommended. If the recommended version is
+/// yanked after it was chosen, the crate has no recommended version until it
+/// is unyanked again.
+#[derive(Queryable, Selectable, Identifiable, Associations, Clone, Debug)]
+#[diesel(
+    table_name = crate_recommended_versions,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(crate_id),
+    belongs_to(Crate),
+)]
+pub struct RecommendedVersion {
+    pub crate_id: i32,
+    pub version_id: i32,
+    pub updated_by: Option<i32>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl RecommendedVersion {
+    /// Returns the numbers of the recommended versions of the crates, by
+    /// crate ID.
+    pub fn for_crates(
+        conn: &mut PgConnection,
+        crate_ids: &[i32],
+    ) -> QueryResult<HashMap<i32, String>> {
+        if crate_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let recommended: Vec<(i32, String)> = crate_recommended_versions::table
+            .inner_join(versions::table)
+            .filter(crate_recommended_versions::crate_id.eq_any(crate_ids))
+            .filter(versions::yanked.eq(false))
+            .select((crate_recommended_versions::crate_id, versions::num))
+            .load(conn)?;
+
+        Ok(recommended.into_iter().collect())
+    }
+
+    /// Returns the ID of the recommended version of the crate.
+    pub fn version_id_for_crate(
+        conn: &mut PgConnection,
+        crate_id: i32,
+    ) -> QueryResult<Option<i32>> {
+        crate_recommended_versions::table
+            .find(crate_id)
+            .select(crate_recommended_versions::version_id)
+            .first(conn)
+            .optional()
+    }
+
+    /// Recommends the version, replacing the previous recommendation of the
+    /// crate.
+    pub fn set(
+        conn: &mut PgConnection,
+        crate_id: i32,
+        version_id: i32,
+        user_id: i32,
+    ) -> QueryResult<()> {
+        diesel::insert_into(crate_recommended_versions::table)
+            .values((
+                crate_recommended_versions::crate_id.eq(crate_id),
+                crate_recommended_versions::version_id.eq(version_id),
+                crate_recommended_versions::updated_by.eq(user_id),
+            ))
+            .on_conflict(crate_recommended_versions::crate_id)
+            .do_update()
+            .set((
+                crate_recommended_versions::version_id.eq(version_id),
+                crate_recommended_versions::updated_by.eq(user_id),
+                crate_recommended_versions::updated_at.eq(now),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Removes the recommendation of the crate, and returns whether the crate
+    /// had a recommended version.
+    pub fn remove(conn: &mut PgConnection, crate_id: i32) -> QueryResult<bool> {
+        let deleted =
+            diesel::delete(crate_recommended_versions::table.find(crate_id)).execute(conn)?;
+
+        Ok(deleted > 0)
+    }
+}
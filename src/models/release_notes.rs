@@ -0,0 +1,67 @@
+use crate::models::Version;
+use crate::schema::version_release_notes;
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+
+/// Release notes that the owners of a crate attached to a version after
+/// publishing it.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Selectable)]
+#[diesel(
+    table_name = version_release_notes,
+    primary_key(version_id),
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(Version),
+)]
+pub struct ReleaseNotes {
+    pub version_id: i32,
+    pub markdown: String,
+    pub html: String,
+    pub updated_by: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+impl ReleaseNotes {
+    pub fn by_version(conn: &mut PgConnection, version: &Version) -> QueryResult<Option<Self>> {
+        Self::belonging_to(version)
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Deletes the release notes of the version, and returns whether there
+    /// were any.
+    pub fn delete(conn: &mut PgConnection, version: &Version) -> QueryResult<bool> {
+        let deleted = diesel::delete(Self::belonging_to(version)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = version_release_notes, check_for_backend(diesel::pg::Pg))]
+pub struct NewReleaseNotes<'a> {
+    pub version_id: i32,
+    pub markdown: &'a str,
+    pub html: &'a str,
+    pub updated_by: i32,
+}
+
+impl NewReleaseNotes<'_> {
+    /// Inserts the release notes, or replaces the existing release notes of
+    /// the version.
+    pub fn save(&self, conn: &mut PgConnection) -> QueryResult<ReleaseNotes> {
+        diesel::insert_into(version_release_notes::table)
+            .values(self)
+            .on_conflict(version_release_notes::version_id)
+            .do_update()
+            .set((
+                version_release_notes::markdown.eq(excluded(version_release_notes::markdown)),
+                version_release_notes::html.eq(excluded(version_release_notes::html)),
+                version_release_notes::updated_by.eq(excluded(version_release_notes::updated_by)),
+                version_release_notes::updated_at.eq(now),
+            ))
+            .returning(ReleaseNotes::as_returning())
+            .get_result(conn)
+    }
+}
@@ -0,0 +1,104 @@
+//! Settings that administrators can change at runtime through the admin API,
+//! so that no redeploy is necessary to react to an incident.
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::{blocked_routes, excluded_crate_names};
+use crate::util::rfc3339;
+
+/// An HTTP route pattern that is temporarily blocked.
+#[derive(Clone, Queryable, Identifiable, Selectable, Debug, Serialize)]
+#[diesel(
+    table_name = blocked_routes,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(route)
+)]
+pub struct BlockedRoute {
+    pub route: String,
+    #[serde(skip)]
+    pub created_by: Option<i32>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl BlockedRoute {
+    pub fn all(conn: &mut PgConnection) -> QueryResult<Vec<BlockedRoute>> {
+        blocked_routes::table
+            .select(BlockedRoute::as_select())
+            .order(blocked_routes::route)
+            .load(conn)
+    }
+
+    /// Blocks the route, or returns `None` if it is blocked already.
+    pub fn insert(
+        conn: &mut PgConnection,
+        route: &str,
+        created_by: i32,
+    ) -> QueryResult<Option<BlockedRoute>> {
+        diesel::insert_into(blocked_routes::table)
+            .values((
+                blocked_routes::route.eq(route),
+                blocked_routes::created_by.eq(created_by),
+            ))
+            .on_conflict_do_nothing()
+            .returning(BlockedRoute::as_returning())
+            .get_result(conn)
+            .optional()
+    }
+
+    /// Unblocks the route, and returns whether it was blocked.
+    pub fn delete(conn: &mut PgConnection, route: &str) -> QueryResult<bool> {
+        let deleted = diesel::delete(blocked_routes::table.find(route)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
+
+/// The name of a crate that is excluded from the most downloaded lists of the
+/// summary endpoint.
+#[derive(Clone, Queryable, Identifiable, Selectable, Debug, Serialize)]
+#[diesel(
+    table_name = excluded_crate_names,
+    check_for_backend(diesel::pg::Pg),
+    primary_key(name)
+)]
+pub struct ExcludedCrateName {
+    pub name: String,
+    #[serde(skip)]
+    pub created_by: Option<i32>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl ExcludedCrateName {
+    pub fn all(conn: &mut PgConnection) -> QueryResult<Vec<ExcludedCrateName>> {
+        excluded_crate_names::table
+            .select(ExcludedCrateName::as_select())
+            .order(excluded_crate_names::name)
+            .load(conn)
+    }
+
+    /// Excludes the crate name, or returns `None` if it is excluded already.
+    pub fn insert(
+        conn: &mut PgConnection,
+        name: &str,
+        created_by: i32,
+    ) -> QueryResult<Option<ExcludedCrateName>> {
+        diesel::insert_into(excluded_crate_names::table)
+            .values((
+                excluded_crate_names::name.eq(name),
+                excluded_crate_names::created_by.eq(created_by),
+            ))
+            .on_conflict_do_nothing()
+            .returning(ExcludedCrateName::as_returning())
+            .get_result(conn)
+            .optional()
+    }
+
+    /// Removes the exclusion of the crate name, and returns whether it was
+    /// excluded.
+    pub fn delete(conn: &mut PgConnection, name: &str) -> QueryResult<bool> {
+        let deleted = diesel::delete(excluded_crate_names::table.find(name)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::scheduled_releases;
+use crate::util::rfc3339;
+
+/// The outcome of a scheduled release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledReleaseResolution {
+    /// The version was released, at the scheduled time or early.
+    Released,
+    /// The release was cancelled and the version was deleted.
+    Cancelled,
+}
+
+impl ScheduledReleaseResolution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Released => "released",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A version that was published with a release time.
+///
+/// Until the release, the version is hidden from the index and the API (see
+/// [`Version::is_released()`](crate::models::Version::is_released)) and its
+/// crate file is kept in a private location. Resolved schedules are kept as
+/// an audit trail, even if the version was deleted.
+#[derive(Clone, Queryable, Identifiable, Selectable, Debug, Serialize)]
+#[diesel(table_name = scheduled_releases, check_for_backend(diesel::pg::Pg))]
+pub struct ScheduledRelease {
+    pub id: i32,
+    #[serde(skip)]
+    pub version_id: Option<i32>,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    #[serde(rename = "version")]
+    pub version_num: String,
+    #[serde(with = "rfc3339")]
+    pub release_at: NaiveDateTime,
+    pub scheduled_by: i32,
+    #[serde(with = "rfc3339")]
+    pub scheduled_at: NaiveDateTime,
+    #[serde(skip)]
+    pub upload_id: String,
+    #[serde(skip)]
+    pub readme_job: Option<serde_json::Value>,
+    pub resolution: Option<String>,
+    pub resolved_by: Option<i32>,
+    #[serde(with = "rfc3339::option")]
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+impl ScheduledRelease {
+    /// Returns the pending release of the version of the crate, if there is
+    /// one.
+    pub fn pending(
+        conn: &mut PgConnection,
+        crate_name: &str,
+        version_num: &str,
+    ) -> QueryResult<Option<Self>> {
+        scheduled_releases::table
+            .filter(scheduled_releases::crate_name.eq(crate_name))
+            .filter(scheduled_releases::version_num.eq(version_num))
+            .filter(scheduled_releases::version_id.is_not_null())
+            .filter(scheduled_releases::resolved_at.is_null())
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the pending releases of the crate, in the order of their
+    /// release.
+    pub fn pending_for_crate(conn: &mut PgConnection, crate_name: &str) -> QueryResult<Vec<Self>> {
+        scheduled_releases::table
+            .filter(scheduled_releases::crate_name.eq(crate_name))
+            .filter(scheduled_releases::version_id.is_not_null())
+            .filter(scheduled_releases::resolved_at.is_null())
+            .select(Self::as_select())
+            .order((scheduled_releases::release_at, scheduled_releases::id))
+            .load(conn)
+    }
+
+    /// Returns the pending releases whose release time has passed, oldest
+    /// first.
+    pub fn due(conn: &mut PgConnection) -> QueryResult<Vec<Self>> {
+        scheduled_releases::table
+            .filter(scheduled_releases::release_at.le(now))
+            .filter(scheduled_releases::version_id.is_not_null())
+            .filter(scheduled_releases::resolved_at.is_null())
+            .select(Self::as_select())
+            .order((scheduled_releases::release_at, scheduled_releases::id))
+            .load(conn)
+    }
+
+    /// Locks the row of the release for the current transaction, and returns
+    /// its current state.
+    pub fn lock(&self, conn: &mut PgConnection) -> QueryResult<Self> {
+        scheduled_releases::table
+            .find(self.id)
+            .select(Self::as_select())
+            .for_update()
+            .first(conn)
+    }
+
+    /// Returns the outcome of the schedule, or `None` while the release is
+    /// pending.
+    pub fn outcome(&self) -> Option<ScheduledReleaseResolution> {
+        match self.resolution.as_deref()? {
+            "released" => Some(ScheduledReleaseResolution::Released),
+            "cancelled" => Some(ScheduledReleaseResolution::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Moves the release time to now, so that the version is released by the
+    /// next run of the
+    /// [`ReleaseScheduledVersions`](crate::worker::jobs::ReleaseScheduledVersions)
+    /// job.
+    pub fn release_now(&self, conn: &mut PgConnection) -> QueryResult<Self> {
+        diesel::update(self)
+            .set(scheduled_releases::release_at.eq(now))
+            .returning(Self::as_returning())
+            .get_result(conn)
+    }
+
+    /// Records the outcome of the schedule. The user is `None` if the version
+    /// was released at the scheduled time.
+    pub fn resolve(
+        &self,
+        conn: &mut PgConnection,
+        resolution: ScheduledReleaseResolution,
+        user_id: Option<i32>,
+    ) -> QueryResult<Self> {
+        diesel::update(self)
+            .set((
+                scheduled_releases::resolution.eq(resolution.as_str()),
+                scheduled_releases::resolved_by.eq(user_id),
+                scheduled_releases::resolved_at.eq(now),
+            ))
+            .returning(Self::as_returning())
+            .get_result(conn)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = scheduled_releases, check_for_backend(diesel::pg::Pg))]
+pub struct NewScheduledRelease<'a> {
+    pub version_id: i32,
+    pub crate_name: &'a str,
+    pub version_num: &'a str,
+    pub release_at: NaiveDateTime,
+    pub scheduled_by: i32,
+    pub upload_id: &'a str,
+    pub readme_job: Option<serde_json::Value>,
+}
+
+impl NewScheduledRelease<'_> {
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<ScheduledRelease> {
+        diesel::insert_into(scheduled_releases::table)
+            .values(self)
+            .returning(ScheduledRelease::as_returning())
+            .get_result(conn)
+    }
+}
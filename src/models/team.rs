@@ -2,7 +2,8 @@ use diesel::prelude::*;
 use http::StatusCode;
 
 use crate::app::App;
-use crate::util::errors::{bad_request, custom, AppResult};
+use crate::email::Email;
+use crate::util::errors::{bad_request, custom, AppResult, BoxedAppError};
 
 use crates_io_github::GitHubError;
 use oauth2::AccessToken;
@@ -14,7 +15,7 @@ use crate::sql::lower;
 
 /// For now, just a Github Team. Can be upgraded to other teams
 /// later if desirable.
-#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
+#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug, Clone)]
 pub struct Team {
     /// Unique table id
     pub id: i32,
@@ -148,13 +149,19 @@ impl Team {
         }
 
         let token = AccessToken::new(req_user.gh_access_token.clone());
-        let team = Handle::current()
+        let team = match Handle::current()
             .block_on(app.github.team_by_name(org_name, team_name, &token))
-            .map_err(|_| {
-                bad_request(format_args!(
+        {
+            Ok(team) => team,
+            Err(error @ GitHubError::Unauthorized(_)) => {
+                return Err(Handle::current().block_on(github_error(app, req_user, error)));
+            }
+            Err(_) => {
+                return Err(bad_request(format_args!(
                     "could not find the github team {org_name}/{team_name}"
-                ))
-            })?;
+                )));
+            }
+        };
 
         let org_id = team.organization.id;
 
@@ -165,7 +172,12 @@ impl Team {
             ));
         }
 
-        let org = Handle::current().block_on(app.github.org_by_name(org_name, &token))?;
+        let org = match Handle::current().block_on(app.github.org_by_name(org_name, &token)) {
+            Ok(org) => org,
+            Err(error) => {
+                return Err(Handle::current().block_on(github_error(app, req_user, error)))
+            }
+        };
 
         NewTeam::new(
             &login.to_lowercase(),
@@ -223,7 +235,7 @@ async fn is_gh_org_owner(app: &App, org_id: i32, user: &User) -> AppResult<bool>
     {
         Ok(membership) => Ok(membership.state == "active" && membership.role == "admin"),
         Err(GitHubError::NotFound(_)) => Ok(false),
-        Err(e) => Err(e.into()),
+        Err(e) => Err(github_error(app, user, e).await),
     }
 }
 
@@ -244,10 +256,83 @@ async fn team_with_gh_id_contains_user(
     {
         // Officially how `false` is returned
         Err(GitHubError::NotFound(_)) => return Ok(false),
-        x => x?,
+        Err(e) => return Err(github_error(app, user, e).await),
+        Ok(membership) => membership,
     };
 
     // There is also `state: pending` for which we could possibly give
     // some feedback, but it's not obvious how that should work.
     Ok(membership.state == "active")
 }
+
+/// Converts the error of a GitHub API request that was authenticated with the
+/// access token of `user`.
+///
+/// If GitHub rejected the token, e.g. because the user revoked the
+/// authorization of the crates.io OAuth app, the token is flagged as revoked
+/// and the user is notified, see [`User::flag_revoked_gh_token()`].
+async fn github_error(app: &App, user: &User, error: GitHubError) -> BoxedAppError {
+    if let GitHubError::Unauthorized(_) = error {
+        if let Err(error) = flag_revoked_gh_token(app, user).await {
+            warn!(%error, "Failed to flag revoked GitHub access token");
+        }
+    }
+
+    error.into()
+}
+
+async fn flag_revoked_gh_token(app: &App, user: &User) -> AppResult<()> {
+    let user = user.clone();
+    let user_name = user.gh_login.clone();
+
+    // The request may still fail and roll back its own transaction, so the
+    // token is flagged with a separate connection.
+    let conn = app.db_write().await?;
+    let recipient = conn
+        .interact(move |conn| {
+            if !user.flag_revoked_gh_token(conn)? {
+                return Ok(None);
+            }
+
+            user.verified_email(conn)
+        })
+        .await??;
+
+    warn!(user = %user_name, "GitHub rejected the access token of the user");
+
+    if let Some(recipient) = recipient {
+        let email = GitHubTokenRevokedEmail {
+            user_name: &user_name,
+            domain: &app.emails.domain,
+        };
+
+        if let Err(error) = app.emails.send(&recipient, email) {
+            warn!(?error, "Failed to send revoked GitHub token notification");
+        }
+    }
+
+    Ok(())
+}
+
+struct GitHubTokenRevokedEmail<'a> {
+    user_name: &'a str,
+    domain: &'a str,
+}
+
+impl Email for GitHubTokenRevokedEmail<'_> {
+    const SUBJECT: &'static str = "crates.io: Your GitHub authorization was revoked";
+
+    fn body(&self) -> String {
+        let GitHubTokenRevokedEmail { user_name, domain } = self;
+
+        format!(
+            "Hello {user_name}!
+
+GitHub rejected the authorization of crates.io to access your GitHub account, which usually means that it was revoked in your GitHub settings.
+
+To protect your crates, you were signed out of crates.io, and publishing new versions is blocked until you sign in again at https://{domain}. Your API tokens can still be used for everything else.
+
+If you did not revoke the authorization, please check the security of your GitHub account and contact us at help@crates.io."
+        )
+    }
+}
@@ -70,8 +70,29 @@ impl ApiToken {
         })
     }
 
-    pub fn find_by_api_token(conn: &mut PgConnection, token: &str) -> AppResult<ApiToken> {
-        use diesel::{dsl::now, update};
+    /// Returns whether the crate scopes of the token allow publishing the
+    /// version of the crate.
+    ///
+    /// Unlike [`crate::auth::AuthCheck`], this also checks the version
+    /// requirements of the crate scopes, e.g. so that a token can only be used
+    /// to publish patch releases of an older major version.
+    pub fn allows_publish_of(&self, crate_name: &str, version: &semver::Version) -> bool {
+        match &self.crate_scopes {
+            Some(scopes) if !scopes.is_empty() => scopes
+                .iter()
+                .any(|scope| scope.matches_version(crate_name, version)),
+            _ => true,
+        }
+    }
+
+    /// Finds the token that is neither revoked nor expired at `now`, and
+    /// records `now` as the time of its last use.
+    pub fn find_by_api_token(
+        conn: &mut PgConnection,
+        token: &str,
+        now: NaiveDateTime,
+    ) -> AppResult<ApiToken> {
+        use diesel::update;
 
         let token = HashedToken::parse(token).ok_or_else(InsecurelyGeneratedTokenRevoked::boxed)?;
 
@@ -88,7 +109,7 @@ impl ApiToken {
         // Try updating in a new transaction, if that fails, fall back to reading
         conn.transaction(|conn| {
             update(tokens)
-                .set(api_tokens::last_used_at.eq(now.nullable()))
+                .set(api_tokens::last_used_at.eq(now))
                 .returning(ApiToken::as_returning())
                 .get_result(conn)
         })
@@ -3,6 +3,7 @@ use diesel::deserialize::{self, FromSql};
 use diesel::pg::Pg;
 use diesel::serialize::{self, IsNull, Output, ToSql};
 use diesel::sql_types::Text;
+use semver::{Version, VersionReq};
 use std::io::Write;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, AsExpression, Serialize)]
@@ -54,22 +55,24 @@ impl FromSql<Text, Pg> for EndpointScope {
     }
 }
 
+/// A crate name pattern, optionally followed by `@` and a version requirement
+/// (e.g. `tokio-*` or `serde@~1.0`).
+///
+/// The version requirement only restricts which versions can be published
+/// with the token. Other endpoints only check the crate name pattern.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 #[serde(transparent)]
 pub struct CrateScope {
     pattern: String,
+    #[serde(skip)]
+    version_req: Option<VersionReq>,
 }
 
 impl TryFrom<&str> for CrateScope {
     type Error = String;
 
     fn try_from(pattern: &str) -> Result<Self, Self::Error> {
-        match CrateScope::is_valid_pattern(pattern) {
-            true => Ok(CrateScope {
-                pattern: pattern.to_string(),
-            }),
-            false => Err("Invalid crate scope pattern".to_string()),
-        }
+        CrateScope::try_from(pattern.to_string())
     }
 }
 
@@ -77,13 +80,33 @@ impl TryFrom<String> for CrateScope {
     type Error = String;
 
     fn try_from(pattern: String) -> Result<Self, Self::Error> {
-        match CrateScope::is_valid_pattern(&pattern) {
-            true => Ok(CrateScope { pattern }),
+        let (name_pattern, version_pattern) = split_pattern(&pattern);
+
+        let version_req = version_pattern
+            .map(VersionReq::parse)
+            .transpose()
+            .map_err(|_| "Invalid crate scope version requirement".to_string())?;
+
+        match CrateScope::is_valid_pattern(name_pattern) {
+            true => Ok(CrateScope {
+                pattern,
+                version_req,
+            }),
             false => Err("Invalid crate scope pattern".to_string()),
         }
     }
 }
 
+/// Splits a crate scope pattern into the crate name pattern and the optional
+/// version requirement. `@` is not allowed in crate names, so the first `@`
+/// always separates the two.
+fn split_pattern(pattern: &str) -> (&str, Option<&str>) {
+    match pattern.split_once('@') {
+        Some((name_pattern, version_pattern)) => (name_pattern, Some(version_pattern)),
+        None => (pattern, None),
+    }
+}
+
 impl FromSql<Text, Pg> for CrateScope {
     fn from_sql(bytes: diesel::pg::PgValue<'_>) -> deserialize::Result<Self> {
         let value = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
@@ -112,14 +135,25 @@ impl CrateScope {
     }
 
     pub fn matches(&self, crate_name: &str) -> bool {
-        if self.pattern == "*" {
+        let (name_pattern, _) = split_pattern(&self.pattern);
+        if name_pattern == "*" {
             return true;
         }
 
-        return match self.pattern.strip_suffix('*') {
+        return match name_pattern.strip_suffix('*') {
             Some(prefix) => crate_name.starts_with(prefix),
-            None => crate_name == self.pattern,
+            None => crate_name == name_pattern,
+        };
+    }
+
+    /// Returns whether the scope allows publishing the version of the crate.
+    pub fn matches_version(&self, crate_name: &str, version: &Version) -> bool {
+        let version_matches = match &self.version_req {
+            Some(version_req) => version_req.matches(version),
+            None => true,
         };
+
+        version_matches && self.matches(crate_name)
     }
 }
 
@@ -175,6 +209,14 @@ mod tests {
 
         // invalid characters
         expect_that!(CrateScope::try_from("test#"), err(anything()));
+
+        // version requirements
+        expect_that!(CrateScope::try_from("foo@1.x"), ok(anything()));
+        expect_that!(CrateScope::try_from("foo*@~1.2"), ok(anything()));
+        expect_that!(CrateScope::try_from("*@>=1.0.0, <2.0.0"), ok(anything()));
+        expect_that!(CrateScope::try_from("foo@"), err(anything()));
+        expect_that!(CrateScope::try_from("foo@bar"), err(anything()));
+        expect_that!(CrateScope::try_from("@1.x"), err(anything()));
     }
 
     #[googletest::test]
@@ -205,5 +247,62 @@ mod tests {
         expect_that!(scope("foo-*").matches("foo_bar"), eq(false));
         expect_that!(scope("foo_*").matches("foo-bar"), eq(false));
         expect_that!(scope("foo_*").matches("foo_bar"), eq(true));
+
+        // version requirements are ignored for crate names
+        expect_that!(scope("foo@1.x").matches("foo"), eq(true));
+        expect_that!(scope("foo*@1.x").matches("foo-bar"), eq(true));
+        expect_that!(scope("foo@1.x").matches("bar"), eq(false));
+    }
+
+    #[googletest::test]
+    fn crate_scope_version_matching() {
+        let scope = |pattern: &str| CrateScope::try_from(pattern).unwrap();
+        let version = |version: &str| Version::parse(version).unwrap();
+
+        expect_that!(
+            scope("foo").matches_version("foo", &version("2.0.0")),
+            eq(true)
+        );
+        expect_that!(
+            scope("foo").matches_version("bar", &version("2.0.0")),
+            eq(false)
+        );
+
+        expect_that!(
+            scope("foo@1.x").matches_version("foo", &version("1.0.0")),
+            eq(true)
+        );
+        expect_that!(
+            scope("foo@1.x").matches_version("foo", &version("1.9.3")),
+            eq(true)
+        );
+        expect_that!(
+            scope("foo@1.x").matches_version("foo", &version("2.0.0")),
+            eq(false)
+        );
+        expect_that!(
+            scope("foo@1.x").matches_version("bar", &version("1.0.0")),
+            eq(false)
+        );
+
+        expect_that!(
+            scope("foo@~1.2").matches_version("foo", &version("1.2.7")),
+            eq(true)
+        );
+        expect_that!(
+            scope("foo@~1.2").matches_version("foo", &version("1.3.0")),
+            eq(false)
+        );
+
+        expect_that!(
+            scope("*@1.x").matches_version("bar", &version("1.4.0")),
+            eq(true)
+        );
+    }
+
+    #[googletest::test]
+    fn crate_scope_with_version_serialization() {
+        let scope = assert_ok!(CrateScope::try_from("foo@~1.2"));
+        expect_that!(serde_json::to_string(&scope), ok(eq("\"foo@~1.2\"")));
     }
 }
@@ -0,0 +1,107 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::models::{ApiToken, User};
+use crate::schema::token_anomalies;
+
+/// The kinds of unusual token activity, as stored in the `kind` column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenAnomalyKind {
+    /// A token was used from a network that the user never used before.
+    NewNetwork,
+    /// A token was used from two different networks within a time span
+    /// that makes it unlikely that the same person used it.
+    ImpossibleTravel,
+    /// Unusually many versions were published within a short time.
+    PublishBurst,
+}
+
+impl TokenAnomalyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NewNetwork => "new_network",
+            Self::ImpossibleTravel => "impossible_travel",
+            Self::PublishBurst => "publish_burst",
+        }
+    }
+}
+
+/// Unusual API token activity, as detected by the `detect_token_anomalies`
+/// background job.
+#[derive(Queryable, Selectable, Identifiable, Associations, Clone, Debug)]
+#[diesel(
+    table_name = token_anomalies,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(User),
+    belongs_to(ApiToken),
+)]
+pub struct TokenAnomaly {
+    pub id: i64,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub kind: String,
+    pub details: String,
+    pub publishes_locked: bool,
+    pub confirmation_token: String,
+    pub confirmed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl TokenAnomaly {
+    /// Returns whether the user has an unconfirmed anomaly that locks their
+    /// publishes.
+    pub fn publishes_locked(conn: &mut PgConnection, user_id: i32) -> QueryResult<bool> {
+        let query = token_anomalies::table
+            .filter(token_anomalies::user_id.eq(user_id))
+            .filter(token_anomalies::publishes_locked.eq(true))
+            .filter(token_anomalies::confirmed_at.is_null());
+
+        diesel::select(diesel::dsl::exists(query)).get_result(conn)
+    }
+
+    /// Marks all unconfirmed anomalies of the user that the confirmation
+    /// token belongs to as confirmed, and returns the number of anomalies
+    /// that were confirmed.
+    ///
+    /// A single confirmation covers all of them, since they are reported to
+    /// the user together.
+    pub fn confirm(conn: &mut PgConnection, confirmation_token: &str) -> QueryResult<usize> {
+        let user_id: Option<i32> = token_anomalies::table
+            .filter(token_anomalies::confirmation_token.eq(confirmation_token))
+            .select(token_anomalies::user_id)
+            .first(conn)
+            .optional()?;
+
+        let Some(user_id) = user_id else {
+            return Ok(0);
+        };
+
+        let query = token_anomalies::table
+            .filter(token_anomalies::user_id.eq(user_id))
+            .filter(token_anomalies::confirmed_at.is_null());
+
+        diesel::update(query)
+            .set(token_anomalies::confirmed_at.eq(now.nullable()))
+            .execute(conn)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = token_anomalies, check_for_backend(diesel::pg::Pg))]
+pub struct NewTokenAnomaly<'a> {
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub kind: &'a str,
+    pub details: &'a str,
+    pub publishes_locked: bool,
+}
+
+impl NewTokenAnomaly<'_> {
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<TokenAnomaly> {
+        diesel::insert_into(token_anomalies::table)
+            .values(self)
+            .returning(TokenAnomaly::as_returning())
+            .get_result(conn)
+    }
+}
@@ -1,10 +1,11 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::now;
 use diesel::prelude::*;
 use secrecy::SecretString;
 
 use crate::app::App;
 use crate::controllers::user::me::UserConfirmEmail;
-use crate::email::Emails;
+use crate::email::{Emails, Locale};
 use crate::util::errors::AppResult;
 
 use crate::models::{ApiToken, Crate, CrateOwner, Email, NewEmail, Owner, OwnerKind, Rights};
@@ -23,6 +24,11 @@ pub struct User {
     pub account_lock_reason: Option<String>,
     pub account_lock_until: Option<NaiveDateTime>,
     pub is_admin: bool,
+    pub follow_digest: bool,
+    pub locale: String,
+    pub gh_token_revoked_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+    pub block_unsolicited_invitations: bool,
 }
 
 /// Represents a new user record insertable to the `users` table
@@ -60,7 +66,7 @@ impl<'a> NewUser<'a> {
         emails: &Emails,
         conn: &mut PgConnection,
     ) -> QueryResult<User> {
-        use diesel::dsl::sql;
+        use diesel::dsl::{exists, select, sql};
         use diesel::insert_into;
         use diesel::pg::upsert::excluded;
         use diesel::sql_types::Integer;
@@ -83,11 +89,16 @@ impl<'a> NewUser<'a> {
                     users::name.eq(excluded(users::name)),
                     users::gh_avatar.eq(excluded(users::gh_avatar)),
                     users::gh_access_token.eq(excluded(users::gh_access_token)),
+                    // Signing in again stores a new access token, which
+                    // lifts the restrictions of a revoked one.
+                    users::gh_token_revoked_at.eq(None::<NaiveDateTime>),
                 ))
                 .get_result(conn)?;
 
-            // To send the user an account verification email
-            if let Some(user_email) = email {
+            // To send the user an account verification email. Users that
+            // already have an email address manage their addresses themselves.
+            let has_email: bool = select(exists(Email::belonging_to(&user))).get_result(conn)?;
+            if let Some(user_email) = email.filter(|_| !has_email) {
                 let new_email = NewEmail {
                     user_id: user.id,
                     email: user_email,
@@ -108,7 +119,8 @@ impl<'a> NewUser<'a> {
                         domain: &emails.domain,
                         token,
                     };
-                    let _ = emails.send(user_email, email);
+                    let locale = Locale::from_preference(&user.locale);
+                    let _ = emails.send_localized(user_email, locale, email);
                 }
             }
 
@@ -124,7 +136,7 @@ impl User {
 
     /// Queries the database for a user with a certain `api_token` value.
     pub fn find_by_api_token(conn: &mut PgConnection, token: &str) -> AppResult<User> {
-        let api_token = ApiToken::find_by_api_token(conn, token)?;
+        let api_token = ApiToken::find_by_api_token(conn, token, Utc::now().naive_utc())?;
 
         Ok(Self::find(conn, api_token.user_id)?)
     }
@@ -176,20 +188,41 @@ impl User {
         Ok(best)
     }
 
-    /// Queries the database for the verified emails
-    /// belonging to a given user
+    /// Flags the GitHub access token of the user as revoked, after GitHub
+    /// rejected it, and removes it from the database.
+    ///
+    /// The sessions of the user are invalid and publishing is blocked until
+    /// the user signs in again. Returns `false` if the token was already
+    /// flagged, or replaced by signing in again in the meantime.
+    pub fn flag_revoked_gh_token(&self, conn: &mut PgConnection) -> QueryResult<bool> {
+        let updated = diesel::update(users::table.find(self.id))
+            .filter(users::gh_access_token.eq(&self.gh_access_token))
+            .filter(users::gh_token_revoked_at.is_null())
+            .set((
+                users::gh_access_token.eq(""),
+                users::gh_token_revoked_at.eq(now.nullable()),
+            ))
+            .execute(conn)?;
+
+        Ok(updated > 0)
+    }
+
+    /// Queries the database for the primary email address of the user, if
+    /// it is verified
     pub fn verified_email(&self, conn: &mut PgConnection) -> QueryResult<Option<String>> {
         Email::belonging_to(self)
             .select(emails::email)
+            .filter(emails::is_primary)
             .filter(emails::verified.eq(true))
             .first(conn)
             .optional()
     }
 
-    /// Queries for the email belonging to a particular user
+    /// Queries for the primary email address of a particular user
     pub fn email(&self, conn: &mut PgConnection) -> QueryResult<Option<String>> {
         Email::belonging_to(self)
             .select(emails::email)
+            .filter(emails::is_primary)
             .first(conn)
             .optional()
     }
@@ -1,7 +1,10 @@
 use std::collections::BTreeMap;
 
 use chrono::NaiveDateTime;
+use diesel::dsl::sql;
+use diesel::expression::SqlLiteral;
 use diesel::prelude::*;
+use diesel::sql_types::Bool;
 
 use crate::util::errors::{bad_request, AppResult};
 
@@ -118,6 +121,15 @@ impl Version {
             .execute(conn)
     }
 
+    /// SQL filter that excludes the versions whose scheduled release is still
+    /// pending (see [`ScheduledRelease`](crate::models::ScheduledRelease)).
+    pub fn is_released() -> SqlLiteral<Bool> {
+        sql(
+            "versions.id NOT IN (SELECT version_id FROM scheduled_releases \
+             WHERE resolved_at IS NULL AND version_id IS NOT NULL)",
+        )
+    }
+
     /// Gets the User who ran `cargo publish` for this version, if recorded.
     /// Not for use when you have a group of versions you need the publishers for.
     pub fn published_by(&self, conn: &mut PgConnection) -> Option<User> {
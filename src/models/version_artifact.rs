@@ -0,0 +1,98 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::Version;
+use crate::schema::version_artifacts;
+
+/// The maximum length of a target triple.
+const MAX_TARGET_LENGTH: usize = 64;
+
+/// A pre-built binary artifact of a version for a single target platform.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Selectable)]
+#[diesel(
+    table_name = version_artifacts,
+    check_for_backend(diesel::pg::Pg),
+    belongs_to(Version),
+)]
+pub struct VersionArtifact {
+    pub id: i32,
+    pub version_id: i32,
+    pub target: String,
+    pub checksum: String,
+    pub size: i64,
+    pub uploaded_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionArtifact {
+    /// Returns the artifacts of the version, ordered by target.
+    pub fn by_version(conn: &mut PgConnection, version: &Version) -> QueryResult<Vec<Self>> {
+        Self::belonging_to(version)
+            .select(Self::as_select())
+            .order(version_artifacts::target)
+            .load(conn)
+    }
+
+    /// Checks whether `target` looks like a target triple, e.g.
+    /// `x86_64-unknown-linux-gnu` or `thumbv7em-none-eabihf`.
+    ///
+    /// The list of targets is not checked, since custom targets are valid
+    /// too, but the target is part of the file name of the artifact, so only
+    /// a conservative set of characters is allowed.
+    pub fn is_valid_target(target: &str) -> bool {
+        let is_valid_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+
+        target.len() <= MAX_TARGET_LENGTH
+            && target.split('-').count() >= 2
+            && target
+                .split('-')
+                .all(|part| !part.is_empty() && part.chars().all(is_valid_char))
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = version_artifacts, check_for_backend(diesel::pg::Pg))]
+pub struct NewVersionArtifact<'a> {
+    pub version_id: i32,
+    pub target: &'a str,
+    pub checksum: &'a str,
+    pub size: i64,
+    pub uploaded_by: i32,
+}
+
+impl NewVersionArtifact<'_> {
+    /// Inserts the artifact, or returns `None` if the version already has an
+    /// artifact for the target.
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<Option<VersionArtifact>> {
+        diesel::insert_into(version_artifacts::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .returning(VersionArtifact::as_returning())
+            .get_result(conn)
+            .optional()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_validation() {
+        assert!(VersionArtifact::is_valid_target("x86_64-unknown-linux-gnu"));
+        assert!(VersionArtifact::is_valid_target("aarch64-apple-darwin"));
+        assert!(VersionArtifact::is_valid_target("thumbv7em-none-eabihf"));
+        assert!(VersionArtifact::is_valid_target("wasm32-wasip1"));
+        assert!(VersionArtifact::is_valid_target("x86_64-pc-windows-msvc"));
+
+        assert!(!VersionArtifact::is_valid_target(""));
+        assert!(!VersionArtifact::is_valid_target("linux"));
+        assert!(!VersionArtifact::is_valid_target("x86_64--linux"));
+        assert!(!VersionArtifact::is_valid_target("-x86_64-linux"));
+        assert!(!VersionArtifact::is_valid_target(
+            "x86_64-unknown-linux-gnu/.."
+        ));
+        assert!(!VersionArtifact::is_valid_target("x86_64 unknown-linux"));
+        assert!(!VersionArtifact::is_valid_target(&"x".repeat(65)));
+    }
+}
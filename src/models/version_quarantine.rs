@@ -0,0 +1,125 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use std::collections::HashSet;
+
+use crate::schema::version_quarantines;
+use crate::util::rfc3339;
+
+/// The outcome of the review of a quarantined version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuarantineResolution {
+    /// The version was found to be harmless and is available again.
+    Released,
+    /// The version was deleted.
+    Deleted,
+}
+
+impl QuarantineResolution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Released => "released",
+            Self::Deleted => "deleted",
+        }
+    }
+}
+
+/// A quarantine of a version by an administrator, pending a review.
+///
+/// While a version is quarantined, its downloads through the API are
+/// rejected and it is marked as yanked in the index. Resolved quarantines are
+/// kept as an audit trail, even if the version was deleted.
+#[derive(Clone, Queryable, Identifiable, Selectable, Debug, Serialize)]
+#[diesel(table_name = version_quarantines, check_for_backend(diesel::pg::Pg))]
+pub struct VersionQuarantine {
+    pub id: i32,
+    #[serde(skip)]
+    pub version_id: Option<i32>,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    #[serde(rename = "version")]
+    pub version_num: String,
+    pub reason: String,
+    pub quarantined_by: i32,
+    #[serde(with = "rfc3339")]
+    pub quarantined_at: NaiveDateTime,
+    pub resolution: Option<String>,
+    pub resolved_by: Option<i32>,
+    #[serde(with = "rfc3339::option")]
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+impl VersionQuarantine {
+    /// Returns the unresolved quarantine of the version, if there is one.
+    pub fn active(conn: &mut PgConnection, version_id: i32) -> QueryResult<Option<Self>> {
+        version_quarantines::table
+            .filter(version_quarantines::version_id.eq(version_id))
+            .filter(version_quarantines::resolved_at.is_null())
+            .select(Self::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns all unresolved quarantines, oldest first.
+    pub fn all_active(conn: &mut PgConnection) -> QueryResult<Vec<Self>> {
+        version_quarantines::table
+            .filter(version_quarantines::resolved_at.is_null())
+            .select(Self::as_select())
+            .order(version_quarantines::id)
+            .load(conn)
+    }
+
+    /// Returns the IDs of the given versions that are currently quarantined.
+    pub fn quarantined_version_ids(
+        conn: &mut PgConnection,
+        version_ids: &[i32],
+    ) -> QueryResult<HashSet<i32>> {
+        let ids: Vec<Option<i32>> = version_quarantines::table
+            .filter(version_quarantines::version_id.eq_any(version_ids))
+            .filter(version_quarantines::resolved_at.is_null())
+            .select(version_quarantines::version_id)
+            .load(conn)?;
+
+        Ok(ids.into_iter().flatten().collect())
+    }
+
+    /// Records the outcome of the review.
+    pub fn resolve(
+        &self,
+        conn: &mut PgConnection,
+        resolution: QuarantineResolution,
+        user_id: i32,
+    ) -> QueryResult<Self> {
+        diesel::update(self)
+            .set((
+                version_quarantines::resolution.eq(resolution.as_str()),
+                version_quarantines::resolved_by.eq(user_id),
+                version_quarantines::resolved_at.eq(now),
+            ))
+            .returning(Self::as_returning())
+            .get_result(conn)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = version_quarantines, check_for_backend(diesel::pg::Pg))]
+pub struct NewVersionQuarantine<'a> {
+    pub version_id: i32,
+    pub crate_name: &'a str,
+    pub version_num: &'a str,
+    pub reason: &'a str,
+    pub quarantined_by: i32,
+}
+
+impl NewVersionQuarantine<'_> {
+    /// Inserts the quarantine, or returns `None` if the version is already
+    /// quarantined.
+    pub fn insert(&self, conn: &mut PgConnection) -> QueryResult<Option<VersionQuarantine>> {
+        diesel::insert_into(version_quarantines::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .returning(VersionQuarantine::as_returning())
+            .get_result(conn)
+            .optional()
+    }
+}
@@ -1,28 +1,38 @@
 use crate::schema::{publish_limit_buckets, publish_rate_overrides};
 use crate::sql::{date_part, floor, greatest, interval_part, least, pg_enum};
 use crate::util::errors::{AppResult, TooManyRequests};
-use chrono::{NaiveDateTime, Utc};
+use chrono::NaiveDateTime;
 use diesel::dsl::IntervalDsl;
 use diesel::prelude::*;
 use diesel::sql_types::Interval;
-use std::borrow::Cow;
+use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::time::Duration;
 
+pub mod auth_failures;
+pub mod downloads;
+mod redis_store;
+
+use self::redis_store::RedisStore;
+
 pg_enum! {
     pub enum LimitedAction {
         PublishNew = 0,
         PublishUpdate = 1,
         YankUnyank = 2,
+        ReserveName = 3,
+        DataExport = 4,
     }
 }
 
 impl LimitedAction {
     pub fn default_rate_seconds(&self) -> u64 {
         match self {
-            LimitedAction::PublishNew => 10 * 60, // 10 minutes
-            LimitedAction::PublishUpdate => 60,   // 1 minute
-            LimitedAction::YankUnyank => 60,      // 1 minute
+            LimitedAction::PublishNew => 10 * 60,  // 10 minutes
+            LimitedAction::PublishUpdate => 60,    // 1 minute
+            LimitedAction::YankUnyank => 60,       // 1 minute
+            LimitedAction::ReserveName => 60 * 60, // 1 hour
+            LimitedAction::DataExport => 60 * 60,  // 1 hour
         }
     }
 
@@ -31,6 +41,8 @@ impl LimitedAction {
             LimitedAction::PublishNew => 5,
             LimitedAction::PublishUpdate => 30,
             LimitedAction::YankUnyank => 100,
+            LimitedAction::ReserveName => 5,
+            LimitedAction::DataExport => 1,
         }
     }
 
@@ -39,6 +51,8 @@ impl LimitedAction {
             LimitedAction::PublishNew => "PUBLISH_NEW",
             LimitedAction::PublishUpdate => "PUBLISH_UPDATE",
             LimitedAction::YankUnyank => "YANK_UNYANK",
+            LimitedAction::ReserveName => "RESERVE_NAME",
+            LimitedAction::DataExport => "DATA_EXPORT",
         }
     }
 
@@ -53,11 +67,17 @@ impl LimitedAction {
             LimitedAction::YankUnyank => {
                 "You have yanked or unyanked too many versions in a short period of time"
             }
+            LimitedAction::ReserveName => {
+                "You have reserved too many crate names in a short period of time"
+            }
+            LimitedAction::DataExport => {
+                "You have requested too many data exports in a short period of time"
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RateLimiterConfig {
     pub rate: Duration,
     pub burst: i32,
@@ -65,21 +85,52 @@ pub struct RateLimiterConfig {
 
 #[derive(Debug)]
 pub struct RateLimiter {
-    config: HashMap<LimitedAction, RateLimiterConfig>,
+    config: RwLock<HashMap<LimitedAction, RateLimiterConfig>>,
+    store: Box<dyn RateLimiterStore>,
 }
 
 impl RateLimiter {
+    /// Creates a rate limiter that keeps its buckets in the database.
     pub fn new(config: HashMap<LimitedAction, RateLimiterConfig>) -> Self {
-        Self { config }
+        Self::with_store(config, Box::new(DatabaseStore))
+    }
+
+    /// Creates a rate limiter that keeps its buckets in Redis, so that the
+    /// limits are shared between all server instances without writing to
+    /// the database for every limited request.
+    pub fn with_redis(
+        config: HashMap<LimitedAction, RateLimiterConfig>,
+        redis_url: &str,
+    ) -> anyhow::Result<Self> {
+        let store = RedisStore::new(redis_url)?;
+        Ok(Self::with_store(config, Box::new(store)))
     }
 
+    fn with_store(
+        config: HashMap<LimitedAction, RateLimiterConfig>,
+        store: Box<dyn RateLimiterStore>,
+    ) -> Self {
+        let config = RwLock::new(config);
+        Self { config, store }
+    }
+
+    /// Replaces the rate limits, e.g. after a configuration reload. The
+    /// buckets of the users are kept.
+    pub fn set_config(&self, config: HashMap<LimitedAction, RateLimiterConfig>) {
+        *self.config.write() = config;
+    }
+
+    /// Takes a token from the bucket of the user for the action, and fails
+    /// with a `429 Too Many Requests` error if the bucket is empty at `now`
+    /// (see [`App::clock`](crate::app::App::clock)).
     pub fn check_rate_limit(
         &self,
         uploader: i32,
         performed_action: LimitedAction,
+        now: NaiveDateTime,
         conn: &mut PgConnection,
     ) -> AppResult<()> {
-        let bucket = self.take_token(uploader, performed_action, Utc::now().naive_utc(), conn)?;
+        let bucket = self.take_token(uploader, performed_action, now, conn)?;
         if bucket.tokens >= 1 {
             Ok(())
         } else {
@@ -108,7 +159,6 @@ impl RateLimiter {
         conn: &mut PgConnection,
     ) -> QueryResult<Bucket> {
         let config = self.config_for_action(performed_action);
-        let refill_rate = (config.rate.as_millis() as i64).milliseconds();
 
         let burst: i32 = publish_rate_overrides::table
             .find((uploader, performed_action))
@@ -122,6 +172,54 @@ impl RateLimiter {
             .optional()?
             .unwrap_or(config.burst);
 
+        self.store
+            .take_token(uploader, performed_action, config.rate, burst, now, conn)
+    }
+
+    fn config_for_action(&self, action: LimitedAction) -> RateLimiterConfig {
+        // Returns the default config for the action when not configured.
+        match self.config.read().get(&action) {
+            Some(config) => *config,
+            None => RateLimiterConfig {
+                rate: Duration::from_secs(action.default_rate_seconds()),
+                burst: action.default_burst(),
+            },
+        }
+    }
+}
+
+/// Storage of the token buckets of the [`RateLimiter`].
+pub(crate) trait RateLimiterStore: Send + Sync + std::fmt::Debug {
+    /// Refills the bucket of the user for the action as needed, takes a token
+    /// from it, and returns the updated bucket (see
+    /// [`RateLimiter::take_token()`]).
+    fn take_token(
+        &self,
+        user_id: i32,
+        action: LimitedAction,
+        rate: Duration,
+        burst: i32,
+        now: NaiveDateTime,
+        conn: &mut PgConnection,
+    ) -> QueryResult<Bucket>;
+}
+
+/// Keeps the buckets in the `publish_limit_buckets` table.
+#[derive(Debug)]
+struct DatabaseStore;
+
+impl RateLimiterStore for DatabaseStore {
+    fn take_token(
+        &self,
+        user_id: i32,
+        action: LimitedAction,
+        rate: Duration,
+        burst: i32,
+        now: NaiveDateTime,
+        conn: &mut PgConnection,
+    ) -> QueryResult<Bucket> {
+        let refill_rate = (rate.as_millis() as i64).milliseconds();
+
         // Interval division is poorly defined in general (what is 1 month / 30 days?)
         // However, for the intervals we're dealing with, it is always well
         // defined, so we convert to an f64 of seconds to represent this.
@@ -132,8 +230,8 @@ impl RateLimiter {
 
         diesel::insert_into(publish_limit_buckets::table)
             .values((
-                publish_limit_buckets::user_id.eq(uploader),
-                publish_limit_buckets::action.eq(performed_action),
+                publish_limit_buckets::user_id.eq(user_id),
+                publish_limit_buckets::action.eq(action),
                 publish_limit_buckets::tokens.eq(burst),
                 publish_limit_buckets::last_refill.eq(now),
             ))
@@ -152,23 +250,12 @@ impl RateLimiter {
             ))
             .get_result(conn)
     }
-
-    fn config_for_action(&self, action: LimitedAction) -> Cow<'_, RateLimiterConfig> {
-        // The wrapper returns the default config for the action when not configured.
-        match self.config.get(&action) {
-            Some(config) => Cow::Borrowed(config),
-            None => Cow::Owned(RateLimiterConfig {
-                rate: Duration::from_secs(action.default_rate_seconds()),
-                burst: action.default_burst(),
-            }),
-        }
-    }
 }
 
 #[derive(Queryable, Insertable, Debug, PartialEq, Clone, Copy)]
 #[diesel(table_name = publish_limit_buckets, check_for_backend(diesel::pg::Pg))]
 #[allow(dead_code)] // Most fields only read in tests
-struct Bucket {
+pub(crate) struct Bucket {
     user_id: i32,
     tokens: i32,
     last_refill: NaiveDateTime,
@@ -180,6 +267,7 @@ mod tests {
     use super::*;
     use crate::email::Emails;
     use crate::test_util::*;
+    use chrono::Utc;
 
     #[test]
     fn default_rate_limits() -> QueryResult<()> {
@@ -0,0 +1,291 @@
+//! Rate limiting of failed authentication attempts.
+//!
+//! Invalid API tokens and stale session cookies are counted per client IP
+//! address and per token prefix. After a number of free attempts every
+//! further failure delays the next attempt, with the delay doubling for each
+//! failure up to a maximum, and clients that keep failing are blocked
+//! completely for a while. This makes brute-forcing API tokens impractical
+//! without affecting users that mistype a token once or twice.
+//!
+//! The failures are kept in memory and are therefore local to each server
+//! instance.
+
+use super::downloads::normalize_ip;
+use crate::config::AuthFailureLimiterConfig;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Number of entries the store can hold before stale entries are pruned.
+const MIN_PRUNE_THRESHOLD: usize = 1024;
+
+/// Number of characters of an API token that are used as its prefix.
+const TOKEN_PREFIX_LENGTH: usize = 8;
+
+/// A client whose failed authentication attempts are counted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AuthFailureKey {
+    Ip(IpAddr),
+    TokenPrefix(String),
+}
+
+impl AuthFailureKey {
+    pub fn ip(ip: IpAddr) -> Self {
+        Self::Ip(normalize_ip(ip))
+    }
+
+    pub fn token_prefix(token: &str) -> Self {
+        Self::TokenPrefix(token.chars().take(TOKEN_PREFIX_LENGTH).collect())
+    }
+
+    /// The label of the key in the `auth_failures_rate_limited_total` metric.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Ip(_) => "ip",
+            Self::TokenPrefix(_) => "token_prefix",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AuthFailureLimiter {
+    config: AuthFailureLimiterConfig,
+    store: Mutex<Store>,
+}
+
+impl AuthFailureLimiter {
+    pub fn new(config: AuthFailureLimiterConfig) -> Self {
+        Self {
+            config,
+            store: Mutex::new(Store::default()),
+        }
+    }
+
+    /// Checks whether any of the `keys` has to wait before it may attempt to
+    /// authenticate again.
+    ///
+    /// Returns the key with the longest wait and the remaining duration.
+    pub fn check(
+        &self,
+        keys: &[AuthFailureKey],
+        now: Instant,
+    ) -> Result<(), (AuthFailureKey, Duration)> {
+        let store = self.store.lock();
+
+        let longest_wait = keys
+            .iter()
+            .filter_map(|key| {
+                let retry_at = store.failures.get(key)?.retry_at?;
+                let wait = retry_at.checked_duration_since(now)?;
+                (!wait.is_zero()).then(|| (key.clone(), wait))
+            })
+            .max_by_key(|(_, wait)| *wait);
+
+        match longest_wait {
+            Some(longest_wait) => Err(longest_wait),
+            None => Ok(()),
+        }
+    }
+
+    /// Records a failed authentication attempt for all of the `keys`.
+    pub fn record_failure(&self, keys: &[AuthFailureKey], now: Instant) {
+        let config = &self.config;
+        let mut store = self.store.lock();
+
+        if store.failures.len() >= store.prune_threshold.max(MIN_PRUNE_THRESHOLD) {
+            store
+                .failures
+                .retain(|_, failures| !failures.is_expired(config.window, now));
+
+            // Avoid pruning on every failure if most of the entries are
+            // still in use.
+            store.prune_threshold = store.failures.len() * 2;
+        }
+
+        for key in keys {
+            store
+                .failures
+                .entry(key.clone())
+                .or_insert_with(|| Failures::new(now))
+                .record(config, now);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    failures: HashMap<AuthFailureKey, Failures>,
+    prune_threshold: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Failures {
+    count: u32,
+    first_failure: Instant,
+    retry_at: Option<Instant>,
+}
+
+impl Failures {
+    fn new(now: Instant) -> Self {
+        Self {
+            count: 0,
+            first_failure: now,
+            retry_at: None,
+        }
+    }
+
+    /// Returns `true` if the failures are older than the `window` and the
+    /// client is no longer delayed or blocked.
+    fn is_expired(&self, window: Duration, now: Instant) -> bool {
+        let window_ended = now.saturating_duration_since(self.first_failure) >= window;
+        let still_delayed = self.retry_at.is_some_and(|retry_at| retry_at > now);
+        window_ended && !still_delayed
+    }
+
+    fn record(&mut self, config: &AuthFailureLimiterConfig, now: Instant) {
+        if self.is_expired(config.window, now) {
+            *self = Self::new(now);
+        }
+
+        self.count = self.count.saturating_add(1);
+
+        let delay = if self.count >= config.block_after {
+            Some(config.block_duration)
+        } else if self.count > config.free_attempts {
+            let exponent = self.count - config.free_attempts - 1;
+            let factor = 2u32.checked_pow(exponent).unwrap_or(u32::MAX);
+            let delay = config.base_delay.saturating_mul(factor);
+            Some(delay.min(config.max_delay))
+        } else {
+            None
+        };
+
+        self.retry_at = delay.map(|delay| now + delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    fn config() -> AuthFailureLimiterConfig {
+        AuthFailureLimiterConfig {
+            free_attempts: 2,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            block_after: 6,
+            block_duration: Duration::from_secs(100),
+            window: Duration::from_secs(1000),
+        }
+    }
+
+    #[test]
+    fn failures_are_delayed_progressively() {
+        let config = config();
+        let now = Instant::now();
+        let mut failures = Failures::new(now);
+
+        let mut delays = Vec::new();
+        for _ in 0..7 {
+            failures.record(&config, now);
+            let delay = failures.retry_at.map(|retry_at| retry_at - now);
+            delays.push(delay.map(|delay| delay.as_secs()));
+        }
+
+        let expected = [None, None, Some(1), Some(2), Some(4), Some(100), Some(100)];
+        assert_eq!(delays, expected);
+    }
+
+    #[test]
+    fn delays_are_capped() {
+        let config = AuthFailureLimiterConfig {
+            block_after: u32::MAX,
+            ..config()
+        };
+        let now = Instant::now();
+        let mut failures = Failures::new(now);
+
+        for _ in 0..100 {
+            failures.record(&config, now);
+        }
+        assert_eq!(failures.retry_at, Some(now + config.max_delay));
+    }
+
+    #[test]
+    fn failures_expire_after_window() {
+        let config = config();
+        let now = Instant::now();
+        let mut failures = Failures::new(now);
+
+        for _ in 0..3 {
+            failures.record(&config, now);
+        }
+        assert_eq!(failures.count, 3);
+
+        let later = now + config.window;
+        failures.record(&config, later);
+        assert_eq!(failures.count, 1);
+        assert_eq!(failures.retry_at, None);
+    }
+
+    #[test]
+    fn blocked_clients_do_not_expire() {
+        let config = AuthFailureLimiterConfig {
+            block_duration: Duration::from_secs(2000),
+            ..config()
+        };
+        let now = Instant::now();
+        let mut failures = Failures::new(now);
+
+        for _ in 0..6 {
+            failures.record(&config, now);
+        }
+
+        let later = now + config.window;
+        assert!(!failures.is_expired(config.window, later));
+        failures.record(&config, later);
+        assert_eq!(failures.count, 7);
+    }
+
+    #[test]
+    fn check_returns_longest_wait() {
+        let limiter = AuthFailureLimiter::new(config());
+        let now = Instant::now();
+
+        let ip = AuthFailureKey::ip("127.0.0.1".parse().unwrap());
+        let prefix = AuthFailureKey::token_prefix("cioAAAAAAAAAAAAA");
+        let keys = [ip.clone(), prefix.clone()];
+
+        for _ in 0..3 {
+            limiter.record_failure(&keys, now);
+        }
+        limiter.record_failure(&[prefix.clone()], now);
+
+        let (key, wait) = assert_err!(limiter.check(&keys, now));
+        assert_eq!(key, prefix);
+        assert_eq!(wait, Duration::from_secs(2));
+
+        let (key, wait) = assert_err!(limiter.check(&[ip.clone()], now));
+        assert_eq!(key, ip);
+        assert_eq!(wait, Duration::from_secs(1));
+
+        assert_ok!(limiter.check(&keys, now + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn keys_are_normalized() {
+        let a = "2001:db8::1".parse::<Ipv6Addr>().unwrap();
+        let b = "2001:db8::2".parse::<Ipv6Addr>().unwrap();
+        assert_eq!(
+            AuthFailureKey::ip(IpAddr::V6(a)),
+            AuthFailureKey::ip(IpAddr::V6(b))
+        );
+
+        assert_eq!(
+            AuthFailureKey::token_prefix("cio1234567890"),
+            AuthFailureKey::TokenPrefix("cio12345".into())
+        );
+    }
+}
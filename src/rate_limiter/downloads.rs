@@ -0,0 +1,354 @@
+//! IP-based rate limiting for the download endpoint.
+//!
+//! Most download traffic is served by the CDN directly, but clients that
+//! bypass it hit the `GET /api/v1/crates/:crate_id/:version/download` route
+//! on the origin servers. To protect the origin, anonymous download requests
+//! can be limited per client IP address using a token bucket algorithm.
+//!
+//! The buckets are either kept in an in-memory sharded store, which is local
+//! to each server instance, or in Redis, which allows all server instances to
+//! share the same buckets.
+
+use crate::config::DownloadRateLimiterConfig;
+use parking_lot::Mutex;
+use redis::aio::ConnectionManager;
+use secrecy::ExposeSecret;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::net::{IpAddr, Ipv6Addr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::OnceCell;
+
+/// Number of independently locked shards of the in-memory store.
+const NUM_SHARDS: usize = 64;
+
+/// Number of entries a shard can hold before stale buckets are pruned.
+const MIN_PRUNE_THRESHOLD: usize = 1024;
+
+/// Prefix for all keys stored in Redis by the download rate limiter.
+const REDIS_KEY_PREFIX: &str = "download_rate_limit";
+
+/// Atomically refills the bucket stored at `KEYS[1]` and tries to take a
+/// token from it. The algorithm matches [`Bucket::take_token()`].
+///
+/// Returns `0` if a token was taken, or the number of milliseconds until the
+/// next token becomes available otherwise.
+const REDIS_TAKE_TOKEN_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local burst = tonumber(ARGV[3])
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'last_refill')
+local tokens = tonumber(bucket[1]) or burst
+local last_refill = tonumber(bucket[2]) or now
+
+local refill = math.floor((now - last_refill) / rate)
+if refill > 0 then
+    tokens = math.min(burst, tokens + refill)
+    last_refill = last_refill + refill * rate
+end
+if tokens >= burst then
+    last_refill = now
+end
+
+local retry_after = 0
+if tokens > 0 then
+    tokens = tokens - 1
+else
+    retry_after = last_refill + rate - now
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tokens, 'last_refill', last_refill)
+redis.call('PEXPIRE', KEYS[1], (burst - tokens + 1) * rate)
+
+return retry_after
+"#;
+
+pub struct DownloadRateLimiter {
+    rate: Duration,
+    burst: u32,
+    store: Store,
+}
+
+enum Store {
+    Memory(MemoryStore),
+    Redis(Box<RedisStore>),
+}
+
+impl DownloadRateLimiter {
+    pub fn from_config(config: &DownloadRateLimiterConfig) -> anyhow::Result<Self> {
+        let store = match &config.redis_url {
+            Some(url) => Store::Redis(Box::new(RedisStore::new(url.expose_secret())?)),
+            None => Store::Memory(MemoryStore::new()),
+        };
+
+        Ok(Self {
+            rate: config.rate,
+            burst: config.burst,
+            store,
+        })
+    }
+
+    /// Takes a token from the bucket of the given IP address.
+    ///
+    /// Returns `Err` with the duration after which the client may retry if
+    /// the bucket is currently empty.
+    pub async fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        let ip = normalize_ip(ip);
+
+        match &self.store {
+            Store::Memory(store) => store.take_token(ip, self.rate, self.burst, Instant::now()),
+            Store::Redis(store) => match store.take_token(ip, self.rate, self.burst).await {
+                Ok(result) => result,
+                Err(error) => {
+                    // We'd rather let the request through than block all
+                    // downloads if Redis is unavailable.
+                    warn!(%error, "Failed to check download rate limit");
+                    Ok(())
+                }
+            },
+        }
+    }
+}
+
+/// IPv6 clients are typically assigned a full `/64` network, so we limit
+/// them by their network prefix instead of their full address.
+pub(crate) fn normalize_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => ip,
+        IpAddr::V6(ip) => {
+            let prefix = u128::from(ip) & !(u64::MAX as u128);
+            IpAddr::V6(Ipv6Addr::from(prefix))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bucket {
+    tokens: u32,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn new(burst: u32, now: Instant) -> Self {
+        Self {
+            tokens: burst,
+            last_refill: now,
+        }
+    }
+
+    /// Refills the bucket as needed and tries to take a token from it.
+    fn take_token(&mut self, rate: Duration, burst: u32, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let refill = elapsed.as_nanos() / rate.as_nanos().max(1);
+        if refill > 0 {
+            let refill = u32::try_from(refill).unwrap_or(u32::MAX);
+            self.tokens = self.tokens.saturating_add(refill).min(burst);
+            self.last_refill += rate.saturating_mul(refill);
+        }
+        if self.tokens >= burst {
+            self.last_refill = now;
+        }
+
+        if self.tokens > 0 {
+            self.tokens -= 1;
+            Ok(())
+        } else {
+            Err((self.last_refill + rate).saturating_duration_since(now))
+        }
+    }
+
+    /// A bucket that would be completely refilled by now is equivalent to a
+    /// missing bucket, so it can be removed from the store.
+    fn is_full(&self, rate: Duration, burst: u32, now: Instant) -> bool {
+        let missing_tokens = burst.saturating_sub(self.tokens);
+        self.last_refill + rate.saturating_mul(missing_tokens) <= now
+    }
+}
+
+struct MemoryStore {
+    hasher: RandomState,
+    shards: Vec<Mutex<Shard>>,
+}
+
+#[derive(Default)]
+struct Shard {
+    buckets: HashMap<IpAddr, Bucket>,
+    prune_threshold: usize,
+}
+
+impl MemoryStore {
+    fn new() -> Self {
+        let shards = (0..NUM_SHARDS).map(|_| Mutex::default()).collect();
+        let hasher = RandomState::new();
+        Self { hasher, shards }
+    }
+
+    fn take_token(
+        &self,
+        ip: IpAddr,
+        rate: Duration,
+        burst: u32,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let shard_index = self.hasher.hash_one(ip) as usize % self.shards.len();
+        let mut shard = self.shards[shard_index].lock();
+        shard.take_token(ip, rate, burst, now)
+    }
+}
+
+impl Shard {
+    fn take_token(
+        &mut self,
+        ip: IpAddr,
+        rate: Duration,
+        burst: u32,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        if self.buckets.len() >= self.prune_threshold.max(MIN_PRUNE_THRESHOLD) {
+            self.buckets
+                .retain(|_, bucket| !bucket.is_full(rate, burst, now));
+
+            // Avoid pruning on every request if most of the buckets are
+            // still in use.
+            self.prune_threshold = self.buckets.len() * 2;
+        }
+
+        self.buckets
+            .entry(ip)
+            .or_insert_with(|| Bucket::new(burst, now))
+            .take_token(rate, burst, now)
+    }
+}
+
+struct RedisStore {
+    client: redis::Client,
+    connection: OnceCell<ConnectionManager>,
+    script: redis::Script,
+}
+
+impl RedisStore {
+    fn new(url: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            connection: OnceCell::new(),
+            script: redis::Script::new(REDIS_TAKE_TOKEN_SCRIPT),
+        })
+    }
+
+    async fn take_token(
+        &self,
+        ip: IpAddr,
+        rate: Duration,
+        burst: u32,
+    ) -> redis::RedisResult<Result<(), Duration>> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| self.client.get_connection_manager())
+            .await?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let retry_after: u64 = self
+            .script
+            .key(format!("{REDIS_KEY_PREFIX}:{ip}"))
+            .arg(now.as_millis() as u64)
+            .arg(rate.as_millis().max(1) as u64)
+            .arg(burst)
+            .invoke_async(&mut connection.clone())
+            .await?;
+
+        Ok(match retry_after {
+            0 => Ok(()),
+            millis => Err(Duration::from_millis(millis)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn bucket_allows_burst_then_rejects() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(3, now);
+
+        for _ in 0..3 {
+            assert_ok!(bucket.take_token(RATE, 3, now));
+        }
+        assert_err_eq!(bucket.take_token(RATE, 3, now), RATE);
+
+        let later = now + Duration::from_millis(400);
+        assert_err_eq!(
+            bucket.take_token(RATE, 3, later),
+            Duration::from_millis(600)
+        );
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let now = Instant::now();
+        let mut bucket = Bucket::new(2, now);
+
+        assert_ok!(bucket.take_token(RATE, 2, now));
+        assert_ok!(bucket.take_token(RATE, 2, now));
+        assert_err!(bucket.take_token(RATE, 2, now));
+
+        let now = now + RATE;
+        assert_ok!(bucket.take_token(RATE, 2, now));
+        assert_err!(bucket.take_token(RATE, 2, now));
+
+        // Refills never exceed the burst size
+        let now = now + RATE * 10;
+        assert_ok!(bucket.take_token(RATE, 2, now));
+        assert_ok!(bucket.take_token(RATE, 2, now));
+        assert_err!(bucket.take_token(RATE, 2, now));
+    }
+
+    #[test]
+    fn memory_store_tracks_ips_separately() {
+        let store = MemoryStore::new();
+        let now = Instant::now();
+        let ip1 = IpAddr::from([1, 2, 3, 4]);
+        let ip2 = IpAddr::from([5, 6, 7, 8]);
+
+        assert_ok!(store.take_token(ip1, RATE, 1, now));
+        assert_err!(store.take_token(ip1, RATE, 1, now));
+        assert_ok!(store.take_token(ip2, RATE, 1, now));
+    }
+
+    #[test]
+    fn shard_prunes_full_buckets() {
+        let mut shard = Shard::default();
+        let now = Instant::now();
+
+        for i in 0..MIN_PRUNE_THRESHOLD as u32 {
+            assert_ok!(shard.take_token(IpAddr::from(i.to_be_bytes()), RATE, 1, now));
+        }
+        assert_eq!(shard.buckets.len(), MIN_PRUNE_THRESHOLD);
+
+        // All previous buckets are full again after `RATE`
+        let ip = IpAddr::from([255, 255, 255, 255]);
+        assert_ok!(shard.take_token(ip, RATE, 1, now + RATE));
+        assert_eq!(shard.buckets.len(), 1);
+    }
+
+    #[test]
+    fn ipv6_addresses_are_grouped_by_prefix() {
+        let ip1: IpAddr = "2001:db8:1:2:aaaa::1".parse().unwrap();
+        let ip2: IpAddr = "2001:db8:1:2:bbbb::2".parse().unwrap();
+        let ip3: IpAddr = "2001:db8:1:3::1".parse().unwrap();
+
+        assert_eq!(normalize_ip(ip1), normalize_ip(ip2));
+        assert_ne!(normalize_ip(ip1), normalize_ip(ip3));
+
+        let ipv4 = IpAddr::from([1, 2, 3, 4]);
+        assert_eq!(normalize_ip(ipv4), ipv4);
+    }
+}
@@ -0,0 +1,131 @@
+//! Redis storage of the [`RateLimiter`](super::RateLimiter) buckets.
+//!
+//! Publishing and yanking take a token from a bucket on every request. Keeping
+//! the buckets in Redis avoids a write to the primary database for each of
+//! these requests, and the limits still hold across all server instances.
+
+use super::{Bucket, LimitedAction, RateLimiterStore};
+use chrono::{DateTime, NaiveDateTime};
+use diesel::prelude::*;
+use redis::aio::ConnectionManager;
+use std::fmt;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::OnceCell;
+
+/// Prefix for all keys stored in Redis by the rate limiter.
+const REDIS_KEY_PREFIX: &str = "rate_limit";
+
+/// Atomically refills the bucket stored at `KEYS[1]` and takes a token from
+/// it. The algorithm matches the database implementation: the stored number
+/// of tokens is only decremented on the next request.
+///
+/// Returns the number of tokens and the time of the last refill in
+/// milliseconds since the Unix epoch.
+const REDIS_TAKE_TOKEN_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local burst = tonumber(ARGV[3])
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'last_refill')
+local tokens = tonumber(bucket[1])
+local last_refill = tonumber(bucket[2])
+
+if tokens == nil or last_refill == nil then
+    tokens = burst
+    last_refill = now
+else
+    local refill = math.floor((now - last_refill) / rate)
+    tokens = math.min(burst, math.max(0, tokens - 1) + refill)
+    last_refill = last_refill + refill * rate
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tokens, 'last_refill', last_refill)
+redis.call('PEXPIRE', KEYS[1], (burst - tokens + 2) * rate)
+
+return {tokens, last_refill}
+"#;
+
+pub(super) struct RedisStore {
+    client: redis::Client,
+    connection: OnceCell<ConnectionManager>,
+    script: redis::Script,
+}
+
+impl RedisStore {
+    pub(super) fn new(url: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            connection: OnceCell::new(),
+            script: redis::Script::new(REDIS_TAKE_TOKEN_SCRIPT),
+        })
+    }
+
+    async fn invoke(
+        &self,
+        key: String,
+        rate: Duration,
+        burst: i32,
+        now: NaiveDateTime,
+    ) -> redis::RedisResult<(i32, i64)> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| self.client.get_connection_manager())
+            .await?;
+
+        self.script
+            .key(key)
+            .arg(now.and_utc().timestamp_millis())
+            .arg(rate.as_millis().max(1) as u64)
+            .arg(burst)
+            .invoke_async(&mut connection.clone())
+            .await
+    }
+}
+
+impl RateLimiterStore for RedisStore {
+    fn take_token(
+        &self,
+        user_id: i32,
+        action: LimitedAction,
+        rate: Duration,
+        burst: i32,
+        now: NaiveDateTime,
+        _conn: &mut PgConnection,
+    ) -> QueryResult<Bucket> {
+        let key = format!("{REDIS_KEY_PREFIX}:{}:{user_id}", action.env_var_key());
+
+        // The rate limiter is used from the blocking database closures, so
+        // the async Redis client has to be driven from here.
+        let result = Handle::current().block_on(self.invoke(key, rate, burst, now));
+
+        let (tokens, last_refill) = match result {
+            Ok((tokens, last_refill)) => {
+                let last_refill = DateTime::from_timestamp_millis(last_refill)
+                    .map(|last_refill| last_refill.naive_utc())
+                    .unwrap_or(now);
+
+                (tokens, last_refill)
+            }
+            Err(error) => {
+                // We'd rather let the request through than block all
+                // publishes if Redis is unavailable.
+                warn!(%error, "Failed to check rate limit");
+                (burst, now)
+            }
+        };
+
+        Ok(Bucket {
+            user_id,
+            tokens,
+            last_refill,
+            action,
+        })
+    }
+}
+
+impl fmt::Debug for RedisStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisStore").finish_non_exhaustive()
+    }
+}
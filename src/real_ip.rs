@@ -1,11 +1,26 @@
+//! Determining the IP address of the client of a request
+//!
+//! Requests usually reach the server through AWS CloudFront and the Heroku
+//! router, which append the addresses of their peers to the
+//! `X-Forwarded-For` header. Other deployments may use the `Forwarded` header
+//! instead, and a known number of proxies. See [`ClientIpConfig`] for the
+//! available settings.
+//!
+//! The result is stored in the [`RealIp`](crate::middleware::real_ip::RealIp)
+//! request extension, which is used for authentication, rate limiting and
+//! the request logs alike.
+
+use crate::config::{ClientIpConfig, ForwardedHeader};
 use http::{HeaderMap, HeaderValue};
 use ipnetwork::IpNetwork;
 use once_cell::sync::Lazy;
 use std::iter::Iterator;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::from_utf8;
 
 const X_FORWARDED_FOR: &str = "X-Forwarded-For";
+const FORWARDED: &str = "Forwarded";
+const CLOUDFRONT_VIEWER_ADDRESS: &str = "CloudFront-Viewer-Address";
 
 static CLOUD_FRONT_NETWORKS: Lazy<Vec<IpNetwork>> = Lazy::new(|| {
     let ipv4_prefixes = aws_ip_ranges::IP_RANGES
@@ -38,7 +53,110 @@ fn is_cloud_front_ip(ip: &IpAddr) -> bool {
         .any(|trusted_proxy| trusted_proxy.contains(*ip))
 }
 
-pub fn process_xff_headers(headers: &HeaderMap) -> Option<IpAddr> {
+/// Returns the IP address of the client, based on the headers that the
+/// proxies in front of the server added to the request, or the address of
+/// the connected `peer` if there are none.
+pub fn client_ip(headers: &HeaderMap, peer: IpAddr, config: &ClientIpConfig) -> IpAddr {
+    if config.trust_cloudfront_viewer_address {
+        let edge = edge_address(headers, peer, config);
+        if edge.is_some_and(|ip| is_cloud_front_ip(&ip)) {
+            if let Some(ip) = cloudfront_viewer_address(headers) {
+                return ip;
+            }
+        }
+    }
+
+    match (config.trusted_proxy_depth, config.header) {
+        (Some(depth), header) => {
+            let chain = forwarded_chain(headers, header);
+            nth_from_right(&chain, depth).unwrap_or(peer)
+        }
+        (None, ForwardedHeader::XForwardedFor) => process_xff_headers(headers).unwrap_or(peer),
+        (None, ForwardedHeader::Forwarded) => forwarded_chain(headers, ForwardedHeader::Forwarded)
+            .into_iter()
+            .flatten()
+            .filter(|ip| !is_cloud_front_ip(ip))
+            .next_back()
+            .unwrap_or(peer),
+    }
+}
+
+/// Returns the address of the peer that connected to the outermost trusted
+/// proxy, or `None` if it is unknown or cannot be trusted.
+///
+/// The `CloudFront-Viewer-Address` header is only trusted if this address
+/// belongs to CloudFront, since clients that connect to the server directly
+/// could send the header themselves.
+fn edge_address(headers: &HeaderMap, peer: IpAddr, config: &ClientIpConfig) -> Option<IpAddr> {
+    match config.trusted_proxy_depth {
+        Some(0) => return Some(peer),
+        Some(depth) => {
+            let chain = forwarded_chain(headers, config.header);
+            return if chain.len() < depth {
+                None
+            } else {
+                nth_from_right(&chain, depth)
+            };
+        }
+        None => {}
+    }
+
+    if config.header == ForwardedHeader::XForwardedFor {
+        // The Heroku router appends the connecting IP to the first header,
+        // even if there are multiple (see `process_xff_headers()`).
+        return match headers.get(X_FORWARDED_FOR) {
+            Some(first_header) => parse_xff_header(first_header).pop()?.ok(),
+            None => Some(peer),
+        };
+    }
+
+    let chain = forwarded_chain(headers, config.header);
+    match chain.last() {
+        Some(ip) => *ip,
+        None => Some(peer),
+    }
+}
+
+/// Returns the addresses in the header, from the client to the last proxy.
+/// Entries that are not valid IP addresses are `None`.
+fn forwarded_chain(headers: &HeaderMap, header: ForwardedHeader) -> Vec<Option<IpAddr>> {
+    match header {
+        ForwardedHeader::XForwardedFor => headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .flat_map(parse_xff_header)
+            .map(Result::ok)
+            .collect(),
+        ForwardedHeader::Forwarded => headers
+            .get_all(FORWARDED)
+            .iter()
+            .flat_map(parse_forwarded_header)
+            .collect(),
+    }
+}
+
+/// Returns the address that was appended by the `depth`-th proxy from the
+/// right, i.e. the outermost trusted proxy, or the left-most address if the
+/// chain is shorter than that. A depth of zero means that there are no
+/// proxies, so the headers are ignored.
+fn nth_from_right(chain: &[Option<IpAddr>], depth: usize) -> Option<IpAddr> {
+    if depth == 0 {
+        return None;
+    }
+
+    let index = chain.len().saturating_sub(depth);
+    chain.get(index).copied().flatten()
+}
+
+/// Returns the IP address of the `CloudFront-Viewer-Address` header, which
+/// has the format `<ip>:<port>`, without brackets around IPv6 addresses.
+fn cloudfront_viewer_address(headers: &HeaderMap) -> Option<IpAddr> {
+    let value = headers.get(CLOUDFRONT_VIEWER_ADDRESS)?.to_str().ok()?;
+    let (ip, _port) = value.trim().rsplit_once(':')?;
+    ip.parse().ok()
+}
+
+fn process_xff_headers(headers: &HeaderMap) -> Option<IpAddr> {
     let mut xff_iter = headers.get_all(X_FORWARDED_FOR).iter();
     let first_header = xff_iter.next()?;
 
@@ -91,6 +209,73 @@ fn parse_xff_header(header: &HeaderValue) -> Vec<Result<IpAddr, &[u8]>> {
         .collect()
 }
 
+/// Parses the content of a `Forwarded` header (RFC 7239) into the addresses
+/// of the `for` parameters of its elements.
+///
+/// Elements without a `for` parameter, or with an obfuscated identifier or
+/// `unknown` instead of an IP address, are returned as `None`.
+fn parse_forwarded_header(header: &HeaderValue) -> Vec<Option<IpAddr>> {
+    let Ok(value) = header.to_str() else {
+        return vec![None];
+    };
+
+    if value.trim().is_empty() {
+        return vec![];
+    }
+
+    split_unquoted(value, ',')
+        .into_iter()
+        .map(|element| {
+            split_unquoted(element, ';').into_iter().find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                let is_for = key.trim().eq_ignore_ascii_case("for");
+                is_for.then(|| parse_forwarded_node(value.trim()))
+            })?
+        })
+        .collect()
+}
+
+/// Parses a node identifier of the `Forwarded` header, like `192.0.2.60`,
+/// `"192.0.2.60:4711"` or `"[2001:db8:cafe::17]:4711"`.
+fn parse_forwarded_node(value: &str) -> Option<IpAddr> {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+
+    if let Some(rest) = value.strip_prefix('[') {
+        let (ip, _port) = rest.split_once(']')?;
+        return ip.parse::<Ipv6Addr>().ok().map(IpAddr::V6);
+    }
+
+    let host = value.split_once(':').map_or(value, |(host, _port)| host);
+    host.parse::<Ipv4Addr>().ok().map(IpAddr::V4)
+}
+
+/// Splits the value at the separator, except within quoted strings.
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (index, char) in value.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            _ if char == separator && !in_quotes => {
+                parts.push(&value[start..index]);
+                start = index + char.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&value[start..]);
+    parts
+}
+
 fn parse_ip_addr(bytes: &[u8]) -> Result<IpAddr, &[u8]> {
     from_utf8(bytes)
         .map_err(|_| bytes)?
@@ -143,6 +328,195 @@ mod tests {
         );
     }
 
+    #[track_caller]
+    fn headers(input: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in input {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let peer: IpAddr = "10.0.0.1".parse().unwrap();
+
+        #[track_caller]
+        fn test(input: &[(&'static str, &'static str)], config: ClientIpConfig, expected: &str) {
+            let peer = "10.0.0.1".parse().unwrap();
+            let expected: IpAddr = expected.parse().unwrap();
+            assert_eq!(client_ip(&headers(input), peer, &config), expected);
+        }
+
+        let default = ClientIpConfig::default();
+        test(&[], default, "10.0.0.1");
+        test(
+            &[("x-forwarded-for", "1.1.1.1, 2.2.2.2")],
+            default,
+            "2.2.2.2",
+        );
+        test(
+            &[("x-forwarded-for", "1.1.1.1, 130.176.118.147")],
+            default,
+            "1.1.1.1",
+        );
+
+        // The `Forwarded` header is ignored unless configured
+        test(&[("forwarded", "for=1.1.1.1")], default, "10.0.0.1");
+
+        let forwarded = ClientIpConfig {
+            header: ForwardedHeader::Forwarded,
+            ..default
+        };
+        test(&[("x-forwarded-for", "1.1.1.1")], forwarded, "10.0.0.1");
+        test(
+            &[("forwarded", "for=1.1.1.1, for=\"[2001:db8::1]:4711\"")],
+            forwarded,
+            "2001:db8::1",
+        );
+        test(
+            &[("forwarded", "for=1.1.1.1;proto=https, for=130.176.118.147")],
+            forwarded,
+            "1.1.1.1",
+        );
+
+        // With a trusted proxy depth, the address appended by the outermost
+        // trusted proxy is used, regardless of CloudFront
+        let depth = |depth| ClientIpConfig {
+            trusted_proxy_depth: Some(depth),
+            ..default
+        };
+        let chain = [("x-forwarded-for", "1.1.1.1, 2.2.2.2, 3.3.3.3")];
+        test(&chain, depth(0), "10.0.0.1");
+        test(&chain, depth(1), "3.3.3.3");
+        test(&chain, depth(2), "2.2.2.2");
+        test(&chain, depth(3), "1.1.1.1");
+        test(&chain, depth(4), "1.1.1.1");
+        test(
+            &[("x-forwarded-for", "1.1.1.1, oops")],
+            depth(1),
+            "10.0.0.1",
+        );
+        test(
+            &[
+                ("x-forwarded-for", "1.1.1.1"),
+                ("x-forwarded-for", "2.2.2.2"),
+            ],
+            depth(2),
+            "1.1.1.1",
+        );
+
+        let forwarded_depth = ClientIpConfig {
+            header: ForwardedHeader::Forwarded,
+            trusted_proxy_depth: Some(2),
+            ..default
+        };
+        test(
+            &[("forwarded", "for=1.1.1.1, for=_hidden, for=3.3.3.3")],
+            forwarded_depth,
+            "10.0.0.1",
+        );
+        test(
+            &[(
+                "forwarded",
+                "for=1.1.1.1, for=2.2.2.2;by=_proxy, for=3.3.3.3",
+            )],
+            forwarded_depth,
+            "2.2.2.2",
+        );
+
+        assert_eq!(client_ip(&HeaderMap::new(), peer, &depth(1)), peer);
+    }
+
+    #[test]
+    fn test_cloudfront_viewer_address() {
+        #[track_caller]
+        fn test(input: &[(&'static str, &'static str)], config: ClientIpConfig, expected: &str) {
+            let peer = "10.0.0.1".parse().unwrap();
+            let expected: IpAddr = expected.parse().unwrap();
+            assert_eq!(client_ip(&headers(input), peer, &config), expected);
+        }
+
+        let default = ClientIpConfig::default();
+        let viewer = ("cloudfront-viewer-address", "4.4.4.4:46532");
+        let viewer_v6 = ("cloudfront-viewer-address", "2001:db8::4:46532");
+
+        // Requests that came in through CloudFront
+        let via_cloudfront = ("x-forwarded-for", "1.1.1.1, 130.176.118.147");
+        test(&[via_cloudfront, viewer], default, "4.4.4.4");
+        test(&[via_cloudfront, viewer_v6], default, "2001:db8::4");
+        test(
+            &[via_cloudfront, ("cloudfront-viewer-address", "invalid")],
+            default,
+            "1.1.1.1",
+        );
+
+        let disabled = ClientIpConfig {
+            trust_cloudfront_viewer_address: false,
+            ..default
+        };
+        test(&[via_cloudfront, viewer], disabled, "1.1.1.1");
+
+        // Requests that did not come in through CloudFront can't be trusted
+        test(
+            &[("x-forwarded-for", "1.1.1.1"), viewer],
+            default,
+            "1.1.1.1",
+        );
+        test(&[viewer], default, "10.0.0.1");
+        test(
+            &[
+                ("x-forwarded-for", "1.1.1.1"),
+                ("x-forwarded-for", "130.176.118.147"),
+                viewer,
+            ],
+            default,
+            "1.1.1.1",
+        );
+
+        let depth = ClientIpConfig {
+            trusted_proxy_depth: Some(1),
+            ..default
+        };
+        test(&[via_cloudfront, viewer], depth, "4.4.4.4");
+        test(
+            &[("x-forwarded-for", "130.176.118.147, 1.1.1.1"), viewer],
+            depth,
+            "1.1.1.1",
+        );
+    }
+
+    #[test]
+    fn test_parse_forwarded_header() {
+        #[track_caller]
+        fn test(input: &'static str, expectation: Vec<Option<&str>>) {
+            let header = HeaderValue::from_static(input);
+
+            let expectation: Vec<Option<IpAddr>> = expectation
+                .into_iter()
+                .map(|ip| ip.map(|ip| ip.parse().unwrap()))
+                .collect();
+
+            assert_eq!(parse_forwarded_header(&header), expectation)
+        }
+
+        test("", vec![]);
+        test("for=192.0.2.60", vec![Some("192.0.2.60")]);
+        test("For=\"192.0.2.60:4711\"", vec![Some("192.0.2.60")]);
+        test(
+            "for=\"[2001:db8:cafe::17]:4711\"",
+            vec![Some("2001:db8:cafe::17")],
+        );
+        test(
+            "for=192.0.2.43, for=198.51.100.17;by=203.0.113.60;proto=http",
+            vec![Some("192.0.2.43"), Some("198.51.100.17")],
+        );
+        test(
+            "for=unknown, by=1.2.3.4, for=_hidden, for=\"_a,b\", for=1.1.1.1",
+            vec![None, None, None, None, Some("1.1.1.1")],
+        );
+    }
+
     #[test]
     fn test_parse_xff_header() {
         #[track_caller]
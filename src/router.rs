@@ -1,4 +1,5 @@
 use axum::extract::DefaultBodyLimit;
+use axum::middleware::from_fn_with_state;
 use axum::response::IntoResponse;
 use axum::routing::{delete, get, post, put};
 use axum::Router;
@@ -6,6 +7,7 @@ use http::{Method, StatusCode};
 
 use crate::app::AppState;
 use crate::controllers::*;
+use crate::middleware::download_rate_limit;
 use crate::util::errors::not_found;
 use crate::Env;
 
@@ -22,6 +24,7 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
                 .layer(DefaultBodyLimit::max(MAX_PUBLISH_CONTENT_LENGTH))
                 .get(krate::metadata::show_new),
         )
+        .route("/api/v1/publish_nonce", post(krate::publish::nonce::create))
         .route(
             "/api/v1/crates/:crate_id/owners",
             get(krate::owners::owners)
@@ -36,9 +39,21 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/:version/unyank",
             put(version::yank::unyank),
         )
+        .route(
+            "/api/v1/crates/:crate_id/yank_bulk",
+            post(version::yank::bulk_yank),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/artifacts/:target",
+            put(version::artifacts::upload)
+                .layer(DefaultBodyLimit::max(MAX_PUBLISH_CONTENT_LENGTH)),
+        )
         .route(
             "/api/v1/crates/:crate_id/:version/download",
-            get(version::downloads::download),
+            get(version::downloads::download).layer(from_fn_with_state(
+                state.clone(),
+                download_rate_limit::middleware,
+            )),
         )
         // Routes used by the frontend
         .route("/api/v1/crates/:crate_id", get(krate::metadata::show))
@@ -54,6 +69,14 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/:version/dependencies",
             get(version::metadata::dependencies),
         )
+        .route(
+            "/api/v1/crates/:crate_id/:version/dependency_graph",
+            get(version::dependency_graph::dependency_graph),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/license_summary",
+            get(version::license_summary::license_summary),
+        )
         .route(
             "/api/v1/crates/:crate_id/:version/downloads",
             get(version::downloads::downloads),
@@ -62,14 +85,59 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/:version/authors",
             get(version::metadata::authors),
         )
+        .route(
+            "/api/v1/crates/:crate_id/:version/provenance",
+            get(version::metadata::provenance),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/artifacts",
+            get(version::artifacts::list),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/release_notes",
+            get(version::release_notes::show).put(version::release_notes::update),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/status",
+            get(version::status::status),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/scheduled_release",
+            delete(krate::scheduled_releases::cancel),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/scheduled_release/release",
+            put(krate::scheduled_releases::release),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/:version/file/*path",
+            get(version::files::file),
+        )
         .route(
             "/api/v1/crates/:crate_id/downloads",
             get(krate::downloads::downloads),
         )
+        .route(
+            "/api/v1/crates/:crate_id/client_downloads",
+            get(krate::downloads::client_downloads),
+        )
+        .route("/api/v1/crates/:crate_id/diff", get(krate::diff::diff))
         .route(
             "/api/v1/crates/:crate_id/versions",
             get(krate::versions::versions),
         )
+        .route(
+            "/api/v1/crates/:crate_id/availability",
+            get(krate::availability::availability),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/scheduled_releases",
+            get(krate::scheduled_releases::list),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/reserve",
+            post(krate::reserve::reserve),
+        )
         .route(
             "/api/v1/crates/:crate_id/follow",
             put(krate::follow::follow).delete(krate::follow::unfollow),
@@ -78,6 +146,22 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/following",
             get(krate::follow::following),
         )
+        .route(
+            "/api/v1/crates/:crate_id/permissions",
+            get(krate::permissions::permissions),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/descriptions",
+            get(krate::descriptions::list),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/descriptions/:locale",
+            put(krate::descriptions::update).delete(krate::descriptions::delete),
+        )
+        .route(
+            "/api/v1/crates/:crate_id/recommended_version",
+            put(krate::recommended_version::update).delete(krate::recommended_version::delete),
+        )
         .route(
             "/api/v1/crates/:crate_id/owner_team",
             get(krate::owners::owner_team),
@@ -86,10 +170,19 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/crates/:crate_id/owner_user",
             get(krate::owners::owner_user),
         )
+        .route(
+            "/api/v1/crates/:crate_id/owners/history",
+            get(krate::owners::owner_history),
+        )
         .route(
             "/api/v1/crates/:crate_id/reverse_dependencies",
             get(krate::metadata::reverse_dependencies),
         )
+        .route(
+            "/api/v1/crates/:crate_id/dependents_history",
+            get(krate::metadata::dependents_history),
+        )
+        .route("/api/v1/dependencies/validate", post(dependency::validate))
         .route("/api/v1/keywords", get(keyword::index))
         .route("/api/v1/keywords/:keyword_id", get(keyword::show))
         .route("/api/v1/categories", get(category::index))
@@ -103,9 +196,16 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
         .route("/api/v1/teams/:team_id", get(team::show_team))
         .route("/api/v1/me", get(user::me::me))
         .route("/api/v1/me/updates", get(user::me::updates))
+        .route("/api/v1/me/crates", get(user::me::crates))
+        .route("/api/v1/me/export", get(user::me::export_data))
+        .route(
+            "/api/v1/me/export/:token",
+            get(user::me::download_data_export),
+        )
         .route("/api/v1/me/tokens", get(token::list).put(token::new))
         .route("/api/v1/me/tokens/:id", delete(token::revoke))
         .route("/api/v1/tokens/current", delete(token::revoke_current))
+        .route("/api/v1/tokens/revoke/:token", put(token::revoke_with_link))
         .route(
             "/api/v1/me/crate_owner_invitations",
             get(crate_owner_invitation::list),
@@ -118,15 +218,55 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/me/crate_owner_invitations/accept/:token",
             put(crate_owner_invitation::handle_invite_with_token),
         )
+        .route(
+            "/api/v1/me/invitation_settings",
+            get(user::invitation_settings::show).put(user::invitation_settings::update),
+        )
+        .route(
+            "/api/v1/me/invitation_settings/blocked_users/:login",
+            put(user::invitation_settings::block_user)
+                .delete(user::invitation_settings::unblock_user),
+        )
+        .route("/api/v1/me/usage", get(user::usage::show))
         .route(
             "/api/v1/me/email_notifications",
             put(user::me::update_email_notifications),
         )
+        .route(
+            "/api/v1/me/follow_digest",
+            put(user::me::update_follow_digest),
+        )
+        .route("/api/v1/me/following", put(krate::follow::update_following))
+        .route("/api/v1/me/locale", put(user::me::update_locale))
+        .route(
+            "/api/v1/me/emails",
+            get(user::emails::list).post(user::emails::add),
+        )
+        .route("/api/v1/me/emails/:email_id", delete(user::emails::delete))
+        .route(
+            "/api/v1/me/emails/:email_id/primary",
+            put(user::emails::set_primary),
+        )
+        .route(
+            "/api/v1/me/emails/:email_id/resend",
+            put(user::emails::resend),
+        )
+        .route(
+            "/api/v1/unsubscribe/follow_digest/:token",
+            put(user::me::unsubscribe_follow_digest),
+        )
         .route("/api/v1/summary", get(summary::summary))
+        .route("/api/v1/readme_images", get(readme_image::proxy))
+        .route("/api/v1/stats/trending", get(stats::trending))
+        .route("/api/v1/stats/site", get(stats::site))
         .route(
             "/api/v1/confirm/:email_token",
             put(user::me::confirm_user_email),
         )
+        .route(
+            "/api/v1/confirm_publishes/:token",
+            put(user::me::confirm_publishes),
+        )
         .route(
             "/api/v1/users/:user_id/resend",
             put(user::me::regenerate_token_and_send),
@@ -135,20 +275,85 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
             "/api/v1/site_metadata",
             get(site_metadata::show_deployed_sha),
         )
+        // Search engine crawlers
+        .route("/robots.txt", get(sitemap::robots_txt))
+        .route("/sitemap.xml", get(sitemap::index))
+        .route("/sitemaps/crates/:page", get(sitemap::crate_list))
+        .route("/sitemaps/versions", get(sitemap::recent_versions))
+        // Index signing
+        .route(
+            "/.well-known/index-signing-keys.json",
+            get(index_signing::list_keys),
+        )
         // Session management
         .route("/api/private/session/begin", get(user::session::begin))
         .route(
             "/api/private/session/authorize",
             get(user::session::authorize),
         )
+        .route(
+            "/api/private/session/challenge",
+            put(user::session::challenge),
+        )
         .route("/api/private/session", delete(user::session::logout))
         // Metrics
         .route("/api/private/metrics/:kind", get(metrics::prometheus))
+        .route("/api/private/build_info", get(build_info::build_info))
         // Crate ownership invitations management in the frontend
         .route(
             "/api/private/crate_owner_invitations",
             get(crate_owner_invitation::private_list),
         )
+        // Administrative actions
+        .route(
+            "/api/private/admin/crates/:crate_id/resync",
+            post(admin::resync_crate),
+        )
+        .route(
+            "/api/private/admin/crates/:crate_id/overview",
+            get(admin::crate_overview),
+        )
+        .route(
+            "/api/private/admin/categories/:category_id/synonyms",
+            post(admin::add_category_synonym),
+        )
+        .route(
+            "/api/private/admin/categories/:category_id/migrations",
+            post(admin::migrate_category),
+        )
+        .route(
+            "/api/private/admin/crates/:crate_id/:version/quarantine",
+            post(admin::quarantine_version),
+        )
+        .route(
+            "/api/private/admin/crates/:crate_id/:version/quarantine/release",
+            post(admin::release_quarantine),
+        )
+        .route(
+            "/api/private/admin/crates/:crate_id/:version/quarantine/delete",
+            post(admin::delete_quarantined_version),
+        )
+        .route(
+            "/api/private/admin/quarantines",
+            get(admin::list_quarantines),
+        )
+        .route(
+            "/api/private/admin/blocked_routes",
+            get(admin::list_blocked_routes)
+                .post(admin::block_route)
+                .delete(admin::unblock_route),
+        )
+        .route(
+            "/api/private/admin/excluded_crate_names",
+            get(admin::list_excluded_crate_names)
+                .post(admin::exclude_crate_name)
+                .delete(admin::include_crate_name),
+        )
+        .route(
+            "/api/private/admin/config/reload",
+            post(admin::reload_config),
+        )
+        .route("/api/private/admin/jobs", get(admin::job_status))
         // Alerts from GitHub scanning for exposed API tokens
         .route(
             "/api/github/secret-scanning/verify",
@@ -166,6 +371,12 @@ pub fn build_axum_router(state: AppState) -> Router<()> {
         );
     }
 
+    // Serve the sparse index of the upstream-proxy mode, which includes the
+    // crates of the upstream registry.
+    if state.upstream.is_some() {
+        router = router.route("/index/*path", get(upstream::index));
+    }
+
     router
         .fallback(|method: Method| async move {
             match method {
@@ -24,6 +24,18 @@ pub mod sql_types {
     pub use diesel_full_text_search::Tsvector;
 }
 
+diesel::table! {
+    /// Approximate number of API requests that were authenticated with an API token per month.
+    api_token_usage (api_token_id, month) {
+        /// Reference to the API token that the requests were authenticated with
+        api_token_id -> Int4,
+        /// First day of the month in which the requests were sent
+        month -> Date,
+        /// Number of requests
+        requests -> Int8,
+    }
+}
+
 diesel::table! {
     /// Representation of the `api_tokens` table.
     ///
@@ -84,6 +96,84 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Audit events that were not exported to the external SIEM yet. The events are written in the transaction of the action that they record, so the events of rolled back actions are never exported.
+    audit_export_outbox (id) {
+        /// Unique identifier of the event, in the order in which the events were recorded
+        id -> Int8,
+        /// JSON representation of the event
+        event -> Jsonb,
+        /// Date and time at which the event was recorded
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Log of background jobs that were recovered after the lease of their worker expired
+    background_job_incidents (id) {
+        /// Unique identifier of the incident
+        id -> Int8,
+        /// Reference to the job in the `background_jobs` table, which might not exist anymore
+        job_id -> Int8,
+        /// Type of the job
+        job_type -> Text,
+        /// Name of the worker whose lease expired
+        worker -> Text,
+        /// How the job was recovered: `retried` if it was released for an immediate retry, `failed` if it was marked as failed because its lease had expired before
+        action -> Text,
+        /// Date and time when the incident was recorded
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Leases of the background jobs that are currently running. The worker of a job regularly extends the lease, so an expired lease means that the worker crashed or hangs while the job is still marked as running. There is intentionally no foreign key to `background_jobs`, since the lease is written while the job row is locked by the worker.
+    background_job_leases (job_id) {
+        /// Reference to the job in the `background_jobs` table
+        job_id -> Int8,
+        /// Name of the worker that runs the job
+        worker -> Text,
+        /// Process ID of the database session whose transaction locks the job row
+        backend_pid -> Int4,
+        /// Date and time when the job was started
+        started_at -> Timestamp,
+        /// Date and time of the last heartbeat of the worker
+        heartbeat_at -> Timestamp,
+        /// Date and time after which the job is considered stuck, unless the lease is extended before
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Statistics of the advisory locks that keep background jobs with the same lock key, e.g. the index syncs of a crate, from running at the same time
+    background_job_lock_waits (job_type) {
+        /// Type of the jobs
+        job_type -> Text,
+        /// Number of times a job of this type acquired its lock
+        acquisitions -> Int8,
+        /// Number of times a job of this type had to wait for another job that held the lock
+        contentions -> Int8,
+        /// Total time in seconds that jobs of this type waited for their lock
+        wait_seconds -> Float8,
+        /// Date and time when a job of this type last acquired its lock
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Latest progress reported by long-running background jobs. There is intentionally no foreign key to `background_jobs`, since the progress is written while the job row is locked by the worker.
+    background_job_progress (job_id) {
+        /// Reference to the job in the `background_jobs` table
+        job_id -> Int8,
+        /// Progress of the job in percent, from 0 to 100
+        percent -> Int2,
+        /// Description of the current step of the job
+        message -> Text,
+        /// Date and time when the progress was last reported
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `background_jobs` table.
     ///
@@ -131,6 +221,20 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         priority -> Int2,
+        /// ID of the HTTP request that enqueued the job, used to correlate the job logs and Sentry events with the request. `NULL` if the job was not enqueued by a request.
+        request_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    /// HTTP route patterns that are temporarily blocked by an administrator, e.g. during an incident. Requests to these routes are answered with a `503 Service Unavailable` response.
+    blocked_routes (route) {
+        /// Pattern of the blocked route, e.g. `/api/v1/crates/:crate_id/:version/download`
+        route -> Varchar,
+        /// Reference to the administrator who blocked the route
+        created_by -> Nullable<Int4>,
+        /// Date and time when the route was blocked
+        created_at -> Timestamp,
     }
 }
 
@@ -181,6 +285,106 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Audit trail of the bulk migrations of crates from one category to another by the crates.io team, e.g. after changes of the category taxonomy. The migrations are performed by the `migrate_category` background job.
+    category_migrations (id) {
+        /// Unique identifier of the migration
+        id -> Int8,
+        /// Reference to the category whose crates are moved
+        source_category_id -> Int4,
+        /// Reference to the category that the crates are moved to
+        target_category_id -> Int4,
+        /// Reference to the administrator that requested the migration
+        created_by -> Nullable<Int4>,
+        /// Number of crates that were moved, or NULL if the migration has not been performed yet
+        crates_migrated -> Nullable<Int4>,
+        /// Date and time when the migration was requested
+        created_at -> Timestamp,
+        /// Date and time when the migration was performed, or NULL if it has not been performed yet
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    /// Alternative slugs of categories, e.g. the old slugs of renamed categories. Crates that are published with a synonym are added to the category of the synonym.
+    category_synonyms (synonym) {
+        /// The alternative slug, which must not be the slug of an existing category
+        synonym -> Varchar,
+        /// Reference to the category that the synonym resolves to
+        category_id -> Int4,
+        /// Reference to the administrator that created the synonym
+        created_by -> Nullable<Int4>,
+        /// Date and time when the synonym was created
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Results of the `probe_cdn_health` background job, which requests a file from the CDN to measure its error rate. Download redirects fall back to a secondary location while the recent probes show an elevated error rate.
+    cdn_health_probes (id) {
+        /// Unique identifier of the probe
+        id -> Int8,
+        /// Date and time when the probe was performed
+        probed_at -> Timestamp,
+        /// Number of requests that were sent to the CDN
+        requests -> Int4,
+        /// Number of requests that failed or returned an error status
+        errors -> Int4,
+    }
+}
+
+diesel::table! {
+    /// Paths that need to be invalidated on CloudFront. The queue is flushed in batches by the `flush_cloudfront_invalidations` background job, since CloudFront charges per path and rate limits the invalidation API.
+    cloudfront_invalidation_queue (id) {
+        /// Unique identifier of the queued path, which also determines the order in which the paths are invalidated
+        id -> Int8,
+        /// Path of the file to invalidate, e.g. `/config.json` or `/re/ge/regex`
+        path -> Text,
+        /// Date and time when the path was queued for invalidation
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Number of downloads per crate, day and version of the cargo client, as counted from the CDN log files.
+    crate_client_downloads (crate_id, date, client_version) {
+        /// Reference to the crate that was downloaded
+        crate_id -> Int4,
+        /// The major and minor version of the cargo client, e.g. `1.74`
+        client_version -> Varchar,
+        /// The day on which the downloads happened
+        date -> Date,
+        /// The number of downloads on this day with this client version
+        downloads -> Int4,
+    }
+}
+
+diesel::table! {
+    /// Weekly snapshots of the number of crates that depend on a crate, as computed by the `snapshot_dependents_counts` background job. Crates without dependents are not included.
+    crate_dependents_history (crate_id, date) {
+        /// Reference to the crate that the other crates depend on
+        crate_id -> Int4,
+        /// The day on which the snapshot was taken
+        date -> Date,
+        /// The number of crates whose latest version depends on the crate
+        dependents -> Int4,
+    }
+}
+
+diesel::table! {
+    /// Translations of the descriptions of crates, which are provided by the crate owners. The API returns the translation that best matches the `Accept-Language` header of the request instead of the description of the manifest.
+    crate_description_translations (crate_id, locale) {
+        /// Reference to the crate that the description belongs to
+        crate_id -> Int4,
+        /// Lowercase language tag of the translation, e.g. `de` or `pt-br`
+        locale -> Text,
+        /// The translated description
+        description -> Text,
+        /// Date and time when the translation was last changed
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Number of downloads per crate. This was extracted from the `crates` table for performance reasons.
     crate_downloads (crate_id) {
@@ -191,6 +395,42 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Crate names that have been reserved by a user for a limited period of time. Other users can not publish a crate with a reserved name until the reservation expires.
+    crate_name_reservations (name) {
+        /// The reserved crate name
+        name -> Varchar,
+        /// Reference to the user that reserved the crate name
+        user_id -> Int4,
+        /// Time when the crate name was reserved
+        created_at -> Timestamp,
+        /// Time when the reservation expires
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Audit log of the owners that were added to or removed from crates. Used to show the ownership history of a crate.
+    crate_owner_actions (id) {
+        /// Unique identifier of the action
+        id -> Int4,
+        /// Reference to the crate whose owners were changed
+        crate_id -> Int4,
+        /// Reference to the user or team that was added or removed, depending on `owner_kind`
+        owner_id -> Int4,
+        /// Kind of the owner: 0 = user, 1 = team
+        owner_kind -> Int4,
+        /// Reference to the user that performed the action, or NULL if it was performed by the crates.io team or is unknown
+        actor_id -> Nullable<Int4>,
+        /// The action: 0 = add, 1 = remove
+        action -> Int4,
+        /// How the action was performed: 0 = unknown, 1 = publish of the first version, 2 = accepted invitation, 3 = direct change by an owner, 4 = crates.io team
+        via -> Int4,
+        /// Date and time when the action was performed
+        time -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `crate_owner_invitations` table.
     ///
@@ -283,6 +523,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Versions that the crate owners recommend to users, e.g. the latest release of a long-term support line. The recommended version is independent of the highest version of the crate.
+    crate_recommended_versions (crate_id) {
+        /// Reference to the crate
+        crate_id -> Int4,
+        /// Reference to the recommended version of the crate
+        version_id -> Int4,
+        /// Reference to the user who last changed the recommended version
+        updated_by -> Nullable<Int4>,
+        /// Date and time when the recommended version was last changed
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::Tsvector;
@@ -406,6 +660,44 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Dependencies whose version requirement was changed or could not be parsed by a backfill, which is the report of the backfill.
+    dependency_req_backfill_rows (backfill_id, dependency_id) {
+        /// Reference to the backfill
+        backfill_id -> Int8,
+        /// Reference to the dependency
+        dependency_id -> Int4,
+        /// Version requirement before the backfill
+        original_req -> Varchar,
+        /// Canonical form of the version requirement, or NULL if it could not be parsed
+        normalized_req -> Nullable<Varchar>,
+        /// Error of the semver parser if the version requirement could not be parsed
+        error -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    /// Runs of the backfill that rewrites the version requirements of all dependencies in their canonical semver form.
+    dependency_req_backfills (id) {
+        /// Unique identifier of the backfill
+        id -> Int8,
+        /// Whether the backfill only reports the requirements that would be changed, without changing them
+        dry_run -> Bool,
+        /// Checkpoint of the backfill: the ID of the last dependency that was processed
+        last_dependency_id -> Int4,
+        /// Number of dependencies that were processed
+        checked -> Int8,
+        /// Number of dependencies whose requirement was not in its canonical form
+        normalized -> Int8,
+        /// Number of dependencies whose requirement could not be parsed
+        irregular -> Int8,
+        /// Date and time when the backfill was started
+        created_at -> Timestamp,
+        /// Date and time when the backfill processed the last dependency
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     /// Representation of the `dependencies` table.
     ///
@@ -474,6 +766,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Days on which a crate was downloaded unusually often compared to the previous weeks, as detected by the `detect_download_spikes` background job. Such spikes can be caused by dependency confusion attacks or misconfigured mirrors.
+    download_spikes (crate_id, date) {
+        /// Reference to the crate that was downloaded
+        crate_id -> Int4,
+        /// The day with the unusually high number of downloads
+        date -> Date,
+        /// The number of downloads of all versions of the crate on this day
+        downloads -> Int8,
+        /// The median of the daily downloads of the crate in the weeks before, which the downloads were compared to
+        baseline -> Int8,
+        /// Date and time when the spike was detected
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `emails` table.
     ///
@@ -515,6 +823,20 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         token_generated_at -> Nullable<Timestamp>,
+        /// Whether this is the primary email address of the user, which is used for notifications. Every user has at most one primary email address.
+        is_primary -> Bool,
+    }
+}
+
+diesel::table! {
+    /// Names of crates that are excluded from the most downloaded lists of the summary endpoint, e.g. because their downloads are inflated by automated systems.
+    excluded_crate_names (name) {
+        /// Exact name of the excluded crate
+        name -> Varchar,
+        /// Reference to the administrator who excluded the crate
+        created_by -> Nullable<Int4>,
+        /// Date and time when the crate was excluded
+        created_at -> Timestamp,
     }
 }
 
@@ -538,6 +860,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Users whose crate ownership invitations are rejected by another user.
+    invitation_blocks (user_id, blocked_user_id) {
+        /// Reference to the user who blocks the invitations
+        user_id -> Int4,
+        /// Reference to the user whose invitations are blocked
+        blocked_user_id -> Int4,
+        /// Date and time when the user was blocked
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `keywords` table.
     ///
@@ -626,6 +960,24 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// One-time nonces that authorize a single publish of a crate version, so that captured publish requests can not be replayed.
+    publish_nonces (nonce) {
+        /// SHA-256 hash of the nonce
+        nonce -> Bytea,
+        /// Reference to the user who requested the nonce
+        user_id -> Int4,
+        /// Name of the crate that the nonce is bound to
+        crate_name -> Varchar,
+        /// Version that the nonce is bound to
+        version -> Varchar,
+        /// Date and time when the nonce was requested
+        created_at -> Timestamp,
+        /// Date and time after which the nonce can no longer be used
+        expires_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `publish_rate_overrides` table.
     ///
@@ -710,6 +1062,66 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Versions that were published with a release time. Until the release, the versions are hidden from the index and the API, and their crate files are kept in a private location. Resolved schedules are kept as an audit trail.
+    scheduled_releases (id) {
+        /// Unique identifier of the scheduled release
+        id -> Int4,
+        /// Reference to the scheduled version, or NULL if the version was deleted
+        version_id -> Nullable<Int4>,
+        /// Name of the crate, which is kept after the version was deleted
+        crate_name -> Varchar,
+        /// Version number of the scheduled version, which is kept after the version was deleted
+        version_num -> Varchar,
+        /// Date and time when the version is released
+        release_at -> Timestamp,
+        /// Reference to the user who published the version
+        scheduled_by -> Int4,
+        /// Date and time when the version was published
+        scheduled_at -> Timestamp,
+        /// Random identifier of the private location of the crate file until the release
+        upload_id -> Varchar,
+        /// The job that renders the README of the version, which is only enqueued on release
+        readme_job -> Nullable<Jsonb>,
+        /// Outcome of the schedule (`released` or `cancelled`), or NULL while the release is pending
+        resolution -> Nullable<Varchar>,
+        /// Reference to the owner who released the version early or cancelled the release, or NULL if the version was released at the scheduled time
+        resolved_by -> Nullable<Int4>,
+        /// Date and time when the version was released or the release was cancelled
+        resolved_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    /// Daily rollups of the registry-wide statistics, as computed by the `update_site_stats` background job. Used by the `GET /api/v1/stats/site` endpoint.
+    site_stats (date) {
+        /// The day that the rollup covers. The rollup is computed after the end of the day.
+        date -> Date,
+        /// The total number of crates when the rollup was computed
+        crates -> Int8,
+        /// The total number of versions when the rollup was computed
+        versions -> Int8,
+        /// The total number of downloads when the rollup was computed
+        downloads -> Int8,
+        /// The number of versions that were published on the day
+        publishes -> Int4,
+        /// The number of users that signed up on the day
+        new_users -> Int4,
+    }
+}
+
+diesel::table! {
+    /// Cache of the generated sitemap documents, to avoid querying all crates for every request by a search engine crawler.
+    sitemaps (path) {
+        /// Request path of the sitemap document
+        path -> Varchar,
+        /// The generated XML document
+        content -> Text,
+        /// Time when the document was generated
+        generated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `teams` table.
     ///
@@ -742,6 +1154,114 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    /// Unusual API token activity that was detected by the `detect_token_anomalies` background job, e.g. publishes from new networks. Used to notify the users, and to lock their publishes until they confirm the activity.
+    token_anomalies (id) {
+        /// Unique identifier of the anomaly
+        id -> Int8,
+        /// Reference to the user that owns the API token
+        user_id -> Int4,
+        /// Reference to the API token that was used, if the anomaly is specific to a single token
+        api_token_id -> Nullable<Int4>,
+        /// Kind of the anomaly: `new_network`, `impossible_travel` or `publish_burst`
+        kind -> Text,
+        /// Human-readable description of the activity that was flagged, which is included in the notification email
+        details -> Text,
+        /// Whether publishing is blocked for the user until the anomaly is confirmed
+        publishes_locked -> Bool,
+        /// Secret token of the confirmation link in the notification email
+        confirmation_token -> Text,
+        /// Date and time when the user confirmed the activity, or NULL if it was not confirmed yet
+        confirmed_at -> Nullable<Timestamp>,
+        /// Date and time when the anomaly was detected
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Possible typosquatting of popular crates by new crates, as detected by the `check_typosquat` background job.
+    typosquat_flags (id) {
+        /// Unique identifier of the flag
+        id -> Int4,
+        /// Reference to the new crate that may be typosquatting another crate
+        crate_id -> Int4,
+        /// The name of the popular crate that may be typosquatted
+        squatted_crate -> Varchar,
+        /// A description of the check that was triggered
+        description -> Text,
+        /// Date and time when the possible typosquatting was detected
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Daily snapshots of the downloads and publishes per keyword and category in the last week and the week before, as computed by the `update_trending_stats` background job. Keywords and categories without any downloads or publishes in both weeks are not included.
+    trending_stats (date, kind, name) {
+        /// The day on which the snapshot was taken. The weeks end on the day before.
+        date -> Date,
+        /// Whether the row belongs to a keyword or a category (`keyword` or `category`)
+        kind -> Varchar,
+        /// The keyword, or the slug of the category
+        name -> Varchar,
+        /// The number of downloads of the crates with the keyword or in the category in the last week
+        downloads -> Int8,
+        /// The number of downloads of the crates with the keyword or in the category in the week before the last week
+        previous_downloads -> Int8,
+        /// The number of versions published of the crates with the keyword or in the category in the last week
+        publishes -> Int4,
+        /// The number of versions published of the crates with the keyword or in the category in the week before the last week
+        previous_publishes -> Int4,
+    }
+}
+
+diesel::table! {
+    /// Approximate number of authenticated API requests of users per month, regardless of whether they were authenticated with a session cookie or an API token.
+    user_api_usage (user_id, month) {
+        /// Reference to the user who sent the requests
+        user_id -> Int4,
+        /// First day of the month in which the requests were sent
+        month -> Date,
+        /// Number of requests
+        requests -> Int8,
+    }
+}
+
+diesel::table! {
+    /// Successful sign-ins of users. Used to detect suspicious sign-ins from unusual locations.
+    user_sign_ins (id) {
+        /// Unique identifier of the sign-in
+        id -> Int8,
+        /// Reference to the user that signed in
+        user_id -> Int4,
+        /// IP address of the client that signed in
+        ip -> Inet,
+        /// Date and time of the sign-in
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Audit trail of user accounts that were merged into other accounts by the crates.io team, e.g. after GitHub account changes.
+    user_merges (id) {
+        /// Unique identifier of the merge
+        id -> Int8,
+        /// Reference to the user that was merged into the target user and then locked
+        source_user_id -> Int4,
+        /// Reference to the user that received the crate ownerships and follows of the source user
+        target_user_id -> Int4,
+        /// Number of crate ownerships that were transferred to the target user
+        crates_transferred -> Int4,
+        /// Number of followed crates that were transferred to the target user
+        follows_transferred -> Int4,
+        /// Number of API tokens of the source user that were revoked
+        tokens_revoked -> Int4,
+        /// Whether the email address of the source user was transferred to the target user
+        email_transferred -> Bool,
+        /// Date and time of the merge
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     /// Representation of the `users` table.
     ///
@@ -801,6 +1321,37 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         is_admin -> Bool,
+        /// Whether the user receives the weekly email digest of new versions of the crates they follow.
+        follow_digest -> Bool,
+        /// The preferred language of the user for emails, e.g. `en` or `de`.
+        locale -> Varchar,
+        /// Date and time when GitHub rejected the access token of the user, e.g. because the user revoked the authorization of the OAuth app. The sessions of the user are invalid and publishing is blocked until the user signs in again. NULL if the token was not rejected.
+        gh_token_revoked_at -> Nullable<Timestamp>,
+        /// Date and time when the user signed up. NULL for users that signed up before the column was added.
+        created_at -> Nullable<Timestamp>,
+        /// Whether the user rejects ownership invitations from users that they do not share the ownership of a crate with.
+        block_unsolicited_invitations -> Bool,
+    }
+}
+
+diesel::table! {
+    /// Pre-built binary artifacts of versions, one per target platform, which the owners of a crate upload after publishing a version. The files are stored next to the crate files, so that tools like cargo-binstall can download official binaries instead of building the crate.
+    version_artifacts (id) {
+        /// Unique identifier of the artifact
+        id -> Int4,
+        /// Reference to the version that the artifact was built from
+        version_id -> Int4,
+        /// Target triple of the platform that the artifact was built for, e.g. `x86_64-unknown-linux-gnu`
+        target -> Varchar,
+        /// SHA256 checksum of the artifact file, as a hex string
+        #[max_length = 64]
+        checksum -> Bpchar,
+        /// Size of the artifact file in bytes
+        size -> Int8,
+        /// Reference to the user who uploaded the artifact
+        uploaded_by -> Int4,
+        /// Date and time when the artifact was uploaded
+        created_at -> Timestamp,
     }
 }
 
@@ -883,6 +1434,72 @@ diesel::table! {
         ///
         /// (Automatically generated by Diesel.)
         time -> Timestamp,
+        /// IP address of the client that performed the action. Used to detect publishes from unusual locations.
+        ip -> Nullable<Inet>,
+        /// Reason given by the user for the action, e.g. the advisory that caused a bulk yank.
+        reason -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    /// Information about how a version was published, e.g. whether it was published by a human or from a CI workflow.
+    version_provenance (version_id) {
+        /// Reference to the published version
+        version_id -> Int4,
+        /// Reference to the API token that was used to publish the version, or NULL if it was published with a session cookie or the token was deleted since
+        api_token_id -> Nullable<Int4>,
+        /// Name of the API token at the time of the publish, since the token might be renamed or deleted later on
+        api_token_name -> Nullable<Varchar>,
+        /// Name of the CI service that the publish request originated from, or NULL if it was not detected as a CI publish
+        ci_service -> Nullable<Varchar>,
+        /// User-Agent header of the publish request
+        user_agent -> Nullable<Varchar>,
+        /// IP address of the client that published the version
+        ip -> Nullable<Inet>,
+        /// Date and time of the publish
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    /// Versions that were quarantined by an administrator pending a review. Downloads of quarantined versions through the API are rejected and the versions are marked as yanked in the index, until the quarantine is resolved by releasing or deleting the version. Resolved quarantines are kept as an audit trail.
+    version_quarantines (id) {
+        /// Unique identifier of the quarantine
+        id -> Int4,
+        /// Reference to the quarantined version, or NULL if the version was deleted
+        version_id -> Nullable<Int4>,
+        /// Name of the crate, which is kept after the version was deleted
+        crate_name -> Varchar,
+        /// Version number of the quarantined version, which is kept after the version was deleted
+        version_num -> Varchar,
+        /// Reason for the quarantine, which is sent to the owners of the crate
+        reason -> Text,
+        /// Reference to the administrator who quarantined the version
+        quarantined_by -> Int4,
+        /// Date and time when the version was quarantined
+        quarantined_at -> Timestamp,
+        /// Outcome of the review (`released` or `deleted`), or NULL while the version is quarantined
+        resolution -> Nullable<Varchar>,
+        /// Reference to the administrator who resolved the quarantine
+        resolved_by -> Nullable<Int4>,
+        /// Date and time when the quarantine was resolved
+        resolved_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    /// Release notes of versions, which the owners of a crate can add or change after publishing a version, so that changelogs can be displayed without parsing files of the repository.
+    version_release_notes (version_id) {
+        /// Reference to the version that the release notes belong to
+        version_id -> Int4,
+        /// The release notes as Markdown, as they were submitted by the owner
+        markdown -> Text,
+        /// The release notes rendered to HTML
+        html -> Text,
+        /// Reference to the user who last changed the release notes
+        updated_by -> Int4,
+        /// Date and time when the release notes were last changed
+        updated_at -> Timestamp,
     }
 }
 
@@ -1008,58 +1625,126 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(api_token_usage -> api_tokens (api_token_id));
 diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(blocked_routes -> users (created_by));
+diesel::joinable!(category_migrations -> users (created_by));
+diesel::joinable!(category_synonyms -> categories (category_id));
+diesel::joinable!(category_synonyms -> users (created_by));
+diesel::joinable!(crate_client_downloads -> crates (crate_id));
+diesel::joinable!(crate_dependents_history -> crates (crate_id));
+diesel::joinable!(crate_description_translations -> crates (crate_id));
 diesel::joinable!(crate_downloads -> crates (crate_id));
+diesel::joinable!(crate_name_reservations -> users (user_id));
+diesel::joinable!(crate_owner_actions -> crates (crate_id));
+diesel::joinable!(crate_owner_actions -> users (actor_id));
 diesel::joinable!(crate_owner_invitations -> crates (crate_id));
 diesel::joinable!(crate_owners -> crates (crate_id));
 diesel::joinable!(crate_owners -> teams (owner_id));
 diesel::joinable!(crate_owners -> users (owner_id));
+diesel::joinable!(crate_recommended_versions -> crates (crate_id));
+diesel::joinable!(crate_recommended_versions -> users (updated_by));
+diesel::joinable!(crate_recommended_versions -> versions (version_id));
 diesel::joinable!(crates_categories -> categories (category_id));
 diesel::joinable!(crates_categories -> crates (crate_id));
 diesel::joinable!(crates_keywords -> crates (crate_id));
 diesel::joinable!(crates_keywords -> keywords (keyword_id));
 diesel::joinable!(dependencies -> crates (crate_id));
 diesel::joinable!(dependencies -> versions (version_id));
+diesel::joinable!(dependency_req_backfill_rows -> dependencies (dependency_id));
+diesel::joinable!(dependency_req_backfill_rows -> dependency_req_backfills (backfill_id));
+diesel::joinable!(download_spikes -> crates (crate_id));
 diesel::joinable!(emails -> users (user_id));
+diesel::joinable!(excluded_crate_names -> users (created_by));
 diesel::joinable!(follows -> crates (crate_id));
 diesel::joinable!(follows -> users (user_id));
 diesel::joinable!(publish_limit_buckets -> users (user_id));
+diesel::joinable!(publish_nonces -> users (user_id));
 diesel::joinable!(publish_rate_overrides -> users (user_id));
 diesel::joinable!(readme_renderings -> versions (version_id));
 diesel::joinable!(recent_crate_downloads -> crates (crate_id));
+diesel::joinable!(scheduled_releases -> versions (version_id));
+diesel::joinable!(token_anomalies -> api_tokens (api_token_id));
+diesel::joinable!(token_anomalies -> users (user_id));
+diesel::joinable!(typosquat_flags -> crates (crate_id));
+diesel::joinable!(user_api_usage -> users (user_id));
+diesel::joinable!(user_sign_ins -> users (user_id));
+diesel::joinable!(version_artifacts -> users (uploaded_by));
+diesel::joinable!(version_artifacts -> versions (version_id));
 diesel::joinable!(version_downloads -> versions (version_id));
 diesel::joinable!(version_owner_actions -> api_tokens (api_token_id));
 diesel::joinable!(version_owner_actions -> users (user_id));
 diesel::joinable!(version_owner_actions -> versions (version_id));
+diesel::joinable!(version_provenance -> api_tokens (api_token_id));
+diesel::joinable!(version_provenance -> versions (version_id));
+diesel::joinable!(version_quarantines -> versions (version_id));
+diesel::joinable!(version_release_notes -> users (updated_by));
+diesel::joinable!(version_release_notes -> versions (version_id));
 diesel::joinable!(versions -> crates (crate_id));
 diesel::joinable!(versions -> users (published_by));
 diesel::joinable!(versions_published_by -> versions (version_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_token_usage,
     api_tokens,
+    audit_export_outbox,
+    background_job_incidents,
+    background_job_leases,
+    background_job_lock_waits,
+    background_job_progress,
     background_jobs,
+    blocked_routes,
     categories,
+    category_migrations,
+    category_synonyms,
+    cdn_health_probes,
+    cloudfront_invalidation_queue,
+    crate_client_downloads,
+    crate_dependents_history,
+    crate_description_translations,
     crate_downloads,
+    crate_name_reservations,
+    crate_owner_actions,
     crate_owner_invitations,
     crate_owners,
+    crate_recommended_versions,
     crates,
     crates_categories,
     crates_keywords,
     dependencies,
+    dependency_req_backfill_rows,
+    dependency_req_backfills,
+    download_spikes,
     emails,
+    excluded_crate_names,
     follows,
+    invitation_blocks,
     keywords,
     metadata,
     processed_log_files,
     publish_limit_buckets,
+    publish_nonces,
     publish_rate_overrides,
     readme_renderings,
     recent_crate_downloads,
     reserved_crate_names,
+    scheduled_releases,
+    site_stats,
+    sitemaps,
     teams,
+    token_anomalies,
+    trending_stats,
+    typosquat_flags,
+    user_api_usage,
+    user_merges,
+    user_sign_ins,
     users,
+    version_artifacts,
     version_downloads,
     version_owner_actions,
+    version_provenance,
+    version_quarantines,
+    version_release_notes,
     versions,
     versions_published_by,
 );
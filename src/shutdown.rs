@@ -0,0 +1,37 @@
+//! Helpers for shutting down the server and the background worker
+//!
+//! On SIGTERM both processes stop accepting new work and wait up to
+//! [`SHUTDOWN_TIMEOUT`] for the work in progress to finish, before
+//! reporting and abandoning whatever is still running.
+
+use std::time::Duration;
+use tokio::signal::unix::{signal as unix_signal, SignalKind};
+
+/// How long to wait for in-flight requests and running background jobs
+/// during a shutdown.
+///
+/// Heroku sends a SIGKILL 30 seconds after the SIGTERM, so this leaves us
+/// a few seconds to report what was abandoned.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// Resolves once the process receives a SIGINT or SIGTERM.
+pub async fn signal() {
+    let interrupt = async {
+        unix_signal(SignalKind::interrupt())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    let terminate = async {
+        unix_signal(SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+}
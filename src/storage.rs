@@ -1,5 +1,6 @@
 use anyhow::Context;
-use crates_io_env_vars::required_var;
+use chrono::{DateTime, Utc};
+use crates_io_env_vars::{required_var, var_parsed};
 use futures_util::{StreamExt, TryStreamExt};
 use hyper::body::Bytes;
 use object_store::aws::{AmazonS3, AmazonS3Builder};
@@ -7,7 +8,10 @@ use object_store::local::LocalFileSystem;
 use object_store::memory::InMemory;
 use object_store::path::Path;
 use object_store::prefix::PrefixStore;
-use object_store::{ClientOptions, ObjectStore, Result};
+use object_store::signer::Signer;
+use object_store::{
+    ClientOptions, ObjectStore, PutMultipartOpts, PutOptions, Result, TagSet, WriteMultipart,
+};
 use reqwest::header::CACHE_CONTROL;
 use reqwest::header::{HeaderMap, HeaderValue};
 use secrecy::{ExposeSecret, SecretString};
@@ -15,10 +19,13 @@ use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 const PREFIX_CRATES: &str = "crates";
 const PREFIX_READMES: &str = "readmes";
+const PREFIX_DATA_EXPORTS: &str = "data-exports";
+const PREFIX_ARTIFACTS: &str = "artifacts";
+const PREFIX_SCHEDULED_RELEASES: &str = "scheduled-releases";
 const DEFAULT_REGION: &str = "us-west-1";
 const CONTENT_TYPE_CRATE: &str = "application/gzip";
 const CONTENT_TYPE_DB_DUMP: &str = "application/gzip";
@@ -28,19 +35,48 @@ const CACHE_CONTROL_IMMUTABLE: &str = "public,max-age=31536000,immutable";
 const CACHE_CONTROL_INDEX: &str = "public,max-age=600";
 const CACHE_CONTROL_README: &str = "public,max-age=604800";
 
+/// How long presigned download URLs are valid.
+const PRESIGNED_URL_LIFETIME: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// The size of the parts of multipart uploads. S3 requires all parts except
+/// the last one to be at least 5 MiB.
+const MULTIPART_CHUNK_SIZE: usize = 10 * 1024 * 1024;
+/// The maximum number of parts of a multipart upload that are uploaded
+/// concurrently.
+const MULTIPART_MAX_CONCURRENCY: usize = 8;
+
+mod url_template;
+
+pub use self::url_template::{DownloadUrlTemplate, InvalidUrlTemplate};
+
 type StdPath = std::path::Path;
 
 #[derive(Debug)]
 pub struct StorageConfig {
     backend: StorageBackend,
     pub cdn_prefix: Option<String>,
+    /// Overrides the URLs that crate downloads are redirected to, which are
+    /// otherwise derived from the `cdn_prefix`.
+    pub download_url_template: Option<DownloadUrlTemplate>,
 }
 
+/// The file storage backends supported by [`Storage`].
+///
+/// All backends are accessed through the [`ObjectStore`] trait, so supporting
+/// another backend only requires a new variant here and a corresponding branch
+/// in [`Storage::from_config()`].
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum StorageBackend {
-    S3 { default: S3Config, index: S3Config },
-    LocalFileSystem { path: PathBuf },
+    S3 {
+        default: S3Config,
+        index: S3Config,
+        private: S3Config,
+    },
+    LocalFileSystem {
+        path: PathBuf,
+        private_path: PathBuf,
+    },
     InMemory,
 }
 
@@ -57,67 +93,122 @@ impl StorageConfig {
         Self {
             backend: StorageBackend::InMemory,
             cdn_prefix: None,
+            download_url_template: None,
         }
     }
 
+    /// Reads the storage configuration from the environment.
+    ///
+    /// The backend can be selected explicitly via the `STORAGE_BACKEND`
+    /// environment variable (`s3`, `local` or `memory`). If it is not set, the
+    /// S3 backend is used if `S3_BUCKET` is set, and the local file system
+    /// backend otherwise.
+    ///
+    /// The URLs of crate downloads can be overridden independently of the
+    /// backend via the `DOWNLOAD_URL_TEMPLATE` environment variable, see
+    /// [`DownloadUrlTemplate`].
     pub fn from_environment() -> Self {
-        if let Ok(bucket) = dotenvy::var("S3_BUCKET") {
-            let region = dotenvy::var("S3_REGION").ok();
-            let cdn_prefix = dotenvy::var("S3_CDN").ok();
-
-            let index_bucket = required_var("S3_INDEX_BUCKET").unwrap();
-            let index_region = dotenvy::var("S3_INDEX_REGION").ok();
-
-            let access_key = required_var("AWS_ACCESS_KEY").unwrap();
-            let secret_key: SecretString = required_var("AWS_SECRET_KEY").unwrap().into();
-
-            let default = S3Config {
-                bucket,
-                region,
-                access_key: access_key.clone(),
-                secret_key: secret_key.clone(),
-            };
-
-            let index = S3Config {
-                bucket: index_bucket,
-                region: index_region,
-                access_key,
-                secret_key,
-            };
-
-            let backend = StorageBackend::S3 { default, index };
-
-            return Self {
-                backend,
-                cdn_prefix,
-            };
+        let mut config = match dotenvy::var("STORAGE_BACKEND").ok().as_deref() {
+            Some("s3") => Self::s3_from_environment(),
+            Some("local") => Self::local_from_environment(),
+            Some("memory") => Self::in_memory(),
+            Some(backend) => panic!("Unknown STORAGE_BACKEND: {backend}"),
+            None if dotenvy::var("S3_BUCKET").is_ok() => Self::s3_from_environment(),
+            None => Self::local_from_environment(),
+        };
+
+        config.download_url_template = var_parsed("DOWNLOAD_URL_TEMPLATE").unwrap();
+        config
+    }
+
+    fn s3_from_environment() -> Self {
+        let bucket = required_var("S3_BUCKET").unwrap();
+        let region = dotenvy::var("S3_REGION").ok();
+        let cdn_prefix = dotenvy::var("S3_CDN").ok();
+
+        let index_bucket = required_var("S3_INDEX_BUCKET").unwrap();
+        let index_region = dotenvy::var("S3_INDEX_REGION").ok();
+
+        let private_bucket = required_var("S3_PRIVATE_BUCKET").unwrap();
+        let private_region = dotenvy::var("S3_PRIVATE_REGION").ok();
+
+        let access_key = required_var("AWS_ACCESS_KEY").unwrap();
+        let secret_key: SecretString = required_var("AWS_SECRET_KEY").unwrap().into();
+
+        let default = S3Config {
+            bucket,
+            region,
+            access_key: access_key.clone(),
+            secret_key: secret_key.clone(),
+        };
+
+        let index = S3Config {
+            bucket: index_bucket,
+            region: index_region,
+            access_key: access_key.clone(),
+            secret_key: secret_key.clone(),
+        };
+
+        let private = S3Config {
+            bucket: private_bucket,
+            region: private_region,
+            access_key,
+            secret_key,
+        };
+
+        let backend = StorageBackend::S3 {
+            default,
+            index,
+            private,
+        };
+
+        Self {
+            backend,
+            cdn_prefix,
+            download_url_template: None,
         }
+    }
 
+    fn local_from_environment() -> Self {
         let current_dir = std::env::current_dir()
             .context("Failed to read the current directory")
             .unwrap();
 
         let path = current_dir.join("local_uploads");
 
-        let backend = StorageBackend::LocalFileSystem { path };
+        // The `local_uploads` directory is served by the development server,
+        // so the private files are kept elsewhere
+        let private_path = current_dir.join("tmp").join("private_uploads");
+
+        let backend = StorageBackend::LocalFileSystem { path, private_path };
 
         Self {
             backend,
             cdn_prefix: None,
+            download_url_template: None,
         }
     }
 }
 
 pub struct Storage {
     cdn_prefix: Option<String>,
+    download_url_template: Option<DownloadUrlTemplate>,
 
     store: Box<dyn ObjectStore>,
-    crate_upload_store: Box<dyn ObjectStore>,
+    crate_upload_store: Arc<dyn ObjectStore>,
     readme_upload_store: Box<dyn ObjectStore>,
     db_dump_upload_store: Arc<dyn ObjectStore>,
 
     index_store: Box<dyn ObjectStore>,
     index_upload_store: Box<dyn ObjectStore>,
+
+    /// Stores files that must not be served by the CDN, like the personal
+    /// data exports of users.
+    private_store: Box<dyn ObjectStore>,
+
+    /// Creates presigned URLs of the files in the default store, if the
+    /// backend supports them.
+    signer: Option<Arc<dyn Signer>>,
 }
 
 impl Storage {
@@ -127,9 +218,14 @@ impl Storage {
 
     pub fn from_config(config: &StorageConfig) -> Self {
         let cdn_prefix = config.cdn_prefix.clone();
+        let download_url_template = config.download_url_template.clone();
 
         match &config.backend {
-            StorageBackend::S3 { default, index } => {
+            StorageBackend::S3 {
+                default,
+                index,
+                private,
+            } => {
                 let options = ClientOptions::default();
                 let store = build_s3(default, options);
 
@@ -149,22 +245,29 @@ impl Storage {
                 let options = client_options(CONTENT_TYPE_INDEX, CACHE_CONTROL_INDEX);
                 let index_upload_store = build_s3(index, options);
 
+                let private_store = build_s3(private, ClientOptions::default());
+
+                let signer = build_s3(default, ClientOptions::default());
+
                 if cdn_prefix.is_none() {
                     panic!("Missing S3_CDN environment variable");
                 }
 
                 Self {
                     store: Box::new(store),
-                    crate_upload_store: Box::new(crate_upload_store),
+                    crate_upload_store: Arc::new(crate_upload_store),
                     readme_upload_store: Box::new(readme_upload_store),
                     db_dump_upload_store: Arc::new(db_dump_upload_store),
                     cdn_prefix,
+                    download_url_template,
                     index_store: Box::new(index_store),
                     index_upload_store: Box::new(index_upload_store),
+                    private_store: Box::new(private_store),
+                    signer: Some(Arc::new(signer)),
                 }
             }
 
-            StorageBackend::LocalFileSystem { path } => {
+            StorageBackend::LocalFileSystem { path, private_path } => {
                 warn!(?path, "Using local file system for file storage");
 
                 let index_path = path.join("index");
@@ -173,6 +276,10 @@ impl Storage {
                     .context("Failed to create file storage directories")
                     .unwrap();
 
+                fs::create_dir_all(private_path)
+                    .context("Failed to create file storage directories")
+                    .unwrap();
+
                 let local = LocalFileSystem::new_with_prefix(path)
                     .context("Failed to initialize local file system storage")
                     .unwrap();
@@ -181,17 +288,24 @@ impl Storage {
                     .context("Failed to initialize local file system storage")
                     .unwrap();
 
+                let local_private = LocalFileSystem::new_with_prefix(private_path)
+                    .context("Failed to initialize local file system storage")
+                    .unwrap();
+
                 let store: Arc<dyn ObjectStore> = Arc::new(local);
                 let index_store: Arc<dyn ObjectStore> = Arc::new(local_index);
 
                 Self {
                     store: Box::new(store.clone()),
-                    crate_upload_store: Box::new(store.clone()),
+                    crate_upload_store: store.clone(),
                     readme_upload_store: Box::new(store.clone()),
                     db_dump_upload_store: store,
                     cdn_prefix,
+                    download_url_template,
                     index_store: Box::new(index_store.clone()),
                     index_upload_store: Box::new(index_store),
+                    private_store: Box::new(local_private),
+                    signer: None,
                 }
             }
 
@@ -201,24 +315,68 @@ impl Storage {
 
                 Self {
                     store: Box::new(store.clone()),
-                    crate_upload_store: Box::new(store.clone()),
+                    crate_upload_store: store.clone(),
                     readme_upload_store: Box::new(store.clone()),
                     db_dump_upload_store: store.clone(),
                     cdn_prefix,
+                    download_url_template,
                     index_store: Box::new(PrefixStore::new(store.clone(), "index")),
-                    index_upload_store: Box::new(PrefixStore::new(store, "index")),
+                    index_upload_store: Box::new(PrefixStore::new(store.clone(), "index")),
+                    private_store: Box::new(PrefixStore::new(store, "private")),
+                    signer: None,
                 }
             }
         }
     }
 
-    /// Returns the URL of an uploaded crate's version archive.
+    /// Returns the URL of an uploaded crate's version archive, or the URL from
+    /// the download URL template if one is configured.
     ///
     /// The function doesn't check for the existence of the file.
     pub fn crate_location(&self, name: &str, version: &str) -> String {
+        if let Some(template) = &self.download_url_template {
+            return template.render(name, version);
+        }
+
         apply_cdn_prefix(&self.cdn_prefix, &crate_file_path(name, version)).replace('+', "%2B")
     }
 
+    /// Returns the URL of an uploaded crate's version archive on another CDN
+    /// than the configured one, e.g. a secondary CDN that serves the same
+    /// files.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn crate_location_on_cdn(&self, cdn_prefix: &str, name: &str, version: &str) -> String {
+        let cdn_prefix = Some(cdn_prefix.to_string());
+        apply_cdn_prefix(&cdn_prefix, &crate_file_path(name, version)).replace('+', "%2B")
+    }
+
+    /// Returns a presigned URL of an uploaded crate's version archive, which
+    /// is served by the storage backend directly instead of the CDN.
+    ///
+    /// Returns `None` if the backend doesn't support presigned URLs. The
+    /// function doesn't check for the existence of the file.
+    pub async fn signed_crate_location(&self, name: &str, version: &str) -> Result<Option<String>> {
+        let Some(signer) = &self.signer else {
+            return Ok(None);
+        };
+
+        let path = crate_file_path(name, version);
+        let url = signer
+            .signed_url(http::Method::GET, &path, PRESIGNED_URL_LIFETIME)
+            .await?;
+
+        Ok(Some(url.to_string()))
+    }
+
+    /// Returns the URL of a file on the CDN, or `None` if no CDN is
+    /// configured.
+    pub fn cdn_location(&self, path: &str) -> Option<String> {
+        self.cdn_prefix
+            .as_ref()
+            .map(|_| apply_cdn_prefix(&self.cdn_prefix, &path.into()))
+    }
+
     /// Returns the URL of an uploaded crate's version readme.
     ///
     /// The function doesn't check for the existence of the file.
@@ -226,6 +384,41 @@ impl Storage {
         apply_cdn_prefix(&self.cdn_prefix, &readme_path(name, version)).replace('+', "%2B")
     }
 
+    /// Returns the URL of a binary artifact of a crate version.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn artifact_location(&self, name: &str, version: &str, target: &str) -> String {
+        apply_cdn_prefix(&self.cdn_prefix, &artifact_path(name, version, target))
+            .replace('+', "%2B")
+    }
+
+    /// Checks whether the crate file of a crate version exists in the storage.
+    #[instrument(skip(self))]
+    pub async fn crate_file_exists(&self, name: &str, version: &str) -> Result<bool> {
+        let path = crate_file_path(name, version);
+        self.exists(&path).await
+    }
+
+    /// Checks whether the rendered README of a crate version exists in the
+    /// storage.
+    #[instrument(skip(self))]
+    pub async fn readme_exists(&self, name: &str, version: &str) -> Result<bool> {
+        let path = readme_path(name, version);
+        self.exists(&path).await
+    }
+
+    /// Returns the content of the crate file of a crate version, or `None` if
+    /// the file does not exist.
+    #[instrument(skip(self))]
+    pub async fn read_crate_file(&self, name: &str, version: &str) -> Result<Option<Bytes>> {
+        let path = crate_file_path(name, version);
+        match self.store.get(&path).await {
+            Ok(result) => Ok(Some(result.bytes().await?)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
     #[instrument(skip(self))]
     pub async fn delete_all_crate_files(&self, name: &str) -> Result<()> {
         let prefix = format!("{PREFIX_CRATES}/{name}").into();
@@ -250,17 +443,142 @@ impl Storage {
         self.store.delete(&path).await
     }
 
+    #[instrument(skip(self))]
+    pub async fn delete_all_artifacts(&self, name: &str) -> Result<()> {
+        let prefix = format!("{PREFIX_ARTIFACTS}/{name}").into();
+        self.delete_all_with_prefix(&prefix).await
+    }
+
+    /// Deletes the binary artifacts of a crate version for all targets.
+    #[instrument(skip(self))]
+    pub async fn delete_artifacts(&self, name: &str, version: &str) -> Result<()> {
+        let prefix = format!("{PREFIX_ARTIFACTS}/{name}/{version}").into();
+        self.delete_all_with_prefix(&prefix).await
+    }
+
+    /// Uploads a crate file, tagged with the crate name and version (see
+    /// [`object_tags()`]).
     #[instrument(skip(self, bytes))]
     pub async fn upload_crate_file(&self, name: &str, version: &str, bytes: Bytes) -> Result<()> {
         let path = crate_file_path(name, version);
-        self.crate_upload_store.put(&path, bytes.into()).await?;
+        let opts = PutOptions::from(object_tags(name, version, false));
+        self.crate_upload_store
+            .put_opts(&path, bytes.into(), opts)
+            .await?;
+        Ok(())
+    }
+
+    /// Uploads a crate file from an [`AsyncRead`] source, without having to
+    /// buffer the whole file in memory first.
+    #[instrument(skip(self, reader))]
+    pub async fn upload_crate_file_stream<R: AsyncRead + Unpin>(
+        &self,
+        name: &str,
+        version: &str,
+        reader: &mut R,
+    ) -> anyhow::Result<()> {
+        let store = self.crate_upload_store.clone();
+        let path = crate_file_path(name, version);
+        let tags = object_tags(name, version, false);
+        upload_stream(store, path, tags, reader).await
+    }
+
+    /// Uploads the crate file of a version whose release is scheduled, tagged
+    /// like other crate files (see [`object_tags()`]).
+    ///
+    /// The file is stored under the random `upload_id` instead of the crate
+    /// name and version until [`Self::release_scheduled_crate_file()`] is
+    /// called, since the files of the default store are publicly accessible
+    /// through the CDN.
+    #[instrument(skip(self, bytes))]
+    pub async fn upload_scheduled_crate_file(
+        &self,
+        upload_id: &str,
+        name: &str,
+        version: &str,
+        bytes: Bytes,
+    ) -> Result<()> {
+        let path = scheduled_crate_file_path(upload_id);
+        let opts = PutOptions::from(object_tags(name, version, false));
+        self.crate_upload_store
+            .put_opts(&path, bytes.into(), opts)
+            .await?;
         Ok(())
     }
 
+    /// Uploads the crate file of a version whose release is scheduled from an
+    /// [`AsyncRead`] source (see [`Self::upload_scheduled_crate_file()`]).
+    #[instrument(skip(self, reader))]
+    pub async fn upload_scheduled_crate_file_stream<R: AsyncRead + Unpin>(
+        &self,
+        upload_id: &str,
+        name: &str,
+        version: &str,
+        reader: &mut R,
+    ) -> anyhow::Result<()> {
+        let store = self.crate_upload_store.clone();
+        let path = scheduled_crate_file_path(upload_id);
+        let tags = object_tags(name, version, false);
+        upload_stream(store, path, tags, reader).await
+    }
+
+    /// Moves the crate file of a scheduled release to the public location of
+    /// the crate file of the version.
+    ///
+    /// Succeeds without changes if the file was already moved, so that a
+    /// failed release can be retried.
+    #[instrument(skip(self))]
+    pub async fn release_scheduled_crate_file(
+        &self,
+        upload_id: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<()> {
+        let from = scheduled_crate_file_path(upload_id);
+        let to = crate_file_path(name, version);
+        match self.crate_upload_store.copy(&from, &to).await {
+            Ok(()) => self.store.delete(&from).await,
+            Err(object_store::Error::NotFound { .. }) if self.exists(&to).await? => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn delete_scheduled_crate_file(&self, upload_id: &str) -> Result<()> {
+        let path = scheduled_crate_file_path(upload_id);
+        self.store.delete(&path).await
+    }
+
+    /// Uploads a rendered README, tagged with the crate name and version (see
+    /// [`object_tags()`]).
     #[instrument(skip(self, bytes))]
     pub async fn upload_readme(&self, name: &str, version: &str, bytes: Bytes) -> Result<()> {
         let path = readme_path(name, version);
-        self.readme_upload_store.put(&path, bytes.into()).await?;
+        let opts = PutOptions::from(object_tags(name, version, false));
+        self.readme_upload_store
+            .put_opts(&path, bytes.into(), opts)
+            .await?;
+        Ok(())
+    }
+
+    /// Updates the tags of the crate file and the rendered README of a crate
+    /// version to match its yanked status.
+    ///
+    /// The [`ObjectStore`] trait does not support changing the tags of an
+    /// existing object, so the files are uploaded again with the new tags.
+    /// Files that don't exist are skipped.
+    #[instrument(skip(self))]
+    pub async fn update_tags(&self, name: &str, version: &str, yanked: bool) -> Result<()> {
+        let tags = object_tags(name, version, yanked);
+
+        let path = crate_file_path(name, version);
+        let upload_store = self.crate_upload_store.as_ref();
+        retag(self.store.as_ref(), upload_store, &path, tags.clone()).await?;
+
+        let path = readme_path(name, version);
+        let upload_store = self.readme_upload_store.as_ref();
+        retag(self.store.as_ref(), upload_store, &path, tags).await?;
+
         Ok(())
     }
 
@@ -276,6 +594,35 @@ impl Storage {
         Ok(())
     }
 
+    /// Uploads or removes the `.sig` file with the signatures of an index
+    /// file (see [`crate::index_signing`]).
+    #[instrument(skip(self, content))]
+    pub async fn sync_index_signatures(&self, name: &str, content: Option<String>) -> Result<()> {
+        let path = index_signatures_path(name);
+        if let Some(content) = content {
+            self.index_upload_store.put(&path, content.into()).await?;
+        } else {
+            match self.index_store.delete(&path).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the content of the index file of the crate, or `None` if the
+    /// crate has no index file.
+    #[instrument(skip(self))]
+    pub async fn read_index(&self, name: &str) -> Result<Option<Bytes>> {
+        let path = crates_io_index::Repository::relative_index_file_for_url(name).into();
+        match self.index_store.get(&path).await {
+            Ok(result) => Ok(Some(result.bytes().await?)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
     #[instrument(skip(self))]
     pub async fn upload_db_dump(&self, target: &str, local_path: &StdPath) -> anyhow::Result<()> {
         let store = self.db_dump_upload_store.clone();
@@ -283,39 +630,107 @@ impl Storage {
         // Open the local tarball file
         let mut local_file = File::open(local_path).await?;
 
-        // Set up a multipart upload
-        let path = target.into();
-        let mut writer = object_store::buffered::BufWriter::new(store, path);
-
-        // Upload file contents
-        if let Err(error) = tokio::io::copy(&mut local_file, &mut writer).await {
-            // Abort the upload if something failed
-            writer.abort().await?;
-            return Err(error.into());
-        }
+        upload_stream(store, target.into(), TagSet::default(), &mut local_file).await
+    }
 
-        // ... or finalize upload
-        writer.shutdown().await?;
+    /// Uploads a binary artifact of a crate version, tagged like the crate
+    /// file (see [`object_tags()`]).
+    #[instrument(skip(self, bytes))]
+    pub async fn upload_artifact(
+        &self,
+        name: &str,
+        version: &str,
+        target: &str,
+        bytes: Bytes,
+    ) -> Result<()> {
+        let path = artifact_path(name, version, target);
+        let opts = PutOptions::from(object_tags(name, version, false));
+        self.crate_upload_store
+            .put_opts(&path, bytes.into(), opts)
+            .await?;
+        Ok(())
+    }
 
+    /// Uploads the personal data export archive of a user to the private
+    /// store and deletes all previous exports of the user.
+    #[instrument(skip(self, bytes))]
+    pub async fn upload_data_export(
+        &self,
+        user_id: i32,
+        export_id: &str,
+        bytes: Bytes,
+    ) -> Result<()> {
+        let prefix = format!("{PREFIX_DATA_EXPORTS}/{user_id}").into();
+        delete_all_in_store(&*self.private_store, &prefix).await?;
+
+        let path = data_export_path(user_id, export_id);
+        self.private_store.put(&path, bytes.into()).await?;
         Ok(())
     }
 
+    /// Deletes the personal data export archives that were uploaded before
+    /// `before`, since their download links have expired.
+    ///
+    /// Returns the number of deleted archives.
+    #[instrument(skip(self))]
+    pub async fn delete_data_exports_before(&self, before: DateTime<Utc>) -> Result<usize> {
+        let prefix = PREFIX_DATA_EXPORTS.into();
+        let locations = self
+            .private_store
+            .list(Some(&prefix))
+            .try_filter(|meta| futures_util::future::ready(meta.last_modified < before))
+            .map_ok(|meta| meta.location)
+            .boxed();
+
+        let deleted = self
+            .private_store
+            .delete_stream(locations)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        Ok(deleted.len())
+    }
+
+    /// Returns a personal data export archive of a user, or `None` if the
+    /// export does not exist (anymore).
+    #[instrument(skip(self))]
+    pub async fn read_data_export(&self, user_id: i32, export_id: &str) -> Result<Option<Bytes>> {
+        let path = data_export_path(user_id, export_id);
+        match self.private_store.get(&path).await {
+            Ok(result) => Ok(Some(result.bytes().await?)),
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
     /// This should only be used for assertions in the test suite!
     pub fn as_inner(&self) -> &dyn ObjectStore {
         &self.store
     }
 
+    async fn exists(&self, path: &Path) -> Result<bool> {
+        match self.store.head(path).await {
+            Ok(_) => Ok(true),
+            Err(object_store::Error::NotFound { .. }) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
     async fn delete_all_with_prefix(&self, prefix: &Path) -> Result<()> {
-        let objects = self.store.list(Some(prefix));
-        let locations = objects.map(|meta| meta.map(|m| m.location)).boxed();
+        delete_all_in_store(&*self.store, prefix).await
+    }
+}
 
-        self.store
-            .delete_stream(locations)
-            .try_collect::<Vec<_>>()
-            .await?;
+async fn delete_all_in_store(store: &dyn ObjectStore, prefix: &Path) -> Result<()> {
+    let objects = store.list(Some(prefix));
+    let locations = objects.map(|meta| meta.map(|m| m.location)).boxed();
 
-        Ok(())
-    }
+    store
+        .delete_stream(locations)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    Ok(())
 }
 
 fn client_options(content_type: &str, cache_control: &'static str) -> ClientOptions {
@@ -339,14 +754,105 @@ fn build_s3(config: &S3Config, client_options: ClientOptions) -> AmazonS3 {
         .unwrap()
 }
 
+/// Uploads the content of `reader` to `path` with a multipart upload, so that
+/// the content does not have to be buffered in memory.
+async fn upload_stream<R: AsyncRead + Unpin>(
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    tags: TagSet,
+    reader: &mut R,
+) -> anyhow::Result<()> {
+    let opts = PutMultipartOpts {
+        tags,
+        ..Default::default()
+    };
+    let upload = store.put_multipart_opts(&path, opts).await?;
+    let mut writer = WriteMultipart::new_with_chunk_size(upload, MULTIPART_CHUNK_SIZE);
+
+    // Upload file contents
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let len = match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(error) => {
+                // Abort the upload if something failed
+                writer.abort().await?;
+                return Err(error.into());
+            }
+        };
+
+        writer.wait_for_capacity(MULTIPART_MAX_CONCURRENCY).await?;
+        writer.write(&buffer[..len]);
+    }
+
+    // ... or finalize upload
+    writer.finish().await?;
+
+    Ok(())
+}
+
+/// Returns the object tags of the files of a crate version.
+///
+/// Storage-side lifecycle and replication policies can be based on these
+/// tags, e.g. to move the files of yanked versions to a cheaper storage class.
+fn object_tags(name: &str, version: &str, yanked: bool) -> TagSet {
+    let mut tags = TagSet::default();
+    tags.push("crate", name);
+    tags.push("version", version);
+    tags.push("yanked", if yanked { "true" } else { "false" });
+    tags
+}
+
+/// Uploads the object at `path` again through `upload_store`, with the new
+/// `tags`.
+///
+/// Does nothing if the object does not exist.
+async fn retag(
+    store: &dyn ObjectStore,
+    upload_store: &dyn ObjectStore,
+    path: &Path,
+    tags: TagSet,
+) -> Result<()> {
+    let bytes = match store.get(path).await {
+        Ok(result) => result.bytes().await?,
+        Err(object_store::Error::NotFound { .. }) => return Ok(()),
+        Err(error) => return Err(error),
+    };
+
+    upload_store
+        .put_opts(path, bytes.into(), tags.into())
+        .await?;
+
+    Ok(())
+}
+
 fn crate_file_path(name: &str, version: &str) -> Path {
     format!("{PREFIX_CRATES}/{name}/{name}-{version}.crate").into()
 }
 
+fn scheduled_crate_file_path(upload_id: &str) -> Path {
+    format!("{PREFIX_SCHEDULED_RELEASES}/{upload_id}.crate").into()
+}
+
 fn readme_path(name: &str, version: &str) -> Path {
     format!("{PREFIX_READMES}/{name}/{name}-{version}.html").into()
 }
 
+fn artifact_path(name: &str, version: &str, target: &str) -> Path {
+    format!("{PREFIX_ARTIFACTS}/{name}/{version}/{name}-{version}-{target}.tar.gz").into()
+}
+
+fn data_export_path(user_id: i32, export_id: &str) -> Path {
+    format!("{PREFIX_DATA_EXPORTS}/{user_id}/{export_id}.tar.gz").into()
+}
+
+/// Returns the path of the `.sig` file for the index file of the crate.
+fn index_signatures_path(name: &str) -> Path {
+    let path = crates_io_index::Repository::relative_index_file_for_url(name);
+    format!("{path}.sig").into()
+}
+
 fn apply_cdn_prefix(cdn_prefix: &Option<String>, path: &Path) -> String {
     match cdn_prefix {
         Some(cdn_prefix) if !cdn_prefix.starts_with("https://") => {
@@ -421,6 +927,29 @@ mod tests {
         for (name, version, expected) in readme_tests {
             assert_eq!(storage.readme_location(name, version), expected);
         }
+
+        assert_eq!(
+            storage.artifact_location("foo", "1.2.3+bar", "x86_64-unknown-linux-gnu"),
+            "https://static.crates.io/artifacts/foo/1.2.3%2Bbar/foo-1.2.3%2Bbar-x86_64-unknown-linux-gnu.tar.gz"
+        );
+    }
+
+    #[test]
+    fn download_url_template_location() {
+        let mut config = StorageConfig::in_memory();
+        config.cdn_prefix = Some("static.crates.io".to_string());
+        config.download_url_template =
+            Some("https://cdn.example.com/{crate}/{version}".parse().unwrap());
+
+        let storage = Storage::from_config(&config);
+        assert_eq!(
+            storage.crate_location("foo", "1.2.3+bar"),
+            "https://cdn.example.com/foo/1.2.3%2Bbar"
+        );
+        assert_eq!(
+            storage.readme_location("foo", "1.2.3"),
+            "https://static.crates.io/readmes/foo/foo-1.2.3.html"
+        );
     }
 
     #[test]
@@ -495,6 +1024,16 @@ mod tests {
         assert_eq!(stored_files(&storage.store).await, expected_files);
     }
 
+    #[tokio::test]
+    async fn file_exists() {
+        let storage = prepare().await;
+
+        assert!(storage.crate_file_exists("foo", "1.2.3").await.unwrap());
+        assert!(!storage.crate_file_exists("foo", "9.9.9").await.unwrap());
+        assert!(storage.readme_exists("bar", "2.0.0").await.unwrap());
+        assert!(!storage.readme_exists("bar", "9.9.9").await.unwrap());
+    }
+
     #[tokio::test]
     async fn delete_readme() {
         let storage = prepare().await;
@@ -533,6 +1072,57 @@ mod tests {
         assert_eq!(stored_files(&s.store).await, expected_files);
     }
 
+    #[tokio::test]
+    async fn upload_crate_file_stream() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let mut content: &[u8] = b"crate file content";
+        s.upload_crate_file_stream("foo", "1.2.3", &mut content)
+            .await
+            .unwrap();
+
+        let expected_files = vec!["crates/foo/foo-1.2.3.crate"];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+
+        let path = crate_file_path("foo", "1.2.3");
+        let result = s.store.get(&path).await.unwrap();
+        let bytes = result.bytes().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"crate file content"));
+    }
+
+    #[tokio::test]
+    async fn upload_and_delete_artifacts() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let bytes = Bytes::from_static(b"artifact");
+        for (version, target) in [
+            ("1.0.0", "x86_64-unknown-linux-gnu"),
+            ("1.0.0", "aarch64-apple-darwin"),
+            ("2.0.0", "x86_64-unknown-linux-gnu"),
+        ] {
+            s.upload_artifact("foo", version, target, bytes.clone())
+                .await
+                .unwrap();
+        }
+        s.upload_artifact("foo-bar", "1.0.0", "x86_64-unknown-linux-gnu", bytes)
+            .await
+            .unwrap();
+
+        s.delete_artifacts("foo", "1.0.0").await.unwrap();
+
+        let expected_files = vec![
+            "artifacts/foo-bar/1.0.0/foo-bar-1.0.0-x86_64-unknown-linux-gnu.tar.gz",
+            "artifacts/foo/2.0.0/foo-2.0.0-x86_64-unknown-linux-gnu.tar.gz",
+        ];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+
+        s.delete_all_artifacts("foo").await.unwrap();
+
+        let expected_files =
+            vec!["artifacts/foo-bar/1.0.0/foo-bar-1.0.0-x86_64-unknown-linux-gnu.tar.gz"];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+    }
+
     #[tokio::test]
     async fn upload_readme() {
         let s = Storage::from_config(&StorageConfig::in_memory());
@@ -554,6 +1144,37 @@ mod tests {
         assert_eq!(stored_files(&s.store).await, expected_files);
     }
 
+    #[tokio::test]
+    async fn update_tags() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let bytes = Bytes::from_static(b"crate file content");
+        s.upload_crate_file("foo", "1.2.3", bytes).await.unwrap();
+
+        // The README of the version does not exist, which is not an error
+        s.update_tags("foo", "1.2.3", true).await.unwrap();
+
+        let expected_files = vec!["crates/foo/foo-1.2.3.crate"];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+
+        let path = crate_file_path("foo", "1.2.3");
+        let result = s.store.get(&path).await.unwrap();
+        let bytes = result.bytes().await.unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"crate file content"));
+    }
+
+    #[test]
+    fn tags() {
+        let tags = object_tags("foo", "1.2.3+build", false);
+        assert_eq!(
+            tags.encoded(),
+            "crate=foo&version=1.2.3%2Bbuild&yanked=false"
+        );
+
+        let tags = object_tags("foo", "1.2.3", true);
+        assert_eq!(tags.encoded(), "crate=foo&version=1.2.3&yanked=true");
+    }
+
     #[tokio::test]
     async fn sync_index() {
         let s = Storage::from_config(&StorageConfig::in_memory());
@@ -571,6 +1192,36 @@ mod tests {
         assert!(stored_files(&s.store).await.is_empty());
     }
 
+    #[tokio::test]
+    async fn read_index() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        assert_none!(s.read_index("foo").await.unwrap());
+
+        let content = "foo".to_string();
+        s.sync_index("foo", Some(content)).await.unwrap();
+
+        let content = s.read_index("foo").await.unwrap();
+        assert_eq!(content, Some(Bytes::from_static(b"foo")));
+    }
+
+    #[tokio::test]
+    async fn sync_index_signatures() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let content = "{}".to_string();
+        s.sync_index_signatures("foo", Some(content)).await.unwrap();
+
+        let expected_files = vec!["index/3/f/foo.sig"];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+
+        s.sync_index_signatures("foo", None).await.unwrap();
+        assert!(stored_files(&s.store).await.is_empty());
+
+        // Removing missing signatures is not an error
+        s.sync_index_signatures("foo", None).await.unwrap();
+    }
+
     #[tokio::test]
     async fn upload_db_dump() {
         let s = Storage::from_config(&StorageConfig::in_memory());
@@ -584,4 +1235,28 @@ mod tests {
         let expected_files = vec![target];
         assert_eq!(stored_files(&s.store).await, expected_files);
     }
+
+    #[tokio::test]
+    async fn data_exports() {
+        let s = Storage::from_config(&StorageConfig::in_memory());
+
+        let bytes = Bytes::from_static(b"export");
+        s.upload_data_export(42, "abc", bytes.clone())
+            .await
+            .unwrap();
+
+        // The exports are not stored at a location that the CDN serves
+        let expected_files = vec!["private/data-exports/42/abc.tar.gz"];
+        assert_eq!(stored_files(&s.store).await, expected_files);
+        assert_some_eq!(s.read_data_export(42, "abc").await.unwrap(), bytes);
+
+        let an_hour_ago = Utc::now() - chrono::Duration::hours(1);
+        let deleted = s.delete_data_exports_before(an_hour_ago).await.unwrap();
+        assert_eq!(deleted, 0);
+
+        let in_an_hour = Utc::now() + chrono::Duration::hours(1);
+        let deleted = s.delete_data_exports_before(in_an_hour).await.unwrap();
+        assert_eq!(deleted, 1);
+        assert_none!(s.read_data_export(42, "abc").await.unwrap());
+    }
 }
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::str::FromStr;
+use url::Url;
+
+/// A template for the URLs that crate downloads are redirected to.
+///
+/// The template supports the same markers as the `dl` field of the registry
+/// configuration of cargo, except for `{sha256-checksum}`:
+///
+/// - `{crate}`: the name of the crate
+/// - `{version}`: the version of the crate
+/// - `{prefix}`: the directory prefix of the crate in the index, e.g. `se/rd`
+///   for `serde`
+/// - `{lowerprefix}`: the same prefix, but lowercased
+///
+/// For example: `https://cdn.example.com/crates/{crate}/{crate}-{version}.crate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadUrlTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Crate,
+    Version,
+    Prefix,
+    LowerPrefix,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum InvalidUrlTemplate {
+    #[error("unknown marker `{{{0}}}`")]
+    UnknownMarker(String),
+    #[error("unclosed marker at position {0}")]
+    UnclosedMarker(usize),
+    #[error("the template must contain the `{{crate}}` and `{{version}}` markers")]
+    MissingMarkers,
+    #[error("the template must produce an absolute `http` or `https` URL")]
+    InvalidUrl,
+}
+
+impl FromStr for DownloadUrlTemplate {
+    type Err = InvalidUrlTemplate;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+
+            let position = template.len() - rest.len() + start;
+            let end = rest[start..]
+                .find('}')
+                .ok_or(InvalidUrlTemplate::UnclosedMarker(position))?;
+
+            parts.push(match &rest[start + 1..start + end] {
+                "crate" => Part::Crate,
+                "version" => Part::Version,
+                "prefix" => Part::Prefix,
+                "lowerprefix" => Part::LowerPrefix,
+                marker => return Err(InvalidUrlTemplate::UnknownMarker(marker.to_string())),
+            });
+
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        if !parts.contains(&Part::Crate) || !parts.contains(&Part::Version) {
+            return Err(InvalidUrlTemplate::MissingMarkers);
+        }
+
+        let template = Self { parts };
+
+        let example = template.render("Example-Crate", "1.0.0-beta.1+build");
+        let is_valid = Url::parse(&example)
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host());
+        if !is_valid {
+            return Err(InvalidUrlTemplate::InvalidUrl);
+        }
+
+        Ok(template)
+    }
+}
+
+impl DownloadUrlTemplate {
+    /// Returns the download URL of the given crate version.
+    pub fn render(&self, name: &str, version: &str) -> String {
+        let mut url = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => url.push_str(literal),
+                Part::Crate => url.push_str(name),
+                Part::Version => url.push_str(&version.replace('+', "%2B")),
+                Part::Prefix => url.push_str(&prefix(name)),
+                Part::LowerPrefix => url.push_str(&prefix(&name.to_lowercase())),
+            }
+        }
+        url
+    }
+}
+
+impl fmt::Display for DownloadUrlTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => f.write_str(literal)?,
+                Part::Crate => f.write_str("{crate}")?,
+                Part::Version => f.write_str("{version}")?,
+                Part::Prefix => f.write_str("{prefix}")?,
+                Part::LowerPrefix => f.write_str("{lowerprefix}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the directory prefix of the crate in the index.
+///
+/// see <https://doc.rust-lang.org/cargo/reference/registry-index.html#index-files>
+fn prefix(name: &str) -> String {
+    // Downloads of missing crates are redirected too, so the name is not
+    // necessarily a valid crate name, and might contain multibyte characters.
+    let chars = name.chars().collect::<Vec<_>>();
+    match chars.len() {
+        0 => String::new(),
+        1 => "1".to_string(),
+        2 => "2".to_string(),
+        3 => format!("3/{}", chars[0]),
+        _ => format!(
+            "{}/{}",
+            chars[..2].iter().collect::<String>(),
+            chars[2..4].iter().collect::<String>()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, name: &str, version: &str) -> String {
+        let template = template.parse::<DownloadUrlTemplate>().unwrap();
+        template.render(name, version)
+    }
+
+    #[test]
+    fn rendering() {
+        let template = "https://cdn.example.com/crates/{crate}/{crate}-{version}.crate";
+        assert_eq!(
+            render(template, "foo", "1.0.0+bar"),
+            "https://cdn.example.com/crates/foo/foo-1.0.0%2Bbar.crate"
+        );
+
+        let template = "https://cdn.example.com/{prefix}/{lowerprefix}/{crate}/{version}";
+        assert_eq!(
+            render(template, "a", "1.0.0"),
+            "https://cdn.example.com/1/1/a/1.0.0"
+        );
+        assert_eq!(
+            render(template, "Foo", "1.0.0"),
+            "https://cdn.example.com/3/F/3/f/Foo/1.0.0"
+        );
+        assert_eq!(
+            render(template, "Serde", "1.0.0"),
+            "https://cdn.example.com/Se/rd/se/rd/Serde/1.0.0"
+        );
+
+        let template = "https://{crate}.example.com/{version}";
+        assert_eq!(
+            render(template, "foo", "1.0.0"),
+            "https://foo.example.com/1.0.0"
+        );
+    }
+
+    #[test]
+    fn display() {
+        let template = "https://cdn.example.com/{lowerprefix}/{crate}-{version}.crate";
+        let parsed = template.parse::<DownloadUrlTemplate>().unwrap();
+        assert_eq!(parsed.to_string(), template);
+    }
+
+    #[test]
+    fn validation() {
+        let parse = |template: &str| template.parse::<DownloadUrlTemplate>().unwrap_err();
+
+        assert_eq!(
+            parse("https://cdn.example.com/{crate}/{version}/{checksum}"),
+            InvalidUrlTemplate::UnknownMarker("checksum".into())
+        );
+        assert_eq!(
+            parse("https://cdn.example.com/{crate}/{version"),
+            InvalidUrlTemplate::UnclosedMarker(32)
+        );
+        assert_eq!(
+            parse("https://cdn.example.com/{crate}"),
+            InvalidUrlTemplate::MissingMarkers
+        );
+        assert_eq!(
+            parse("/crates/{crate}/{version}"),
+            InvalidUrlTemplate::InvalidUrl
+        );
+        assert_eq!(
+            parse("ftp://cdn.example.com/{crate}/{version}"),
+            InvalidUrlTemplate::InvalidUrl
+        );
+    }
+}
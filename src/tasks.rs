@@ -1,3 +1,4 @@
+use crate::middleware::deadline::{Deadline, DeadlineExceeded};
 use sentry::Hub;
 use std::convert::identity;
 use tokio::task::JoinError;
@@ -7,14 +8,22 @@ use tokio::task::JoinError;
 /// This is using [tokio::task::spawn_blocking] internally, but automatically
 /// runs the callback function in the context of the current Sentry [Hub].
 ///
+/// If the current request has already exceeded its [Deadline], the closure
+/// is not run at all, since nobody would be waiting for its result anymore.
+///
 /// The function also returns a flattened [Result], which requires the error
-/// variant of the [Result] to implement [From\<JoinError>].
+/// variant of the [Result] to implement [From\<JoinError>] and
+/// [From\<DeadlineExceeded>].
 pub async fn spawn_blocking<F, R, E>(f: F) -> Result<R, E>
 where
     F: FnOnce() -> Result<R, E> + Send + 'static,
     R: Send + 'static,
-    E: Send + From<JoinError> + 'static,
+    E: Send + From<JoinError> + From<DeadlineExceeded> + 'static,
 {
+    if let Some(deadline) = Deadline::current() {
+        deadline.check()?;
+    }
+
     let current_span = tracing::Span::current();
     let hub = Hub::current();
     tokio::task::spawn_blocking(move || current_span.in_scope(|| Hub::run(hub, f)))
@@ -45,4 +54,18 @@ mod tests {
             .await
             .unwrap()
     }
+
+    /// Test that [spawn_blocking] does not run the closure once the deadline
+    /// of the current request has passed.
+    #[tokio::test]
+    async fn test_spawn_blocking_deadline_exceeded() {
+        let deadline = Deadline::after(std::time::Duration::ZERO);
+        let result = deadline
+            .scope(spawn_blocking::<_, (), anyhow::Error>(|| {
+                panic!("closure should not run")
+            }))
+            .await;
+
+        assert_eq!(result.unwrap_err().to_string(), "request deadline exceeded");
+    }
 }
@@ -63,3 +63,18 @@ async fn expired_account_lock() {
 
     user.get::<serde_json::Value>(URL).await.good();
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn account_lock_expires_over_time() {
+    let until = Utc::now().naive_utc() + Duration::days(1);
+
+    let (app, _anon, user) = TestApp::init().with_user();
+    lock_account(&app, user.as_model().id, Some(until));
+
+    let response = user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    app.advance_time(Duration::days(2));
+
+    user.get::<serde_json::Value>(URL).await.good();
+}
@@ -11,11 +11,9 @@ use crate::util::{RequestHelper, TestApp};
 use crates_io::{
     models::{Crate, CrateOwner, NewCategory, NewTeam, NewUser, OwnerKind, Team, User},
     schema::crate_owners,
-    views::{
-        EncodableCategory, EncodableCategoryWithSubcategories, EncodableCrate, EncodableKeyword,
-        EncodableOwner, EncodableVersion, GoodCrate,
-    },
+    views::{EncodableCategory, EncodableCategoryWithSubcategories, EncodableOwner, GoodCrate},
 };
+pub use crates_io_client::{CrateList, CrateResponse, OwnersResponse, VersionResponse};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use diesel::prelude::*;
@@ -25,6 +23,7 @@ mod authentication;
 mod blocked_routes;
 mod builders;
 mod categories;
+mod challenge;
 mod dump_db;
 mod github_secret_scanning;
 mod krate;
@@ -41,43 +40,17 @@ mod server_binary;
 mod team;
 mod token;
 mod unhealthy_database;
+mod upstream;
 mod user;
 mod util;
 mod version;
 mod worker;
 
-#[derive(Deserialize)]
-pub struct CrateList {
-    crates: Vec<EncodableCrate>,
-    meta: CrateMeta,
-}
-#[derive(Deserialize)]
-struct CrateMeta {
-    total: i32,
-    next_page: Option<String>,
-    prev_page: Option<String>,
-}
-#[derive(Deserialize)]
-#[allow(dead_code)]
-pub struct CrateResponse {
-    #[serde(rename = "crate")]
-    krate: EncodableCrate,
-    versions: Option<Vec<EncodableVersion>>,
-    keywords: Option<Vec<EncodableKeyword>>,
-}
-#[derive(Deserialize)]
-pub struct VersionResponse {
-    version: EncodableVersion,
-}
 #[derive(Deserialize)]
 pub struct OwnerTeamsResponse {
     teams: Vec<EncodableOwner>,
 }
 #[derive(Deserialize)]
-pub struct OwnersResponse {
-    users: Vec<EncodableOwner>,
-}
-#[derive(Deserialize)]
 pub struct CategoryResponse {
     category: EncodableCategoryWithSubcategories,
 }
@@ -4,6 +4,8 @@ use crate::TestApp;
 use crate::util::encode_session_header;
 use http::{header, Method, StatusCode};
 use insta::assert_snapshot;
+use secrecy::ExposeSecret;
+use std::time::Duration;
 
 static URL: &str = "/api/v1/me/updates";
 
@@ -43,3 +45,71 @@ async fn cookie_auth_cannot_find_user() {
     let error = anon.run::<()>(request).await;
     assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn repeated_auth_failures_are_rate_limited() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.auth_failure_limiter.free_attempts = 2;
+            config.auth_failure_limiter.max_delay = Duration::from_secs(60);
+            config.auth_failure_limiter.base_delay = Duration::from_secs(60);
+        })
+        .empty();
+
+    let request = || {
+        let mut request = anon.request_builder(Method::GET, URL);
+        request.header(header::AUTHORIZATION, "cio1tkfake-token");
+        request
+    };
+
+    for _ in 0..3 {
+        let response: Response<()> = anon.run(request()).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    let response: Response<()> = anon.run(request()).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "60");
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"Too many failed authentication attempts. Please try again after 60 seconds."}]}"###);
+
+    // Unauthenticated requests from the same client are limited as well
+    let response: Response<()> = anon.get(URL).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn token_auth_with_bearer_scheme() {
+    // `/me/updates` only supports cookie authentication
+    const URL: &str = "/api/v1/me/usage";
+
+    let (_, anon, _, token) = TestApp::init().with_token();
+    let token = token.plaintext().expose_secret();
+
+    let mut request = anon.request_builder(Method::GET, URL);
+    request.header(header::AUTHORIZATION, &format!("Bearer {token}"));
+    let response: Response<()> = anon.run(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut request = anon.request_builder(Method::GET, URL);
+    request.header(header::AUTHORIZATION, token);
+    let response: Response<()> = anon.run(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn token_auth_with_unsupported_scheme() {
+    let (_, anon, _, token) = TestApp::init().with_token();
+    let token = token.plaintext().expose_secret();
+
+    let mut request = anon.request_builder(Method::GET, URL);
+    request.header(header::AUTHORIZATION, &format!("Token {token}"));
+    let response: Response<()> = anon.run(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"unsupported authorization scheme `Token`, expected `Bearer <token>`"}]}"###);
+
+    let mut request = anon.request_builder(Method::GET, URL);
+    request.header(header::AUTHORIZATION, "Bearer");
+    let response: Response<()> = anon.run(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the `Authorization` header is missing the token after `Bearer`"}]}"###);
+}
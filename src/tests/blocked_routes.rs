@@ -1,14 +1,11 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
+use crates_io::models::BlockedRoute;
 use http::StatusCode;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn test_non_blocked_download_route() {
-    let (app, anon, user) = TestApp::init()
-        .with_config(|config| {
-            config.blocked_routes.clear();
-        })
-        .with_user();
+    let (app, anon, user) = TestApp::init().with_user();
 
     app.db(|conn| {
         CrateBuilder::new("foo", user.as_model().id)
@@ -25,16 +22,12 @@ async fn test_non_blocked_download_route() {
 
 #[tokio::test(flavor = "multi_thread")]
 async fn test_blocked_download_route() {
-    let (app, anon, user) = TestApp::init()
-        .with_config(|config| {
-            config.blocked_routes.clear();
-            config
-                .blocked_routes
-                .insert("/api/v1/crates/:crate_id/:version/download".into());
-        })
-        .with_user();
+    let (app, anon, user) = TestApp::init().with_user();
 
     app.db(|conn| {
+        let route = "/api/v1/crates/:crate_id/:version/download";
+        BlockedRoute::insert(conn, route, user.as_model().id).unwrap();
+
         CrateBuilder::new("foo", user.as_model().id)
             .version(VersionBuilder::new("1.0.0"))
             .expect_build(conn);
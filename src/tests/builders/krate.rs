@@ -1,4 +1,5 @@
 use crates_io::{
+    audit::AuditLog,
     models::{Category, Crate, Keyword, NewCrate},
     schema::{crates, version_downloads},
     util::errors::AppResult,
@@ -120,7 +121,9 @@ impl<'a> CrateBuilder<'a> {
     pub fn build(mut self, connection: &mut PgConnection) -> AppResult<Crate> {
         use diesel::{insert_into, select, update};
 
-        let mut krate = self.krate.create(connection, self.owner_id)?;
+        let mut krate = self
+            .krate
+            .create(connection, self.owner_id, &AuditLog::default())?;
 
         // Since we are using `NewCrate`, we can't set all the
         // crate properties in a single DB call.
@@ -1,5 +1,6 @@
 use bytes::{BufMut, Bytes, BytesMut};
 use cargo_manifest::{DependencyDetail, DepsSet, MaybeInherited};
+use chrono::NaiveDateTime;
 use crates_io::models::DependencyKind;
 use crates_io::views::krate_publish as u;
 use std::collections::BTreeMap;
@@ -23,6 +24,7 @@ pub struct PublishBuilder {
     license_file: Option<String>,
     manifest: Manifest,
     readme: Option<String>,
+    release_at: Option<NaiveDateTime>,
     version: semver::Version,
     features: BTreeMap<String, Vec<String>>,
 }
@@ -34,6 +36,9 @@ enum Manifest {
 }
 
 impl PublishBuilder {
+    /// The boundary of the bodies created by [`Self::create_multipart_publish_body`].
+    pub const MULTIPART_BOUNDARY: &'static str = "crates-io-publish-boundary";
+
     /// Create a request to publish a crate with the given name and version, and no files
     /// in its tarball.
     pub fn new(krate_name: &str, version: &str) -> Self {
@@ -49,6 +54,7 @@ impl PublishBuilder {
             license_file: None,
             manifest: Manifest::Generated,
             readme: None,
+            release_at: None,
             version: semver::Version::parse(version).unwrap(),
             features: BTreeMap::new(),
         }
@@ -79,6 +85,12 @@ impl PublishBuilder {
         self
     }
 
+    /// Schedule the release of this version
+    pub fn release_at(mut self, release_at: NaiveDateTime) -> Self {
+        self.release_at = Some(release_at);
+        self
+    }
+
     /// Set the documentation URL of this crate
     pub fn documentation(mut self, documentation: &str) -> Self {
         self.doc_url = Some(documentation.to_string());
@@ -144,6 +156,7 @@ impl PublishBuilder {
             vers: self.version.to_string(),
             readme: self.readme,
             readme_file: None,
+            release_at: self.release_at,
         };
 
         let mut tarball_builder = TarballBuilder::new();
@@ -223,6 +236,27 @@ impl PublishBuilder {
 
         body.freeze()
     }
+
+    /// Create a `multipart/form-data` request body with separate `metadata`
+    /// and `tarball` parts, using [`Self::MULTIPART_BOUNDARY`] as boundary.
+    pub fn create_multipart_publish_body(json: &str, tarball: &[u8]) -> Bytes {
+        let boundary = Self::MULTIPART_BOUNDARY;
+
+        let mut body = BytesMut::new();
+        body.put_slice(format!("--{boundary}\r\n").as_bytes());
+        body.put_slice(b"Content-Disposition: form-data; name=\"metadata\"\r\n");
+        body.put_slice(b"Content-Type: application/json\r\n\r\n");
+        body.put_slice(json.as_bytes());
+        body.put_slice(format!("\r\n--{boundary}\r\n").as_bytes());
+        body.put_slice(
+            b"Content-Disposition: form-data; name=\"tarball\"; filename=\"crate.tar.gz\"\r\n",
+        );
+        body.put_slice(b"Content-Type: application/gzip\r\n\r\n");
+        body.put_slice(tarball);
+        body.put_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+        body.freeze()
+    }
 }
 
 impl From<PublishBuilder> for Bytes {
@@ -11,7 +11,7 @@ use diesel::prelude::*;
 /// A builder to create version records for the purpose of inserting directly into the database.
 pub struct VersionBuilder<'a> {
     created_at: Option<NaiveDateTime>,
-    dependencies: Vec<(i32, Option<&'static str>)>,
+    dependencies: Vec<(i32, Option<&'static str>, &'static str)>,
     features: BTreeMap<String, Vec<String>>,
     license: Option<&'a str>,
     num: semver::Version,
@@ -69,7 +69,13 @@ impl<'a> VersionBuilder<'a> {
 
     /// Adds a dependency to this version.
     pub fn dependency(mut self, dependency: &Crate, target: Option<&'static str>) -> Self {
-        self.dependencies.push((dependency.id, target));
+        self.dependencies.push((dependency.id, target, ">= 0"));
+        self
+    }
+
+    /// Adds a dependency with the version requirement `req` to this version.
+    pub fn dependency_with_req(mut self, dependency: &Crate, req: &'static str) -> Self {
+        self.dependencies.push((dependency.id, None, req));
         self
     }
 
@@ -128,10 +134,10 @@ impl<'a> VersionBuilder<'a> {
         let new_deps = self
             .dependencies
             .into_iter()
-            .map(|(crate_id, target)| {
+            .map(|(crate_id, target, req)| {
                 (
                     dependencies::version_id.eq(vers.id),
-                    dependencies::req.eq(">= 0"),
+                    dependencies::req.eq(req),
                     dependencies::crate_id.eq(crate_id),
                     dependencies::target.eq(target),
                     dependencies::optional.eq(false),
@@ -0,0 +1,112 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockCookieUser, MockRequestExt, RequestHelper, Response};
+use crate::TestApp;
+use crates_io::challenge::{record_sign_in, MockChallengeProvider, CHALLENGE_RESPONSE_HEADER};
+use crates_io::config::ChallengeConfig;
+use http::{Method, StatusCode};
+use insta::assert_json_snapshot;
+
+/// Sets up a crate with a pending ownership invitation for a user who has
+/// only ever signed in from a different network than the test requests.
+async fn prepare() -> (TestApp, MockCookieUser, i32) {
+    let (app, _, owner, owner_token) = TestApp::init()
+        .with_config(|config| config.challenge = Some(ChallengeConfig::Mock))
+        .with_token();
+
+    let owner = owner.as_model();
+    let invited_user = app.db_new_user("user_bar");
+    let krate = app.db(|conn| CrateBuilder::new("foo", owner.id).expect_build(conn));
+
+    app.db(|conn| {
+        let ip = "10.0.0.1".parse().unwrap();
+        record_sign_in(conn, invited_user.as_model().id, ip).unwrap();
+    });
+
+    owner_token.add_named_owner("foo", "user_bar").await.good();
+
+    (app, invited_user, krate.id)
+}
+
+async fn accept_invitation(
+    user: &MockCookieUser,
+    krate_id: i32,
+    challenge_response: Option<&str>,
+) -> Response<()> {
+    let body = json!({
+        "crate_owner_invite": {
+            "crate_id": krate_id,
+            "accepted": true
+        }
+    });
+
+    let url = format!("/api/v1/me/crate_owner_invitations/{krate_id}");
+    let mut request = user.request_builder(Method::PUT, &url);
+    *request.body_mut() = body.to_string().into();
+    if let Some(challenge_response) = challenge_response {
+        request.header(CHALLENGE_RESPONSE_HEADER, challenge_response);
+    }
+
+    user.run(request).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn accept_invitation_from_unknown_network_requires_challenge() {
+    let (_app, user, krate_id) = prepare().await;
+
+    let response = accept_invitation(&user, krate_id, None).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_json_snapshot!(response.json(), @r###"
+    {
+      "errors": [
+        {
+          "detail": "this action requires solving a challenge"
+        }
+      ]
+    }
+    "###);
+
+    let response = accept_invitation(&user, krate_id, Some("invalid")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response =
+        accept_invitation(&user, krate_id, Some(MockChallengeProvider::VALID_RESPONSE)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn accept_invitation_from_known_network_requires_no_challenge() {
+    let (app, user, krate_id) = prepare().await;
+
+    app.db(|conn| {
+        let ip = "127.0.0.1".parse().unwrap();
+        record_sign_in(conn, user.as_model().id, ip).unwrap();
+    });
+
+    let response = accept_invitation(&user, krate_id, None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn challenge_without_pending_sign_in() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| config.challenge = Some(ChallengeConfig::Mock))
+        .empty();
+
+    let mut request = anon.request_builder(Method::PUT, "/api/private/session/challenge");
+    request.header(
+        CHALLENGE_RESPONSE_HEADER,
+        MockChallengeProvider::VALID_RESPONSE,
+    );
+
+    let response: Response<()> = anon.run(request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json(), @r###"
+    {
+      "errors": [
+        {
+          "detail": "there is no sign-in waiting for a challenge"
+        }
+      ]
+    }
+    "###);
+}
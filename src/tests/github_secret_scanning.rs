@@ -1,6 +1,7 @@
 use crate::util::MockRequestExt;
 use crate::{RequestHelper, TestApp};
 use crates_io::util::token::HashedToken;
+use crates_io::util::tracing::capture_for_test;
 use crates_io::{models::ApiToken, schema::api_tokens};
 use diesel::prelude::*;
 use googletest::prelude::*;
@@ -42,6 +43,7 @@ async fn github_secret_alert_revokes_token() {
             .unwrap();
     });
 
+    let logs = capture_for_test();
     let mut request = anon.post_request(URL);
     *request.body_mut() = GITHUB_ALERT.into();
     request.header("GITHUB-PUBLIC-KEY-IDENTIFIER", GITHUB_PUBLIC_KEY_IDENTIFIER);
@@ -50,6 +52,9 @@ async fn github_secret_alert_revokes_token() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_json_snapshot!(response.json());
 
+    // Ensure that the revocation was logged
+    logs.assert_contains("Active API token received and revoked (true positive)");
+
     // Ensure that the token was revoked
     app.db(|conn| {
         let tokens: Vec<ApiToken> = assert_ok!(ApiToken::belonging_to(user.as_model())
@@ -135,3 +135,83 @@ async fn test_api_token_auth() {
     let json = token.search("following=1").await;
     assert_that!(json.crates, len(eq(1)));
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_following() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        let user_id = user.as_model().id;
+        CrateBuilder::new("foo", user_id).expect_build(conn);
+        CrateBuilder::new("bar", user_id).expect_build(conn);
+        CrateBuilder::new("baz", user_id).expect_build(conn);
+    });
+
+    follow("baz", &user).await;
+
+    let body = json!({ "follow": ["foo", "bar"], "unfollow": ["baz"] }).to_string();
+    let response = user.put::<()>("/api/v1/me/following", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "following": ["bar", "foo"] }));
+
+    assert_is_following("foo", true, &user).await;
+    assert_is_following("bar", true, &user).await;
+    assert_is_following("baz", false, &user).await;
+
+    // Following and unfollowing is idempotent
+    let body = json!({ "follow": ["foo"], "unfollow": ["baz"] }).to_string();
+    let response = user.put::<()>("/api/v1/me/following", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "following": ["bar", "foo"] }));
+
+    let body = json!({ "follow": ["foo"] }).to_string();
+    let response = anon.put::<()>("/api/v1/me/following", body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_following_is_atomic() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        let user_id = user.as_model().id;
+        CrateBuilder::new("foo", user_id).expect_build(conn);
+        CrateBuilder::new("bar", user_id).expect_build(conn);
+    });
+
+    follow("bar", &user).await;
+
+    let body = json!({ "follow": ["foo", "unknown"], "unfollow": ["bar"] }).to_string();
+    let response = user.put::<()>("/api/v1/me/following", body).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `unknown` does not exist"}]}"###);
+
+    assert_is_following("foo", false, &user).await;
+    assert_is_following("bar", true, &user).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_following_invalid_requests() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "follow": ["foo"], "unfollow": ["foo"] }).to_string();
+    let response = user.put::<()>("/api/v1/me/following", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` can't be followed and unfollowed at the same time"}]}"###);
+
+    let names = (0..101).map(|i| format!("crate-{i}")).collect::<Vec<_>>();
+    let body = json!({ "follow": names }).to_string();
+    let response = user.put::<()>("/api/v1/me/following", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"at most 100 crates can be followed or unfollowed at once"}]}"###);
+
+    let body = json!({ "follow": "foo" }).to_string();
+    let response = user.put::<()>("/api/v1/me/following", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid json request"}]}"###);
+}
@@ -1,5 +1,6 @@
 use crate::builders::{CrateBuilder, PublishBuilder};
 use crate::util::{RequestHelper, TestApp};
+use crates_io::models::token::{CrateScope, EndpointScope};
 use crates_io::schema::api_tokens;
 use diesel::{ExpressionMethods, RunQueryDsl};
 use googletest::prelude::*;
@@ -50,3 +51,44 @@ async fn new_krate_wrong_user() {
 
     assert_that!(app.stored_files().await, empty());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn version_scoped_token() {
+    let (app, _, user) = TestApp::full().with_user();
+    let owner_token = user.db_new_token("owner");
+    owner_token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    owner_token
+        .publish_crate(PublishBuilder::new("foo", "2.0.0"))
+        .await
+        .good();
+
+    let crate_scopes = Some(vec![CrateScope::try_from("foo@~1.0").unwrap()]);
+    let endpoint_scopes = Some(vec![EndpointScope::PublishUpdate]);
+    let token = user.db_new_scoped_token("backports", crate_scopes, endpoint_scopes, None);
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.1"))
+        .await
+        .good();
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "2.0.1"))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this token does not have the required permissions to publish version 2.0.1 of this crate"}]}"###);
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let versions = app
+        .crates_from_index_head("foo")
+        .into_iter()
+        .map(|krate| krate.vers)
+        .collect::<Vec<_>>();
+    assert_eq!(versions, ["1.0.0", "2.0.0", "1.0.1"]);
+}
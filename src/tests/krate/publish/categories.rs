@@ -1,6 +1,7 @@
 use crate::builders::PublishBuilder;
 use crate::new_category;
 use crate::util::{RequestHelper, TestApp};
+use crates_io::models::NewCategorySynonym;
 use googletest::prelude::*;
 use http::StatusCode;
 use insta::assert_json_snapshot;
@@ -25,16 +26,60 @@ async fn good_categories() {
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn ignored_categories() {
-    let (_, _, _, token) = TestApp::full().with_token();
+async fn unknown_categories() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        new_category("Parsing", "parsing", "Parsing crates")
+            .create_or_update(conn)
+            .unwrap();
+    });
 
-    let crate_to_publish = PublishBuilder::new("foo_ignored_cat", "1.0.0").category("bar");
+    let crate_to_publish = PublishBuilder::new("foo_unknown_cat", "1.0.0")
+        .category("parsng")
+        .category("bar");
     let response = token.publish_crate(crate_to_publish).await;
-    assert_eq!(response.status(), StatusCode::OK);
-    assert_json_snapshot!(response.json(), {
-        ".crate.created_at" => "[datetime]",
-        ".crate.updated_at" => "[datetime]",
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json());
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn subcategories_and_synonyms() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        new_category("Web programming", "web-programming", "Web crates")
+            .create_or_update(conn)
+            .unwrap();
+        let http_client = new_category(
+            "Web programming::HTTP client",
+            "web-programming::http-client",
+            "HTTP client crates",
+        )
+        .create_or_update(conn)
+        .unwrap();
+
+        NewCategorySynonym {
+            synonym: "http-client",
+            category_id: http_client.id,
+            created_by: user.as_model().id,
+        }
+        .insert(conn)
+        .unwrap();
     });
+
+    let crate_to_publish = PublishBuilder::new("foo_subcat", "1.0.0")
+        .category("web-programming::http-client")
+        .category("http-client");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let crate_to_publish =
+        PublishBuilder::new("foo_subcat", "1.1.0").category("web-programming::http-klient");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json());
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -11,9 +11,15 @@ mod inheritance;
 mod keywords;
 mod manifest;
 mod max_size;
+mod multipart;
+mod nonce;
+mod notifications;
 mod rate_limit;
 mod readme;
+mod request_id;
+mod scheduled_release;
 mod similar_names;
 mod tarball;
 mod timestamps;
+mod token_anomalies;
 mod validation;
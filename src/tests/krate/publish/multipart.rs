@@ -0,0 +1,100 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockRequestExt, MockTokenUser, RequestHelper, Response, TestApp};
+use googletest::prelude::*;
+use hex::ToHex;
+use http::{header, Method, StatusCode};
+use insta::assert_json_snapshot;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+async fn publish_multipart(token: &MockTokenUser, body: bytes::Bytes) -> Response<Value> {
+    let content_type = format!(
+        "multipart/form-data; boundary={}",
+        PublishBuilder::MULTIPART_BOUNDARY
+    );
+
+    let mut request = token.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = body;
+    request.header(header::CONTENT_TYPE, &content_type);
+
+    let response = token.run(request).await;
+    token.app().run_pending_background_jobs().await;
+    response
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_krate_multipart() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let (json, tarball) = PublishBuilder::new("foo", "1.0.0").build();
+    let body = PublishBuilder::create_multipart_publish_body(&json, &tarball);
+
+    let response = publish_multipart(&token, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".crate.created_at" => "[datetime]",
+        ".crate.updated_at" => "[datetime]",
+    });
+
+    let expected_files = vec!["crates/foo/foo-1.0.0.crate", "index/3/f/foo"];
+    assert_that!(app.stored_files().await, eq(expected_files));
+
+    let json = token.get::<()>("/api/v1/crates/foo/1.0.0").await.json();
+    let expected_checksum: String = Sha256::digest(&tarball).encode_hex();
+    assert_eq!(json["version"]["checksum"], expected_checksum);
+    assert_eq!(json["version"]["crate_size"], tarball.len());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_krate_multipart_too_big() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.max_upload_size = 100)
+        .with_token();
+
+    let (json, tarball) = PublishBuilder::new("foo", "1.0.0")
+        .add_file("foo-1.0.0/big", "a".repeat(1000))
+        .build();
+    let body = PublishBuilder::create_multipart_publish_body(&json, &tarball);
+
+    let response = publish_multipart(&token, body).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_json_snapshot!(response.json());
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_krate_multipart_missing_tarball() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let (json, _tarball) = PublishBuilder::new("foo", "1.0.0").build();
+    let boundary = PublishBuilder::MULTIPART_BOUNDARY;
+    let body = format!(
+        "--{boundary}\r\n\
+        Content-Disposition: form-data; name=\"metadata\"\r\n\r\n\
+        {json}\r\n\
+        --{boundary}--\r\n"
+    );
+
+    let response = publish_multipart(&token, body.into()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json());
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_krate_multipart_unexpected_field() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let boundary = PublishBuilder::MULTIPART_BOUNDARY;
+    let body = format!(
+        "--{boundary}\r\n\
+        Content-Disposition: form-data; name=\"readme\"\r\n\r\n\
+        # foo\r\n\
+        --{boundary}--\r\n"
+    );
+
+    let response = publish_multipart(&token, body.into()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json());
+    assert_that!(app.stored_files().await, empty());
+}
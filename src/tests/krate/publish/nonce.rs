@@ -0,0 +1,98 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockRequestExt, MockTokenUser, RequestHelper, Response, TestApp};
+use http::{header, Method, StatusCode};
+use insta::assert_snapshot;
+use serde_json::Value;
+
+async fn request_nonce(token: &MockTokenUser, krate: &str, version: &str) -> String {
+    let mut request = token.post_request("/api/v1/publish_nonce");
+    *request.body_mut() = json!({ "crate": krate, "version": version })
+        .to_string()
+        .into();
+    request.header(header::CONTENT_TYPE, "application/json");
+
+    let response = token.run::<Value>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json()["nonce"].as_str().unwrap().to_string()
+}
+
+async fn publish_with_nonce(
+    token: &MockTokenUser,
+    publish_builder: PublishBuilder,
+    nonce: &str,
+) -> Response<()> {
+    let mut request = token.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = publish_builder.body();
+    request.header("publish-nonce", nonce);
+    token.run(request).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_requires_nonce_when_configured() {
+    let (_app, _, _, token) = TestApp::full()
+        .with_config(|config| config.publish_nonces.required = true)
+        .with_token();
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"publishing requires a nonce from `POST /api/v1/publish_nonce` in the `Publish-Nonce` header"}]}"###);
+
+    let nonce = request_nonce(&token, "foo", "1.0.0").await;
+    let response = publish_with_nonce(&token, PublishBuilder::new("foo", "1.0.0"), &nonce).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Replaying the request with the consumed nonce fails
+    let nonce = request_nonce(&token, "foo", "1.1.0").await;
+    let response = publish_with_nonce(&token, PublishBuilder::new("foo", "1.1.0"), &nonce).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = publish_with_nonce(&token, PublishBuilder::new("foo", "1.1.0"), &nonce).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the publish nonce is invalid, expired, already used, or was requested for a different crate version"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn nonce_is_bound_to_crate_version() {
+    let (_app, _, _, token) = TestApp::full().with_token();
+
+    let nonce = request_nonce(&token, "foo", "1.0.0").await;
+
+    let response = publish_with_nonce(&token, PublishBuilder::new("foo", "2.0.0"), &nonce).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = publish_with_nonce(&token, PublishBuilder::new("bar", "1.0.0"), &nonce).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // The nonce is not consumed by the failed publishes
+    let response = publish_with_nonce(&token, PublishBuilder::new("foo", "1.0.0"), &nonce).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn nonces_are_optional_by_default() {
+    let (_app, _, _, token) = TestApp::full().with_token();
+
+    let response = token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = publish_with_nonce(&token, PublishBuilder::new("foo", "1.1.0"), "invalid").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn nonce_request_requires_auth() {
+    let (_app, anon) = TestApp::full().empty();
+
+    let mut request = anon.post_request("/api/v1/publish_nonce");
+    *request.body_mut() = json!({ "crate": "foo", "version": "1.0.0" })
+        .to_string()
+        .into();
+    request.header(header::CONTENT_TYPE, "application/json");
+
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,113 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockRequestExt, MockTokenUser, RequestHelper, TestApp};
+use crates_io::models::OwnerKind;
+use crates_io::schema::{crate_owners, crates, users};
+use diesel::prelude::*;
+use http::{Method, StatusCode};
+use insta::assert_snapshot;
+use regex::Regex;
+
+async fn publish_from_ip(token: &MockTokenUser, crate_to_publish: PublishBuilder, ip: &str) {
+    let mut request = token.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = crate_to_publish.body();
+    request.header("X-Forwarded-For", ip);
+
+    let response = token.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    token.app().run_pending_background_jobs().await;
+}
+
+fn sent_emails(app: &TestApp) -> Vec<String> {
+    let email_header_regex = Regex::new(r"(Message-ID|Date): [^\r\n]+\r\n").unwrap();
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    emails
+        .iter()
+        .map(|(_, email)| email_header_regex.replace_all(email, "").into_owned())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_notifies_other_owners() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    publish_from_ip(&token, PublishBuilder::new("foo", "1.0.0"), "10.0.0.1").await;
+    assert_eq!(sent_emails(&app).len(), 0);
+
+    let other_owner = app.db_new_user("bar");
+    app.db(|conn| {
+        let crate_id: i32 = crates::table
+            .filter(crates::name.eq("foo"))
+            .select(crates::id)
+            .first(conn)
+            .unwrap();
+
+        diesel::insert_into(crate_owners::table)
+            .values((
+                crate_owners::crate_id.eq(crate_id),
+                crate_owners::owner_id.eq(other_owner.as_model().id),
+                crate_owners::owner_kind.eq(OwnerKind::User),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    publish_from_ip(&token, PublishBuilder::new("foo", "1.1.0"), "10.0.0.1").await;
+    publish_from_ip(&token, PublishBuilder::new("foo", "1.2.0"), "10.0.0.2").await;
+
+    let emails = sent_emails(&app);
+    assert_eq!(emails.len(), 2);
+    assert_snapshot!(emails[0]);
+    assert_snapshot!(emails[1]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_does_not_notify_the_publisher() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await
+        .good();
+
+    assert_eq!(sent_emails(&app).len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_notification_uses_locale_of_recipient() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    publish_from_ip(&token, PublishBuilder::new("foo", "1.0.0"), "10.0.0.1").await;
+
+    let other_owner = app.db_new_user("bar");
+    app.db(|conn| {
+        let crate_id: i32 = crates::table
+            .filter(crates::name.eq("foo"))
+            .select(crates::id)
+            .first(conn)
+            .unwrap();
+
+        diesel::insert_into(crate_owners::table)
+            .values((
+                crate_owners::crate_id.eq(crate_id),
+                crate_owners::owner_id.eq(other_owner.as_model().id),
+                crate_owners::owner_kind.eq(OwnerKind::User),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        diesel::update(users::table.find(other_owner.as_model().id))
+            .set(users::locale.eq("de"))
+            .execute(conn)
+            .unwrap();
+    });
+
+    publish_from_ip(&token, PublishBuilder::new("foo", "1.1.0"), "10.0.0.2").await;
+
+    let emails = sent_emails(&app);
+    assert_eq!(emails.len(), 1);
+    assert_snapshot!(emails[0]);
+}
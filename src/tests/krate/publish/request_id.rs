@@ -0,0 +1,50 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockRequestExt, RequestHelper, TestApp};
+use crates_io_worker::schema::background_jobs;
+use diesel::prelude::*;
+use http::{Method, StatusCode};
+
+fn enqueued_jobs(app: &TestApp) -> Vec<(String, Option<String>)> {
+    app.db(|conn| {
+        background_jobs::table
+            .select((background_jobs::job_type, background_jobs::request_id))
+            .order(background_jobs::id)
+            .load(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn jobs_record_the_request_id() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let mut request = token.request_builder(Method::PUT, "/api/v1/crates/new");
+    *request.body_mut() = PublishBuilder::new("foo", "1.0.0").body();
+    request.header("x-request-id", "ab3d5d7f-1c4d-4d8b-a6e5-6e2c2ad3c5d1");
+
+    let response = token.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let jobs = enqueued_jobs(&app);
+    assert!(!jobs.is_empty());
+    for (job_type, request_id) in jobs {
+        let request_id = request_id.as_deref();
+        let expected = Some("ab3d5d7f-1c4d-4d8b-a6e5-6e2c2ad3c5d1");
+        assert_eq!(request_id, expected, "unexpected request id for {job_type}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn jobs_without_request_id() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    let response = token
+        .put::<()>("/api/v1/crates/new", crate_to_publish.body())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let jobs = enqueued_jobs(&app);
+    assert!(!jobs.is_empty());
+    assert!(jobs.iter().all(|(_, request_id)| request_id.is_none()));
+}
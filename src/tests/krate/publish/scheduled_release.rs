@@ -0,0 +1,146 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use chrono::{Duration, NaiveDateTime, Utc};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+fn in_one_day() -> NaiveDateTime {
+    (Utc::now() + Duration::days(1)).naive_utc()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn scheduled_version_is_hidden_until_released() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0").release_at(in_one_day());
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    let warnings = json["warnings"]["other"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+
+    // Only the released version is visible
+    let json = token.get::<()>("/api/v1/crates/foo/versions").await.json();
+    let versions = json["versions"].as_array().unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0]["num"], "1.0.0");
+
+    let crates = app.crates_from_index_head("foo");
+    assert_eq!(crates.len(), 1);
+
+    let stored_files = app.stored_files().await;
+    assert!(!stored_files.contains(&"crates/foo/foo-1.1.0.crate".to_string()));
+    assert!(stored_files
+        .iter()
+        .any(|path| path.starts_with("scheduled-releases/")));
+
+    let json = token
+        .get::<()>("/api/v1/crates/foo/scheduled_releases")
+        .await
+        .json();
+    let scheduled_releases = json["scheduled_releases"].as_array().unwrap();
+    assert_eq!(scheduled_releases.len(), 1);
+    assert_eq!(scheduled_releases[0]["version"], "1.1.0");
+
+    // Releasing the version early makes it visible
+    let response = token
+        .put::<()>(
+            "/api/v1/crates/foo/1.1.0/scheduled_release/release",
+            b"" as &[u8],
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs().await;
+
+    // The release job enqueues the index sync, which the workers of the
+    // `repository` queue might only pick up in the next run
+    app.run_pending_background_jobs().await;
+
+    let json = token.get::<()>("/api/v1/crates/foo/versions").await.json();
+    assert_eq!(json["versions"].as_array().unwrap().len(), 2);
+
+    let crates = app.crates_from_index_head("foo");
+    assert_eq!(crates.len(), 2);
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files.contains(&"crates/foo/foo-1.1.0.crate".to_string()));
+    assert!(!stored_files
+        .iter()
+        .any(|path| path.starts_with("scheduled-releases/")));
+
+    let json = token
+        .get::<()>("/api/v1/crates/foo/scheduled_releases")
+        .await
+        .json();
+    assert_eq!(json["scheduled_releases"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cancelled_version_is_deleted() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0").release_at(in_one_day());
+    token.publish_crate(crate_to_publish).await.good();
+
+    let response = token
+        .delete::<()>("/api/v1/crates/foo/1.1.0/scheduled_release")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json()["scheduled_release"]["resolution"],
+        "cancelled"
+    );
+
+    let response = token
+        .delete::<()>("/api/v1/crates/foo/1.1.0/scheduled_release")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"version `1.1.0` of crate `foo` has no pending release"}]}"###);
+
+    let expected_files = vec!["crates/foo/foo-1.0.0.crate", "index/3/f/foo"];
+    assert_eq!(app.stored_files().await, expected_files);
+
+    // The version number can be published again
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await
+        .good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn release_at_in_the_past() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let release_at = (Utc::now() - Duration::hours(1)).naive_utc();
+    let crate_to_publish = PublishBuilder::new("foo", "1.1.0").release_at(release_at);
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`release_at` must be in the future"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn release_at_for_new_crate() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").release_at(in_one_day());
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"scheduled releases are only supported for new versions of existing crates"}]}"###);
+
+    assert_eq!(app.stored_files().await.len(), 0);
+}
@@ -0,0 +1,68 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::{NewTokenAnomaly, TokenAnomaly, TokenAnomalyKind};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_is_locked_until_the_activity_is_confirmed() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    let anomaly = app.db(|conn| {
+        NewTokenAnomaly {
+            user_id: token.as_model().user_id,
+            api_token_id: Some(token.as_model().id),
+            kind: TokenAnomalyKind::NewNetwork.as_str(),
+            details: "publish from 203.0.113.1",
+            publishes_locked: true,
+        }
+        .insert(conn)
+        .unwrap()
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo_locked", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"Publishing is locked because of unusual activity of your API tokens. Please confirm the activity via the link in the email that was sent to you, or contact help@crates.io."}]}"###);
+    assert_eq!(app.stored_files().await.len(), 0);
+
+    let url = "/api/v1/confirm_publishes/invalid";
+    let response = anon.put::<()>(url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let url = format!("/api/v1/confirm_publishes/{}", anomaly.confirmation_token);
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let locked = app.db(|conn| TokenAnomaly::publishes_locked(conn, token.as_model().user_id));
+    assert!(!locked.unwrap());
+
+    // The token can only be used once
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let crate_to_publish = PublishBuilder::new("foo_locked", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unlocked_anomalies_do_not_block_publishing() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        NewTokenAnomaly {
+            user_id: token.as_model().user_id,
+            api_token_id: None,
+            kind: TokenAnomalyKind::PublishBurst.as_str(),
+            details: "100 versions were published within one hour",
+            publishes_locked: false,
+        }
+        .insert(conn)
+        .unwrap()
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo_unlocked", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
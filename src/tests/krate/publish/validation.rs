@@ -115,3 +115,36 @@ async fn invalid_urls() {
 
     assert_that!(app.stored_files().await, empty());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn multiple_validation_errors() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .unset_description()
+        .documentation("javascript:alert('boom')")
+        .keyword("áccênts");
+
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json(), @r###"
+    {
+      "errors": [
+        {
+          "detail": "missing or empty metadata fields: description. Please see https://doc.rust-lang.org/cargo/reference/manifest.html for more information on configuring these fields",
+          "field": "description"
+        },
+        {
+          "detail": "URL for field `documentation` must begin with http:// or https:// (url: javascript:alert('boom'))",
+          "field": "documentation"
+        },
+        {
+          "detail": "\"áccênts\" is an invalid keyword",
+          "field": "keywords"
+        }
+      ]
+    }
+    "###);
+
+    assert_that!(app.stored_files().await, empty());
+}
@@ -0,0 +1,42 @@
+use crate::util::{RequestHelper, TestApp};
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn saturated_route_is_rejected() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| {
+            // A limit of zero rejects all requests, as if the route was
+            // saturated by other in-flight requests.
+            config
+                .route_concurrency_limits
+                .insert("/api/v1/summary".into(), 0);
+        })
+        .empty();
+
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "5");
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"This route is currently overloaded. Please try again later."}]}"###);
+
+    // Other routes are not affected
+    let response = anon.get::<()>("/api/v1/site_metadata").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn route_below_limit_is_served() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| {
+            config
+                .route_concurrency_limits
+                .insert("/api/v1/summary".into(), 1);
+        })
+        .empty();
+
+    // The permit is released after each response
+    for _ in 0..3 {
+        let response = anon.get::<()>("/api/v1/summary").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
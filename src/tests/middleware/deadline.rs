@@ -0,0 +1,43 @@
+use crate::util::TestApp;
+use crates_io::db::DbConnection;
+use crates_io::middleware::deadline::Deadline;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use std::time::Duration;
+
+#[derive(QueryableByName)]
+struct StatementTimeout {
+    #[diesel(sql_type = Text)]
+    statement_timeout: String,
+}
+
+async fn statement_timeout(conn: DbConnection) -> String {
+    conn.interact(|conn| {
+        diesel::sql_query("SHOW statement_timeout").get_result::<StatementTimeout>(conn)
+    })
+    .await
+    .unwrap()
+    .unwrap()
+    .statement_timeout
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn statement_timeout_is_limited_by_deadline() {
+    let (app, _) = TestApp::init().empty();
+    let app = app.as_inner();
+
+    let conn = app.db_write().await.unwrap();
+    assert_eq!(statement_timeout(conn).await, "1s");
+
+    let deadline = Deadline::after(Duration::from_secs(60));
+    let conn = deadline.scope(app.db_write()).await.unwrap();
+    assert_eq!(statement_timeout(conn).await, "1s");
+
+    let deadline = Deadline::after(Duration::ZERO);
+    let conn = deadline.scope(app.db_write()).await.unwrap();
+    assert_eq!(statement_timeout(conn).await, "1ms");
+
+    // The configured timeout is restored after each interaction
+    let conn = app.db_write().await.unwrap();
+    assert_eq!(statement_timeout(conn).await, "1s");
+}
@@ -0,0 +1,40 @@
+use crate::util::{RequestHelper, TestApp};
+use http::{header, StatusCode};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deprecated_routes_and_parameters_have_headers() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| {
+            let deprecations = &mut config.api_deprecations;
+            deprecations.info_url = Some("https://crates.io/docs/deprecations".into());
+            deprecations.deprecations = vec![
+                "/api/v1/summary=2024-06-01/2024-12-01".parse().unwrap(),
+                "/api/v1/crates?letter=2024-06-01".parse().unwrap(),
+            ];
+        })
+        .empty();
+
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers["deprecation"], "@1717200000");
+    assert_eq!(headers["sunset"], "Sun, 01 Dec 2024 00:00:00 GMT");
+    assert_eq!(
+        headers[header::LINK],
+        "<https://crates.io/docs/deprecations>; rel=\"deprecation\"; type=\"text/html\""
+    );
+
+    let response = anon.get::<()>("/api/v1/crates?letter=a").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["deprecation"], "@1717200000");
+    assert!(!response.headers().contains_key("sunset"));
+
+    // Other parameters and routes are not deprecated
+    let response = anon.get::<()>("/api/v1/crates?page=1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("deprecation"));
+
+    let response = anon.get::<()>("/api/v1/site_metadata").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("deprecation"));
+}
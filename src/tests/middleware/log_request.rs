@@ -0,0 +1,24 @@
+use crate::util::{RequestHelper, TestApp};
+use crates_io::util::tracing::capture_for_test;
+use http::StatusCode;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn logs_requests() {
+    let (_, anon) = TestApp::init().empty();
+    let logs = capture_for_test();
+
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let events = logs
+        .events()
+        .into_iter()
+        .filter(|event| event.target == "http")
+        .collect::<Vec<_>>();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].level, tracing::Level::INFO);
+    logs.assert_contains(r#"method=GET path="/api/v1/crates/foo""#);
+    logs.assert_contains("status=404");
+    logs.assert_not_contains("SLOW REQUEST");
+}
@@ -1 +1,7 @@
+mod concurrency_limit;
+mod deadline;
+mod deprecation;
 mod head;
+mod log_request;
+mod problem_json;
+mod request_timeout;
@@ -0,0 +1,71 @@
+use crate::util::{MockRequestExt, RequestHelper, Response, TestApp};
+use http::{header, StatusCode};
+use insta::assert_json_snapshot;
+use serde_json::Value;
+
+const PROBLEM_JSON: &str = "application/problem+json";
+
+async fn get_problem(anon: &impl RequestHelper, url: &str) -> Response<()> {
+    let mut request = anon.get_request(url);
+    request.header(header::ACCEPT, PROBLEM_JSON);
+    request.header("x-request-id", "abcd");
+    anon.run(request).await
+}
+
+/// Decodes a problem document, which `Response::json()` rejects because of its
+/// content type.
+fn problem_body(response: &Response<()>) -> Value {
+    assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+    serde_json::from_slice(response.bytes()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn not_found_as_problem_document() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let response = get_problem(&anon, "/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()[header::VARY], "Accept");
+    assert_json_snapshot!(problem_body(&response), @r###"
+    {
+      "code": "crate_not_found",
+      "detail": "crate `foo` does not exist",
+      "errors": [
+        {
+          "detail": "crate `foo` does not exist"
+        }
+      ],
+      "request_id": "abcd",
+      "status": 404,
+      "title": "Not Found",
+      "type": "urn:crates-io:error:crate_not_found"
+    }
+    "###);
+
+    // Without the `Accept` header, the regular format is returned
+    let response = anon.get::<()>("/api/v1/crates/foo").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate `foo` does not exist" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn middleware_errors_as_problem_documents() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| {
+            config
+                .route_concurrency_limits
+                .insert("/api/v1/summary".into(), 0);
+        })
+        .empty();
+
+    let response = get_problem(&anon, "/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "5");
+    let problem = problem_body(&response);
+    assert_eq!(problem["code"], "route_overloaded");
+    assert_eq!(problem["type"], "urn:crates-io:error:route_overloaded");
+}
@@ -0,0 +1,25 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_routes_time_out() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| config.request_timeouts.read = Duration::ZERO)
+        .empty();
+
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.json()["errors"][0]["detail"].is_string());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn uploads_use_their_own_timeout() {
+    let (_app, _anon, _user, token) = TestApp::full()
+        .with_config(|config| config.request_timeouts.read = Duration::ZERO)
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+}
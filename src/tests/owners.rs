@@ -357,7 +357,9 @@ async fn deleted_ownership_isnt_in_owner_user() {
 
     app.db(|conn| {
         let krate = CrateBuilder::new("foo_my_packages", user.id).expect_build(conn);
-        krate.owner_remove(conn, &user.gh_login).unwrap();
+        krate
+            .owner_remove(app.as_inner(), conn, user, &user.gh_login)
+            .unwrap();
     });
 
     let json: UserResponse = anon
@@ -602,8 +604,9 @@ async fn test_accept_expired_invitation() {
         .await
         .good();
 
-    // Manually update the creation time to simulate the invite expiring
-    expire_invitation(&app, krate.id);
+    // Let the invite expire
+    let expiration = app.as_inner().config.ownership_invitations_expiration_days as i64;
+    app.advance_time(Duration::days(expiration));
 
     // New owner tries to accept the invitation but it fails
     let resp = invited_user
@@ -640,8 +643,9 @@ async fn test_decline_expired_invitation() {
         .await
         .good();
 
-    // Manually update the creation time to simulate the invite expiring
-    expire_invitation(&app, krate.id);
+    // Let the invite expire
+    let expiration = app.as_inner().config.ownership_invitations_expiration_days as i64;
+    app.advance_time(Duration::days(expiration));
 
     // New owner declines the invitation and it succeeds, even though the invitation expired.
     invited_user
@@ -658,7 +662,9 @@ async fn test_accept_expired_invitation_by_mail() {
     let (app, anon, owner, owner_token) = TestApp::init().with_token();
     let owner = owner.as_model();
     let _invited_user = app.db_new_user("demo_user");
-    let krate = app.db(|conn| CrateBuilder::new("demo_crate", owner.id).expect_build(conn));
+    app.db(|conn| {
+        CrateBuilder::new("demo_crate", owner.id).expect_build(conn);
+    });
 
     // Invite a new owner
     owner_token
@@ -666,8 +672,9 @@ async fn test_accept_expired_invitation_by_mail() {
         .await
         .good();
 
-    // Manually update the creation time to simulate the invite expiring
-    expire_invitation(&app, krate.id);
+    // Let the invite expire
+    let expiration = app.as_inner().config.ownership_invitations_expiration_days as i64;
+    app.advance_time(Duration::days(expiration));
 
     // Retrieve the ownership invitation
     let invite_token = extract_token_from_invite_email(&app.as_inner().emails);
@@ -756,6 +763,54 @@ async fn highest_gh_id_is_most_recent_account_we_know_of() {
     assert_eq!(json.crate_owner_invitations.len(), 1);
 }
 
+/// The ownership history records who added and removed owners, and how
+#[tokio::test(flavor = "multi_thread")]
+async fn owner_history() {
+    let (app, anon, user, token) = TestApp::init().with_token();
+
+    let krate =
+        app.db(|conn| CrateBuilder::new("owner_history", user.as_model().id).expect_build(conn));
+
+    create_and_add_owner(&app, &token, "secondowner", &krate).await;
+    token
+        .remove_named_owner("owner_history", "secondowner")
+        .await
+        .good();
+
+    let json: serde_json::Value = anon
+        .get("/api/v1/crates/owner_history/owners/history")
+        .await
+        .good();
+
+    let actions = json["actions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|action| {
+            (
+                action["action"].as_str().unwrap(),
+                action["via"].as_str().unwrap(),
+                action["owner"]["login"].as_str().unwrap(),
+                action["actor"]["login"].as_str().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        actions,
+        vec![
+            ("add", "publish", "foo", "foo"),
+            ("add", "invitation", "secondowner", "foo"),
+            ("remove", "direct", "secondowner", "foo"),
+        ]
+    );
+
+    let response = anon
+        .get::<()>("/api/v1/crates/unknown/owners/history")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
 fn extract_token_from_invite_email(emails: &Emails) -> String {
     let emails = emails.mails_in_memory().unwrap();
 
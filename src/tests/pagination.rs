@@ -1,5 +1,6 @@
 use crate::builders::CrateBuilder;
-use crate::util::{RequestHelper, TestApp};
+use crate::util::{MockRequestExt, RequestHelper, TestApp};
+use crates_io::util::ip_blocklist::IpBlocklist;
 use http::status::StatusCode;
 use ipnetwork::IpNetwork;
 use serde_json::json;
@@ -9,7 +10,8 @@ async fn pagination_blocks_ip_from_cidr_block_list() {
     let (app, anon, user) = TestApp::init()
         .with_config(|config| {
             config.max_allowed_page_offset = 1;
-            config.page_offset_cidr_blocklist = vec!["127.0.0.1/24".parse::<IpNetwork>().unwrap()];
+            config.page_offset_cidr_blocklist =
+                IpBlocklist::from_iter(["127.0.0.1/24".parse::<IpNetwork>().unwrap()]);
         })
         .with_user();
     let user = user.as_model();
@@ -29,3 +31,30 @@ async fn pagination_blocks_ip_from_cidr_block_list() {
         json!({ "errors": [{ "detail": "Page 2 is unavailable for performance reasons. Please take a look at https://crates.io/data-access for alternatives." }] })
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pagination_blocks_ipv6_from_cidr_block_list() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.max_allowed_page_offset = 1;
+            config.page_offset_cidr_blocklist =
+                IpBlocklist::from_iter(["2001:db8:1234:5678::/64".parse::<IpNetwork>().unwrap()]);
+        })
+        .with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("pagination_links_1", user.id).expect_build(conn);
+        CrateBuilder::new("pagination_links_2", user.id).expect_build(conn);
+    });
+
+    let mut request = anon.get_request("/api/v1/crates?page=2&per_page=1");
+    request.header("x-forwarded-for", "2001:db8:1234:5678:9abc::1");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let mut request = anon.get_request("/api/v1/crates?page=2&per_page=1");
+    request.header("x-forwarded-for", "2001:db8:1234:5679::1");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
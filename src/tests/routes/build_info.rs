@@ -0,0 +1,84 @@
+use crate::util::{MockAnonymousUser, MockRequestExt, Response};
+use crate::{RequestHelper, TestApp};
+use crates_io::config::MetricsToken;
+use http::StatusCode;
+use insta::assert_json_snapshot;
+
+async fn request_build_info(anon: &MockAnonymousUser, token: Option<&str>) -> Response<()> {
+    let mut req = anon.get_request("/api/private/build_info");
+    if let Some(token) = token {
+        req.header("Authorization", &format!("Bearer {token}"));
+    }
+    anon.run(req).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn build_info() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![MetricsToken::new("foobar", None)]
+        })
+        .empty();
+
+    let response = request_build_info(&anon, Some("foobar")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".build_timestamp" => "[datetime]",
+        ".rustc_version" => "[version]",
+        ".config_digest" => "[digest]",
+    }, @r###"
+    {
+      "build_timestamp": "[datetime]",
+      "commit": "unknown",
+      "config_digest": "[digest]",
+      "features": [],
+      "rustc_version": "[version]"
+    }
+    "###);
+
+    let json = response.json();
+    let digest = json["config_digest"].as_str().unwrap();
+    assert_eq!(digest.len(), 64);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn config_digest_changes_with_config() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![MetricsToken::new("foobar", None)]
+        })
+        .empty();
+    let json = request_build_info(&anon, Some("foobar")).await.json();
+    let digest = json["config_digest"].clone();
+
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![MetricsToken::new("foobar", None)];
+            config.max_upload_size = 1;
+        })
+        .empty();
+    let json = request_build_info(&anon, Some("foobar")).await.json();
+    assert_ne!(json["config_digest"], digest);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn build_info_requires_metrics_token() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![MetricsToken::new("secret", None)]
+        })
+        .empty();
+
+    let response = request_build_info(&anon, Some("foobar")).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = request_build_info(&anon, None).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.metrics_authorization_tokens = vec![])
+        .empty();
+
+    let response = request_build_info(&anon, Some("foobar")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
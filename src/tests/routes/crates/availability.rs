@@ -0,0 +1,76 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::upstream::MockUpstreamRegistry;
+use http::StatusCode;
+use insta::assert_json_snapshot;
+use serde_json::Value;
+
+async fn availability(anon: &impl RequestHelper, name: &str) -> Value {
+    let url = format!("/api/v1/crates/{name}/availability");
+    anon.get::<Value>(&url).await.good()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn available_name() {
+    let (_app, anon) = TestApp::init().empty();
+
+    assert_json_snapshot!(availability(&anon, "foo").await, @r###"
+    {
+      "availability": {
+        "available": true,
+        "detail": null,
+        "name": "foo",
+        "reason": null
+      }
+    }
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unavailable_names() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_bar", user.as_model().id).expect_build(conn);
+    });
+
+    let request = user.post_request("/api/v1/crates/reserved_name/reserve");
+    let response = user.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = availability(&anon, "foo_bar").await;
+    assert_eq!(json["availability"]["available"], false);
+    assert_eq!(json["availability"]["reason"], "taken");
+
+    let json = availability(&anon, "Foo-Bar").await;
+    assert_eq!(json["availability"]["reason"], "too_similar");
+    assert_eq!(
+        json["availability"]["detail"],
+        "crate name `Foo-Bar` is too similar to the existing crate `foo_bar`"
+    );
+
+    let json = availability(&anon, "std").await;
+    assert_eq!(json["availability"]["reason"], "blocked");
+
+    let json = availability(&anon, "reserved-name").await;
+    assert_eq!(json["availability"]["reason"], "reserved");
+
+    let json = availability(&anon, "1foo").await;
+    assert_eq!(json["availability"]["reason"], "invalid");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upstream_crate_names_are_unavailable() {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream.expect_index_file().returning(|name| match name {
+        "serde_json" => Ok(Some(Default::default())),
+        _ => Ok(None),
+    });
+
+    let (_app, anon) = TestApp::init().with_upstream(upstream).empty();
+
+    let json = availability(&anon, "serde-json").await;
+    assert_eq!(json["availability"]["reason"], "upstream");
+
+    let json = availability(&anon, "foo").await;
+    assert_eq!(json["availability"]["available"], true);
+}
@@ -0,0 +1,89 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use chrono::NaiveDate;
+use crates_io::schema::crate_dependents_history;
+use crates_io::worker::jobs::SnapshotDependentsCounts;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dependents_history() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id).expect_build(conn);
+
+        CrateBuilder::new("c2", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .version(
+                VersionBuilder::new("1.1.0")
+                    .dependency(&c1, None)
+                    .dependency(&c1, Some("foo")),
+            )
+            .expect_build(conn);
+
+        // Only the latest version is taken into account
+        CrateBuilder::new("c3", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .version("2.0.0")
+            .expect_build(conn);
+
+        // Yanked versions are ignored
+        CrateBuilder::new("c4", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .version(VersionBuilder::new("1.1.0").yanked(true))
+            .expect_build(conn);
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        diesel::insert_into(crate_dependents_history::table)
+            .values((
+                crate_dependents_history::crate_id.eq(c1.id),
+                crate_dependents_history::date.eq(date),
+                crate_dependents_history::dependents.eq(5),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        SnapshotDependentsCounts.enqueue(conn).unwrap();
+    });
+
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/api/v1/crates/c1/dependents_history").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".dependents_history[1].date" => "[date]",
+    }, @r###"
+    {
+      "dependents_history": [
+        {
+          "date": "2024-01-01",
+          "dependents": 5
+        },
+        {
+          "date": "[date]",
+          "dependents": 2
+        }
+      ]
+    }
+    "###);
+
+    // c2 has no dependents
+    let response = anon.get::<()>("/api/v1/crates/c2/dependents_history").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"dependents_history":[]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dependents_history_of_missing_crate() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon
+        .get::<()>("/api/v1/crates/missing/dependents_history")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `missing` does not exist"}]}"###);
+}
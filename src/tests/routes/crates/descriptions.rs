@@ -0,0 +1,154 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockRequestExt, RequestHelper, Response, TestApp};
+use http::{header, StatusCode};
+use serde_json::{json, Value};
+
+async fn get_with_language(
+    anon: &impl RequestHelper,
+    url: &str,
+    accept_language: &str,
+) -> Response<Value> {
+    let mut request = anon.get_request(url);
+    request.header(header::ACCEPT_LANGUAGE, accept_language);
+    anon.run(request).await
+}
+
+async fn put_description(
+    user: &impl RequestHelper,
+    crate_name: &str,
+    locale: &str,
+    description: &str,
+) -> Response<Value> {
+    let url = format!("/api/v1/crates/{crate_name}/descriptions/{locale}");
+    let body = json!({ "description": description }).to_string();
+    user.put(&url, body).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn translated_descriptions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .description("A crate for foos")
+            .expect_build(conn);
+    });
+
+    let response = put_description(&user, "foo", "de", "  Eine Kiste für Foos ").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["description"]["locale"], "de");
+    assert_eq!(
+        response.json()["description"]["description"],
+        "Eine Kiste für Foos"
+    );
+
+    let response = put_description(&user, "foo", "pt-BR", "Uma caixa para foos").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["description"]["locale"], "pt-br");
+
+    let response = anon.get::<Value>("/api/v1/crates/foo/descriptions").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let locales = response.json()["descriptions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|translation| translation["locale"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(locales, vec!["de", "pt-br"]);
+
+    // The manifest description is used without a matching translation
+    let json = anon.show_crate("foo").await;
+    assert_eq!(json.krate.description.unwrap(), "A crate for foos");
+
+    let response = get_with_language(&anon, "/api/v1/crates/foo", "fr, en;q=0.5").await;
+    assert_eq!(response.json()["crate"]["description"], "A crate for foos");
+
+    let response = get_with_language(&anon, "/api/v1/crates/foo", "de-CH, en;q=0.5").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json()["crate"]["description"],
+        "Eine Kiste für Foos"
+    );
+
+    let response = get_with_language(&anon, "/api/v1/crates?q=foo", "pt-BR").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json()["crates"][0]["description"],
+        "Uma caixa para foos"
+    );
+
+    // Replacing an existing translation
+    let response = put_description(&user, "foo", "DE", "Eine Kiste voller Foos").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = get_with_language(&anon, "/api/v1/crates/foo", "de").await;
+    assert_eq!(
+        response.json()["crate"]["description"],
+        "Eine Kiste voller Foos"
+    );
+
+    let response = user
+        .delete::<Value>("/api/v1/crates/foo/descriptions/de")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = get_with_language(&anon, "/api/v1/crates/foo", "de").await;
+    assert_eq!(response.json()["crate"]["description"], "A crate for foos");
+
+    let response = user
+        .delete::<Value>("/api/v1/crates/foo/descriptions/de")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate `foo` has no description for locale `de`" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_descriptions() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let response = put_description(&user, "foo", "de_DE", "Eine Kiste für Foos").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "`de_DE` is not a valid language tag, e.g. `de` or `pt-BR`" }] })
+    );
+
+    let response = put_description(&user, "foo", "de", "   ").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "the description must not be empty" }] })
+    );
+
+    let response = put_description(&user, "foo", "de", &"a".repeat(1001)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = put_description(&user, "bar", "de", "Eine Kiste für Bars").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_change_descriptions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let response = put_description(&anon, "foo", "de", "Eine Kiste für Foos").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let other_user = app.db_new_user("bar");
+    let response = put_description(&other_user, "foo", "de", "Eine Kiste für Foos").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "only owners have permission to change the descriptions of a crate" }] })
+    );
+
+    let response = put_description(&user, "foo", "de", "Eine Kiste für Foos").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = other_user
+        .delete::<Value>("/api/v1/crates/foo/descriptions/de")
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
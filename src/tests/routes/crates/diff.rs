@@ -0,0 +1,76 @@
+use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn diff() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        for name in ["dep-a", "dep-b", "dep-c", "dep-d"] {
+            CrateBuilder::new(name, user.as_model().id).expect_build(conn);
+        }
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .license("MIT")
+        .feature("default", &["std"])
+        .feature("std", &[])
+        .feature("old", &[])
+        .dependency(DependencyBuilder::new("dep-a").version_req("^1.0"))
+        .dependency(DependencyBuilder::new("dep-b"))
+        .dependency(DependencyBuilder::new("dep-c"));
+    token.publish_crate(crate_to_publish).await.good();
+
+    let crate_to_publish = PublishBuilder::new("foo", "2.0.0")
+        .license("MIT OR Apache-2.0")
+        .feature("default", &["std", "new"])
+        .feature("std", &[])
+        .feature("new", &[])
+        .dependency(DependencyBuilder::new("dep-a").version_req("^2.0"))
+        .dependency(DependencyBuilder::new("dep-b"))
+        .dependency(DependencyBuilder::new("dep-d"));
+    token.publish_crate(crate_to_publish).await.good();
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/foo/diff", "from=1.0.0&to=2.0.0")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json());
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/foo/diff", "from=2.0.0&to=2.0.0")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn diff_errors() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/foo/diff", "from=1.0.0")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"missing `to` query parameter"}]}"###);
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/foo/diff", "from=1.0.0&to=2.0.0")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not have a version `2.0.0`"}]}"###);
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/bar/diff", "from=1.0.0&to=2.0.0")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `bar` does not exist"}]}"###);
+}
@@ -1,7 +1,7 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{MockAnonymousUser, RequestHelper, TestApp};
 use chrono::{Duration, Utc};
-use crates_io::schema::{crates, version_downloads, versions};
+use crates_io::schema::{crate_client_downloads, crates, version_downloads, versions};
 use crates_io::views::EncodableVersionDownload;
 use diesel::prelude::*;
 use http::StatusCode;
@@ -216,3 +216,63 @@ async fn test_version_downloads() {
         @r###"{"errors":[{"detail":"crate `foo` does not have a version `invalid-version`"}]}"###
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_client_downloads() {
+    let (app, anon, cookie) = TestApp::init().with_user();
+    let other_user = app.db_new_user("bar");
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo", cookie.as_model().id).expect_build(conn);
+
+        let today = Utc::now().date_naive();
+        let rows = [
+            (today, "1.74", 3),
+            (today, "1.60", 1),
+            (today - Duration::days(1), "1.74", 2),
+            (today - Duration::days(100), "1.74", 5),
+        ];
+        for (date, client_version, downloads) in rows {
+            diesel::insert_into(crate_client_downloads::table)
+                .values((
+                    crate_client_downloads::crate_id.eq(krate.id),
+                    crate_client_downloads::date.eq(date),
+                    crate_client_downloads::client_version.eq(client_version),
+                    crate_client_downloads::downloads.eq(downloads),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    let response = cookie
+        .get::<()>("/api/v1/crates/foo/client_downloads")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".client_downloads[].date" => "[date]",
+    });
+
+    // check that only owners have access
+    let response = other_user
+        .get::<()>("/api/v1/crates/foo/client_downloads")
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(
+        response.text(),
+        @r###"{"errors":[{"detail":"must be an owner of the crate to view its client download statistics"}]}"###
+    );
+
+    let response = anon.get::<()>("/api/v1/crates/foo/client_downloads").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // check different crate name
+    let response = cookie
+        .get::<()>("/api/v1/crates/baz/client_downloads")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(
+        response.text(),
+        @r###"{"errors":[{"detail":"crate `baz` does not exist"}]}"###
+    );
+}
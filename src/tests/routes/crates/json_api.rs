@@ -0,0 +1,127 @@
+//! Tests for the JSON:API serialization mode of the crate, version and owner
+//! endpoints
+
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockRequestExt, RequestHelper, Response, TestApp};
+use crates_io::views::json_api::MEDIA_TYPE;
+use http::{header, StatusCode};
+use serde_json::{json, Value};
+
+async fn get_json_api(anon: &impl RequestHelper, url: &str) -> Response<()> {
+    let mut request = anon.get_request(url);
+    request.header(header::ACCEPT, MEDIA_TYPE);
+    anon.run(request).await
+}
+
+fn json_api_body(response: &Response<()>) -> Value {
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], MEDIA_TYPE);
+    // `Response::json()` only accepts `application/json` responses
+    serde_json::from_slice(response.bytes()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn show_crate() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .description("description")
+            .version(VersionBuilder::new("1.0.0"))
+            .keyword("kw1")
+            .expect_build(conn);
+    });
+
+    let response = get_json_api(&anon, "/api/v1/crates/foo").await;
+    let json = json_api_body(&response);
+
+    assert_eq!(json["data"]["type"], "crates");
+    assert_eq!(json["data"]["id"], "foo");
+    assert_eq!(json["data"]["attributes"]["description"], "description");
+    assert_eq!(json["data"]["attributes"].get("versions"), None);
+    assert_eq!(
+        json["data"]["relationships"]["keywords"],
+        json!({ "data": [{ "type": "keywords", "id": "kw1" }] })
+    );
+
+    let version_id = &json["data"]["relationships"]["versions"]["data"][0]["id"];
+    let included = json["included"].as_array().unwrap();
+    let version = included.iter().find(|r| r["type"] == "versions").unwrap();
+    assert_eq!(&version["id"], version_id);
+    assert_eq!(version["attributes"]["num"], "1.0.0");
+    assert_eq!(
+        version["relationships"]["crate"],
+        json!({ "data": { "type": "crates", "id": "foo" } })
+    );
+
+    let keyword = included.iter().find(|r| r["type"] == "keywords").unwrap();
+    assert_eq!(keyword["id"], "kw1");
+
+    // Without the `Accept` header, the regular response is returned
+    let json: Value = anon.get("/api/v1/crates/foo").await.good();
+    assert_eq!(json["crate"]["id"], "foo");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sparse_fieldsets() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .description("description")
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo?fields[crates]=name,versions&fields[versions]=num";
+    let response = get_json_api(&anon, url).await;
+    let json = json_api_body(&response);
+
+    assert_eq!(json["data"]["attributes"], json!({ "name": "foo" }));
+    let relationships = json["data"]["relationships"].as_object().unwrap();
+    assert_eq!(relationships.keys().collect::<Vec<_>>(), ["versions"]);
+
+    let version = &json["included"][0];
+    assert_eq!(version["attributes"], json!({ "num": "1.0.0" }));
+    assert_eq!(version.get("relationships"), None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn show_version_and_owners() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let response = get_json_api(&anon, "/api/v1/crates/foo/1.0.0").await;
+    let json = json_api_body(&response);
+    assert_eq!(json["data"]["type"], "versions");
+    assert_eq!(json["data"]["attributes"]["num"], "1.0.0");
+    assert_eq!(
+        json["data"]["relationships"]["published_by"],
+        json!({ "data": { "type": "users", "id": user.id.to_string() } })
+    );
+
+    let response = get_json_api(&anon, "/api/v1/crates/foo/owners").await;
+    let json = json_api_body(&response);
+    assert_eq!(
+        json["data"],
+        json!([{
+            "type": "users",
+            "id": user.id.to_string(),
+            "attributes": {
+                "avatar": null,
+                "kind": "user",
+                "login": "foo",
+                "name": null,
+                "url": "https://github.com/foo",
+            },
+        }])
+    );
+}
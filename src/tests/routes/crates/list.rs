@@ -1,12 +1,13 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
 use crate::{new_category, new_user};
+use crates_io::config::RankingWeights;
 use crates_io::models::Category;
 use crates_io::schema::crates;
 use diesel::{dsl::*, prelude::*, update};
 use googletest::prelude::*;
 use http::StatusCode;
-use insta::assert_json_snapshot;
+use insta::{assert_json_snapshot, assert_snapshot};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
@@ -261,6 +262,52 @@ async fn exact_match_first_on_queries() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn ranking_experiments() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            let weights = config.search_ranking.weights;
+            let popular = RankingWeights {
+                downloads: 1.,
+                ..weights
+            };
+            let experiments = &mut config.search_ranking.experiments;
+            experiments.insert("popular".into(), popular);
+        })
+        .with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("relevant", user.id)
+            .description("ranking ranking ranking")
+            .expect_build(conn);
+
+        CrateBuilder::new("popular", user.id)
+            .description("ranking")
+            .downloads(1000)
+            .expect_build(conn);
+    });
+
+    for query in ["q=ranking", "q=ranking&experiment=unknown"] {
+        for json in search_both(&anon, query).await {
+            assert_eq!(json.meta.total, 2);
+            assert_eq!(json.crates[0].name, "relevant");
+            assert_eq!(json.crates[1].name, "popular");
+        }
+    }
+
+    for json in search_both(&anon, "q=ranking&experiment=popular").await {
+        assert_eq!(json.meta.total, 2);
+        assert_eq!(json.crates[0].name, "popular");
+        assert_eq!(json.crates[1].name, "relevant");
+    }
+
+    let (resp, calls) = page_with_seek(&anon, "q=ranking&experiment=popular").await;
+    assert_eq!(calls, 3);
+    assert_eq!(resp[0].crates[0].name, "popular");
+    assert_eq!(resp[1].crates[0].name, "relevant");
+}
+
 #[tokio::test(flavor = "multi_thread")]
 #[allow(clippy::cognitive_complexity)]
 async fn index_sorting() {
@@ -446,14 +493,14 @@ async fn index_sorting() {
                     .into_owned()
                     .collect::<indexmap::IndexMap<String, String>>();
                 query.get("seek").map(|s| {
-                    let d = decode_seek::<(bool, f32, i32)>(s).unwrap();
-                    (d.0, (d.1 * 1e12) as i64, name)
+                    let d = decode_seek::<(f64, i32)>(s).unwrap();
+                    ((d.0 * 1e12) as i64, name)
                 })
             })
             .collect::<Vec<_>>();
-        // ordering (exact match desc, rank desc, name asc)
+        // ordering (score desc, name asc)
         let mut sorted = decoded_seeks.clone();
-        sorted.sort_by_key(|k| (Reverse(k.0), Reverse(k.1), k.2.to_owned()));
+        sorted.sort_by_key(|k| (Reverse(k.0), k.1.to_owned()));
         assert_eq!(sorted, decoded_seeks);
         for json in search_both(&anon, query).await {
             assert_eq!(json.meta.total, resp[0].meta.total);
@@ -727,6 +774,149 @@ async fn index_include_yanked() {
         assert_eq!(json.crates[1].name, "oldest_yanked");
         assert_eq!(json.crates[2].name, "unyanked");
     }
+
+    // Yanked versions are only considered for the max version if they are
+    // included explicitly
+    for json in search_both(&anon, "q=newest_yanked").await {
+        assert_eq!(json.crates[0].max_version, "1.0.0");
+    }
+
+    for json in search_both(&anon, "q=newest_yanked&include_yanked=true").await {
+        assert_eq!(json.crates[0].max_version, "2.0.0");
+    }
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "include_yanked=maybe")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_msrv() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("no_msrv", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+
+        CrateBuilder::new("old_msrv", user.id)
+            .version(VersionBuilder::new("1.0.0").rust_version("1.56"))
+            .expect_build(conn);
+
+        CrateBuilder::new("new_msrv", user.id)
+            .version(VersionBuilder::new("1.0.0").rust_version("1.70.1"))
+            .expect_build(conn);
+
+        CrateBuilder::new("bumped_msrv", user.id)
+            .version(VersionBuilder::new("1.0.0").rust_version("1.60"))
+            .version(VersionBuilder::new("2.0.0").rust_version("1.75.0"))
+            .expect_build(conn);
+
+        CrateBuilder::new("yanked_msrv", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .rust_version("1.40")
+                    .yanked(true),
+            )
+            .version(VersionBuilder::new("2.0.0").rust_version("1.80"))
+            .expect_build(conn);
+    });
+
+    for json in search_both(&anon, "msrv=1.70&sort=alphabetical").await {
+        assert_eq!(json.meta.total, 2);
+        assert_eq!(json.crates[0].name, "bumped_msrv");
+        assert_eq!(json.crates[1].name, "old_msrv");
+    }
+
+    for json in search_both(&anon, "msrv=1.70.1&sort=alphabetical").await {
+        assert_eq!(json.meta.total, 3);
+        assert_eq!(json.crates[0].name, "bumped_msrv");
+        assert_eq!(json.crates[1].name, "new_msrv");
+        assert_eq!(json.crates[2].name, "old_msrv");
+    }
+
+    for json in search_both(&anon, "msrv=1&sort=alphabetical").await {
+        assert_eq!(json.meta.total, 0);
+    }
+
+    for value in ["", "1.x", "1.70.0.1", "^1.70", "1.70-beta"] {
+        let response = anon
+            .get_with_query::<()>("/api/v1/crates", &format!("msrv={value}"))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_depends_on() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let serde = CrateBuilder::new("serde", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        CrateBuilder::new("no_serde", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        CrateBuilder::new("serde_0", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency_with_req(&serde, "0.9"))
+            .expect_build(conn);
+
+        CrateBuilder::new("serde_1", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency_with_req(&serde, "^1.0.100"))
+            .expect_build(conn);
+
+        CrateBuilder::new("serde_range", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency_with_req(&serde, ">=1.2, <2"))
+            .expect_build(conn);
+
+        CrateBuilder::new("serde_yanked", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .dependency_with_req(&serde, "1")
+                    .yanked(true),
+            )
+            .expect_build(conn);
+    });
+
+    for json in search_both(&anon, "depends_on=serde&sort=alphabetical").await {
+        assert_eq!(json.meta.total, 3);
+        assert_eq!(json.crates[0].name, "serde_0");
+        assert_eq!(json.crates[1].name, "serde_1");
+        assert_eq!(json.crates[2].name, "serde_range");
+    }
+
+    for json in search_both(&anon, "depends_on=serde&req=%5E1&sort=alphabetical").await {
+        assert_eq!(json.meta.total, 2);
+        assert_eq!(json.crates[0].name, "serde_1");
+        assert_eq!(json.crates[1].name, "serde_range");
+    }
+
+    for json in search_both(&anon, "depends_on=serde&req=%3E%3D1.1&sort=alphabetical").await {
+        assert_eq!(json.meta.total, 1);
+        assert_eq!(json.crates[0].name, "serde_range");
+    }
+
+    for json in search_both(&anon, "depends_on=serde&req=2").await {
+        assert_eq!(json.meta.total, 0);
+    }
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "depends_on=unknown")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `unknown` does not exist"}]}"###);
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "depends_on=serde&req=foo")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid `req` value: `foo`"}]}"###);
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -1015,6 +1205,49 @@ async fn test_pages_work_even_with_seek_based_pagination() {
     assert_eq!(second.meta.total, 3);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn minimal_meta_omits_total() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("pagination_links_1", user.id).expect_build(conn);
+        CrateBuilder::new("pagination_links_2", user.id).expect_build(conn);
+        CrateBuilder::new("pagination_links_3", user.id).expect_build(conn);
+    });
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "per_page=1&meta=minimal")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["crates"][0]["name"], "pagination_links_1");
+    assert_eq!(json["meta"].get("total"), None);
+    let next_page = json["meta"]["next_page"].as_str().unwrap();
+    assert!(next_page.contains("meta=minimal"));
+    assert!(next_page.contains("seek="));
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "page=2&per_page=1&meta=minimal")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json()["meta"], @r###"
+    {
+      "next_page": "?page=3&per_page=1&meta=minimal",
+      "prev_page": "?page=1&per_page=1&meta=minimal"
+    }
+    "###);
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates", "meta=none")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "invalid meta, expected `full` or `minimal`" }] })
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn invalid_seek_parameter() {
     let (_app, anon, _cookie) = TestApp::init().with_user();
@@ -1075,7 +1308,9 @@ async fn crates_by_user_id_not_including_deleted_owners() {
 
     app.db(|conn| {
         let krate = CrateBuilder::new("foo_my_packages", user.id).expect_build(conn);
-        krate.owner_remove(conn, "foo").unwrap();
+        krate
+            .owner_remove(app.as_inner(), conn, user, "foo")
+            .unwrap();
     });
 
     for response in search_both_by_user_id(&anon, user.id).await {
@@ -1115,7 +1350,7 @@ async fn search_both_by_user_id<U: RequestHelper>(anon: &U, id: i32) -> [crate::
     search_both(anon, &url).await
 }
 
-async fn page_with_seek<U: RequestHelper>(anon: &U, query: &str) -> (Vec<crate::CrateList>, i32) {
+async fn page_with_seek<U: RequestHelper>(anon: &U, query: &str) -> (Vec<crate::CrateList>, i64) {
     let mut url = Some(format!("?per_page=1&{query}"));
     let mut results = Vec::new();
     let mut calls = 0;
@@ -1,8 +1,16 @@
+mod availability;
+mod dependents_history;
+mod descriptions;
+mod diff;
 pub mod downloads;
 mod following;
+mod json_api;
 mod list;
 mod new;
 pub mod owners;
+mod permissions;
 mod read;
+mod recommended_version;
+mod reserve;
 mod reverse_dependencies;
 pub mod versions;
@@ -0,0 +1,101 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::token::{CrateScope, EndpointScope};
+use crates_io::schema::emails;
+use crates_io::views::EncodableCratePermissions;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+
+#[derive(Deserialize)]
+struct PermissionsResponse {
+    permissions: EncodableCratePermissions,
+}
+
+async fn permissions(user: &impl RequestHelper, crate_name: &str) -> EncodableCratePermissions {
+    let url = format!("/api/v1/crates/{crate_name}/permissions");
+    user.get::<PermissionsResponse>(&url)
+        .await
+        .good()
+        .permissions
+}
+
+const ALL: EncodableCratePermissions = EncodableCratePermissions {
+    publish: true,
+    yank: true,
+    change_owners: true,
+};
+
+const NONE: EncodableCratePermissions = EncodableCratePermissions {
+    publish: false,
+    yank: false,
+    change_owners: false,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn owner_has_all_permissions() {
+    let (app, _, user, token) = TestApp::init().with_token();
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    assert_eq!(permissions(&user, "foo").await, ALL);
+    assert_eq!(permissions(&token, "foo").await, ALL);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn non_owner_has_no_permissions() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let other_user = app.db_new_user("bar");
+    assert_eq!(permissions(&other_user, "foo").await, NONE);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publishing_requires_verified_email() {
+    let (app, _, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+    app.db(|conn| {
+        CrateBuilder::new("foo", user_id).expect_build(conn);
+
+        diesel::update(emails::table.filter(emails::user_id.eq(user_id)))
+            .set(emails::verified.eq(false))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let expected = EncodableCratePermissions {
+        publish: false,
+        ..ALL
+    };
+    assert_eq!(permissions(&user, "foo").await, expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn token_scopes_are_considered() {
+    let crate_scopes = Some(vec![CrateScope::try_from("foo").unwrap()]);
+    let endpoint_scopes = Some(vec![EndpointScope::Yank]);
+    let (app, _, user, token) = TestApp::init().with_scoped_token(crate_scopes, endpoint_scopes);
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("bar", user.as_model().id).expect_build(conn);
+    });
+
+    let expected = EncodableCratePermissions { yank: true, ..NONE };
+    assert_eq!(permissions(&token, "foo").await, expected);
+    assert_eq!(permissions(&token, "bar").await, NONE);
+    assert_eq!(permissions(&user, "bar").await, ALL);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn anonymous_and_unknown_crate() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+
+    let response = anon.get::<()>("/api/v1/crates/foo/permissions").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let response = user.get::<()>("/api/v1/crates/unknown/permissions").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `unknown` does not exist"}]}"###);
+}
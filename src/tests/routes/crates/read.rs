@@ -77,6 +77,43 @@ async fn show_minimal() {
     });
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn show_owners() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_show_owners", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .keyword("kw1")
+            .expect_build(conn)
+    });
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo_show_owners?include=keywords,owners")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".crate.created_at" => "[datetime]",
+        ".crate.updated_at" => "[datetime]",
+        ".keywords[].created_at" => "[datetime]",
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn show_invalid_include() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| CrateBuilder::new("foo_show_invalid", user.id).expect_build(conn));
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo_show_invalid?include=versions,foo")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid component for ?include= (expected 'versions', 'keywords', 'categories', 'badges', 'downloads', 'owners', or 'full')"}]}"###);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_missing() {
     let (_, anon) = TestApp::init().empty();
@@ -0,0 +1,136 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::routes::crates::versions::yank_unyank::YankRequestHelper;
+use crate::util::{RequestHelper, Response, TestApp};
+use http::StatusCode;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/v1/crates/foo/recommended_version";
+
+async fn put_recommended_version(user: &impl RequestHelper, version: &str) -> Response<Value> {
+    let body = json!({ "version": version }).to_string();
+    user.put(URL, body).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recommended_version() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("foo", "2.0.0"))
+        .await
+        .good();
+
+    let json = anon.show_crate("foo").await;
+    assert_eq!(json.krate.recommended_version, None);
+
+    let response = put_recommended_version(&token, "1.0.0").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "recommended_version": "1.0.0" }));
+    app.run_pending_background_jobs().await;
+
+    let json = anon.show_crate("foo").await;
+    assert_eq!(json.krate.max_version, "2.0.0");
+    assert_eq!(json.krate.recommended_version.as_deref(), Some("1.0.0"));
+
+    let json = anon.search("q=foo").await;
+    assert_eq!(json.crates[0].recommended_version.as_deref(), Some("1.0.0"));
+
+    let crates = app.crates_from_index_head("foo");
+    let recommended = crates
+        .iter()
+        .map(|krate| (krate.vers.as_str(), krate.recommended))
+        .collect::<Vec<_>>();
+    assert_eq!(recommended, [("1.0.0", Some(true)), ("2.0.0", None)]);
+
+    // Yanking the recommended version hides the recommendation
+    token.yank("foo", "1.0.0").await.good();
+
+    let json = anon.show_crate("foo").await;
+    assert_eq!(json.krate.recommended_version, None);
+
+    let crates = app.crates_from_index_head("foo");
+    assert!(crates.iter().all(|krate| krate.recommended.is_none()));
+
+    token.unyank("foo", "1.0.0").await.good();
+
+    let json = anon.show_crate("foo").await;
+    assert_eq!(json.krate.recommended_version.as_deref(), Some("1.0.0"));
+
+    let response = token.delete::<Value>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs().await;
+
+    let json = anon.show_crate("foo").await;
+    assert_eq!(json.krate.recommended_version, None);
+
+    let crates = app.crates_from_index_head("foo");
+    assert!(crates.iter().all(|krate| krate.recommended.is_none()));
+
+    let response = token.delete::<Value>(URL).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate `foo` has no recommended version" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_recommended_versions() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .version(VersionBuilder::new("1.1.0").yanked(true))
+            .expect_build(conn);
+    });
+
+    let response = put_recommended_version(&user, "1.1.0").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "version `1.1.0` is yanked and can't be recommended" }] })
+    );
+
+    let response = put_recommended_version(&user, "2.0.0").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate `foo` does not have a version `2.0.0`" }] })
+    );
+
+    let response = user
+        .put::<Value>(
+            "/api/v1/crates/bar/recommended_version",
+            json!({ "version": "1.0.0" }).to_string(),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_change_recommended_version() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let response = put_recommended_version(&anon, "1.0.0").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let other_user = app.db_new_user("bar");
+    let response = put_recommended_version(&other_user, "1.0.0").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "only owners have permission to change the recommended version of a crate" }] })
+    );
+
+    let response = other_user.delete::<Value>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
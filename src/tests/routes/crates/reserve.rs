@@ -0,0 +1,179 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{RequestHelper, Response, TestApp};
+use chrono::{TimeDelta, Utc};
+use crates_io::models::CrateNameReservation;
+use crates_io::rate_limiter::LimitedAction;
+use crates_io::schema::crate_name_reservations;
+use crates_io::views::EncodableCrateNameReservation;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_json_snapshot;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+struct ReservationResponse {
+    reservation: EncodableCrateNameReservation,
+}
+
+trait ReserveRequestHelper {
+    async fn reserve(&self, krate_name: &str) -> Response<ReservationResponse>;
+}
+
+impl<T: RequestHelper> ReserveRequestHelper for T {
+    async fn reserve(&self, krate_name: &str) -> Response<ReservationResponse> {
+        let url = format!("/api/v1/crates/{krate_name}/reserve");
+        self.run(self.post_request(&url)).await
+    }
+}
+
+fn expire_reservations(app: &TestApp) {
+    app.db(|conn| {
+        let yesterday = Utc::now().naive_utc() - TimeDelta::try_days(1).unwrap();
+        diesel::update(crate_name_reservations::table)
+            .set(crate_name_reservations::expires_at.eq(yesterday))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reserve_crate_name() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    let response = user.reserve("foo-bar").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".reservation.created_at" => "[datetime]",
+        ".reservation.expires_at" => "[datetime]",
+    });
+
+    app.db(|conn| {
+        let reservation = CrateNameReservation::find_active(conn, "foo_bar")
+            .unwrap()
+            .unwrap();
+        assert_eq!(reservation.name, "foo-bar");
+        assert_eq!(reservation.user_id, user.as_model().id);
+        let duration = reservation.expires_at - reservation.created_at;
+        assert_eq!(duration.num_days(), 30);
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reserve_requires_authentication() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.reserve("foo").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reserve_unavailable_names() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_existing", user.as_model().id).expect_build(conn);
+    });
+
+    let response = user.reserve("foo-existing").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate name `foo-existing` is not available" }] })
+    );
+
+    let response = user.reserve("std").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate name `std` is not available" }] })
+    );
+
+    let response = user.reserve("1foo").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reserve_already_reserved_name() {
+    let (app, _, user) = TestApp::init().with_user();
+    let another_user = app.db_new_user("bar");
+
+    user.reserve("foo_bar").await.good();
+
+    let response = user.reserve("foo_bar").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "you have already reserved the crate name `foo_bar`" }] })
+    );
+
+    let response = another_user.reserve("foo-bar").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate name `foo-bar` is already reserved" }] })
+    );
+
+    // Expired reservations don't block anyone
+    expire_reservations(&app);
+    let json = another_user.reserve("foo-bar").await.good();
+    assert_eq!(json.reservation.name, "foo-bar");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reserve_too_many_names() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    for name in ["foo1", "foo2", "foo3"] {
+        user.reserve(name).await.good();
+    }
+
+    let response = user.reserve("foo4").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "you can not have more than 3 active crate name reservations" }] })
+    );
+
+    expire_reservations(&app);
+    user.reserve("foo4").await.good();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reserve_is_rate_limited() {
+    let (_, _, user) = TestApp::init()
+        .with_rate_limit(LimitedAction::ReserveName, Duration::from_secs(60), 1)
+        .with_user();
+
+    user.reserve("foo1").await.good();
+
+    user.reserve("foo2")
+        .await
+        .assert_rate_limited(LimitedAction::ReserveName);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reserved_name_blocks_publishing() {
+    let (app, _, user) = TestApp::full().with_user();
+    let another_user = app.db_new_user("bar");
+
+    user.reserve("foo_reserved").await.good();
+
+    let crate_to_publish = PublishBuilder::new("foo-reserved", "1.0.0");
+    let response = another_user.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate name `foo-reserved` has been reserved by another user" }] })
+    );
+
+    let crate_to_publish = PublishBuilder::new("foo_reserved", "1.0.0");
+    user.publish_crate(crate_to_publish).await.good();
+
+    app.db(|conn| {
+        let count: i64 = crate_name_reservations::table
+            .count()
+            .get_result(conn)
+            .unwrap();
+        assert_eq!(count, 0);
+    });
+}
@@ -2,6 +2,7 @@ use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
 use http::StatusCode;
 use insta::{assert_json_snapshot, assert_snapshot};
+use serde_json::Value;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn reverse_dependencies() {
@@ -162,6 +163,35 @@ async fn yanked_versions_not_included_in_reverse_dependencies() {
     });
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn yanked_versions_included_in_reverse_dependencies_on_request() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        CrateBuilder::new("c2", user.id)
+            .version("1.0.0")
+            .version(
+                VersionBuilder::new("2.0.0")
+                    .dependency(&c1, None)
+                    .yanked(true),
+            )
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/c1/reverse_dependencies";
+    let json: Value = anon.get(url).await.good();
+    assert_eq!(json["meta"]["total"], 0);
+
+    let json: Value = anon.get_with_query(url, "include_yanked=true").await.good();
+    assert_eq!(json["meta"]["total"], 1);
+    assert_eq!(json["versions"][0]["crate"], "c2");
+    assert_eq!(json["versions"][0]["num"], "2.0.0");
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn reverse_dependencies_includes_published_by_user_when_present() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -228,6 +258,51 @@ async fn reverse_dependencies_query_supports_u64_version_number_parts() {
     });
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn reverse_dependencies_pagination() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id).expect_build(conn);
+        for name in ["c2", "c3", "c4"] {
+            CrateBuilder::new(name, user.id)
+                .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+                .expect_build(conn);
+        }
+    });
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/c1/reverse_dependencies", "per_page=2")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["dependencies"].as_array().unwrap().len(), 2);
+    assert_json_snapshot!(json["meta"], @r###"
+    {
+      "next_page": "?per_page=2&page=2",
+      "prev_page": null,
+      "total": 3
+    }
+    "###);
+
+    let response = anon
+        .get_with_query::<()>(
+            "/api/v1/crates/c1/reverse_dependencies",
+            "per_page=2&meta=minimal&page=2",
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["dependencies"].as_array().unwrap().len(), 1);
+    assert_json_snapshot!(json["meta"], @r###"
+    {
+      "next_page": null,
+      "prev_page": "?per_page=2&meta=minimal&page=1"
+    }
+    "###);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_unknown_crate() {
     let (_, anon) = TestApp::init().empty();
@@ -0,0 +1,117 @@
+use crate::builders::PublishBuilder;
+use crate::util::insta::assert_json_snapshot;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/crates/foo/1.0.0/artifacts";
+
+/// A minimal gzip stream, which is enough for the upload validation.
+const ARTIFACT: &[u8] = &[0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_and_list() {
+    let (app, anon, _user, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let json: Value = anon.get(URL).await.good();
+    assert_json_snapshot!(json, @r###"
+    {
+      "artifacts": []
+    }
+    "###);
+
+    let target_url = format!("{URL}/x86_64-unknown-linux-gnu");
+    let json: Value = token.put(&target_url, ARTIFACT).await.good();
+    assert_json_snapshot!(json, {
+        ".artifact.created_at" => "[datetime]",
+    }, @r###"
+    {
+      "artifact": {
+        "checksum": "87bcdd44f7d4d2605074cefe3f0a5e41dfdab2700c027148a4e8273fb1d4284a",
+        "created_at": "[datetime]",
+        "size": 8,
+        "target": "x86_64-unknown-linux-gnu",
+        "url": "https://static.crates.io/artifacts/foo/1.0.0/foo-1.0.0-x86_64-unknown-linux-gnu.tar.gz"
+      }
+    }
+    "###);
+
+    let response = token
+        .put::<()>(&format!("{URL}/aarch64-apple-darwin"), ARTIFACT)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json: Value = anon.get(URL).await.good();
+    let targets = json["artifacts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|artifact| artifact["target"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        targets,
+        ["aarch64-apple-darwin", "x86_64-unknown-linux-gnu"]
+    );
+
+    let stored_files = app.stored_files().await;
+    assert!(stored_files
+        .contains(&"artifacts/foo/1.0.0/foo-1.0.0-x86_64-unknown-linux-gnu.tar.gz".to_string()));
+
+    // Artifacts can't be replaced
+    let response = token.put::<()>(&target_url, ARTIFACT).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` version `1.0.0` already has an artifact for target `x86_64-unknown-linux-gnu`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_uploads() {
+    let (_app, _anon, _user, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let response = token.put::<()>(&format!("{URL}/linux"), ARTIFACT).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid target triple `linux`"}]}"###);
+
+    let url = format!("{URL}/x86_64-unknown-linux-gnu");
+    let response = token.put::<()>(&url, b"not a tarball" as &[u8]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"artifacts must be gzip-compressed tarballs"}]}"###);
+
+    let url = "/api/v1/crates/foo/2.0.0/artifacts/x86_64-unknown-linux-gnu";
+    let response = token.put::<()>(url, ARTIFACT).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not have a version `2.0.0`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_upload_artifacts() {
+    let (app, anon, _user, token) = TestApp::full().with_token();
+    let other_user = app.db_new_user("other");
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let url = format!("{URL}/x86_64-unknown-linux-gnu");
+    let response = other_user.put::<()>(&url, ARTIFACT).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must already be an owner to upload artifacts"}]}"###);
+
+    let response = anon.put::<()>(&url, ARTIFACT).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let json: Value = anon.get(URL).await.good();
+    assert_eq!(json["artifacts"].as_array().unwrap().len(), 0);
+}
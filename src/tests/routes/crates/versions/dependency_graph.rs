@@ -0,0 +1,170 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::DependencyKind;
+use crates_io::schema::{dependencies, versions};
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+
+/// Creates the following graph, where all requirements are `>= 0` unless
+/// noted otherwise:
+///
+/// - `foo 1.0.0` -> `bar`, `baz` (build, optional), `qux` (dev)
+/// - `bar 1.0.0`, `bar 1.1.0` -> `foo` (cycle), `bar-sys` (`^2`, unresolved)
+/// - `baz 1.0.0` -> `bar`
+fn setup(app: &TestApp, user_id: i32) {
+    app.db(|conn| {
+        let foo_crate = CrateBuilder::new("foo", user_id).expect_build(conn);
+        let bar_crate = CrateBuilder::new("bar", user_id).expect_build(conn);
+        let baz_crate = CrateBuilder::new("baz", user_id).expect_build(conn);
+        let qux_crate = CrateBuilder::new("qux", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let bar_sys = CrateBuilder::new("bar-sys", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        VersionBuilder::new("1.0.0")
+            .dependency(&bar_crate, None)
+            .dependency(&baz_crate, None)
+            .dependency(&qux_crate, None)
+            .expect_build(foo_crate.id, user_id, conn);
+
+        VersionBuilder::new("1.0.0").expect_build(bar_crate.id, user_id, conn);
+        VersionBuilder::new("1.1.0")
+            .dependency(&foo_crate, None)
+            .dependency(&bar_sys, None)
+            .expect_build(bar_crate.id, user_id, conn);
+        VersionBuilder::new("1.2.0")
+            .yanked(true)
+            .expect_build(bar_crate.id, user_id, conn);
+
+        VersionBuilder::new("1.0.0")
+            .dependency(&bar_crate, None)
+            .expect_build(baz_crate.id, user_id, conn);
+
+        let dependency = |from: i32, to: i32| {
+            dependencies::table
+                .filter(
+                    dependencies::version_id.eq_any(
+                        versions::table
+                            .filter(versions::crate_id.eq(from))
+                            .select(versions::id),
+                    ),
+                )
+                .filter(dependencies::crate_id.eq(to))
+        };
+
+        diesel::update(dependency(foo_crate.id, baz_crate.id))
+            .set((
+                dependencies::kind.eq(DependencyKind::Build),
+                dependencies::optional.eq(true),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        diesel::update(dependency(foo_crate.id, qux_crate.id))
+            .set(dependencies::kind.eq(DependencyKind::Dev))
+            .execute(conn)
+            .unwrap();
+
+        diesel::update(dependency(bar_crate.id, bar_sys.id))
+            .set(dependencies::req.eq("^2"))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dependency_graph_json() {
+    let (app, anon, user) = TestApp::init().with_user();
+    setup(&app, user.as_model().id);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/dependency_graph")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dependency_graph_depth() {
+    let (app, anon, user) = TestApp::init().with_user();
+    setup(&app, user.as_model().id);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/dependency_graph?depth=1")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["dependency_graph"]["depth"], 1);
+    assert_eq!(
+        json["dependency_graph"]["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| node["id"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        vec!["foo@1.0.0", "bar@1.1.0", "baz@1.0.0"]
+    );
+    assert_eq!(json["dependency_graph"]["unresolved"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dependency_graph_dot() {
+    let (app, anon, user) = TestApp::init().with_user();
+    setup(&app, user.as_model().id);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/dependency_graph?format=dot")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/vnd.graphviz; charset=utf-8"
+    );
+    assert_snapshot!(response.text(), @r###"
+    digraph "foo@1.0.0" {
+      "foo@1.0.0";
+      "bar@1.1.0";
+      "baz@1.0.0";
+      "foo@1.0.0" -> "bar@1.1.0" [label=">= 0"];
+      "foo@1.0.0" -> "baz@1.0.0" [label=">= 0", style=dashed, color=gray];
+      "bar@1.1.0" -> "foo@1.0.0" [label=">= 0"];
+      "baz@1.0.0" -> "bar@1.1.0" [label=">= 0"];
+    }
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dependency_graph_invalid_params() {
+    let (app, anon, user) = TestApp::init().with_user();
+    setup(&app, user.as_model().id);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/dependency_graph?depth=0")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "`depth` must be an integer between 1 and 10" }] })
+    );
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/dependency_graph?format=svg")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "`format` must be either `json` or `dot`" }] })
+    );
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/9.9.9/dependency_graph")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate `foo` does not have a version `9.9.9`" }] })
+    );
+}
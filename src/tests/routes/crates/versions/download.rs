@@ -1,5 +1,10 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
-use crate::util::{RequestHelper, TestApp};
+use crate::util::{MockAnonymousUser, MockRequestExt, RequestHelper, TestApp};
+use crates_io::config::DownloadRateLimiterConfig;
+use crates_io::models::CdnHealthProbe;
+use http::{header, StatusCode};
+use insta::assert_json_snapshot;
+use std::time::Duration;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn test_redirects() {
@@ -32,6 +37,33 @@ async fn test_redirects() {
         .assert_redirect_ends_with("/crates/bar-download/bar-download-1.0.0.crate");
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn redirects_with_download_url_template() {
+    let template = "https://cdn.example.com/{lowerprefix}/{crate}/{version}.crate";
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.storage.download_url_template = Some(template.parse().unwrap());
+        })
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("Foo-download", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0+bar"))
+            .expect_build(conn);
+    });
+
+    anon.get::<()>("/api/v1/crates/Foo-download/1.0.0+bar/download")
+        .await
+        .assert_redirect_ends_with("https://cdn.example.com/fo/o-/Foo-download/1.0.0%2Bbar.crate");
+
+    // READMEs are not affected by the template
+    anon.get::<()>("/api/v1/crates/Foo-download/1.0.0+bar/readme")
+        .await
+        .assert_redirect_ends_with(
+            "https://static.crates.io/readmes/Foo-download/Foo-download-1.0.0%2Bbar.html",
+        );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn download_with_build_metadata() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -51,3 +83,125 @@ async fn download_with_build_metadata() {
         .await
         .assert_redirect_ends_with("/readmes/foo/foo-1.0.0%2Bbar.html");
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_rate_limit() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.download_rate_limiter = Some(DownloadRateLimiterConfig {
+                rate: Duration::from_secs(60),
+                burst: 2,
+                redis_url: None,
+            });
+        })
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    for _ in 0..2 {
+        let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+    }
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "60");
+    assert_json_snapshot!(response.json());
+
+    // Other endpoints are not affected by the download rate limit
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Requests from other IP addresses have their own bucket
+    let mut request = anon.get_request("/api/v1/crates/foo/1.0.0/download");
+    request.header("x-forwarded-for", "1.2.3.4");
+    let response = anon.run::<()>(request).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_with_expected_sha256() {
+    const CHECKSUM: &str = "c2a1f5ee1c1f1d3b0e2df1f3a1b7e0b3e1d8dbbf0f7ad2f1a3c4ed4d1e37a43d";
+
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0").checksum(CHECKSUM))
+            .expect_build(conn);
+    });
+
+    let url = format!("/api/v1/crates/foo/1.0.0/download?expected_sha256={CHECKSUM}");
+    anon.get::<()>(&url)
+        .await
+        .assert_redirect_ends_with("/crates/foo/foo-1.0.0.crate");
+
+    // The comparison is case-insensitive
+    let url = format!(
+        "/api/v1/crates/foo/1.0.0/download?expected_sha256={}",
+        CHECKSUM.to_ascii_uppercase()
+    );
+    anon.get::<()>(&url)
+        .await
+        .assert_redirect_ends_with("/crates/foo/foo-1.0.0.crate");
+
+    let other_checksum = "0".repeat(64);
+    let url = format!("/api/v1/crates/foo/1.0.0/download?expected_sha256={other_checksum}");
+    let response = anon.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_json_snapshot!(response.json());
+
+    let url = "/api/v1/crates/foo/1.0.0/download?expected_sha256=foo";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_json_snapshot!(response.json());
+
+    // Unlike regular downloads, missing versions are not redirected
+    let url = format!("/api/v1/crates/foo/2.0.0/download?expected_sha256={CHECKSUM}");
+    let response = anon.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn redirects_to_fallback_while_cdn_is_unavailable() {
+    let (app, anon) = TestApp::init()
+        .with_config(|config| {
+            config.cdn_fallback.probe_path = Some("crates/foo/foo-1.0.0.crate".to_string());
+            config.cdn_fallback.secondary_cdn_prefix = Some("fallback.example.com".to_string());
+            config.cdn_fallback.recovery_probes = 2;
+        })
+        .empty();
+
+    async fn location(anon: &MockAnonymousUser) -> String {
+        let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/download").await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+        let location = response.headers().get(header::LOCATION).unwrap();
+        location.to_str().unwrap().to_string()
+    }
+
+    let record_probe = |errors| {
+        app.db(|conn| CdnHealthProbe::record(conn, 10, errors).unwrap());
+        app.as_inner().cdn_circuit_breaker.invalidate();
+    };
+
+    let primary = "https://static.crates.io/crates/foo/foo-1.0.0.crate";
+    assert_eq!(location(&anon).await, primary);
+
+    record_probe(1);
+    assert_eq!(location(&anon).await, primary);
+
+    record_probe(8);
+    let fallback = "https://fallback.example.com/crates/foo/foo-1.0.0.crate";
+    assert_eq!(location(&anon).await, fallback);
+
+    // The CDN is only used again after enough healthy probes in a row
+    record_probe(0);
+    assert_eq!(location(&anon).await, fallback);
+
+    record_probe(0);
+    assert_eq!(location(&anon).await, primary);
+}
@@ -0,0 +1,61 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn preview_files() {
+    let (_app, anon, _user, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0")
+        .add_file("foo-1.0.0/LICENSE", "MIT License")
+        .add_file("foo-1.0.0/src/lib.rs", "pub fn foo() {}");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/file/LICENSE")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text(), "MIT License");
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/file/src/lib.rs")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text(), "pub fn foo() {}");
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/file/Cargo.toml")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.text().contains("name = \"foo\""));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn preview_missing_files() {
+    let (_app, anon, _user, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/file/README.md")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` version `1.0.0` has no file `README.md`"}]}"###);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/file/../foo-1.0.0/Cargo.toml")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/2.0.0/file/Cargo.toml")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/bar/1.0.0/file/Cargo.toml")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
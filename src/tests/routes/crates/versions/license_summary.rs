@@ -0,0 +1,145 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crates_io::models::DependencyKind;
+use crates_io::schema::dependencies;
+use diesel::prelude::*;
+use http::StatusCode;
+
+/// Creates `foo 1.0.0` with the following dependencies, where all
+/// requirements are `>= 0` unless noted otherwise:
+///
+/// - `bar` (`MIT`, the yanked `1.2.0` is `GPL-3.0`)
+/// - `baz` (`MIT OR Apache-2.0`, build, optional)
+/// - `qux` (`GPL-3.0`, dev)
+/// - `no-license` (license file only)
+/// - `bar-sys` (`^2`, unresolved)
+fn setup(app: &TestApp, user_id: i32) {
+    app.db(|conn| {
+        let foo_crate = CrateBuilder::new("foo", user_id).expect_build(conn);
+        let bar_crate = CrateBuilder::new("bar", user_id).expect_build(conn);
+        let baz_crate = CrateBuilder::new("baz", user_id).expect_build(conn);
+        let qux_crate = CrateBuilder::new("qux", user_id).expect_build(conn);
+        let no_license = CrateBuilder::new("no-license", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let bar_sys = CrateBuilder::new("bar-sys", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        let foo_version = VersionBuilder::new("1.0.0")
+            .dependency(&bar_crate, None)
+            .dependency(&baz_crate, None)
+            .dependency(&qux_crate, None)
+            .dependency(&no_license, None)
+            .dependency_with_req(&bar_sys, "^2")
+            .expect_build(foo_crate.id, user_id, conn);
+
+        VersionBuilder::new("1.0.0")
+            .license(Some("MIT"))
+            .expect_build(bar_crate.id, user_id, conn);
+        VersionBuilder::new("1.1.0")
+            .license(Some("MIT"))
+            .expect_build(bar_crate.id, user_id, conn);
+        VersionBuilder::new("1.2.0")
+            .license(Some("GPL-3.0"))
+            .yanked(true)
+            .expect_build(bar_crate.id, user_id, conn);
+        VersionBuilder::new("1.0.0")
+            .license(Some("MIT OR Apache-2.0"))
+            .expect_build(baz_crate.id, user_id, conn);
+        VersionBuilder::new("1.0.0")
+            .license(Some("GPL-3.0"))
+            .expect_build(qux_crate.id, user_id, conn);
+
+        let dependency = |crate_id: i32| {
+            dependencies::table
+                .filter(dependencies::version_id.eq(foo_version.id))
+                .filter(dependencies::crate_id.eq(crate_id))
+        };
+
+        diesel::update(dependency(baz_crate.id))
+            .set((
+                dependencies::kind.eq(DependencyKind::Build),
+                dependencies::optional.eq(true),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        diesel::update(dependency(qux_crate.id))
+            .set(dependencies::kind.eq(DependencyKind::Dev))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn license_summary() {
+    let (app, anon, user) = TestApp::init().with_user();
+    setup(&app, user.as_model().id);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.0.0/license_summary")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({
+            "license_summary": {
+                "licenses": [
+                    { "license": "MIT", "crates": ["bar@1.1.0"] },
+                    { "license": "MIT OR Apache-2.0", "crates": ["baz@1.0.0"] },
+                ],
+                "dependencies": [
+                    {
+                        "crate": "bar",
+                        "req": ">= 0",
+                        "kind": "normal",
+                        "optional": false,
+                        "version": "1.1.0",
+                        "license": "MIT",
+                    },
+                    {
+                        "crate": "bar-sys",
+                        "req": "^2",
+                        "kind": "normal",
+                        "optional": false,
+                        "version": null,
+                        "license": null,
+                    },
+                    {
+                        "crate": "no-license",
+                        "req": ">= 0",
+                        "kind": "normal",
+                        "optional": false,
+                        "version": "1.0.0",
+                        "license": null,
+                    },
+                    {
+                        "crate": "baz",
+                        "req": ">= 0",
+                        "kind": "build",
+                        "optional": true,
+                        "version": "1.0.0",
+                        "license": "MIT OR Apache-2.0",
+                    },
+                ],
+                "unknown": ["bar-sys", "no-license"],
+            }
+        })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn license_summary_unknown_version() {
+    let (app, anon, user) = TestApp::init().with_user();
+    setup(&app, user.as_model().id);
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/9.9.9/license_summary")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate `foo` does not have a version `9.9.9`" }] })
+    );
+}
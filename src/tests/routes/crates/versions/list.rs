@@ -198,6 +198,37 @@ async fn test_seek_based_pagination_semver_sorting() {
     assert_eq!(json.meta.total, 0);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn include_yanked() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_versions", user.id)
+            .version("1.0.0")
+            .version(VersionBuilder::new("1.1.0").yanked(true))
+            .version("1.2.0")
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo_versions/versions";
+    let json: AllVersions = anon.get(url).await.good();
+    assert_eq!(nums(&json.versions), vec!["1.2.0", "1.1.0", "1.0.0"]);
+
+    for query in ["include_yanked=false", "include_yanked=false&sort=date"] {
+        let json: AllVersions = anon.get_with_query(url, query).await.good();
+        let mut nums = nums(&json.versions);
+        nums.sort();
+        assert_eq!(nums, vec!["1.0.0", "1.2.0"]);
+    }
+
+    let (resp, _) = page_with_seek(&anon, &format!("{url}?include_yanked=false")).await;
+    assert_eq!(resp[0].meta.total, 2);
+
+    let response = anon.get_with_query::<()>(url, "include_yanked=maybe").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid `include_yanked` value: `maybe`, expected `true` or `false`"}]}"###);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn invalid_seek_parameter() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -1,6 +1,13 @@
+mod artifacts;
 mod authors;
 pub mod dependencies;
+mod dependency_graph;
 pub mod download;
+mod files;
+mod license_summary;
 mod list;
+mod provenance;
 mod read;
+mod release_notes;
+mod status;
 pub mod yank_unyank;
@@ -0,0 +1,73 @@
+use crate::builders::PublishBuilder;
+use crate::util::insta::{self, assert_json_snapshot};
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/crates/foo/1.0.0/provenance";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_token() {
+    let (_app, anon, user, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    // The version API only contains the redacted form
+    let json: Value = anon.get("/api/v1/crates/foo/1.0.0").await.good();
+    assert_json_snapshot!(json["version"]["published_via"], @r###"
+    {
+      "ci_service": null,
+      "kind": "api_token"
+    }
+    "###);
+
+    let json: Value = user.get(URL).await.good();
+    assert_json_snapshot!(json, {
+        ".provenance.api_token_id" => insta::any_id_redaction(),
+        ".provenance.created_at" => "[datetime]",
+    }, @r###"
+    {
+      "provenance": {
+        "api_token_id": "[id]",
+        "api_token_name": "bar",
+        "ci_service": null,
+        "created_at": "[datetime]",
+        "ip": "127.0.0.1",
+        "kind": "api_token",
+        "user_agent": "conduit-test"
+      }
+    }
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_cookie() {
+    let (_app, _anon, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    user.publish_crate(crate_to_publish).await.good();
+
+    let json: Value = user.get(URL).await.good();
+    assert_eq!(json["provenance"]["kind"], "session");
+    assert_eq!(json["provenance"]["api_token_id"], Value::Null);
+    assert_eq!(json["provenance"]["api_token_name"], Value::Null);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_see_the_provenance() {
+    let (app, anon, _user, token) = TestApp::full().with_token();
+    let other_user = app.db_new_user("other");
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let response = other_user.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only owners of this crate can view the provenance of its versions"}]}"###);
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+}
@@ -0,0 +1,103 @@
+use crate::builders::PublishBuilder;
+use crate::util::insta::assert_json_snapshot;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+const URL: &str = "/api/v1/crates/foo/1.0.0/release_notes";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn update_and_show() {
+    let (_app, anon, _user, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let json: Value = anon.get(URL).await.good();
+    assert_eq!(json, json!({ "release_notes": null }));
+
+    let body = json!({ "release_notes": "## Fixes\n\n- Fixed *everything*" });
+    let json: Value = token.put(URL, body.to_string()).await.good();
+    assert_json_snapshot!(json, {
+        ".release_notes.updated_at" => "[datetime]",
+    }, @r###"
+    {
+      "release_notes": {
+        "html": "<h2><a href=\"#fixes\" id=\"user-content-fixes\" rel=\"nofollow noopener noreferrer\"></a>Fixes</h2>\n<ul>\n<li>Fixed <em>everything</em></li>\n</ul>\n",
+        "markdown": "## Fixes\n\n- Fixed *everything*",
+        "updated_at": "[datetime]"
+      }
+    }
+    "###);
+
+    let shown: Value = anon.get(URL).await.good();
+    assert_eq!(shown, json);
+
+    let version: Value = anon.get("/api/v1/crates/foo/1.0.0").await.good();
+    assert_eq!(version["version"]["release_notes_path"], URL);
+
+    // Release notes can be replaced
+    let body = json!({ "release_notes": "Nothing to see here" });
+    let json: Value = token.put(URL, body.to_string()).await.good();
+    assert_eq!(json["release_notes"]["markdown"], "Nothing to see here");
+    assert_eq!(
+        json["release_notes"]["html"],
+        "<p>Nothing to see here</p>\n"
+    );
+
+    // Empty release notes remove the existing release notes
+    let body = json!({ "release_notes": "  " });
+    let json: Value = token.put(URL, body.to_string()).await.good();
+    assert_eq!(json, json!({ "release_notes": null }));
+
+    let json: Value = anon.get(URL).await.good();
+    assert_eq!(json, json!({ "release_notes": null }));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_updates() {
+    let (_app, _anon, _user, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let body = json!({ "release_notes": "a".repeat(64 * 1024 + 1) });
+    let response = token.put::<()>(URL, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"release notes must not be larger than 65536 bytes"}]}"###);
+
+    let url = "/api/v1/crates/foo/2.0.0/release_notes";
+    let body = json!({ "release_notes": "Fixes" });
+    let response = token.put::<()>(url, body.to_string()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not have a version `2.0.0`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_owners_can_update_release_notes() {
+    let (app, anon, _user, token) = TestApp::full().with_token();
+    let other_user = app.db_new_user("other");
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let body = json!({ "release_notes": "Fixes" }).to_string();
+
+    let response = other_user.put::<()>(URL, body.clone()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must already be an owner to change release notes"}]}"###);
+
+    let response = anon.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let json: Value = anon.get(URL).await.good();
+    assert_eq!(json, json!({ "release_notes": null }));
+}
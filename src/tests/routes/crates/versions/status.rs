@@ -0,0 +1,79 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn status_after_publish() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    // Publish without running the background jobs
+    let body = PublishBuilder::new("foo", "1.0.0").body();
+    let response = token.put::<()>("/api/v1/crates/new", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.0.0/status").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "status": { "crate": "foo", "version": "1.0.0", "index_sync": "pending" } })
+    );
+
+    // Waiting times out while the jobs are not run
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/foo/1.0.0/status", "wait=1s")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["status"]["index_sync"], "pending");
+
+    app.run_pending_background_jobs().await;
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/foo/1.0.0/status", "wait=30s")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["status"]["index_sync"], "synced");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn status_wait_ends_before_request_timeout() {
+    let (_, anon, _, token) = TestApp::full()
+        .with_config(|config| config.request_timeouts.read = Duration::from_secs(3))
+        .with_token();
+
+    let body = PublishBuilder::new("foo", "1.0.0").body();
+    let response = token.put::<()>("/api/v1/crates/new", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/foo/1.0.0/status", "wait=30s")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["status"]["index_sync"], "pending");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn status_invalid_params() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/crates/foo/1.0.0/status", "wait=5m")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "`wait` must be a number of seconds between 0 and 60, e.g. `30s`" }] })
+    );
+
+    let response = anon.get::<()>("/api/v1/crates/foo/9.9.9/status").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "crate `foo` does not have a version `9.9.9`" }] })
+    );
+}
@@ -209,6 +209,26 @@ mod auth {
         assert!(!is_yanked(&app));
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn token_user_expires_over_time() {
+        let expired_at = Utc::now() + Duration::days(7);
+
+        let (app, _, client) = prepare().await;
+        let client =
+            client.db_new_scoped_token("test-token", None, None, Some(expired_at.naive_utc()));
+
+        let response = client.yank(CRATE_NAME, CRATE_VERSION).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(is_yanked(&app));
+
+        app.advance_time(Duration::days(8));
+
+        let response = client.unyank(CRATE_NAME, CRATE_VERSION).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"authentication failed"}]}"###);
+        assert!(is_yanked(&app));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn token_user_with_correct_endpoint_scope() {
         let (app, _, client) = prepare().await;
@@ -356,3 +376,103 @@ mod auth {
         assert!(!is_yanked(&app));
     }
 }
+
+mod bulk {
+    use super::*;
+    use crate::util::{MockRequestExt, RequestHelper};
+    use crates_io::models::{VersionAction, VersionOwnerAction};
+    use insta::assert_snapshot;
+    use serde_json::Value;
+
+    async fn bulk_yank(client: &impl RequestHelper, body: Value) -> Response<Value> {
+        let mut request = client.post_request("/api/v1/crates/foo/yank_bulk");
+        *request.body_mut() = body.to_string().into();
+        request.header(http::header::CONTENT_TYPE, "application/json");
+
+        let response = client.run(request).await;
+        client.app().run_pending_background_jobs().await;
+        response
+    }
+
+    async fn prepare(client: &impl RequestHelper) {
+        for version in ["0.1.0", "0.2.0", "0.2.5", "0.3.0"] {
+            let pb = PublishBuilder::new("foo", version);
+            client.publish_crate(pb).await.good();
+        }
+    }
+
+    fn yanked_versions_in_index(app: &TestApp) -> Vec<String> {
+        app.crates_from_index_head("foo")
+            .into_iter()
+            .filter(|krate| krate.yanked == Some(true))
+            .map(|krate| krate.vers)
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn bulk_yank_matching_versions() {
+        let (app, _, _, token) = TestApp::full().with_token();
+        prepare(&token).await;
+
+        let body = json!({ "versions": ["<0.2.5", "=0.3.0"], "reason": "RUSTSEC-2024-0001" });
+        let response = bulk_yank(&token, body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.json(),
+            json!({ "yanked": ["0.1.0", "0.2.0", "0.3.0"] })
+        );
+        assert_eq!(yanked_versions_in_index(&app), ["0.1.0", "0.2.0", "0.3.0"]);
+
+        let yank_reasons = app.db(|conn| {
+            VersionOwnerAction::all(conn)
+                .unwrap()
+                .into_iter()
+                .filter(|action| action.action == VersionAction::Yank)
+                .map(|action| action.reason)
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(yank_reasons, vec![Some("RUSTSEC-2024-0001".to_string()); 3]);
+
+        // Already yanked versions are skipped
+        let body = json!({ "versions": ["<0.2.5"], "reason": "RUSTSEC-2024-0002" });
+        let response = bulk_yank(&token, body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.json(), json!({ "yanked": [] }));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn bulk_yank_by_a_non_owner_fails() {
+        let (app, _, user) = TestApp::full().with_user();
+        prepare(&user).await;
+
+        let another_user = app.db_new_user("bar");
+        let body = json!({ "versions": ["*"], "reason": "RUSTSEC-2024-0001" });
+        let response = bulk_yank(&another_user, body).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"must already be an owner to yank or unyank"}]}"###);
+        assert_eq!(yanked_versions_in_index(&app), Vec::<String>::new());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn bulk_yank_invalid_requests() {
+        let (app, _, user) = TestApp::full().with_user();
+        prepare(&user).await;
+
+        let body = json!({ "versions": ["*"], "reason": " " });
+        let response = bulk_yank(&user, body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"a reason is required to yank versions in bulk"}]}"###);
+
+        let body = json!({ "versions": [], "reason": "RUSTSEC-2024-0001" });
+        let response = bulk_yank(&user, body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"at least one version requirement is required"}]}"###);
+
+        let body = json!({ "versions": ["not a requirement"], "reason": "RUSTSEC-2024-0001" });
+        let response = bulk_yank(&user, body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid version requirement: `not a requirement`"}]}"###);
+
+        assert_eq!(yanked_versions_in_index(&app), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockRequestExt, RequestHelper, Response, TestApp};
+use http::{header, StatusCode};
+use serde_json::{json, Value};
+
+async fn validate(anon: &impl RequestHelper, body: Value) -> Response<Value> {
+    let mut request = anon.post_request("/api/v1/dependencies/validate");
+    *request.body_mut() = body.to_string().into();
+    request.header(header::CONTENT_TYPE, "application/json");
+    anon.run(request).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn validate_dependencies() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        let user_id = user.as_model().id;
+        CrateBuilder::new("foo", user_id)
+            .version("0.9.0")
+            .version("1.0.0")
+            .version("1.2.0")
+            .version(VersionBuilder::new("1.3.0").yanked(true))
+            .version("2.0.0-beta.1")
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", user_id)
+            .version(VersionBuilder::new("1.0.0").yanked(true))
+            .expect_build(conn);
+    });
+
+    let body = json!({
+        "dependencies": [
+            { "name": "foo", "req": "^1" },
+            { "name": "foo", "req": "=0.9.0" },
+            { "name": "foo", "req": ">=3" },
+            { "name": "foo", "req": "2.0.0-beta" },
+            { "name": "bar", "req": "1" },
+            { "name": "baz", "req": "*" },
+        ]
+    });
+
+    let response = validate(&anon, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({
+            "dependencies": [
+                { "name": "foo", "req": "^1", "exists": true, "matches": true, "latest_matching": "1.2.0" },
+                { "name": "foo", "req": "=0.9.0", "exists": true, "matches": true, "latest_matching": "0.9.0" },
+                { "name": "foo", "req": ">=3", "exists": true, "matches": false, "latest_matching": null },
+                { "name": "foo", "req": "2.0.0-beta", "exists": true, "matches": true, "latest_matching": "2.0.0-beta.1" },
+                { "name": "bar", "req": "1", "exists": true, "matches": false, "latest_matching": null },
+                { "name": "baz", "req": "*", "exists": false, "matches": false, "latest_matching": null },
+            ]
+        })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_requirement() {
+    let (_, anon) = TestApp::init().empty();
+
+    let body = json!({ "dependencies": [{ "name": "foo", "req": "not a version" }] });
+    let response = validate(&anon, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "invalid version requirement `not a version` for crate `foo`" }] })
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn too_many_dependencies() {
+    let (_, anon) = TestApp::init().empty();
+
+    let dependencies = (0..201)
+        .map(|i| json!({ "name": format!("foo{i}"), "req": "*" }))
+        .collect::<Vec<_>>();
+
+    let response = validate(&anon, json!({ "dependencies": dependencies })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "at most 200 dependencies can be validated at once" }] })
+    );
+}
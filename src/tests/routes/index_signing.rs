@@ -0,0 +1,52 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use chrono::{TimeZone, Utc};
+use crates_io::index_signing::IndexSigningKey;
+use http::StatusCode;
+use insta::assert_json_snapshot;
+
+fn signing_keys() -> Vec<IndexSigningKey> {
+    let expires_at = Utc.with_ymd_and_hms(2100, 1, 1, 0, 0, 0).unwrap();
+
+    vec![
+        IndexSigningKey::from_seed("new", &[1; 32], None).unwrap(),
+        IndexSigningKey::from_seed("old", &[2; 32], Some(expires_at)).unwrap(),
+    ]
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_keys() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.index_signing_keys = signing_keys())
+        .empty();
+
+    let response = anon.get::<()>("/.well-known/index-signing-keys.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn list_keys_without_configured_keys() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/.well-known/index-signing-keys.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "keys": [] }));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_stores_signatures() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| config.index_signing_keys = signing_keys())
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let expected_files = vec![
+        "crates/foo/foo-1.0.0.crate",
+        "index/3/f/foo",
+        "index/3/f/foo.sig",
+    ];
+    assert_eq!(app.stored_files().await, expected_files);
+}
@@ -0,0 +1,98 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crate::{CrateList, OkBool};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+async fn list(user: &MockCookieUser, query: &str) -> Vec<(String, i64, Option<i64>)> {
+    let json: CrateList = user.get_with_query("/api/v1/me/crates", query).await.good();
+    json.crates
+        .into_iter()
+        .map(|krate| (krate.name, krate.downloads, krate.recent_downloads))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn api_token_cannot_list_dashboard_crates() {
+    let (_, _, _, token) = TestApp::init().with_token();
+    token.get("/api/v1/me/crates").await.assert_forbidden();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn owned_and_followed_crates() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other_user = app.db_new_user("bar");
+
+    app.db(|conn| {
+        let user_id = user.as_model().id;
+        let other_user_id = other_user.as_model().id;
+
+        CrateBuilder::new("owned_a", user_id)
+            .downloads(1000)
+            .recent_downloads(10)
+            .expect_build(conn);
+        CrateBuilder::new("owned_b", user_id)
+            .downloads(100)
+            .recent_downloads(100)
+            .expect_build(conn);
+        CrateBuilder::new("followed", other_user_id)
+            .downloads(500)
+            .recent_downloads(50)
+            .expect_build(conn);
+        CrateBuilder::new("unrelated", other_user_id)
+            .downloads(5000)
+            .recent_downloads(500)
+            .expect_build(conn);
+    });
+
+    user.put::<OkBool>("/api/v1/crates/followed/follow", b"" as &[u8])
+        .await
+        .good();
+
+    let crates = list(&user, "").await;
+    assert_eq!(
+        crates,
+        vec![
+            ("owned_b".to_string(), 100, Some(100)),
+            ("followed".to_string(), 500, Some(50)),
+            ("owned_a".to_string(), 1000, Some(10)),
+        ]
+    );
+
+    let crates = list(&user, "sort=downloads").await;
+    let names = crates.iter().map(|(name, _, _)| name).collect::<Vec<_>>();
+    assert_eq!(names, ["owned_a", "followed", "owned_b"]);
+
+    let crates = list(&user, "sort=alpha&filter=owned").await;
+    let names = crates.iter().map(|(name, _, _)| name).collect::<Vec<_>>();
+    assert_eq!(names, ["owned_a", "owned_b"]);
+
+    let crates = list(&user, "filter=following").await;
+    let names = crates.iter().map(|(name, _, _)| name).collect::<Vec<_>>();
+    assert_eq!(names, ["followed"]);
+
+    let json: CrateList = user
+        .get_with_query("/api/v1/me/crates", "per_page=2")
+        .await
+        .good();
+    assert_eq!(json.crates.len(), 2);
+    assert_eq!(json.meta.total, 3);
+    assert_eq!(json.meta.next_page.as_deref(), Some("?per_page=2&page=2"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_parameters() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let response = user
+        .get_with_query::<()>("/api/v1/me/crates", "filter=foo")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid filter: `foo`"}]}"###);
+
+    let response = user
+        .get_with_query::<()>("/api/v1/me/crates", "sort=foo")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid sort: `foo`"}]}"###);
+}
@@ -0,0 +1,176 @@
+use crate::util::insta::assert_json_snapshot;
+use crate::util::{MockCookieUser, MockRequestExt, RequestHelper, Response, TestApp};
+use crates_io::schema::emails;
+use diesel::prelude::*;
+use http::{header, StatusCode};
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/me/emails";
+
+async fn add_email(user: &MockCookieUser, email: &str) -> Response<Value> {
+    let mut request = user.post_request(URL);
+    *request.body_mut() = json!({ "email": email }).to_string().into();
+    request.header(header::CONTENT_TYPE, "application/json");
+    user.run(request).await
+}
+
+fn email_id(app: &TestApp, email: &str) -> i32 {
+    app.db(|conn| {
+        emails::table
+            .filter(emails::email.eq(email))
+            .select(emails::id)
+            .first(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn manage_email_addresses() {
+    let (app, _anon, user) = TestApp::init().with_user();
+
+    let json: Value = user.get(URL).await.good();
+    assert_json_snapshot!(json, {
+        ".emails[].id" => "[id]",
+    }, @r###"
+    {
+      "emails": [
+        {
+          "email": "something@example.com",
+          "id": "[id]",
+          "primary": true,
+          "verification_email_sent": true,
+          "verified": true
+        }
+      ]
+    }
+    "###);
+
+    let response = add_email(&user, "other@example.com").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".email.id" => "[id]",
+    }, @r###"
+    {
+      "email": {
+        "email": "other@example.com",
+        "id": "[id]",
+        "primary": false,
+        "verification_email_sent": true,
+        "verified": false
+      }
+    }
+    "###);
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+
+    let other_id = email_id(&app, "other@example.com");
+
+    // Unverified addresses can't become the primary address
+    let url = format!("{URL}/{other_id}/primary");
+    let response = user.put::<()>(&url, &[] as &[u8]).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the email address must be verified before it can become the primary address"}]}"###);
+
+    let response = user
+        .put::<()>(&format!("{URL}/{other_id}/resend"), &[] as &[u8])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 2);
+
+    let token: String = app.db(|conn| {
+        emails::table
+            .find(other_id)
+            .select(emails::token)
+            .first(conn)
+            .unwrap()
+    });
+    let response = user
+        .put::<()>(&format!("/api/v1/confirm/{token}"), &[] as &[u8])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.put::<()>(&url, &[] as &[u8]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let me = user.show_me().await;
+    assert_eq!(me.user.email.as_deref(), Some("other@example.com"));
+    assert!(me.user.email_verified);
+
+    let json: Value = user.get(URL).await.good();
+    let emails = json["emails"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|email| (email["email"].as_str().unwrap(), email["primary"] == true))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        emails,
+        [
+            ("other@example.com", true),
+            ("something@example.com", false)
+        ]
+    );
+
+    // The primary address can't be removed
+    let response = user.delete::<()>(&format!("{URL}/{other_id}")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the primary email address can't be removed, choose another primary address first"}]}"###);
+
+    let old_id = email_id(&app, "something@example.com");
+    let response = user.delete::<()>(&format!("{URL}/{old_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json: Value = user.get(URL).await.good();
+    assert_eq!(json["emails"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn add_email_rejects_duplicates_and_too_many_addresses() {
+    let (_app, _anon, user) = TestApp::init().with_user();
+
+    let response = add_email(&user, "SOMETHING@example.com").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this email address was already added"}]}"###);
+
+    let response = add_email(&user, " ").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"empty email rejected"}]}"###);
+
+    for i in 1..5 {
+        let response = add_email(&user, &format!("foo{i}@example.com")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = add_email(&user, "foo5@example.com").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"an account can have at most 5 email addresses"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn email_addresses_of_other_users_are_not_found() {
+    let (app, _anon, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    let response = add_email(&other, "other@example.com").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let other_id = email_id(&app, "other@example.com");
+
+    let response = user.delete::<()>(&format!("{URL}/{other_id}")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = user
+        .put::<()>(&format!("{URL}/{other_id}/resend"), &[] as &[u8])
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn email_management_requires_cookie_auth() {
+    let (_app, anon, _user, token) = TestApp::init().with_token();
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = token.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,181 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use crates_io::rate_limiter::LimitedAction;
+use crates_io::schema::emails;
+use diesel::prelude::*;
+use flate2::read::GzDecoder;
+use http::{header, StatusCode};
+use insta::{assert_json_snapshot, assert_snapshot};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::time::Duration;
+
+/// Returns the download path of the data export from the sent emails.
+fn download_path(app: &TestApp) -> String {
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+
+    // Long lines are wrapped with soft line breaks by the quoted-printable encoding
+    let regex = Regex::new(r"/api/v1/me/export/([0-9a-f.]+)").unwrap();
+    let tokens = emails
+        .iter()
+        .map(|(_, email)| email.replace("=\r\n", ""))
+        .filter_map(|email| Some(regex.captures(&email)?[1].to_string()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(tokens.len(), 1);
+    format!("/api/v1/me/export/{}", tokens[0])
+}
+
+/// Returns the JSON files of the data export archive, by their path.
+fn unpack(bytes: &[u8]) -> BTreeMap<String, Value> {
+    let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+
+    let mut files = BTreeMap::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let path = entry.path().unwrap().to_string_lossy().into_owned();
+
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        files.insert(path, serde_json::from_str(&content).unwrap());
+    }
+    files
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_data_export() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_export", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let response = user.get::<OkBool>("/api/v1/me/export").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>(&download_path(&app)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/gzip");
+
+    let files = unpack(response.bytes());
+    assert_json_snapshot!(files, {
+        ".*.id" => "[id]",
+        ".*.github_id" => "[id]",
+        ".*[].owner_since" => "[datetime]",
+        ".*[].created_at" => "[datetime]",
+        ".*[].last_used_at" => "[datetime]",
+        ".*[].time" => "[datetime]",
+        ".*[].ip" => "[ip]",
+    }, @r###"
+    {
+      "crates-io-data-export/actions.json": [
+        {
+          "action": "publish",
+          "crate": "foo_export",
+          "ip": "[ip]",
+          "reason": null,
+          "time": "[datetime]",
+          "version": "1.0.0"
+        }
+      ],
+      "crates-io-data-export/crates.json": [
+        {
+          "email_notifications": true,
+          "name": "foo_export",
+          "owner_since": "[datetime]"
+        }
+      ],
+      "crates-io-data-export/emails.json": [
+        {
+          "email": "something@example.com",
+          "primary": true,
+          "verified": true
+        }
+      ],
+      "crates-io-data-export/profile.json": {
+        "avatar": null,
+        "email": "something@example.com",
+        "email_verified": true,
+        "follow_digest": true,
+        "github_id": "[id]",
+        "id": "[id]",
+        "locale": "en",
+        "login": "foo",
+        "name": null
+      },
+      "crates-io-data-export/tokens.json": [
+        {
+          "crate_scopes": null,
+          "created_at": "[datetime]",
+          "endpoint_scopes": null,
+          "expired_at": null,
+          "last_used_at": "[datetime]",
+          "name": "bar",
+          "revoked": false
+        }
+      ]
+    }
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_data_export_requires_verified_email() {
+    let (app, _anon, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        diesel::update(emails::table.filter(emails::user_id.eq(user_id)))
+            .set(emails::verified.eq(false))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = user.get::<()>("/api/v1/me/export").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"A verified email address is required to export your data. The download link is sent to this address."}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_data_export_rate_limit() {
+    let (app, anon, user) = TestApp::full()
+        .with_rate_limit(LimitedAction::DataExport, Duration::from_secs(60 * 60), 1)
+        .with_user();
+
+    let response = user.get::<OkBool>("/api/v1/me/export").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    user.get::<()>("/api/v1/me/export")
+        .await
+        .assert_rate_limited(LimitedAction::DataExport);
+
+    // Only the first request sends an email
+    app.run_pending_background_jobs().await;
+    let response = anon.get::<()>(&download_path(&app)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_data_export_requires_login() {
+    let (_app, anon, _user, token) = TestApp::init().with_token();
+
+    let response = anon.get::<()>("/api/v1/me/export").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = token.get::<()>("/api/v1/me/export").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_with_invalid_token() {
+    let (_app, anon, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+
+    let url = format!("/api/v1/me/export/{user_id}.abcdef.9999999999.0123456789abcdef");
+    let response = anon.get::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid or expired download link"}]}"###);
+}
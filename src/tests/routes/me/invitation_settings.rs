@@ -0,0 +1,154 @@
+use crate::builders::CrateBuilder;
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crates_io::models::{CrateOwner, OwnerKind};
+use crates_io::schema::crate_owners;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/me/invitation_settings";
+
+async fn pending_invitations(user: &MockCookieUser) -> usize {
+    let json: Value = user.get("/api/v1/me/crate_owner_invitations").await.good();
+    json["crate_owner_invitations"].as_array().unwrap().len()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn manage_invitation_settings() {
+    let (app, _anon, user) = TestApp::init().with_user();
+    app.db_new_user("spammer");
+
+    let json: Value = user.get(URL).await.good();
+    assert_eq!(
+        json,
+        json!({ "invitation_settings": { "block_unsolicited": false, "blocked_users": [] } })
+    );
+
+    let url = format!("{URL}/blocked_users/spammer");
+    let response = user.put::<()>(&url, &[] as &[u8]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = json!({ "block_unsolicited": true }).to_string();
+    let response = user.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json: Value = user.get(URL).await.good();
+    assert_eq!(json["invitation_settings"]["block_unsolicited"], true);
+    let blocked_users = json["invitation_settings"]["blocked_users"]
+        .as_array()
+        .unwrap();
+    assert_eq!(blocked_users.len(), 1);
+    assert_eq!(blocked_users[0]["login"], "spammer");
+
+    let response = user.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"user `spammer` is not blocked"}]}"###);
+
+    let json: Value = user.get(URL).await.good();
+    assert_eq!(json["invitation_settings"]["blocked_users"], json!([]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn block_unknown_user_or_self() {
+    let (_app, _anon, user) = TestApp::init().with_user();
+
+    let response = user
+        .put::<()>(&format!("{URL}/blocked_users/unknown"), &[] as &[u8])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"could not find user with login `unknown`"}]}"###);
+
+    let response = user
+        .put::<()>(&format!("{URL}/blocked_users/foo"), &[] as &[u8])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"you can't block yourself"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocked_users_cannot_invite() {
+    let (app, _anon, owner, token) = TestApp::init().with_token();
+    let invited = app.db_new_user("invited");
+    app.db(|conn| {
+        CrateBuilder::new("foo_blocked", owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("bar_blocked", owner.as_model().id).expect_build(conn);
+    });
+
+    token.add_named_owner("foo_blocked", "invited").await.good();
+    assert_eq!(pending_invitations(&invited).await, 1);
+
+    // Blocking the inviter removes their pending invitations
+    let url = format!("{URL}/blocked_users/foo");
+    let response = invited.put::<()>(&url, &[] as &[u8]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(pending_invitations(&invited).await, 0);
+
+    let response = token.add_named_owner("bar_blocked", "invited").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"user invited does not accept ownership invitations from you"}]}"###);
+    assert_eq!(pending_invitations(&invited).await, 0);
+
+    let response = invited.delete::<()>(&url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    token.add_named_owner("bar_blocked", "invited").await.good();
+    assert_eq!(pending_invitations(&invited).await, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unsolicited_invitations_can_be_blocked() {
+    let (app, _anon, owner, token) = TestApp::init().with_token();
+    let co_owner = app.db_new_user("co_owner");
+    let co_owner_token = co_owner.db_new_token("co_owner_token");
+    let invited = app.db_new_user("invited");
+    app.db(|conn| {
+        CrateBuilder::new("foo_unsolicited", owner.as_model().id).expect_build(conn);
+        CrateBuilder::new("bar_unsolicited", co_owner.as_model().id).expect_build(conn);
+
+        let krate = CrateBuilder::new("baz_unsolicited", co_owner.as_model().id).expect_build(conn);
+        diesel::insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: krate.id,
+                owner_id: invited.as_model().id,
+                created_by: co_owner.as_model().id,
+                owner_kind: OwnerKind::User,
+                email_notifications: true,
+            })
+            .execute(conn)
+            .unwrap();
+    });
+
+    let body = json!({ "block_unsolicited": true }).to_string();
+    let response = invited.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The owner of `foo_unsolicited` does not own a crate together with the user
+    let response = token.add_named_owner("foo_unsolicited", "invited").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(pending_invitations(&invited).await, 0);
+
+    // The user owns `baz_unsolicited` together with the owner of `bar_unsolicited`
+    co_owner_token
+        .add_named_owner("bar_unsolicited", "invited")
+        .await
+        .good();
+    assert_eq!(pending_invitations(&invited).await, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invitation_settings_require_cookie_auth() {
+    let (_app, anon, _user, token) = TestApp::init().with_token();
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = token.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let body = json!({ "block_unsolicited": true }).to_string();
+    let response = token.put::<()>(URL, body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
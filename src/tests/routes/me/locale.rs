@@ -0,0 +1,37 @@
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_locale() {
+    let (_, _, user) = TestApp::init().with_user();
+    assert_eq!(user.show_me().await.user.locale, "en");
+
+    let body = json!({ "locale": "de" }).to_string();
+    let response = user.put::<()>("/api/v1/me/locale", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "ok": true }));
+
+    assert_eq!(user.show_me().await.user.locale, "de");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_unsupported_locale() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let body = json!({ "locale": "xx" }).to_string();
+    let response = user.put::<()>("/api/v1/me/locale", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"unsupported locale `xx`, expected one of: en, de, fr"}]}"###);
+
+    assert_eq!(user.show_me().await.user.locale, "en");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_locale_requires_auth() {
+    let (_, anon) = TestApp::init().empty();
+
+    let body = json!({ "locale": "de" }).to_string();
+    let response = anon.put::<()>("/api/v1/me/locale", body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -1,4 +1,10 @@
+mod crates;
 mod email_notifications;
+mod emails;
+mod export;
 pub mod get;
+mod invitation_settings;
+mod locale;
 pub mod tokens;
 mod updates;
+mod usage;
@@ -5,6 +5,7 @@ use crates_io::models::ApiToken;
 use diesel::prelude::*;
 use googletest::prelude::*;
 use http::StatusCode;
+use regex::Regex;
 use serde_json::Value;
 
 static NEW_BAR: &[u8] = br#"{ "api_token": { "name": "bar" } }"#;
@@ -270,3 +271,47 @@ async fn create_token_with_expiry_date() {
         ".api_token.token" => insta::api_token_redaction(),
     });
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn create_token_sends_notification_email() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    let json = json!({
+        "api_token": {
+            "name": "bar",
+            "crate_scopes": ["tokio", "tokio-*"],
+            "endpoint_scopes": ["publish-update"],
+        }
+    });
+
+    let response = user
+        .put::<()>("/api/v1/me/tokens", serde_json::to_vec(&json).unwrap())
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_that!(emails, len(eq(1)));
+
+    // Long lines are wrapped with soft line breaks by the quoted-printable encoding
+    let email = emails[0].1.replace("=\r\n", "");
+    assert!(email.contains("Subject: New API token created"));
+    assert!(email.contains("Name: bar"));
+    assert!(email.contains("Crates: tokio, tokio-*"));
+    assert!(email.contains("Endpoints: publish-update"));
+    assert!(email.contains("Browser/device: conduit-test"));
+
+    let regex = Regex::new(r"https://crates.io/tokens/revoke/([0-9a-f.]+)").unwrap();
+    let revoke_token = &regex.captures(&email).unwrap()[1];
+
+    let url = format!("/api/v1/tokens/revoke/{revoke_token}");
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let tokens: Vec<ApiToken> = app.db(|conn| {
+        assert_ok!(ApiToken::belonging_to(user.as_model())
+            .select(ApiToken::as_select())
+            .load(conn))
+    });
+    assert_that!(tokens, len(eq(1)));
+    assert!(tokens[0].revoked);
+}
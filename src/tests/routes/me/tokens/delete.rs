@@ -1,7 +1,9 @@
 use crate::util::{RequestHelper, TestApp};
 use crates_io::models::ApiToken;
 use crates_io::schema::api_tokens;
+use crates_io::util::token_revocation;
 use diesel::prelude::*;
+use http::StatusCode;
 
 #[derive(Deserialize)]
 pub struct RevokedResponse {}
@@ -72,3 +74,47 @@ async fn revoke_token_success() {
         assert_eq!(count, Ok(0));
     });
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn revoke_token_with_link() {
+    let (app, anon, user, token) = TestApp::init().with_token();
+    let user_id = user.as_model().id;
+    let token_id = token.as_model().id;
+    let key = app.as_inner().session_key();
+
+    let is_revoked = || {
+        app.db(|conn| {
+            api_tokens::table
+                .find(token_id)
+                .select(api_tokens::revoked)
+                .get_result::<bool>(conn)
+                .unwrap()
+        })
+    };
+
+    // Links can't be altered to revoke the tokens of other users
+    let other_user_id = app.db_new_user("baz").as_model().id;
+    let other_link = token_revocation::generate_token(key, other_user_id, token_id);
+    let response = anon
+        .put::<()>(&format!("/api/v1/tokens/revoke/{other_link}"), "")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!is_revoked());
+
+    let response = anon
+        .put::<()>("/api/v1/tokens/revoke/1.2.invalid", "")
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "invalid revocation token" }] })
+    );
+    assert!(!is_revoked());
+
+    let link = token_revocation::generate_token(key, user_id, token_id);
+    let response = anon
+        .put::<()>(&format!("/api/v1/tokens/revoke/{link}"), "")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(is_revoked());
+}
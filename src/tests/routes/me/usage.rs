@@ -0,0 +1,63 @@
+use crate::util::{RequestHelper, TestApp};
+use http::StatusCode;
+use insta::assert_snapshot;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/me/usage";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage_counts_requests_per_user_and_token() {
+    let (app, _anon, user, token) = TestApp::init().with_token();
+
+    for _ in 0..2 {
+        let response = token.get::<()>(URL).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let json: Value = user.get(URL).await.good();
+    let usage = &json["usage"];
+    assert_eq!(usage["requests"], 3);
+    assert_eq!(usage["quota"], Value::Null);
+    assert_eq!(
+        usage["tokens"],
+        json!([{
+            "id": token.as_model().id,
+            "name": "bar",
+            "requests": 2,
+            "quota": null,
+        }])
+    );
+
+    // The counts stay the same after they were written to the database
+    app.db(|conn| app.as_inner().api_usage.flush(conn));
+
+    let json: Value = user.get(URL).await.good();
+    assert_eq!(json["usage"]["requests"], 4);
+    assert_eq!(json["usage"]["tokens"][0]["requests"], 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quota_warning_header() {
+    let (_app, _anon, _user, token) = TestApp::init()
+        .with_config(|config| config.api_quotas.token_monthly_requests = Some(1))
+        .with_token();
+
+    let response = token.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("x-api-quota-warning"));
+
+    let response = token.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(
+        response.headers()["x-api-quota-warning"].to_str().unwrap(),
+        @"the monthly quota of 1 requests per API token is exceeded"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn usage_requires_authentication() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>(URL).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
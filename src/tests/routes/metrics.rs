@@ -1,11 +1,17 @@
+use crate::builders::CrateBuilder;
 use crate::util::{MockAnonymousUser, MockRequestExt, Response};
 use crate::{RequestHelper, TestApp};
+use chrono::{Duration, Utc};
+use crates_io::config::{MetricsToken, TlsConfig};
+use crates_io::tls::VerifiedClientCertificate;
 use http::StatusCode;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn metrics_endpoint_works() {
     let (_, anon) = TestApp::init()
-        .with_config(|config| config.metrics_authorization_token = Some("foobar".into()))
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![MetricsToken::new("foobar", None)]
+        })
         .empty();
 
     let resp = request_metrics(&anon, "service", Some("foobar")).await;
@@ -21,7 +27,9 @@ async fn metrics_endpoint_works() {
 #[tokio::test(flavor = "multi_thread")]
 async fn metrics_endpoint_wrong_auth() {
     let (_, anon) = TestApp::init()
-        .with_config(|config| config.metrics_authorization_token = Some("secret".into()))
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![MetricsToken::new("secret", None)]
+        })
         .empty();
 
     // Wrong secret
@@ -50,7 +58,7 @@ async fn metrics_endpoint_wrong_auth() {
 #[tokio::test(flavor = "multi_thread")]
 async fn metrics_endpoint_auth_disabled() {
     let (_, anon) = TestApp::init()
-        .with_config(|config| config.metrics_authorization_token = None)
+        .with_config(|config| config.metrics_authorization_tokens = vec![])
         .empty();
 
     // Wrong secret
@@ -76,6 +84,108 @@ async fn metrics_endpoint_auth_disabled() {
     assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics_endpoint_token_rotation() {
+    let expired_at = Utc::now() - Duration::hours(1);
+    let expires_at = Utc::now() + Duration::hours(1);
+
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![
+                MetricsToken::new("new", None),
+                MetricsToken::new("old", Some(expires_at)),
+                MetricsToken::new("expired", Some(expired_at)),
+            ]
+        })
+        .empty();
+
+    let resp = request_metrics(&anon, "service", Some("new")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = request_metrics(&anon, "service", Some("old")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = request_metrics(&anon, "service", Some("expired")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics_endpoint_client_certificate() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![];
+            config.tls = Some(TlsConfig {
+                cert_path: "server.crt".into(),
+                key_path: "server.key".into(),
+                client_ca_path: Some("client-ca.crt".into()),
+            });
+        })
+        .empty();
+
+    let mut req = anon.get_request("/api/private/metrics/service");
+    req.extensions_mut().insert(VerifiedClientCertificate);
+    let resp: Response<()> = anon.run(req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Without a verified client certificate a token is required
+
+    let resp = request_metrics(&anon, "service", None).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = request_metrics(&anon, "service", Some("foobar")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics_endpoint_counts_invitations() {
+    let (app, anon, user, token) = TestApp::init()
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![MetricsToken::new("foobar", None)]
+        })
+        .with_token();
+
+    app.db(|conn| CrateBuilder::new("foo", user.as_model().id).expect_build(conn));
+    app.db_new_user("bar");
+    token.add_named_owner("foo", "bar").await.good();
+
+    let resp = request_metrics(&anon, "service", Some("foobar")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let text = resp.text();
+    assert!(text.contains(r#"cratesio_service_crate_owner_invitations{state="pending"} 1"#));
+    assert!(text.contains(r#"cratesio_service_crate_owner_invitations{state="expired"} 0"#));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics_endpoint_counts_lookup_cache_hits() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.metrics_authorization_tokens = vec![MetricsToken::new("foobar", None)]
+        })
+        .with_user();
+
+    app.db(|conn| CrateBuilder::new("foo_bar", user.as_model().id).expect_build(conn));
+
+    // The second request uses a different spelling of the crate name, which
+    // is still answered from the cache.
+    anon.get::<serde_json::Value>("/api/v1/crates/foo_bar/downloads")
+        .await
+        .good();
+    anon.get::<serde_json::Value>("/api/v1/crates/Foo-Bar/downloads")
+        .await
+        .good();
+    let resp = anon.get::<()>("/api/v1/crates/missing/downloads").await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = request_metrics(&anon, "instance", Some("foobar")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let text = resp.text();
+    assert!(text.contains(r#"cratesio_instance_lookup_cache_hits{cache="crate_id"} 1"#));
+    assert!(text.contains(r#"cratesio_instance_lookup_cache_misses{cache="crate_id"} 2"#));
+    assert!(text.contains(r#"cratesio_instance_lookup_cache_entries{cache="crate_id"} 1"#));
+}
+
 async fn request_metrics(
     anon: &MockAnonymousUser,
     kind: &str,
@@ -11,13 +11,19 @@
 //! - testing output serialization of a route
 //! - testing query parameter combinations of a route
 
+pub mod build_info;
 pub mod categories;
 pub mod category_slugs;
 pub mod crates;
+pub mod dependencies;
+pub mod index_signing;
 pub mod keywords;
 pub mod me;
 pub mod metrics;
 mod private;
+mod readme_images;
 pub mod session;
+pub mod sitemap;
+pub mod stats;
 pub mod summary;
 pub mod users;
@@ -0,0 +1,741 @@
+//! Tests for the `/api/private/admin/` endpoints
+
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::new_category;
+use crate::util::insta::{self, assert_json_snapshot};
+use crate::util::{MockCookieUser, RequestHelper, Response, TestApp};
+use crates_io::schema::{
+    background_job_progress, background_jobs, crates, download_spikes, typosquat_flags, users,
+};
+use crates_io::worker::jobs;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use googletest::prelude::*;
+use http::{Method, StatusCode};
+use insta::assert_snapshot;
+use serde_json::{json, Value};
+
+fn make_admin(app: &TestApp, user: &MockCookieUser) {
+    app.db(|conn| {
+        diesel::update(user.as_model())
+            .set(users::is_admin.eq(true))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+async fn resync(user: &impl RequestHelper, crate_name: &str, query: &str) -> Response<()> {
+    let url = format!("/api/private/admin/crates/{crate_name}/resync{query}");
+    user.run(user.post_request(&url)).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resync_requires_admin() {
+    let (app, anon, user) = TestApp::full().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let response = resync(&anon, "foo", "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"this action requires authentication"}]}"###);
+
+    let response = resync(&user, "foo", "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can resync crates"}]}"###);
+
+    app.run_pending_background_jobs().await;
+    assert_that!(app.stored_files().await, empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resync_unknown_crate() {
+    let (app, _, user) = TestApp::full().with_user();
+    make_admin(&app, &user);
+
+    let response = resync(&user, "foo", "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate `foo` does not exist"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resync_index() {
+    let (app, _, user) = TestApp::full().with_user();
+    make_admin(&app, &user);
+
+    // `CrateBuilder` does not sync the crate to the index
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+    });
+    assert_that!(app.stored_files().await, empty());
+
+    let response = resync(&user, "foo", "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"ok":true}"###);
+
+    app.run_pending_background_jobs().await;
+    assert_that!(app.stored_files().await, eq(["index/3/f/foo"]));
+
+    let versions = app.crates_from_index_head("foo");
+    let versions = versions.iter().map(|c| c.vers.as_str()).collect::<Vec<_>>();
+    assert_eq!(versions, ["1.0.0", "1.1.0"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resync_restores_missing_readme() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    make_admin(&app, &user);
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").readme("# foo");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let storage = &app.as_inner().storage;
+    storage.delete_all_readmes("foo").await.unwrap();
+    let expected_files = vec!["crates/foo/foo-1.0.0.crate", "index/3/f/foo"];
+    assert_that!(app.stored_files().await, eq(expected_files));
+
+    let response = resync(&user, "foo", "?check_files=true").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.run_pending_background_jobs().await;
+
+    let expected_files = vec![
+        "crates/foo/foo-1.0.0.crate",
+        "index/3/f/foo",
+        "readmes/foo/foo-1.0.0.html",
+    ];
+    assert_that!(app.stored_files().await, eq(expected_files));
+}
+
+async fn admin_post(user: &impl RequestHelper, url: &str, body: Value) -> Response<()> {
+    let mut request = user.request_builder(Method::POST, url);
+    *request.body_mut() = body.to_string().into();
+    user.run(request).await
+}
+
+async fn crate_categories(anon: &impl RequestHelper, crate_name: &str) -> Value {
+    let url = format!("/api/v1/crates/{crate_name}");
+    let json: Value = anon.get(&url).await.good();
+    json["crate"]["categories"].clone()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn category_management_requires_admin() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+    });
+
+    let url = "/api/private/admin/categories/cat1/synonyms";
+    let response = admin_post(&user, url, json!({ "synonym": "old-cat" })).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can manage categories"}]}"###);
+
+    let url = "/api/private/admin/categories/cat1/migrations";
+    let response = admin_post(&user, url, json!({ "target": "cat2" })).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn category_synonyms() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+    make_admin(&app, &user);
+
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+    });
+
+    let url = "/api/private/admin/categories/cat1/synonyms";
+    let response = admin_post(&user, url, json!({ "synonym": "Old-Cat" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), { ".synonym.created_at" => "[datetime]" }, @r###"
+    {
+      "synonym": {
+        "category_id": 1,
+        "created_at": "[datetime]",
+        "synonym": "old-cat"
+      }
+    }
+    "###);
+
+    let response = admin_post(&user, url, json!({ "synonym": "old-cat" })).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the synonym `old-cat` already exists"}]}"###);
+
+    let response = admin_post(&user, url, json!({ "synonym": "cat1" })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"`cat1` is already the slug of a category"}]}"###);
+
+    let url = "/api/private/admin/categories/unknown/synonyms";
+    let response = admin_post(&user, url, json!({ "synonym": "foo" })).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"category `unknown` does not exist"}]}"###);
+
+    // Crates that are published with the synonym are added to the category
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").category("old-cat");
+    token.publish_crate(crate_to_publish).await.good();
+    assert_eq!(crate_categories(&anon, "foo").await, json!(["cat1"]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn category_migrations() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+    make_admin(&app, &user);
+
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+        new_category("Category 2", "cat2", "Category 2 crates")
+            .create_or_update(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo", "1.0.0").category("cat1");
+    token.publish_crate(crate_to_publish).await.good();
+    let crate_to_publish = PublishBuilder::new("bar", "1.0.0")
+        .category("cat1")
+        .category("cat2");
+    token.publish_crate(crate_to_publish).await.good();
+
+    let url = "/api/private/admin/categories/cat1/migrations";
+    let response = admin_post(&user, url, json!({ "target": "cat1" })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"a category cannot be migrated to itself"}]}"###);
+
+    let response = admin_post(&user, url, json!({ "target": "cat2" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".migration.id" => insta::any_id_redaction(),
+        ".migration.created_at" => "[datetime]",
+    }, @r###"
+    {
+      "migration": {
+        "crates_migrated": null,
+        "created_at": "[datetime]",
+        "finished_at": null,
+        "id": "[id]",
+        "source_category_id": 1,
+        "target_category_id": 2
+      }
+    }
+    "###);
+
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(crate_categories(&anon, "foo").await, json!(["cat2"]));
+    assert_eq!(crate_categories(&anon, "bar").await, json!(["cat2"]));
+
+    let json: Value = anon.get("/api/v1/categories/cat1").await.good();
+    assert_eq!(json["category"]["crates_cnt"], 0);
+    let json: Value = anon.get("/api/v1/categories/cat2").await.good();
+    assert_eq!(json["category"]["crates_cnt"], 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn job_status() {
+    let (app, anon, user) = TestApp::full().with_user();
+
+    let response = anon.get::<()>("/api/private/admin/jobs").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.get::<()>("/api/private/admin/jobs").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can view the job queue"}]}"###);
+
+    make_admin(&app, &user);
+
+    app.db(|conn| {
+        jobs::DumpDb::new("postgres://localhost/db", "db-dump.tar.gz")
+            .enqueue(conn)
+            .unwrap();
+        let job_id = jobs::SyncAdmins.enqueue(conn).unwrap();
+        jobs::SyncAdmins.enqueue(conn).unwrap();
+
+        diesel::insert_into(background_job_progress::table)
+            .values((
+                background_job_progress::job_id.eq(job_id),
+                background_job_progress::percent.eq(40),
+                background_job_progress::message.eq("Syncing admins"),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = user.get::<()>("/api/private/admin/jobs").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".progress[].id" => insta::any_id_redaction(),
+        ".progress[].created_at" => "[datetime]",
+        ".progress[].updated_at" => "[datetime]",
+    }, @r###"
+    {
+      "progress": [
+        {
+          "created_at": "[datetime]",
+          "id": "[id]",
+          "job_type": "sync_admins",
+          "message": "Syncing admins",
+          "percent": 40,
+          "retries": 0,
+          "updated_at": "[datetime]"
+        }
+      ],
+      "queue": [
+        {
+          "count": 1,
+          "failed": 0,
+          "job_type": "dump_db"
+        },
+        {
+          "count": 2,
+          "failed": 0,
+          "job_type": "sync_admins"
+        }
+      ]
+    }
+    "###);
+    // The jobs are not supposed to actually run in this test
+    app.db(|conn| {
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+fn sent_emails(app: &TestApp) -> Vec<String> {
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    emails.into_iter().map(|(_, email)| email).collect()
+}
+
+async fn download_status(anon: &impl RequestHelper, crate_name: &str, version: &str) -> StatusCode {
+    let url = format!("/api/v1/crates/{crate_name}/{version}/download");
+    anon.get::<()>(&url).await.status()
+}
+
+fn yanked_in_index(app: &TestApp, crate_name: &str) -> Vec<(String, bool)> {
+    app.crates_from_index_head(crate_name)
+        .into_iter()
+        .map(|c| (c.vers, c.yanked.unwrap_or_default()))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn crate_overview() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await
+        .good();
+
+    let url = "/api/private/admin/crates/foo/overview";
+    let response = anon.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = user.get::<()>(url).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can view crate overviews"}]}"###);
+
+    make_admin(&app, &user);
+
+    let response = user
+        .get::<()>("/api/private/admin/crates/bar/overview")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    app.db(|conn| {
+        let crate_id: i32 = crates::table
+            .filter(crates::name.eq("foo"))
+            .select(crates::id)
+            .first(conn)
+            .unwrap();
+
+        diesel::insert_into(download_spikes::table)
+            .values((
+                download_spikes::crate_id.eq(crate_id),
+                download_spikes::date.eq(chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()),
+                download_spikes::downloads.eq(50_000),
+                download_spikes::baseline.eq(100),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        diesel::insert_into(typosquat_flags::table)
+            .values((
+                typosquat_flags::crate_id.eq(crate_id),
+                typosquat_flags::squatted_crate.eq("fooo"),
+                typosquat_flags::description.eq("omitted character"),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let quarantine_url = "/api/private/admin/crates/foo/1.1.0/quarantine";
+    let response = admin_post(
+        &user,
+        quarantine_url,
+        json!({ "reason": "Contains malware" }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json: Value = user.get(url).await.good();
+    assert_eq!(json["crate"]["name"], "foo");
+    assert_eq!(json["owners"].as_array().unwrap().len(), 1);
+    assert_eq!(json["owners"][0]["login"], "foo");
+
+    let publishes = json["publishes"].as_array().unwrap();
+    let versions = publishes.iter().map(|p| &p["num"]).collect::<Vec<_>>();
+    assert_eq!(versions, vec!["1.1.0", "1.0.0"]);
+    assert_eq!(publishes[0]["published_by"], "foo");
+
+    assert_eq!(json["download_spikes"][0]["date"], "2024-05-01");
+    assert_eq!(json["download_spikes"][0]["downloads"], 50_000);
+    assert_eq!(json["download_spikes"][0]["baseline"], 100);
+
+    assert_eq!(json["typosquat_flags"][0]["squatted_crate"], "fooo");
+
+    assert_eq!(json["quarantines"].as_array().unwrap().len(), 1);
+    assert_eq!(json["quarantines"][0]["version"], "1.1.0");
+
+    assert_eq!(json["owner_actions"][0]["action"], "add");
+    assert_eq!(json["owner_actions"][0]["via"], "publish");
+
+    let version_actions = json["version_actions"].as_array().unwrap();
+    assert_eq!(version_actions.len(), 2);
+    assert_eq!(version_actions[0]["version"], "1.1.0");
+    assert_eq!(version_actions[0]["action"], "publish");
+    assert_eq!(version_actions[0]["user"], "foo");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quarantine_requires_admin() {
+    let (app, anon, user) = TestApp::full().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let url = "/api/private/admin/crates/foo/1.0.0/quarantine";
+    let response = admin_post(&anon, url, json!({ "reason": "malware" })).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = admin_post(&user, url, json!({ "reason": "malware" })).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can quarantine versions"}]}"###);
+
+    let response = user.get::<()>("/api/private/admin/quarantines").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can review quarantined versions"}]}"###);
+
+    assert_eq!(
+        download_status(&anon, "foo", "1.0.0").await,
+        StatusCode::FOUND
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quarantine_and_release() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+    make_admin(&app, &user);
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0"))
+        .await
+        .good();
+
+    let url = "/api/private/admin/crates/foo/1.1.0/quarantine";
+    let response = admin_post(&user, url, json!({ "reason": " " })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the reason must not be empty"}]}"###);
+
+    let response = admin_post(&user, url, json!({ "reason": "Contains malware" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".quarantine.id" => insta::any_id_redaction(),
+        ".quarantine.quarantined_at" => "[datetime]",
+        ".quarantine.quarantined_by" => "[id]",
+    }, @r###"
+    {
+      "quarantine": {
+        "crate": "foo",
+        "id": "[id]",
+        "quarantined_at": "[datetime]",
+        "quarantined_by": "[id]",
+        "reason": "Contains malware",
+        "resolution": null,
+        "resolved_at": null,
+        "resolved_by": null,
+        "version": "1.1.0"
+      }
+    }
+    "###);
+
+    let response = admin_post(&user, url, json!({ "reason": "Contains malware" })).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"version `1.1.0` of crate `foo` is already quarantined"}]}"###);
+
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.1.0/download").await;
+    assert_eq!(response.status(), StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"version `1.1.0` of crate `foo` is quarantined pending review"}]}"###);
+    assert_eq!(
+        download_status(&anon, "foo", "1.0.0").await,
+        StatusCode::FOUND
+    );
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.1.0/file/Cargo.toml")
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+
+    let expected = vec![("1.0.0".to_string(), false), ("1.1.0".to_string(), true)];
+    assert_eq!(yanked_in_index(&app, "foo"), expected);
+
+    let emails = sent_emails(&app);
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0].contains("Subject: crates.io: A version of your crate was quarantined"));
+
+    let json: Value = user.get("/api/private/admin/quarantines").await.good();
+    assert_eq!(json["quarantines"].as_array().unwrap().len(), 1);
+    assert_eq!(json["quarantines"][0]["version"], "1.1.0");
+
+    let url = "/api/private/admin/crates/foo/1.0.0/quarantine/release";
+    let response = admin_post(&user, url, json!({})).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"version `1.0.0` of crate `foo` is not quarantined"}]}"###);
+
+    let url = "/api/private/admin/crates/foo/1.1.0/quarantine/release";
+    let response = admin_post(&user, url, json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["quarantine"]["resolution"], "released");
+    assert!(json["quarantine"]["resolved_at"].is_string());
+
+    app.run_pending_background_jobs().await;
+
+    assert_eq!(
+        download_status(&anon, "foo", "1.1.0").await,
+        StatusCode::FOUND
+    );
+
+    let expected = vec![("1.0.0".to_string(), false), ("1.1.0".to_string(), false)];
+    assert_eq!(yanked_in_index(&app, "foo"), expected);
+
+    let emails = sent_emails(&app);
+    assert_eq!(emails.len(), 2);
+    assert!(emails[1]
+        .contains("Subject: crates.io: A version of your crate was released from quarantine"));
+
+    let json: Value = user.get("/api/private/admin/quarantines").await.good();
+    assert_eq!(json, json!({ "quarantines": [] }));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_quarantined_version() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+    make_admin(&app, &user);
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0").readme("# foo"))
+        .await
+        .good();
+    app.run_pending_background_jobs().await;
+
+    let response = anon
+        .get::<()>("/api/v1/crates/foo/1.1.0/file/Cargo.toml")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(app.as_inner().file_preview_cache.len(), 1);
+
+    let url = "/api/private/admin/crates/foo/1.1.0/quarantine/delete";
+    let response = admin_post(&user, url, json!({})).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let url = "/api/private/admin/crates/foo/1.1.0/quarantine";
+    let response = admin_post(&user, url, json!({ "reason": "Contains malware" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let url = "/api/private/admin/crates/foo/1.1.0/quarantine/delete";
+    let response = admin_post(&user, url, json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["quarantine"]["resolution"], "deleted");
+
+    app.run_pending_background_jobs().await;
+
+    let expected_files = vec!["crates/foo/foo-1.0.0.crate", "index/3/f/foo"];
+    assert_that!(app.stored_files().await, eq(expected_files));
+
+    let expected = vec![("1.0.0".to_string(), false)];
+    assert_eq!(yanked_in_index(&app, "foo"), expected);
+
+    let response = anon.get::<()>("/api/v1/crates/foo/1.1.0").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(app.as_inner().file_preview_cache.is_empty());
+
+    let emails = sent_emails(&app);
+    assert_eq!(emails.len(), 2);
+    assert!(
+        emails[1].contains("Subject: crates.io: A quarantined version of your crate was deleted")
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reload_config() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    let url = "/api/private/admin/config/reload";
+    let response = admin_post(&anon, url, json!({})).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = admin_post(&user, url, json!({})).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can reload the configuration"}]}"###);
+
+    make_admin(&app, &user);
+
+    let response = admin_post(&user, url, json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.json()["changed"].is_array());
+
+    // Nothing changed since the previous reload.
+    let response = admin_post(&user, url, json!({})).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json(), json!({ "changed": [] }));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn runtime_settings_require_admin() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let response = user.get::<()>("/api/private/admin/blocked_routes").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can manage blocked routes"}]}"###);
+
+    let url = "/api/private/admin/excluded_crate_names";
+    let response = admin_post(&user, url, json!({ "name": "foo" })).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"only administrators can manage excluded crate names"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocked_routes() {
+    let (app, anon, user) = TestApp::init().with_user();
+    make_admin(&app, &user);
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let route = "/api/v1/crates/:crate_id/:version/download";
+    assert_eq!(
+        download_status(&anon, "foo", "1.0.0").await,
+        StatusCode::FOUND
+    );
+
+    let url = "/api/private/admin/blocked_routes";
+    let response = admin_post(&user, url, json!({ "route": route })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), { ".blocked_route.created_at" => "[datetime]" }, @r###"
+    {
+      "blocked_route": {
+        "created_at": "[datetime]",
+        "route": "/api/v1/crates/:crate_id/:version/download"
+      }
+    }
+    "###);
+
+    let response = admin_post(&user, url, json!({ "route": route })).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = admin_post(&user, url, json!({ "route": url })).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the admin routes cannot be blocked"}]}"###);
+
+    assert_eq!(
+        download_status(&anon, "foo", "1.0.0").await,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    let json: Value = user.get(url).await.good();
+    assert_eq!(json["blocked_routes"][0]["route"], route);
+
+    let delete_url = format!("{url}?route={route}");
+    let response = user.delete::<()>(&delete_url).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = user.delete::<()>(&delete_url).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    assert_eq!(
+        download_status(&anon, "foo", "1.0.0").await,
+        StatusCode::FOUND
+    );
+
+    let json: Value = user.get(url).await.good();
+    assert_eq!(json, json!({ "blocked_routes": [] }));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn excluded_crate_names() {
+    let (app, anon, user) = TestApp::init().with_user();
+    make_admin(&app, &user);
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version("1.0.0")
+            .downloads(100)
+            .expect_build(conn);
+    });
+
+    let most_downloaded = || async {
+        let json: Value = anon.get("/api/v1/summary").await.good();
+        json["most_downloaded"].as_array().unwrap().len()
+    };
+    assert_eq!(most_downloaded().await, 1);
+
+    let url = "/api/private/admin/excluded_crate_names";
+    let response = admin_post(&user, url, json!({ "name": "foo" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["excluded_crate_name"]["name"], "foo");
+
+    let response = admin_post(&user, url, json!({ "name": "foo" })).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the crate name `foo` is already excluded"}]}"###);
+
+    assert_eq!(most_downloaded().await, 0);
+
+    let response = user.delete::<()>(&format!("{url}?name=foo")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(most_downloaded().await, 1);
+}
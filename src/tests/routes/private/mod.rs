@@ -1 +1,2 @@
+mod admin;
 mod crate_owner_invitations;
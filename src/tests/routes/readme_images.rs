@@ -0,0 +1,40 @@
+use crate::util::{RequestHelper, TestApp};
+use crates_io::util::image_proxy;
+use http::StatusCode;
+use insta::assert_snapshot;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejects_missing_parameters() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/api/v1/readme_images").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"missing `url` or `sig` query parameter"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rejects_unsigned_urls() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let query = "url=https%3A%2F%2Fexample.com%2Flogo.png&sig=abcdef";
+    let response = anon
+        .get_with_query::<()>("/api/v1/readme_images", query)
+        .await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid image signature"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn does_not_fetch_private_addresses() {
+    let (app, anon) = TestApp::init().empty();
+
+    let url = "http://127.0.0.1:1/logo.png";
+    let proxy_url = image_proxy::proxy_url(app.as_inner().session_key(), "crates.io", url);
+    let (_, query) = proxy_url.split_once('?').unwrap();
+
+    let response = anon
+        .get_with_query::<()>("/api/v1/readme_images", query)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"failed to fetch the image"}]}"###);
+}
@@ -0,0 +1,121 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use chrono::{TimeDelta, Utc};
+use crates_io::schema::sitemaps;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_snapshot;
+use regex::Regex;
+
+fn redact_lastmod(xml: &str) -> String {
+    let regex = Regex::new(r"<lastmod>[^<]+</lastmod>").unwrap();
+    regex
+        .replace_all(xml, "<lastmod>[datetime]</lastmod>")
+        .into_owned()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn robots_txt() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/robots.txt").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"
+    # http://www.robotstxt.org
+    User-agent: *
+    Disallow:
+
+    Sitemap: https://crates.io/sitemap.xml
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sitemap() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    let last_year = Utc::now().naive_utc() - TimeDelta::try_days(365).unwrap();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id)
+            .version(VersionBuilder::new("1.0.0").created_at(last_year))
+            .version("1.1.0")
+            .version(VersionBuilder::new("1.2.0").yanked(true))
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", user.id)
+            .version(VersionBuilder::new("0.1.0").created_at(last_year))
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/sitemap.xml").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/xml; charset=utf-8"
+    );
+    assert_snapshot!(response.text(), @r###"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+      <sitemap><loc>https://crates.io/sitemaps/crates/1</loc></sitemap>
+      <sitemap><loc>https://crates.io/sitemaps/versions</loc></sitemap>
+    </sitemapindex>
+    "###);
+
+    let response = anon.get::<()>("/sitemaps/crates/1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(redact_lastmod(&response.text()), @r###"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+      <url><loc>https://crates.io/crates/foo</loc><lastmod>[datetime]</lastmod></url>
+      <url><loc>https://crates.io/crates/bar</loc><lastmod>[datetime]</lastmod></url>
+    </urlset>
+    "###);
+
+    let response = anon.get::<()>("/sitemaps/crates/2").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = anon.get::<()>("/sitemaps/versions").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(redact_lastmod(&response.text()), @r###"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+      <url><loc>https://crates.io/crates/foo/1.1.0</loc><lastmod>[datetime]</lastmod></url>
+    </urlset>
+    "###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sitemap_is_cached() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.id).expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/sitemaps/crates/1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let cached = response.text();
+    assert!(cached.contains("/crates/foo<"));
+
+    app.db(|conn| {
+        CrateBuilder::new("bar", user.id).expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/sitemaps/crates/1").await;
+    assert_eq!(response.text(), cached);
+
+    // Expire the cached document
+    app.db(|conn| {
+        diesel::update(sitemaps::table)
+            .set(
+                sitemaps::generated_at.eq(Utc::now().naive_utc() - TimeDelta::try_days(1).unwrap()),
+            )
+            .execute(conn)
+            .unwrap();
+    });
+
+    let response = anon.get::<()>("/sitemaps/crates/1").await;
+    assert!(response.text().contains("/crates/bar<"));
+}
@@ -0,0 +1,198 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::new_category;
+use crate::util::{RequestHelper, TestApp};
+use chrono::{TimeDelta, Utc};
+use crates_io::models::Crate;
+use crates_io::schema::{metadata, users, versions};
+use crates_io::worker::jobs::{UpdateSiteStats, UpdateTrendingStats};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+use serde_json::json;
+
+fn add_downloads(conn: &mut PgConnection, krate: &Crate, num: &str, days_ago: i32, downloads: i32) {
+    let version_id: i32 = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .filter(versions::num.eq(num))
+        .select(versions::id)
+        .first(conn)
+        .unwrap();
+
+    diesel::sql_query(
+        "INSERT INTO version_downloads (version_id, date, downloads) VALUES ($1, CURRENT_DATE - $2, $3)",
+    )
+    .bind::<Integer, _>(version_id)
+    .bind::<Integer, _>(days_ago)
+    .bind::<Integer, _>(downloads)
+    .execute(conn)
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trending_stats() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+
+    let response = anon.get::<()>("/api/v1/stats/trending").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "date": null, "keywords": [], "categories": [] })
+    );
+
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+
+        let days_ago = |days| Utc::now().naive_utc() - TimeDelta::days(days);
+
+        let krate = CrateBuilder::new("foo", user.id)
+            .keyword("async")
+            .category("cat1")
+            .version(VersionBuilder::new("1.0.0").created_at(days_ago(10)))
+            .version(VersionBuilder::new("1.1.0").created_at(days_ago(3)))
+            .version(VersionBuilder::new("1.2.0").created_at(days_ago(2)))
+            .expect_build(conn);
+
+        add_downloads(conn, &krate, "1.0.0", 10, 50);
+        add_downloads(conn, &krate, "1.1.0", 3, 100);
+
+        let bar = CrateBuilder::new("bar", user.id)
+            .keyword("async")
+            .keyword("web")
+            .version(VersionBuilder::new("1.0.0").created_at(days_ago(20)))
+            .expect_build(conn);
+
+        add_downloads(conn, &bar, "1.0.0", 9, 40);
+        add_downloads(conn, &bar, "1.0.0", 2, 20);
+
+        // Downloads of the current day and older than two weeks are ignored
+        add_downloads(conn, &bar, "1.0.0", 0, 1000);
+        add_downloads(conn, &bar, "1.0.0", 15, 1000);
+
+        UpdateTrendingStats.enqueue(conn).unwrap();
+    });
+
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/api/v1/stats/trending").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), { ".date" => "[date]" }, @r###"
+    {
+      "categories": [
+        {
+          "download_growth": 1.0,
+          "downloads": 100,
+          "id": "cat1",
+          "previous_downloads": 50,
+          "previous_publishes": 1,
+          "publishes": 2
+        }
+      ],
+      "date": "[date]",
+      "keywords": [
+        {
+          "download_growth": 0.3333333333333333,
+          "downloads": 120,
+          "id": "async",
+          "previous_downloads": 90,
+          "previous_publishes": 1,
+          "publishes": 2
+        }
+      ]
+    }
+    "###);
+
+    let response = anon
+        .get::<()>("/api/v1/stats/trending?sort=publishes&per_page=1")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.json();
+    assert_eq!(json["keywords"][0]["id"], "async");
+    assert_eq!(json["categories"][0]["id"], "cat1");
+
+    let response = anon.get::<()>("/api/v1/stats/trending?sort=foo").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid sort, expected `downloads` or `publishes`"}]}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn site_stats() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+
+    let response = anon.get::<()>("/api/v1/stats/site").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json(),
+        json!({ "date": null, "totals": null, "daily": [] })
+    );
+
+    app.db(|conn| {
+        let yesterday = Utc::now().naive_utc() - TimeDelta::days(1);
+
+        CrateBuilder::new("foo", user.id)
+            .version(VersionBuilder::new("1.0.0").created_at(yesterday - TimeDelta::days(5)))
+            .version(VersionBuilder::new("1.1.0").created_at(yesterday))
+            .version(VersionBuilder::new("1.2.0").created_at(yesterday))
+            .expect_build(conn);
+
+        CrateBuilder::new("bar", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+
+        diesel::update(users::table.find(user.id))
+            .set(users::created_at.eq(yesterday))
+            .execute(conn)
+            .unwrap();
+
+        diesel::update(metadata::table)
+            .set(metadata::total_downloads.eq(1234))
+            .execute(conn)
+            .unwrap();
+
+        // Rollups of earlier days, the oldest of which is outside of the
+        // 90 day window
+        diesel::sql_query(
+            "INSERT INTO site_stats (date, crates, versions, downloads, publishes, new_users) \
+             VALUES (CURRENT_DATE - 2, 1, 1, 1000, 1, 0), (CURRENT_DATE - 100, 0, 0, 0, 5, 5)",
+        )
+        .execute(conn)
+        .unwrap();
+
+        UpdateSiteStats.enqueue(conn).unwrap();
+    });
+
+    app.run_pending_background_jobs().await;
+
+    let response = anon.get::<()>("/api/v1/stats/site").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), {
+        ".date" => "[date]",
+        ".daily[].date" => "[date]",
+    }, @r###"
+    {
+      "daily": [
+        {
+          "date": "[date]",
+          "new_users": 0,
+          "publishes": 1
+        },
+        {
+          "date": "[date]",
+          "new_users": 1,
+          "publishes": 2
+        }
+      ],
+      "date": "[date]",
+      "totals": {
+        "crates": 2,
+        "downloads": 1234,
+        "versions": 4
+      }
+    }
+    "###);
+}
@@ -2,6 +2,7 @@ use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::new_category;
 use crate::util::{RequestHelper, TestApp};
 use chrono::Utc;
+use crates_io::models::ExcludedCrateName;
 use crates_io::schema::metadata;
 use crates_io::views::{EncodableCategory, EncodableCrate, EncodableKeyword};
 use diesel::{update, Connection, ExpressionMethods, RunQueryDsl};
@@ -114,18 +115,14 @@ async fn summary_new_crates() {
 
 #[tokio::test(flavor = "multi_thread")]
 async fn excluded_crate_id() {
-    let (app, anon, user) = TestApp::init()
-        .with_config(|config| {
-            config.excluded_crate_names = vec![
-                "most_recent_downloads".into(),
-                // make sure no error occurs with a crate name that doesn't exist and that the name
-                // matches are exact, not substrings
-                "downloads".into(),
-            ];
-        })
-        .with_user();
+    let (app, anon, user) = TestApp::init().with_user();
     let user = user.as_model();
     app.db(|conn| {
+        ExcludedCrateName::insert(conn, "most_recent_downloads", user.id).unwrap();
+        // make sure no error occurs with a crate name that doesn't exist and that the name
+        // matches are exact, not substrings
+        ExcludedCrateName::insert(conn, "downloads", user.id).unwrap();
+
         CrateBuilder::new("some_downloads", user.id)
             .version(VersionBuilder::new("0.1.0"))
             .description("description")
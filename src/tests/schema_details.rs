@@ -23,6 +23,10 @@ fn all_columns_called_crate_id_have_a_cascading_foreign_key() {
     }
 }
 
+/// Tables that keep their rows as an audit trail after the referenced version
+/// was deleted.
+const VERSION_AUDIT_TRAIL_TABLES: &[&str] = &["scheduled_releases", "version_quarantines"];
+
 #[test]
 fn all_columns_called_version_id_have_a_cascading_foreign_key() {
     for row in get_fk_constraint_definitions("version_id") {
@@ -33,7 +37,15 @@ fn all_columns_called_version_id_have_a_cascading_foreign_key() {
                 row.table_name
             ),
         };
-        if !constraint.definition.contains("ON DELETE CASCADE") {
+        if VERSION_AUDIT_TRAIL_TABLES.contains(&row.table_name.as_str()) {
+            if !constraint.definition.contains("ON DELETE SET NULL") {
+                panic!(
+                    "Foreign key {} on table {} should have `ON DELETE SET NULL` \
+                     but it doesn't.",
+                    constraint.name, row.table_name
+                );
+            }
+        } else if !constraint.definition.contains("ON DELETE CASCADE") {
             panic!(
                 "Foreign key {} on table {} should have `ON DELETE CASCADE` \
                  but it doesn't.",
@@ -1,6 +1,6 @@
 use crate::builders::*;
 use crate::util::*;
-use std::collections::HashSet;
+use crates_io::util::ip_blocklist::IpBlocklist;
 
 use ::insta::assert_json_snapshot;
 use http::{header, Request, StatusCode};
@@ -41,7 +41,7 @@ async fn user_agent_is_not_required_for_download() {
 async fn blocked_traffic_doesnt_panic_if_checked_header_is_not_present() {
     let (app, anon, user) = TestApp::init()
         .with_config(|config| {
-            config.blocked_traffic = vec![("Never-Given".into(), vec!["1".into()])];
+            config.reloadable.blocked_traffic = vec![("Never-Given".into(), vec!["1".into()])];
         })
         .with_user();
 
@@ -59,7 +59,8 @@ async fn blocked_traffic_doesnt_panic_if_checked_header_is_not_present() {
 async fn block_traffic_via_arbitrary_header_and_value() {
     let (app, anon, user) = TestApp::init()
         .with_config(|config| {
-            config.blocked_traffic = vec![("User-Agent".into(), vec!["1".into(), "2".into()])];
+            config.reloadable.blocked_traffic =
+                vec![("User-Agent".into(), vec!["1".into(), "2".into()])];
         })
         .with_user();
 
@@ -92,11 +93,39 @@ async fn block_traffic_via_arbitrary_header_and_value() {
     assert_eq!(resp.status(), StatusCode::FOUND);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn block_traffic_after_config_reload() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("dl_no_ua", user.as_model().id).expect_build(conn);
+    });
+
+    let request = || {
+        Request::get("/api/v1/crates/dl_no_ua/0.99.0/download")
+            .header(header::USER_AGENT, "1")
+            .body("")
+            .unwrap()
+    };
+
+    let resp = anon.run::<()>(request()).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+
+    let mut config = (*app.as_inner().reloadable_config()).clone();
+    config.blocked_traffic = vec![("User-Agent".into(), vec!["1".into()])];
+    let changes = app.as_inner().apply_config(config);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].setting, "BLOCKED_TRAFFIC");
+
+    let resp = anon.run::<()>(request()).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn block_traffic_via_ip() {
     let (_app, anon) = TestApp::init()
         .with_config(|config| {
-            config.blocked_ips = HashSet::from(["127.0.0.1".parse().unwrap()]);
+            config.blocked_ips = IpBlocklist::from_iter(["127.0.0.1".parse().unwrap()]);
         })
         .empty();
 
@@ -104,3 +133,43 @@ async fn block_traffic_via_ip() {
     assert_eq!(resp.status(), StatusCode::FORBIDDEN);
     assert_json_snapshot!(resp.json());
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn block_traffic_via_cidr_blocks() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| {
+            config.blocked_ips = IpBlocklist::from_iter([
+                "2a02:8071:b182:c500::/64".parse().unwrap(),
+                "203.0.113.0/24".parse().unwrap(),
+            ]);
+        })
+        .empty();
+
+    async fn status(anon: &MockAnonymousUser, forwarded_for: &str) -> StatusCode {
+        let mut request = anon.get_request("/api/v1/crates");
+        request.header("x-forwarded-for", forwarded_for);
+        anon.run::<()>(request).await.status()
+    }
+
+    // The Heroku router appends the connecting address to the header
+    let blocked = [
+        "2a02:8071:b182:c500:a5f4:3d4d:e5b9:4b21",
+        "198.51.100.1, 2a02:8071:b182:c500::1",
+        "203.0.113.42",
+        "2a02:8071:b182:c500::1, 203.0.113.42",
+        "::ffff:203.0.113.42",
+    ];
+    for forwarded_for in blocked {
+        assert_eq!(status(&anon, forwarded_for).await, StatusCode::FORBIDDEN);
+    }
+
+    let allowed = [
+        "2a02:8071:b182:c501::1",
+        "2a02:8071:b182:c500::1, 198.51.100.1",
+        "203.0.114.1",
+        "::203.0.113.42",
+    ];
+    for forwarded_for in allowed {
+        assert_eq!(status(&anon, forwarded_for).await, StatusCode::OK);
+    }
+}
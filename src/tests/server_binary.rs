@@ -34,6 +34,41 @@ fn normal_startup() {
     let location = assert_some!(resp.headers().get("location"));
     let location = assert_ok!(location.to_str());
     assert_that!(location, ends_with("/crates/foo/foo-1.0.0.crate"));
+
+    // Ensure the API is working and backed by the database
+    let client = running_server.api_client().unwrap();
+    let response = running_server.block_on(client.krate("foo")).unwrap();
+    assert_eq!(response.krate.name, "foo");
+    assert_eq!(response.krate.max_version, "1.0.0");
+}
+
+#[test]
+fn http2_prior_knowledge() {
+    let mut server_bin = ServerBin::prepare().unwrap();
+    server_bin.env.insert("HTTP2_ENABLED".into(), "true".into());
+    initialize_dummy_crate(&mut server_bin.db().unwrap());
+
+    let running_server = server_bin.start().unwrap();
+
+    let http2 = Client::builder()
+        .http2_prior_knowledge()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    let url = format!("http://127.0.0.1:{}/api/v1/crates/foo", running_server.port);
+    let resp = http2
+        .get(url)
+        .header("User-Agent", "crates.io test suite")
+        .send()
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.version(), reqwest::Version::HTTP_2);
+
+    // HTTP/1 clients are still supported
+    let resp = running_server.get("api/v1/crates/foo").unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.version(), reqwest::Version::HTTP_11);
 }
 
 #[cfg(feature = "slow-tests")]
@@ -79,7 +114,7 @@ fn initialize_dummy_crate(conn: &mut PgConnection) {
 }
 
 struct ServerBin {
-    _runtime: Runtime,
+    runtime: Runtime,
     chaosproxy: Arc<ChaosProxy>,
     db_url: String,
     env: HashMap<String, String>,
@@ -112,7 +147,7 @@ impl ServerBin {
         env.insert("READ_ONLY_REPLICA_URL".into(), db_url.clone());
 
         Ok(ServerBin {
-            _runtime: runtime,
+            runtime,
             chaosproxy,
             db_url,
             env,
@@ -156,6 +191,7 @@ impl ServerBin {
             http,
             _chaosproxy: self.chaosproxy,
             _test_database: self.test_database,
+            runtime: self.runtime,
         })
     }
 }
@@ -165,9 +201,10 @@ struct RunningServer {
     port: u16,
     http: Client,
 
-    // Keep these two items at the bottom in this order to drop everything in the correct order.
+    // Keep these three items at the bottom in this order to drop everything in the correct order.
     _chaosproxy: Arc<ChaosProxy>,
     _test_database: TestDatabase,
+    runtime: Runtime,
 }
 
 impl RunningServer {
@@ -178,6 +215,18 @@ impl RunningServer {
             .header("User-Agent", "crates.io test suite")
             .send()?)
     }
+
+    fn api_client(&self) -> Result<crates_io_client::Client, Error> {
+        let base_url = Url::parse(&format!("http://127.0.0.1:{}", self.port))?;
+        Ok(crates_io_client::Client::new(
+            base_url,
+            "crates.io test suite",
+        )?)
+    }
+
+    fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
 }
 
 impl Drop for RunningServer {
@@ -1,11 +1,13 @@
 use crate::{
     add_team_to_crate,
     builders::{CrateBuilder, PublishBuilder},
-    new_team, OwnerTeamsResponse, RequestHelper, TestApp,
+    new_team,
+    util::REVOKED_GITHUB_TOKEN,
+    OwnerTeamsResponse, RequestHelper, TestApp,
 };
 use crates_io::{
     models::{Crate, NewTeam},
-    schema::teams,
+    schema::{teams, users},
 };
 
 use diesel::*;
@@ -343,6 +345,80 @@ async fn publish_not_owned() {
     );
 }
 
+/// Test publishing with a GitHub access token that GitHub rejects
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_with_revoked_github_token() {
+    let (app, _) = TestApp::full().empty();
+    let user_on_both_teams = app.db_new_user("user-all-teams");
+    let token_on_both_teams = user_on_both_teams.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_revoked", user_on_both_teams.as_model().id).expect_build(conn);
+    });
+
+    token_on_both_teams
+        .add_named_owner("foo_revoked", "github:test-org:all")
+        .await
+        .good();
+
+    let user_on_one_team = app.db_new_user("user-one-team");
+    let user_id = user_on_one_team.as_model().id;
+    let token_on_one_team = user_on_one_team.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_own", user_id).expect_build(conn);
+
+        diesel::update(users::table.find(user_id))
+            .set(users::gh_access_token.eq(REVOKED_GITHUB_TOKEN))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo_revoked", "2.0.0");
+    let response = token_on_one_team.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "GitHub rejected your authorization of crates.io. Please sign in to crates.io again." }] })
+    );
+
+    let (access_token, revoked_at) = app.db(|conn| {
+        users::table
+            .find(user_id)
+            .select((users::gh_access_token, users::gh_token_revoked_at))
+            .first::<(String, Option<chrono::NaiveDateTime>)>(conn)
+            .unwrap()
+    });
+    assert_eq!(access_token, "");
+    assert!(revoked_at.is_some());
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), 1);
+    assert!(emails[0]
+        .1
+        .contains("Subject: crates.io: Your GitHub authorization was revoked"));
+
+    // The session of the user ended
+    let response = user_on_one_team.get::<()>("/api/v1/me").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "GitHub rejected your authorization of crates.io. Please sign in again." }] })
+    );
+
+    // Publishing is blocked, even for crates that are owned directly
+    let crate_to_publish = PublishBuilder::new("foo_own", "2.0.0");
+    let response = token_on_one_team.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "Publishing is blocked because GitHub rejected your authorization of crates.io. Please sign in to https://crates.io again to unblock it." }] })
+    );
+
+    // The user is only notified once
+    assert_eq!(app.as_inner().emails.mails_in_memory().unwrap().len(), 1);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn publish_org_owner_owned() {
     let (app, _) = TestApp::full().empty();
@@ -482,7 +558,9 @@ async fn crates_by_team_id_not_including_deleted_owners() {
 
         let krate = CrateBuilder::new("foo", user.id).expect_build(conn);
         add_team_to_crate(&t, &krate, user, conn).unwrap();
-        krate.owner_remove(conn, &t.login).unwrap();
+        krate
+            .owner_remove(app.as_inner(), conn, user, &t.login)
+            .unwrap();
         t
     });
 
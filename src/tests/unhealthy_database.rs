@@ -1,4 +1,4 @@
-use crate::util::{RequestHelper, TestApp};
+use crate::util::{NetworkConditions, RequestHelper, TestApp};
 use deadpool_diesel::postgres::Pool;
 use http::StatusCode;
 use std::time::{Duration, Instant};
@@ -46,6 +46,60 @@ async fn http_error_with_unhealthy_database() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_database_delays_responses() {
+    const LATENCY: Duration = Duration::from_millis(100);
+
+    let (app, anon) = TestApp::init().with_chaos_proxy().empty();
+
+    app.primary_db_chaosproxy()
+        .set_network_conditions(NetworkConditions {
+            latency: LATENCY,
+            jitter: Duration::from_millis(20),
+            bandwidth: Some(64 * 1024),
+            ..Default::default()
+        });
+
+    // A degraded network is slow, but the requests are still successful
+    let start_time = Instant::now();
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(start_time.elapsed() >= LATENCY);
+
+    app.primary_db_chaosproxy().reset_network_conditions();
+
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn http_error_with_mid_stream_disconnects() {
+    let (app, anon) = TestApp::init().with_chaos_proxy().empty();
+
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Unlike `break_networking()`, the proxy still accepts new connections,
+    // but closes them as soon as any data is sent
+    app.primary_db_chaosproxy()
+        .set_network_conditions(NetworkConditions {
+            disconnect_after_bytes: Some(0),
+            ..Default::default()
+        });
+    app.primary_db_chaosproxy()
+        .disconnect_connections()
+        .unwrap();
+
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    app.primary_db_chaosproxy().reset_network_conditions();
+    wait_until_healthy(&app.as_inner().primary_database).await;
+
+    let response = anon.get::<()>("/api/v1/summary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn fallback_to_replica_returns_user_info() {
     const URL: &str = "/api/v1/users/foo";
@@ -0,0 +1,190 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use bytes::Bytes;
+use crates_io::upstream::MockUpstreamRegistry;
+use http::StatusCode;
+use insta::{assert_json_snapshot, assert_snapshot};
+use sha2::{Digest, Sha256};
+
+const CRATE_FILE: &[u8] = b"upstream crate file";
+
+/// Returns an upstream index file with an entry for `serde` version `1.0.0`
+/// with the given checksum.
+fn index_file(cksum: &str) -> Bytes {
+    let entry = json!({ "name": "serde", "vers": "1.0.0", "cksum": cksum });
+    Bytes::from(entry.to_string())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_fetches_and_caches_upstream_crate_file() {
+    let cksum = hex::encode(Sha256::digest(CRATE_FILE));
+
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream
+        .expect_index_file()
+        .withf(|name| name == "serde")
+        .times(1)
+        .returning(move |_| Ok(Some(index_file(&cksum))));
+    upstream
+        .expect_crate_file()
+        .withf(|name, version| name == "serde" && version == "1.0.0")
+        .times(1)
+        .returning(|_, _| Ok(Some(Bytes::from_static(CRATE_FILE))));
+
+    let (app, anon) = TestApp::init().with_upstream(upstream).empty();
+
+    let url = "/api/v1/crates/serde/1.0.0/download";
+    anon.get::<()>(url)
+        .await
+        .assert_redirect_ends_with("/crates/serde/serde-1.0.0.crate");
+
+    assert_eq!(
+        app.stored_files().await,
+        vec!["crates/serde/serde-1.0.0.crate"]
+    );
+
+    // The cached crate file is used for the following downloads
+    anon.get::<()>(url)
+        .await
+        .assert_redirect_ends_with("/crates/serde/serde-1.0.0.crate");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_of_missing_upstream_version() {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream
+        .expect_index_file()
+        .returning(|_| Ok(Some(index_file("0000"))));
+    upstream.expect_crate_file().never();
+
+    let (app, anon) = TestApp::init().with_upstream(upstream).empty();
+
+    let response = anon.get::<()>("/api/v1/crates/serde/0.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_json_snapshot!(response.json(), @r###"
+    {
+      "errors": [
+        {
+          "detail": "crate `serde` does not have a version `0.0.0`"
+        }
+      ]
+    }
+    "###);
+
+    assert!(app.stored_files().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn download_of_upstream_crate_file_with_wrong_checksum() {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream
+        .expect_index_file()
+        .returning(|_| Ok(Some(index_file("0000"))));
+    upstream
+        .expect_crate_file()
+        .returning(|_, _| Ok(Some(Bytes::from_static(CRATE_FILE))));
+
+    let (app, anon) = TestApp::init().with_upstream(upstream).empty();
+
+    let response = anon.get::<()>("/api/v1/crates/serde/1.0.0/download").await;
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"the checksum of the crate file from the upstream registry does not match its index entry"}]}"###);
+
+    assert!(app.stored_files().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn local_crates_take_precedence_over_upstream_crates() {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream.expect_crate_file().never();
+
+    let (app, anon, user) = TestApp::init().with_upstream(upstream).with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    anon.get::<()>("/api/v1/crates/foo/1.0.0/download")
+        .await
+        .assert_redirect_ends_with("/crates/foo/foo-1.0.0.crate");
+
+    assert!(app.stored_files().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_passthrough() {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream.expect_index_file().returning(|name| match name {
+        "serde" => Ok(Some(Bytes::from_static(b"{\"name\":\"serde\"}"))),
+        _ => Ok(None),
+    });
+
+    let (app, anon) = TestApp::init().with_upstream(upstream).empty();
+
+    let response = anon.get::<()>("/index/config.json").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_json_snapshot!(response.json(), @r###"
+    {
+      "api": "https://crates.io",
+      "dl": "https://crates.io/api/v1/crates"
+    }
+    "###);
+
+    let response = anon.get::<()>("/index/se/rd/serde").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"name":"serde"}"###);
+
+    let response = anon.get::<()>("/index/3/m/mis").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The path has to match the crate name
+    let response = anon.get::<()>("/index/xx/yy/serde").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Index files of local crates take precedence
+    let storage = app.as_inner().storage.clone();
+    let content = "{\"name\":\"serde\",\"local\":true}".to_string();
+    storage.sync_index("serde", Some(content)).await.unwrap();
+
+    let response = anon.get::<()>("/index/se/rd/serde").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_snapshot!(response.text(), @r###"{"name":"serde","local":true}"###);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn index_is_not_served_without_upstream() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/index/config.json").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_conflicting_with_upstream_crate() {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream.expect_index_file().returning(|name| match name {
+        "foo_bar" => Ok(Some(Bytes::from_static(b"{}"))),
+        _ => Ok(None),
+    });
+
+    let (app, _, _, token) = TestApp::full().with_upstream(upstream).with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo-bar", "1.0.0");
+    let response = token.publish_crate(crate_to_publish).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"crate name `foo-bar` conflicts with the crate `foo_bar` of the upstream registry. Crates of the upstream registry are served through this registry, so a crate with the same name can't be published here."}]}"###);
+    assert!(app.stored_files().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn publish_without_upstream_conflict() {
+    let mut upstream = MockUpstreamRegistry::new();
+    upstream.expect_index_file().returning(|_| Ok(None));
+
+    let (_app, _, _, token) = TestApp::full().with_upstream(upstream).with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo-bar", "1.0.0");
+    token.publish_crate(crate_to_publish).await.good();
+}
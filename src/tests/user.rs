@@ -128,6 +128,43 @@ async fn github_with_email_does_not_overwrite_email() {
     assert_eq!(json.user.email, Some(original_email));
 }
 
+/// Given a user that already has an email address, check that signing in
+/// again with a GitHub email address neither adds another address to the
+/// account nor sends out a verification email.
+#[tokio::test(flavor = "multi_thread")]
+async fn github_email_is_not_added_to_user_with_email() {
+    use crates_io::schema::emails;
+
+    let (app, _, user) = TestApp::init().with_user();
+    let model = user.as_model();
+    let sent_emails = app.as_inner().emails.mails_in_memory().unwrap().len();
+
+    app.db(|conn| {
+        let u = NewUser {
+            gh_id: model.gh_id,
+            ..new_user("arbitrary_username")
+        };
+        u.create_or_update(
+            Some("new-email-in-github@example.com"),
+            &app.as_inner().emails,
+            conn,
+        )
+        .unwrap();
+    });
+
+    let addresses: Vec<String> = app.db(|conn| {
+        Email::belonging_to(model)
+            .select(emails::email)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(addresses.len(), 1);
+    assert_ne!(addresses[0], "new-email-in-github@example.com");
+
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.len(), sent_emails);
+}
+
 /// Given a crates.io user, check that the user's email can be
 /// updated in the database (PUT /user/:user_id), then check
 /// that the updated email is sent back to the user (GET /me).
@@ -41,6 +41,7 @@ use std::net::SocketAddr;
 use tower::ServiceExt;
 
 mod chaosproxy;
+mod clock;
 mod github;
 pub mod insta;
 pub mod matchers;
@@ -48,7 +49,8 @@ mod mock_request;
 mod response;
 mod test_app;
 
-pub(crate) use chaosproxy::ChaosProxy;
+pub(crate) use chaosproxy::{ChaosProxy, NetworkConditions};
+pub(crate) use github::REVOKED_GITHUB_TOKEN;
 use mock_request::MockRequest;
 pub use mock_request::MockRequestExt;
 pub use response::Response;
@@ -1,23 +1,60 @@
 use anyhow::{anyhow, Context};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::{
         tcp::{OwnedReadHalf, OwnedWriteHalf},
         TcpListener, TcpStream,
     },
-    sync::broadcast::Sender,
+    sync::{broadcast, watch},
 };
 use tracing::{debug, error};
 use url::Url;
 
+/// Degraded network conditions that the [`ChaosProxy`] applies to all data
+/// it forwards, in both directions.
+///
+/// The conditions are applied to every connection on its own, e.g. every
+/// connection can use the full `bandwidth`, and they also apply to the
+/// connections that were established before the conditions were changed.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct NetworkConditions {
+    /// Fixed delay before each chunk of data is forwarded.
+    pub(crate) latency: Duration,
+    /// Maximum random delay that is added on top of the `latency`.
+    pub(crate) jitter: Duration,
+    /// Maximum number of bytes per second that are forwarded per connection
+    /// and direction.
+    pub(crate) bandwidth: Option<u64>,
+    /// Closes a connection in the middle of the stream once this many bytes
+    /// have been forwarded in one direction.
+    pub(crate) disconnect_after_bytes: Option<u64>,
+}
+
+impl NetworkConditions {
+    /// Returns how long forwarding a chunk of `len` bytes is delayed.
+    fn delay(&self, len: usize) -> Duration {
+        let mut delay = self.latency;
+        if !self.jitter.is_zero() {
+            delay += self.jitter.mul_f64(rand::random::<f64>());
+        }
+        if let Some(bandwidth) = self.bandwidth {
+            delay += Duration::from_secs_f64(len as f64 / bandwidth.max(1) as f64);
+        }
+        delay
+    }
+}
+
 pub(crate) struct ChaosProxy {
     address: SocketAddr,
     backend_address: SocketAddr,
 
-    break_networking_send: Sender<()>,
-    restore_networking_send: Sender<()>,
+    break_networking_send: broadcast::Sender<()>,
+    restore_networking_send: broadcast::Sender<()>,
+    disconnect_send: broadcast::Sender<()>,
+    conditions_send: watch::Sender<NetworkConditions>,
 }
 
 impl ChaosProxy {
@@ -28,8 +65,10 @@ impl ChaosProxy {
         let address = listener.local_addr()?;
         debug!("ChaosProxy listening on {address}");
 
-        let (break_networking_send, _) = tokio::sync::broadcast::channel(16);
-        let (restore_networking_send, _) = tokio::sync::broadcast::channel(16);
+        let (break_networking_send, _) = broadcast::channel(16);
+        let (restore_networking_send, _) = broadcast::channel(16);
+        let (disconnect_send, _) = broadcast::channel(16);
+        let (conditions_send, _) = watch::channel(NetworkConditions::default());
 
         let instance = Arc::new(ChaosProxy {
             address,
@@ -37,6 +76,8 @@ impl ChaosProxy {
 
             break_networking_send,
             restore_networking_send,
+            disconnect_send,
+            conditions_send,
         });
 
         debug!("Spawning ChaosProxy server loop");
@@ -86,6 +127,26 @@ impl ChaosProxy {
             .context("Failed to send the restore_networking message")
     }
 
+    /// Closes all established connections, while new connections are still
+    /// accepted (unlike [`Self::break_networking()`]).
+    pub(crate) fn disconnect_connections(&self) -> anyhow::Result<usize> {
+        self.disconnect_send
+            .send(())
+            .context("Failed to send the disconnect message")
+    }
+
+    /// Degrades the network instead of breaking it completely, see
+    /// [`NetworkConditions`].
+    pub(crate) fn set_network_conditions(&self, conditions: NetworkConditions) {
+        debug!(?conditions, "ChaosProxy changing network conditions");
+        self.conditions_send.send_replace(conditions);
+    }
+
+    /// Restores the default network conditions without any degradation.
+    pub(crate) fn reset_network_conditions(&self) {
+        self.set_network_conditions(NetworkConditions::default());
+    }
+
     async fn server_loop(&self, initial_listener: TcpListener) -> anyhow::Result<()> {
         let mut listener = Some(initial_listener);
 
@@ -99,7 +160,11 @@ impl ChaosProxy {
                     accepted = l.accept() => {
                         let (stream, address ) = accepted?;
                         debug!("ChaosProxy accepted connection from {address}");
-                        self.accept_connection(stream).await?;
+
+                        // A failing connection must not take down the whole proxy
+                        if let Err(error) = self.accept_connection(stream).await {
+                            error!(%error, "ChaosProxy failed to connect to the backend");
+                        }
                     },
 
                     _ = break_networking_recv.recv() => {
@@ -126,33 +191,46 @@ impl ChaosProxy {
             .await?
             .into_split();
 
-        let break_networking_send = self.break_networking_send.clone();
-        tokio::spawn(async move {
-            if let Err(error) = proxy_data(break_networking_send, client_read, backend_write).await
-            {
-                error!(%error, "ChaosProxy connection error");
-            }
-        });
+        // Closing one direction of the connection closes the other one too
+        let (connection_closed_send, _) = broadcast::channel(1);
 
-        let break_networking_send = self.break_networking_send.clone();
-        tokio::spawn(async move {
-            if let Err(error) = proxy_data(break_networking_send, backend_read, client_write).await
-            {
-                error!(%error, "ChaosProxy connection error");
-            }
-        });
+        let directions = [(client_read, backend_write), (backend_read, client_write)];
+        for (from, to) in directions {
+            let signals = CloseSignals {
+                break_networking: self.break_networking_send.subscribe(),
+                disconnect: self.disconnect_send.subscribe(),
+                connection_closed: connection_closed_send.subscribe(),
+            };
+            let conditions = self.conditions_send.subscribe();
+            let connection_closed_send = connection_closed_send.clone();
+
+            tokio::spawn(async move {
+                if let Err(error) = proxy_data(from, to, conditions, signals).await {
+                    error!(%error, "ChaosProxy connection error");
+                }
+                let _ = connection_closed_send.send(());
+            });
+        }
 
         Ok(())
     }
 }
 
+/// The signals that close a proxied connection.
+struct CloseSignals {
+    break_networking: broadcast::Receiver<()>,
+    disconnect: broadcast::Receiver<()>,
+    connection_closed: broadcast::Receiver<()>,
+}
+
 async fn proxy_data(
-    break_networking_send: Sender<()>,
     mut from: OwnedReadHalf,
     mut to: OwnedWriteHalf,
+    mut conditions: watch::Receiver<NetworkConditions>,
+    mut signals: CloseSignals,
 ) -> anyhow::Result<()> {
-    let mut break_connections_recv = break_networking_send.subscribe();
     let mut buf = [0; 1024];
+    let mut forwarded: u64 = 0;
 
     loop {
         tokio::select! {
@@ -162,9 +240,33 @@ async fn proxy_data(
                     // EOF, the socket was closed
                     return Ok(());
                 }
+
+                let conditions = *conditions.borrow_and_update();
+
+                let remaining = conditions
+                    .disconnect_after_bytes
+                    .map(|limit| limit.saturating_sub(forwarded));
+                let len = remaining.map_or(len, |remaining| len.min(remaining as usize));
+
+                tokio::time::sleep(conditions.delay(len)).await;
                 to.write_all(&buf[0..len]).await?;
+                forwarded += len as u64;
+
+                if remaining.is_some_and(|remaining| remaining <= len as u64) {
+                    debug!("ChaosProxy disconnecting in the middle of the stream");
+                    to.shutdown().await?;
+                    return Ok(());
+                }
+            }
+            _ = signals.break_networking.recv() => {
+                to.shutdown().await?;
+                return Ok(());
+            }
+            _ = signals.disconnect.recv() => {
+                to.shutdown().await?;
+                return Ok(());
             }
-            _ = break_connections_recv.recv() => {
+            _ = signals.connection_closed.recv() => {
                 to.shutdown().await?;
                 return Ok(());
             }
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Duration, Utc};
+use crates_io::clock::Clock;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// A clock that runs at the system time plus an offset, which tests can
+/// increase to fast-forward time (see [`TestApp::advance_time()`]).
+///
+/// The clock keeps running, since timestamps like the creation time of an
+/// ownership invitation are still set by the database.
+///
+/// [`TestApp::advance_time()`]: crate::util::TestApp::advance_time
+#[derive(Clone)]
+pub struct MockClock {
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self {
+            offset: Arc::new(Mutex::new(Duration::zero())),
+        }
+    }
+}
+
+impl MockClock {
+    pub fn advance(&self, duration: Duration) {
+        *self.offset.lock() += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() + *self.offset.lock()
+    }
+}
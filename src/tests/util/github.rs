@@ -6,6 +6,10 @@ use crates_io_github::{
 };
 use oauth2::AccessToken;
 
+/// Access token that the mock client rejects, like GitHub does after a user
+/// revoked the authorization of the OAuth app.
+pub(crate) const REVOKED_GITHUB_TOKEN: &str = "revoked token";
+
 pub(crate) const MOCK_GITHUB_DATA: MockData = MockData {
     orgs: &[MockOrg {
         id: 1000,
@@ -80,8 +84,9 @@ impl GitHubClient for MockGitHubClient {
     async fn org_by_name(
         &self,
         org_name: &str,
-        _auth: &AccessToken,
+        auth: &AccessToken,
     ) -> Result<GitHubOrganization, GitHubError> {
+        check_token(auth)?;
         let org = self
             .data
             .orgs
@@ -100,6 +105,7 @@ impl GitHubClient for MockGitHubClient {
         team_name: &str,
         auth: &AccessToken,
     ) -> Result<GitHubTeam, GitHubError> {
+        check_token(auth)?;
         let team = self
             .data
             .orgs
@@ -122,8 +128,9 @@ impl GitHubClient for MockGitHubClient {
         org_id: i32,
         team_id: i32,
         username: &str,
-        _auth: &AccessToken,
+        auth: &AccessToken,
     ) -> Result<GitHubTeamMembership, GitHubError> {
+        check_token(auth)?;
         let team = self
             .data
             .orgs
@@ -147,8 +154,9 @@ impl GitHubClient for MockGitHubClient {
         &self,
         org_id: i32,
         username: &str,
-        _auth: &AccessToken,
+        auth: &AccessToken,
     ) -> Result<GitHubOrgMembership, GitHubError> {
+        check_token(auth)?;
         let org = self
             .data
             .orgs
@@ -183,6 +191,14 @@ impl GitHubClient for MockGitHubClient {
     }
 }
 
+fn check_token(auth: &AccessToken) -> Result<(), GitHubError> {
+    if auth.secret() == REVOKED_GITHUB_TOKEN || auth.secret().is_empty() {
+        return Err(GitHubError::Unauthorized(anyhow!("401")));
+    }
+
+    Ok(())
+}
+
 fn not_found() -> GitHubError {
     GitHubError::NotFound(anyhow!("404"))
 }
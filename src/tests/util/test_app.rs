@@ -1,5 +1,6 @@
 use super::{MockAnonymousUser, MockCookieUser, MockTokenUser};
 use crate::util::chaosproxy::ChaosProxy;
+use crate::util::clock::MockClock;
 use crate::util::github::{MockGitHubClient, MOCK_GITHUB_DATA};
 use crates_io::config::{
     self, Base, CdnLogQueueConfig, CdnLogStorageConfig, DatabasePools, DbPoolConfig,
@@ -9,6 +10,7 @@ use crates_io::models::token::{CrateScope, EndpointScope};
 use crates_io::rate_limiter::{LimitedAction, RateLimiterConfig};
 use crates_io::storage::StorageConfig;
 use crates_io::team_repo::MockTeamRepo;
+use crates_io::upstream::MockUpstreamRegistry;
 use crates_io::worker::{Environment, RunnerExt};
 use crates_io::{App, Emails, Env};
 use crates_io_index::testing::UpstreamIndex;
@@ -18,7 +20,7 @@ use crates_io_worker::Runner;
 use diesel::PgConnection;
 use futures_util::TryStreamExt;
 use oauth2::{ClientId, ClientSecret};
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::{rc::Rc, sync::Arc, time::Duration};
 use tokio::runtime::Handle;
 use tokio::task::block_in_place;
@@ -32,6 +34,8 @@ struct TestAppInner {
     primary_db_chaosproxy: Option<Arc<ChaosProxy>>,
     replica_db_chaosproxy: Option<Arc<ChaosProxy>>,
 
+    clock: MockClock,
+
     // Must be the last field of the struct!
     test_database: TestDatabase,
 }
@@ -96,6 +100,7 @@ impl TestApp {
             build_job_runner: false,
             use_chaos_proxy: false,
             team_repo: MockTeamRepo::new(),
+            upstream: None,
         }
     }
 
@@ -182,6 +187,12 @@ impl TestApp {
         &self.0.app
     }
 
+    /// Fast-forwards the clock of the application, e.g. to let rate limits
+    /// refill or API tokens and ownership invitations expire.
+    pub fn advance_time(&self, duration: chrono::Duration) {
+        self.0.clock.advance(duration);
+    }
+
     /// Obtain a reference to the axum Router
     pub fn router(&self) -> &axum::Router {
         &self.0.router
@@ -208,6 +219,7 @@ pub struct TestAppBuilder {
     build_job_runner: bool,
     use_chaos_proxy: bool,
     team_repo: MockTeamRepo,
+    upstream: Option<MockUpstreamRegistry>,
 }
 
 impl TestAppBuilder {
@@ -252,7 +264,8 @@ impl TestAppBuilder {
             (primary_proxy, replica_proxy)
         };
 
-        let (app, router) = build_app(self.config);
+        let clock = MockClock::default();
+        let (app, router) = build_app(self.config, self.upstream, clock.clone());
 
         let runner = if self.build_job_runner {
             let index = self
@@ -292,6 +305,7 @@ impl TestAppBuilder {
             runner,
             primary_db_chaosproxy,
             replica_db_chaosproxy,
+            clock,
         };
         let test_app = TestApp(Rc::new(test_app_inner));
         let anon = MockAnonymousUser {
@@ -334,6 +348,7 @@ impl TestAppBuilder {
     pub fn with_rate_limit(self, action: LimitedAction, rate: Duration, burst: i32) -> Self {
         self.with_config(|config| {
             config
+                .reloadable
                 .rate_limiter
                 .insert(action, RateLimiterConfig { rate, burst });
         })
@@ -359,6 +374,11 @@ impl TestAppBuilder {
         self
     }
 
+    pub fn with_upstream(mut self, upstream: MockUpstreamRegistry) -> Self {
+        self.upstream = Some(upstream);
+        self
+    }
+
     pub fn with_replica(mut self) -> Self {
         let primary = &self.config.db.primary;
 
@@ -367,6 +387,7 @@ impl TestAppBuilder {
             read_only_mode: true,
             pool_size: primary.pool_size,
             min_idle: primary.min_idle,
+            statement_timeout: primary.statement_timeout,
         });
 
         self
@@ -385,11 +406,12 @@ fn simple_config() -> config::Server {
             read_only_mode: false,
             pool_size: 3,
             min_idle: None,
+            statement_timeout: Duration::from_secs(1),
         },
         replica: None,
         tcp_timeout_ms: 1000, // 1 second
         connection_timeout: Duration::from_secs(1),
-        statement_timeout: Duration::from_secs(1),
+        slow_query_threshold: Duration::from_secs(1),
         helper_threads: 1,
         enforce_tls: false,
     };
@@ -413,24 +435,26 @@ fn simple_config() -> config::Server {
         max_unpack_size: 128 * 1024, // 128 kB should be enough for most testing purposes
         max_features: 10,
         max_dependencies: 10,
-        rate_limiter: Default::default(),
+        reloadable: Default::default(),
+        rate_limiter_redis_url: None,
         new_version_rate_limit: Some(10),
-        blocked_traffic: Default::default(),
+        download_rate_limiter: None,
+        auth_failure_limiter: Default::default(),
         blocked_ips: Default::default(),
         max_allowed_page_offset: 200,
         page_offset_ua_blocklist: vec![],
-        page_offset_cidr_blocklist: vec![],
-        excluded_crate_names: vec![],
+        page_offset_cidr_blocklist: Default::default(),
         domain_name: "crates.io".into(),
         allowed_origins: Default::default(),
         downloads_persist_interval: Duration::from_secs(1),
         ownership_invitations_expiration_days: 30,
-        metrics_authorization_token: None,
+        metrics_authorization_tokens: vec![],
         instance_metrics_log_every_seconds: None,
-        blocked_routes: HashSet::new(),
+        route_concurrency_limits: HashMap::new(),
         version_id_cache_size: 10000,
         version_id_cache_ttl: Duration::from_secs(5 * 60),
-        cdn_user_agent: "Amazon CloudFront".to_string(),
+        crate_id_cache_size: 10000,
+        crate_id_cache_ttl: Duration::from_secs(5 * 60),
 
         // The middleware has its own unit tests to verify its functionality.
         // Here, we can test what would happen if we toggled the status code
@@ -441,10 +465,33 @@ fn simple_config() -> config::Server {
         serve_dist: false,
         serve_html: false,
         content_security_policy: None,
+        sandbox_reset_enabled: false,
+        challenge: None,
+        tls: None,
+        http: Default::default(),
+        request_timeouts: Default::default(),
+        client_ip: Default::default(),
+        index_signing_keys: vec![],
+        search_ranking: Default::default(),
+        invitation_report_emails: vec![],
+        token_anomalies: Default::default(),
+        download_spikes: Default::default(),
+        upstream: None,
+        safe_fetch: Default::default(),
+        readme_image_proxy: false,
+        api_quotas: Default::default(),
+        audit_export: None,
+        api_deprecations: Default::default(),
+        publish_nonces: Default::default(),
+        cdn_fallback: Default::default(),
     }
 }
 
-fn build_app(config: config::Server) -> (Arc<App>, axum::Router) {
+fn build_app(
+    config: config::Server,
+    upstream: Option<MockUpstreamRegistry>,
+    clock: MockClock,
+) -> (Arc<App>, axum::Router) {
     // Use the in-memory email backend for all tests, allowing tests to analyze the emails sent by
     // the application. This will also prevent cluttering the filesystem.
     let emails = Emails::new_in_memory();
@@ -453,7 +500,17 @@ fn build_app(config: config::Server) -> (Arc<App>, axum::Router) {
     // organizations without actually having to create GitHub accounts.
     let github = Box::new(MockGitHubClient::new(&MOCK_GITHUB_DATA));
 
-    let app = App::new(config, emails, github);
+    let mut app = App::new(config, emails, github);
+
+    // Use a mock of the upstream registry for the tests of the upstream-proxy
+    // mode, instead of fetching crates from crates.io.
+    if let Some(upstream) = upstream {
+        app.upstream = Some(Box::new(upstream));
+    }
+
+    // Use a clock that the tests can fast-forward, instead of sleeping or
+    // moving timestamps in the database.
+    app.clock = Arc::new(clock);
 
     let app = Arc::new(app);
     let router = crates_io::build_handler(Arc::clone(&app));
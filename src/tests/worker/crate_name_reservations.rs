@@ -0,0 +1,45 @@
+use crate::util::TestApp;
+use chrono::{TimeDelta, Utc};
+use crates_io::schema::crate_name_reservations;
+use crates_io::worker::jobs::ExpireCrateNameReservations;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn expire_crate_name_reservations() {
+    let (app, _, user) = TestApp::full().with_user();
+    let user_id = user.as_model().id;
+
+    app.db(|conn| {
+        let now = Utc::now().naive_utc();
+        let day = TimeDelta::try_days(1).unwrap();
+
+        diesel::insert_into(crate_name_reservations::table)
+            .values(&vec![
+                (
+                    crate_name_reservations::name.eq("expired"),
+                    crate_name_reservations::user_id.eq(user_id),
+                    crate_name_reservations::expires_at.eq(now - day),
+                ),
+                (
+                    crate_name_reservations::name.eq("active"),
+                    crate_name_reservations::user_id.eq(user_id),
+                    crate_name_reservations::expires_at.eq(now + day),
+                ),
+            ])
+            .execute(conn)
+            .unwrap();
+
+        ExpireCrateNameReservations.enqueue(conn).unwrap();
+    });
+
+    app.run_pending_background_jobs().await;
+
+    let names: Vec<String> = app.db(|conn| {
+        crate_name_reservations::table
+            .select(crate_name_reservations::name)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(names, vec!["active"]);
+}
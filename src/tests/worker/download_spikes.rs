@@ -0,0 +1,114 @@
+use crate::builders::CrateBuilder;
+use crate::util::TestApp;
+use chrono::{NaiveDate, TimeDelta, Utc};
+use crates_io::schema::{crates, download_spikes, version_downloads, versions};
+use crates_io::worker::jobs::DetectDownloadSpikes;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+
+/// Inserts the downloads of the only version of the crate on each of the
+/// given number of days ago.
+fn save_downloads(conn: &mut PgConnection, crate_id: i32, downloads: &[(i64, i32)]) {
+    let version_id: i32 = versions::table
+        .filter(versions::crate_id.eq(crate_id))
+        .select(versions::id)
+        .first(conn)
+        .unwrap();
+
+    let today = Utc::now().date_naive();
+    for (days_ago, count) in downloads {
+        diesel::insert_into(version_downloads::table)
+            .values((
+                version_downloads::version_id.eq(version_id),
+                version_downloads::date.eq(today - TimeDelta::days(*days_ago)),
+                version_downloads::downloads.eq(count),
+            ))
+            .execute(conn)
+            .unwrap();
+    }
+}
+
+fn sent_emails(app: &TestApp) -> Vec<(Vec<String>, String)> {
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    emails
+        .into_iter()
+        .map(|(envelope, email)| {
+            let to = envelope.to().iter().map(ToString::to_string).collect();
+            (to, email)
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn detects_download_spikes() {
+    let (app, _, user) = TestApp::full()
+        .with_config(|config| {
+            config.download_spikes.min_downloads = 1000;
+            config.download_spikes.notify_owners = true;
+            config.download_spikes.notification_emails = vec!["security@crates.io".into()];
+        })
+        .with_user();
+
+    let user_id = user.as_model().id;
+    let last_year = Utc::now().naive_utc() - TimeDelta::days(365);
+
+    let spiky = app.db(|conn| {
+        let spiky = CrateBuilder::new("spiky", user_id).expect_build(conn);
+        let steady = CrateBuilder::new("steady", user_id).expect_build(conn);
+        let young = CrateBuilder::new("young", user_id).expect_build(conn);
+
+        diesel::update(crates::table.filter(crates::id.eq_any([spiky.id, steady.id])))
+            .set(crates::created_at.eq(last_year))
+            .execute(conn)
+            .unwrap();
+
+        let usual_days = (2..30).map(|days_ago| (days_ago, 200)).collect::<Vec<_>>();
+
+        save_downloads(conn, spiky.id, &usual_days);
+        save_downloads(conn, spiky.id, &[(1, 5000)]);
+
+        save_downloads(conn, steady.id, &usual_days);
+        save_downloads(conn, steady.id, &[(1, 1500)]);
+
+        // Crates without download history are not analyzed
+        save_downloads(conn, young.id, &[(1, 5000)]);
+
+        spiky
+    });
+
+    app.db(|conn| DetectDownloadSpikes.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let spikes: Vec<(i32, NaiveDate, i64, i64)> = app.db(|conn| {
+        download_spikes::table
+            .select((
+                download_spikes::crate_id,
+                download_spikes::date,
+                download_spikes::downloads,
+                download_spikes::baseline,
+            ))
+            .load(conn)
+            .unwrap()
+    });
+
+    let yesterday = Utc::now().date_naive() - TimeDelta::days(1);
+    assert_eq!(spikes, vec![(spiky.id, yesterday, 5000, 200)]);
+
+    let emails = sent_emails(&app);
+    let recipients = emails.iter().map(|(to, _)| to.clone()).collect::<Vec<_>>();
+    assert_eq!(
+        recipients,
+        vec![
+            vec!["security@crates.io".to_string()],
+            vec!["something@example.com".to_string()],
+        ]
+    );
+    assert!(emails[0]
+        .1
+        .contains("The crate spiky was downloaded 5000 times"));
+
+    // Spikes are only reported once
+    app.db(|conn| DetectDownloadSpikes.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+    assert_eq!(sent_emails(&app).len(), 2);
+}
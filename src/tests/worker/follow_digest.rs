@@ -0,0 +1,120 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use chrono::{TimeDelta, Utc};
+use crates_io::schema::{follows, users};
+use crates_io::worker::jobs::SendFollowDigest;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::{assert_debug_snapshot, assert_snapshot};
+use regex::Regex;
+
+fn sent_emails(app: &TestApp) -> Vec<String> {
+    let email_header_regex = Regex::new(r"(Message-ID|Date): [^\r\n]+\r\n").unwrap();
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    emails
+        .iter()
+        .map(|(_, email)| email_header_regex.replace_all(email, "").into_owned())
+        .collect()
+}
+
+fn unsubscribe_path(email: &str) -> String {
+    // Long lines are wrapped with soft line breaks by the quoted-printable encoding
+    let email = email.replace("=\r\n", "");
+    let regex = Regex::new(r"/unsubscribe/follow_digest/([0-9a-f.]+)").unwrap();
+    let token = &regex.captures(&email).unwrap()[1];
+    format!("/api/v1/unsubscribe/follow_digest/{token}")
+}
+
+fn follow_digest(app: &TestApp, user_id: i32) -> bool {
+    app.db(|conn| {
+        users::table
+            .find(user_id)
+            .select(users::follow_digest)
+            .first(conn)
+            .unwrap()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_follow_digest() {
+    let (app, anon, owner) = TestApp::full().with_user();
+    let owner = owner.as_model();
+    let follower = app.db_new_user("follower");
+    let follower_id = follower.as_model().id;
+    let opted_out = app.db_new_user("opted-out");
+    let opted_out_id = opted_out.as_model().id;
+
+    let last_month = Utc::now().naive_utc() - TimeDelta::try_days(30).unwrap();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo", owner.id)
+            .version(VersionBuilder::new("1.0.0").created_at(last_month))
+            .version("1.1.0")
+            .version("1.2.0")
+            .version(VersionBuilder::new("1.3.0").yanked(true))
+            .expect_build(conn);
+
+        let bar = CrateBuilder::new("bar", owner.id)
+            .version("0.1.0")
+            .expect_build(conn);
+
+        let old = CrateBuilder::new("old", owner.id)
+            .version(VersionBuilder::new("1.0.0").created_at(last_month))
+            .expect_build(conn);
+
+        let owned = CrateBuilder::new("owned", follower_id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        let follows = [
+            (follower_id, krate.id),
+            (follower_id, bar.id),
+            (follower_id, old.id),
+            (follower_id, owned.id),
+            (opted_out_id, krate.id),
+        ];
+
+        for (user_id, crate_id) in follows {
+            diesel::insert_into(follows::table)
+                .values((follows::user_id.eq(user_id), follows::crate_id.eq(crate_id)))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    let response = opted_out
+        .put::<OkBool>("/api/v1/me/follow_digest", r#"{"follow_digest":false}"#)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!follow_digest(&app, opted_out_id));
+
+    app.db(|conn| SendFollowDigest.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let emails = sent_emails(&app);
+    assert_debug_snapshot!(emails);
+
+    assert!(follow_digest(&app, follower_id));
+    let response = anon.put::<OkBool>(&unsubscribe_path(&emails[0]), "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!follow_digest(&app, follower_id));
+
+    app.db(|conn| SendFollowDigest.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+    assert_eq!(sent_emails(&app).len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unsubscribe_with_invalid_token() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+
+    let url = format!("/api/v1/unsubscribe/follow_digest/{user_id}.0123456789abcdef");
+    let response = anon.put::<()>(&url, "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_snapshot!(response.text(), @r###"{"errors":[{"detail":"invalid unsubscribe token"}]}"###);
+
+    assert!(follow_digest(&app, user_id));
+}
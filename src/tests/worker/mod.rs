@@ -1,2 +1,6 @@
+mod crate_name_reservations;
+mod download_spikes;
+mod follow_digest;
 mod git;
+mod sandbox;
 mod sync_admins;
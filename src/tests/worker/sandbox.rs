@@ -0,0 +1,89 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use chrono::{TimeDelta, Utc};
+use crates_io::models::ApiToken;
+use crates_io::schema::{crates, users, versions};
+use crates_io::worker::jobs::ResetSandbox;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use http::StatusCode;
+use insta::assert_debug_snapshot;
+use secrecy::ExposeSecret;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reset_sandbox() {
+    let (app, _, _, token) = TestApp::full()
+        .with_config(|config| {
+            config.domain_name = "staging.crates.io".into();
+            config.sandbox_reset_enabled = true;
+        })
+        .with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    let artifact_url = "/api/v1/crates/foo/1.0.0/artifacts/x86_64-unknown-linux-gnu";
+    let artifact: &[u8] = &[0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
+    let response = token.put::<()>(artifact_url, artifact).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let release_at = (Utc::now() + TimeDelta::try_days(1).unwrap()).naive_utc();
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.1.0").release_at(release_at))
+        .await
+        .good();
+    app.run_pending_background_jobs().await;
+
+    app.db(|conn| ResetSandbox.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let (users, versions) = app.db(|conn| {
+        let users: Vec<(i32, String)> = users::table
+            .select((users::id, users::gh_login))
+            .order(users::id)
+            .load(conn)
+            .unwrap();
+
+        let versions: Vec<(String, String, bool)> = versions::table
+            .inner_join(crates::table)
+            .select((crates::name, versions::num, versions::yanked))
+            .order((crates::name, versions::id))
+            .load(conn)
+            .unwrap();
+
+        (users, versions)
+    });
+
+    assert_debug_snapshot!(users);
+    assert_debug_snapshot!(versions);
+    assert_debug_snapshot!(app.stored_files().await);
+
+    app.db(|conn| {
+        let now = app.as_inner().clock.naive_now();
+
+        let sandbox_token = "cioSandboxAlice0000000000000000000";
+        let sandbox_token = ApiToken::find_by_api_token(conn, sandbox_token, now).unwrap();
+        assert_eq!(sandbox_token.user_id, 1);
+
+        let old_token = token.plaintext().expose_secret();
+        assert!(ApiToken::find_by_api_token(conn, old_token, now).is_err());
+    });
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reset_sandbox_is_disabled_by_default() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    token
+        .publish_crate(PublishBuilder::new("foo", "1.0.0"))
+        .await
+        .good();
+
+    app.db(|conn| ResetSandbox.enqueue(conn).unwrap());
+    app.run_pending_background_jobs().await;
+
+    let crates: Vec<String> = app.db(|conn| crates::table.select(crates::name).load(conn).unwrap());
+    assert_eq!(crates, vec!["foo"]);
+}
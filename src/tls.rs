@@ -0,0 +1,71 @@
+//! Terminating TLS in the server process
+//!
+//! Usually TLS is terminated by a load balancer in front of the server, but
+//! for deployments that expose the server directly it can be terminated
+//! locally instead. In that case, clients may also authenticate with a
+//! certificate, which is currently used to grant access to the metrics
+//! endpoints (see [`VerifiedClientCertificate`]).
+//!
+//! The connections themselves are served by [`crate::http_server::serve()`].
+
+use crate::config::TlsConfig;
+use anyhow::{anyhow, Context};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+/// Request extension that is present if the client presented a certificate
+/// that was signed by one of the configured client CAs.
+#[derive(Clone, Copy, Debug)]
+pub struct VerifiedClientCertificate;
+
+/// Builds a [`TlsAcceptor`] from the certificate, private key and optional
+/// client CAs of the configuration.
+///
+/// Clients are not required to present a certificate, so that regular
+/// requests keep working when client certificate verification is enabled.
+/// HTTP/2 is only offered to clients if `http2` is `true`.
+pub fn acceptor(config: &TlsConfig, http2: bool) -> anyhow::Result<TlsAcceptor> {
+    let certs = load_certs(&config.cert_path)?;
+
+    let mut reader = BufReader::new(File::open(&config.key_path)?);
+    let key = rustls_pemfile::private_key(&mut reader)?
+        .ok_or_else(|| anyhow!("No private key found in {}", config.key_path.display()))?;
+
+    let builder = ServerConfig::builder();
+    let builder = match &config.client_ca_path {
+        Some(client_ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(client_ca_path)? {
+                roots.add(cert)?;
+            }
+
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .allow_unauthenticated()
+                .build()?;
+
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder.with_single_cert(certs, key)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    if http2 {
+        config.alpn_protocols.insert(0, b"h2".to_vec());
+    }
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read certificates from {}", path.display()))
+}
@@ -3,6 +3,7 @@ use std::collections::BTreeMap;
 use diesel::{prelude::*, PgConnection};
 
 use crate::{
+    audit::AuditLog,
     models::{
         Crate, CrateOwner, NewCrate, NewTeam, NewUser, NewVersion, Owner, OwnerKind, User, Version,
     },
@@ -58,7 +59,7 @@ impl Faker {
             description: Some(description),
             ..Default::default()
         }
-        .create(conn, user.id)?;
+        .create(conn, user.id, &AuditLog::default())?;
 
         diesel::update(crate_downloads::table)
             .filter(crate_downloads::crate_id.eq(krate.id))
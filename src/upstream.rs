@@ -0,0 +1,79 @@
+//! Upstream-proxy mode for private registry deployments
+//!
+//! If an upstream registry is configured (see [`UpstreamConfig`]), crates
+//! that were not published to this registry are fetched from the upstream
+//! registry on demand:
+//!
+//! - the sparse index files are passed through by the `/index/*path` route,
+//! - the crate files are downloaded from the upstream registry on the first
+//!   download and cached in the local [`Storage`](crate::storage::Storage).
+//!
+//! Crates that were published to this registry always take precedence. To
+//! avoid shadowing upstream crates unintentionally, new crates can't be
+//! published with a name that is already used in the upstream registry.
+
+use crate::config::UpstreamConfig;
+use async_trait::async_trait;
+use bytes::Bytes;
+use mockall::automock;
+use reqwest::{Client, StatusCode};
+
+#[automock]
+#[async_trait]
+pub trait UpstreamRegistry {
+    /// Returns the content of the sparse index file of the crate, or `None`
+    /// if the crate does not exist in the upstream registry.
+    async fn index_file(&self, name: &str) -> anyhow::Result<Option<Bytes>>;
+
+    /// Returns the crate file of the crate version, or `None` if the version
+    /// does not exist in the upstream registry.
+    async fn crate_file(&self, name: &str, version: &str) -> anyhow::Result<Option<Bytes>>;
+}
+
+impl dyn UpstreamRegistry {
+    pub fn from_config(
+        config: &UpstreamConfig,
+        client: Client,
+    ) -> Box<dyn UpstreamRegistry + Send + Sync> {
+        Box::new(HttpUpstreamRegistry::new(client, config.clone()))
+    }
+}
+
+/// Fetches the index files and crate files of an upstream registry over HTTP.
+pub struct HttpUpstreamRegistry {
+    client: Client,
+    config: UpstreamConfig,
+}
+
+impl HttpUpstreamRegistry {
+    pub fn new(client: Client, config: UpstreamConfig) -> Self {
+        Self { client, config }
+    }
+
+    async fn get(&self, url: String) -> anyhow::Result<Option<Bytes>> {
+        let response = self.client.get(url).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let bytes = response.error_for_status()?.bytes().await?;
+        Ok(Some(bytes))
+    }
+}
+
+#[async_trait]
+impl UpstreamRegistry for HttpUpstreamRegistry {
+    #[instrument(skip(self))]
+    async fn index_file(&self, name: &str) -> anyhow::Result<Option<Bytes>> {
+        let path = crates_io_index::Repository::relative_index_file_for_url(name);
+        let url = format!("{}/{path}", self.config.index_url);
+        self.get(url).await
+    }
+
+    #[instrument(skip(self))]
+    async fn crate_file(&self, name: &str, version: &str) -> anyhow::Result<Option<Bytes>> {
+        let version = version.replace('+', "%2B");
+        let url = format!("{}/{name}/{name}-{version}.crate", self.config.dl_url);
+        self.get(url).await
+    }
+}
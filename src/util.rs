@@ -3,14 +3,21 @@ use std::cmp;
 pub use self::bytes_request::BytesRequest;
 pub use self::io_util::{read_fill, read_le_u32};
 pub use self::request_helpers::*;
+pub use crates_io_api_types::rfc3339;
 
+pub mod accept_language;
 mod bytes_request;
+pub mod data_export;
 pub mod errors;
+pub mod image_proxy;
 mod io_util;
+pub mod ip_blocklist;
 mod request_helpers;
-pub mod rfc3339;
+pub mod retry;
 pub mod token;
+pub mod token_revocation;
 pub mod tracing;
+pub mod unsubscribe;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Maximums {
@@ -0,0 +1,158 @@
+//! Parsing of the `Accept-Language` header.
+//!
+//! The best matching language is selected with the "lookup" scheme of
+//! [RFC 4647](https://www.rfc-editor.org/rfc/rfc4647#section-3.4): the
+//! language ranges are tried in the order of their quality values, and
+//! subtags are removed from the end of each range until one of the available
+//! language tags matches. A request for `de-CH` is therefore answered with
+//! `de` if there is no `de-CH` variant.
+
+use http::{header, HeaderMap};
+
+/// The language ranges of an `Accept-Language` header, ordered by preference.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AcceptLanguage {
+    ranges: Vec<String>,
+}
+
+impl AcceptLanguage {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(Self::parse)
+            .unwrap_or_default()
+    }
+
+    /// Parses the value of an `Accept-Language` header.
+    ///
+    /// Invalid entries, the `*` wildcard and ranges with a quality value of
+    /// zero are skipped, since they never select a specific language.
+    pub fn parse(value: &str) -> Self {
+        let mut ranges = value
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';').map(str::trim);
+                let range = parts.next()?;
+                if range == "*" || !is_valid_language_tag(range) {
+                    return None;
+                }
+
+                let mut quality = 1.;
+                for param in parts {
+                    if let Some(q) = param.strip_prefix("q=") {
+                        quality = q.parse::<f32>().ok().filter(|q| (0. ..=1.).contains(q))?;
+                    }
+                }
+
+                (quality > 0.).then(|| (range.to_ascii_lowercase(), quality))
+            })
+            .collect::<Vec<_>>();
+
+        // The sort is stable, so ranges with the same quality keep their order.
+        ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        Self {
+            ranges: ranges.into_iter().map(|(range, _)| range).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the available language tag that best matches the header, or
+    /// `None` if none of them is acceptable.
+    pub fn best_match<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        self.ranges.iter().find_map(|range| {
+            let mut range = range.as_str();
+            loop {
+                let found = available.iter().find(|tag| tag.eq_ignore_ascii_case(range));
+                if let Some(tag) = found {
+                    return Some(*tag);
+                }
+
+                let (prefix, _) = range.rsplit_once('-')?;
+                range = prefix;
+
+                // Single-character subtags like the `x` of private use
+                // subtags cannot stand at the end of a tag.
+                if let Some((prefix, last)) = range.rsplit_once('-') {
+                    if last.len() == 1 {
+                        range = prefix;
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Returns `true` if the tag is a well-formed language tag like `de`,
+/// `pt-BR` or `zh-Hant-TW`.
+///
+/// The subtags are not checked against the registry of the IANA, so tags
+/// like `xx` are accepted too.
+pub fn is_valid_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+
+    let language = subtags.next().unwrap_or_default();
+    let valid_language =
+        (2..=8).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic());
+
+    valid_language
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(value: &str) -> Vec<String> {
+        AcceptLanguage::parse(value).ranges
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(ranges(""), Vec::<String>::new());
+        assert_eq!(ranges("de"), vec!["de"]);
+        assert_eq!(
+            ranges("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5"),
+            vec!["fr-ch", "fr", "en", "de"]
+        );
+        assert_eq!(ranges("en;q=0.5, de, fr;q=0.9"), vec!["de", "fr", "en"]);
+        assert_eq!(ranges("de;q=0, en"), vec!["en"]);
+        assert_eq!(ranges("de;q=2, en;q=foo, ../..,fr"), vec!["fr"]);
+    }
+
+    #[test]
+    fn best_match() {
+        let available = ["de", "pt-BR", "zh-Hant"];
+        let best_match = |value| AcceptLanguage::parse(value).best_match(&available);
+
+        assert_eq!(best_match(""), None);
+        assert_eq!(best_match("en"), None);
+        assert_eq!(best_match("de"), Some("de"));
+        assert_eq!(best_match("de-CH"), Some("de"));
+        assert_eq!(best_match("DE-ch-x-private"), Some("de"));
+        assert_eq!(best_match("pt-br"), Some("pt-BR"));
+        assert_eq!(best_match("pt"), None);
+        assert_eq!(best_match("zh-Hant-TW"), Some("zh-Hant"));
+        assert_eq!(best_match("en, de;q=0.5, zh-Hant;q=0.8"), Some("zh-Hant"));
+    }
+
+    #[test]
+    fn language_tags() {
+        assert!(is_valid_language_tag("de"));
+        assert!(is_valid_language_tag("pt-BR"));
+        assert!(is_valid_language_tag("zh-Hant-TW"));
+        assert!(is_valid_language_tag("sl-rozaj-biske"));
+        assert!(!is_valid_language_tag(""));
+        assert!(!is_valid_language_tag("d"));
+        assert!(!is_valid_language_tag("de-"));
+        assert!(!is_valid_language_tag("de_DE"));
+        assert!(!is_valid_language_tag("de-verylongsubtag"));
+        assert!(!is_valid_language_tag("../de"));
+    }
+}
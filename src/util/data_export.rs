@@ -0,0 +1,98 @@
+//! Signed tokens for the download links of personal data exports.
+//!
+//! Like the unsubscribe tokens (see [`crate::util::unsubscribe`]), the tokens
+//! are not stored in the database. They consist of the user ID, the random ID
+//! of the export, the expiry time of the link and an HMAC of all three, keyed
+//! with the signing key of the session cookies, so that a link can't be
+//! altered to download another export or used after it has expired.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Generates a download token for the data export `export_id` of the given
+/// user, that is valid until `expires_at` (a UNIX timestamp).
+pub fn generate_token(key: &cookie::Key, user_id: i32, export_id: &str, expires_at: i64) -> String {
+    let signature = mac(key, user_id, export_id, expires_at)
+        .finalize()
+        .into_bytes();
+
+    let signature = hex::encode(signature);
+    format!("{user_id}.{export_id}.{expires_at}.{signature}")
+}
+
+/// Returns the user ID and the export ID that the token was generated for,
+/// or `None` if the token is malformed or has expired at the time `now` (a
+/// UNIX timestamp).
+pub fn verify_token<'a>(key: &cookie::Key, token: &'a str, now: i64) -> Option<(i32, &'a str)> {
+    let mut parts = token.splitn(4, '.');
+    let user_id = parts.next()?.parse().ok()?;
+    let export_id = parts.next()?;
+    let expires_at = parts.next()?.parse().ok()?;
+    let signature = hex::decode(parts.next()?).ok()?;
+
+    mac(key, user_id, export_id, expires_at)
+        .verify_slice(&signature)
+        .ok()?;
+
+    (now < expires_at).then_some((user_id, export_id))
+}
+
+fn mac(key: &cookie::Key, user_id: i32, export_id: &str, expires_at: i64) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(key.signing()).expect("HMAC can take a key of any size");
+    mac.update(b"data_export:");
+    mac.update(user_id.to_string().as_bytes());
+    mac.update(b":");
+    mac.update(export_id.as_bytes());
+    mac.update(b":");
+    mac.update(expires_at.to_string().as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> cookie::Key {
+        cookie::Key::derive_from("test this has to be over 32 bytes long".as_bytes())
+    }
+
+    #[test]
+    fn test_generate_and_verify() {
+        let token = generate_token(&key(), 42, "abcdef", 1000);
+        assert!(token.starts_with("42.abcdef.1000."));
+        assert_eq!(verify_token(&key(), &token, 999), Some((42, "abcdef")));
+    }
+
+    #[test]
+    fn test_verify_rejects_expired_tokens() {
+        let token = generate_token(&key(), 42, "abcdef", 1000);
+        assert_eq!(verify_token(&key(), &token, 1000), None);
+        assert_eq!(verify_token(&key(), &token, 2000), None);
+    }
+
+    #[test]
+    fn test_verify_rejects_invalid_tokens() {
+        let token = generate_token(&key(), 42, "abcdef", 1000);
+        let signature = token.rsplit('.').next().unwrap();
+
+        let other_key =
+            cookie::Key::derive_from("a different key that is also over 32 bytes".as_bytes());
+        assert_eq!(verify_token(&other_key, &token, 999), None);
+
+        let altered = [
+            format!("43.abcdef.1000.{signature}"),
+            format!("42.abcdeg.1000.{signature}"),
+            format!("42.abcdef.2000.{signature}"),
+        ];
+        for token in altered {
+            assert_eq!(verify_token(&key(), &token, 999), None);
+        }
+
+        assert_eq!(verify_token(&key(), "42.abcdef.1000", 999), None);
+        assert_eq!(verify_token(&key(), "42.abcdef.1000.zz", 999), None);
+        assert_eq!(verify_token(&key(), "", 999), None);
+    }
+}
@@ -25,14 +25,20 @@ use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use http::StatusCode;
 use tokio::task::JoinError;
 
+use crate::middleware::deadline::DeadlineExceeded;
 use crate::middleware::log_request::ErrorField;
+use crate::middleware::problem_json::ProblemJson;
 
 mod json;
 
 use crate::email::EmailError;
 use crates_io_github::GitHubError;
+pub use json::ValidationErrors;
 pub use json::TOKEN_FORMAT_ERROR;
-pub(crate) use json::{custom, InsecurelyGeneratedTokenRevoked, ReadOnlyMode, TooManyRequests};
+pub(crate) use json::{
+    custom, custom_with_code, InsecurelyGeneratedTokenRevoked, ReadOnlyMode, TooManyAuthFailures,
+    TooManyRequests,
+};
 
 pub type BoxedAppError = Box<dyn AppError>;
 
@@ -52,7 +58,7 @@ pub fn account_locked(reason: &str, until: Option<NaiveDateTime>) -> BoxedAppErr
         .map(|until| format!("This account is locked until {until}. Reason: {reason}"))
         .unwrap_or_else(|| format!("This account is indefinitely locked. Reason: {reason}"));
 
-    custom(StatusCode::FORBIDDEN, detail)
+    custom_with_code(StatusCode::FORBIDDEN, "account_locked", detail)
 }
 
 pub fn forbidden(detail: impl Into<Cow<'static, str>>) -> BoxedAppError {
@@ -75,12 +81,12 @@ pub fn service_unavailable() -> BoxedAppError {
 
 pub fn crate_not_found(krate: &str) -> BoxedAppError {
     let detail = format!("crate `{krate}` does not exist");
-    custom(StatusCode::NOT_FOUND, detail)
+    custom_with_code(StatusCode::NOT_FOUND, "crate_not_found", detail)
 }
 
 pub fn version_not_found(krate: &str, version: &str) -> BoxedAppError {
     let detail = format!("crate `{krate}` does not have a version `{version}`");
-    custom(StatusCode::NOT_FOUND, detail)
+    custom_with_code(StatusCode::NOT_FOUND, "version_not_found", detail)
 }
 
 // =============================================================================
@@ -163,6 +169,10 @@ impl From<EmailError> for BoxedAppError {
         match error {
             EmailError::AddressError(error) => Box::new(error),
             EmailError::MessageBuilderError(error) => Box::new(error),
+            EmailError::TemplateError(error) => {
+                error!(?error, "Failed to render email template");
+                server_error("Failed to send the email")
+            }
             EmailError::TransportError(error) => {
                 error!(?error, "Failed to send email");
                 server_error("Failed to send the email")
@@ -219,9 +229,24 @@ impl From<JoinError> for BoxedAppError {
     }
 }
 
+impl From<DeadlineExceeded> for BoxedAppError {
+    fn from(_err: DeadlineExceeded) -> BoxedAppError {
+        custom_with_code(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "request_timeout",
+            "Request timed out",
+        )
+    }
+}
+
 impl From<GitHubError> for BoxedAppError {
     fn from(error: GitHubError) -> Self {
         match error {
+            GitHubError::Unauthorized(_) => custom(
+                StatusCode::FORBIDDEN,
+                "GitHub rejected your authorization of crates.io. \
+                     Please sign in to crates.io again.",
+            ),
             GitHubError::Permission(_) => custom(
                 StatusCode::FORBIDDEN,
                 "It looks like you don't have permission \
@@ -269,9 +294,19 @@ pub fn internal<S: ToString>(error: S) -> BoxedAppError {
 }
 
 fn server_error_response(error: String) -> axum::response::Response {
+    let error = Extension(ErrorField(error));
+
+    // The details of internal errors are only logged, not returned
+    if let Some(problem_json) = ProblemJson::current() {
+        let errors = vec![json!({ "detail": "Internal Server Error" })];
+        let status = StatusCode::INTERNAL_SERVER_ERROR;
+        let response = problem_json.response(status, "internal_error", errors);
+        return (error, response).into_response();
+    }
+
     (
         StatusCode::INTERNAL_SERVER_ERROR,
-        Extension(ErrorField(error)),
+        error,
         "Internal Server Error",
     )
         .into_response()
@@ -316,4 +351,50 @@ mod tests {
             StatusCode::INTERNAL_SERVER_ERROR
         );
     }
+
+    #[tokio::test]
+    async fn validation_errors() {
+        let errors = ValidationErrors::new();
+        assert!(errors.into_result().is_ok());
+
+        let mut errors = ValidationErrors::new();
+        errors.add("keywords", "expected at most 5 keywords per crate");
+        errors.check("license", Err::<(), _>("invalid license expression"));
+        errors.check("homepage", Ok::<_, String>(()));
+        errors.add_with_suggestions(
+            "categories",
+            "tests",
+            "unknown category",
+            vec!["testing".into()],
+        );
+
+        let error = errors.into_result().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected at most 5 keywords per crate; invalid license expression; unknown category"
+        );
+
+        let response = error.response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "errors": [
+                    { "detail": "expected at most 5 keywords per crate", "field": "keywords" },
+                    { "detail": "invalid license expression", "field": "license" },
+                    {
+                        "detail": "unknown category",
+                        "field": "categories",
+                        "value": "tests",
+                        "suggestions": ["testing"],
+                    },
+                ]
+            })
+        );
+    }
 }
@@ -2,18 +2,48 @@ use axum::response::{IntoResponse, Response};
 use axum::{Extension, Json};
 use std::borrow::Cow;
 use std::fmt;
+use std::time::Duration;
 
 use super::{AppError, BoxedAppError};
 
 use crate::middleware::log_request::CauseField;
+use crate::middleware::problem_json::ProblemJson;
 use crate::rate_limiter::LimitedAction;
 use chrono::NaiveDateTime;
 use http::{header, StatusCode};
+use serde_json::Value;
 
 /// Generates a response with the provided status and description as JSON
-fn json_error(detail: &str, status: StatusCode) -> Response {
-    let json = json!({ "errors": [{ "detail": detail }] });
-    (status, Json(json)).into_response()
+fn json_error(code: &str, detail: &str, status: StatusCode) -> Response {
+    error_response(status, code, vec![json!({ "detail": detail })])
+}
+
+/// Generates a response with the provided errors, either in the default
+/// `{"errors": [...]}` format or as a problem document if the client
+/// requested one.
+fn error_response(status: StatusCode, code: &str, errors: Vec<Value>) -> Response {
+    match ProblemJson::current() {
+        Some(problem_json) => problem_json.response(status, code, errors),
+        None => (status, Json(json!({ "errors": errors }))).into_response(),
+    }
+}
+
+/// Returns the error code of errors that don't have a more specific one.
+fn default_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        StatusCode::INTERNAL_SERVER_ERROR => "internal_error",
+        StatusCode::SERVICE_UNAVAILABLE => "service_unavailable",
+        status if status.is_client_error() => "client_error",
+        _ => "server_error",
+    }
 }
 
 // The following structs are empty and do not provide a custom message to the user
@@ -25,7 +55,7 @@ impl AppError for ReadOnlyMode {
     fn response(&self) -> Response {
         let detail = "crates.io is currently in read-only mode for maintenance. \
                       Please try again later.";
-        json_error(detail, StatusCode::SERVICE_UNAVAILABLE)
+        json_error("read_only_mode", detail, StatusCode::SERVICE_UNAVAILABLE)
     }
 }
 
@@ -38,8 +68,19 @@ impl fmt::Display for ReadOnlyMode {
 // The following structs wrap owned data and provide a custom message to the user
 
 pub fn custom(status: StatusCode, detail: impl Into<Cow<'static, str>>) -> BoxedAppError {
+    custom_with_code(status, default_code(status), detail)
+}
+
+/// Like [`custom()`], but with an error `code` that is more specific than the
+/// status, e.g. `crate_not_found` instead of `not_found`.
+pub fn custom_with_code(
+    status: StatusCode,
+    code: &'static str,
+    detail: impl Into<Cow<'static, str>>,
+) -> BoxedAppError {
     Box::new(CustomApiError {
         status,
+        code,
         detail: detail.into(),
     })
 }
@@ -47,6 +88,7 @@ pub fn custom(status: StatusCode, detail: impl Into<Cow<'static, str>>) -> Boxed
 #[derive(Debug, Clone)]
 pub struct CustomApiError {
     status: StatusCode,
+    code: &'static str,
     detail: Cow<'static, str>,
 }
 
@@ -58,7 +100,111 @@ impl fmt::Display for CustomApiError {
 
 impl AppError for CustomApiError {
     fn response(&self) -> Response {
-        json_error(&self.detail, self.status)
+        json_error(self.code, &self.detail, self.status)
+    }
+}
+
+/// Collects the validation errors of the fields of a request, so that all of
+/// them can be returned in a single response instead of only the first one.
+///
+/// The response has one entry per error, with the `field` that failed the
+/// validation next to the usual `detail` message. Errors that were recorded
+/// with [`ValidationErrors::add_with_suggestions()`] additionally contain the
+/// invalid `value` and a list of `suggestions`.
+#[derive(Debug, Default)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+#[derive(Debug)]
+struct FieldError {
+    field: &'static str,
+    detail: String,
+    value: Option<String>,
+    suggestions: Vec<String>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a validation error of `field`.
+    pub fn add(&mut self, field: &'static str, detail: impl ToString) {
+        let detail = detail.to_string();
+        self.errors.push(FieldError {
+            field,
+            detail,
+            value: None,
+            suggestions: vec![],
+        });
+    }
+
+    /// Records a validation error of `field` that was caused by one of its
+    /// values, together with similar valid values that could be used
+    /// instead.
+    pub fn add_with_suggestions(
+        &mut self,
+        field: &'static str,
+        value: impl ToString,
+        detail: impl ToString,
+        suggestions: Vec<String>,
+    ) {
+        self.errors.push(FieldError {
+            field,
+            detail: detail.to_string(),
+            value: Some(value.to_string()),
+            suggestions,
+        });
+    }
+
+    /// Records the error of `result` as a validation error of `field`, if
+    /// the validation failed.
+    pub fn check<T, E: fmt::Display>(&mut self, field: &'static str, result: Result<T, E>) {
+        if let Err(error) = result {
+            self.add(field, error);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns an error with all recorded validation errors, if there are
+    /// any.
+    pub fn into_result(self) -> Result<(), BoxedAppError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(Box::new(self))
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details = self.errors.iter().map(|error| error.detail.as_str());
+        details.collect::<Vec<_>>().join("; ").fmt(f)
+    }
+}
+
+impl AppError for ValidationErrors {
+    fn response(&self) -> Response {
+        let errors = self
+            .errors
+            .iter()
+            .map(|error| match &error.value {
+                Some(value) => json!({
+                    "detail": error.detail,
+                    "field": error.field,
+                    "value": value,
+                    "suggestions": error.suggestions,
+                }),
+                None => json!({ "detail": error.detail, "field": error.field }),
+            })
+            .collect::<Vec<_>>();
+
+        error_response(StatusCode::BAD_REQUEST, "validation_failed", errors)
     }
 }
 
@@ -78,7 +224,7 @@ impl AppError for TooManyRequests {
              help@crates.io to have your limit increased.",
             self.action.error_message()
         );
-        let mut response = json_error(&detail, StatusCode::TOO_MANY_REQUESTS);
+        let mut response = json_error("rate_limited", &detail, StatusCode::TOO_MANY_REQUESTS);
         response.headers_mut().insert(
             header::RETRY_AFTER,
             retry_after
@@ -96,6 +242,36 @@ impl fmt::Display for TooManyRequests {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct TooManyAuthFailures {
+    pub retry_after: Duration,
+}
+
+impl AppError for TooManyAuthFailures {
+    fn response(&self) -> Response {
+        // `Retry-After` only supports full seconds, so we round up
+        let retry_after =
+            self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0);
+
+        let detail = format!(
+            "Too many failed authentication attempts. \
+             Please try again after {retry_after} seconds."
+        );
+        let code = "too_many_auth_failures";
+        let mut response = json_error(code, &detail, StatusCode::TOO_MANY_REQUESTS);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, retry_after.into());
+        response
+    }
+}
+
+impl fmt::Display for TooManyAuthFailures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "Too many failed authentication attempts".fmt(f)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct InsecurelyGeneratedTokenRevoked;
 
@@ -108,7 +284,8 @@ impl InsecurelyGeneratedTokenRevoked {
 impl AppError for InsecurelyGeneratedTokenRevoked {
     fn response(&self) -> Response {
         let cause = CauseField("insecurely generated, revoked 2020-07".to_string());
-        let response = json_error(&self.to_string(), StatusCode::UNAUTHORIZED);
+        let detail = self.to_string();
+        let response = json_error("token_revoked", &detail, StatusCode::UNAUTHORIZED);
         (Extension(cause), response).into_response()
     }
 }
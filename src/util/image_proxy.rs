@@ -0,0 +1,86 @@
+//! Signed URLs of the image proxy for rendered READMEs.
+//!
+//! The images in rendered READMEs are served through the
+//! `GET /api/v1/readme_images` endpoint instead of being loaded from the
+//! servers that host them, which could otherwise track the readers and might
+//! only be reachable over plain HTTP. Like the unsubscribe tokens (see
+//! [`crate::util::unsubscribe`]), the proxied URLs are signed with an HMAC
+//! keyed with the signing key of the session cookies, so that the endpoint
+//! can't be used to fetch arbitrary URLs.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The path of the image proxy endpoint.
+pub const PATH: &str = "/api/v1/readme_images";
+
+/// Returns the URL under which the image at `url` is served by the image
+/// proxy of the given domain.
+pub fn proxy_url(key: &cookie::Key, domain_name: &str, url: &str) -> String {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("url", url)
+        .append_pair("sig", &sign(key, url))
+        .finish();
+
+    format!("https://{domain_name}{PATH}?{query}")
+}
+
+/// Returns the hex-encoded signature of the image URL.
+pub fn sign(key: &cookie::Key, url: &str) -> String {
+    hex::encode(mac(key, url).finalize().into_bytes())
+}
+
+/// Returns whether `signature` is the signature of the image URL.
+pub fn verify(key: &cookie::Key, url: &str, signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    mac(key, url).verify_slice(&signature).is_ok()
+}
+
+fn mac(key: &cookie::Key, url: &str) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(key.signing()).expect("HMAC can take a key of any size");
+    mac.update(b"readme_image:");
+    mac.update(url.as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> cookie::Key {
+        cookie::Key::derive_from("test this has to be over 32 bytes long".as_bytes())
+    }
+
+    #[test]
+    fn test_proxy_url() {
+        let url = "http://example.com/logo.png?size=large&v=2";
+        let proxy_url = proxy_url(&key(), "crates.io", url);
+        let signature = sign(&key(), url);
+        assert_eq!(
+            proxy_url,
+            format!(
+                "https://crates.io/api/v1/readme_images?url=http%3A%2F%2Fexample.com%2Flogo.png%3Fsize%3Dlarge%26v%3D2&sig={signature}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let url = "https://example.com/logo.png";
+        let signature = sign(&key(), url);
+        assert!(verify(&key(), url, &signature));
+
+        let other_key =
+            cookie::Key::derive_from("a different key that is also over 32 bytes".as_bytes());
+        assert!(!verify(&other_key, url, &signature));
+        assert!(!verify(&key(), "https://example.com/other.png", &signature));
+        assert!(!verify(&key(), url, "zz"));
+        assert!(!verify(&key(), url, ""));
+    }
+}
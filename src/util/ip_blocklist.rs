@@ -0,0 +1,196 @@
+//! Matching of client IP addresses against lists of IPv4 and IPv6 CIDR
+//! blocks.
+//!
+//! The blocks are grouped by address family and prefix length, with the
+//! network addresses of each group in a hash map. A lookup masks the address
+//! once per distinct prefix length, starting with the longest one, so it only
+//! depends on the number of distinct prefix lengths and not on the number of
+//! blocks.
+//!
+//! IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`), which dual-stack proxies
+//! like the Heroku router may forward, are treated like the IPv4 addresses
+//! they represent.
+
+use ipnetwork::{IpNetwork, Ipv4Network};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Prefix length of the `::ffff:0:0/96` range of IPv4-mapped IPv6 addresses.
+const IPV4_MAPPED_PREFIX: u8 = 96;
+
+#[derive(Debug, Clone, Default)]
+pub struct IpBlocklist {
+    v4: PrefixTable,
+    v6: PrefixTable,
+}
+
+impl IpBlocklist {
+    pub fn insert(&mut self, network: IpNetwork) {
+        let network = canonical_network(network);
+        match network {
+            IpNetwork::V4(v4) => self.v4.insert(u32::from(v4.network()).into(), 32, network),
+            IpNetwork::V6(v6) => self.v6.insert(u128::from(v6.network()), 128, network),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.v4.is_empty() && self.v6.is_empty()
+    }
+
+    /// Returns `true` if the address is contained in any of the blocks.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.longest_match(ip).is_some()
+    }
+
+    /// Returns the most specific block that contains the address.
+    pub fn longest_match(&self, ip: IpAddr) -> Option<&IpNetwork> {
+        match ip.to_canonical() {
+            IpAddr::V4(ip) => self.v4.longest_match(u32::from(ip).into(), 32),
+            IpAddr::V6(ip) => self.v6.longest_match(u128::from(ip), 128),
+        }
+    }
+}
+
+impl FromIterator<IpNetwork> for IpBlocklist {
+    fn from_iter<I: IntoIterator<Item = IpNetwork>>(iter: I) -> Self {
+        let mut blocklist = Self::default();
+        for network in iter {
+            blocklist.insert(network);
+        }
+        blocklist
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct PrefixTable {
+    /// Blocks by their prefix length and masked network address, sorted by
+    /// descending prefix length.
+    prefixes: Vec<(u8, HashMap<u128, IpNetwork>)>,
+}
+
+impl PrefixTable {
+    fn is_empty(&self) -> bool {
+        self.prefixes.is_empty()
+    }
+
+    fn insert(&mut self, address: u128, width: u8, network: IpNetwork) {
+        let prefix = network.prefix();
+        let address = mask(address, width, prefix);
+
+        let index = match self.prefixes.binary_search_by(|(p, _)| prefix.cmp(p)) {
+            Ok(index) => index,
+            Err(index) => {
+                self.prefixes.insert(index, (prefix, HashMap::new()));
+                index
+            }
+        };
+
+        self.prefixes[index].1.insert(address, network);
+    }
+
+    fn longest_match(&self, address: u128, width: u8) -> Option<&IpNetwork> {
+        self.prefixes
+            .iter()
+            .find_map(|(prefix, networks)| networks.get(&mask(address, width, *prefix)))
+    }
+}
+
+/// Clears the host bits of an address that is `width` bits long.
+fn mask(address: u128, width: u8, prefix: u8) -> u128 {
+    let host_bits = u32::from(width.saturating_sub(prefix));
+    address
+        .checked_shr(host_bits)
+        .and_then(|network| network.checked_shl(host_bits))
+        .unwrap_or(0)
+}
+
+/// Converts blocks of IPv4-mapped IPv6 addresses to the equivalent IPv4
+/// blocks, so that they match the canonical form of the client addresses.
+fn canonical_network(network: IpNetwork) -> IpNetwork {
+    let IpNetwork::V6(v6) = network else {
+        return network;
+    };
+
+    match v6.ip().to_ipv4_mapped() {
+        Some(ip) if v6.prefix() >= IPV4_MAPPED_PREFIX => {
+            Ipv4Network::new(ip, v6.prefix() - IPV4_MAPPED_PREFIX)
+                .map(IpNetwork::V4)
+                .unwrap_or(network)
+        }
+        _ => network,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocklist(networks: &[&str]) -> IpBlocklist {
+        networks
+            .iter()
+            .map(|network| network.parse::<IpNetwork>().unwrap())
+            .collect()
+    }
+
+    fn longest_match(blocklist: &IpBlocklist, ip: &str) -> Option<String> {
+        let ip = ip.parse().unwrap();
+        blocklist.longest_match(ip).map(ToString::to_string)
+    }
+
+    #[test]
+    fn empty_blocklist() {
+        let blocklist = IpBlocklist::default();
+        assert!(blocklist.is_empty());
+        assert!(!blocklist.contains("127.0.0.1".parse().unwrap()));
+        assert!(!blocklist.contains("::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn ipv4_blocks() {
+        let blocklist = blocklist(&["192.168.0.0/16", "192.168.1.0/24", "10.0.0.1"]);
+        assert!(!blocklist.is_empty());
+
+        let m = |ip| longest_match(&blocklist, ip);
+        assert_eq!(m("192.168.1.42").as_deref(), Some("192.168.1.0/24"));
+        assert_eq!(m("192.168.2.42").as_deref(), Some("192.168.0.0/16"));
+        assert_eq!(m("10.0.0.1").as_deref(), Some("10.0.0.1/32"));
+        assert_eq!(m("10.0.0.2"), None);
+        assert_eq!(m("192.169.0.1"), None);
+    }
+
+    #[test]
+    fn ipv6_blocks() {
+        let blocklist = blocklist(&["2001:db8::/32", "2001:db8:1234:5678::/64", "::/0"]);
+
+        let m = |ip| longest_match(&blocklist, ip);
+        assert_eq!(
+            m("2001:db8:1234:5678:9abc::1").as_deref(),
+            Some("2001:db8:1234:5678::/64")
+        );
+        assert_eq!(m("2001:db8:ffff::1").as_deref(), Some("2001:db8::/32"));
+        assert_eq!(m("2a02:8071::1").as_deref(), Some("::/0"));
+
+        // IPv6 blocks never match IPv4 addresses
+        assert_eq!(m("192.168.0.1"), None);
+    }
+
+    #[test]
+    fn blocks_with_host_bits() {
+        let blocklist = blocklist(&["127.0.0.1/24", "2001:db8::1/64"]);
+        assert!(blocklist.contains("127.0.0.200".parse().unwrap()));
+        assert!(blocklist.contains("2001:db8::ffff".parse().unwrap()));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses() {
+        let blocklist = blocklist(&["203.0.113.0/24", "::ffff:198.51.100.0/120"]);
+
+        let m = |ip| longest_match(&blocklist, ip);
+        assert_eq!(m("::ffff:203.0.113.7").as_deref(), Some("203.0.113.0/24"));
+        assert_eq!(m("198.51.100.7").as_deref(), Some("198.51.100.0/24"));
+        assert_eq!(m("::ffff:198.51.100.7").as_deref(), Some("198.51.100.0/24"));
+
+        // IPv4-compatible addresses are not IPv4-mapped
+        assert_eq!(m("::203.0.113.7"), None);
+    }
+}
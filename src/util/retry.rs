@@ -0,0 +1,359 @@
+//! Retrying of operations that failed with transient errors
+//!
+//! Background jobs talk to S3, the git index and other HTTP services, which
+//! occasionally fail for a moment. Instead of failing the whole job (which
+//! then has to wait for the next retry of the job runner), these calls are
+//! retried a few times with exponentially growing, jittered delays.
+//!
+//! Only errors that [`is_transient()`] considers temporary are retried by
+//! default, and the retries of an operation are limited by both a maximum
+//! number of attempts and a total time budget.
+
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, Opts};
+use std::future::Future;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+/// Number of retries and of operations that ran out of retries, by
+/// operation. Registered in the default registry, so that the counters can be
+/// collected by every process that performs retries.
+static RETRIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let opts =
+        Opts::new("retries_total", "Number of retries of failed operations").namespace("cratesio");
+    let counter = IntCounterVec::new(opts, &["operation", "outcome"]).unwrap();
+    prometheus::register(Box::new(counter.clone())).unwrap();
+    counter
+});
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// The delay before the first retry, which doubles with every retry.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// No retries are started once the operation would run longer than this,
+    /// including the delays.
+    pub budget: Duration,
+    is_retryable: fn(&anyhow::Error) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            budget: Duration::from_secs(60),
+            is_retryable: is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retries all errors instead of only the transient ones, for operations
+    /// whose errors cannot be classified but that are safe to repeat.
+    pub fn retry_all_errors(self) -> Self {
+        Self {
+            is_retryable: |_| true,
+            ..self
+        }
+    }
+
+    /// Runs the asynchronous `operation` until it succeeds or the policy
+    /// gives up, in which case the last error is returned.
+    pub async fn run<T, F, Fut>(&self, name: &'static str, mut operation: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let error = match operation().await {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+
+            let Some(delay) = self.delay_after_failure(name, &error, attempt, start.elapsed())
+            else {
+                return Err(error);
+            };
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Runs the blocking `operation` until it succeeds or the policy gives
+    /// up, in which case the last error is returned.
+    ///
+    /// The current thread is blocked while waiting for the next attempt, so
+    /// this must only be used on threads that are allowed to block.
+    pub fn run_blocking<T, F>(&self, name: &'static str, mut operation: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> anyhow::Result<T>,
+    {
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let error = match operation() {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+
+            let Some(delay) = self.delay_after_failure(name, &error, attempt, start.elapsed())
+            else {
+                return Err(error);
+            };
+
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// Returns the delay before the next attempt, or `None` if the operation
+    /// should not be retried.
+    fn delay_after_failure(
+        &self,
+        name: &'static str,
+        error: &anyhow::Error,
+        attempt: u32,
+        elapsed: Duration,
+    ) -> Option<Duration> {
+        if !(self.is_retryable)(error) {
+            return None;
+        }
+
+        let delay = with_jitter(self.backoff(attempt));
+        if attempt >= self.max_attempts || elapsed + delay > self.budget {
+            warn!(
+                operation = name,
+                attempt,
+                ?error,
+                "Giving up retrying operation"
+            );
+            RETRIES.with_label_values(&[name, "exhausted"]).inc();
+            return None;
+        }
+
+        warn!(
+            operation = name,
+            attempt,
+            ?delay,
+            ?error,
+            "Retrying failed operation"
+        );
+        RETRIES.with_label_values(&[name, "retried"]).inc();
+        Some(delay)
+    }
+
+    /// Returns the delay before the next attempt after the given number of
+    /// failed attempts, without jitter.
+    fn backoff(&self, failed_attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failed_attempts.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Randomizes the delay to somewhere between half of it and all of it, so
+/// that jobs that failed at the same time don't retry at the same time.
+fn with_jitter(delay: Duration) -> Duration {
+    let half = delay / 2;
+    half + half.mul_f64(rand::random::<f64>())
+}
+
+/// Returns `true` if the error is likely to go away when the operation is
+/// repeated, like timeouts, connection failures and server errors.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(error) = cause.downcast_ref::<object_store::Error>() {
+            return !matches!(
+                error,
+                object_store::Error::NotFound { .. }
+                    | object_store::Error::InvalidPath { .. }
+                    | object_store::Error::AlreadyExists { .. }
+                    | object_store::Error::Precondition { .. }
+                    | object_store::Error::NotModified { .. }
+                    | object_store::Error::NotSupported { .. }
+                    | object_store::Error::NotImplemented
+                    | object_store::Error::UnknownConfigurationKey { .. }
+            );
+        }
+
+        if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+            let status = error.status();
+            return error.is_timeout()
+                || error.is_connect()
+                || status.is_some_and(|status| {
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                });
+        }
+
+        if let Some(error) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(
+                error.kind(),
+                ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::UnexpectedEof
+            );
+        }
+
+        false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    fn timeout() -> anyhow::Error {
+        std::io::Error::from(ErrorKind::TimedOut).into()
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn jitter() {
+        for _ in 0..100 {
+            let delay = with_jitter(Duration::from_secs(2));
+            assert!(delay >= Duration::from_secs(1));
+            assert!(delay <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn transient_errors() {
+        assert!(is_transient(&timeout()));
+        assert!(is_transient(&timeout().context("Failed to upload")));
+        assert!(!is_transient(&anyhow!("invalid data")));
+
+        let not_found = object_store::Error::NotFound {
+            path: "foo".into(),
+            source: "missing".into(),
+        };
+        assert!(!is_transient(&not_found.into()));
+
+        let generic = object_store::Error::Generic {
+            store: "S3",
+            source: "connection closed".into(),
+        };
+        assert!(is_transient(&generic.into()));
+    }
+
+    #[test]
+    fn retries_transient_errors() {
+        let mut attempts = 0;
+        let result = policy().run_blocking("test", || {
+            attempts += 1;
+            if attempts < 3 {
+                Err(timeout())
+            } else {
+                Ok(attempts)
+            }
+        });
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut attempts = 0;
+        let result = policy().run_blocking("test", || -> anyhow::Result<()> {
+            attempts += 1;
+            Err(timeout())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 4);
+    }
+
+    #[test]
+    fn does_not_retry_permanent_errors() {
+        let mut attempts = 0;
+        let result = policy().run_blocking("test", || -> anyhow::Result<()> {
+            attempts += 1;
+            Err(anyhow!("invalid data"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result = policy()
+            .retry_all_errors()
+            .run_blocking("test", || -> anyhow::Result<()> {
+                attempts += 1;
+                Err(anyhow!("invalid data"))
+            });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 4);
+    }
+
+    #[test]
+    fn respects_the_budget() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(10),
+            budget: Duration::from_secs(1),
+            ..Default::default()
+        };
+
+        let mut attempts = 0;
+        let result = policy.run_blocking("test", || -> anyhow::Result<()> {
+            attempts += 1;
+            Err(timeout())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn retries_async_operations() {
+        let mut attempts = 0;
+        let result = policy()
+            .run("test", || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < 2 {
+                        Err(timeout())
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 2);
+    }
+}
@@ -0,0 +1,76 @@
+//! Signed tokens for the revoke links in the emails about new API tokens.
+//!
+//! Like the unsubscribe tokens (see [`crate::util::unsubscribe`]), the tokens
+//! are not stored in the database. They consist of the user ID, the ID of the
+//! API token and an HMAC of both, keyed with the signing key of the session
+//! cookies, so that a link can't be altered to revoke the API tokens of other
+//! users. The links don't expire, since revoking a token is always safe.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Generates a revocation token for the API token `api_token_id` of the
+/// given user.
+pub fn generate_token(key: &cookie::Key, user_id: i32, api_token_id: i32) -> String {
+    let signature = mac(key, user_id, api_token_id).finalize().into_bytes();
+    format!("{user_id}.{api_token_id}.{}", hex::encode(signature))
+}
+
+/// Returns the user ID and the ID of the API token that the token was
+/// generated for, or `None` if the token is malformed.
+pub fn verify_token(key: &cookie::Key, token: &str) -> Option<(i32, i32)> {
+    let mut parts = token.splitn(3, '.');
+    let user_id = parts.next()?.parse().ok()?;
+    let api_token_id = parts.next()?.parse().ok()?;
+    let signature = hex::decode(parts.next()?).ok()?;
+
+    mac(key, user_id, api_token_id)
+        .verify_slice(&signature)
+        .ok()?;
+
+    Some((user_id, api_token_id))
+}
+
+fn mac(key: &cookie::Key, user_id: i32, api_token_id: i32) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(key.signing()).expect("HMAC can take a key of any size");
+    mac.update(b"revoke_api_token:");
+    mac.update(user_id.to_string().as_bytes());
+    mac.update(b":");
+    mac.update(api_token_id.to_string().as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> cookie::Key {
+        cookie::Key::derive_from("test this has to be over 32 bytes long".as_bytes())
+    }
+
+    #[test]
+    fn test_generate_and_verify() {
+        let token = generate_token(&key(), 42, 7);
+        assert!(token.starts_with("42.7."));
+        assert_eq!(verify_token(&key(), &token), Some((42, 7)));
+    }
+
+    #[test]
+    fn test_verify_rejects_invalid_tokens() {
+        let token = generate_token(&key(), 42, 7);
+        let signature = token.rsplit('.').next().unwrap();
+
+        let other_key =
+            cookie::Key::derive_from("a different key that is also over 32 bytes".as_bytes());
+        assert_eq!(verify_token(&other_key, &token), None);
+
+        assert_eq!(verify_token(&key(), &format!("43.7.{signature}")), None);
+        assert_eq!(verify_token(&key(), &format!("42.8.{signature}")), None);
+        assert_eq!(verify_token(&key(), "42.7"), None);
+        assert_eq!(verify_token(&key(), "42.7.zz"), None);
+        assert_eq!(verify_token(&key(), ""), None);
+    }
+}
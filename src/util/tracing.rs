@@ -1,7 +1,16 @@
+use parking_lot::Mutex;
 use sentry::integrations::tracing::EventFilter;
-use tracing::Level;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::span::EnteredSpan;
 use tracing::Metadata;
+use tracing::{Event, Level, Subscriber};
 use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::{LookupSpan, Registry};
+use tracing_subscriber::reload;
 use tracing_subscriber::{prelude::*, EnvFilter};
 
 /// Initializes the `tracing` logging framework.
@@ -13,14 +22,40 @@ use tracing_subscriber::{prelude::*, EnvFilter};
 /// This function also sets up the Sentry error reporting integration for the
 /// `tracing` framework, which is hardcoded to include all `INFO` level events.
 pub fn init() {
-    init_with_default_level(LevelFilter::INFO)
+    init_with_default_level(DEFAULT_LEVEL)
 }
 
+/// Replaces the filter of the regular CLI output with the given
+/// [`RUST_LOG`](tracing_subscriber::filter::EnvFilter) directives, e.g. after
+/// a configuration reload.
+pub fn set_log_filter(directives: Option<&str>) -> anyhow::Result<()> {
+    let Some(handle) = LOG_FILTER.get() else {
+        anyhow::bail!("the logging framework was not initialized with `init()`");
+    };
+
+    let env_filter = EnvFilter::builder()
+        .with_default_directive(DEFAULT_LEVEL.into())
+        .parse_lossy(directives.unwrap_or_default());
+
+    handle.reload(env_filter)?;
+    Ok(())
+}
+
+/// The level of the regular CLI output if `RUST_LOG` is not set.
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
+
+/// Allows replacing the filter of the regular CLI output after
+/// initialization, see [`set_log_filter()`].
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
 fn init_with_default_level(level: LevelFilter) {
     let env_filter = EnvFilter::builder()
         .with_default_directive(level.into())
         .from_env_lossy();
 
+    let (env_filter, handle) = reload::Layer::new(env_filter);
+    let _ = LOG_FILTER.set(handle);
+
     let log_layer = tracing_subscriber::fmt::layer()
         .compact()
         .without_time()
@@ -46,14 +81,230 @@ pub fn event_filter(metadata: &Metadata<'_>) -> EventFilter {
 }
 
 /// Initializes the `tracing` logging framework for usage in tests.
+///
+/// Besides printing the events to the test output, this also allows
+/// capturing them with [`capture_for_test()`].
 pub fn init_for_test() {
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::DEBUG.into())
         .from_env_lossy();
 
-    let _ = tracing_subscriber::fmt()
+    let log_layer = tracing_subscriber::fmt::layer()
         .compact()
-        .with_env_filter(env_filter)
         .with_test_writer()
+        .with_filter(env_filter);
+
+    let capture_layer = CaptureLayer.with_filter(LevelFilter::DEBUG);
+
+    let _ = tracing_subscriber::registry()
+        .with(log_layer)
+        .with(capture_layer)
         .try_init();
 }
+
+const CAPTURE_SPAN_NAME: &str = "log_capture";
+
+/// Starts capturing the `tracing` events of the current test.
+///
+/// All events up to `DEBUG` level that are emitted on the current thread
+/// are captured until the returned [`LogCapture`] is dropped. Events on other
+/// threads are captured too if the current span is propagated to them, which
+/// is the case for `interact()` calls on database connections, but not for
+/// tasks spawned with `tokio::spawn()`.
+///
+/// ```ignore
+/// let logs = capture_for_test();
+/// anon.get::<()>("/api/v1/crates/foo").await;
+/// logs.assert_contains("status=404");
+/// ```
+pub fn capture_for_test() -> LogCapture {
+    init_for_test();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+
+    let span = tracing::info_span!(CAPTURE_SPAN_NAME);
+    span.with_subscriber(|(id, dispatch)| {
+        let registry = dispatch.downcast_ref::<Registry>()?;
+        let span = registry.span(id)?;
+        span.extensions_mut().insert(CaptureBuffer(events.clone()));
+        Some(())
+    });
+
+    LogCapture {
+        events,
+        _span: span.entered(),
+    }
+}
+
+/// The events captured by [`capture_for_test()`].
+pub struct LogCapture {
+    events: Arc<Mutex<Vec<CapturedEvent>>>,
+    _span: EnteredSpan,
+}
+
+impl LogCapture {
+    /// Returns the events that were captured so far.
+    pub fn events(&self) -> Vec<CapturedEvent> {
+        self.events.lock().clone()
+    }
+
+    /// Returns `true` if the rendered text of any captured event contains
+    /// `needle`.
+    pub fn contains(&self, needle: &str) -> bool {
+        let events = self.events.lock();
+        events
+            .iter()
+            .any(|event| event.to_string().contains(needle))
+    }
+
+    /// Panics if no captured event contains `needle`.
+    #[track_caller]
+    pub fn assert_contains(&self, needle: &str) {
+        if !self.contains(needle) {
+            panic!("no captured event contains `{needle}`:\n{}", self.render());
+        }
+    }
+
+    /// Panics if any captured event contains `needle`.
+    #[track_caller]
+    pub fn assert_not_contains(&self, needle: &str) {
+        if self.contains(needle) {
+            panic!("a captured event contains `{needle}`:\n{}", self.render());
+        }
+    }
+
+    fn render(&self) -> String {
+        let events = self.events.lock();
+        events.iter().fold(String::new(), |mut output, event| {
+            let _ = writeln!(output, "  {event}");
+            output
+        })
+    }
+}
+
+/// A `tracing` event captured by [`capture_for_test()`].
+#[derive(Debug, Clone)]
+pub struct CapturedEvent {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl CapturedEvent {
+    fn new(event: &Event<'_>) -> Self {
+        let metadata = event.metadata();
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        Self {
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+            fields: visitor.fields,
+        }
+    }
+
+    /// Returns the value of the field with the given name.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for CapturedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.level, self.target, self.message)?;
+        for (name, value) in &self.fields {
+            write!(f, " {name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Vec<(String, String)>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, format!("{value:?}"));
+    }
+}
+
+impl FieldVisitor {
+    fn record(&mut self, field: &Field, value: String) {
+        match field.name() {
+            "message" => self.message = value,
+            name => self.fields.push((name.to_string(), value)),
+        }
+    }
+}
+
+/// The buffer of a [`LogCapture`], stored in the extensions of its span.
+struct CaptureBuffer(Arc<Mutex<Vec<CapturedEvent>>>);
+
+/// Appends events to the [`CaptureBuffer`] of the closest enclosing capture
+/// span, if there is one.
+struct CaptureLayer;
+
+impl<S> Layer<S> for CaptureLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+
+        for span in scope {
+            if span.name() != CAPTURE_SPAN_NAME {
+                continue;
+            }
+
+            if let Some(buffer) = span.extensions().get::<CaptureBuffer>() {
+                buffer.0.lock().push(CapturedEvent::new(event));
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_for_test() {
+        warn!("before the capture");
+
+        let logs = capture_for_test();
+        info!(target: "http", status = 200, path = "/foo", "request finished");
+        debug!("{} items processed", 3);
+        std::thread::spawn(|| info!("on another thread"))
+            .join()
+            .unwrap();
+
+        let events = logs.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].level, Level::INFO);
+        assert_eq!(events[0].target, "http");
+        assert_eq!(events[0].message, "request finished");
+        assert_some_eq!(events[0].field("status"), "200");
+        assert_some_eq!(events[0].field("path"), "/foo");
+        assert_eq!(events[1].message, "3 items processed");
+
+        logs.assert_contains("INFO http: request finished status=200 path=/foo");
+        logs.assert_not_contains("before the capture");
+        logs.assert_not_contains("on another thread");
+    }
+}
@@ -0,0 +1,76 @@
+//! Signed tokens for the unsubscribe links in notification emails.
+//!
+//! The tokens are not stored in the database. They consist of the user ID and
+//! an HMAC of the user ID and the kind of notification, keyed with the signing
+//! key of the session cookies, so that they can be verified without a lookup
+//! and can not be reused to unsubscribe from other kinds of notifications.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The kind of notification for the weekly digest of new versions of followed
+/// crates.
+pub const FOLLOW_DIGEST: &str = "follow_digest";
+
+/// Generates an unsubscribe token for the given user and kind of notification.
+pub fn generate_token(key: &cookie::Key, kind: &str, user_id: i32) -> String {
+    let signature = mac(key, kind, user_id).finalize().into_bytes();
+    format!("{user_id}.{}", hex::encode(signature))
+}
+
+/// Returns the ID of the user that the token was generated for, or `None` if
+/// the token is malformed or was not generated for this kind of notification.
+pub fn verify_token(key: &cookie::Key, kind: &str, token: &str) -> Option<i32> {
+    let (user_id, signature) = token.split_once('.')?;
+    let user_id = user_id.parse().ok()?;
+    let signature = hex::decode(signature).ok()?;
+
+    mac(key, kind, user_id).verify_slice(&signature).ok()?;
+    Some(user_id)
+}
+
+fn mac(key: &cookie::Key, kind: &str, user_id: i32) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(key.signing()).expect("HMAC can take a key of any size");
+    mac.update(b"unsubscribe:");
+    mac.update(kind.as_bytes());
+    mac.update(b":");
+    mac.update(user_id.to_string().as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> cookie::Key {
+        cookie::Key::derive_from("test this has to be over 32 bytes long".as_bytes())
+    }
+
+    #[test]
+    fn test_generate_and_verify() {
+        let token = generate_token(&key(), FOLLOW_DIGEST, 42);
+        assert!(token.starts_with("42."));
+        assert_eq!(verify_token(&key(), FOLLOW_DIGEST, &token), Some(42));
+    }
+
+    #[test]
+    fn test_verify_rejects_invalid_tokens() {
+        let token = generate_token(&key(), FOLLOW_DIGEST, 42);
+        let (_, signature) = token.split_once('.').unwrap();
+
+        let other_key =
+            cookie::Key::derive_from("a different key that is also over 32 bytes".as_bytes());
+        assert_eq!(verify_token(&other_key, FOLLOW_DIGEST, &token), None);
+        assert_eq!(verify_token(&key(), "other", &token), None);
+        assert_eq!(
+            verify_token(&key(), FOLLOW_DIGEST, &format!("43.{signature}")),
+            None
+        );
+        assert_eq!(verify_token(&key(), FOLLOW_DIGEST, "42"), None);
+        assert_eq!(verify_token(&key(), FOLLOW_DIGEST, "42.zz"), None);
+        assert_eq!(verify_token(&key(), FOLLOW_DIGEST, ""), None);
+    }
+}
@@ -1,28 +1,25 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use secrecy::ExposeSecret;
+use std::collections::BTreeMap;
 
 use crate::external_urls::remove_blocked_urls;
 use crate::models::{
-    ApiToken, Category, Crate, CrateOwnerInvitation, CreatedApiToken, Dependency, DependencyKind,
-    Keyword, Owner, ReverseDependency, Team, TopVersions, User, Version, VersionDownload,
-    VersionOwnerAction,
+    ApiToken, Category, Crate, CrateNameReservation, CrateOwnerInvitation, CreatedApiToken,
+    Dependency, DependencyKind, DescriptionTranslation, Email, Keyword, Owner, OwnerAction,
+    OwnerActionVia, ReleaseNotes, ReverseDependency, Team, TopVersions, User, Version,
+    VersionArtifact, VersionDownload, VersionOwnerAction, VersionProvenance,
 };
 use crate::util::rfc3339;
 use crates_io_github as github;
 
+pub mod json_api;
 pub mod krate_publish;
 pub use self::krate_publish::{EncodableCrateDependency, PublishMetadata};
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct EncodableCategory {
-    pub id: String,
-    pub category: String,
-    pub slug: String,
-    pub description: String,
-    #[serde(with = "rfc3339")]
-    pub created_at: NaiveDateTime,
-    pub crates_cnt: i32,
-}
+pub use crates_io_api_types::{
+    EncodableAuditAction, EncodableCategory, EncodableCrate, EncodableCrateLinks, EncodableKeyword,
+    EncodableOwner, EncodablePublicUser, EncodablePublishedVia, EncodableVersion,
+    EncodableVersionLinks,
+};
 
 impl From<Category> for EncodableCategory {
     fn from(category: Category) -> Self {
@@ -150,6 +147,135 @@ impl EncodableDependency {
     }
 }
 
+/// The differences between two versions of a crate.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionDiff {
+    pub from: String,
+    pub to: String,
+    /// `None` if both versions have the same license.
+    pub license: Option<EncodableLicenseChange>,
+    pub features: EncodableFeaturesDiff,
+    pub dependencies: EncodableDependenciesDiff,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableLicenseChange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct EncodableFeaturesDiff {
+    pub added: BTreeMap<String, Vec<String>>,
+    pub removed: BTreeMap<String, Vec<String>>,
+    pub changed: BTreeMap<String, EncodableFeatureChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableFeatureChange {
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct EncodableDependenciesDiff {
+    pub added: Vec<EncodableDependency>,
+    pub removed: Vec<EncodableDependency>,
+    pub updated: Vec<EncodableDependencyChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyChange {
+    pub from: EncodableDependency,
+    pub to: EncodableDependency,
+}
+
+/// The transitive dependency graph of a crate version.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyGraph {
+    /// The ID of the node of the requested version.
+    pub root: String,
+    pub depth: u32,
+    /// `true` if nodes were left out because the graph exceeded the node
+    /// limit.
+    pub truncated: bool,
+    pub nodes: Vec<EncodableDependencyGraphNode>,
+    pub edges: Vec<EncodableDependencyGraphEdge>,
+    /// Dependencies without a published version matching their requirement.
+    pub unresolved: Vec<EncodableUnresolvedDependency>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyGraphNode {
+    /// `name@version`
+    pub id: String,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyGraphEdge {
+    pub from: String,
+    pub to: String,
+    pub req: String,
+    pub kind: DependencyKind,
+    pub optional: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableUnresolvedDependency {
+    pub from: String,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub req: String,
+    pub kind: DependencyKind,
+    pub optional: bool,
+}
+
+/// The licenses of the direct dependencies of a crate version.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableLicenseSummary {
+    /// The distinct license expressions of the resolved dependencies, in
+    /// alphabetical order.
+    pub licenses: Vec<EncodableLicenseUsage>,
+    pub dependencies: Vec<EncodableDependencyLicense>,
+    /// The names of the dependencies whose license is unknown, either because
+    /// no published version matches their requirement or because the
+    /// matching version only has a license file.
+    pub unknown: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableLicenseUsage {
+    pub license: String,
+    /// The dependencies using the license, as `name@version`.
+    pub crates: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyLicense {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub req: String,
+    pub kind: DependencyKind,
+    pub optional: bool,
+    /// The highest non-yanked version matching the requirement.
+    pub version: Option<String>,
+    pub license: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyValidation {
+    pub name: String,
+    pub req: String,
+    /// `true` if a crate with this name was published.
+    pub exists: bool,
+    /// `true` if a non-yanked version matches the requirement.
+    pub matches: bool,
+    /// The highest non-yanked version matching the requirement.
+    pub latest_matching: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersionDownload {
     pub version: i32,
@@ -167,13 +293,126 @@ impl From<VersionDownload> for EncodableVersionDownload {
     }
 }
 
+/// The actions the authenticated user (or API token) may perform on a crate.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableCratePermissions {
+    pub publish: bool,
+    pub yank: bool,
+    pub change_owners: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableClientDownload {
+    pub date: String,
+    pub client_version: String,
+    pub downloads: i32,
+}
+
+impl From<(NaiveDate, String, i32)> for EncodableClientDownload {
+    fn from((date, client_version, downloads): (NaiveDate, String, i32)) -> Self {
+        Self {
+            date: date.to_string(),
+            client_version,
+            downloads,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependentsCount {
+    pub date: String,
+    pub dependents: i32,
+}
+
+impl From<(NaiveDate, i32)> for EncodableDependentsCount {
+    fn from((date, dependents): (NaiveDate, i32)) -> Self {
+        Self {
+            date: date.to_string(),
+            dependents,
+        }
+    }
+}
+
+/// The downloads and publishes of the crates with a keyword or in a category
+/// in the last week and the week before.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct EncodableKeyword {
+pub struct EncodableTrendingStat {
+    /// The keyword, or the slug of the category.
     pub id: String,
-    pub keyword: String,
+    pub downloads: i64,
+    pub previous_downloads: i64,
+    /// The relative change of the downloads, or `None` if there were no
+    /// downloads in the week before.
+    pub download_growth: Option<f64>,
+    pub publishes: i32,
+    pub previous_publishes: i32,
+}
+
+impl From<(String, i64, i64, i32, i32)> for EncodableTrendingStat {
+    fn from(
+        (id, downloads, previous_downloads, publishes, previous_publishes): (
+            String,
+            i64,
+            i64,
+            i32,
+            i32,
+        ),
+    ) -> Self {
+        let download_growth = (previous_downloads > 0)
+            .then(|| (downloads - previous_downloads) as f64 / previous_downloads as f64);
+
+        Self {
+            id,
+            downloads,
+            previous_downloads,
+            download_growth,
+            publishes,
+            previous_publishes,
+        }
+    }
+}
+
+/// The publishes and new users of a single day in the site-wide statistics.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableSiteStatsDay {
+    pub date: String,
+    pub publishes: i32,
+    pub new_users: i32,
+}
+
+impl From<(NaiveDate, i32, i32)> for EncodableSiteStatsDay {
+    fn from((date, publishes, new_users): (NaiveDate, i32, i32)) -> Self {
+        Self {
+            date: date.to_string(),
+            publishes,
+            new_users,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateNameReservation {
+    pub name: String,
     #[serde(with = "rfc3339")]
     pub created_at: NaiveDateTime,
-    pub crates_cnt: i32,
+    #[serde(with = "rfc3339")]
+    pub expires_at: NaiveDateTime,
+}
+
+impl From<CrateNameReservation> for EncodableCrateNameReservation {
+    fn from(reservation: CrateNameReservation) -> Self {
+        let CrateNameReservation {
+            name,
+            created_at,
+            expires_at,
+            ..
+        } = reservation;
+        Self {
+            name,
+            created_at,
+            expires_at,
+        }
+    }
 }
 
 impl From<Keyword> for EncodableKeyword {
@@ -193,161 +432,116 @@ impl From<Keyword> for EncodableKeyword {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct EncodableCrate {
-    pub id: String,
-    pub name: String,
-    #[serde(with = "rfc3339")]
-    pub updated_at: NaiveDateTime,
-    pub versions: Option<Vec<i32>>,
-    pub keywords: Option<Vec<String>>,
-    pub categories: Option<Vec<String>>,
-    pub badges: Option<Vec<()>>,
-    #[serde(with = "rfc3339")]
-    pub created_at: NaiveDateTime,
-    // NOTE: Used by shields.io, altering `downloads` requires a PR with shields.io
-    pub downloads: i64,
-    pub recent_downloads: Option<i64>,
-    // NOTE: Used by shields.io, altering `max_version` requires a PR with shields.io
-    pub max_version: String,
-    pub newest_version: String, // Most recently updated version, which may not be max
-    pub max_stable_version: Option<String>,
-    pub description: Option<String>,
-    pub homepage: Option<String>,
-    pub documentation: Option<String>,
-    pub repository: Option<String>,
-    pub links: EncodableCrateLinks,
-    pub exact_match: bool,
-}
-
-impl EncodableCrate {
-    #[allow(clippy::too_many_arguments)]
-    pub fn from(
-        krate: Crate,
-        top_versions: Option<&TopVersions>,
-        versions: Option<Vec<i32>>,
-        keywords: Option<&[Keyword]>,
-        categories: Option<&[Category]>,
-        badges: Option<Vec<()>>,
-        exact_match: bool,
-        downloads: i64,
-        recent_downloads: Option<i64>,
-    ) -> Self {
-        let Crate {
-            name,
-            created_at,
-            updated_at,
-            description,
-            homepage,
-            documentation,
-            repository,
-            ..
-        } = krate;
-        let versions_link = match versions {
-            Some(..) => None,
-            None => Some(format!("/api/v1/crates/{name}/versions")),
-        };
-        let keyword_ids = keywords.map(|kws| kws.iter().map(|kw| kw.keyword.clone()).collect());
-        let category_ids = categories.map(|cats| cats.iter().map(|cat| cat.slug.clone()).collect());
-        let badges = badges.map(|_| vec![]);
-        let homepage = remove_blocked_urls(homepage);
-        let documentation = remove_blocked_urls(documentation);
-        let repository = remove_blocked_urls(repository);
-
-        let max_version = top_versions
-            .and_then(|v| v.highest.as_ref())
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| "0.0.0".to_string());
-
-        let newest_version = top_versions
-            .and_then(|v| v.newest.as_ref())
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| "0.0.0".to_string());
-
-        let max_stable_version = top_versions
-            .and_then(|v| v.highest_stable.as_ref())
-            .map(|v| v.to_string());
-
-        // the total number of downloads is eventually consistent, but can lag
-        // behind the number of "recent downloads". to hide this inconsistency
-        // we will use the "recent downloads" as "total downloads" in case it is
-        // higher.
-        let downloads = if matches!(recent_downloads, Some(x) if x > downloads) {
-            recent_downloads.unwrap()
-        } else {
-            downloads
-        };
+/// Builds the [`EncodableCrate`] view of a crate.
+#[allow(clippy::too_many_arguments)]
+pub fn encodable_crate(
+    krate: Crate,
+    top_versions: Option<&TopVersions>,
+    versions: Option<Vec<i32>>,
+    keywords: Option<&[Keyword]>,
+    categories: Option<&[Category]>,
+    badges: Option<Vec<()>>,
+    exact_match: bool,
+    downloads: i64,
+    recent_downloads: Option<i64>,
+) -> EncodableCrate {
+    let Crate {
+        name,
+        created_at,
+        updated_at,
+        description,
+        homepage,
+        documentation,
+        repository,
+        ..
+    } = krate;
+    let versions_link = match versions {
+        Some(..) => None,
+        None => Some(format!("/api/v1/crates/{name}/versions")),
+    };
+    let keyword_ids = keywords.map(|kws| kws.iter().map(|kw| kw.keyword.clone()).collect());
+    let category_ids = categories.map(|cats| cats.iter().map(|cat| cat.slug.clone()).collect());
+    let badges = badges.map(|_| vec![]);
+    let homepage = remove_blocked_urls(homepage);
+    let documentation = remove_blocked_urls(documentation);
+    let repository = remove_blocked_urls(repository);
 
-        EncodableCrate {
-            id: name.clone(),
-            name: name.clone(),
-            updated_at,
-            created_at,
-            downloads,
-            recent_downloads,
-            versions,
-            keywords: keyword_ids,
-            categories: category_ids,
-            badges,
-            max_version,
-            newest_version,
-            max_stable_version,
-            documentation,
-            homepage,
-            exact_match,
-            description,
-            repository,
-            links: EncodableCrateLinks {
-                version_downloads: format!("/api/v1/crates/{name}/downloads"),
-                versions: versions_link,
-                owners: Some(format!("/api/v1/crates/{name}/owners")),
-                owner_team: Some(format!("/api/v1/crates/{name}/owner_team")),
-                owner_user: Some(format!("/api/v1/crates/{name}/owner_user")),
-                reverse_dependencies: format!("/api/v1/crates/{name}/reverse_dependencies"),
-            },
-        }
-    }
+    let max_version = top_versions
+        .and_then(|v| v.highest.as_ref())
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "0.0.0".to_string());
 
-    pub fn from_minimal(
-        krate: Crate,
-        top_versions: Option<&TopVersions>,
-        badges: Option<Vec<()>>,
-        exact_match: bool,
-        downloads: i64,
-        recent_downloads: Option<i64>,
-    ) -> Self {
-        Self::from(
-            krate,
-            top_versions,
-            None,
-            None,
-            None,
-            badges,
-            exact_match,
-            downloads,
-            recent_downloads,
-        )
-    }
-}
+    let newest_version = top_versions
+        .and_then(|v| v.newest.as_ref())
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "0.0.0".to_string());
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct EncodableCrateLinks {
-    pub version_downloads: String,
-    pub versions: Option<String>,
-    pub owners: Option<String>,
-    pub owner_team: Option<String>,
-    pub owner_user: Option<String>,
-    pub reverse_dependencies: String,
+    let max_stable_version = top_versions
+        .and_then(|v| v.highest_stable.as_ref())
+        .map(|v| v.to_string());
+
+    // the total number of downloads is eventually consistent, but can lag
+    // behind the number of "recent downloads". to hide this inconsistency
+    // we will use the "recent downloads" as "total downloads" in case it is
+    // higher.
+    let downloads = if matches!(recent_downloads, Some(x) if x > downloads) {
+        recent_downloads.unwrap()
+    } else {
+        downloads
+    };
+
+    EncodableCrate {
+        id: name.clone(),
+        name: name.clone(),
+        updated_at,
+        created_at,
+        downloads,
+        recent_downloads,
+        versions,
+        keywords: keyword_ids,
+        categories: category_ids,
+        badges,
+        max_version,
+        newest_version,
+        max_stable_version,
+        recommended_version: None,
+        documentation,
+        homepage,
+        exact_match,
+        description,
+        repository,
+        links: EncodableCrateLinks {
+            version_downloads: format!("/api/v1/crates/{name}/downloads"),
+            versions: versions_link,
+            owners: Some(format!("/api/v1/crates/{name}/owners")),
+            owner_team: Some(format!("/api/v1/crates/{name}/owner_team")),
+            owner_user: Some(format!("/api/v1/crates/{name}/owner_user")),
+            reverse_dependencies: format!("/api/v1/crates/{name}/reverse_dependencies"),
+        },
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct EncodableOwner {
-    pub id: i32,
-    pub login: String,
-    pub kind: String,
-    pub url: Option<String>,
-    pub name: Option<String>,
-    pub avatar: Option<String>,
+/// Builds the [`EncodableCrate`] view of a crate, without its versions,
+/// keywords and categories.
+pub fn encodable_crate_minimal(
+    krate: Crate,
+    top_versions: Option<&TopVersions>,
+    badges: Option<Vec<()>>,
+    exact_match: bool,
+    downloads: i64,
+    recent_downloads: Option<i64>,
+) -> EncodableCrate {
+    encodable_crate(
+        krate,
+        top_versions,
+        None,
+        None,
+        None,
+        badges,
+        exact_match,
+        downloads,
+        recent_downloads,
+    )
 }
 
 impl From<Owner> for EncodableOwner {
@@ -468,6 +662,8 @@ pub struct EncodablePrivateUser {
     pub avatar: Option<String>,
     pub url: Option<String>,
     pub is_admin: bool,
+    pub follow_digest: bool,
+    pub locale: String,
 }
 
 impl EncodablePrivateUser {
@@ -484,6 +680,8 @@ impl EncodablePrivateUser {
             gh_login,
             gh_avatar,
             is_admin,
+            follow_digest,
+            locale,
             ..
         } = user;
         let url = format!("https://github.com/{gh_login}");
@@ -498,19 +696,32 @@ impl EncodablePrivateUser {
             name,
             url: Some(url),
             is_admin,
+            follow_digest,
+            locale,
         }
     }
 }
 
-/// The serialization format for the `User` model.
-/// Same as private user, except no email field
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
-pub struct EncodablePublicUser {
+/// One of the email addresses of the authenticated user.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableEmail {
     pub id: i32,
-    pub login: String,
-    pub name: Option<String>,
-    pub avatar: Option<String>,
-    pub url: String,
+    pub email: String,
+    pub verified: bool,
+    pub verification_email_sent: bool,
+    pub primary: bool,
+}
+
+impl From<Email> for EncodableEmail {
+    fn from(email: Email) -> Self {
+        Self {
+            id: email.id,
+            email: email.email,
+            verified: email.verified,
+            verification_email_sent: email.verified || email.token_generated_at.is_some(),
+            primary: email.is_primary,
+        }
+    }
 }
 
 /// Converts a `User` model into an `EncodablePublicUser` for JSON serialization.
@@ -534,102 +745,212 @@ impl From<User> for EncodablePublicUser {
     }
 }
 
+/// An entry of the ownership history of a crate.
 #[derive(Deserialize, Serialize, Debug)]
-pub struct EncodableAuditAction {
-    pub action: String,
-    pub user: EncodablePublicUser,
+pub struct EncodableCrateOwnerAction {
+    pub action: OwnerAction,
+    pub via: OwnerActionVia,
+    /// The user or team that was added or removed, or `None` if it no longer
+    /// exists.
+    pub owner: Option<EncodableOwner>,
+    /// The user that performed the action, or `None` if it was performed by
+    /// the crates.io team or is unknown.
+    pub actor: Option<EncodablePublicUser>,
     #[serde(with = "rfc3339")]
     pub time: NaiveDateTime,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-pub struct EncodableVersion {
-    pub id: i32,
-    #[serde(rename = "crate")]
-    pub krate: String,
-    pub num: String,
-    pub dl_path: String,
-    pub readme_path: String,
+pub struct EncodableDescriptionTranslation {
+    pub locale: String,
+    pub description: String,
     #[serde(with = "rfc3339")]
     pub updated_at: NaiveDateTime,
+}
+
+impl From<DescriptionTranslation> for EncodableDescriptionTranslation {
+    fn from(translation: DescriptionTranslation) -> Self {
+        let DescriptionTranslation {
+            locale,
+            description,
+            updated_at,
+            ..
+        } = translation;
+
+        Self {
+            locale,
+            description,
+            updated_at,
+        }
+    }
+}
+
+/// Builds the [`EncodableVersion`] view of a version of the crate
+/// `crate_name`.
+pub fn encodable_version(
+    version: Version,
+    crate_name: &str,
+    published_by: Option<User>,
+    audit_actions: Vec<(VersionOwnerAction, User)>,
+    provenance: Option<VersionProvenance>,
+) -> EncodableVersion {
+    let Version {
+        id,
+        num,
+        updated_at,
+        created_at,
+        downloads,
+        features,
+        yanked,
+        license,
+        crate_size,
+        checksum,
+        rust_version,
+        ..
+    } = version;
+
+    let links = EncodableVersionLinks {
+        dependencies: format!("/api/v1/crates/{crate_name}/{num}/dependencies"),
+        version_downloads: format!("/api/v1/crates/{crate_name}/{num}/downloads"),
+        authors: format!("/api/v1/crates/{crate_name}/{num}/authors"),
+    };
+
+    EncodableVersion {
+        dl_path: format!("/api/v1/crates/{crate_name}/{num}/download"),
+        readme_path: format!("/api/v1/crates/{crate_name}/{num}/readme"),
+        release_notes_path: format!("/api/v1/crates/{crate_name}/{num}/release_notes"),
+        num,
+        id,
+        krate: crate_name.to_string(),
+        updated_at,
+        created_at,
+        downloads,
+        features,
+        yanked,
+        license,
+        links,
+        crate_size,
+        checksum,
+        rust_version,
+        published_by: published_by.map(User::into),
+        audit_actions: audit_actions
+            .into_iter()
+            .map(|(audit_action, user)| EncodableAuditAction {
+                action: audit_action.action.into(),
+                user: user.into(),
+                time: audit_action.time,
+            })
+            .collect(),
+        published_via: provenance.as_ref().map(EncodablePublishedVia::from),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableReleaseNotes {
+    pub markdown: String,
+    pub html: String,
     #[serde(with = "rfc3339")]
-    pub created_at: NaiveDateTime,
-    // NOTE: Used by shields.io, altering `downloads` requires a PR with shields.io
-    pub downloads: i32,
-    pub features: serde_json::Value,
-    pub yanked: bool,
-    // NOTE: Used by shields.io, altering `license` requires a PR with shields.io
-    pub license: Option<String>,
-    pub links: EncodableVersionLinks,
-    pub crate_size: Option<i32>,
-    pub published_by: Option<EncodablePublicUser>,
-    pub audit_actions: Vec<EncodableAuditAction>,
-    pub checksum: String,
-    pub rust_version: Option<String>,
+    pub updated_at: NaiveDateTime,
 }
 
-impl EncodableVersion {
-    pub fn from(
-        version: Version,
-        crate_name: &str,
-        published_by: Option<User>,
-        audit_actions: Vec<(VersionOwnerAction, User)>,
-    ) -> Self {
-        let Version {
-            id,
-            num,
+impl From<ReleaseNotes> for EncodableReleaseNotes {
+    fn from(release_notes: ReleaseNotes) -> Self {
+        let ReleaseNotes {
+            markdown,
+            html,
             updated_at,
-            created_at,
-            downloads,
-            features,
-            yanked,
-            license,
-            crate_size,
-            checksum,
-            rust_version,
             ..
-        } = version;
+        } = release_notes;
+
+        Self {
+            markdown,
+            html,
+            updated_at,
+        }
+    }
+}
 
-        let links = EncodableVersionLinks {
-            dependencies: format!("/api/v1/crates/{crate_name}/{num}/dependencies"),
-            version_downloads: format!("/api/v1/crates/{crate_name}/{num}/downloads"),
-            authors: format!("/api/v1/crates/{crate_name}/{num}/authors"),
+impl From<&VersionProvenance> for EncodablePublishedVia {
+    fn from(provenance: &VersionProvenance) -> Self {
+        let kind = match provenance.published_with_token() {
+            true => "api_token",
+            false => "session",
         };
 
         Self {
-            dl_path: format!("/api/v1/crates/{crate_name}/{num}/download"),
-            readme_path: format!("/api/v1/crates/{crate_name}/{num}/readme"),
-            num,
-            id,
-            krate: crate_name.to_string(),
-            updated_at,
+            kind: kind.to_string(),
+            ci_service: provenance.ci_service.clone(),
+        }
+    }
+}
+
+/// The full [`VersionProvenance`] record, which is only visible to the owners
+/// of the crate.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionProvenance {
+    #[serde(flatten)]
+    pub published_via: EncodablePublishedVia,
+    pub api_token_id: Option<i32>,
+    pub api_token_name: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl From<VersionProvenance> for EncodableVersionProvenance {
+    fn from(provenance: VersionProvenance) -> Self {
+        let published_via = EncodablePublishedVia::from(&provenance);
+
+        let VersionProvenance {
+            api_token_id,
+            api_token_name,
+            user_agent,
+            ip,
+            created_at,
+            ..
+        } = provenance;
+
+        Self {
+            published_via,
+            api_token_id,
+            api_token_name,
+            user_agent,
+            ip: ip.map(|ip| ip.ip().to_string()),
             created_at,
-            downloads,
-            features,
-            yanked,
-            license,
-            links,
-            crate_size,
-            checksum,
-            rust_version,
-            published_by: published_by.map(User::into),
-            audit_actions: audit_actions
-                .into_iter()
-                .map(|(audit_action, user)| EncodableAuditAction {
-                    action: audit_action.action.into(),
-                    user: user.into(),
-                    time: audit_action.time,
-                })
-                .collect(),
         }
     }
 }
 
+/// A [`VersionArtifact`] together with the URL of its file.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct EncodableVersionLinks {
-    pub dependencies: String,
-    pub version_downloads: String,
-    pub authors: String,
+pub struct EncodableVersionArtifact {
+    pub target: String,
+    pub checksum: String,
+    pub size: i64,
+    pub url: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl EncodableVersionArtifact {
+    pub fn from(artifact: VersionArtifact, url: String) -> Self {
+        let VersionArtifact {
+            target,
+            checksum,
+            size,
+            created_at,
+            ..
+        } = artifact;
+
+        Self {
+            target,
+            checksum,
+            size,
+            url,
+            created_at,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -716,6 +1037,7 @@ mod tests {
             num: "".to_string(),
             dl_path: "".to_string(),
             readme_path: "".to_string(),
+            release_notes_path: "".to_string(),
             updated_at: NaiveDate::from_ymd_opt(2017, 1, 6)
                 .unwrap()
                 .and_hms_opt(14, 23, 11)
@@ -737,6 +1059,7 @@ mod tests {
             checksum: String::new(),
             rust_version: None,
             published_by: None,
+            published_via: None,
             audit_actions: vec![EncodableAuditAction {
                 action: "publish".to_string(),
                 user: EncodablePublicUser {
@@ -784,6 +1107,7 @@ mod tests {
             max_version: "".to_string(),
             newest_version: "".to_string(),
             max_stable_version: None,
+            recommended_version: None,
             description: None,
             homepage: None,
             documentation: None,
@@ -0,0 +1,310 @@
+//! [JSON:API](https://jsonapi.org/format/) serialization of selected
+//! endpoints.
+//!
+//! Clients opt into this mode by sending an `Accept: application/vnd.api+json`
+//! header. The documents are built from the same `Encodable*` structs as the
+//! regular responses: the `id` of a struct becomes the resource identifier,
+//! references to other resources become `relationships`, and all remaining
+//! fields become `attributes`. Sparse fieldsets (`?fields[crates]=name,...`)
+//! are supported for all resource types.
+
+use crate::views::{EncodableCrate, EncodableOwner, EncodableVersion};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use http::{header, HeaderMap};
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// The media type of JSON:API documents.
+pub const MEDIA_TYPE: &str = "application/vnd.api+json";
+
+/// Returns `true` if the client asked for a JSON:API document.
+pub fn is_requested(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| media_type.trim() == MEDIA_TYPE)
+}
+
+/// The sparse fieldsets of a request, i.e. the attributes and relationships
+/// that should be returned per resource type.
+#[derive(Debug, Default)]
+pub struct Fieldsets(HashMap<String, HashSet<String>>);
+
+impl Fieldsets {
+    /// Parses the `fields[TYPE]=a,b` query parameters.
+    pub fn from_query(query: &IndexMap<String, String>) -> Self {
+        let fieldsets = query
+            .iter()
+            .filter_map(|(key, value)| {
+                let resource_type = key.strip_prefix("fields[")?.strip_suffix(']')?;
+                let fields = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|field| !field.is_empty())
+                    .map(String::from)
+                    .collect();
+
+                Some((resource_type.to_string(), fields))
+            })
+            .collect();
+
+        Self(fieldsets)
+    }
+
+    fn apply(&self, resource: &mut Resource) {
+        if let Some(fields) = self.0.get(resource.resource_type) {
+            resource.attributes.retain(|name, _| fields.contains(name));
+            resource
+                .relationships
+                .retain(|name, _| fields.contains(name));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Resource {
+    resource_type: &'static str,
+    id: String,
+    attributes: Map<String, Value>,
+    relationships: Map<String, Value>,
+    links: Option<Value>,
+}
+
+impl Resource {
+    /// Creates a resource from a struct that serializes to an object with an
+    /// `id` field.
+    pub fn new(resource_type: &'static str, object: impl Serialize) -> Self {
+        let mut attributes = match serde_json::to_value(object) {
+            Ok(Value::Object(attributes)) => attributes,
+            _ => Map::new(),
+        };
+
+        let id = match attributes.remove("id") {
+            Some(Value::String(id)) => id,
+            Some(id) => id.to_string(),
+            None => String::new(),
+        };
+
+        let links = attributes.remove("links");
+
+        Self {
+            resource_type,
+            id,
+            attributes,
+            relationships: Map::new(),
+            links,
+        }
+    }
+
+    /// Replaces the `attribute`, which refers to resources of the
+    /// `resource_type`, with a relationship of the same name.
+    ///
+    /// The relationship is omitted if the attribute is `null`, which means
+    /// that it was not loaded.
+    fn relationship(
+        mut self,
+        attribute: &str,
+        resource_type: &str,
+        id: impl Fn(&Value) -> Option<String>,
+    ) -> Self {
+        let identifier =
+            |value: &Value| id(value).map(|id| json!({ "type": resource_type, "id": id }));
+
+        let data = match self.attributes.remove(attribute) {
+            Some(Value::Array(values)) => values.iter().filter_map(identifier).collect(),
+            Some(Value::Null) | None => return self,
+            Some(value) => identifier(&value).unwrap_or(Value::Null),
+        };
+
+        let relationship = json!({ "data": data });
+        self.relationships.insert(attribute.into(), relationship);
+        self
+    }
+
+    pub fn from_crate(krate: EncodableCrate) -> Self {
+        Self::new("crates", krate)
+            .relationship("versions", "versions", id_of_value)
+            .relationship("keywords", "keywords", id_of_value)
+            .relationship("categories", "categories", id_of_value)
+    }
+
+    pub fn from_version(version: EncodableVersion) -> Self {
+        Self::new("versions", version)
+            .relationship("crate", "crates", id_of_value)
+            .relationship("published_by", "users", id_of_object)
+    }
+
+    pub fn from_owner(owner: EncodableOwner) -> Self {
+        let resource_type = match owner.kind.as_str() {
+            "team" => "teams",
+            _ => "users",
+        };
+
+        Self::new(resource_type, owner)
+    }
+
+    fn into_value(mut self, fieldsets: &Fieldsets) -> Value {
+        fieldsets.apply(&mut self);
+
+        let mut resource = json!({
+            "type": self.resource_type,
+            "id": self.id,
+            "attributes": self.attributes,
+        });
+        if !self.relationships.is_empty() {
+            resource["relationships"] = Value::Object(self.relationships);
+        }
+        if let Some(links) = self.links {
+            resource["links"] = links;
+        }
+
+        resource
+    }
+}
+
+/// Returns the identifier of a relationship that is serialized as a plain
+/// value, e.g. a crate name or a version ID.
+fn id_of_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(id) => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the identifier of a relationship that is serialized as an object
+/// with an `id` field, e.g. a user.
+fn id_of_object(value: &Value) -> Option<String> {
+    value.get("id").and_then(id_of_value)
+}
+
+#[derive(Debug)]
+enum PrimaryData {
+    Single(Resource),
+    Collection(Vec<Resource>),
+}
+
+/// A JSON:API document with its primary data and included resources.
+#[derive(Debug)]
+pub struct Document {
+    data: PrimaryData,
+    included: Vec<Resource>,
+    fieldsets: Fieldsets,
+}
+
+impl Document {
+    pub fn single(resource: Resource, fieldsets: Fieldsets) -> Self {
+        Self {
+            data: PrimaryData::Single(resource),
+            included: Vec::new(),
+            fieldsets,
+        }
+    }
+
+    pub fn collection(resources: Vec<Resource>, fieldsets: Fieldsets) -> Self {
+        Self {
+            data: PrimaryData::Collection(resources),
+            included: Vec::new(),
+            fieldsets,
+        }
+    }
+
+    /// Adds related resources to the `included` member of the document.
+    pub fn include(mut self, resources: impl IntoIterator<Item = Resource>) -> Self {
+        self.included.extend(resources);
+        self
+    }
+}
+
+impl IntoResponse for Document {
+    fn into_response(self) -> Response {
+        let fieldsets = &self.fieldsets;
+
+        let data = match self.data {
+            PrimaryData::Single(resource) => resource.into_value(fieldsets),
+            PrimaryData::Collection(resources) => resources
+                .into_iter()
+                .map(|resource| resource.into_value(fieldsets))
+                .collect(),
+        };
+
+        let mut document = json!({ "data": data });
+        if !self.included.is_empty() {
+            let included = self
+                .included
+                .into_iter()
+                .map(|resource| resource.into_value(fieldsets))
+                .collect::<Vec<_>>();
+
+            document["included"] = json!(included);
+        }
+
+        ([(header::CONTENT_TYPE, MEDIA_TYPE)], Json(document)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn test_is_requested() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_requested(&headers));
+
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        assert!(!is_requested(&headers));
+
+        let accept = HeaderValue::from_static("application/json, application/vnd.api+json");
+        headers.insert(header::ACCEPT, accept);
+        assert!(is_requested(&headers));
+    }
+
+    #[test]
+    fn test_relationships_and_fieldsets() {
+        let object = json!({
+            "id": 42,
+            "name": "foo",
+            "description": "bar",
+            "versions": [1, 2],
+            "keywords": null,
+            "links": { "self": "/foo" },
+        });
+
+        let resource = Resource::new("crates", object)
+            .relationship("versions", "versions", id_of_value)
+            .relationship("keywords", "keywords", id_of_value);
+
+        let value = resource.into_value(&Fieldsets::default());
+        assert_eq!(
+            value,
+            json!({
+                "type": "crates",
+                "id": "42",
+                "attributes": { "name": "foo", "description": "bar" },
+                "relationships": {
+                    "versions": {
+                        "data": [
+                            { "type": "versions", "id": "1" },
+                            { "type": "versions", "id": "2" },
+                        ],
+                    },
+                },
+                "links": { "self": "/foo" },
+            })
+        );
+
+        let query = IndexMap::from([("fields[crates]".to_string(), "name".to_string())]);
+        let fieldsets = Fieldsets::from_query(&query);
+
+        let resource = Resource::new("crates", json!({ "id": "foo", "name": "foo", "x": 1 }));
+        let value = resource.into_value(&fieldsets);
+        assert_eq!(value["attributes"], json!({ "name": "foo" }));
+    }
+}
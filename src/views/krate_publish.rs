@@ -3,9 +3,11 @@
 //! to and from structs. The serializing is only utilised in
 //! integration tests.
 
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 use crate::models::DependencyKind;
+use crate::util::rfc3339;
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct PublishMetadata {
@@ -13,6 +15,14 @@ pub struct PublishMetadata {
     pub vers: String,
     pub readme: Option<String>,
     pub readme_file: Option<String>,
+    /// Publishes the version with a scheduled release. The version is hidden
+    /// until this time (see [`ScheduledRelease`](crate::models::ScheduledRelease)).
+    #[serde(
+        default,
+        with = "rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub release_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug)]
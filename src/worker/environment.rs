@@ -1,3 +1,4 @@
+use crate::audit::AuditLog;
 use crate::cloudfront::CloudFront;
 use crate::fastly::Fastly;
 use crate::storage::Storage;
@@ -30,6 +31,10 @@ pub struct Environment {
     pub emails: Emails,
     pub team_repo: Box<dyn TeamRepo + Send + Sync>,
 
+    /// The sinks of the audit events of the jobs, see [`crate::audit`]
+    #[builder(default)]
+    pub audit_log: AuditLog,
+
     /// A lazily initialised cache of the most popular crates ready to use in typosquatting checks.
     #[builder(default, setter(skip))]
     typosquat_cache: OnceLock<Result<typosquat::Cache, typosquat::CacheError>>,
@@ -0,0 +1,70 @@
+use crate::models::{Category, CategoryMigration};
+use crate::schema::category_migrations;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use std::sync::Arc;
+
+/// Moves all crates of the source category of a [`CategoryMigration`] to its
+/// target category.
+///
+/// The number of moved crates is recorded in the `category_migrations`
+/// table. Migrations that have already been performed are skipped, so the
+/// job can safely be retried.
+#[derive(Serialize, Deserialize)]
+pub struct MigrateCategory {
+    migration_id: i64,
+}
+
+impl MigrateCategory {
+    pub fn new(migration_id: i64) -> Self {
+        Self { migration_id }
+    }
+}
+
+impl BackgroundJob for MigrateCategory {
+    const JOB_NAME: &'static str = "migrate_category";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(migration_id = self.migration_id))]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let migration_id = self.migration_id;
+
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| {
+            conn.transaction::<_, anyhow::Error, _>(|conn| {
+                let migration: CategoryMigration = category_migrations::table
+                    .find(migration_id)
+                    .select(CategoryMigration::as_select())
+                    .for_update()
+                    .first(conn)?;
+
+                if migration.finished_at.is_some() {
+                    info!("Category migration was already performed");
+                    return Ok(());
+                }
+
+                let count = Category::migrate_crates(
+                    conn,
+                    migration.source_category_id,
+                    migration.target_category_id,
+                )?;
+
+                diesel::update(category_migrations::table.find(migration_id))
+                    .set((
+                        category_migrations::crates_migrated.eq(count as i32),
+                        category_migrations::finished_at.eq(diesel::dsl::now),
+                    ))
+                    .execute(conn)?;
+
+                info!("Moved {count} crates to the target category");
+
+                Ok(())
+            })
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
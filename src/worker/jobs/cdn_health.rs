@@ -0,0 +1,72 @@
+use crate::models::CdnHealthProbe;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Requests that take longer than this are counted as errors.
+const PROBE_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Requests the file at `CDN_HEALTH_PROBE_PATH` from the CDN a few times, and
+/// records the share of failed requests in the `cdn_health_probes` table.
+///
+/// The recorded probes determine whether crate downloads are redirected to a
+/// fallback location (see [`crate::cdn_fallback`]). The job is supposed to
+/// run every minute, since the probes are ignored after 15 minutes.
+#[derive(Serialize, Deserialize)]
+pub struct ProbeCdnHealth;
+
+impl BackgroundJob for ProbeCdnHealth {
+    const JOB_NAME: &'static str = "probe_cdn_health";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let config = &env.config.cdn_fallback;
+
+        let Some(probe_path) = &config.probe_path else {
+            info!("Skipping CDN health probe since CDN_HEALTH_PROBE_PATH is not set");
+            return Ok(());
+        };
+
+        let Some(url) = env.storage.cdn_location(probe_path) else {
+            warn!("Skipping CDN health probe since no CDN is configured");
+            return Ok(());
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(PROBE_REQUEST_TIMEOUT)
+            .build()?;
+
+        let requests = config.probe_requests;
+        let mut errors = 0;
+        for _ in 0..requests {
+            let result = client
+                .head(&url)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+
+            if let Err(error) = result {
+                debug!(%error, "CDN health probe request failed");
+                errors += 1;
+            }
+        }
+
+        let error_rate = errors as f64 / requests.max(1) as f64;
+        if error_rate >= config.error_rate {
+            warn!(%url, requests, errors, "CDN health probe shows an elevated error rate");
+        } else {
+            info!(%url, requests, errors, "CDN health probe finished");
+        }
+
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| CdnHealthProbe::record(conn, requests as i32, errors))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        Ok(())
+    }
+}
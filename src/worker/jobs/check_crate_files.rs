@@ -0,0 +1,132 @@
+use crate::models::{Crate, Version};
+use crate::schema::{crates, readme_renderings};
+use crate::worker::jobs::RenderAndUploadReadme;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Checks that the crate files and rendered READMEs of all versions of a
+/// crate exist in the storage.
+///
+/// Crate files can not be restored from the database, so missing crate files
+/// are only reported. The README of the most recently published version is
+/// stored in the `crates.readme` column though, so it is rendered and uploaded
+/// again if it is missing.
+#[derive(Serialize, Deserialize)]
+pub struct CheckCrateFiles {
+    krate: String,
+}
+
+impl CheckCrateFiles {
+    pub fn new(krate: impl Into<String>) -> Self {
+        let krate = krate.into();
+        Self { krate }
+    }
+}
+
+impl BackgroundJob for CheckCrateFiles {
+    const JOB_NAME: &'static str = "check_crate_files";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(krate.name = ? self.krate))]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let crate_name = self.krate.clone();
+        let conn = env.deadpool.get().await?;
+        let (krate, readme, versions, rendered_readmes) = conn
+            .interact(move |conn| load_crate(&crate_name, conn))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        let mut missing_crate_files = Vec::new();
+        for version in &versions {
+            if !env
+                .storage
+                .crate_file_exists(&krate.name, &version.num)
+                .await?
+            {
+                missing_crate_files.push(version.num.as_str());
+            }
+        }
+
+        let latest_version = versions.iter().max_by_key(|version| version.created_at);
+        let readme = readme.filter(|readme| !readme.is_empty());
+
+        let mut missing_readmes = Vec::new();
+        let mut readme_job = None;
+        for version in versions.iter() {
+            if !rendered_readmes.contains(&version.id)
+                || env.storage.readme_exists(&krate.name, &version.num).await?
+            {
+                continue;
+            }
+
+            let is_latest = latest_version.is_some_and(|latest| latest.id == version.id);
+            match &readme {
+                Some(readme) if is_latest => {
+                    info!(version = %version.num, "Re-rendering missing README");
+                    readme_job = Some(RenderAndUploadReadme::new(
+                        version.id,
+                        readme.clone(),
+                        // The original README file path and the path of the
+                        // package in the repository are not stored in the
+                        // database, so we fall back to the defaults here.
+                        String::from("README.md"),
+                        krate.repository.clone(),
+                        None,
+                    ));
+                }
+                _ => missing_readmes.push(version.num.as_str()),
+            }
+        }
+
+        if !missing_crate_files.is_empty() {
+            error!(
+                ?missing_crate_files,
+                "Crate files are missing from the storage"
+            );
+        }
+
+        if !missing_readmes.is_empty() {
+            warn!(
+                ?missing_readmes,
+                "Rendered READMEs are missing from the storage"
+            );
+        }
+
+        if let Some(job) = readme_job {
+            let conn = env.deadpool.get().await?;
+            conn.interact(move |conn| job.enqueue(conn))
+                .await
+                .map_err(|err| anyhow!(err.to_string()))??;
+        }
+
+        Ok(())
+    }
+}
+
+type CrateData = (Crate, Option<String>, Vec<Version>, HashSet<i32>);
+
+fn load_crate(crate_name: &str, conn: &mut PgConnection) -> anyhow::Result<CrateData> {
+    let krate: Crate = Crate::by_name(crate_name).first(conn)?;
+
+    let readme = crates::table
+        .find(krate.id)
+        .select(crates::readme)
+        .first(conn)?;
+
+    let versions: Vec<Version> = Version::belonging_to(&krate).load(conn)?;
+
+    let version_ids = versions.iter().map(|version| version.id);
+    let rendered_readmes = readme_renderings::table
+        .filter(readme_renderings::version_id.eq_any(version_ids))
+        .select(readme_renderings::version_id)
+        .load::<i32>(conn)?
+        .into_iter()
+        .collect();
+
+    Ok((krate, readme, versions, rendered_readmes))
+}
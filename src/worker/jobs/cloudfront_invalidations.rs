@@ -0,0 +1,218 @@
+use crate::schema::cloudfront_invalidation_queue;
+use crate::worker::Environment;
+use anyhow::{anyhow, Context};
+use chrono::{NaiveDateTime, Utc};
+use crates_io_worker::schema::background_jobs;
+use crates_io_worker::{BackgroundJob, EnqueueError};
+use diesel::dsl::{exists, not};
+use diesel::prelude::*;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Maximum number of queued paths that are sent to CloudFront in a single
+/// invalidation request.
+///
+/// CloudFront allows up to 3000 paths to be in progress at the same time, so
+/// this leaves some room for invalidations that were created manually.
+const MAX_BATCH_SIZE: i64 = 1000;
+
+/// Adds the paths to the CloudFront invalidation queue and enqueues a
+/// [`FlushCloudFrontInvalidations`] job, unless one is already waiting to be
+/// run.
+///
+/// The job is not deduplicated against a job that is currently running,
+/// since that job might have loaded its batch before these paths were added.
+#[instrument(skip_all, fields(paths.count = paths.len()))]
+pub fn queue_cloudfront_invalidations(
+    conn: &mut PgConnection,
+    paths: &[String],
+) -> Result<(), EnqueueError> {
+    let rows = paths
+        .iter()
+        .map(|path| cloudfront_invalidation_queue::path.eq(path))
+        .collect::<Vec<_>>();
+
+    diesel::insert_into(cloudfront_invalidation_queue::table)
+        .values(&rows)
+        .execute(conn)?;
+
+    let pending_jobs = background_jobs::table
+        .select(background_jobs::id)
+        .filter(background_jobs::job_type.eq(FlushCloudFrontInvalidations::JOB_NAME))
+        .for_update()
+        .skip_locked();
+
+    if diesel::select(not(exists(pending_jobs))).get_result(conn)? {
+        FlushCloudFrontInvalidations.enqueue(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Sends the queued CloudFront invalidations in batches of up to
+/// [`MAX_BATCH_SIZE`] paths.
+///
+/// Paths are only removed from the queue once CloudFront accepted the
+/// invalidation request. If the request fails, e.g. because too many
+/// invalidations are in progress, the job fails and the remaining paths are
+/// sent when the job is retried.
+#[derive(Serialize, Deserialize)]
+pub struct FlushCloudFrontInvalidations;
+
+impl BackgroundJob for FlushCloudFrontInvalidations {
+    const JOB_NAME: &'static str = "flush_cloudfront_invalidations";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let Some(cloudfront) = env.cloudfront() else {
+            warn!("CloudFront is not configured, skipping queued invalidations");
+            return Ok(());
+        };
+
+        loop {
+            let conn = env.deadpool.get().await?;
+            let batch = conn
+                .interact(|conn| Batch::load(conn, MAX_BATCH_SIZE))
+                .await
+                .map_err(|err| anyhow!(err.to_string()))??;
+
+            let Some(batch) = batch else {
+                info!("No more queued CloudFront invalidations");
+                return Ok(());
+            };
+
+            let is_full = batch.ids.len() as i64 >= MAX_BATCH_SIZE;
+            let paths = batch.paths.iter().cloned().collect::<Vec<_>>();
+
+            info!(
+                paths.count = paths.len(),
+                rows.count = batch.ids.len(),
+                "Invalidating queued paths on CloudFront"
+            );
+
+            let future = cloudfront.invalidate_many(paths);
+            future.await.context("Failed to invalidate CloudFront")?;
+
+            let latency = Utc::now().naive_utc() - batch.oldest_created_at;
+            info!(
+                latency_secs = latency.num_seconds(),
+                "Queued paths were invalidated on CloudFront"
+            );
+
+            let conn = env.deadpool.get().await?;
+            conn.interact(move |conn| batch.delete(conn))
+                .await
+                .map_err(|err| anyhow!(err.to_string()))??;
+
+            // Paths that were queued while this batch was sent are handled
+            // by the job that was enqueued together with them.
+            if !is_full {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// A batch of queued paths, with duplicate paths combined.
+#[derive(Debug)]
+struct Batch {
+    ids: Vec<i64>,
+    paths: BTreeSet<String>,
+    oldest_created_at: NaiveDateTime,
+}
+
+impl Batch {
+    /// Loads up to `limit` of the oldest rows from the queue, or returns
+    /// `None` if the queue is empty.
+    fn load(conn: &mut PgConnection, limit: i64) -> QueryResult<Option<Self>> {
+        let rows: Vec<(i64, String, NaiveDateTime)> = cloudfront_invalidation_queue::table
+            .select((
+                cloudfront_invalidation_queue::id,
+                cloudfront_invalidation_queue::path,
+                cloudfront_invalidation_queue::created_at,
+            ))
+            .order(cloudfront_invalidation_queue::id)
+            .limit(limit)
+            .load(conn)?;
+
+        let Some(oldest_created_at) = rows.iter().map(|(_, _, created_at)| *created_at).min()
+        else {
+            return Ok(None);
+        };
+
+        let (ids, paths) = rows.into_iter().map(|(id, path, _)| (id, path)).unzip();
+
+        Ok(Some(Self {
+            ids,
+            paths,
+            oldest_created_at,
+        }))
+    }
+
+    /// Removes the rows of this batch from the queue.
+    fn delete(&self, conn: &mut PgConnection) -> QueryResult<usize> {
+        let query = cloudfront_invalidation_queue::table
+            .filter(cloudfront_invalidation_queue::id.eq_any(&self.ids));
+
+        diesel::delete(query).execute(conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_db_connection;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    fn job_count(conn: &mut PgConnection) -> i64 {
+        background_jobs::table
+            .filter(background_jobs::job_type.eq(FlushCloudFrontInvalidations::JOB_NAME))
+            .count()
+            .get_result(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn queue_enqueues_a_single_job() {
+        let (_test_db, conn) = &mut test_db_connection();
+
+        queue_cloudfront_invalidations(conn, &paths(&["/config.json"])).unwrap();
+        queue_cloudfront_invalidations(conn, &paths(&["/fo/o/foo", "/fo/o/foo.sig"])).unwrap();
+
+        let queued: i64 = cloudfront_invalidation_queue::table
+            .count()
+            .get_result(conn)
+            .unwrap();
+
+        assert_eq!(queued, 3);
+        assert_eq!(job_count(conn), 1);
+    }
+
+    #[test]
+    fn batches_combine_duplicate_paths() {
+        let (_test_db, conn) = &mut test_db_connection();
+
+        let queued = paths(&["/3/f/foo", "/3/b/bar", "/3/f/foo", "/se/rd/serde"]);
+        queue_cloudfront_invalidations(conn, &queued).unwrap();
+
+        let batch = Batch::load(conn, 3).unwrap().unwrap();
+        assert_eq!(batch.ids.len(), 3);
+        assert_eq!(
+            batch.paths,
+            paths(&["/3/b/bar", "/3/f/foo"]).into_iter().collect()
+        );
+
+        assert_eq!(batch.delete(conn).unwrap(), 3);
+
+        let batch = Batch::load(conn, 3).unwrap().unwrap();
+        assert_eq!(batch.paths, paths(&["/se/rd/serde"]).into_iter().collect());
+
+        batch.delete(conn).unwrap();
+        assert_none!(Batch::load(conn, 3).unwrap());
+    }
+}
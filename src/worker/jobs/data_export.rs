@@ -0,0 +1,334 @@
+use crate::email::Email;
+use crate::models::{CrateOwner, OwnerKind, VersionAction};
+use crate::schema::{
+    api_tokens, crate_owners, crates, emails, users, version_owner_actions, versions,
+};
+use crate::util::retry::RetryPolicy;
+use crate::util::{data_export, rfc3339};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use axum::body::Bytes;
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ipnetwork::IpNetwork;
+use std::sync::Arc;
+
+/// The number of days that the download link of a data export is valid.
+const LINK_VALIDITY_DAYS: i64 = 7;
+
+/// Assembles the personal data of a user into a `.tar.gz` archive of JSON
+/// files, uploads it to the storage and sends the user an email with a
+/// signed download link, that expires after [`LINK_VALIDITY_DAYS`].
+///
+/// Only the latest export of a user is kept in the storage, and it's deleted
+/// by the [`DeleteExpiredDataExports`] job once the link has expired.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportUserData {
+    user_id: i32,
+}
+
+impl ExportUserData {
+    pub fn new(user_id: i32) -> Self {
+        Self { user_id }
+    }
+}
+
+impl BackgroundJob for ExportUserData {
+    const JOB_NAME: &'static str = "export_user_data";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let user_id = self.user_id;
+
+        let conn = env.deadpool.get().await?;
+        let data = conn
+            .interact(move |conn| UserData::load(conn, user_id))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        let Some(data) = data else {
+            warn!("User does not exist anymore, skipping the data export");
+            return Ok(());
+        };
+
+        let Some(recipient) = data.profile.verified_email() else {
+            warn!("User has no verified email address, skipping the data export");
+            return Ok(());
+        };
+
+        info!("Uploading data export");
+        let archive = Bytes::from(data.to_archive()?);
+        let export_id = hex::encode(rand::random::<[u8; 16]>());
+        RetryPolicy::default()
+            .run("data_export_upload", || async {
+                let future = env
+                    .storage
+                    .upload_data_export(user_id, &export_id, archive.clone());
+                Ok(future.await?)
+            })
+            .await?;
+
+        let expires_at = Utc::now() + TimeDelta::try_days(LINK_VALIDITY_DAYS).unwrap();
+        let token = data_export::generate_token(
+            &env.config.session_key,
+            user_id,
+            &export_id,
+            expires_at.timestamp(),
+        );
+
+        let email = DataExportReadyEmail {
+            domain: &env.emails.domain,
+            token,
+            expires_at,
+        };
+
+        env.emails.send(recipient, email)?;
+
+        Ok(())
+    }
+}
+
+/// Deletes the data export archives whose download links have expired.
+///
+/// The archives contain personal data, so they are not kept in the storage
+/// longer than necessary.
+#[derive(Serialize, Deserialize)]
+pub struct DeleteExpiredDataExports;
+
+impl BackgroundJob for DeleteExpiredDataExports {
+    const JOB_NAME: &'static str = "delete_expired_data_exports";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let before = Utc::now() - TimeDelta::try_days(LINK_VALIDITY_DAYS).unwrap();
+        let count = env.storage.delete_data_exports_before(before).await?;
+
+        info!("Deleted {count} expired data exports");
+
+        Ok(())
+    }
+}
+
+/// The personal data of a user, as it is included in the data export.
+struct UserData {
+    profile: Profile,
+    emails: Vec<EmailAddress>,
+    crates: Vec<OwnedCrate>,
+    tokens: Vec<Token>,
+    actions: Vec<Action>,
+}
+
+#[derive(Debug, Queryable, Serialize)]
+struct Profile {
+    id: i32,
+    login: String,
+    name: Option<String>,
+    avatar: Option<String>,
+    github_id: i32,
+    locale: String,
+    follow_digest: bool,
+    email: Option<String>,
+    email_verified: Option<bool>,
+}
+
+impl Profile {
+    fn verified_email(&self) -> Option<&str> {
+        let verified = self.email_verified.unwrap_or(false);
+        self.email.as_deref().filter(|_| verified)
+    }
+}
+
+#[derive(Debug, Queryable, Serialize)]
+struct EmailAddress {
+    email: String,
+    verified: bool,
+    primary: bool,
+}
+
+#[derive(Debug, Queryable, Serialize)]
+struct OwnedCrate {
+    name: String,
+    #[serde(with = "rfc3339")]
+    owner_since: NaiveDateTime,
+    email_notifications: bool,
+}
+
+#[derive(Debug, Queryable, Serialize)]
+struct Token {
+    name: String,
+    #[serde(with = "rfc3339")]
+    created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    last_used_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339::option")]
+    expired_at: Option<NaiveDateTime>,
+    revoked: bool,
+    crate_scopes: Option<Vec<String>>,
+    endpoint_scopes: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Action {
+    #[serde(rename = "crate")]
+    krate: String,
+    version: String,
+    action: &'static str,
+    #[serde(with = "rfc3339")]
+    time: NaiveDateTime,
+    ip: Option<String>,
+    reason: Option<String>,
+}
+
+impl UserData {
+    /// Loads the personal data of the user, or returns `None` if the user
+    /// does not exist.
+    fn load(conn: &mut PgConnection, user_id: i32) -> QueryResult<Option<Self>> {
+        let profile = users::table
+            .find(user_id)
+            .left_join(emails::table.on(emails::user_id.eq(users::id).and(emails::is_primary)))
+            .select((
+                users::id,
+                users::gh_login,
+                users::name,
+                users::gh_avatar,
+                users::gh_id,
+                users::locale,
+                users::follow_digest,
+                emails::email.nullable(),
+                emails::verified.nullable(),
+            ))
+            .first(conn)
+            .optional()?;
+
+        let Some(profile) = profile else {
+            return Ok(None);
+        };
+
+        let emails = emails::table
+            .filter(emails::user_id.eq(user_id))
+            .select((emails::email, emails::verified, emails::is_primary))
+            .order((emails::is_primary.desc(), emails::id.asc()))
+            .load(conn)?;
+
+        let crates = CrateOwner::by_owner_kind(OwnerKind::User)
+            .inner_join(crates::table)
+            .filter(crate_owners::owner_id.eq(user_id))
+            .select((
+                crates::name,
+                crate_owners::created_at,
+                crate_owners::email_notifications,
+            ))
+            .order(crates::name.asc())
+            .load(conn)?;
+
+        let tokens = api_tokens::table
+            .filter(api_tokens::user_id.eq(user_id))
+            .select((
+                api_tokens::name,
+                api_tokens::created_at,
+                api_tokens::last_used_at,
+                api_tokens::expired_at,
+                api_tokens::revoked,
+                api_tokens::crate_scopes,
+                api_tokens::endpoint_scopes,
+            ))
+            .order(api_tokens::created_at.asc())
+            .load(conn)?;
+
+        let actions = version_owner_actions::table
+            .inner_join(versions::table.inner_join(crates::table))
+            .filter(version_owner_actions::user_id.eq(user_id))
+            .select((
+                crates::name,
+                versions::num,
+                version_owner_actions::action,
+                version_owner_actions::time,
+                version_owner_actions::ip,
+                version_owner_actions::reason,
+            ))
+            .order(version_owner_actions::time.asc())
+            .load::<(
+                String,
+                String,
+                VersionAction,
+                NaiveDateTime,
+                Option<IpNetwork>,
+                Option<String>,
+            )>(conn)?
+            .into_iter()
+            .map(|(krate, version, action, time, ip, reason)| Action {
+                krate,
+                version,
+                action: action.into(),
+                time,
+                ip: ip.map(|ip| ip.ip().to_string()),
+                reason,
+            })
+            .collect();
+
+        Ok(Some(Self {
+            profile,
+            emails,
+            crates,
+            tokens,
+            actions,
+        }))
+    }
+
+    /// Creates a `.tar.gz` archive with one JSON file per kind of data.
+    fn to_archive(&self) -> anyhow::Result<Vec<u8>> {
+        let files = [
+            ("profile.json", serde_json::to_vec_pretty(&self.profile)?),
+            ("emails.json", serde_json::to_vec_pretty(&self.emails)?),
+            ("crates.json", serde_json::to_vec_pretty(&self.crates)?),
+            ("tokens.json", serde_json::to_vec_pretty(&self.tokens)?),
+            ("actions.json", serde_json::to_vec_pretty(&self.actions)?),
+        ];
+
+        let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(Utc::now().timestamp() as u64);
+            header.set_cksum();
+
+            let path = format!("crates-io-data-export/{path}");
+            archive.append_data(&mut header, path, content.as_slice())?;
+        }
+
+        Ok(archive.into_inner()?.finish()?)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DataExportReadyEmail<'a> {
+    domain: &'a str,
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl Email for DataExportReadyEmail<'_> {
+    const SUBJECT: &'static str = "Your data export is ready";
+
+    fn body(&self) -> String {
+        let domain = self.domain;
+        let token = &self.token;
+        let expires_at = self.expires_at.format("%Y-%m-%d at %H:%M:%S UTC");
+
+        format!(
+            "The export of your personal data on {domain} that you requested is ready. You can download it until {expires_at} from:
+
+https://{domain}/api/v1/me/export/{token}
+
+If you did not request this export, please contact help@crates.io."
+        )
+    }
+}
@@ -0,0 +1,199 @@
+use crate::worker::Environment;
+use anyhow::anyhow;
+use chrono::{NaiveDate, TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Rows of the `version_downloads` table that have been processed by the
+/// [`UpdateDownloads`](super::UpdateDownloads) job are deleted after this
+/// many days. The frontend only shows the downloads of the last 90 days, and
+/// the totals have already been aggregated into the `versions` and
+/// `crate_downloads` tables at this point.
+const VERSION_DOWNLOADS_RETENTION_DAYS: i64 = 90;
+
+/// The maximum number of rows that are deleted by a single `DELETE` query.
+pub(super) const BATCH_SIZE: i64 = 10_000;
+
+/// The pause between two batches, which gives autovacuum and the replicas a
+/// chance to keep up with the deletions.
+const BATCH_PAUSE: Duration = Duration::from_secs(1);
+
+/// This job is responsible for pruning old rows from high-churn tables to
+/// reduce database bloat.
+///
+/// Expired crate owner invitations are deleted by the separate
+/// [`CleanupExpiredInvitations`](super::CleanupExpiredInvitations) job, which
+/// also reports on them.
+///
+/// There is no cleanup of expired sessions, since sessions are stored in
+/// signed cookies on the client and not in a database table.
+///
+/// The rows are deleted in batches with a short pause in between to avoid
+/// holding locks for too long and to avoid generating huge amounts of WAL
+/// in a short period of time.
+#[derive(Serialize, Deserialize)]
+pub struct DataRetention;
+
+impl BackgroundJob for DataRetention {
+    const JOB_NAME: &'static str = "data_retention";
+
+    type Context = Arc<Environment>;
+
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let cut_off_date = version_downloads_cut_off_date();
+        info!(%cut_off_date, "Pruning processed version_downloads rows…");
+        let count = run_batched(&env, move |conn| {
+            delete_version_downloads_batch(conn, cut_off_date, BATCH_SIZE)
+        })
+        .await?;
+        info!("Deleted {count} version_downloads rows");
+
+        Ok(())
+    }
+}
+
+/// Runs `delete_batch` repeatedly until it deletes less than [`BATCH_SIZE`]
+/// rows, and returns the total number of deleted rows.
+pub(super) async fn run_batched<F>(env: &Environment, delete_batch: F) -> anyhow::Result<usize>
+where
+    F: Fn(&mut PgConnection) -> QueryResult<usize> + Clone + Send + 'static,
+{
+    let mut total = 0;
+    loop {
+        let conn = env.deadpool.get().await?;
+        let count = conn
+            .interact(delete_batch.clone())
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        total += count;
+        if (count as i64) < BATCH_SIZE {
+            return Ok(total);
+        }
+
+        tokio::time::sleep(BATCH_PAUSE).await;
+    }
+}
+
+fn version_downloads_cut_off_date() -> NaiveDate {
+    let retention = TimeDelta::try_days(VERSION_DOWNLOADS_RETENTION_DAYS).unwrap();
+    (Utc::now() - retention).date_naive()
+}
+
+/// Deletes up to `batch_size` processed `version_downloads` rows that are
+/// older than `cut_off_date`.
+fn delete_version_downloads_batch(
+    conn: &mut PgConnection,
+    cut_off_date: NaiveDate,
+    batch_size: i64,
+) -> QueryResult<usize> {
+    // `version_downloads` has a composite primary key, so we use the `ctid`
+    // system column to select the rows of the batch.
+    diesel::sql_query(
+        r#"
+            DELETE FROM version_downloads
+            WHERE ctid = ANY(ARRAY(
+                SELECT ctid
+                FROM version_downloads
+                WHERE processed AND date < $1
+                LIMIT $2
+            ))
+        "#,
+    )
+    .bind::<Date, _>(cut_off_date)
+    .bind::<BigInt, _>(batch_size)
+    .execute(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditLog;
+    use crate::email::Emails;
+    use crate::models::{NewCrate, NewUser, NewVersion, User, Version};
+    use crate::schema::version_downloads;
+    use crate::test_util::test_db_connection;
+    use std::collections::BTreeMap;
+
+    fn user(conn: &mut PgConnection, gh_id: i32, login: &str) -> User {
+        NewUser::new(gh_id, login, None, None, "access_token")
+            .create_or_update(None, &Emails::new_in_memory(), conn)
+            .unwrap()
+    }
+
+    fn version(conn: &mut PgConnection, user_id: i32) -> Version {
+        let krate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create(conn, user_id, &AuditLog::default())
+        .unwrap();
+
+        NewVersion::new(
+            krate.id,
+            &semver::Version::parse("1.0.0").unwrap(),
+            &BTreeMap::new(),
+            None,
+            0,
+            user_id,
+            "0000000000000000000000000000000000000000000000000000000000000000".to_string(),
+            None,
+            None,
+        )
+        .unwrap()
+        .save(conn, "someone@example.com")
+        .unwrap()
+    }
+
+    #[test]
+    fn prune_version_downloads() {
+        let (_test_db, conn) = &mut test_db_connection();
+        let user = user(conn, 1, "foo");
+        let version = version(conn, user.id);
+
+        let cut_off_date = version_downloads_cut_off_date();
+        let day = TimeDelta::try_days(1).unwrap();
+
+        let rows = [
+            (cut_off_date - day * 10, true),
+            (cut_off_date - day * 2, true),
+            (cut_off_date - day, false),
+            (cut_off_date, true),
+            (cut_off_date + day, true),
+        ];
+        for (date, processed) in rows {
+            diesel::insert_into(version_downloads::table)
+                .values((
+                    version_downloads::version_id.eq(version.id),
+                    version_downloads::date.eq(date),
+                    version_downloads::processed.eq(processed),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+
+        assert_eq!(delete_version_downloads_batch(conn, cut_off_date, 1), Ok(1));
+        assert_eq!(
+            delete_version_downloads_batch(conn, cut_off_date, 10),
+            Ok(1)
+        );
+        assert_eq!(
+            delete_version_downloads_batch(conn, cut_off_date, 10),
+            Ok(0)
+        );
+
+        let remaining = version_downloads::table
+            .select(version_downloads::date)
+            .order(version_downloads::date)
+            .load::<NaiveDate>(conn)
+            .unwrap();
+
+        assert_eq!(
+            remaining,
+            vec![cut_off_date - day, cut_off_date, cut_off_date + day]
+        );
+    }
+}
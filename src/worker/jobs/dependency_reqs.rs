@@ -0,0 +1,306 @@
+use crate::schema::{dependencies, dependency_req_backfill_rows, dependency_req_backfills};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer};
+use std::sync::Arc;
+
+/// Number of dependencies that are processed in a single transaction.
+const BATCH_SIZE: i64 = 5000;
+
+/// Number of batches that are processed before the job enqueues itself
+/// again, so that the backfill does not block the worker for too long.
+const BATCHES_PER_RUN: usize = 20;
+
+/// Rewrites the version requirements of all dependencies in their canonical
+/// semver form, e.g. `>= 0` as `>=0` and `1.0` as `^1.0`, so that they can
+/// be matched against version ranges.
+///
+/// The progress is recorded in the `dependency_req_backfills` table after
+/// every batch, so a failed job continues after the last processed
+/// dependency. The requirements that were changed or could not be parsed
+/// are recorded in the `dependency_req_backfill_rows` table, which is the
+/// report of the backfill. A dry run only records the report.
+///
+/// The index files are not synced by the backfill, they keep the original
+/// requirements until the next sync of the crate.
+#[derive(Serialize, Deserialize)]
+pub struct NormalizeDependencyReqs {
+    backfill_id: i64,
+}
+
+impl NormalizeDependencyReqs {
+    pub fn new(backfill_id: i64) -> Self {
+        Self { backfill_id }
+    }
+
+    /// Records a new backfill and returns the job that performs it.
+    pub fn start(conn: &mut PgConnection, dry_run: bool) -> QueryResult<Self> {
+        let backfill_id = diesel::insert_into(dependency_req_backfills::table)
+            .values(dependency_req_backfills::dry_run.eq(dry_run))
+            .returning(dependency_req_backfills::id)
+            .get_result(conn)?;
+
+        Ok(Self::new(backfill_id))
+    }
+}
+
+impl BackgroundJob for NormalizeDependencyReqs {
+    const JOB_NAME: &'static str = "normalize_dependency_reqs";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(backfill_id = self.backfill_id))]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let backfill_id = self.backfill_id;
+
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| {
+            for _ in 0..BATCHES_PER_RUN {
+                if !process_batch(conn, backfill_id)? {
+                    log_report(conn, backfill_id)?;
+                    return Ok(());
+                }
+            }
+
+            NormalizeDependencyReqs::new(backfill_id).enqueue(conn)?;
+            Ok(())
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
+
+/// Returns the canonical form of a version requirement.
+fn normalize_req(req: &str) -> Result<String, semver::Error> {
+    semver::VersionReq::parse(req).map(|req| req.to_string())
+}
+
+/// Processes the dependencies after the checkpoint of the backfill, and
+/// returns whether there are more dependencies to process.
+fn process_batch(conn: &mut PgConnection, backfill_id: i64) -> anyhow::Result<bool> {
+    conn.transaction(|conn| {
+        let (dry_run, last_dependency_id, finished_at) = dependency_req_backfills::table
+            .find(backfill_id)
+            .select((
+                dependency_req_backfills::dry_run,
+                dependency_req_backfills::last_dependency_id,
+                dependency_req_backfills::finished_at,
+            ))
+            .for_update()
+            .first::<(bool, i32, Option<chrono::NaiveDateTime>)>(conn)?;
+
+        if finished_at.is_some() {
+            return Ok(false);
+        }
+
+        let rows: Vec<(i32, String)> = dependencies::table
+            .filter(dependencies::id.gt(last_dependency_id))
+            .select((dependencies::id, dependencies::req))
+            .order(dependencies::id)
+            .limit(BATCH_SIZE)
+            .load(conn)?;
+
+        let Some(&(last_id, _)) = rows.last() else {
+            diesel::update(dependency_req_backfills::table.find(backfill_id))
+                .set(dependency_req_backfills::finished_at.eq(now))
+                .execute(conn)?;
+
+            return Ok(false);
+        };
+
+        let mut report = Vec::new();
+        let mut normalized = 0i64;
+        let mut irregular = 0i64;
+        for (dependency_id, req) in &rows {
+            let (normalized_req, error) = match normalize_req(req) {
+                Ok(normalized_req) if normalized_req == *req => continue,
+                Ok(normalized_req) => {
+                    normalized += 1;
+                    (Some(normalized_req), None)
+                }
+                Err(error) => {
+                    irregular += 1;
+                    (None, Some(error.to_string()))
+                }
+            };
+
+            report.push((
+                dependency_req_backfill_rows::backfill_id.eq(backfill_id),
+                dependency_req_backfill_rows::dependency_id.eq(*dependency_id),
+                dependency_req_backfill_rows::original_req.eq(req.clone()),
+                dependency_req_backfill_rows::normalized_req.eq(normalized_req),
+                dependency_req_backfill_rows::error.eq(error),
+            ));
+        }
+
+        diesel::insert_into(dependency_req_backfill_rows::table)
+            .values(&report)
+            .execute(conn)?;
+
+        if !dry_run {
+            diesel::sql_query(
+                r#"
+                    UPDATE dependencies
+                    SET req = rows.normalized_req
+                    FROM dependency_req_backfill_rows rows
+                    WHERE rows.backfill_id = $1
+                      AND rows.dependency_id = dependencies.id
+                      AND rows.normalized_req IS NOT NULL
+                      AND dependencies.id > $2
+                      AND dependencies.id <= $3
+                "#,
+            )
+            .bind::<BigInt, _>(backfill_id)
+            .bind::<Integer, _>(last_dependency_id)
+            .bind::<Integer, _>(last_id)
+            .execute(conn)?;
+        }
+
+        diesel::update(dependency_req_backfills::table.find(backfill_id))
+            .set((
+                dependency_req_backfills::last_dependency_id.eq(last_id),
+                dependency_req_backfills::checked
+                    .eq(dependency_req_backfills::checked + rows.len() as i64),
+                dependency_req_backfills::normalized
+                    .eq(dependency_req_backfills::normalized + normalized),
+                dependency_req_backfills::irregular
+                    .eq(dependency_req_backfills::irregular + irregular),
+            ))
+            .execute(conn)?;
+
+        Ok(true)
+    })
+}
+
+fn log_report(conn: &mut PgConnection, backfill_id: i64) -> QueryResult<()> {
+    let (dry_run, checked, normalized, irregular) = dependency_req_backfills::table
+        .find(backfill_id)
+        .select((
+            dependency_req_backfills::dry_run,
+            dependency_req_backfills::checked,
+            dependency_req_backfills::normalized,
+            dependency_req_backfills::irregular,
+        ))
+        .first::<(bool, i64, i64, i64)>(conn)?;
+
+    info!(
+        dry_run,
+        checked,
+        normalized,
+        irregular,
+        "Finished normalizing the dependency requirements, see the `dependency_req_backfill_rows` table for the changed and irregular requirements"
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditLog;
+    use crate::email::Emails;
+    use crate::models::{NewCrate, NewUser, NewVersion};
+    use crate::test_util::test_db_connection;
+    use std::collections::BTreeMap;
+
+    fn insert_dependencies(conn: &mut PgConnection, reqs: &[&str]) {
+        let user = NewUser::new(1, "foo", None, None, "access_token")
+            .create_or_update(None, &Emails::new_in_memory(), conn)
+            .unwrap();
+
+        let krate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create(conn, user.id, &AuditLog::default())
+        .unwrap();
+
+        let version = NewVersion::new(
+            krate.id,
+            &semver::Version::parse("1.0.0").unwrap(),
+            &BTreeMap::new(),
+            None,
+            0,
+            user.id,
+            "0000000000000000000000000000000000000000000000000000000000000000".to_string(),
+            None,
+            None,
+        )
+        .unwrap()
+        .save(conn, "someone@example.com")
+        .unwrap();
+
+        for req in reqs {
+            diesel::insert_into(dependencies::table)
+                .values((
+                    dependencies::version_id.eq(version.id),
+                    dependencies::crate_id.eq(krate.id),
+                    dependencies::req.eq(*req),
+                    dependencies::optional.eq(false),
+                    dependencies::default_features.eq(true),
+                    dependencies::features.eq(Vec::<String>::new()),
+                    dependencies::kind.eq(0),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+    }
+
+    fn reqs(conn: &mut PgConnection) -> Vec<String> {
+        dependencies::table
+            .select(dependencies::req)
+            .order(dependencies::id)
+            .load(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_normalize_req() {
+        assert_ok_eq!(normalize_req("^1.0"), "^1.0");
+        assert_ok_eq!(normalize_req("1.0"), "^1.0");
+        assert_ok_eq!(normalize_req(">= 0"), ">=0");
+        assert_ok_eq!(normalize_req(">= 1.2, < 2"), ">=1.2, <2");
+        assert_ok_eq!(normalize_req("*"), "*");
+        assert_err!(normalize_req("1.0.0-*"));
+        assert_err!(normalize_req("foo"));
+    }
+
+    #[test]
+    fn backfill_normalizes_requirements() {
+        let (_test_db, conn) = &mut test_db_connection();
+        insert_dependencies(conn, &["^1.0", ">= 0", "foo", "1.2"]);
+
+        let job = NormalizeDependencyReqs::start(conn, true).unwrap();
+        while process_batch(conn, job.backfill_id).unwrap() {}
+        assert_eq!(reqs(conn), vec!["^1.0", ">= 0", "foo", "1.2"]);
+
+        let job = NormalizeDependencyReqs::start(conn, false).unwrap();
+        while process_batch(conn, job.backfill_id).unwrap() {}
+        assert_eq!(reqs(conn), vec!["^1.0", ">=0", "foo", "^1.2"]);
+
+        let counts = dependency_req_backfills::table
+            .find(job.backfill_id)
+            .select((
+                dependency_req_backfills::checked,
+                dependency_req_backfills::normalized,
+                dependency_req_backfills::irregular,
+            ))
+            .first::<(i64, i64, i64)>(conn)
+            .unwrap();
+        assert_eq!(counts, (4, 2, 1));
+
+        let irregular: Vec<String> = dependency_req_backfill_rows::table
+            .filter(dependency_req_backfill_rows::backfill_id.eq(job.backfill_id))
+            .filter(dependency_req_backfill_rows::normalized_req.is_null())
+            .select(dependency_req_backfill_rows::original_req)
+            .load(conn)
+            .unwrap();
+        assert_eq!(irregular, vec!["foo"]);
+
+        // A finished backfill is not processed again
+        assert!(!process_batch(conn, job.backfill_id).unwrap());
+    }
+}
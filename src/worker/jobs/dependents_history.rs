@@ -0,0 +1,38 @@
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use std::sync::Arc;
+
+/// Records the current number of dependent crates of every crate in the
+/// `crate_dependents_history` table.
+///
+/// Like for the reverse dependencies endpoint, only the latest non-yanked
+/// version of each crate is taken into account. The job is supposed to run
+/// once per week. Running it multiple times on the same day replaces the
+/// snapshot of that day.
+#[derive(Serialize, Deserialize)]
+pub struct SnapshotDependentsCounts;
+
+impl BackgroundJob for SnapshotDependentsCounts {
+    const JOB_NAME: &'static str = "snapshot_dependents_counts";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        let count = conn
+            .interact(snapshot_dependents_counts)
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        info!("Recorded the dependents counts of {count} crates");
+
+        Ok(())
+    }
+}
+
+fn snapshot_dependents_counts(conn: &mut PgConnection) -> QueryResult<usize> {
+    diesel::sql_query(include_str!("dependents_history.sql")).execute(conn)
+}
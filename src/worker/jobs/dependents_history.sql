@@ -0,0 +1,22 @@
+INSERT INTO crate_dependents_history (crate_id, date, dependents)
+SELECT
+    dependencies.crate_id,
+    CURRENT_DATE,
+    COUNT(DISTINCT versions.crate_id)
+FROM (
+    -- Only the *max* version of each crate is taken into account
+    SELECT DISTINCT ON (crate_id)
+        crate_id, id
+    FROM versions
+    WHERE NOT yanked
+    ORDER BY
+        crate_id,
+        semver_no_prerelease DESC NULLS LAST,
+        id DESC
+) versions
+INNER JOIN dependencies
+    ON dependencies.version_id = versions.id
+WHERE dependencies.crate_id != versions.crate_id
+GROUP BY dependencies.crate_id
+ON CONFLICT (crate_id, date) DO UPDATE
+    SET dependents = EXCLUDED.dependents
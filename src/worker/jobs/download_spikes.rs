@@ -0,0 +1,310 @@
+//! Detection of unusual crate downloads
+//!
+//! The [`DetectDownloadSpikes`] job compares the downloads of every crate on
+//! the previous day with the median of its daily downloads in the
+//! [`BASELINE_DAYS`] before. Days on which a crate was downloaded many times
+//! more often than usual are recorded in the `download_spikes` table, and the
+//! crates.io team, and optionally the owners of the crate, are notified.
+//!
+//! Sudden spikes can mean that build systems resolve the name of an internal
+//! package to the crate on crates.io (a dependency confusion attack), or that
+//! a mirror is misconfigured and bypasses its cache.
+
+use crate::config::DownloadSpikeConfig;
+use crate::email::Email;
+use crate::models::OwnerKind;
+use crate::schema::{crate_owners, crates, download_spikes, emails, version_downloads, versions};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use chrono::{NaiveDate, NaiveTime, TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::sum;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The downloads of a day are compared to the median of the daily downloads
+/// in this many days before. Crates that are younger than that are skipped,
+/// since there is nothing to compare their downloads to.
+const BASELINE_DAYS: i64 = 28;
+
+/// Analyzes the downloads of the previous day. The job is supposed to be run
+/// once per day, after the downloads of the previous day were counted.
+#[derive(Serialize, Deserialize)]
+pub struct DetectDownloadSpikes;
+
+impl BackgroundJob for DetectDownloadSpikes {
+    const JOB_NAME: &'static str = "detect_download_spikes";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let day = Utc::now().date_naive() - TimeDelta::days(1);
+
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| detect_download_spikes(conn, &env, day))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
+
+fn detect_download_spikes(
+    conn: &mut PgConnection,
+    env: &Environment,
+    day: NaiveDate,
+) -> anyhow::Result<()> {
+    let config = &env.config.download_spikes;
+    let baseline_start = day - TimeDelta::days(BASELINE_DAYS);
+
+    let candidates = crate_downloads_on(conn, day, baseline_start)?
+        .into_iter()
+        .filter(|(_, downloads)| *downloads >= config.min_downloads)
+        .collect::<Vec<_>>();
+
+    info!(%day, crates = candidates.len(), "Analyzing crate downloads…");
+
+    let crate_ids = candidates.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    let mut history = daily_downloads(conn, &crate_ids, baseline_start, day)?;
+
+    for (crate_id, downloads) in candidates {
+        let daily = history.remove(&crate_id).unwrap_or_default();
+        let baseline = median(daily, BASELINE_DAYS as usize);
+        if !is_spike(downloads, baseline, config) {
+            continue;
+        }
+
+        // Spikes that were already recorded by a previous run for the same
+        // day are not reported again.
+        let inserted = diesel::insert_into(download_spikes::table)
+            .values((
+                download_spikes::crate_id.eq(crate_id),
+                download_spikes::date.eq(day),
+                download_spikes::downloads.eq(downloads),
+                download_spikes::baseline.eq(baseline),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        if inserted > 0 {
+            let spike = Spike {
+                crate_id,
+                day,
+                downloads,
+                baseline,
+            };
+
+            notify(conn, env, &spike)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the downloads of all versions of every crate on the given day,
+/// except for crates that were created after `created_before`.
+fn crate_downloads_on(
+    conn: &mut PgConnection,
+    day: NaiveDate,
+    created_before: NaiveDate,
+) -> QueryResult<Vec<(i32, i64)>> {
+    let rows: Vec<(i32, Option<i64>)> = version_downloads::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .filter(version_downloads::date.eq(day))
+        .filter(crates::created_at.lt(created_before.and_time(NaiveTime::MIN)))
+        .group_by(versions::crate_id)
+        .select((versions::crate_id, sum(version_downloads::downloads)))
+        .load(conn)?;
+
+    let downloads = rows
+        .into_iter()
+        .map(|(crate_id, downloads)| (crate_id, downloads.unwrap_or_default()))
+        .collect();
+
+    Ok(downloads)
+}
+
+/// Returns the daily downloads of the crates from `start` up to, but not
+/// including, `end`. Days without downloads are left out.
+fn daily_downloads(
+    conn: &mut PgConnection,
+    crate_ids: &[i32],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> QueryResult<HashMap<i32, Vec<i64>>> {
+    // `version_downloads` has a single row per version and day, so the
+    // downloads of the versions are summed up per crate and day here instead
+    // of grouping by columns of both tables in the query.
+    let rows: Vec<(i32, NaiveDate, i32)> = version_downloads::table
+        .inner_join(versions::table)
+        .filter(versions::crate_id.eq_any(crate_ids))
+        .filter(version_downloads::date.ge(start))
+        .filter(version_downloads::date.lt(end))
+        .select((
+            versions::crate_id,
+            version_downloads::date,
+            version_downloads::downloads,
+        ))
+        .load(conn)?;
+
+    let mut daily = HashMap::<(i32, NaiveDate), i64>::new();
+    for (crate_id, date, downloads) in rows {
+        *daily.entry((crate_id, date)).or_default() += i64::from(downloads);
+    }
+
+    let mut history = HashMap::<i32, Vec<i64>>::new();
+    for ((crate_id, _), downloads) in daily {
+        history.entry(crate_id).or_default().push(downloads);
+    }
+
+    Ok(history)
+}
+
+/// Returns the median of the daily downloads of `days` days, of which the
+/// days that are missing from `daily` had no downloads.
+fn median(mut daily: Vec<i64>, days: usize) -> i64 {
+    daily.resize(days.max(daily.len()), 0);
+    daily.sort_unstable();
+
+    let middle = daily.len() / 2;
+    match daily.len() {
+        0 => 0,
+        len if len % 2 == 0 => (daily[middle - 1] + daily[middle]) / 2,
+        _ => daily[middle],
+    }
+}
+
+/// Returns `true` if the downloads of a day are unusually high compared to
+/// the `baseline`.
+///
+/// Crates that are usually not downloaded at all are compared to a baseline
+/// of a single download per day.
+fn is_spike(downloads: i64, baseline: i64, config: &DownloadSpikeConfig) -> bool {
+    downloads >= config.min_downloads && downloads as f64 >= config.factor * baseline.max(1) as f64
+}
+
+#[derive(Debug)]
+struct Spike {
+    crate_id: i32,
+    day: NaiveDate,
+    downloads: i64,
+    baseline: i64,
+}
+
+/// Notifies the crates.io team and, if enabled, the owners of the crate about
+/// the spike.
+fn notify(conn: &mut PgConnection, env: &Environment, spike: &Spike) -> anyhow::Result<()> {
+    let config = &env.config.download_spikes;
+
+    let crate_name: String = crates::table
+        .find(spike.crate_id)
+        .select(crates::name)
+        .first(conn)?;
+
+    warn!(
+        crate_name = %crate_name,
+        day = %spike.day,
+        downloads = spike.downloads,
+        baseline = spike.baseline,
+        "Download spike detected"
+    );
+
+    let mut recipients = config.notification_emails.clone();
+    if config.notify_owners {
+        let owner_emails: Vec<String> = crate_owners::table
+            .inner_join(emails::table.on(emails::user_id.eq(crate_owners::owner_id)))
+            .filter(crate_owners::crate_id.eq(spike.crate_id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::email_notifications.eq(true))
+            .filter(emails::is_primary)
+            .filter(emails::verified.eq(true))
+            .select(emails::email)
+            .load(conn)?;
+
+        recipients.extend(owner_emails);
+    }
+
+    let email = DownloadSpikeEmail {
+        domain: &env.config.domain_name,
+        crate_name: &crate_name,
+        spike,
+    };
+
+    for recipient in &recipients {
+        if let Err(error) = env.emails.send(recipient, email.clone()) {
+            warn!(
+                ?error,
+                ?recipient,
+                "Failed to send download spike notification"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct DownloadSpikeEmail<'a> {
+    domain: &'a str,
+    crate_name: &'a str,
+    spike: &'a Spike,
+}
+
+impl Email for DownloadSpikeEmail<'_> {
+    const SUBJECT: &'static str = "crates.io: Unusual number of crate downloads";
+
+    fn body(&self) -> String {
+        let DownloadSpikeEmail {
+            domain,
+            crate_name,
+            spike,
+        } = self;
+
+        format!(
+            "Hello!
+
+The crate {crate_name} was downloaded {downloads} times on {day}, while it was downloaded {baseline} times on a usual day of the {BASELINE_DAYS} days before:
+
+https://{domain}/crates/{crate_name}
+
+Such spikes are usually harmless, but they can also be caused by misconfigured mirrors, or by build systems that download the crate instead of an internal package with the same name. If you cannot explain the downloads, please contact us at help@crates.io.",
+            downloads = spike.downloads,
+            day = spike.day,
+            baseline = spike.baseline,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_daily_downloads() {
+        assert_eq!(median(vec![], 0), 0);
+        assert_eq!(median(vec![], 28), 0);
+        assert_eq!(median(vec![5, 1, 3], 3), 3);
+        assert_eq!(median(vec![5, 1, 3, 4], 4), 3);
+
+        // Days without downloads are counted as zeros
+        assert_eq!(median(vec![10, 10, 10], 5), 10);
+        assert_eq!(median(vec![10, 10], 5), 0);
+    }
+
+    #[test]
+    fn spikes() {
+        let config = DownloadSpikeConfig {
+            factor: 10.,
+            min_downloads: 1000,
+            ..Default::default()
+        };
+
+        assert!(is_spike(10_000, 1000, &config));
+        assert!(!is_spike(9_999, 1000, &config));
+
+        // Rarely downloaded crates need at least `min_downloads`
+        assert!(is_spike(1000, 0, &config));
+        assert!(!is_spike(999, 0, &config));
+    }
+}
@@ -2,7 +2,7 @@ use crate::config::CdnLogStorageConfig;
 use crate::worker::Environment;
 use anyhow::{anyhow, Context};
 use chrono::NaiveDate;
-use crates_io_cdn_logs::{count_downloads, Decompressor, DownloadsMap};
+use crates_io_cdn_logs::{count_downloads, ClientVersionsMap, Decompressor, DownloadsMap};
 use crates_io_worker::BackgroundJob;
 use deadpool_diesel::postgres::Pool;
 use diesel::dsl::exists;
@@ -21,6 +21,9 @@ use tokio::io::BufReader;
 /// A background job that loads a CDN log file from an object store (aka. S3),
 /// counts the number of downloads for each crate and version, and then inserts
 /// the results into the database.
+///
+/// The downloads are also counted per crate and cargo version, if the log
+/// file contains the `User-Agent` header of the requests.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProcessCdnLog {
     pub region: String,
@@ -105,7 +108,7 @@ async fn run(store: Arc<dyn ObjectStore>, path: &str, db_pool: Pool) -> anyhow::
     let parsed_path =
         Path::parse(path).with_context(|| format!("Failed to parse path: {path:?}"))?;
 
-    let downloads = load_and_count(&parsed_path, store).await?;
+    let mut downloads = load_and_count(&parsed_path, store).await?;
     if downloads.is_empty() {
         info!("No downloads found in log file");
         return Ok(());
@@ -113,6 +116,8 @@ async fn run(store: Arc<dyn ObjectStore>, path: &str, db_pool: Pool) -> anyhow::
 
     log_stats(&downloads);
 
+    let client_versions = downloads.take_client_versions();
+
     let path = path.to_string();
     let conn = db_pool.get().await?;
     conn.interact(|conn| {
@@ -129,7 +134,8 @@ async fn run(store: Arc<dyn ObjectStore>, path: &str, db_pool: Pool) -> anyhow::
             // file again.
             save_as_processed(path, conn)?;
 
-            save_downloads(downloads, conn)
+            save_downloads(downloads, conn)?;
+            save_client_downloads(client_versions, conn)
         })?;
 
         Ok::<_, anyhow::Error>(())
@@ -164,6 +170,9 @@ fn log_stats(downloads: &DownloadsMap) {
 
     let total_inserts = downloads.len();
     info!("Number of needed inserts: {total_inserts}");
+
+    let client_version_inserts = downloads.client_versions().len();
+    info!("Number of needed client version inserts: {client_version_inserts}");
 }
 
 table! {
@@ -341,6 +350,143 @@ impl Debug for NameAndVersion {
     }
 }
 
+table! {
+    /// Diesel table definition for the temporary `temp_client_downloads`
+    /// table that is created by the [`create_temp_client_downloads_table`]
+    /// function.
+    ///
+    /// The primary key does not actually exist, but specifying one is
+    /// required by Diesel.
+    temp_client_downloads (name, date, client_version) {
+        name -> Text,
+        date -> Date,
+        client_version -> Text,
+        downloads -> BigInt,
+    }
+}
+
+/// Helper struct for inserting downloads into the `temp_client_downloads`
+/// table.
+#[derive(Insertable)]
+#[diesel(table_name = temp_client_downloads)]
+struct NewClientDownload {
+    name: String,
+    date: NaiveDate,
+    client_version: String,
+    downloads: i64,
+}
+
+impl From<(String, NaiveDate, String, u64)> for NewClientDownload {
+    fn from((name, date, client_version, downloads): (String, NaiveDate, String, u64)) -> Self {
+        Self {
+            name,
+            date,
+            client_version,
+            downloads: downloads as i64,
+        }
+    }
+}
+
+/// Saves the downloads from the given [`ClientVersionsMap`] to the database
+/// into the `crate_client_downloads` table.
+///
+/// Like [`save_downloads()`], this function **should be run inside a
+/// transaction** to ensure that the temporary table is dropped afterwards.
+pub fn save_client_downloads(
+    client_versions: ClientVersionsMap,
+    conn: &mut PgConnection,
+) -> anyhow::Result<()> {
+    if client_versions.is_empty() {
+        return Ok(());
+    }
+
+    debug!("Creating temp_client_downloads table");
+    create_temp_client_downloads_table(conn)
+        .context("Failed to create temp_client_downloads table")?;
+
+    debug!("Saving counted client downloads to temp_client_downloads table");
+    fill_temp_client_downloads_table(client_versions, conn)
+        .context("Failed to fill temp_client_downloads table")?;
+
+    debug!("Saving temp_client_downloads to crate_client_downloads table");
+    save_to_crate_client_downloads(conn)
+        .context("Failed to save temp_client_downloads to crate_client_downloads table")?;
+
+    Ok(())
+}
+
+/// Creates the temporary `temp_client_downloads` table that is used to store
+/// the counted client downloads before they are inserted into the
+/// `crate_client_downloads` table.
+#[instrument("db.query", skip_all, fields(message = "CREATE TEMPORARY TABLE ..."))]
+fn create_temp_client_downloads_table(conn: &mut PgConnection) -> QueryResult<usize> {
+    diesel::sql_query(
+        r#"
+            CREATE TEMPORARY TABLE temp_client_downloads (
+                name VARCHAR NOT NULL,
+                date DATE NOT NULL,
+                client_version VARCHAR NOT NULL,
+                downloads INTEGER NOT NULL
+            ) ON COMMIT DROP;
+        "#,
+    )
+    .execute(conn)
+}
+
+/// Fills the temporary `temp_client_downloads` table with the downloads from
+/// the given [`ClientVersionsMap`].
+#[instrument(
+    "db.query",
+    skip_all,
+    fields(message = "INSERT INTO temp_client_downloads ...")
+)]
+fn fill_temp_client_downloads_table(
+    client_versions: ClientVersionsMap,
+    conn: &mut PgConnection,
+) -> QueryResult<()> {
+    // See `fill_temp_downloads_table()` for the reasoning behind this limit.
+    const MAX_BATCH_SIZE: usize = 10_000;
+
+    let map = client_versions
+        .into_vec()
+        .into_iter()
+        .map(NewClientDownload::from)
+        .collect::<Vec<_>>();
+
+    for chunk in map.chunks(MAX_BATCH_SIZE) {
+        diesel::insert_into(temp_client_downloads::table)
+            .values(chunk)
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Saves the downloads from the temporary `temp_client_downloads` table to
+/// the `crate_client_downloads` table.
+///
+/// Downloads of unknown crates are silently skipped, since they are already
+/// reported by [`save_to_version_downloads()`].
+#[instrument(
+    "db.query",
+    skip_all,
+    fields(message = "INSERT INTO crate_client_downloads ...")
+)]
+fn save_to_crate_client_downloads(conn: &mut PgConnection) -> QueryResult<usize> {
+    diesel::sql_query(
+        r#"
+            INSERT INTO crate_client_downloads (crate_id, date, client_version, downloads)
+            SELECT crates.id, temp_client_downloads.date, temp_client_downloads.client_version, temp_client_downloads.downloads
+            FROM temp_client_downloads
+            INNER JOIN crates ON crates.name = temp_client_downloads.name
+            ORDER BY crates.id, temp_client_downloads.date, temp_client_downloads.client_version
+            ON CONFLICT (crate_id, date, client_version)
+            DO UPDATE SET downloads = crate_client_downloads.downloads + EXCLUDED.downloads
+        "#,
+    )
+    .execute(conn)
+}
+
 /// Checks if the given log file has already been processed.
 ///
 /// Acquires a connection from the pool before passing it to the
@@ -385,7 +531,7 @@ fn save_as_processed(path: impl Into<String>, conn: &mut PgConnection) -> QueryR
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::schema::{crates, version_downloads, versions};
+    use crate::schema::{crate_client_downloads, crates, version_downloads, versions};
     use crates_io_test_db::TestDatabase;
     use deadpool_diesel::postgres::Manager;
     use deadpool_diesel::Runtime;
@@ -416,6 +562,14 @@ mod tests {
             "tracing-core | 0.1.32 | 1 | 0 | 2024-01-16 | false",
         ]
         "###);
+        assert_debug_snapshot!(all_client_downloads(db_pool.clone()).await, @r###"
+        [
+            "bindgen | 2024-01-16 | 1.74 | 1",
+            "quick-error | 2024-01-16 | 1.74 | 2",
+            "quick-error | 2024-01-17 | 1.74 | 1",
+            "tracing-core | 2024-01-16 | 1.74 | 1",
+        ]
+        "###);
 
         // Check that processing the same log file again does not insert
         // duplicate data.
@@ -519,6 +673,35 @@ mod tests {
             .collect()
     }
 
+    /// Queries all client downloads from the database and returns them as a
+    /// [`Vec`] of strings for use with [`assert_debug_snapshot!()`].
+    async fn all_client_downloads(db_pool: Pool) -> Vec<String> {
+        let conn = db_pool.get().await.unwrap();
+        let downloads: Vec<(String, NaiveDate, String, i32)> = conn
+            .interact(|conn| {
+                crate_client_downloads::table
+                    .inner_join(crates::table)
+                    .select((
+                        crates::name,
+                        crate_client_downloads::date,
+                        crate_client_downloads::client_version,
+                        crate_client_downloads::downloads,
+                    ))
+                    .order((crates::name, crate_client_downloads::date))
+                    .load(conn)
+                    .unwrap()
+            })
+            .await
+            .unwrap();
+
+        downloads
+            .into_iter()
+            .map(|(name, date, client_version, downloads)| {
+                format!("{name} | {date} | {client_version} | {downloads}")
+            })
+            .collect()
+    }
+
     /// Queries all version downloads from the database and returns them as a
     /// [`Vec`] of tuples.
     fn query_all_version_downloads(
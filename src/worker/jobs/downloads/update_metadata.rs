@@ -1,7 +1,7 @@
 use crate::schema::version_downloads;
 use crate::worker::Environment;
 use anyhow::anyhow;
-use crates_io_worker::BackgroundJob;
+use crates_io_worker::{BackgroundJob, ShutdownSignal};
 use diesel::prelude::*;
 use diesel::sql_types::BigInt;
 use std::sync::Arc;
@@ -16,8 +16,10 @@ impl BackgroundJob for UpdateDownloads {
     type Context = Arc<Environment>;
 
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let shutdown = ShutdownSignal::current();
+
         let conn = env.deadpool.get().await?;
-        conn.interact(update)
+        conn.interact(move |conn| update(conn, &shutdown))
             .await
             .map_err(|err| anyhow!(err.to_string()))??;
 
@@ -25,7 +27,7 @@ impl BackgroundJob for UpdateDownloads {
     }
 }
 
-fn update(conn: &mut PgConnection) -> QueryResult<()> {
+fn update(conn: &mut PgConnection, shutdown: &ShutdownSignal) -> QueryResult<()> {
     use diesel::dsl::now;
     use diesel::select;
 
@@ -53,6 +55,13 @@ fn update(conn: &mut PgConnection) -> QueryResult<()> {
             info!("Time limit reached, stopping batch update");
             break;
         }
+
+        // Each batch is committed on its own, so the remaining downloads
+        // will be counted by the next run of this job.
+        if shutdown.is_requested() {
+            info!("Shutdown requested, stopping batch update");
+            return Ok(());
+        }
     }
 
     info!("Finished updating versions");
@@ -168,6 +177,7 @@ fn batch_update(batch_size: i64, conn: &mut PgConnection) -> QueryResult<i64> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audit::AuditLog;
     use crate::email::Emails;
     use crate::models::{Crate, NewCrate, NewUser, NewVersion, User, Version};
     use crate::schema::{crate_downloads, crates, versions};
@@ -185,7 +195,7 @@ mod tests {
             name: "foo",
             ..Default::default()
         }
-        .create(conn, user_id)
+        .create(conn, user_id, &AuditLog::default())
         .unwrap();
         let version = NewVersion::new(
             krate.id,
@@ -223,7 +233,7 @@ mod tests {
             .execute(conn)
             .unwrap();
 
-        super::update(conn).unwrap();
+        super::update(conn, &ShutdownSignal::current()).unwrap();
 
         let version_downloads = versions::table
             .find(version.id)
@@ -237,7 +247,7 @@ mod tests {
             .first(conn);
         assert_eq!(crate_downloads, Ok(1));
 
-        super::update(conn).unwrap();
+        super::update(conn, &ShutdownSignal::current()).unwrap();
 
         let version_downloads = versions::table
             .find(version.id)
@@ -263,7 +273,7 @@ mod tests {
             ))
             .execute(conn)
             .unwrap();
-        super::update(conn).unwrap();
+        super::update(conn, &ShutdownSignal::current()).unwrap();
         let processed = version_downloads::table
             .filter(version_downloads::version_id.eq(version.id))
             .select(version_downloads::processed)
@@ -287,7 +297,7 @@ mod tests {
             ))
             .execute(conn)
             .unwrap();
-        super::update(conn).unwrap();
+        super::update(conn, &ShutdownSignal::current()).unwrap();
         let processed = version_downloads::table
             .filter(version_downloads::version_id.eq(version.id))
             .select(version_downloads::processed)
@@ -335,7 +345,7 @@ mod tests {
             .first(conn)
             .unwrap();
 
-        super::update(conn).unwrap();
+        super::update(conn, &ShutdownSignal::current()).unwrap();
 
         let version2: Version = versions::table.find(version.id).first(conn).unwrap();
         assert_eq!(version2.downloads, 2);
@@ -354,7 +364,7 @@ mod tests {
             .unwrap();
         assert_eq!(krate2_downloads, 2);
 
-        super::update(conn).unwrap();
+        super::update(conn, &ShutdownSignal::current()).unwrap();
 
         let version3: Version = versions::table.find(version.id).first(conn).unwrap();
         assert_eq!(version3.downloads, 2);
@@ -394,7 +404,7 @@ mod tests {
             .execute(conn)
             .unwrap();
 
-        super::update(conn).unwrap();
+        super::update(conn, &ShutdownSignal::current()).unwrap();
         let versions_changed = versions::table
             .select(versions::updated_at.ne(now - 2.days()))
             .get_result(conn);
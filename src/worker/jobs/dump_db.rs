@@ -1,6 +1,8 @@
 use self::configuration::VisibilityConfig;
 use crate::storage::Storage;
 use crate::tasks::spawn_blocking;
+use crate::util::retry::RetryPolicy;
+use crate::worker::jobs::queue_cloudfront_invalidations;
 use crate::worker::Environment;
 use anyhow::{anyhow, Context};
 use crates_io_worker::BackgroundJob;
@@ -37,23 +39,37 @@ impl BackgroundJob for DumpDb {
             let directory = DumpDirectory::create()?;
 
             info!(path = ?directory.export_dir, "Begin exporting database");
+            Self::report_progress(0, "Exporting database");
             directory.populate(&database_url)?;
 
             info!(path = ?directory.export_dir, "Creating tarball");
+            Self::report_progress(60, "Creating tarball");
             DumpTarball::create(&directory.export_dir)
         })
         .await?;
 
         info!("Uploading tarball");
-        Storage::from_environment()
-            .upload_db_dump(&self.target_name, &tarball.tarball_path)
+        Self::report_progress(80, "Uploading tarball");
+        let storage = Storage::from_environment();
+        RetryPolicy::default()
+            .run("db_dump_upload", || {
+                storage.upload_db_dump(&self.target_name, &tarball.tarball_path)
+            })
             .await?;
         info!("Database dump tarball uploaded");
 
         info!("Invalidating CDN caches");
-        if let Some(cloudfront) = env.cloudfront() {
-            if let Err(error) = cloudfront.invalidate(&self.target_name).await {
-                warn!("failed to invalidate CloudFront cache: {}", error);
+        Self::report_progress(95, "Invalidating CDN caches");
+        if env.cloudfront().is_some() {
+            let paths = vec![self.target_name.clone()];
+            let conn = env.deadpool.get().await?;
+            let result = conn
+                .interact(move |conn| queue_cloudfront_invalidations(conn, &paths))
+                .await
+                .map_err(|err| anyhow!(err.to_string()))?;
+
+            if let Err(error) = result {
+                warn!("failed to queue CloudFront invalidation: {}", error);
             }
         }
 
@@ -0,0 +1,31 @@
+use crate::models::CrateNameReservation;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use std::sync::Arc;
+
+/// Deletes all crate name reservations that have expired.
+///
+/// Expired reservations do not block publishing anymore, so this job only
+/// makes sure that they don't accumulate in the database.
+#[derive(Serialize, Deserialize)]
+pub struct ExpireCrateNameReservations;
+
+impl BackgroundJob for ExpireCrateNameReservations {
+    const JOB_NAME: &'static str = "expire_crate_name_reservations";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        let count = conn
+            .interact(CrateNameReservation::delete_expired)
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        info!("Deleted {count} expired crate name reservations");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use crate::email::Email;
+use crate::models::OwnerKind;
+use crate::schema::{crate_owners, crates, emails, follows, users, versions};
+use crate::util::unsubscribe;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use chrono::{TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::dsl::{exists, not};
+use diesel::prelude::*;
+use std::sync::Arc;
+
+/// The digest includes all versions that were published in this many days
+/// before the job runs. The job is supposed to be scheduled once a week.
+const DIGEST_PERIOD_DAYS: i64 = 7;
+
+/// Sends a summary email of the versions that were recently published for the
+/// crates they follow to every user that has not opted out of the digest.
+///
+/// Crates that the user owns are skipped, since owners already receive
+/// notifications about new versions of their crates. Users without new
+/// versions of their followed crates don't receive an email at all.
+#[derive(Serialize, Deserialize)]
+pub struct SendFollowDigest;
+
+impl BackgroundJob for SendFollowDigest {
+    const JOB_NAME: &'static str = "send_follow_digest";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| send_digests(&env, conn))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
+
+/// A recently published version of a followed crate, together with the user
+/// following the crate.
+#[derive(Debug, Queryable)]
+struct FollowedVersion {
+    user_id: i32,
+    email: String,
+    crate_name: String,
+    num: String,
+}
+
+fn send_digests(env: &Environment, conn: &mut PgConnection) -> anyhow::Result<()> {
+    let since = Utc::now().naive_utc() - TimeDelta::try_days(DIGEST_PERIOD_DAYS).unwrap();
+
+    let is_owner = exists(
+        crate_owners::table
+            .filter(crate_owners::crate_id.eq(follows::crate_id))
+            .filter(crate_owners::owner_id.eq(follows::user_id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+            .filter(crate_owners::deleted.eq(false)),
+    );
+
+    let followed_versions: Vec<FollowedVersion> = follows::table
+        .inner_join(users::table)
+        .inner_join(crates::table)
+        .inner_join(emails::table.on(emails::user_id.eq(follows::user_id)))
+        .inner_join(versions::table.on(versions::crate_id.eq(follows::crate_id)))
+        .filter(users::follow_digest.eq(true))
+        .filter(emails::is_primary)
+        .filter(emails::verified.eq(true))
+        .filter(versions::created_at.gt(since))
+        .filter(versions::yanked.eq(false))
+        .filter(not(is_owner))
+        .select((follows::user_id, emails::email, crates::name, versions::num))
+        .order((follows::user_id, crates::name, versions::created_at))
+        .load(conn)?;
+
+    info!(
+        count = followed_versions.len(),
+        "Sending follow digest emails"
+    );
+
+    for versions in followed_versions.chunk_by(|a, b| a.user_id == b.user_id) {
+        let user_id = versions[0].user_id;
+        let recipient = &versions[0].email;
+
+        let email = FollowDigestEmail {
+            domain: &env.emails.domain,
+            versions,
+            unsubscribe_token: unsubscribe::generate_token(
+                &env.config.session_key,
+                unsubscribe::FOLLOW_DIGEST,
+                user_id,
+            ),
+        };
+
+        if let Err(error) = env.emails.send(recipient, email) {
+            error!(?error, ?recipient, "Failed to send follow digest email");
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct FollowDigestEmail<'a> {
+    domain: &'a str,
+    versions: &'a [FollowedVersion],
+    unsubscribe_token: String,
+}
+
+impl Email for FollowDigestEmail<'_> {
+    const SUBJECT: &'static str = "New versions of the crates you follow";
+
+    fn body(&self) -> String {
+        let domain = self.domain;
+        let token = &self.unsubscribe_token;
+
+        let versions = self
+            .versions
+            .iter()
+            .map(|version| {
+                let crate_name = &version.crate_name;
+                let num = &version.num;
+                format!("- {crate_name} {num}: https://{domain}/crates/{crate_name}/{num}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        format!(
+            "The following new versions of crates you follow have been published in the last week:
+
+{versions}
+
+You are receiving this email because you follow these crates on {domain}. To stop receiving this weekly digest, visit:
+
+https://{domain}/unsubscribe/follow_digest/{token}"
+        )
+    }
+}
@@ -1,5 +1,8 @@
+use crate::index_signing::sign_index_file;
 use crate::models;
 use crate::tasks::spawn_blocking;
+use crate::util::retry::RetryPolicy;
+use crate::worker::jobs::queue_cloudfront_invalidations;
 use crate::worker::Environment;
 use anyhow::{anyhow, Context};
 use chrono::Utc;
@@ -26,6 +29,15 @@ impl SyncToGitIndex {
     }
 }
 
+/// The syncs of a crate to one of the indexes, e.g. after a publish or a
+/// yank, are run one after the other, so that an older state of the crate
+/// can't overwrite a newer one, and retries don't interleave with other
+/// syncs. The git and sparse indexes are separate files, so their syncs use
+/// different keys and don't wait for each other.
+fn index_lock_key(index: &str, crate_name: &str) -> String {
+    format!("{index}:{crate_name}")
+}
+
 impl BackgroundJob for SyncToGitIndex {
     const JOB_NAME: &'static str = "sync_to_git_index";
     const PRIORITY: i16 = 100;
@@ -33,6 +45,10 @@ impl BackgroundJob for SyncToGitIndex {
 
     type Context = Arc<Environment>;
 
+    fn lock_key(&self) -> Option<String> {
+        Some(index_lock_key("git-index", &self.krate))
+    }
+
     /// Regenerates or removes an index file for a single crate
     #[instrument(skip_all, fields(krate.name = ? self.krate))]
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
@@ -43,42 +59,58 @@ impl BackgroundJob for SyncToGitIndex {
         conn.interact(move |conn| {
             let new = get_index_data(&crate_name, conn).context("Failed to get index data")?;
 
-            let repo = env.lock_index()?;
-            let dst = repo.index_file(&crate_name);
-
-            // Read the previous crate contents
-            let old = match fs::read_to_string(&dst) {
-                Ok(content) => Some(content),
-                Err(error) if error.kind() == ErrorKind::NotFound => None,
-                Err(error) => return Err(error.into()),
-            };
-
-            match (old, new) {
-                (None, Some(new)) => {
-                    fs::create_dir_all(dst.parent().unwrap())?;
-                    let mut file = File::create(&dst)?;
-                    file.write_all(new.as_bytes())?;
-                    repo.commit_and_push(&format!("Create crate `{}`", &crate_name), &dst)?;
-                }
-                (Some(old), Some(new)) if old != new => {
-                    let mut file = File::create(&dst)?;
-                    file.write_all(new.as_bytes())?;
-                    repo.commit_and_push(&format!("Update crate `{}`", &crate_name), &dst)?;
-                }
-                (Some(_old), None) => {
-                    fs::remove_file(&dst)?;
-                    repo.commit_and_push(&format!("Delete crate `{}`", &crate_name), &dst)?;
-                }
-                _ => debug!("Skipping sync because index is up-to-date"),
-            }
-
-            Ok(())
+            // Locking the index resets it to the state of the remote, so the
+            // sync can be repeated after any error, e.g. when a push fails
+            // because another commit was pushed in the meantime.
+            RetryPolicy::default()
+                .retry_all_errors()
+                .run_blocking("git_index_sync", || {
+                    sync_git_index_file(&env, &crate_name, new.as_deref())
+                })
         })
         .await
         .map_err(|err| anyhow!(err.to_string()))?
     }
 }
 
+/// Creates, updates or removes the index file of the crate in the git index.
+fn sync_git_index_file(
+    env: &Environment,
+    crate_name: &str,
+    new: Option<&str>,
+) -> anyhow::Result<()> {
+    let repo = env.lock_index()?;
+    let dst = repo.index_file(crate_name);
+
+    // Read the previous crate contents
+    let old = match fs::read_to_string(&dst) {
+        Ok(content) => Some(content),
+        Err(error) if error.kind() == ErrorKind::NotFound => None,
+        Err(error) => return Err(error.into()),
+    };
+
+    match (old, new) {
+        (None, Some(new)) => {
+            fs::create_dir_all(dst.parent().unwrap())?;
+            let mut file = File::create(&dst)?;
+            file.write_all(new.as_bytes())?;
+            repo.commit_and_push(&format!("Create crate `{}`", &crate_name), &dst)?;
+        }
+        (Some(old), Some(new)) if old != new => {
+            let mut file = File::create(&dst)?;
+            file.write_all(new.as_bytes())?;
+            repo.commit_and_push(&format!("Update crate `{}`", &crate_name), &dst)?;
+        }
+        (Some(_old), None) => {
+            fs::remove_file(&dst)?;
+            repo.commit_and_push(&format!("Delete crate `{}`", &crate_name), &dst)?;
+        }
+        _ => debug!("Skipping sync because index is up-to-date"),
+    }
+
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SyncToSparseIndex {
     krate: String,
@@ -97,6 +129,10 @@ impl BackgroundJob for SyncToSparseIndex {
 
     type Context = Arc<Environment>;
 
+    fn lock_key(&self) -> Option<String> {
+        Some(index_lock_key("sparse-index", &self.krate))
+    }
+
     /// Regenerates or removes an index file for a single crate
     #[instrument(skip_all, fields(krate.name = ?self.krate))]
     async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
@@ -110,15 +146,50 @@ impl BackgroundJob for SyncToSparseIndex {
             .map_err(|err| anyhow!(err.to_string()))?
             .context("Failed to get index data")?;
 
-        let future = env.storage.sync_index(&self.krate, content);
-        future.await.context("Failed to sync index data")?;
+        let signing_keys = &env.config.index_signing_keys;
+        let signatures = match &content {
+            Some(content) if !signing_keys.is_empty() => {
+                sign_index_file(signing_keys, content, Utc::now())
+                    .context("Failed to sign index data")?
+            }
+            _ => None,
+        };
+
+        let retry_policy = RetryPolicy::default();
+        retry_policy
+            .run("sparse_index_sync", || async {
+                let future = env.storage.sync_index(&self.krate, content.clone());
+                Ok(future.await?)
+            })
+            .await
+            .context("Failed to sync index data")?;
+
+        if !signing_keys.is_empty() {
+            retry_policy
+                .run("sparse_index_signatures_sync", || async {
+                    let future = env
+                        .storage
+                        .sync_index_signatures(&self.krate, signatures.clone());
+                    Ok(future.await?)
+                })
+                .await
+                .context("Failed to sync index signatures")?;
+        }
 
-        if let Some(cloudfront) = env.cloudfront() {
+        if env.cloudfront().is_some() {
             let path = Repository::relative_index_file_for_url(&self.krate);
 
-            info!(%path, "Invalidating index file on CloudFront");
-            let future = cloudfront.invalidate(&path);
-            future.await.context("Failed to invalidate CloudFront")?;
+            let mut paths = vec![path.clone()];
+            if !signing_keys.is_empty() {
+                paths.push(format!("{path}.sig"));
+            }
+
+            info!(?paths, "Queueing CloudFront invalidation of index files");
+            let conn = env.deadpool.get().await?;
+            conn.interact(move |conn| queue_cloudfront_invalidations(conn, &paths))
+                .await
+                .map_err(|err| anyhow!(err.to_string()))?
+                .context("Failed to queue CloudFront invalidations")?;
         }
         Ok(())
     }
@@ -0,0 +1,218 @@
+use crate::email::Email;
+use crate::models::{CrateOwnerInvitation, InvitationCounts};
+use crate::schema::crate_owner_invitations;
+use crate::worker::jobs::data_retention::{run_batched, BATCH_SIZE};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Timestamp};
+use std::sync::Arc;
+
+/// Crate owner invitations are deleted this many days after they expired.
+///
+/// Expired invitations are kept for a while, so that users trying to accept
+/// them get a proper "invitation expired" error instead of a generic
+/// "not found" error.
+const EXPIRED_INVITATIONS_RETENTION_DAYS: i64 = 30;
+
+/// The report includes the invitations that were created in this many days
+/// before the job runs. The job is supposed to be scheduled once a week.
+const REPORT_PERIOD_DAYS: i64 = 7;
+
+/// Deletes crate owner invitations that expired more than
+/// [`EXPIRED_INVITATIONS_RETENTION_DAYS`] ago, and emails a summary of the
+/// invitations to the addresses in `INVITATION_REPORT_EMAILS`.
+///
+/// The current number of pending and expired invitations is also exposed
+/// by the `cratesio_service_crate_owner_invitations` metric.
+#[derive(Serialize, Deserialize)]
+pub struct CleanupExpiredInvitations;
+
+impl BackgroundJob for CleanupExpiredInvitations {
+    const JOB_NAME: &'static str = "cleanup_expired_invitations";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let expiration_days = env.config.ownership_invitations_expiration_days as i64;
+        let cut_off = deletion_cut_off(expiration_days);
+        info!(%cut_off, "Deleting expired crate owner invitations…");
+        let deleted = run_batched(&env, move |conn| {
+            delete_invitations_batch(conn, cut_off, BATCH_SIZE)
+        })
+        .await?;
+        info!("Deleted {deleted} crate owner invitations");
+
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| send_report(&env, conn, deleted))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
+
+fn deletion_cut_off(expiration_days: i64) -> NaiveDateTime {
+    let days = expiration_days + EXPIRED_INVITATIONS_RETENTION_DAYS;
+    (Utc::now() - TimeDelta::try_days(days).unwrap()).naive_utc()
+}
+
+/// Deletes up to `batch_size` crate owner invitations that were created
+/// before `cut_off`.
+fn delete_invitations_batch(
+    conn: &mut PgConnection,
+    cut_off: NaiveDateTime,
+    batch_size: i64,
+) -> QueryResult<usize> {
+    diesel::sql_query(
+        r#"
+            DELETE FROM crate_owner_invitations
+            WHERE ctid = ANY(ARRAY(
+                SELECT ctid
+                FROM crate_owner_invitations
+                WHERE created_at < $1
+                LIMIT $2
+            ))
+        "#,
+    )
+    .bind::<Timestamp, _>(cut_off)
+    .bind::<BigInt, _>(batch_size)
+    .execute(conn)
+}
+
+fn send_report(env: &Environment, conn: &mut PgConnection, deleted: usize) -> anyhow::Result<()> {
+    let recipients = &env.config.invitation_report_emails;
+    if recipients.is_empty() {
+        info!("No INVITATION_REPORT_EMAILS configured, skipping the invitation report");
+        return Ok(());
+    }
+
+    let now = Utc::now().naive_utc();
+    let since = now - TimeDelta::try_days(REPORT_PERIOD_DAYS).unwrap();
+    let created = crate_owner_invitations::table
+        .filter(crate_owner_invitations::created_at.gt(since))
+        .count()
+        .get_result(conn)?;
+
+    let email = InvitationReportEmail {
+        created,
+        deleted,
+        counts: CrateOwnerInvitation::counts(conn, &env.config, now)?,
+    };
+
+    for recipient in recipients {
+        if let Err(error) = env.emails.send(recipient, email) {
+            error!(?error, ?recipient, "Failed to send invitation report email");
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct InvitationReportEmail {
+    created: i64,
+    deleted: usize,
+    counts: InvitationCounts,
+}
+
+impl Email for InvitationReportEmail {
+    const SUBJECT: &'static str = "Weekly crate owner invitation report";
+
+    fn body(&self) -> String {
+        let InvitationReportEmail {
+            created,
+            deleted,
+            counts: InvitationCounts { pending, expired },
+        } = *self;
+
+        format!(
+            "Summary of the crate owner invitations:
+
+- {created} invitations were created in the last week
+- {deleted} expired invitations were deleted
+- {pending} invitations are pending
+- {expired} invitations have expired and are deleted {EXPIRED_INVITATIONS_RETENTION_DAYS} days after their expiry"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditLog;
+    use crate::email::Emails;
+    use crate::models::{NewCrate, NewUser, User};
+    use crate::test_util::test_db_connection;
+
+    fn user(conn: &mut PgConnection, gh_id: i32, login: &str) -> User {
+        NewUser::new(gh_id, login, None, None, "access_token")
+            .create_or_update(None, &Emails::new_in_memory(), conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn prune_expired_invitations() {
+        let (_test_db, conn) = &mut test_db_connection();
+        let owner = user(conn, 1, "owner");
+        let invitee = user(conn, 2, "invitee");
+        let other_invitee = user(conn, 3, "other-invitee");
+
+        let krate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create(conn, owner.id, &AuditLog::default())
+        .unwrap();
+
+        let cut_off = deletion_cut_off(30);
+        let hour = TimeDelta::try_hours(1).unwrap();
+
+        let invitations = [
+            (invitee.id, cut_off - hour),
+            (other_invitee.id, cut_off + hour),
+        ];
+        for (invited_user_id, created_at) in invitations {
+            diesel::insert_into(crate_owner_invitations::table)
+                .values((
+                    crate_owner_invitations::invited_user_id.eq(invited_user_id),
+                    crate_owner_invitations::invited_by_user_id.eq(owner.id),
+                    crate_owner_invitations::crate_id.eq(krate.id),
+                    crate_owner_invitations::created_at.eq(created_at),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+
+        assert_eq!(delete_invitations_batch(conn, cut_off, 10), Ok(1));
+
+        let remaining = crate_owner_invitations::table
+            .select(crate_owner_invitations::invited_user_id)
+            .load::<i32>(conn)
+            .unwrap();
+
+        assert_eq!(remaining, vec![other_invitee.id]);
+    }
+
+    #[test]
+    fn report_body() {
+        let email = InvitationReportEmail {
+            created: 3,
+            deleted: 2,
+            counts: InvitationCounts {
+                pending: 5,
+                expired: 1,
+            },
+        };
+
+        insta::assert_snapshot!(email.body(), @r###"
+        Summary of the crate owner invitations:
+
+        - 3 invitations were created in the last week
+        - 2 expired invitations were deleted
+        - 5 invitations are pending
+        - 1 invitations have expired and are deleted 30 days after their expiry
+        "###);
+    }
+}
@@ -0,0 +1,509 @@
+use crate::audit::{AuditEvent, AuditLog};
+use crate::email::Email;
+use crate::models::{NewCrateOwnerAction, OwnerAction, OwnerActionVia, OwnerKind, User};
+use crate::schema::{api_tokens, crate_owners, crates, emails, follows, user_merges, users};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+/// Merges the user account `source_user_id` into `target_user_id`.
+///
+/// This is used when users end up with multiple accounts, e.g. because they
+/// signed in with a new GitHub account. The crate ownerships, followed crates
+/// and (if the target account has none) the email address of the source
+/// account are transferred to the target account. The API tokens of the
+/// source account are revoked and the account is locked.
+///
+/// Each merge is recorded in the `user_merges` table, and both accounts are
+/// notified by email.
+#[derive(Serialize, Deserialize)]
+pub struct MergeUsers {
+    source_user_id: i32,
+    target_user_id: i32,
+}
+
+impl MergeUsers {
+    pub fn new(source_user_id: i32, target_user_id: i32) -> Self {
+        Self {
+            source_user_id,
+            target_user_id,
+        }
+    }
+}
+
+impl BackgroundJob for MergeUsers {
+    const JOB_NAME: &'static str = "merge_users";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(source = self.source_user_id, target = self.target_user_id))]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let source_user_id = self.source_user_id;
+        let target_user_id = self.target_user_id;
+
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| {
+            let source = User::find(conn, source_user_id)?;
+            let target = User::find(conn, target_user_id)?;
+
+            // The email address might be transferred to the target user, so
+            // it has to be looked up before the merge.
+            let source_email = source.verified_email(conn)?;
+
+            info!("Merging user {} into {}…", source.gh_login, target.gh_login);
+            let report = conn.transaction(|conn| merge(conn, &env.audit_log, &source, &target))?;
+            info!(
+                crates = report.crates.len(),
+                follows = report.follows,
+                tokens = report.tokens,
+                "Merged user {} into {}",
+                source.gh_login,
+                target.gh_login
+            );
+
+            let email = MergeNotificationEmail {
+                source: &source.gh_login,
+                target: &target.gh_login,
+                report: &report,
+            };
+
+            let target_email = target.verified_email(conn)?;
+            let mut recipients = vec![target_email, source_email];
+            recipients.dedup();
+
+            for recipient in recipients.into_iter().flatten() {
+                if let Err(error) = env.emails.send(&recipient, email) {
+                    warn!(?error, "Failed to send user merge notification");
+                }
+            }
+
+            Ok(())
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
+
+/// What merging one user account into another would change.
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    /// Names of the crates that are owned by the source user
+    pub crates: Vec<String>,
+    /// Number of crates that are followed by the source user, but not by the
+    /// target user
+    pub follows: i64,
+    /// Number of active API tokens of the source user
+    pub tokens: i64,
+    /// The email address of the source user, if it will be transferred
+    /// because the target user has no email address
+    pub email: Option<String>,
+}
+
+impl MergeReport {
+    pub fn new(conn: &mut PgConnection, source: &User, target: &User) -> QueryResult<Self> {
+        let crates = crates::table
+            .inner_join(crate_owners::table)
+            .filter(crate_owners::owner_id.eq(source.id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+            .filter(crate_owners::deleted.eq(false))
+            .select(crates::name)
+            .order(crates::name)
+            .load(conn)?;
+
+        let target_follows: Vec<i32> = follows::table
+            .filter(follows::user_id.eq(target.id))
+            .select(follows::crate_id)
+            .load(conn)?;
+
+        let follows = follows::table
+            .filter(follows::user_id.eq(source.id))
+            .filter(follows::crate_id.ne_all(target_follows))
+            .count()
+            .get_result(conn)?;
+
+        let tokens = api_tokens::table
+            .filter(api_tokens::user_id.eq(source.id))
+            .filter(api_tokens::revoked.eq(false))
+            .count()
+            .get_result(conn)?;
+
+        let email = match target.email(conn)? {
+            Some(_) => None,
+            None => source.email(conn)?,
+        };
+
+        Ok(Self {
+            crates,
+            follows,
+            tokens,
+            email,
+        })
+    }
+}
+
+impl Display for MergeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let crates = self.crates.len();
+        writeln!(f, "- {crates} crate ownerships are transferred")?;
+        for name in &self.crates {
+            writeln!(f, "  - {name}")?;
+        }
+
+        writeln!(f, "- {} followed crates are transferred", self.follows)?;
+        writeln!(f, "- {} API tokens are revoked", self.tokens)?;
+
+        match &self.email {
+            Some(email) => writeln!(f, "- the email address {email} is transferred"),
+            None => writeln!(f, "- no email address is transferred"),
+        }
+    }
+}
+
+/// Merges `source` into `target`. This should be run in a transaction.
+fn merge(
+    conn: &mut PgConnection,
+    audit_log: &AuditLog,
+    source: &User,
+    target: &User,
+) -> QueryResult<MergeReport> {
+    let report = MergeReport::new(conn, source, target)?;
+
+    // Record the ownership changes in the audit log, before the ownerships
+    // themselves are changed.
+    let source_crates = owned_crate_ids(conn, source)?;
+    let target_crates = owned_crate_ids(conn, target)?;
+
+    let added = source_crates
+        .iter()
+        .filter(|crate_id| !target_crates.contains(crate_id))
+        .map(|crate_id| (*crate_id, target.id, OwnerAction::Add));
+    let removed = source_crates
+        .iter()
+        .map(|crate_id| (*crate_id, source.id, OwnerAction::Remove));
+
+    for (crate_id, owner_id, action) in added.chain(removed) {
+        let action = NewCrateOwnerAction {
+            crate_id,
+            owner_id,
+            owner_kind: OwnerKind::User,
+            actor_id: None,
+            action,
+            via: OwnerActionVia::Admin,
+        };
+        audit_log.record(conn, AuditEvent::OwnerAction(action))?;
+    }
+
+    // Crate ownerships. Ownerships of crates that are already owned by the
+    // target user are kept as they are, except that they are restored if
+    // they were removed before.
+    diesel::sql_query(
+        r#"
+            INSERT INTO crate_owners (crate_id, owner_id, owner_kind, created_by, email_notifications)
+            SELECT crate_id, $2, owner_kind, created_by, email_notifications
+            FROM crate_owners
+            WHERE owner_id = $1 AND owner_kind = $3 AND NOT deleted
+            ON CONFLICT (crate_id, owner_id, owner_kind) DO UPDATE SET deleted = false
+        "#,
+    )
+    .bind::<Integer, _>(source.id)
+    .bind::<Integer, _>(target.id)
+    .bind::<Integer, _>(OwnerKind::User as i32)
+    .execute(conn)?;
+
+    diesel::update(crate_owners::table)
+        .filter(crate_owners::owner_id.eq(source.id))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .set(crate_owners::deleted.eq(true))
+        .execute(conn)?;
+
+    // Followed crates
+    diesel::sql_query(
+        r#"
+            INSERT INTO follows (user_id, crate_id)
+            SELECT $2, crate_id
+            FROM follows
+            WHERE user_id = $1
+            ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind::<Integer, _>(source.id)
+    .bind::<Integer, _>(target.id)
+    .execute(conn)?;
+
+    diesel::delete(follows::table.filter(follows::user_id.eq(source.id))).execute(conn)?;
+
+    // API tokens
+    diesel::update(api_tokens::table)
+        .filter(api_tokens::user_id.eq(source.id))
+        .filter(api_tokens::revoked.eq(false))
+        .set(api_tokens::revoked.eq(true))
+        .execute(conn)?;
+
+    // Email address
+    if report.email.is_some() {
+        diesel::update(emails::table)
+            .filter(emails::user_id.eq(source.id))
+            .set(emails::user_id.eq(target.id))
+            .execute(conn)?;
+    }
+
+    let reason = format!("This account was merged into `{}`", target.gh_login);
+    diesel::update(source)
+        .set((
+            users::account_lock_reason.eq(reason),
+            users::account_lock_until.eq(None::<chrono::NaiveDateTime>),
+        ))
+        .execute(conn)?;
+
+    diesel::insert_into(user_merges::table)
+        .values((
+            user_merges::source_user_id.eq(source.id),
+            user_merges::target_user_id.eq(target.id),
+            user_merges::crates_transferred.eq(report.crates.len() as i32),
+            user_merges::follows_transferred.eq(report.follows as i32),
+            user_merges::tokens_revoked.eq(report.tokens as i32),
+            user_merges::email_transferred.eq(report.email.is_some()),
+        ))
+        .execute(conn)?;
+
+    Ok(report)
+}
+
+/// Returns the IDs of the crates that the user currently owns.
+fn owned_crate_ids(conn: &mut PgConnection, user: &User) -> QueryResult<Vec<i32>> {
+    crate_owners::table
+        .filter(crate_owners::owner_id.eq(user.id))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .filter(crate_owners::deleted.eq(false))
+        .select(crate_owners::crate_id)
+        .order(crate_owners::crate_id)
+        .load(conn)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MergeNotificationEmail<'a> {
+    source: &'a str,
+    target: &'a str,
+    report: &'a MergeReport,
+}
+
+impl Email for MergeNotificationEmail<'_> {
+    const SUBJECT: &'static str = "crates.io: Your accounts have been merged";
+
+    fn body(&self) -> String {
+        let MergeNotificationEmail {
+            source,
+            target,
+            report,
+        } = self;
+
+        format!(
+            "Hello,
+
+the crates.io account `{source}` has been merged into the account `{target}` by the crates.io team:
+
+{report}
+The `{source}` account has been locked, and you can continue using crates.io with the `{target}` account.
+
+If you did not request this change, please reply to this email or contact us at help@crates.io."
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditLog, OutboxSink};
+    use crate::email::Emails;
+    use crate::models::{ApiToken, CrateOwner, NewCrate, NewUser};
+    use crate::schema::audit_export_outbox;
+    use crate::test_util::test_db_connection;
+    use serde_json::Value;
+
+    fn user(conn: &mut PgConnection, gh_id: i32, login: &str, email: Option<&str>) -> User {
+        NewUser::new(gh_id, login, None, None, "access_token")
+            .create_or_update(email, &Emails::new_in_memory(), conn)
+            .unwrap()
+    }
+
+    fn follow(conn: &mut PgConnection, user: &User, crate_id: i32) {
+        diesel::insert_into(follows::table)
+            .values((follows::user_id.eq(user.id), follows::crate_id.eq(crate_id)))
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn owned_crates(conn: &mut PgConnection, user: &User) -> Vec<String> {
+        crates::table
+            .inner_join(crate_owners::table)
+            .filter(crate_owners::owner_id.eq(user.id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+            .filter(crate_owners::deleted.eq(false))
+            .select(crates::name)
+            .order(crates::name)
+            .load(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn merge_users() {
+        let (_test_db, conn) = &mut test_db_connection();
+        let old = user(conn, 1, "old", Some("old@example.com"));
+        let new = user(conn, 2, "new", None);
+
+        let foo = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create(conn, old.id, &AuditLog::default())
+        .unwrap();
+
+        let bar = NewCrate {
+            name: "bar",
+            ..Default::default()
+        }
+        .create(conn, new.id, &AuditLog::default())
+        .unwrap();
+
+        diesel::insert_into(crate_owners::table)
+            .values(CrateOwner {
+                crate_id: bar.id,
+                owner_id: old.id,
+                created_by: new.id,
+                owner_kind: OwnerKind::User,
+                email_notifications: true,
+            })
+            .execute(conn)
+            .unwrap();
+
+        follow(conn, &old, foo.id);
+        follow(conn, &old, bar.id);
+        follow(conn, &new, bar.id);
+
+        ApiToken::insert(conn, old.id, "token").unwrap();
+
+        let report = MergeReport::new(conn, &old, &new).unwrap();
+        insta::assert_snapshot!(report, @r###"
+        - 2 crate ownerships are transferred
+          - bar
+          - foo
+        - 1 followed crates are transferred
+        - 1 API tokens are revoked
+        - the email address old@example.com is transferred
+        "###);
+
+        let audit_log = AuditLog::default().with_sink(Arc::new(OutboxSink));
+        conn.transaction(|conn| merge(conn, &audit_log, &old, &new))
+            .unwrap();
+
+        assert_eq!(owned_crates(conn, &old), Vec::<String>::new());
+        assert_eq!(owned_crates(conn, &new), vec!["bar", "foo"]);
+
+        // The ownership changes are exported like those of the server
+        let events: Vec<Value> = audit_export_outbox::table
+            .select(audit_export_outbox::event)
+            .order(audit_export_outbox::id)
+            .load(conn)
+            .unwrap();
+        let actions = events
+            .iter()
+            .map(|event| (event["crate_id"].clone(), event["owner_id"].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![
+                (foo.id.into(), new.id.into()),
+                (foo.id.into(), old.id.into()),
+                (bar.id.into(), old.id.into()),
+            ]
+        );
+
+        let followed: Vec<(i32, i32)> = follows::table
+            .select((follows::user_id, follows::crate_id))
+            .order(follows::crate_id)
+            .load(conn)
+            .unwrap();
+        assert_eq!(followed, vec![(new.id, foo.id), (new.id, bar.id)]);
+
+        let revoked: Vec<bool> = api_tokens::table
+            .filter(api_tokens::user_id.eq(old.id))
+            .select(api_tokens::revoked)
+            .load(conn)
+            .unwrap();
+        assert_eq!(revoked, vec![true]);
+
+        assert_none!(old.email(conn).unwrap());
+        assert_some_eq!(new.email(conn).unwrap(), "old@example.com");
+
+        let old = User::find(conn, old.id).unwrap();
+        assert_some_eq!(
+            old.account_lock_reason,
+            "This account was merged into `new`"
+        );
+        assert_none!(old.account_lock_until);
+
+        let merges: Vec<(i32, i32, i32, i32, i32, bool)> = user_merges::table
+            .select((
+                user_merges::source_user_id,
+                user_merges::target_user_id,
+                user_merges::crates_transferred,
+                user_merges::follows_transferred,
+                user_merges::tokens_revoked,
+                user_merges::email_transferred,
+            ))
+            .load(conn)
+            .unwrap();
+        assert_eq!(merges, vec![(old.id, new.id, 2, 1, 1, true)]);
+    }
+
+    #[test]
+    fn email_is_kept_if_target_has_one() {
+        let (_test_db, conn) = &mut test_db_connection();
+        let old = user(conn, 1, "old", Some("old@example.com"));
+        let new = user(conn, 2, "new", Some("new@example.com"));
+
+        let report = conn
+            .transaction(|conn| merge(conn, &AuditLog::default(), &old, &new))
+            .unwrap();
+        assert_none!(report.email);
+
+        assert_some_eq!(old.email(conn).unwrap(), "old@example.com");
+        assert_some_eq!(new.email(conn).unwrap(), "new@example.com");
+    }
+
+    #[test]
+    fn notification_body() {
+        let report = MergeReport {
+            crates: vec!["foo".to_string()],
+            follows: 3,
+            tokens: 2,
+            email: None,
+        };
+
+        let email = MergeNotificationEmail {
+            source: "old",
+            target: "new",
+            report: &report,
+        };
+
+        insta::assert_snapshot!(email.body(), @r###"
+        Hello,
+
+        the crates.io account `old` has been merged into the account `new` by the crates.io team:
+
+        - 1 crate ownerships are transferred
+          - foo
+        - 3 followed crates are transferred
+        - 2 API tokens are revoked
+        - no email address is transferred
+
+        The `old` account has been locked, and you can continue using crates.io with the `new` account.
+
+        If you did not request this change, please reply to this email or contact us at help@crates.io.
+        "###);
+    }
+}
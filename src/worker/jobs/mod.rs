@@ -1,26 +1,68 @@
 use crates_io_worker::schema::background_jobs;
-use crates_io_worker::{BackgroundJob, EnqueueError};
+use crates_io_worker::{current_request_id, BackgroundJob, EnqueueError};
 use diesel::dsl::{exists, not};
 use diesel::prelude::*;
-use diesel::sql_types::{Int2, Jsonb, Text};
+use diesel::sql_types::{Int2, Jsonb, Nullable, Text};
 use std::fmt::Display;
 
+mod category_migrations;
+mod cdn_health;
+mod check_crate_files;
+mod cloudfront_invalidations;
 mod daily_db_maintenance;
+mod data_export;
+mod data_retention;
+mod dependency_reqs;
+mod dependents_history;
+mod download_spikes;
 mod downloads;
 pub mod dump_db;
+mod expire_crate_name_reservations;
+mod follow_digest;
 mod git;
+mod invitation_cleanup;
+mod merge_users;
+mod publish_notifications;
 mod readmes;
+mod sandbox;
+mod scheduled_releases;
+mod site_stats;
+mod storage_tags;
 mod sync_admins;
+mod token_anomalies;
+mod trending_stats;
 mod typosquat;
 
+pub use self::category_migrations::MigrateCategory;
+pub use self::cdn_health::ProbeCdnHealth;
+pub use self::check_crate_files::CheckCrateFiles;
+pub use self::cloudfront_invalidations::{
+    queue_cloudfront_invalidations, FlushCloudFrontInvalidations,
+};
 pub use self::daily_db_maintenance::DailyDbMaintenance;
+pub use self::data_export::{DeleteExpiredDataExports, ExportUserData};
+pub use self::data_retention::DataRetention;
+pub use self::dependency_reqs::NormalizeDependencyReqs;
+pub use self::dependents_history::SnapshotDependentsCounts;
+pub use self::download_spikes::DetectDownloadSpikes;
 pub use self::downloads::{
     CleanProcessedLogFiles, ProcessCdnLog, ProcessCdnLogQueue, UpdateDownloads,
 };
 pub use self::dump_db::DumpDb;
+pub use self::expire_crate_name_reservations::ExpireCrateNameReservations;
+pub use self::follow_digest::SendFollowDigest;
 pub use self::git::{NormalizeIndex, SquashIndex, SyncToGitIndex, SyncToSparseIndex};
+pub use self::invitation_cleanup::CleanupExpiredInvitations;
+pub use self::merge_users::{MergeReport, MergeUsers};
+pub use self::publish_notifications::SendPublishNotifications;
 pub use self::readmes::RenderAndUploadReadme;
+pub use self::sandbox::ResetSandbox;
+pub use self::scheduled_releases::ReleaseScheduledVersions;
+pub use self::site_stats::UpdateSiteStats;
+pub use self::storage_tags::UpdateStorageTags;
 pub use self::sync_admins::SyncAdmins;
+pub use self::token_anomalies::DetectTokenAnomalies;
+pub use self::trending_stats::UpdateTrendingStats;
 pub use self::typosquat::CheckTyposquat;
 
 /// Enqueue both index sync jobs (git and sparse) for a crate, unless they
@@ -48,14 +90,17 @@ pub fn enqueue_sync_to_index<T: Display>(
                 .skip_locked()
         };
 
-    // Returns one `job_type, data, priority` row with values from the
-    // passed-in `job`, unless a similar row already exists.
+    let request_id = current_request_id();
+
+    // Returns one `job_type, data, priority, request_id` row with values from
+    // the passed-in `job`, unless a similar row already exists.
     let deduplicated_select_query =
         |job_type: &'static str, data: serde_json::Value, priority: i16| {
             diesel::select((
                 job_type.into_sql::<Text>(),
                 data.clone().into_sql::<Jsonb>(),
                 priority.into_sql::<Int2>(),
+                request_id.clone().into_sql::<Nullable<Text>>(),
             ))
             .filter(not(exists(find_similar_jobs_query(
                 job_type, data, priority,
@@ -82,6 +127,7 @@ pub fn enqueue_sync_to_index<T: Display>(
             background_jobs::job_type,
             background_jobs::data,
             background_jobs::priority,
+            background_jobs::request_id,
         ))
         .execute(conn)?;
 
@@ -93,3 +139,19 @@ pub fn enqueue_sync_to_index<T: Display>(
 
     Ok(())
 }
+
+/// Returns `true` while one of the index sync jobs of a crate is queued or
+/// running, including jobs that failed and will be retried.
+pub fn index_sync_pending(krate: &str, conn: &mut PgConnection) -> QueryResult<bool> {
+    // Both sync jobs only contain the crate name
+    let data = serde_json::json!({ "krate": krate });
+
+    let jobs = background_jobs::table
+        .filter(
+            background_jobs::job_type
+                .eq_any([SyncToGitIndex::JOB_NAME, SyncToSparseIndex::JOB_NAME]),
+        )
+        .filter(background_jobs::data.eq(data));
+
+    diesel::select(exists(jobs)).get_result(conn)
+}
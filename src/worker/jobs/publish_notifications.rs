@@ -0,0 +1,211 @@
+use crate::email::{Locale, LocalizedEmail};
+use crate::models::{OwnerKind, VersionAction, VersionOwnerAction};
+use crate::schema::{
+    api_tokens, crate_owners, crates, emails, users, version_owner_actions, versions,
+};
+use crate::worker::Environment;
+use crate::Emails;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use ipnetwork::IpNetwork;
+use std::sync::Arc;
+
+/// Notifies the other owners of a crate that a new version of the crate has
+/// been published.
+///
+/// The notification includes who published the version and from where, and
+/// highlights publishes from IP addresses or API tokens that have not been
+/// used to publish the crate before, to help owners detect compromised
+/// accounts or tokens quickly.
+#[derive(Serialize, Deserialize)]
+pub struct SendPublishNotifications {
+    version_id: i32,
+}
+
+impl SendPublishNotifications {
+    pub fn new(version_id: i32) -> Self {
+        Self { version_id }
+    }
+}
+
+impl BackgroundJob for SendPublishNotifications {
+    const JOB_NAME: &'static str = "send_publish_notifications";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all, fields(version_id = self.version_id))]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let version_id = self.version_id;
+
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| send_notifications(&env.emails, conn, version_id))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
+
+fn send_notifications(
+    emails: &Emails,
+    conn: &mut PgConnection,
+    version_id: i32,
+) -> anyhow::Result<()> {
+    let (version, crate_id, crate_name): (String, i32, String) = versions::table
+        .inner_join(crates::table)
+        .filter(versions::id.eq(version_id))
+        .select((versions::num, crates::id, crates::name))
+        .first(conn)?;
+
+    let publish: VersionOwnerAction = version_owner_actions::table
+        .filter(version_owner_actions::version_id.eq(version_id))
+        .filter(version_owner_actions::action.eq(VersionAction::Publish))
+        .first(conn)?;
+
+    let publisher: String = users::table
+        .find(publish.user_id)
+        .select(users::gh_login)
+        .first(conn)?;
+
+    let token_name: Option<String> = publish
+        .api_token_id
+        .map(|id| {
+            api_tokens::table
+                .find(id)
+                .select(api_tokens::name)
+                .first(conn)
+        })
+        .transpose()?;
+
+    let previous_publishes: Vec<(Option<IpNetwork>, Option<i32>)> = version_owner_actions::table
+        .inner_join(versions::table)
+        .filter(versions::crate_id.eq(crate_id))
+        .filter(version_owner_actions::action.eq(VersionAction::Publish))
+        .filter(version_owner_actions::id.ne(publish.id))
+        .select((
+            version_owner_actions::ip,
+            version_owner_actions::api_token_id,
+        ))
+        .load(conn)?;
+
+    let recipients: Vec<(String, String)> = crate_owners::table
+        .inner_join(emails::table.on(emails::user_id.eq(crate_owners::owner_id)))
+        .inner_join(users::table.on(users::id.eq(crate_owners::owner_id)))
+        .filter(crate_owners::crate_id.eq(crate_id))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User))
+        .filter(crate_owners::owner_id.ne(publish.user_id))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(crate_owners::email_notifications.eq(true))
+        .filter(emails::is_primary)
+        .filter(emails::verified.eq(true))
+        .select((emails::email, users::locale))
+        .load(conn)?;
+
+    if recipients.is_empty() {
+        return Ok(());
+    }
+
+    let email = PublishNotificationEmail {
+        domain: &emails.domain,
+        crate_name: &crate_name,
+        version: &version,
+        publisher: &publisher,
+        token_name: token_name.as_deref(),
+        ip: publish.ip,
+        new_ip: is_new_ip(publish.ip, &previous_publishes),
+        new_token: is_new_token(publish.api_token_id, &previous_publishes),
+    };
+
+    for (recipient, locale) in &recipients {
+        let locale = Locale::from_preference(locale);
+        if let Err(error) = emails.send_localized(recipient, locale, email.clone()) {
+            error!(?error, ?recipient, "Failed to send publish notification");
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the crate has been published from other IP addresses
+/// before, but never from `ip`.
+///
+/// Publishes from before IP addresses were recorded are ignored, so that the
+/// first publish after that change is not flagged for every crate.
+fn is_new_ip(ip: Option<IpNetwork>, previous: &[(Option<IpNetwork>, Option<i32>)]) -> bool {
+    let Some(ip) = ip else { return false };
+
+    let mut previous_ips = previous.iter().filter_map(|(ip, _)| *ip).peekable();
+    previous_ips.peek().is_some() && previous_ips.all(|previous_ip| previous_ip.ip() != ip.ip())
+}
+
+/// Returns `true` if `token_id` belongs to an API token that has not been
+/// used to publish the crate before.
+fn is_new_token(token_id: Option<i32>, previous: &[(Option<IpNetwork>, Option<i32>)]) -> bool {
+    let Some(token_id) = token_id else {
+        return false;
+    };
+
+    previous.iter().all(|(_, id)| *id != Some(token_id))
+}
+
+#[derive(Debug, Clone)]
+struct PublishNotificationEmail<'a> {
+    domain: &'a str,
+    crate_name: &'a str,
+    version: &'a str,
+    publisher: &'a str,
+    token_name: Option<&'a str>,
+    ip: Option<IpNetwork>,
+    new_ip: bool,
+    new_token: bool,
+}
+
+impl LocalizedEmail for PublishNotificationEmail<'_> {
+    const TEMPLATE: &'static str = "publish_notification";
+
+    fn context(&self) -> minijinja::Value {
+        minijinja::context! {
+            domain => self.domain,
+            crate_name => self.crate_name,
+            version => self.version,
+            publisher => self.publisher,
+            token_name => self.token_name,
+            ip => self.ip.map(|ip| ip.ip().to_string()),
+            new_ip => self.new_ip,
+            new_token => self.new_token,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> Option<IpNetwork> {
+        Some(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn new_ip() {
+        assert!(!is_new_ip(None, &[(ip("10.0.0.1"), None)]));
+        assert!(!is_new_ip(ip("10.0.0.1"), &[]));
+        assert!(!is_new_ip(ip("10.0.0.1"), &[(None, None)]));
+        assert!(!is_new_ip(ip("10.0.0.1"), &[(ip("10.0.0.1"), None)]));
+        assert!(!is_new_ip(
+            ip("10.0.0.1"),
+            &[(ip("10.0.0.2"), None), (ip("10.0.0.1"), None)]
+        ));
+        assert!(is_new_ip(ip("10.0.0.1"), &[(ip("10.0.0.2"), None)]));
+        assert!(is_new_ip(
+            ip("10.0.0.1"),
+            &[(None, None), (ip("10.0.0.2"), None)]
+        ));
+    }
+
+    #[test]
+    fn new_token() {
+        assert!(!is_new_token(None, &[(None, Some(1))]));
+        assert!(!is_new_token(Some(1), &[(None, Some(1))]));
+        assert!(is_new_token(Some(1), &[]));
+        assert!(is_new_token(Some(1), &[(None, None), (None, Some(2))]));
+    }
+}
@@ -2,9 +2,12 @@
 
 use crate::models::Version;
 use crate::tasks::spawn_blocking;
+use crate::util::image_proxy;
+use crate::util::retry::RetryPolicy;
 use crate::worker::Environment;
 use anyhow::anyhow;
-use crates_io_markdown::text_to_html;
+use axum::body::Bytes;
+use crates_io_markdown::{text_to_html, text_to_html_with_image_proxy};
 use crates_io_worker::BackgroundJob;
 use std::sync::Arc;
 use tokio::runtime::Handle;
@@ -50,12 +53,28 @@ impl BackgroundJob for RenderAndUploadReadme {
         info!(version_id = ?self.version_id, "Rendering README");
 
         let job = self.clone();
+        let config = env.config.clone();
         let rendered = spawn_blocking(move || {
-            Ok::<_, anyhow::Error>(text_to_html(
-                &job.text,
-                &job.readme_path,
-                job.base_url.as_deref(),
-                job.pkg_path_in_vcs.as_ref(),
+            let text = &job.text;
+            let readme_path = &job.readme_path;
+            let base_url = job.base_url.as_deref();
+            let pkg_path_in_vcs = job.pkg_path_in_vcs.as_ref();
+
+            if !config.readme_image_proxy {
+                let html = text_to_html(text, readme_path, base_url, pkg_path_in_vcs);
+                return Ok::<_, anyhow::Error>(html);
+            }
+
+            let image_proxy = move |url: &str| {
+                image_proxy::proxy_url(&config.session_key, &config.domain_name, url)
+            };
+
+            Ok(text_to_html_with_image_proxy(
+                text,
+                readme_path,
+                base_url,
+                pkg_path_in_vcs,
+                image_proxy,
             ))
         })
         .await?;
@@ -76,8 +95,12 @@ impl BackgroundJob for RenderAndUploadReadme {
 
                 tracing::Span::current().record("krate.name", tracing::field::display(&crate_name));
 
-                let bytes = rendered.into();
-                let future = env.storage.upload_readme(&crate_name, &vers, bytes);
+                let bytes = Bytes::from(rendered);
+                let retry_policy = RetryPolicy::default();
+                let future = retry_policy.run("readme_upload", || async {
+                    let future = env.storage.upload_readme(&crate_name, &vers, bytes.clone());
+                    Ok(future.await?)
+                });
                 Handle::current().block_on(future)?;
 
                 Ok(())
@@ -0,0 +1,281 @@
+//! Resetting of the staging "sandbox" registry
+//!
+//! The staging environment is used for testing cargo against a registry and
+//! for demo purposes, which requires a known set of users, API tokens and
+//! crates. The [`ResetSandbox`] job replaces the content of the registry with
+//! the fixtures in `sandbox/fixtures.toml`.
+
+use self::fixtures::{CrateFixture, Fixtures};
+use crate::audit::AuditLog;
+use crate::config;
+use crate::models::{CrateOwner, NewCrate, NewUser, NewVersion, OwnerKind};
+use crate::schema::{
+    api_tokens, categories, crate_owners, crates, emails, metadata, scheduled_releases, users,
+    versions,
+};
+use crate::util::token::HashedToken;
+use crate::worker::jobs::enqueue_sync_to_index;
+use crate::worker::Environment;
+use anyhow::{anyhow, Context};
+use axum::body::Bytes;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+
+mod fixtures;
+
+/// Wipes all crates, users and API tokens from the database and the storage,
+/// and seeds the registry with the sandbox fixtures afterwards.
+///
+/// The job refuses to run unless the `SANDBOX_RESET_ENABLED` environment
+/// variable is set, and it never runs for the `crates.io` domain.
+#[derive(Serialize, Deserialize)]
+pub struct ResetSandbox;
+
+impl BackgroundJob for ResetSandbox {
+    const JOB_NAME: &'static str = "reset_sandbox";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        if let Err(error) = check_reset_allowed(&env.config) {
+            // Returning an error would only cause the job to be retried, so
+            // the job is dropped instead.
+            error!("Skipping sandbox reset: {error}");
+            return Ok(());
+        }
+
+        let fixtures = Fixtures::load().context("Failed to load the sandbox fixtures")?;
+
+        info!("Resetting the sandbox registry");
+        let conn = env.deadpool.get().await?;
+        let Reset {
+            deleted_crates,
+            scheduled_uploads,
+            crate_files,
+        } = conn
+            .interact(move |conn| conn.transaction(|conn| reset_database(&fixtures, conn)))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        for name in &deleted_crates {
+            info!(%name, "Deleting crate files, READMEs and artifacts");
+            env.storage.delete_all_crate_files(name).await?;
+            env.storage.delete_all_readmes(name).await?;
+            env.storage.delete_all_artifacts(name).await?;
+        }
+
+        for upload_id in &scheduled_uploads {
+            info!(%upload_id, "Deleting crate file of scheduled release");
+            match env.storage.delete_scheduled_crate_file(upload_id).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => {}
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        for (name, version, bytes) in &crate_files {
+            info!(%name, %version, "Uploading crate file");
+            env.storage
+                .upload_crate_file(name, version, bytes.clone())
+                .await?;
+        }
+
+        let crate_names = deleted_crates
+            .into_iter()
+            .chain(crate_files.into_iter().map(|(name, _, _)| name))
+            .collect::<BTreeSet<_>>();
+
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| {
+            for name in crate_names {
+                enqueue_sync_to_index(name, conn)?;
+            }
+            Ok::<_, anyhow::Error>(())
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))??;
+
+        info!("Sandbox registry has been reset");
+
+        Ok(())
+    }
+}
+
+fn check_reset_allowed(config: &config::Server) -> anyhow::Result<()> {
+    if !config.sandbox_reset_enabled {
+        return Err(anyhow!("SANDBOX_RESET_ENABLED is not set"));
+    }
+
+    if config.domain_name == "crates.io" {
+        return Err(anyhow!("the production registry can not be reset"));
+    }
+
+    Ok(())
+}
+
+/// The name, version and content of a crate file that has to be uploaded.
+type CrateFile = (String, String, Bytes);
+
+/// The changes to the storage that follow from a reset of the database.
+struct Reset {
+    /// The names of the deleted crates, whose files have to be deleted.
+    deleted_crates: Vec<String>,
+    /// The upload IDs of the crate files of pending scheduled releases, which
+    /// are kept in a private location until the release.
+    scheduled_uploads: Vec<String>,
+    /// The crate files of the seeded crates.
+    crate_files: Vec<CrateFile>,
+}
+
+/// Replaces the content of the database with the fixtures.
+fn reset_database(fixtures: &Fixtures, conn: &mut PgConnection) -> anyhow::Result<Reset> {
+    let deleted_crates = crates::table.select(crates::name).load(conn)?;
+
+    let scheduled_uploads = scheduled_releases::table
+        .filter(scheduled_releases::resolution.is_null())
+        .select(scheduled_releases::upload_id)
+        .load(conn)?;
+
+    // Restarting the sequences makes sure that the seeded records get the
+    // same IDs after every reset.
+    diesel::sql_query("TRUNCATE crates, users, teams, keywords, sitemaps RESTART IDENTITY CASCADE")
+        .execute(conn)?;
+
+    // `TRUNCATE` does not run the triggers that update these counters
+    diesel::update(categories::table)
+        .set(categories::crates_cnt.eq(0))
+        .execute(conn)?;
+
+    diesel::update(metadata::table)
+        .set(metadata::total_downloads.eq(0))
+        .execute(conn)?;
+
+    let mut users = HashMap::new();
+    for fixture in &fixtures.users {
+        let new_user = NewUser::new(
+            fixture.gh_id,
+            &fixture.login,
+            fixture.name.as_deref(),
+            None,
+            "",
+        );
+
+        let user_id: i32 = diesel::insert_into(users::table)
+            .values(&new_user)
+            .returning(users::id)
+            .get_result(conn)?;
+
+        diesel::insert_into(emails::table)
+            .values((
+                emails::user_id.eq(user_id),
+                emails::email.eq(&fixture.email),
+                emails::verified.eq(true),
+            ))
+            .execute(conn)?;
+
+        for token in &fixture.tokens {
+            diesel::insert_into(api_tokens::table)
+                .values((
+                    api_tokens::user_id.eq(user_id),
+                    api_tokens::name.eq(&token.name),
+                    api_tokens::token.eq(HashedToken::hash(&token.token)),
+                ))
+                .execute(conn)?;
+        }
+
+        users.insert(fixture.login.as_str(), (user_id, fixture.email.as_str()));
+    }
+
+    let mut crate_files = Vec::new();
+    for fixture in &fixtures.crates {
+        crate_files.extend(seed_crate(fixture, &users, conn)?);
+    }
+
+    diesel::sql_query("REFRESH MATERIALIZED VIEW recent_crate_downloads").execute(conn)?;
+
+    Ok(Reset {
+        deleted_crates,
+        scheduled_uploads,
+        crate_files,
+    })
+}
+
+fn seed_crate(
+    fixture: &CrateFixture,
+    users: &HashMap<&str, (i32, &str)>,
+    conn: &mut PgConnection,
+) -> anyhow::Result<Vec<CrateFile>> {
+    let owners = fixture
+        .owners
+        .iter()
+        .map(|login| {
+            users
+                .get(login.as_str())
+                .copied()
+                .ok_or_else(|| anyhow!("unknown owner `{login}` of `{}`", fixture.name))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let (publisher_id, publisher_email) = *owners
+        .first()
+        .ok_or_else(|| anyhow!("`{}` has no owners", fixture.name))?;
+
+    let new_crate = NewCrate {
+        name: &fixture.name,
+        description: fixture.description.as_deref(),
+        ..Default::default()
+    };
+
+    let krate = new_crate.create(conn, publisher_id, &AuditLog::default())?;
+
+    for &(owner_id, _) in owners.iter().skip(1) {
+        let owner = CrateOwner {
+            crate_id: krate.id,
+            owner_id,
+            created_by: publisher_id,
+            owner_kind: OwnerKind::User,
+            email_notifications: true,
+        };
+
+        diesel::insert_into(crate_owners::table)
+            .values(&owner)
+            .execute(conn)?;
+    }
+
+    let mut crate_files = Vec::new();
+    for version in &fixture.versions {
+        let bytes = fixture.crate_file(version)?;
+        let checksum = hex::encode(Sha256::digest(&bytes));
+
+        let new_version = NewVersion::new(
+            krate.id,
+            version,
+            &BTreeMap::new(),
+            fixture.license.clone(),
+            bytes.len() as i32,
+            publisher_id,
+            checksum,
+            None,
+            None,
+        )
+        .map_err(|err| anyhow!(err.to_string()))?;
+
+        let version = new_version
+            .save(conn, publisher_email)
+            .map_err(|err| anyhow!(err.to_string()))?;
+
+        crate_files.push((fixture.name.clone(), version.num, Bytes::from(bytes)));
+    }
+
+    let yanked = fixture.yanked.iter().map(ToString::to_string);
+    diesel::update(versions::table)
+        .filter(versions::crate_id.eq(krate.id))
+        .filter(versions::num.eq_any(yanked))
+        .set(versions::yanked.eq(true))
+        .execute(conn)?;
+
+    Ok(crate_files)
+}
@@ -0,0 +1,117 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// The users and crates that the sandbox registry is seeded with.
+#[derive(Debug, Deserialize)]
+pub struct Fixtures {
+    pub users: Vec<UserFixture>,
+    pub crates: Vec<CrateFixture>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserFixture {
+    pub login: String,
+    pub gh_id: i32,
+    pub name: Option<String>,
+    pub email: String,
+    #[serde(default)]
+    pub tokens: Vec<TokenFixture>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenFixture {
+    pub name: String,
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CrateFixture {
+    pub name: String,
+    /// Logins of the owners of the crate. The first owner is used as the
+    /// publisher of all versions.
+    pub owners: Vec<String>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub versions: Vec<semver::Version>,
+    #[serde(default)]
+    pub yanked: Vec<semver::Version>,
+}
+
+impl Fixtures {
+    pub fn load() -> anyhow::Result<Self> {
+        Ok(toml::from_str(include_str!("fixtures.toml"))?)
+    }
+}
+
+impl CrateFixture {
+    /// Builds a minimal `.crate` file for a version of the crate.
+    ///
+    /// The archive only depends on its input, so that the checksums of the
+    /// crate files are the same after every reset.
+    pub fn crate_file(&self, version: &semver::Version) -> anyhow::Result<Vec<u8>> {
+        let name = &self.name;
+
+        let mut manifest =
+            format!("[package]\nname = \"{name}\"\nversion = \"{version}\"\nedition = \"2021\"\n");
+        if let Some(description) = &self.description {
+            manifest.push_str(&format!("description = {description:?}\n"));
+        }
+        if let Some(license) = &self.license {
+            manifest.push_str(&format!("license = {license:?}\n"));
+        }
+
+        let files = [("Cargo.toml", manifest), ("src/lib.rs", String::new())];
+
+        let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(0);
+            header.set_cksum();
+
+            let path = format!("{name}-{version}/{path}");
+            archive.append_data(&mut header, path, content.as_bytes())?;
+        }
+
+        Ok(archive.into_inner()?.finish()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crates_io_tarball::process_tarball;
+
+    #[test]
+    fn fixtures_are_valid() {
+        let fixtures = Fixtures::load().unwrap();
+
+        for krate in &fixtures.crates {
+            for owner in &krate.owners {
+                assert!(fixtures.users.iter().any(|user| &user.login == owner));
+            }
+
+            for version in &krate.yanked {
+                assert!(krate.versions.contains(version));
+            }
+        }
+    }
+
+    #[test]
+    fn crate_files_are_valid_and_deterministic() {
+        let fixtures = Fixtures::load().unwrap();
+
+        for krate in &fixtures.crates {
+            for version in &krate.versions {
+                let bytes = krate.crate_file(version).unwrap();
+                assert_eq!(bytes, krate.crate_file(version).unwrap());
+
+                let pkg_name = format!("{}-{version}", krate.name);
+                let info = process_tarball(&pkg_name, &*bytes, u64::MAX).unwrap();
+                let package = info.manifest.package.unwrap();
+                assert_eq!(package.name, krate.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::models::{ScheduledRelease, ScheduledReleaseResolution};
+use crate::worker::jobs::{self, RenderAndUploadReadme, SendPublishNotifications};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use std::sync::Arc;
+
+/// Releases the versions whose scheduled release time has passed.
+///
+/// The crate file of a version is moved to its public location first. Then
+/// the schedule is resolved, which makes the version visible in the API, and
+/// the index sync, the README rendering and the publish notifications are
+/// enqueued. Releases that fail are retried by the next run.
+///
+/// The job should be enqueued every minute, since the scheduled release times
+/// are only as precise as the interval between the runs.
+#[derive(Serialize, Deserialize)]
+pub struct ReleaseScheduledVersions;
+
+impl BackgroundJob for ReleaseScheduledVersions {
+    const JOB_NAME: &'static str = "release_scheduled_versions";
+
+    type Context = Arc<Environment>;
+
+    fn lock_key(&self) -> Option<String> {
+        Some(Self::JOB_NAME.into())
+    }
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        let releases = conn
+            .interact(ScheduledRelease::due)
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        let mut failed = 0;
+        for release in releases {
+            let crate_name = release.crate_name.clone();
+            let version = release.version_num.clone();
+            if let Err(error) = release_version(&env, release).await {
+                warn!(%crate_name, %version, ?error, "Failed to release scheduled version");
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            return Err(anyhow!("Failed to release {failed} scheduled versions"));
+        }
+
+        Ok(())
+    }
+}
+
+async fn release_version(env: &Environment, release: ScheduledRelease) -> anyhow::Result<()> {
+    let crate_name = release.crate_name.clone();
+    let version = release.version_num.clone();
+
+    env.storage
+        .release_scheduled_crate_file(&release.upload_id, &crate_name, &version)
+        .await?;
+
+    let conn = env.deadpool.get().await?;
+    let outcome = conn
+        .interact(move |conn| {
+            conn.transaction(|conn| {
+                let release = release.lock(conn)?;
+                if let Some(outcome) = release.outcome() {
+                    return Ok(outcome);
+                }
+
+                let Some(version_id) = release.version_id else {
+                    return Ok(ScheduledReleaseResolution::Cancelled);
+                };
+
+                release.resolve(conn, ScheduledReleaseResolution::Released, None)?;
+                jobs::enqueue_sync_to_index(&release.crate_name, conn)?;
+
+                if let Some(readme_job) = release.readme_job {
+                    serde_json::from_value::<RenderAndUploadReadme>(readme_job)?.enqueue(conn)?;
+                }
+
+                SendPublishNotifications::new(version_id).enqueue(conn)?;
+
+                Ok::<_, anyhow::Error>(ScheduledReleaseResolution::Released)
+            })
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))??;
+
+    // The release was cancelled while the crate file was moved
+    if outcome == ScheduledReleaseResolution::Cancelled {
+        env.storage.delete_crate_file(&crate_name, &version).await?;
+        return Ok(());
+    }
+
+    info!(%crate_name, %version, "Released scheduled version");
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use std::sync::Arc;
+
+/// Records the registry-wide totals and the publishes and new users of the
+/// previous day in the `site_stats` table, which is used by the
+/// `GET /api/v1/stats/site` endpoint.
+///
+/// The job is supposed to run once per day, after the downloads of the
+/// previous day were counted. Running it multiple times on the same day
+/// replaces the rollup of the previous day.
+#[derive(Serialize, Deserialize)]
+pub struct UpdateSiteStats;
+
+impl BackgroundJob for UpdateSiteStats {
+    const JOB_NAME: &'static str = "update_site_stats";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        conn.interact(update_site_stats)
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        info!("Recorded the site stats of the previous day");
+
+        Ok(())
+    }
+}
+
+fn update_site_stats(conn: &mut PgConnection) -> QueryResult<usize> {
+    diesel::sql_query(include_str!("site_stats.sql")).execute(conn)
+}
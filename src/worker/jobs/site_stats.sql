@@ -0,0 +1,24 @@
+INSERT INTO site_stats (date, crates, versions, downloads, publishes, new_users)
+SELECT
+    CURRENT_DATE - 1,
+    (SELECT COUNT(*) FROM crates),
+    (SELECT COUNT(*) FROM versions),
+    (SELECT total_downloads FROM metadata),
+    (
+        SELECT COUNT(*)
+        FROM versions
+        WHERE created_at >= CURRENT_DATE - 1
+          AND created_at < CURRENT_DATE
+    )::integer,
+    (
+        SELECT COUNT(*)
+        FROM users
+        WHERE created_at >= CURRENT_DATE - 1
+          AND created_at < CURRENT_DATE
+    )::integer
+ON CONFLICT (date) DO UPDATE
+    SET crates = EXCLUDED.crates,
+        versions = EXCLUDED.versions,
+        downloads = EXCLUDED.downloads,
+        publishes = EXCLUDED.publishes,
+        new_users = EXCLUDED.new_users
@@ -0,0 +1,64 @@
+use crate::schema::{crates, versions};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use std::sync::Arc;
+
+/// Updates the object tags of the crate file and the rendered README of a
+/// crate version after it was yanked or unyanked.
+///
+/// The yanked status is read from the database when the job runs, so that
+/// the tags are correct even if the version was yanked and unyanked again
+/// in quick succession.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateStorageTags {
+    krate: String,
+    version: String,
+}
+
+impl UpdateStorageTags {
+    pub fn new(krate: impl Into<String>, version: impl Into<String>) -> Self {
+        let krate = krate.into();
+        let version = version.into();
+        Self { krate, version }
+    }
+}
+
+impl BackgroundJob for UpdateStorageTags {
+    const JOB_NAME: &'static str = "update_storage_tags";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip(env), err)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let krate = self.krate.clone();
+        let version = self.version.clone();
+
+        let conn = env.deadpool.get().await?;
+        let yanked: Option<bool> = conn
+            .interact(move |conn| {
+                versions::table
+                    .inner_join(crates::table)
+                    .filter(crates::name.eq(&krate))
+                    .filter(versions::num.eq(&version))
+                    .select(versions::yanked)
+                    .first(conn)
+                    .optional()
+            })
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        let Some(yanked) = yanked else {
+            warn!("Version does not exist anymore, skipping the tag update");
+            return Ok(());
+        };
+
+        info!(yanked, "Updating storage tags");
+        env.storage
+            .update_tags(&self.krate, &self.version, yanked)
+            .await?;
+
+        Ok(())
+    }
+}
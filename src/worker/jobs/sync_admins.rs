@@ -1,5 +1,6 @@
 use crate::email::Email;
 use crate::schema::{emails, users};
+use crate::util::retry::RetryPolicy;
 use crate::worker::Environment;
 use anyhow::anyhow;
 use crates_io_worker::BackgroundJob;
@@ -23,7 +24,13 @@ impl BackgroundJob for SyncAdmins {
     async fn run(&self, ctx: Self::Context) -> anyhow::Result<()> {
         info!("Syncing admins from rust-lang/team repo…");
 
-        let repo_admins = ctx.team_repo.get_permission(PERMISSION_NAME).await?.people;
+        let permission = RetryPolicy::default()
+            .run("team_repo_permission", || {
+                ctx.team_repo.get_permission(PERMISSION_NAME)
+            })
+            .await?;
+
+        let repo_admins = permission.people;
         let repo_admin_ids = repo_admins
             .iter()
             .map(|m| m.github_id)
@@ -42,7 +49,7 @@ impl BackgroundJob for SyncAdmins {
             // Existing admins from the database.
 
             let database_admins = users::table
-                .left_join(emails::table)
+                .left_join(emails::table.on(emails::user_id.eq(users::id).and(emails::is_primary)))
                 .select((users::gh_id, users::gh_login, emails::email.nullable()))
                 .filter(users::is_admin.eq(true))
                 .get_results::<(i32, String, Option<String>)>(conn)?;
@@ -0,0 +1,460 @@
+//! Detection of unusual API token activity
+//!
+//! The [`DetectTokenAnomalies`] job analyzes the recorded token usages (the
+//! publishes, yanks and unyanks in the `version_owner_actions` table) of the
+//! last hour, and flags the accounts of users whose tokens were used in an
+//! unusual way, which could mean that a token was stolen.
+//!
+//! Since there is no geolocation data available, networks are used as an
+//! approximation of locations, like for the suspicious sign-in detection in
+//! [`crate::challenge`].
+
+use crate::challenge::network_of;
+use crate::config::TokenAnomalyConfig;
+use crate::email::Email;
+use crate::models::{NewTokenAnomaly, TokenAnomaly, TokenAnomalyKind, User, VersionAction};
+use crate::schema::{token_anomalies, user_sign_ins, users, version_owner_actions};
+use crate::worker::Environment;
+use anyhow::anyhow;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use ipnetwork::IpNetwork;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// The token usages of this time span before the job runs are analyzed. The
+/// job is supposed to be run once per hour.
+const ANALYSIS_WINDOW_HOURS: i64 = 1;
+
+/// Networks that were used in this many days before the analyzed time span
+/// are considered known.
+const HISTORY_DAYS: i64 = 90;
+
+#[derive(Serialize, Deserialize)]
+pub struct DetectTokenAnomalies;
+
+impl BackgroundJob for DetectTokenAnomalies {
+    const JOB_NAME: &'static str = "detect_token_anomalies";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        conn.interact(move |conn| detect_token_anomalies(conn, &env))
+            .await
+            .map_err(|err| anyhow!(err.to_string()))?
+    }
+}
+
+fn detect_token_anomalies(conn: &mut PgConnection, env: &Environment) -> anyhow::Result<()> {
+    let config = &env.config.token_anomalies;
+    let window_start = Utc::now().naive_utc() - TimeDelta::hours(ANALYSIS_WINDOW_HOURS);
+
+    let mut usages_by_user = BTreeMap::<i32, Vec<TokenUsage>>::new();
+    for usage in TokenUsage::load_since(conn, window_start)? {
+        usages_by_user.entry(usage.user_id).or_default().push(usage);
+    }
+
+    info!(users = usages_by_user.len(), "Analyzing token usages…");
+
+    for (user_id, usages) in usages_by_user {
+        let known_ips = known_ips(conn, user_id, window_start)?;
+        let mut findings = detect(&usages, &known_ips, config);
+
+        // Anomalies that were reported by the previous run are not reported
+        // again, even if their token usages are still within the window.
+        let reported_kinds: Vec<String> = token_anomalies::table
+            .filter(token_anomalies::user_id.eq(user_id))
+            .filter(token_anomalies::created_at.gt(window_start))
+            .select(token_anomalies::kind)
+            .load(conn)?;
+
+        findings.retain(|finding| !reported_kinds.iter().any(|k| k == finding.kind.as_str()));
+
+        if !findings.is_empty() {
+            flag_user(conn, env, user_id, &findings)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Records the anomalies and notifies the user about them.
+fn flag_user(
+    conn: &mut PgConnection,
+    env: &Environment,
+    user_id: i32,
+    findings: &[Finding],
+) -> anyhow::Result<()> {
+    let lock_publishes = env.config.token_anomalies.lock_publishes;
+
+    let user: User = users::table.find(user_id).first(conn)?;
+
+    let kinds = findings.iter().map(|f| f.kind.as_str()).collect::<Vec<_>>();
+    warn!(user = %user.gh_login, ?kinds, lock_publishes, "Unusual token activity detected");
+
+    let anomalies = conn.transaction(|conn| {
+        findings
+            .iter()
+            .map(|finding| {
+                NewTokenAnomaly {
+                    user_id,
+                    api_token_id: finding.api_token_id,
+                    kind: finding.kind.as_str(),
+                    details: &finding.details,
+                    publishes_locked: lock_publishes,
+                }
+                .insert(conn)
+            })
+            .collect::<QueryResult<Vec<TokenAnomaly>>>()
+    })?;
+
+    let Some(recipient) = user.verified_email(conn)? else {
+        warn!(user = %user.gh_login, "No verified email address to notify about token activity");
+        return Ok(());
+    };
+
+    // All unconfirmed anomalies of the user are confirmed together, so any of
+    // the tokens can be used for the confirmation link.
+    let confirmation_token = lock_publishes
+        .then(|| anomalies.first())
+        .flatten()
+        .map(|anomaly| anomaly.confirmation_token.as_str());
+
+    let email = TokenAnomalyEmail {
+        user_name: &user.gh_login,
+        domain: &env.config.domain_name,
+        findings,
+        confirmation_token,
+    };
+
+    if let Err(error) = env.emails.send(&recipient, email) {
+        warn!(?error, "Failed to send token anomaly notification");
+    }
+
+    Ok(())
+}
+
+/// A publish, yank or unyank that was authenticated with an API token.
+#[derive(Debug, Clone)]
+struct TokenUsage {
+    user_id: i32,
+    api_token_id: i32,
+    action: VersionAction,
+    time: NaiveDateTime,
+    ip: IpAddr,
+}
+
+impl TokenUsage {
+    /// Loads the token usages after `since`, ordered by time.
+    fn load_since(conn: &mut PgConnection, since: NaiveDateTime) -> QueryResult<Vec<Self>> {
+        type Row = (
+            i32,
+            Option<i32>,
+            VersionAction,
+            NaiveDateTime,
+            Option<IpNetwork>,
+        );
+
+        let rows: Vec<Row> = version_owner_actions::table
+            .filter(version_owner_actions::api_token_id.is_not_null())
+            .filter(version_owner_actions::ip.is_not_null())
+            .filter(version_owner_actions::time.gt(since))
+            .select((
+                version_owner_actions::user_id,
+                version_owner_actions::api_token_id,
+                version_owner_actions::action,
+                version_owner_actions::time,
+                version_owner_actions::ip,
+            ))
+            .order((version_owner_actions::time, version_owner_actions::id))
+            .load(conn)?;
+
+        let usages = rows
+            .into_iter()
+            .filter_map(|(user_id, api_token_id, action, time, ip)| {
+                Some(Self {
+                    user_id,
+                    api_token_id: api_token_id?,
+                    action,
+                    time,
+                    ip: ip?.ip(),
+                })
+            })
+            .collect();
+
+        Ok(usages)
+    }
+}
+
+/// Returns the IP addresses that the user published, yanked or signed in
+/// from in the [`HISTORY_DAYS`] before `window_start`.
+///
+/// Sign-ins within the analyzed time span are included too, since they
+/// already passed the suspicious sign-in checks.
+fn known_ips(
+    conn: &mut PgConnection,
+    user_id: i32,
+    window_start: NaiveDateTime,
+) -> QueryResult<Vec<IpAddr>> {
+    let history_start = window_start - TimeDelta::days(HISTORY_DAYS);
+
+    let action_ips: Vec<Option<IpNetwork>> = version_owner_actions::table
+        .filter(version_owner_actions::user_id.eq(user_id))
+        .filter(version_owner_actions::time.gt(history_start))
+        .filter(version_owner_actions::time.le(window_start))
+        .filter(version_owner_actions::ip.is_not_null())
+        .select(version_owner_actions::ip)
+        .distinct()
+        .load(conn)?;
+
+    let sign_in_ips: Vec<IpNetwork> = user_sign_ins::table
+        .filter(user_sign_ins::user_id.eq(user_id))
+        .filter(user_sign_ins::created_at.gt(history_start))
+        .select(user_sign_ins::ip)
+        .distinct()
+        .load(conn)?;
+
+    let ips = action_ips
+        .into_iter()
+        .flatten()
+        .chain(sign_in_ips)
+        .map(|network| network.ip())
+        .collect();
+
+    Ok(ips)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Finding {
+    kind: TokenAnomalyKind,
+    api_token_id: Option<i32>,
+    details: String,
+}
+
+/// Returns the anomalies in the token usages of a single user.
+///
+/// `usages` have to be ordered by time, and `known_ips` are the addresses
+/// that the user used before. New networks are not reported for users
+/// without any history, since every network is new to them.
+fn detect(
+    usages: &[TokenUsage],
+    known_ips: &[IpAddr],
+    config: &TokenAnomalyConfig,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if !known_ips.is_empty() {
+        let mut new_networks = HashSet::new();
+        for usage in usages {
+            let network = network_of(usage.ip);
+            let is_known = known_ips.iter().any(|ip| network.contains(*ip));
+            if !is_known && new_networks.insert(network.network()) {
+                let action: &str = usage.action.into();
+                findings.push(Finding {
+                    kind: TokenAnomalyKind::NewNetwork,
+                    api_token_id: Some(usage.api_token_id),
+                    details: format!(
+                        "{action} from {ip} at {time} UTC, a network that was not used with your account in the last {HISTORY_DAYS} days",
+                        ip = usage.ip,
+                        time = usage.time.format("%Y-%m-%d %H:%M"),
+                    ),
+                });
+            }
+        }
+    }
+
+    let travel = usages.windows(2).find(|pair| {
+        let (previous, usage) = (&pair[0], &pair[1]);
+        let same_network = network_of(previous.ip).contains(usage.ip);
+        let elapsed = (usage.time - previous.time).to_std();
+        !same_network && elapsed.is_ok_and(|elapsed| elapsed < config.min_travel_time)
+    });
+
+    if let Some([previous, usage]) = travel {
+        let previous_action: &str = previous.action.into();
+        let action: &str = usage.action.into();
+        findings.push(Finding {
+            kind: TokenAnomalyKind::ImpossibleTravel,
+            api_token_id: Some(usage.api_token_id),
+            details: format!(
+                "{action} from {ip} at {time} UTC, only {seconds} seconds after a {previous_action} from {previous_ip}",
+                ip = usage.ip,
+                time = usage.time.format("%Y-%m-%d %H:%M"),
+                seconds = (usage.time - previous.time).num_seconds(),
+                previous_ip = previous.ip,
+            ),
+        });
+    }
+
+    let publishes = usages
+        .iter()
+        .filter(|usage| usage.action == VersionAction::Publish)
+        .count();
+
+    if publishes > config.max_publishes_per_hour {
+        findings.push(Finding {
+            kind: TokenAnomalyKind::PublishBurst,
+            api_token_id: None,
+            details: format!("{publishes} versions were published within one hour"),
+        });
+    }
+
+    findings
+}
+
+struct TokenAnomalyEmail<'a> {
+    user_name: &'a str,
+    domain: &'a str,
+    findings: &'a [Finding],
+    /// The token of the confirmation link, if publishing was locked.
+    confirmation_token: Option<&'a str>,
+}
+
+impl Email for TokenAnomalyEmail<'_> {
+    const SUBJECT: &'static str = "crates.io: Unusual activity of your API tokens";
+
+    fn body(&self) -> String {
+        let TokenAnomalyEmail {
+            user_name,
+            domain,
+            findings,
+            confirmation_token,
+        } = self;
+
+        let activity = findings.iter().fold(String::new(), |mut output, finding| {
+            let _ = writeln!(output, "- {}", finding.details);
+            output
+        });
+
+        let next_steps = match confirmation_token {
+            Some(token) => format!(
+                "To protect your crates, publishing new versions has been locked for your account. If this activity was yours, please confirm it to unlock publishing again:
+
+https://{domain}/confirm_publishes/{token}
+
+If it was not yours, please revoke your API tokens at https://{domain}/settings/tokens immediately and contact us at help@crates.io."
+            ),
+            None => format!(
+                "If this activity was yours, no action is required. If it was not yours, please revoke your API tokens at https://{domain}/settings/tokens immediately and contact us at help@crates.io."
+            ),
+        };
+
+        format!(
+            "Hello {user_name}!
+
+We noticed unusual activity of the API tokens of your crates.io account:
+
+{activity}
+{next_steps}"
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn usage(minute: u32, action: VersionAction, ip: &str) -> TokenUsage {
+        let time = NaiveDate::from_ymd_opt(2024, 5, 8)
+            .unwrap()
+            .and_hms_opt(12, minute, 0)
+            .unwrap();
+
+        TokenUsage {
+            user_id: 1,
+            api_token_id: 42,
+            action,
+            time,
+            ip: ip.parse().unwrap(),
+        }
+    }
+
+    fn ips(ips: &[&str]) -> Vec<IpAddr> {
+        ips.iter().map(|ip| ip.parse().unwrap()).collect()
+    }
+
+    fn kinds(findings: &[Finding]) -> Vec<TokenAnomalyKind> {
+        findings.iter().map(|finding| finding.kind).collect()
+    }
+
+    #[test]
+    fn usual_activity() {
+        let config = TokenAnomalyConfig::default();
+        let usages = [
+            usage(0, VersionAction::Publish, "192.0.2.1"),
+            usage(1, VersionAction::Publish, "192.0.2.2"),
+            usage(30, VersionAction::Yank, "192.0.200.1"),
+        ];
+
+        let findings = detect(&usages, &ips(&["192.0.100.100"]), &config);
+        assert_eq!(findings, vec![]);
+    }
+
+    #[test]
+    fn new_network() {
+        let config = TokenAnomalyConfig::default();
+        let usages = [
+            usage(0, VersionAction::Publish, "203.0.113.1"),
+            usage(30, VersionAction::Publish, "203.0.113.2"),
+        ];
+
+        let findings = detect(&usages, &ips(&["192.0.2.1"]), &config);
+        assert_eq!(kinds(&findings), vec![TokenAnomalyKind::NewNetwork]);
+        assert_eq!(findings[0].api_token_id, Some(42));
+        assert_eq!(
+            findings[0].details,
+            "publish from 203.0.113.1 at 2024-05-08 12:00 UTC, a network that was not used with your account in the last 90 days"
+        );
+
+        // Every network is new for users without history
+        assert_eq!(detect(&usages, &[], &config), vec![]);
+    }
+
+    #[test]
+    fn impossible_travel() {
+        let config = TokenAnomalyConfig::default();
+        let usages = [
+            usage(0, VersionAction::Publish, "192.0.2.1"),
+            usage(2, VersionAction::Yank, "2001:db8::1"),
+        ];
+
+        let findings = detect(&usages, &ips(&["192.0.2.1", "2001:db8::2"]), &config);
+        assert_eq!(kinds(&findings), vec![TokenAnomalyKind::ImpossibleTravel]);
+        assert_eq!(
+            findings[0].details,
+            "yank from 2001:db8::1 at 2024-05-08 12:02 UTC, only 120 seconds after a publish from 192.0.2.1"
+        );
+    }
+
+    #[test]
+    fn publish_burst() {
+        let config = TokenAnomalyConfig {
+            max_publishes_per_hour: 2,
+            ..Default::default()
+        };
+
+        let usages = [
+            usage(0, VersionAction::Publish, "192.0.2.1"),
+            usage(10, VersionAction::Yank, "192.0.2.1"),
+            usage(20, VersionAction::Publish, "192.0.2.1"),
+        ];
+
+        assert_eq!(detect(&usages, &[], &config), vec![]);
+
+        let usages = [
+            usage(0, VersionAction::Publish, "192.0.2.1"),
+            usage(10, VersionAction::Publish, "192.0.2.1"),
+            usage(20, VersionAction::Publish, "192.0.2.1"),
+        ];
+
+        let findings = detect(&usages, &[], &config);
+        assert_eq!(kinds(&findings), vec![TokenAnomalyKind::PublishBurst]);
+        assert_eq!(findings[0].api_token_id, None);
+    }
+}
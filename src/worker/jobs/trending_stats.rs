@@ -0,0 +1,52 @@
+use crate::schema::trending_stats;
+use crate::worker::Environment;
+use anyhow::anyhow;
+use chrono::{TimeDelta, Utc};
+use crates_io_worker::BackgroundJob;
+use diesel::prelude::*;
+use std::sync::Arc;
+
+/// Snapshots are kept for this many days, which is long enough to compare
+/// the trends of the last few weeks.
+const RETENTION_DAYS: i64 = 90;
+
+/// Records the downloads and publishes of the last week and of the week
+/// before per keyword and category in the `trending_stats` table, which is
+/// used by the `GET /api/v1/stats/trending` endpoint.
+///
+/// The job is supposed to run once per day, after the downloads of the
+/// previous day were counted. Running it multiple times on the same day
+/// replaces the snapshot of that day.
+#[derive(Serialize, Deserialize)]
+pub struct UpdateTrendingStats;
+
+impl BackgroundJob for UpdateTrendingStats {
+    const JOB_NAME: &'static str = "update_trending_stats";
+
+    type Context = Arc<Environment>;
+
+    #[instrument(skip_all)]
+    async fn run(&self, env: Self::Context) -> anyhow::Result<()> {
+        let conn = env.deadpool.get().await?;
+        let count = conn
+            .interact(update_trending_stats)
+            .await
+            .map_err(|err| anyhow!(err.to_string()))??;
+
+        info!("Recorded the trending stats of {count} keywords and categories");
+
+        Ok(())
+    }
+}
+
+fn update_trending_stats(conn: &mut PgConnection) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        let count = diesel::sql_query(include_str!("trending_stats.sql")).execute(conn)?;
+
+        let cutoff = Utc::now().date_naive() - TimeDelta::days(RETENTION_DAYS);
+        diesel::delete(trending_stats::table.filter(trending_stats::date.lt(cutoff)))
+            .execute(conn)?;
+
+        Ok(count)
+    })
+}
@@ -0,0 +1,57 @@
+WITH crate_downloads AS (
+    SELECT
+        versions.crate_id,
+        SUM(version_downloads.downloads) FILTER (WHERE version_downloads.date >= CURRENT_DATE - 7) AS downloads,
+        SUM(version_downloads.downloads) FILTER (WHERE version_downloads.date < CURRENT_DATE - 7) AS previous_downloads
+    FROM version_downloads
+    INNER JOIN versions
+        ON versions.id = version_downloads.version_id
+    WHERE version_downloads.date >= CURRENT_DATE - 14
+      AND version_downloads.date < CURRENT_DATE
+    GROUP BY versions.crate_id
+), crate_publishes AS (
+    SELECT
+        crate_id,
+        COUNT(*) FILTER (WHERE created_at >= CURRENT_DATE - 7) AS publishes,
+        COUNT(*) FILTER (WHERE created_at < CURRENT_DATE - 7) AS previous_publishes
+    FROM versions
+    WHERE created_at >= CURRENT_DATE - 14
+      AND created_at < CURRENT_DATE
+    GROUP BY crate_id
+), crate_stats AS (
+    SELECT
+        crate_id,
+        COALESCE(crate_downloads.downloads, 0) AS downloads,
+        COALESCE(crate_downloads.previous_downloads, 0) AS previous_downloads,
+        COALESCE(crate_publishes.publishes, 0) AS publishes,
+        COALESCE(crate_publishes.previous_publishes, 0) AS previous_publishes
+    FROM crate_downloads
+    FULL OUTER JOIN crate_publishes USING (crate_id)
+), subjects AS (
+    SELECT 'keyword' AS kind, keywords.keyword AS name, crates_keywords.crate_id
+    FROM crates_keywords
+    INNER JOIN keywords
+        ON keywords.id = crates_keywords.keyword_id
+    UNION ALL
+    SELECT 'category' AS kind, categories.slug AS name, crates_categories.crate_id
+    FROM crates_categories
+    INNER JOIN categories
+        ON categories.id = crates_categories.category_id
+)
+INSERT INTO trending_stats (date, kind, name, downloads, previous_downloads, publishes, previous_publishes)
+SELECT
+    CURRENT_DATE,
+    subjects.kind,
+    subjects.name,
+    SUM(crate_stats.downloads)::bigint,
+    SUM(crate_stats.previous_downloads)::bigint,
+    SUM(crate_stats.publishes)::integer,
+    SUM(crate_stats.previous_publishes)::integer
+FROM subjects
+INNER JOIN crate_stats USING (crate_id)
+GROUP BY subjects.kind, subjects.name
+ON CONFLICT (date, kind, name) DO UPDATE
+    SET downloads = EXCLUDED.downloads,
+        previous_downloads = EXCLUDED.previous_downloads,
+        publishes = EXCLUDED.publishes,
+        previous_publishes = EXCLUDED.previous_publishes
@@ -2,10 +2,11 @@ use anyhow::anyhow;
 use std::sync::Arc;
 
 use crates_io_worker::BackgroundJob;
-use diesel::PgConnection;
+use diesel::prelude::*;
 use typomania::Package;
 
 use crate::email::Email;
+use crate::schema::{crates, typosquat_flags};
 use crate::{
     typosquat::{Cache, Crate},
     worker::Environment,
@@ -60,6 +61,28 @@ fn check(
             // hopefully care to check into things more closely.
             info!(?squats, "Found potential typosquatting");
 
+            // The flags are shown to the crates.io team in the admin overview
+            // of the crate.
+            let crate_id: i32 = crates::table
+                .filter(crates::name.eq(name))
+                .select(crates::id)
+                .first(conn)?;
+
+            let flags = squats
+                .iter()
+                .map(|squat| {
+                    (
+                        typosquat_flags::crate_id.eq(crate_id),
+                        typosquat_flags::squatted_crate.eq(squat.package()),
+                        typosquat_flags::description.eq(squat.to_string()),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            diesel::insert_into(typosquat_flags::table)
+                .values(&flags)
+                .execute(conn)?;
+
             let email = PossibleTyposquatEmail {
                 domain: &emails.domain,
                 crate_name: name,
@@ -165,6 +188,15 @@ mod tests {
         let sent = sent_mail.into_iter().next().unwrap();
         assert_eq!(&sent.0.to(), &["admin@example.com".parse::<Address>()?]);
 
+        // The possible typosquatting is recorded for the admin overview.
+        let flagged: Vec<(i32, String)> = typosquat_flags::table
+            .select((typosquat_flags::crate_id, typosquat_flags::squatted_crate))
+            .load(&mut conn)?;
+        assert!(!flagged.is_empty());
+        assert!(flagged
+            .iter()
+            .all(|(crate_id, squatted)| *crate_id == demon.id && squatted == "my-crate"));
+
         Ok(())
     }
 }
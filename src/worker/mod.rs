@@ -19,18 +19,39 @@ pub trait RunnerExt {
 
 impl RunnerExt for Runner<Arc<Environment>> {
     fn register_crates_io_job_types(self) -> Self {
-        self.register_job_type::<jobs::CheckTyposquat>()
+        self.register_job_type::<jobs::CheckCrateFiles>()
+            .register_job_type::<jobs::CheckTyposquat>()
             .register_job_type::<jobs::CleanProcessedLogFiles>()
+            .register_job_type::<jobs::CleanupExpiredInvitations>()
             .register_job_type::<jobs::DailyDbMaintenance>()
+            .register_job_type::<jobs::DataRetention>()
+            .register_job_type::<jobs::DeleteExpiredDataExports>()
+            .register_job_type::<jobs::DetectDownloadSpikes>()
+            .register_job_type::<jobs::DetectTokenAnomalies>()
             .register_job_type::<jobs::DumpDb>()
+            .register_job_type::<jobs::ExpireCrateNameReservations>()
+            .register_job_type::<jobs::ExportUserData>()
+            .register_job_type::<jobs::FlushCloudFrontInvalidations>()
+            .register_job_type::<jobs::MergeUsers>()
+            .register_job_type::<jobs::MigrateCategory>()
+            .register_job_type::<jobs::NormalizeDependencyReqs>()
             .register_job_type::<jobs::NormalizeIndex>()
+            .register_job_type::<jobs::ProbeCdnHealth>()
             .register_job_type::<jobs::ProcessCdnLog>()
             .register_job_type::<jobs::ProcessCdnLogQueue>()
+            .register_job_type::<jobs::ReleaseScheduledVersions>()
             .register_job_type::<jobs::RenderAndUploadReadme>()
+            .register_job_type::<jobs::ResetSandbox>()
+            .register_job_type::<jobs::SendFollowDigest>()
+            .register_job_type::<jobs::SendPublishNotifications>()
+            .register_job_type::<jobs::SnapshotDependentsCounts>()
             .register_job_type::<jobs::SquashIndex>()
             .register_job_type::<jobs::SyncAdmins>()
             .register_job_type::<jobs::SyncToGitIndex>()
             .register_job_type::<jobs::SyncToSparseIndex>()
             .register_job_type::<jobs::UpdateDownloads>()
+            .register_job_type::<jobs::UpdateSiteStats>()
+            .register_job_type::<jobs::UpdateStorageTags>()
+            .register_job_type::<jobs::UpdateTrendingStats>()
     }
 }
@@ -0,0 +1,28 @@
+import { module, test } from 'qunit';
+
+import { patternDescription } from '../../../utils/token-scopes';
+
+module('Unit | Utility | token-scopes', function () {
+  test('patternDescription() describes crate patterns', function (assert) {
+    assert.strictEqual(patternDescription('*'), 'Matches all crates on crates.io');
+    assert.strictEqual(
+      patternDescription('serde*').toString(),
+      'Matches all crates starting with <strong>serde</strong>',
+    );
+    assert.strictEqual(
+      patternDescription('serde@^1').toString(),
+      'Matches only the <strong>serde</strong> crate, and only versions matching <strong>^1</strong> can be published',
+    );
+  });
+
+  test('patternDescription() escapes user input', function (assert) {
+    assert.strictEqual(
+      patternDescription('foo@<img src=x>').toString(),
+      'Matches only the <strong>foo</strong> crate, and only versions matching <strong>&lt;img src&#x3D;x&gt;</strong> can be published',
+    );
+    assert.strictEqual(
+      patternDescription('<b>*').toString(),
+      'Matches all crates starting with <strong>&lt;b&gt;</strong>',
+    );
+  });
+});